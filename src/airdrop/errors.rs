@@ -0,0 +1,19 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum AirdropError {
+    #[error("Could not read recipients file '{0}': {1}")]
+    RecipientsFileError(String, String),
+
+    #[error("Invalid recipient {0}: {1}")]
+    InvalidRecipient(String, String),
+
+    #[error("Not enough items available in the candy machine: {0} required, {1} available")]
+    NotEnoughItems(u64, u64),
+
+    #[error("Failed to parse airdrop state file: {0}")]
+    StateFileWrongFormat(String),
+
+    #[error("Invalid parallelism '{0}', it must be greater than 0")]
+    InvalidParallelism(usize),
+}
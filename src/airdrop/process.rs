@@ -0,0 +1,500 @@
+use console::style;
+use mpl_token_metadata::state::Uses;
+use rayon::prelude::*;
+use serde::Serialize;
+use spl_associated_token_account::get_associated_token_address;
+use std::{
+    fs::{self, OpenOptions},
+    sync::{Arc, Mutex},
+};
+
+use crate::airdrop::AirdropError;
+use crate::cache::load_cache;
+use crate::candy_machine::{get_candy_machine_state, ID as CANDY_MACHINE_ID};
+use crate::common::*;
+use crate::distribute::{transfer_nft, HeldNft};
+use crate::events::{error_event, progress_event};
+use crate::interaction::confirm;
+use crate::journal::Journal;
+use crate::mint::{check_uses_authority, get_mint_settings, mint, MintSpend};
+use crate::spend::track_spend;
+use crate::utils::*;
+
+pub struct AirdropArgs {
+    pub keypair: Option<String>,
+    pub rpc_url: Option<String>,
+    pub cache: String,
+    pub config: String,
+    pub recipients: String,
+    pub state: String,
+    pub candy_machine: Option<String>,
+    pub parallel: usize,
+    pub priority_fee: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct AirdropState {
+    pub airdrops: Vec<Airdrop>,
+}
+
+/// An NFT minted for a recipient. The transfer signature is missing while the
+/// NFT is still held by the authority wallet.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Airdrop {
+    pub recipient: String,
+    pub mint: String,
+    pub mint_signature: String,
+    pub transfer_signature: Option<String>,
+}
+
+/// A pending step of the airdrop: minting a new NFT for a recipient or
+/// transferring an NFT already minted for it.
+enum Job {
+    Mint(Pubkey),
+    Transfer(Pubkey, Pubkey),
+}
+
+impl Job {
+    fn recipient(&self) -> &Pubkey {
+        match self {
+            Job::Mint(recipient) | Job::Transfer(recipient, _) => recipient,
+        }
+    }
+}
+
+/// Journal stage of the airdrop command.
+const STAGE_AIRDROP: &str = "airdrop";
+
+pub fn process_airdrop(args: AirdropArgs) -> Result<()> {
+    track_spend("airdrop", &args.cache);
+
+    let mut journal = Journal::load_for_cache(&args.cache)?;
+    journal.start(STAGE_AIRDROP)?;
+    let journal = Mutex::new(journal);
+
+    let result = airdrop_recipients(args, &journal);
+    let mut journal = journal.into_inner().unwrap();
+
+    match result {
+        Ok(()) => journal.complete(STAGE_AIRDROP),
+        Err(err) => {
+            journal.fail(STAGE_AIRDROP, &err.to_string())?;
+            Err(err)
+        }
+    }
+}
+
+fn airdrop_recipients(args: AirdropArgs, journal: &Mutex<Journal>) -> Result<()> {
+    if args.parallel == 0 {
+        return Err(AirdropError::InvalidParallelism(args.parallel).into());
+    }
+
+    let (uses, config_priority_fee) = get_mint_settings(&args.config)?;
+    // the priority fee specified takes precedence over the one from the config
+    let priority_fee = args.priority_fee.or(config_priority_fee);
+
+    let sugar_config = sugar_setup(args.keypair, args.rpc_url)?;
+    let client = Arc::new(setup_client(&sugar_config)?);
+
+    println!(
+        "{} {}Loading recipients",
+        style("[1/3]").bold().dim(),
+        PAPER_EMOJI
+    );
+
+    let recipients = read_recipients(&args.recipients)?;
+    let state = load_state(&args.state)?;
+    let jobs = plan_jobs(&recipients, &state);
+
+    let requested: u64 = recipients.values().sum();
+    let delivered = state
+        .airdrops
+        .iter()
+        .filter(|airdrop| airdrop.transfer_signature.is_some())
+        .count();
+
+    println!(
+        "Found {} recipient(s) of {} NFT(s), {} already delivered",
+        recipients.len(),
+        requested,
+        delivered
+    );
+
+    // the candy machine specified takes precedence over the one from the cache
+    let candy_machine = match args.candy_machine {
+        Some(candy_machine) => candy_machine,
+        None => load_cache(&args.cache, false)?.program.candy_machine,
+    };
+    let candy_pubkey = Pubkey::from_str(&candy_machine)
+        .map_err(|_| CacheError::InvalidCandyMachineAddress(candy_machine.clone()))?;
+
+    println!(
+        "\n{} {}Checking candy machine",
+        style("[2/3]").bold().dim(),
+        LOOKING_GLASS_EMOJI
+    );
+    println!("Candy machine ID: {}", candy_pubkey);
+
+    let candy_machine_state = Arc::new(get_candy_machine_state(&sugar_config, &candy_pubkey)?);
+    let available = candy_machine_state
+        .data
+        .items_available
+        .saturating_sub(candy_machine_state.items_redeemed);
+    let mints = jobs
+        .iter()
+        .filter(|job| matches!(job, Job::Mint(_)))
+        .count() as u64;
+
+    println!(
+        "{} item(s) available, {} NFT(s) to mint and {} to transfer",
+        available,
+        mints,
+        jobs.len() as u64 - mints
+    );
+
+    if mints > available {
+        return Err(AirdropError::NotEnoughItems(mints, available).into());
+    }
+
+    println!(
+        "\n{} {}Airdropping NFTs",
+        style("[3/3]").bold().dim(),
+        PAYMENT_EMOJI
+    );
+
+    if jobs.is_empty() {
+        println!("\nAll NFTs have been airdropped.");
+        print_report(&recipients, &state, &HashMap::new());
+        return Ok(());
+    }
+
+    if !confirm(&format!("Airdrop {} NFT(s)?", jobs.len()))? {
+        println!("\n{}", style("Airdrop aborted.").red().bold().dim());
+        return Ok(());
+    }
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(args.parallel)
+        .build()?;
+
+    let program = client.program(CANDY_MACHINE_PROGRAM_ID);
+
+    if uses.is_some() {
+        check_uses_authority(&candy_machine_state, &program.payer())?;
+    }
+
+    let mint_spend = MintSpend::start(&program)?;
+
+    let pb = progress_bar_with_style(jobs.len() as u64);
+    let state = Mutex::new(state);
+    let errors = Mutex::new(Vec::new());
+    let failures: Mutex<HashMap<Pubkey, u64>> = Mutex::new(HashMap::new());
+
+    pool.install(|| {
+        jobs.par_iter().for_each(|job| {
+            let recipient = job.recipient();
+
+            let result = airdrop(
+                &client,
+                &sugar_config.keypair,
+                candy_pubkey,
+                &candy_machine_state,
+                job,
+                &uses,
+                priority_fee,
+                &state,
+                &args.state,
+            );
+
+            let result = result.and_then(|nft_mint| {
+                // the NFT was minted and transferred to the recipient
+                journal
+                    .lock()
+                    .unwrap()
+                    .complete_batch(STAGE_AIRDROP, &nft_mint.to_string())
+            });
+
+            if let Err(err) = result {
+                error!("Failed to airdrop to {}: {}", recipient, err);
+                let error = format!("{}: {}", recipient, err);
+                error_event(
+                    "airdrop",
+                    Some(&recipient.to_string()),
+                    pb.position(),
+                    pb.length(),
+                    &error,
+                );
+                errors.lock().unwrap().push(error);
+                *failures.lock().unwrap().entry(*recipient).or_insert(0) += 1;
+            }
+
+            pb.inc(1);
+            progress_event(
+                "airdrop",
+                Some(&recipient.to_string()),
+                pb.position(),
+                pb.length(),
+            );
+        })
+    });
+
+    mint_spend.finish(&program)?;
+
+    let state = state.into_inner().unwrap();
+    let errors = errors.into_inner().unwrap();
+    let failures = failures.into_inner().unwrap();
+
+    if errors.is_empty() {
+        pb.finish_with_message(format!("{}", style("Airdrop successful ").green().bold()));
+    } else {
+        pb.abandon_with_message(format!("{}", style("Airdrop failed ").red().bold()));
+    }
+
+    print_report(&recipients, &state, &failures);
+
+    if !errors.is_empty() {
+        let mut message = format!(
+            "Failed to complete the airdrop, {} error(s) occurred (re-run to resume):",
+            errors.len()
+        );
+        for error in errors {
+            message.push_str(&style("\n=> ").dim().to_string());
+            message.push_str(&error);
+        }
+
+        return Err(anyhow!(message));
+    }
+
+    Ok(())
+}
+
+/// Run a step of the airdrop, saving the progress after each transaction.
+/// Returns the mint of the airdropped NFT.
+#[allow(clippy::too_many_arguments)]
+fn airdrop(
+    client: &Arc<Client>,
+    keypair: &dyn Signer,
+    candy_pubkey: Pubkey,
+    candy_machine_state: &Arc<CandyMachine>,
+    job: &Job,
+    uses: &Option<Uses>,
+    priority_fee: Option<u64>,
+    state: &Mutex<AirdropState>,
+    state_file: &str,
+) -> Result<Pubkey> {
+    let recipient = *job.recipient();
+
+    let nft_mint = match job {
+        Job::Mint(_) => {
+            let minted = mint(
+                Arc::clone(client),
+                keypair,
+                candy_pubkey,
+                Arc::clone(candy_machine_state),
+                uses.clone(),
+                priority_fee,
+                None,
+            )?;
+            let nft_mint = minted.mint;
+
+            if let Some(err) = minted.uses_error {
+                println!(
+                    "{}",
+                    style(format!("Minted without uses: {}", err)).yellow()
+                );
+            }
+
+            let mut state = state.lock().unwrap();
+            state.airdrops.push(Airdrop {
+                recipient: recipient.to_string(),
+                mint: nft_mint.to_string(),
+                mint_signature: minted.signature.to_string(),
+                transfer_signature: None,
+            });
+            save_state(state_file, &state)?;
+
+            nft_mint
+        }
+        Job::Transfer(_, nft_mint) => *nft_mint,
+    };
+
+    let program = client.program(CANDY_MACHINE_ID);
+    let payer = program.payer();
+    let nft = HeldNft {
+        mint: nft_mint,
+        token_account: get_associated_token_address(&payer, &nft_mint),
+    };
+
+    let signature = transfer_nft(&program, keypair, &recipient, &nft, priority_fee, |_, _| {
+        Ok(())
+    })?;
+
+    let mut state = state.lock().unwrap();
+    let nft_mint = nft_mint.to_string();
+
+    if let Some(airdrop) = state
+        .airdrops
+        .iter_mut()
+        .find(|airdrop| airdrop.mint == nft_mint)
+    {
+        airdrop.transfer_signature = Some(signature.to_string());
+    }
+    save_state(state_file, &state)?;
+
+    Ok(nft.mint)
+}
+
+/// Parse the recipients file, either a JSON object of `address: count` pairs or
+/// a CSV file with an `address,count` pair per line. A header line is ignored.
+fn read_recipients(path: &str) -> Result<IndexMap<Pubkey, u64>> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| AirdropError::RecipientsFileError(path.to_string(), e.to_string()))?;
+
+    let entries: Vec<(String, String)> = if content.trim_start().starts_with('{') {
+        let object: IndexMap<String, Value> = serde_json::from_str(&content)
+            .map_err(|e| AirdropError::RecipientsFileError(path.to_string(), e.to_string()))?;
+
+        object
+            .into_iter()
+            .map(|(address, count)| match count {
+                Value::String(count) => (address, count),
+                count => (address, count.to_string()),
+            })
+            .collect()
+    } else {
+        let mut entries = Vec::new();
+
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+
+            if fields.len() != 2 {
+                return Err(AirdropError::InvalidRecipient(
+                    format!("at line {}", number + 1),
+                    "expected 'address,count'".to_string(),
+                )
+                .into());
+            }
+
+            // header line
+            if number == 0 && Pubkey::from_str(fields[0]).is_err() {
+                continue;
+            }
+
+            entries.push((fields[0].to_string(), fields[1].to_string()));
+        }
+
+        entries
+    };
+
+    let mut recipients = IndexMap::new();
+
+    for (address, count) in entries {
+        let recipient = Pubkey::from_str(&address).map_err(|_| {
+            AirdropError::InvalidRecipient(address.clone(), "invalid address".to_string())
+        })?;
+        let count = count.parse::<u64>().map_err(|_| {
+            AirdropError::InvalidRecipient(address.clone(), format!("invalid count '{}'", count))
+        })?;
+
+        // repeated addresses add up
+        *recipients.entry(recipient).or_insert(0) += count;
+    }
+
+    Ok(recipients)
+}
+
+fn load_state(path: &str) -> Result<AirdropState> {
+    if !Path::new(path).exists() {
+        return Ok(AirdropState::default());
+    }
+
+    let file = File::open(path)?;
+    serde_json::from_reader(file)
+        .map_err(|e| AirdropError::StateFileWrongFormat(e.to_string()).into())
+}
+
+fn save_state(path: &str, state: &AirdropState) -> Result<()> {
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)?;
+    serde_json::to_writer_pretty(file, state)?;
+
+    Ok(())
+}
+
+/// Determine the steps still needed to complete the airdrop: NFTs minted but
+/// not transferred are transferred first, then the missing NFTs are minted.
+fn plan_jobs(recipients: &IndexMap<Pubkey, u64>, state: &AirdropState) -> Vec<Job> {
+    let mut transfers = Vec::new();
+    let mut mints = Vec::new();
+
+    for (recipient, count) in recipients {
+        let recipient_str = recipient.to_string();
+        let mut minted = 0;
+
+        for airdrop in state
+            .airdrops
+            .iter()
+            .filter(|airdrop| airdrop.recipient == recipient_str)
+        {
+            minted += 1;
+
+            if airdrop.transfer_signature.is_none() {
+                if let Ok(nft_mint) = Pubkey::from_str(&airdrop.mint) {
+                    transfers.push(Job::Transfer(*recipient, nft_mint));
+                }
+            }
+        }
+
+        for _ in minted..*count {
+            mints.push(Job::Mint(*recipient));
+        }
+    }
+
+    transfers.extend(mints);
+    transfers
+}
+
+/// Print the number of NFTs requested, delivered and failed for each recipient.
+fn print_report(
+    recipients: &IndexMap<Pubkey, u64>,
+    state: &AirdropState,
+    failures: &HashMap<Pubkey, u64>,
+) {
+    println!("\n{}", style("Airdrop report:").bold());
+    println!(
+        "{:<44} {:>9} {:>9} {:>6}",
+        "Recipient", "Requested", "Delivered", "Failed"
+    );
+
+    for (recipient, count) in recipients {
+        let recipient_str = recipient.to_string();
+        let delivered = state
+            .airdrops
+            .iter()
+            .filter(|airdrop| {
+                airdrop.recipient == recipient_str && airdrop.transfer_signature.is_some()
+            })
+            .count();
+        let failed = failures.get(recipient).copied().unwrap_or(0);
+
+        let line = format!(
+            "{:<44} {:>9} {:>9} {:>6}",
+            recipient_str, count, delivered, failed
+        );
+
+        if failed > 0 {
+            println!("{}", style(line).red());
+        } else {
+            println!("{}", line);
+        }
+    }
+}
@@ -0,0 +1,21 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum AppendError {
+    #[error("Cannot append items to a candy machine with hidden settings")]
+    HiddenSettings,
+
+    #[error("Candy machine is sold out ({0} item(s) redeemed)")]
+    SoldOut(u64),
+
+    #[error(
+        "Not enough space to append {0} item(s): {1} of {2} config line(s) already in use (the number of items of a candy machine cannot be extended)"
+    )]
+    NotEnoughSpace(usize, usize, u64),
+
+    #[error("New assets must be numbered sequentially from 0, missing index {0}")]
+    MissingIndex(usize),
+
+    #[error("File '{0}' already exists in the assets directory")]
+    FileExists(String),
+}
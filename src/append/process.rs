@@ -0,0 +1,179 @@
+use console::style;
+use std::{
+    fs,
+    str::FromStr,
+    sync::{atomic::AtomicBool, Arc},
+};
+
+use crate::append::AppendError;
+use crate::cache::load_cache;
+use crate::candy_machine::get_candy_machine_state;
+use crate::common::*;
+use crate::config::get_config_data;
+use crate::deploy::{process_deploy, DeployArgs};
+use crate::spend::track_spend;
+use crate::upload::{get_asset_pairs, hash_file, process_upload, UploadArgs};
+use crate::validate::{process_validate, ValidateArgs};
+
+pub struct AppendArgs {
+    pub new_assets_dir: String,
+    pub assets_dir: String,
+    pub config: String,
+    pub keypair: Option<String>,
+    pub rpc_url: Option<String>,
+    pub cache: String,
+    pub strict: bool,
+    pub interrupted: Arc<AtomicBool>,
+}
+
+pub async fn process_append(args: AppendArgs) -> Result<()> {
+    track_spend("append", &args.cache);
+
+    let cache = load_cache(&args.cache, false)?;
+
+    println!("\n{} sugar validate\n", style(">>>").magenta());
+
+    process_validate(ValidateArgs {
+        assets_dir: args.new_assets_dir.clone(),
+        strict: args.strict,
+        skip: Vec::new(),
+        max_file_size: None,
+        config: None,
+        keypair: None,
+        rpc_url: None,
+    })?;
+
+    // checks that the candy machine has room for the new items; without a
+    // candy machine, the deploy of the append creates one with room for the
+    // number of items of the config
+
+    let (candy_machine, items_available) = if cache.program.candy_machine.is_empty() {
+        let config_data = get_config_data(&args.config)?;
+
+        if config_data.hidden_settings.is_some() {
+            return Err(AppendError::HiddenSettings.into());
+        }
+
+        ("(new)".to_string(), config_data.number)
+    } else {
+        let candy_pubkey = Pubkey::from_str(&cache.program.candy_machine).map_err(|_| {
+            CacheError::InvalidCandyMachineAddress(cache.program.candy_machine.clone())
+        })?;
+        let sugar_config = sugar_setup(args.keypair.clone(), args.rpc_url.clone())?;
+        let state = get_candy_machine_state(&sugar_config, &candy_pubkey)?;
+
+        if state.data.hidden_settings.is_some() {
+            return Err(AppendError::HiddenSettings.into());
+        }
+
+        if state.items_redeemed >= state.data.items_available {
+            return Err(AppendError::SoldOut(state.items_redeemed).into());
+        }
+
+        (candy_pubkey.to_string(), state.data.items_available)
+    };
+
+    // the source files are copied (upload optimizes them, if enabled), so
+    // these are not the uploaded asset pairs; optimizing them here would also
+    // replace the optimized copies of the assets directory, which share the
+    // same file names
+    let new_pairs = get_asset_pairs(&args.new_assets_dir)?;
+    let first = new_pairs.get(&0).ok_or(AppendError::MissingIndex(0))?;
+
+    // a previous append might have failed after copying the new assets (e.g.,
+    // during the upload or deploy), in which case they keep their indices
+    let current = get_asset_pairs(&args.assets_dir)?
+        .into_iter()
+        .filter(|(_, pair)| {
+            pair.metadata_hash == first.metadata_hash && pair.image_hash == first.image_hash
+        })
+        .map(|(index, _)| index)
+        .min()
+        .unwrap_or(cache.items.0.len());
+
+    if (current + new_pairs.len()) as u64 > items_available {
+        return Err(AppendError::NotEnoughSpace(new_pairs.len(), current, items_available).into());
+    }
+
+    println!(
+        "\n{} Appending {} item(s) to candy machine {} (indices {}-{})",
+        style(">>>").magenta(),
+        new_pairs.len(),
+        candy_machine,
+        current,
+        current + new_pairs.len() - 1
+    );
+
+    // copies the new assets into the assets directory, continuing the
+    // numbering after the items already in the cache; files copied by a
+    // previous append are skipped
+
+    let mut copies = Vec::new();
+
+    for index in 0..new_pairs.len() {
+        let pair = new_pairs
+            .get(&index)
+            .ok_or(AppendError::MissingIndex(index))?;
+        let mut files = vec![pair.image.clone(), pair.metadata.clone()];
+
+        if let Some(animation) = &pair.animation {
+            files.push(animation.clone());
+        }
+
+        for file in files {
+            let source = Path::new(&file);
+            let extension = source
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or_default();
+            let target = Path::new(&args.assets_dir).join(format!(
+                "{}.{}",
+                current + index,
+                extension.to_lowercase()
+            ));
+            let target_path = path_to_string(&target)?;
+
+            if target.exists() {
+                if hash_file(&target_path)? == hash_file(&file)? {
+                    continue;
+                }
+                return Err(AppendError::FileExists(target_path).into());
+            }
+
+            copies.push((source.to_path_buf(), target));
+        }
+    }
+
+    for (source, target) in copies {
+        fs::copy(&source, &target)?;
+    }
+
+    println!("\n{} sugar upload\n", style(">>>").magenta());
+
+    process_upload(UploadArgs {
+        assets_dir: args.assets_dir.clone(),
+        config: args.config.clone(),
+        keypair: args.keypair.clone(),
+        rpc_url: args.rpc_url.clone(),
+        cache: args.cache.clone(),
+        concurrency: None,
+        rate_limit: None,
+        dry_run: false,
+        bundlr_node: None,
+        interrupted: args.interrupted.clone(),
+    })
+    .await?;
+
+    println!("\n{} sugar deploy\n", style(">>>").magenta());
+
+    process_deploy(DeployArgs {
+        config: args.config,
+        keypair: args.keypair,
+        rpc_url: args.rpc_url,
+        cache: args.cache,
+        force_rewrite: false,
+        append: true,
+        interrupted: args.interrupted,
+    })
+    .await
+}
@@ -2,28 +2,64 @@ use anchor_client::solana_sdk::{native_token::LAMPORTS_PER_SOL, signer::Signer};
 use bundlr_sdk::{deep_hash::deep_hash, deep_hash::DeepHashChunk};
 use console::style;
 use data_encoding::BASE64URL;
+use futures::{stream, StreamExt};
 
+use crate::cache::load_cache;
 use crate::candy_machine::ID as CANDY_MACHINE_ID;
+use crate::price::usd_suffix;
+use crate::retry::{retry_async, Subsystem};
 use crate::{cli::BundlrAction, common::*, config::*, upload::BundlrHandler, utils::*};
 
 // The minimum amount required for withdraw.
 const LIMIT: u64 = 5000;
 
+/// Number of bytes used to quote the upload price.
+const PRICE_SAMPLE_SIZE: u64 = 1024 * 1024;
+
+/// Prefix of the links of data items uploaded through Bundlr.
+const ARWEAVE_LINK_PREFIX: &str = "https://arweave.net/";
+
+/// Status values of a data item that has been settled on Arweave.
+const SETTLED_STATUS: [&str; 2] = ["CONFIRMED", "FINALIZED"];
+
 pub struct BundlrArgs {
     pub keypair: Option<String>,
     pub rpc_url: Option<String>,
+    pub config: String,
+    pub bundlr_node: Option<String>,
     pub action: BundlrAction,
 }
 
 pub async fn process_bundlr(args: BundlrArgs) -> Result<()> {
     let sugar_config = sugar_setup(args.keypair, args.rpc_url)?;
     let client = setup_client(&sugar_config)?;
+    let program = client.program(CANDY_MACHINE_ID);
+    let http_client = http_client();
+
+    // the node of the command line takes precedence over the config file,
+    // which is optional for bundlr commands
+    let custom_node = match args.bundlr_node {
+        Some(node) => Some(node),
+        None if Path::new(&resolve_config_path(&args.config)).exists() => {
+            get_config_data(&args.config)?.bundlr_node
+        }
+        None => None,
+    };
+    let bundlr_node = BundlrHandler::get_node(&program, custom_node.as_deref())?;
+
+    if custom_node.is_some() {
+        BundlrHandler::get_bundlr_solana_address(&http_client, &bundlr_node).await?;
+    }
+
+    if let BundlrAction::Status { cache } = &args.action {
+        return process_status(&http_client, &bundlr_node, cache).await;
+    }
 
     // retrieving balance
 
     println!(
         "{} {}Retrieving balance",
-        style(if let BundlrAction::Withdraw = args.action {
+        style(if let BundlrAction::Withdraw { .. } = args.action {
             "[1/2]"
         } else {
             "[1/1]"
@@ -36,67 +72,108 @@ pub async fn process_bundlr(args: BundlrArgs) -> Result<()> {
     let pb = spinner_with_style();
     pb.set_message("Connecting...");
 
-    let program = client.program(CANDY_MACHINE_ID);
-    let solana_cluster: Cluster = get_cluster(program.rpc())?;
-
-    let http_client = reqwest::Client::new();
     let keypair = sugar_config.keypair;
     let address = keypair.pubkey().to_string();
-    let bundlr_node = match solana_cluster {
-        Cluster::Devnet => BUNDLR_DEVNET,
-        Cluster::Mainnet => BUNDLR_MAINNET,
-    };
 
-    let balance = BundlrHandler::get_bundlr_balance(&http_client, &address, bundlr_node).await?;
+    let balance = BundlrHandler::get_bundlr_balance(&http_client, &address, &bundlr_node).await?;
+    let price = if let BundlrAction::Balance = args.action {
+        Some(BundlrHandler::get_bundlr_fee(&http_client, &bundlr_node, PRICE_SAMPLE_SIZE).await?)
+    } else {
+        None
+    };
 
     pb.finish_and_clear();
 
+    println!("\nBundlr node:");
+    if custom_node.is_none() {
+        println!("  -> cluster: {}", get_cluster(program.rpc())?.to_string());
+    }
+    println!("  -> url: {}", bundlr_node);
+
     println!("\nFunding address:");
     println!("  -> pubkey: {}", address);
     println!(
-        "  -> lamports: {} (◎ {})",
+        "  -> lamports: {} (◎ {}{})",
         balance,
-        balance as f64 / LAMPORTS_PER_SOL as f64
+        balance as f64 / LAMPORTS_PER_SOL as f64,
+        usd_suffix(balance)
     );
 
+    if let Some(price) = price {
+        println!("\nUpload price:");
+        println!(
+            "  -> per MB: {} lamports (◎ {}{})",
+            price,
+            price as f64 / LAMPORTS_PER_SOL as f64,
+            usd_suffix(price)
+        );
+        println!(
+            "  -> per byte: {:.4} lamports",
+            price as f64 / PRICE_SAMPLE_SIZE as f64
+        );
+    }
+
     // withdrawing funds
 
-    if let BundlrAction::Withdraw = args.action {
+    if let BundlrAction::Withdraw { amount } = args.action {
         println!(
             "\n{} {}Withdrawing funds",
             style("[2/2]").bold().dim(),
             WITHDRAW_EMOJI
         );
 
+        // the node keeps a minimum amount to pay for the withdraw
+        let available = balance.saturating_sub(LIMIT);
+
         if balance == 0 {
             println!("\nNo funds to withdraw.");
-        } else if (balance - LIMIT) > 0 {
+        } else if available == 0 {
+            println!(
+                "\n{}",
+                style("Insufficient balance for withdraw:").red().bold()
+            );
+            println!(
+                "  -> required balance > {} (◎ {}{})",
+                LIMIT,
+                LIMIT as f64 / LAMPORTS_PER_SOL as f64,
+                usd_suffix(LIMIT)
+            );
+        } else {
+            let amount = match amount {
+                Some(amount) if amount == 0 || amount > available => {
+                    return Err(anyhow!(
+                        "Invalid withdraw amount {}, the available balance is {} lamports",
+                        amount,
+                        available
+                    ));
+                }
+                Some(amount) => amount,
+                None => available,
+            };
+
             let pb = spinner_with_style();
             pb.set_message("Connecting...");
 
-            let balance = balance - LIMIT;
-
             // nonce
             let url = format!("{bundlr_node}/account/withdrawals/solana/?address={address}");
-            let nonce = if let Some(value) = http_client
-                .get(&url)
-                .send()
-                .await?
-                .json::<Value>()
-                .await?
-                .as_u64()
+            let nonce = match retry_async(Subsystem::Rpc, || async {
+                Ok(http_client.get(&url).send().await?.json::<Value>().await?)
+            })
+            .await?
+            .as_u64()
             {
-                value
-            } else {
-                pb.finish_and_clear();
-                return Err(anyhow!("Failed to retrieve nonce for withdraw"));
+                Some(value) => value,
+                None => {
+                    pb.finish_and_clear();
+                    return Err(anyhow!("Failed to retrieve nonce for withdraw"));
+                }
             };
 
             // sign the message
 
             let message = deep_hash(DeepHashChunk::Chunks(vec![
                 DeepHashChunk::Chunk("solana".to_string().as_bytes().to_vec().into()),
-                DeepHashChunk::Chunk(balance.to_string().as_bytes().to_vec().into()),
+                DeepHashChunk::Chunk(amount.to_string().as_bytes().to_vec().into()),
                 DeepHashChunk::Chunk(nonce.to_string().as_bytes().to_vec().into()),
             ]))
             .await?;
@@ -105,7 +182,7 @@ pub async fn process_bundlr(args: BundlrArgs) -> Result<()> {
             let mut data = HashMap::new();
             data.insert("publicKey", BASE64URL.encode(&keypair.pubkey().to_bytes()));
             data.insert("currency", "solana".to_string());
-            data.insert("amount", balance.to_string());
+            data.insert("amount", amount.to_string());
             data.insert("nonce", nonce.to_string());
             data.insert("signature", BASE64URL.encode(signature.as_ref()));
             data.insert("sigType", "2".to_string());
@@ -117,23 +194,132 @@ pub async fn process_bundlr(args: BundlrArgs) -> Result<()> {
 
             if response.status() == 200 {
                 println!("\nWithdraw completed.");
+                println!(
+                    "  -> withdrawn: {} (◎ {}{})",
+                    amount,
+                    amount as f64 / LAMPORTS_PER_SOL as f64,
+                    usd_suffix(amount)
+                );
+
+                // the node might take a moment to update the balance
+                if let Ok(remaining) =
+                    BundlrHandler::get_bundlr_balance(&http_client, &address, &bundlr_node).await
+                {
+                    println!(
+                        "  -> remaining balance: {} (◎ {}{})",
+                        remaining,
+                        remaining as f64 / LAMPORTS_PER_SOL as f64,
+                        usd_suffix(remaining)
+                    );
+                }
             } else {
                 println!("\n{}", style("Withdraw failed.").red().bold());
                 let error = response.text().await?;
                 return Err(anyhow!("Failed to complete withdraw ({})", error));
             }
-        } else {
-            println!(
-                "\n{}",
-                style("Insufficient balance for withdraw:").red().bold()
-            );
-            println!(
-                "  -> required balance > {} (◎ {})",
-                LIMIT,
-                LIMIT as f64 / LAMPORTS_PER_SOL as f64
-            );
         }
     }
 
     Ok(())
 }
+
+/// Check the status of every data item referenced by the cache.
+async fn process_status(http_client: &HttpClient, bundlr_node: &str, cache: &str) -> Result<()> {
+    println!(
+        "{} {}Loading cache",
+        style("[1/2]").bold().dim(),
+        ASSETS_EMOJI
+    );
+
+    let cache = load_cache(cache, false)?;
+    let mut items = Vec::new();
+
+    for (index, item) in &cache.items.0 {
+        let mut links = vec![("image", item.image_link.as_str())];
+        if let Some(animation_link) = &item.animation_link {
+            links.push(("animation", animation_link.as_str()));
+        }
+        links.push(("metadata", item.metadata_link.as_str()));
+
+        for (kind, link) in links {
+            if let Some(id) = link.strip_prefix(ARWEAVE_LINK_PREFIX) {
+                items.push((index.to_string(), kind, id.to_string()));
+            }
+        }
+    }
+
+    println!("Found {} Bundlr data item(s) in the cache.", items.len());
+
+    println!(
+        "\n{} {}Checking data items",
+        style("[2/2]").bold().dim(),
+        LOOKING_GLASS_EMOJI
+    );
+
+    let pb = progress_bar_with_style(items.len() as u64);
+
+    let results: Vec<(String, &str, String)> = stream::iter(items)
+        .map(|(index, kind, id)| {
+            let pb = &pb;
+            async move {
+                let status = match get_data_item_status(http_client, bundlr_node, &id).await {
+                    Ok(status) => status,
+                    Err(err) => format!("ERROR ({})", err),
+                };
+                pb.inc(1);
+                (index, kind, status)
+            }
+        })
+        .buffer_unordered(PARALLEL_LIMIT)
+        .collect()
+        .await;
+
+    pb.finish();
+
+    let mut unsettled: Vec<&(String, &str, String)> = results
+        .iter()
+        .filter(|(_, _, status)| !SETTLED_STATUS.contains(&status.as_str()))
+        .collect();
+
+    println!(
+        "\n{} settled, {} not settled",
+        results.len() - unsettled.len(),
+        unsettled.len()
+    );
+
+    if !unsettled.is_empty() {
+        unsettled.sort_by(|a, b| a.0.cmp(&b.0));
+
+        println!("\nData items not settled on Arweave:");
+        for (index, kind, status) in &unsettled {
+            println!("- Item {} ({}): {}", index, kind, status);
+        }
+
+        return Err(anyhow!(
+            "{} data item(s) not settled on Arweave, re-run `upload` if they remain unsettled.",
+            unsettled.len()
+        ));
+    }
+
+    Ok(())
+}
+
+/// Return the status of a data item as reported by the Bundlr node.
+async fn get_data_item_status(http_client: &HttpClient, node: &str, id: &str) -> Result<String> {
+    let response = http_client
+        .get(format!("{node}/tx/{id}/status"))
+        .send()
+        .await?;
+
+    if response.status() == 404 {
+        return Ok("NOT_FOUND".to_string());
+    }
+
+    let data = response.error_for_status()?.json::<Value>().await?;
+
+    Ok(data
+        .get("status")
+        .and_then(Value::as_str)
+        .unwrap_or("UNKNOWN")
+        .to_string())
+}
@@ -0,0 +1,535 @@
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anyhow::Result;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    fmt::{self, Display, Formatter},
+    path::Path,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use mpl_candy_machine::ConfigLine;
+
+use crate::cache::{
+    has_sqlite_extension, is_sqlite_file, lock_cache_file, read_sqlite_cache, write_atomically,
+    SqliteStore,
+};
+use crate::common::*;
+use crate::mint::pdas::get_candy_machine_creator_pda;
+use crate::upload::TemplateVariables;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Cache {
+    /// Version of the cache format, files without a version are version 1.
+    #[serde(default = "legacy_version")]
+    pub version: u8,
+    pub program: CacheProgram,
+    pub items: CacheItems,
+    #[serde(skip_deserializing, skip_serializing)]
+    pub file_path: String,
+    /// Storage format of the cache file.
+    #[serde(skip_deserializing, skip_serializing)]
+    pub backend: CacheBackend,
+    /// Keys of the items modified since the last time the file was written.
+    #[serde(skip_deserializing, skip_serializing)]
+    dirty: HashSet<String>,
+    /// Time of the last write to the cache file.
+    #[serde(skip_deserializing, skip_serializing)]
+    last_sync: Option<Instant>,
+    /// Database of the SQLite backend.
+    #[serde(skip_deserializing, skip_serializing)]
+    pub sqlite: SqliteStore,
+}
+
+impl Cache {
+    pub fn new() -> Self {
+        Cache {
+            version: CACHE_VERSION,
+            program: CacheProgram::new(),
+            items: CacheItems::new(),
+            file_path: String::new(),
+            backend: CacheBackend::Json,
+            dirty: HashSet::new(),
+            last_sync: None,
+            sqlite: SqliteStore::default(),
+        }
+    }
+
+    pub fn write_to_file<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.refresh_statuses();
+        lock_cache_file(path.as_ref())?;
+
+        match self.backend {
+            CacheBackend::Json => {
+                let c = serde_json::to_string(&self)?;
+                write_atomically(path.as_ref(), c.as_bytes())?;
+            }
+            // only the items that differ from the database are written
+            CacheBackend::Sqlite => self.sqlite.write(
+                path.as_ref(),
+                self.version,
+                &self.program,
+                &self.items,
+                None,
+            )?,
+        }
+
+        self.dirty.clear();
+        self.last_sync = Some(Instant::now());
+
+        Ok(())
+    }
+
+    /// Write the modified items to the cache file. The JSON backend has to
+    /// write the whole file, while the SQLite backend only compares the
+    /// flagged items with the database.
+    fn write_dirty(&mut self) -> Result<()> {
+        if self.backend == CacheBackend::Json {
+            return self.sync_file();
+        }
+
+        self.refresh_statuses();
+        let path = PathBuf::from(&self.file_path);
+        lock_cache_file(&path)?;
+        self.sqlite.write(
+            &path,
+            self.version,
+            &self.program,
+            &self.items,
+            Some(&self.dirty),
+        )?;
+
+        self.dirty.clear();
+        self.last_sync = Some(Instant::now());
+
+        Ok(())
+    }
+
+    /// Update the status of the items to match their links.
+    fn refresh_statuses(&mut self) {
+        let now = Utc::now().timestamp();
+
+        for item in self.items.0.values_mut() {
+            item.refresh_status(now);
+        }
+    }
+
+    /// Write the cache to its file, independently of pending changes.
+    pub fn sync_file(&mut self) -> Result<()> {
+        let file_path = self.file_path.clone();
+        self.write_to_file(Path::new(&file_path))
+    }
+
+    /// Return a mutable reference to a cache item, flagging it as modified.
+    pub fn get_item_mut(&mut self, key: &str) -> Option<&mut CacheItem> {
+        let item = self.items.0.get_mut(key);
+
+        if item.is_some() {
+            self.dirty.insert(key.to_string());
+        }
+
+        item
+    }
+
+    /// Flag a cache item as modified.
+    pub fn mark_dirty(&mut self, key: &str) {
+        self.dirty.insert(key.to_string());
+    }
+
+    /// Record the error of the last operation on a cache item. The error is
+    /// cleared once the item moves to a later status.
+    pub fn record_error(&mut self, key: &str, error: &str) {
+        if let Some(item) = self.get_item_mut(key) {
+            item.last_error = Some(error.to_string());
+        }
+    }
+
+    /// Upgrade a cache of a previous version to the current format. The
+    /// status of the items is derived from their links, while the times of
+    /// the previous uploads are unknown.
+    fn migrate(&mut self) {
+        if self.version < 2 {
+            for item in self.items.0.values_mut() {
+                item.status = item.current_status();
+            }
+        }
+
+        info!(
+            "Migrated cache from version {} to {}",
+            self.version, CACHE_VERSION
+        );
+        self.version = CACHE_VERSION;
+    }
+
+    /// Write the cache to its file only if there are modified items and the
+    /// checkpoint interval has elapsed since the last write. Callers must still
+    /// call `sync_file` once they are done to persist any pending changes.
+    pub fn checkpoint(&mut self) -> Result<()> {
+        if self.dirty.is_empty() {
+            return Ok(());
+        }
+
+        let elapsed = match self.last_sync {
+            Some(last_sync) => last_sync.elapsed(),
+            None => Duration::MAX,
+        };
+
+        if elapsed >= Duration::from_secs(CACHE_CHECKPOINT_INTERVAL)
+            || self.dirty.len() >= CACHE_CHECKPOINT_ITEMS
+        {
+            self.write_dirty()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for Cache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CacheProgram {
+    #[serde(rename = "candyMachine")]
+    pub candy_machine: String,
+    #[serde(rename = "candyMachineCreator")]
+    pub candy_machine_creator: String,
+    /// Address of the candy guard wrapping the candy machine.
+    #[serde(
+        rename = "candyGuard",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub candy_guard: Option<String>,
+    /// Id of the collection manifest data item.
+    #[serde(
+        rename = "collectionManifest",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub collection_manifest: Option<String>,
+    /// Mint address of the collection NFT of the candy machine.
+    #[serde(
+        rename = "collectionMint",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub collection_mint: Option<String>,
+    /// Fingerprint of the config and config lines of the last successful deploy.
+    #[serde(
+        rename = "deployFingerprint",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub deploy_fingerprint: Option<String>,
+    /// Values of the metadata template placeholders used in the upload.
+    #[serde(
+        rename = "templateVariables",
+        default,
+        skip_serializing_if = "TemplateVariables::is_empty"
+    )]
+    pub template_variables: TemplateVariables,
+}
+
+impl CacheProgram {
+    pub fn new() -> Self {
+        CacheProgram {
+            candy_machine: String::new(),
+            candy_machine_creator: String::new(),
+            candy_guard: None,
+            collection_manifest: None,
+            collection_mint: None,
+            deploy_fingerprint: None,
+            template_variables: TemplateVariables::new(),
+        }
+    }
+
+    pub fn new_from_cm(candy_machine: &Pubkey) -> Self {
+        let (candy_machine_creator_pda, _creator_bump) =
+            get_candy_machine_creator_pda(candy_machine);
+        CacheProgram {
+            candy_machine: candy_machine.to_string(),
+            candy_machine_creator: candy_machine_creator_pda.to_string(),
+            candy_guard: None,
+            collection_manifest: None,
+            collection_mint: None,
+            deploy_fingerprint: None,
+            template_variables: TemplateVariables::new(),
+        }
+    }
+}
+
+impl Default for CacheProgram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CacheItems(pub IndexMap<String, CacheItem>);
+
+impl CacheItems {
+    pub fn new() -> Self {
+        CacheItems(IndexMap::new())
+    }
+}
+impl Default for CacheItems {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Storage format of a cache file.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CacheBackend {
+    /// Single JSON file, rewritten on every update.
+    #[default]
+    Json,
+    /// SQLite database, only the modified items are written on an update.
+    Sqlite,
+}
+
+impl Display for CacheBackend {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            CacheBackend::Json => write!(f, "json"),
+            CacheBackend::Sqlite => write!(f, "sqlite"),
+        }
+    }
+}
+
+impl FromStr for CacheBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(CacheBackend::Json),
+            "sqlite" => Ok(CacheBackend::Sqlite),
+            _ => Err(format!(
+                "invalid cache backend '{}', expected 'json' or 'sqlite'",
+                s
+            )),
+        }
+    }
+}
+
+/// Progress of a cache item through the upload and deploy.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ItemStatus {
+    /// Some of the files are not uploaded.
+    #[default]
+    Pending,
+    /// All files are uploaded, but the config line is not written.
+    Uploaded,
+    /// The config line is written to the candy machine.
+    OnChain,
+}
+
+impl Display for ItemStatus {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            ItemStatus::Pending => write!(f, "pending"),
+            ItemStatus::Uploaded => write!(f, "uploaded"),
+            ItemStatus::OnChain => write!(f, "on-chain"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CacheItem {
+    pub name: String,
+    pub image_hash: String,
+    pub image_link: String,
+    pub metadata_hash: String,
+    pub metadata_link: String,
+    #[serde(rename = "onChain")]
+    pub on_chain: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub animation_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub animation_link: Option<String>,
+    /// Downscaled preview of the image, set when previews are enabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preview_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preview_link: Option<String>,
+    /// Chunked upload in progress, used to resume it after an interruption.
+    #[serde(
+        rename = "pendingUpload",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub pending_upload: Option<PendingUpload>,
+    /// Status of the item, updated when the cache file is written.
+    #[serde(default)]
+    pub status: ItemStatus,
+    /// Time (unix timestamp) all files of the item were uploaded.
+    #[serde(
+        rename = "uploadedAt",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub uploaded_at: Option<i64>,
+    /// Time (unix timestamp) the config line of the item was written.
+    #[serde(rename = "onChainAt", default, skip_serializing_if = "Option::is_none")]
+    pub on_chain_at: Option<i64>,
+    /// Error of the last failed operation on the item.
+    #[serde(rename = "lastError", default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+/// Resume marker of a chunked upload.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingUpload {
+    /// Type of the uploaded file ("image" or "animation").
+    pub data_type: String,
+    /// Hash of the uploaded file, a different file discards the upload.
+    pub hash: String,
+    /// Id of the upload on the storage node.
+    pub upload_id: String,
+    /// Size of the chunks of the upload.
+    pub chunk_size: u64,
+    /// Path of the signed data being uploaded.
+    pub data_path: String,
+}
+
+impl CacheItem {
+    pub fn into_config_line(&self) -> Option<ConfigLine> {
+        if !self.on_chain {
+            Some(ConfigLine {
+                name: self.name.clone(),
+                uri: self.metadata_link.clone(),
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Return whether all files of the item are uploaded, i.e., every file
+    /// has a link. The hash of each file is the hash of the uploaded content.
+    pub fn is_uploaded(&self) -> bool {
+        let uploaded = |link: &Option<String>| link.as_ref().map_or(true, |l| !l.is_empty());
+
+        !self.image_link.is_empty()
+            && !self.metadata_link.is_empty()
+            && uploaded(&self.animation_link)
+            && uploaded(&self.preview_link)
+    }
+
+    /// Return the status of the item derived from its links.
+    pub fn current_status(&self) -> ItemStatus {
+        if self.on_chain {
+            ItemStatus::OnChain
+        } else if self.is_uploaded() {
+            ItemStatus::Uploaded
+        } else {
+            ItemStatus::Pending
+        }
+    }
+
+    /// Update the status and timestamps of the item. The error of the last
+    /// operation is cleared when the item moves to a later status.
+    pub fn refresh_status(&mut self, now: i64) {
+        let status = self.current_status();
+
+        if status == self.status {
+            return;
+        }
+
+        if status > self.status {
+            self.last_error = None;
+        }
+
+        self.uploaded_at = match status {
+            ItemStatus::Pending => None,
+            _ => self.uploaded_at.or(Some(now)),
+        };
+        self.on_chain_at = match status {
+            ItemStatus::OnChain => self.on_chain_at.or(Some(now)),
+            _ => None,
+        };
+        self.status = status;
+    }
+}
+
+fn legacy_version() -> u8 {
+    1
+}
+
+pub fn load_cache(cache_file_path: &str, create: bool) -> Result<Cache> {
+    let cache_file_path = Path::new(cache_file_path);
+    if !cache_file_path.exists() {
+        if create {
+            lock_cache_file(cache_file_path)?;
+            // if the cache file does not exist, creates a new Cache object
+            let mut cache = Cache::new();
+            cache.file_path = path_to_string(cache_file_path)?;
+            if has_sqlite_extension(cache_file_path) {
+                cache.backend = CacheBackend::Sqlite;
+            }
+            Ok(cache)
+        } else {
+            let cache_file_string = path_to_string(cache_file_path)?;
+            let error = CacheError::CacheFileNotFound(cache_file_string).into();
+            error!("{:?}", error);
+            Err(error)
+        }
+    } else {
+        info!("Cache exists, loading...");
+        // the cache is locked before it is read, so no other process can
+        // update it in the meantime
+        lock_cache_file(cache_file_path)?;
+
+        let mut cache = if is_sqlite_file(cache_file_path) {
+            read_sqlite_cache(cache_file_path).map_err(|error| {
+                error!("{:?}", error);
+                error
+            })?
+        } else {
+            read_json_cache(cache_file_path)?
+        };
+        cache.file_path = path_to_string(cache_file_path)?;
+
+        if cache.version > CACHE_VERSION {
+            let error = CacheError::UnsupportedVersion(cache.version, CACHE_VERSION).into();
+            error!("{:?}", error);
+            return Err(error);
+        }
+
+        // the migrated cache is written on the next update
+        if cache.version < CACHE_VERSION {
+            cache.migrate();
+        }
+
+        Ok(cache)
+    }
+}
+
+fn read_json_cache(cache_file_path: &Path) -> Result<Cache> {
+    let file = match File::open(cache_file_path) {
+        Ok(file) => file,
+        Err(err) => {
+            let cache_file_string = path_to_string(cache_file_path)?;
+            let error =
+                CacheError::FailedToOpenCacheFile(cache_file_string, err.to_string()).into();
+            error!("{:?}", error);
+            return Err(error);
+        }
+    };
+
+    match serde_json::from_reader(file) {
+        Ok(cache) => Ok(cache),
+        Err(err) => {
+            let error = CacheError::CacheFileWrongFormat(err.to_string()).into();
+            error!("{:?}", error);
+            Err(error)
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use fs2::FileExt;
+use std::{
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+use crate::common::*;
+
+/// Lock files held by this process, so that a cache file loaded more than
+/// once (e.g., verify running a deploy) is only locked once.
+static CACHE_LOCKS: Mutex<Vec<(PathBuf, File)>> = Mutex::new(Vec::new());
+
+/// Whether cache files are locked when loaded. Commands that do not write
+/// the cache do not lock it, so they can run alongside a command that does.
+static LOCK_CACHE: AtomicBool = AtomicBool::new(true);
+
+/// Disable the locking of the cache files for the current process.
+pub fn disable_cache_lock() {
+    LOCK_CACHE.store(false, Ordering::SeqCst);
+}
+
+/// Return the path of the lock file of a cache file.
+fn lock_path(cache_file_path: &Path) -> PathBuf {
+    let mut file_name = cache_file_path
+        .file_name()
+        .map(|name| name.to_os_string())
+        .unwrap_or_default();
+    file_name.push(".lock");
+    cache_file_path.with_file_name(file_name)
+}
+
+/// Acquire an advisory lock on a cache file for the lifetime of the process.
+/// Fails straight away if another process holds the lock, instead of waiting
+/// for it. The lock is released by the OS when the process exits, even if it
+/// is killed.
+pub fn lock_cache_file(cache_file_path: &Path) -> Result<()> {
+    if !LOCK_CACHE.load(Ordering::SeqCst) {
+        return Ok(());
+    }
+
+    let path = lock_path(cache_file_path);
+    let file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .open(&path)
+        .map_err(|err| {
+            anyhow!(
+                "Could not open cache lock file '{}': {}",
+                path.display(),
+                err
+            )
+        })?;
+    let key = fs::canonicalize(&path)?;
+
+    let mut locks = CACHE_LOCKS.lock().expect("Cache locks poisoned");
+
+    if locks.iter().any(|(locked, _)| *locked == key) {
+        return Ok(());
+    }
+
+    if file.try_lock_exclusive().is_err() {
+        let error = CacheError::CacheFileLocked(path_to_string(cache_file_path)?).into();
+        error!("{:?}", error);
+        return Err(error);
+    }
+
+    locks.push((key, file));
+
+    Ok(())
+}
+
+/// Write the content of a file atomically: the content is written to a
+/// temporary file in the same directory, which then replaces the file. An
+/// interrupted write leaves the previous content of the file intact.
+pub fn write_atomically(path: &Path, content: &[u8]) -> Result<()> {
+    let mut file_name = path
+        .file_name()
+        .map(|name| name.to_os_string())
+        .unwrap_or_default();
+    file_name.push(format!(".{}.tmp", std::process::id()));
+    let temp_path = path.with_file_name(file_name);
+
+    let result = File::create(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(content)?;
+            // the content must be on disk before the file is replaced
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&temp_path, path));
+
+    if let Err(err) = result {
+        let _ = fs::remove_file(&temp_path);
+        return Err(anyhow!("Could not write '{}': {}", path.display(), err));
+    }
+
+    Ok(())
+}
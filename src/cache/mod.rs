@@ -0,0 +1,13 @@
+pub mod data;
+pub mod lock;
+pub mod process;
+pub mod recover;
+pub mod repair;
+pub mod sqlite;
+
+pub use data::*;
+pub use lock::*;
+pub use process::*;
+pub use recover::*;
+pub use repair::*;
+pub use sqlite::*;
@@ -0,0 +1,458 @@
+use console::style;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+};
+
+use crate::cache::{load_cache, process_recover, process_repair, RecoverArgs, RepairArgs};
+use crate::cli::CacheAction;
+use crate::common::*;
+use crate::upload::list_files;
+
+/// Extensions of the image files of an asset.
+const IMAGE_EXTENSIONS: [&str; 3] = ["png", "jpg", "gif"];
+
+/// Number of item errors listed by the inspect action.
+const MAX_LISTED_ERRORS: usize = 10;
+
+/// Links of a cache item, as exported.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportItem<'a> {
+    index: &'a str,
+    name: &'a str,
+    status: ItemStatus,
+    image_link: &'a str,
+    animation_link: Option<&'a str>,
+    preview_link: Option<&'a str>,
+    metadata_link: &'a str,
+}
+
+pub struct CacheArgs {
+    pub action: CacheAction,
+}
+
+pub async fn process_cache(args: CacheArgs) -> Result<()> {
+    match args.action {
+        CacheAction::Inspect { cache } => process_inspect(&cache),
+        CacheAction::Repair {
+            assets_dir,
+            cache,
+            config,
+            skip_links,
+            dry_run,
+        } => {
+            process_repair(RepairArgs {
+                assets_dir,
+                cache,
+                config,
+                skip_links,
+                dry_run,
+            })
+            .await
+        }
+        CacheAction::Prune {
+            assets_dir,
+            cache,
+            dry_run,
+        } => process_prune(&assets_dir, &cache, dry_run),
+        CacheAction::Recover {
+            candy_machine,
+            keypair,
+            rpc_url,
+            cache,
+            assets_dir,
+            config,
+            force,
+        } => {
+            process_recover(RecoverArgs {
+                candy_machine,
+                keypair,
+                rpc_url,
+                cache,
+                assets_dir,
+                config,
+                force,
+            })
+            .await
+        }
+        CacheAction::Convert {
+            format,
+            cache,
+            output,
+            force,
+        } => process_convert(&cache, format, output, force),
+        CacheAction::Export { cache, csv, output } => process_export(&cache, csv, output),
+    }
+}
+
+fn process_inspect(cache_file: &str) -> Result<()> {
+    let cache = load_cache(cache_file, false)?;
+
+    let mut statuses: BTreeMap<ItemStatus, usize> = BTreeMap::new();
+    let mut missing: BTreeMap<&str, usize> = BTreeMap::new();
+    let mut errors = Vec::new();
+    let mut last_upload = None;
+    let mut last_deploy = None;
+
+    for (index, item) in &cache.items.0 {
+        // the stored status is only updated when the cache is written
+        *statuses.entry(item.current_status()).or_default() += 1;
+
+        let links = [
+            ("image", Some(&item.image_link)),
+            ("animation", item.animation_link.as_ref()),
+            ("preview", item.preview_link.as_ref()),
+            ("metadata", Some(&item.metadata_link)),
+        ];
+
+        for (kind, link) in links {
+            if link.map_or(false, |link| link.is_empty()) {
+                *missing.entry(kind).or_default() += 1;
+            }
+        }
+
+        if let Some(error) = &item.last_error {
+            errors.push((index, error));
+        }
+
+        last_upload = last_upload.max(item.uploaded_at);
+        last_deploy = last_deploy.max(item.on_chain_at);
+    }
+
+    println!(
+        "{} {}",
+        style("Cache file:").bold(),
+        style(format!(
+            "{} ({}, version {})",
+            cache_file, cache.backend, cache.version
+        ))
+        .dim()
+    );
+    println!(
+        "{} {}",
+        style("Candy machine:").bold(),
+        if cache.program.candy_machine.is_empty() {
+            "not deployed".to_string()
+        } else {
+            cache.program.candy_machine.clone()
+        }
+    );
+    if let Some(collection_mint) = &cache.program.collection_mint {
+        println!("{} {}", style("Collection mint:").bold(), collection_mint);
+    }
+
+    println!(
+        "
+{} {}",
+        style("Items:").bold(),
+        cache.items.0.len()
+    );
+    for status in [
+        ItemStatus::OnChain,
+        ItemStatus::Uploaded,
+        ItemStatus::Pending,
+    ] {
+        println!(
+            "  {:<10} {}",
+            format!("{}:", status),
+            statuses.get(&status).unwrap_or(&0)
+        );
+    }
+
+    if missing.is_empty() {
+        println!(
+            "
+All items have their links."
+        );
+    } else {
+        println!(
+            "
+{}",
+            style("Missing links:").bold()
+        );
+        for (kind, count) in &missing {
+            println!("  {:<10} {}", format!("{}:", kind), count);
+        }
+    }
+
+    if last_upload.is_some() || last_deploy.is_some() {
+        println!();
+    }
+    if let Some(timestamp) = last_upload {
+        println!(
+            "{} {}",
+            style("Last upload:").bold(),
+            format_timestamp(timestamp)
+        );
+    }
+    if let Some(timestamp) = last_deploy {
+        println!(
+            "{} {}",
+            style("Last deploy:").bold(),
+            format_timestamp(timestamp)
+        );
+    }
+
+    if !errors.is_empty() {
+        println!(
+            "
+{}",
+            style(format!("Items with errors ({}):", errors.len()))
+                .yellow()
+                .bold()
+        );
+        for (index, error) in errors.iter().take(MAX_LISTED_ERRORS) {
+            println!("- Item {}: {}", index, error);
+        }
+        if errors.len() > MAX_LISTED_ERRORS {
+            println!("... and {} more", errors.len() - MAX_LISTED_ERRORS);
+        }
+    }
+
+    Ok(())
+}
+
+fn process_convert(
+    cache_file: &str,
+    format: CacheBackend,
+    output: Option<String>,
+    force: bool,
+) -> Result<()> {
+    let mut cache = load_cache(cache_file, false)?;
+
+    if cache.backend == format {
+        return Err(anyhow!(
+            "Cache file '{}' already uses the {} format",
+            cache_file,
+            format
+        ));
+    }
+
+    let output = output.unwrap_or_else(|| {
+        let extension = match format {
+            CacheBackend::Json => "json",
+            CacheBackend::Sqlite => "db",
+        };
+        path_to_string(&Path::new(cache_file).with_extension(extension))
+            .unwrap_or_else(|_| format!("cache.{}", extension))
+    });
+    let output_path = Path::new(&output);
+
+    if output_path.exists() {
+        if fs::canonicalize(output_path)? == fs::canonicalize(cache_file)? {
+            return Err(anyhow!(
+                "The converted file must be different from the cache file '{}'",
+                cache_file
+            ));
+        }
+        if !force {
+            return Err(CacheError::CacheFileExists(output).into());
+        }
+        // a SQLite database would otherwise be updated instead of replaced
+        fs::remove_file(output_path)
+            .map_err(|err| anyhow!("Could not remove '{}': {}", output, err))?;
+    }
+
+    cache.backend = format;
+    cache.file_path = output.clone();
+    cache.sync_file()?;
+
+    println!(
+        "{}Converted {} cache item(s) to '{}' ({}).",
+        COMPLETE_EMOJI,
+        cache.items.0.len(),
+        output,
+        format
+    );
+    println!(
+        "Use '--cache {}' to run the other commands with the converted file.",
+        output
+    );
+
+    Ok(())
+}
+
+fn process_export(cache_file: &str, csv: bool, output: Option<String>) -> Result<()> {
+    let cache = load_cache(cache_file, false)?;
+    let output =
+        output.unwrap_or_else(|| format!("cache-export.{}", if csv { "csv" } else { "json" }));
+
+    let content = if csv {
+        let mut content =
+            "index,name,status,image_link,animation_link,preview_link,metadata_link\n".to_string();
+
+        for (index, item) in &cache.items.0 {
+            let values = [
+                index.as_str(),
+                &item.name,
+                &item.current_status().to_string(),
+                &item.image_link,
+                item.animation_link.as_deref().unwrap_or_default(),
+                item.preview_link.as_deref().unwrap_or_default(),
+                &item.metadata_link,
+            ];
+            let values: Vec<String> = values.iter().map(|value| csv_value(value)).collect();
+            content.push_str(&values.join(","));
+            content.push('\n');
+        }
+
+        content
+    } else {
+        let items: Vec<ExportItem> = cache
+            .items
+            .0
+            .iter()
+            .map(|(index, item)| ExportItem {
+                index,
+                name: &item.name,
+                status: item.current_status(),
+                image_link: &item.image_link,
+                animation_link: item.animation_link.as_deref(),
+                preview_link: item.preview_link.as_deref(),
+                metadata_link: &item.metadata_link,
+            })
+            .collect();
+
+        serde_json::to_string_pretty(&items)?
+    };
+
+    fs::write(&output, content)
+        .map_err(|err| anyhow!("Could not write export file '{}': {}", output, err))?;
+
+    println!(
+        "{}Exported {} cache item(s) to '{}'.",
+        COMPLETE_EMOJI,
+        cache.items.0.len(),
+        output
+    );
+
+    Ok(())
+}
+
+/// Quote a CSV value when it contains separators, quotes or line breaks.
+fn csv_value(value: &str) -> String {
+    if value.contains(|c| matches!(c, ',' | '"' | '\n' | '\r')) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn format_timestamp(timestamp: i64) -> String {
+    chrono::NaiveDateTime::from_timestamp_opt(timestamp, 0)
+        .map(|date| date.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+fn process_prune(assets_dir: &str, cache: &str, dry_run: bool) -> Result<()> {
+    println!(
+        "{} {}Checking cache items",
+        style("[1/2]").bold().dim(),
+        LOOKING_GLASS_EMOJI
+    );
+
+    let mut cache = load_cache(cache, false)?;
+
+    // file names (without extension) of the metadata and image files
+    let mut metadata_files = HashSet::new();
+    let mut image_files = HashSet::new();
+
+    for entry in list_files(assets_dir)? {
+        let path = entry.path();
+        let stem = match path.file_stem().and_then(|s| s.to_str()) {
+            Some(stem) => stem.to_string(),
+            None => continue,
+        };
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+
+        if extension == "json" {
+            metadata_files.insert(stem);
+        } else if IMAGE_EXTENSIONS.contains(&extension.as_str()) {
+            image_files.insert(stem);
+        }
+    }
+
+    let stale: Vec<String> = cache
+        .items
+        .0
+        .keys()
+        .filter(|key| !(metadata_files.contains(*key) && image_files.contains(*key)))
+        .cloned()
+        .collect();
+
+    if stale.is_empty() {
+        println!("\nAll cache items have matching assets, nothing to prune.");
+        return Ok(());
+    }
+
+    println!(
+        "\nFound {} cache item(s) without matching assets:",
+        stale.len()
+    );
+
+    let mut on_chain = 0;
+
+    for key in &stale {
+        let item = &cache.items.0[key];
+        let mut notes = Vec::new();
+
+        if !item.image_link.is_empty() || !item.metadata_link.is_empty() {
+            notes.push("uploaded links will be lost");
+        }
+
+        if item.on_chain {
+            notes.push("config line already on-chain");
+            on_chain += 1;
+        }
+
+        if notes.is_empty() {
+            println!("  {} ({})", key, item.name);
+        } else {
+            println!(
+                "  {} ({}) {}",
+                key,
+                item.name,
+                style(format!("[{}]", notes.join(", "))).yellow()
+            );
+        }
+    }
+
+    if on_chain > 0 {
+        println!(
+            "\n{}",
+            style(format!(
+                "Warning: {} pruned item(s) have config lines on-chain, these are not removed \
+                from the candy machine",
+                on_chain
+            ))
+            .yellow()
+        );
+    }
+
+    println!(
+        "\n{} {}Pruning cache",
+        style("[2/2]").bold().dim(),
+        PAPER_EMOJI
+    );
+
+    if dry_run {
+        println!("Dry run, the cache file was not modified.");
+        return Ok(());
+    }
+
+    for key in &stale {
+        cache.items.0.shift_remove(key);
+    }
+
+    cache.sync_file()?;
+
+    println!("Removed {} item(s) from the cache.", stale.len());
+
+    Ok(())
+}
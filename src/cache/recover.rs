@@ -0,0 +1,375 @@
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_lang::AccountDeserialize;
+use console::style;
+use futures::{stream, StreamExt};
+use mpl_candy_machine::CandyMachine;
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    str::FromStr,
+};
+
+use crate::cache::{load_cache, lock_cache_file, CacheProgram};
+use crate::candy_machine::{get_config_line, ID as CANDY_MACHINE_ID};
+use crate::common::*;
+use crate::rpc_cache;
+use crate::setup::setup_client;
+use crate::upload::{get_uploaded_asset_pairs, AssetPair};
+use crate::utils::*;
+use crate::verify::{check_link, content_checks};
+
+pub struct RecoverArgs {
+    pub candy_machine: String,
+    pub keypair: Option<String>,
+    pub rpc_url: Option<String>,
+    pub cache: String,
+    pub assets_dir: Option<String>,
+    pub config: String,
+    pub force: bool,
+}
+
+/// Links of the files of an item, read from its uploaded metadata.
+#[derive(Debug, Default)]
+struct MetadataLinks {
+    image: String,
+    animation: Option<String>,
+    preview: Option<String>,
+}
+
+pub async fn process_recover(args: RecoverArgs) -> Result<()> {
+    let cache_path = Path::new(&args.cache);
+
+    if cache_path.exists() && !args.force {
+        return Err(CacheError::CacheFileExists(args.cache.clone()).into());
+    }
+
+    // fails straight away if another process is using the cache
+    lock_cache_file(cache_path)?;
+
+    // the uploaded content is only verified against the assets
+    let steps = if args.assets_dir.is_some() { 4 } else { 3 };
+
+    println!(
+        "{} {}Reading config lines",
+        style(format!("[1/{}]", steps)).bold().dim(),
+        LOOKING_GLASS_EMOJI
+    );
+
+    let candy_machine_id = Pubkey::from_str(&args.candy_machine)
+        .map_err(|_| CacheError::InvalidCandyMachineAddress(args.candy_machine.clone()))?;
+
+    let pb = spinner_with_style();
+    pb.set_message("Connecting...");
+
+    let sugar_config = sugar_setup(args.keypair, args.rpc_url)?;
+    let client = setup_client(&sugar_config)?;
+    let program = client.program(CANDY_MACHINE_ID);
+    let data = rpc_cache::get_account_data(&program, &candy_machine_id)?;
+    let candy_machine = CandyMachine::try_deserialize(&mut data.as_slice())?;
+
+    pb.finish_and_clear();
+
+    let mut cache = if cache_path.exists() {
+        // the existing items are replaced with the recovered ones
+        let mut cache = load_cache(&args.cache, false)?;
+        cache.items.0.clear();
+        cache
+    } else {
+        load_cache(&args.cache, true)?
+    };
+    cache.program = CacheProgram::new_from_cm(&candy_machine_id);
+
+    if candy_machine.data.hidden_settings.is_some() {
+        cache.sync_file()?;
+        println!(
+            "\nThe candy machine uses hidden settings and has no config lines, only its \
+            address was recovered."
+        );
+        return Ok(());
+    }
+
+    let items_available = candy_machine.data.items_available as usize;
+    let mut lines = Vec::new();
+
+    for index in 0..items_available {
+        let line = get_config_line(&data, index)?;
+
+        // unwritten lines are empty
+        if !(line.name.is_empty() && line.uri.is_empty()) {
+            lines.push((index, line));
+        }
+    }
+
+    println!(
+        "Found {} of {} config line(s) on-chain.",
+        lines.len(),
+        items_available
+    );
+
+    println!(
+        "\n{} {}Downloading metadata",
+        style(format!("[2/{}]", steps)).bold().dim(),
+        COMPUTER_EMOJI
+    );
+
+    println!("Downloading {} metadata file(s):", lines.len());
+    let pb = progress_bar_with_style(lines.len() as u64);
+    let http_client = http_client();
+
+    let mut results: Vec<(usize, Result<MetadataLinks>)> = stream::iter(&lines)
+        .map(|(index, line)| {
+            let http_client = &http_client;
+            let pb = &pb;
+            async move {
+                let result = fetch_metadata_links(http_client, &line.uri).await;
+                pb.inc(1);
+                (*index, result)
+            }
+        })
+        .buffer_unordered(PARALLEL_LIMIT)
+        .collect()
+        .await;
+
+    pb.finish();
+    results.sort_by_key(|(index, _)| *index);
+
+    // the hashes are only known when the uploaded assets are available,
+    // otherwise the next upload uploads all files again
+    let asset_pairs = match &args.assets_dir {
+        Some(assets_dir) => Some(get_uploaded_asset_pairs(assets_dir, &args.config)?),
+        None => None,
+    };
+
+    let mut failed = Vec::new();
+
+    for ((index, line), (_, result)) in lines.into_iter().zip(results) {
+        let pair = asset_pairs.as_ref().and_then(|pairs| pairs.get(&index));
+
+        let (links, last_error) = match result {
+            Ok(links) => (links, None),
+            Err(err) => {
+                failed.push(index);
+                (
+                    MetadataLinks::default(),
+                    Some(format!("Could not recover the metadata: {}", err)),
+                )
+            }
+        };
+
+        let mut item = CacheItem {
+            name: line.name,
+            image_hash: pair.map(|pair| pair.image_hash.clone()).unwrap_or_default(),
+            image_link: links.image,
+            metadata_hash: pair
+                .map(|pair| pair.metadata_hash.clone())
+                .unwrap_or_default(),
+            metadata_link: line.uri,
+            on_chain: true,
+            animation_hash: pair.and_then(|pair| pair.animation_hash.clone()),
+            animation_link: links.animation,
+            preview_hash: pair.and_then(|pair| pair.preview_hash.clone()),
+            preview_link: links.preview,
+            pending_upload: None,
+            status: ItemStatus::Pending,
+            uploaded_at: None,
+            on_chain_at: None,
+            last_error,
+        };
+        // the times of the upload and deploy are unknown
+        item.status = item.current_status();
+
+        cache.items.0.insert(index.to_string(), item);
+    }
+
+    let mut mismatched = BTreeMap::new();
+
+    if let Some(asset_pairs) = &asset_pairs {
+        println!(
+            "\n{} {}Verifying uploaded content",
+            style(format!("[3/{}]", steps)).bold().dim(),
+            LOOKING_GLASS_EMOJI
+        );
+
+        mismatched = verify_uploaded_content(&cache, asset_pairs).await;
+
+        // the local hashes are only kept when the uploaded content matches
+        // them, the other files are uploaded again by the next upload
+        for (index, kinds) in &mismatched {
+            if let Some(item) = cache.items.0.get_mut(&index.to_string()) {
+                for kind in kinds {
+                    clear_hash(item, kind);
+                }
+            }
+        }
+    }
+
+    println!(
+        "\n{} {}Writing cache",
+        style(format!("[{}/{}]", steps, steps)).bold().dim(),
+        PAPER_EMOJI
+    );
+
+    cache.sync_file()?;
+
+    if !failed.is_empty() {
+        println!(
+            "{}",
+            style(format!(
+                "Could not download the metadata of item(s) {}, their image links are missing.",
+                format_ranges(&failed)
+            ))
+            .yellow()
+        );
+    }
+
+    println!(
+        "\n{}Recovered {} item(s) into '{}'.",
+        COMPLETE_EMOJI,
+        cache.items.0.len(),
+        args.cache
+    );
+
+    if !mismatched.is_empty() {
+        println!(
+            "{}",
+            style(format!(
+                "The uploaded content of item(s) {} does not match the assets, they will be \
+                uploaded again.",
+                format_ranges(&mismatched.into_keys().collect::<Vec<usize>>())
+            ))
+            .yellow()
+        );
+    }
+
+    if asset_pairs.is_none() {
+        println!(
+            "The file hashes are unknown, run with --assets-dir to take them from the uploaded \
+            assets."
+        );
+    }
+    println!("Run 'sugar collection set' to restore the collection of the candy machine.");
+
+    Ok(())
+}
+
+/// Compare the content of the recovered links to the hashes of the assets,
+/// returning the kinds of the files that do not match for each item. Without
+/// the links to check, the kind is 'item'.
+async fn verify_uploaded_content(
+    cache: &Cache,
+    asset_pairs: &HashMap<usize, AssetPair>,
+) -> BTreeMap<usize, BTreeSet<&'static str>> {
+    let mut checks = Vec::new();
+    let mut mismatched: BTreeMap<usize, BTreeSet<&'static str>> = BTreeMap::new();
+
+    for (index, item) in &cache.items.0 {
+        let (index_number, pair) = match index
+            .parse::<usize>()
+            .ok()
+            .and_then(|i| asset_pairs.get(&i).map(|pair| (i, pair)))
+        {
+            Some(pair) => pair,
+            // items without assets have no hashes to keep
+            None => continue,
+        };
+
+        // the metadata of the recovered cache has no template values, so
+        // metadata using placeholders does not match and is uploaded again
+        match content_checks(index, item, pair, &cache.program.template_variables) {
+            Ok(mut item_checks) => checks.append(&mut item_checks),
+            Err(_) => {
+                mismatched.entry(index_number).or_default().insert("item");
+            }
+        }
+    }
+
+    println!("Verifying {} link(s):", checks.len());
+    let pb = progress_bar_with_style(checks.len() as u64);
+    let http_client = http_client();
+
+    let results: Vec<(String, &'static str, bool)> = stream::iter(checks)
+        .map(|check| {
+            let http_client = &http_client;
+            let pb = &pb;
+            async move {
+                let result = check_link(http_client, &check).await;
+                pb.inc(1);
+                (check.index, check.kind, result.is_ok())
+            }
+        })
+        .buffer_unordered(PARALLEL_LIMIT)
+        .collect()
+        .await;
+
+    pb.finish();
+
+    for (index, kind, matches) in results {
+        if let (false, Ok(index)) = (matches, index.parse::<usize>()) {
+            mismatched.entry(index).or_default().insert(kind);
+        }
+    }
+
+    mismatched
+}
+
+/// Clear the hash of a file of a cache item, so the file is uploaded again.
+fn clear_hash(item: &mut CacheItem, kind: &str) {
+    match kind {
+        "image" => item.image_hash = String::new(),
+        "animation" => item.animation_hash = Some(String::new()),
+        "preview" => item.preview_hash = Some(String::new()),
+        "metadata" => (),
+        _ => {
+            item.image_hash = String::new();
+            item.animation_hash = item.animation_hash.as_ref().map(|_| String::new());
+            item.preview_hash = item.preview_hash.as_ref().map(|_| String::new());
+        }
+    }
+
+    // the metadata records the links of the other files
+    item.metadata_hash = String::new();
+}
+
+/// Download the metadata of an item and return the links of its files. When
+/// the image in 'properties.files' differs from the 'image' of the metadata,
+/// the latter is the preview of the image.
+async fn fetch_metadata_links(http_client: &HttpClient, uri: &str) -> Result<MetadataLinks> {
+    let response = http_client.get(uri).send().await?;
+
+    if !response.status().is_success() {
+        return Err(anyhow!("{} ({})", response.status(), uri));
+    }
+
+    let metadata: Value = response.json().await?;
+    let image = metadata["image"]
+        .as_str()
+        .ok_or_else(|| anyhow!("missing image in {}", uri))?
+        .to_string();
+    let animation = metadata["animation_url"].as_str().map(str::to_string);
+
+    let full_image = metadata["properties"]["files"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|file| {
+            file["type"]
+                .as_str()
+                .map_or(false, |file_type| file_type.starts_with("image/"))
+        })
+        .filter_map(|file| file["uri"].as_str())
+        .find(|file_uri| *file_uri != image && Some(*file_uri) != animation.as_deref())
+        .map(str::to_string);
+
+    Ok(match full_image {
+        Some(full_image) => MetadataLinks {
+            image: full_image,
+            animation,
+            preview: Some(image),
+        },
+        None => MetadataLinks {
+            image,
+            animation,
+            preview: None,
+        },
+    })
+}
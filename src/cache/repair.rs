@@ -0,0 +1,319 @@
+use console::style;
+use futures::{stream, StreamExt};
+use reqwest::StatusCode;
+use std::collections::BTreeMap;
+
+use crate::cache::load_cache;
+use crate::common::*;
+use crate::upload::{get_uploaded_asset_pairs, AssetPair};
+use crate::utils::*;
+
+pub struct RepairArgs {
+    pub assets_dir: String,
+    pub cache: String,
+    pub config: String,
+    pub skip_links: bool,
+    pub dry_run: bool,
+}
+
+/// Uploaded link of a file of a cache item.
+struct LinkProbe {
+    key: String,
+    kind: &'static str,
+    link: String,
+}
+
+/// Result of checking that an uploaded link is available.
+enum ProbeResult {
+    Available,
+    /// The storage reports that the file does not exist.
+    Missing,
+    /// The link could not be checked (e.g., a network error).
+    Unknown(String),
+}
+
+pub async fn process_repair(args: RepairArgs) -> Result<()> {
+    let steps = if args.skip_links { 2 } else { 3 };
+    let mut step = 1;
+
+    println!(
+        "{} {}Hashing local assets",
+        style(format!("[{}/{}]", step, steps)).bold().dim(),
+        LOOKING_GLASS_EMOJI
+    );
+
+    let pb = spinner_with_style();
+    pb.enable_steady_tick(120);
+    pb.set_message("Reading files...");
+
+    // the hashes of the uploaded files are the hashes of the optimized images
+    // and previews
+    let asset_pairs = get_uploaded_asset_pairs(&args.assets_dir, &args.config)?;
+    let mut cache = load_cache(&args.cache, false)?;
+
+    pb.finish_and_clear();
+
+    let mut indices: Vec<&usize> = asset_pairs.keys().collect();
+    indices.sort_unstable();
+
+    let mut changes: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+
+    for index in indices {
+        let pair = &asset_pairs[index];
+
+        match cache.items.0.get_mut(&index.to_string()) {
+            Some(item) => {
+                let item_changes = rehash_item(item, pair);
+                if !item_changes.is_empty() {
+                    changes.insert(*index, item_changes);
+                }
+            }
+            None => {
+                cache
+                    .items
+                    .0
+                    .insert(index.to_string(), pair.clone().into_cache_item());
+                changes.insert(*index, vec!["added missing item".to_string()]);
+            }
+        }
+    }
+
+    cache.items.0.sort_by(|a, _, b, _| {
+        let index = |key: &str| key.parse::<i64>().unwrap_or(i64::MAX);
+        index(a).cmp(&index(b))
+    });
+
+    let orphaned = cache
+        .items
+        .0
+        .keys()
+        .filter(|key| {
+            key.parse::<usize>()
+                .map_or(true, |index| !asset_pairs.contains_key(&index))
+        })
+        .count();
+
+    println!(
+        "Checked {} asset(s) against {} cache item(s).",
+        asset_pairs.len(),
+        cache.items.0.len()
+    );
+
+    let mut unknown = Vec::new();
+
+    if !args.skip_links {
+        step += 1;
+        println!(
+            "\n{} {}Probing uploaded links",
+            style(format!("[{}/{}]", step, steps)).bold().dim(),
+            COMPUTER_EMOJI
+        );
+
+        let probes = link_probes(&cache);
+        println!("Probing {} link(s): (Ctrl+C to abort)", probes.len());
+
+        let pb = progress_bar_with_style(probes.len() as u64);
+        let http_client = http_client();
+
+        let results: Vec<(LinkProbe, ProbeResult)> = stream::iter(probes)
+            .map(|probe| {
+                let http_client = &http_client;
+                let pb = &pb;
+                async move {
+                    let result = probe_link(http_client, &probe.link).await;
+                    pb.inc(1);
+                    (probe, result)
+                }
+            })
+            .buffer_unordered(PARALLEL_LIMIT)
+            .collect()
+            .await;
+
+        pb.finish();
+
+        for (probe, result) in results {
+            match result {
+                ProbeResult::Available => (),
+                ProbeResult::Missing => {
+                    if let Some(item) = cache.items.0.get_mut(&probe.key) {
+                        clear_link(item, probe.kind);
+                        item.last_error =
+                            Some(format!("{} link not found: {}", probe.kind, probe.link));
+                    }
+                    if let Ok(index) = probe.key.parse::<usize>() {
+                        changes
+                            .entry(index)
+                            .or_default()
+                            .push(format!("{} link not found", probe.kind));
+                    }
+                }
+                ProbeResult::Unknown(error) => unknown.push((probe, error)),
+            }
+        }
+    }
+
+    step += 1;
+    println!(
+        "\n{} {}Repairing cache",
+        style(format!("[{}/{}]", step, steps)).bold().dim(),
+        PAPER_EMOJI
+    );
+
+    for (index, item_changes) in &changes {
+        println!("- Item {}: {}", index, item_changes.join(", "));
+    }
+
+    if !unknown.is_empty() {
+        println!(
+            "\n{}",
+            style(format!(
+                "Could not check {} link(s), these are kept:",
+                unknown.len()
+            ))
+            .yellow()
+        );
+        for (probe, error) in &unknown {
+            println!("- Item {} {}: {}", probe.key, probe.kind, error);
+        }
+    }
+
+    if orphaned > 0 {
+        println!(
+            "\n{} cache item(s) have no local assets, run 'sugar cache prune' to remove them.",
+            orphaned
+        );
+    }
+
+    if changes.is_empty() {
+        println!("\nThe cache matches the local assets, nothing to repair.");
+        return Ok(());
+    }
+
+    if args.dry_run {
+        println!("\nDry run, the cache file was not modified.");
+        return Ok(());
+    }
+
+    cache.sync_file()?;
+
+    println!(
+        "\n{}Repaired {} cache item(s), run 'sugar upload' to upload the missing files.",
+        COMPLETE_EMOJI,
+        changes.len()
+    );
+
+    Ok(())
+}
+
+/// Update the hashes of a cache item from its assets, clearing the links of
+/// the files that changed. Returns the description of the changes.
+fn rehash_item(item: &mut CacheItem, pair: &AssetPair) -> Vec<String> {
+    let mut changes = Vec::new();
+
+    if item.image_hash != pair.image_hash {
+        item.image_hash = pair.image_hash.clone();
+        item.image_link = String::new();
+        changes.push("image changed".to_string());
+    }
+
+    for (kind, hash, link, file_hash, file) in [
+        (
+            "animation",
+            &mut item.animation_hash,
+            &mut item.animation_link,
+            &pair.animation_hash,
+            &pair.animation,
+        ),
+        (
+            "preview",
+            &mut item.preview_hash,
+            &mut item.preview_link,
+            &pair.preview_hash,
+            &pair.preview,
+        ),
+    ] {
+        if hash != file_hash || link.is_some() != file.is_some() {
+            changes.push(match (file, &link) {
+                (None, _) => format!("{} removed", kind),
+                (Some(_), None) => format!("missing {}", kind),
+                (Some(_), Some(_)) => format!("{} changed", kind),
+            });
+            *hash = file_hash.clone();
+            *link = file.as_ref().map(|_| String::new());
+        }
+    }
+
+    if item.metadata_hash != pair.metadata_hash {
+        item.metadata_hash = pair.metadata_hash.clone();
+        changes.push("metadata changed".to_string());
+    }
+
+    // the metadata records the links of the other files
+    if !changes.is_empty() {
+        item.metadata_link = String::new();
+        item.on_chain = false;
+    }
+
+    changes
+}
+
+/// Clear the link of a file of a cache item, so the file is uploaded again.
+fn clear_link(item: &mut CacheItem, kind: &str) {
+    match kind {
+        "image" => item.image_link = String::new(),
+        "animation" => item.animation_link = Some(String::new()),
+        "preview" => item.preview_link = Some(String::new()),
+        _ => (),
+    }
+
+    // the metadata records the links of the other files, and the config line
+    // the link of the metadata
+    item.metadata_link = String::new();
+    item.on_chain = false;
+}
+
+/// Return the uploaded links of the cache items.
+fn link_probes(cache: &Cache) -> Vec<LinkProbe> {
+    let mut probes = Vec::new();
+
+    for (key, item) in &cache.items.0 {
+        let links = [
+            ("image", Some(&item.image_link)),
+            ("animation", item.animation_link.as_ref()),
+            ("preview", item.preview_link.as_ref()),
+            ("metadata", Some(&item.metadata_link)),
+        ];
+
+        for (kind, link) in links {
+            if let Some(link) = link.filter(|link| !link.is_empty()) {
+                probes.push(LinkProbe {
+                    key: key.clone(),
+                    kind,
+                    link: link.clone(),
+                });
+            }
+        }
+    }
+
+    probes
+}
+
+async fn probe_link(http_client: &HttpClient, link: &str) -> ProbeResult {
+    let mut response = http_client.head(link).send().await;
+
+    // not every gateway answers HEAD requests
+    if let Ok(head) = &response {
+        if head.status() == StatusCode::METHOD_NOT_ALLOWED {
+            response = http_client.get(link).send().await;
+        }
+    }
+
+    match response {
+        Ok(response) if response.status().is_success() => ProbeResult::Available,
+        Ok(response) if matches!(response.status(), StatusCode::NOT_FOUND | StatusCode::GONE) => {
+            ProbeResult::Missing
+        }
+        Ok(response) => ProbeResult::Unknown(response.status().to_string()),
+        Err(err) => ProbeResult::Unknown(err.to_string()),
+    }
+}
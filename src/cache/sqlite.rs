@@ -0,0 +1,348 @@
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use std::{collections::HashSet, ffi::OsStr, io::Read, sync::Mutex};
+
+use crate::cache::{CacheItems, CacheProgram};
+use crate::common::*;
+
+/// First bytes of every SQLite database file.
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+
+/// Extensions of the cache files created with the SQLite backend.
+const SQLITE_EXTENSIONS: [&str; 3] = ["db", "sqlite", "sqlite3"];
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS meta (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS items (
+        key TEXT PRIMARY KEY,
+        position INTEGER NOT NULL,
+        item TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS items_position ON items (position);
+";
+
+/// Position and JSON content of an item, as written to the database.
+type WrittenItem = (usize, String);
+
+/// Connection to the SQLite database of a cache, opened on the first write
+/// and kept for the following ones. The items as last written are kept, so
+/// each write only updates the items that changed.
+#[derive(Debug, Default)]
+pub struct SqliteStore {
+    /// Database described by `written`.
+    path: Option<PathBuf>,
+    connection: Mutex<Option<Connection>>,
+    written: HashMap<String, WrittenItem>,
+}
+
+impl SqliteStore {
+    /// Write a cache to the database in a single transaction. When `keys` is
+    /// set, only those items are compared with the database, otherwise all
+    /// the items are. Only the items that changed are written (or deleted, if
+    /// they are no longer in the cache).
+    pub fn write(
+        &mut self,
+        path: &Path,
+        version: u8,
+        program: &CacheProgram,
+        items: &CacheItems,
+        keys: Option<&HashSet<String>>,
+    ) -> Result<()> {
+        let error = |err: rusqlite::Error| anyhow!("Could not write '{}': {}", path.display(), err);
+
+        if self.connection.get_mut().unwrap().is_none() || self.path.as_deref() != Some(path) {
+            self.open(path).map_err(error)?;
+        }
+
+        let mut changes: Vec<(String, Option<WrittenItem>)> = Vec::new();
+        let mut compare = |key: &str| -> Result<()> {
+            let item = match items.0.get_full(key) {
+                Some((position, _, item)) => Some((position, serde_json::to_string(item)?)),
+                None => None,
+            };
+
+            if item.as_ref() != self.written.get(key) {
+                changes.push((key.to_string(), item));
+            }
+
+            Ok(())
+        };
+
+        match keys {
+            Some(keys) => {
+                for key in keys {
+                    compare(key)?;
+                }
+            }
+            None => {
+                let removed = self
+                    .written
+                    .keys()
+                    .filter(|key| !items.0.contains_key(key.as_str()));
+
+                for key in items.0.keys().chain(removed) {
+                    compare(key)?;
+                }
+            }
+        }
+
+        let connection = self.connection.get_mut().unwrap().as_mut().unwrap();
+        let transaction = connection.transaction().map_err(error)?;
+
+        transaction
+            .execute(
+                "INSERT OR REPLACE INTO meta (key, value) VALUES ('version', ?1), ('program', ?2)",
+                params![version.to_string(), serde_json::to_string(program)?],
+            )
+            .map_err(error)?;
+
+        {
+            let mut insert = transaction
+                .prepare("INSERT OR REPLACE INTO items (key, position, item) VALUES (?1, ?2, ?3)")
+                .map_err(error)?;
+            let mut delete = transaction
+                .prepare("DELETE FROM items WHERE key = ?1")
+                .map_err(error)?;
+
+            for (key, item) in &changes {
+                match item {
+                    Some((position, item)) => {
+                        insert
+                            .execute(params![key, position, item])
+                            .map_err(error)?;
+                    }
+                    None => {
+                        delete.execute([key]).map_err(error)?;
+                    }
+                }
+            }
+        }
+
+        transaction.commit().map_err(error)?;
+
+        for (key, item) in changes {
+            match item {
+                Some(item) => self.written.insert(key, item),
+                None => self.written.remove(&key),
+            };
+        }
+
+        Ok(())
+    }
+
+    /// Open the connection to the database, creating its tables. The items of
+    /// the database are read unless it is the one the cache was read from.
+    fn open(&mut self, path: &Path) -> rusqlite::Result<()> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(SCHEMA)?;
+
+        if self.path.as_deref() != Some(path) {
+            let mut written = HashMap::new();
+            let mut statement = connection.prepare("SELECT key, position, item FROM items")?;
+            let rows = statement.query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    (row.get::<_, i64>(1)? as usize, row.get::<_, String>(2)?),
+                ))
+            })?;
+
+            for row in rows {
+                let (key, item) = row?;
+                written.insert(key, item);
+            }
+
+            self.written = written;
+            self.path = Some(path.to_path_buf());
+        }
+
+        *self.connection.get_mut().unwrap() = Some(connection);
+
+        Ok(())
+    }
+}
+
+/// Return whether a file is a SQLite database, independently of its name.
+pub fn is_sqlite_file(path: &Path) -> bool {
+    let mut header = [0u8; 16];
+
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .map_or(false, |_| &header == SQLITE_HEADER)
+}
+
+/// Return whether the name of a cache file selects the SQLite backend.
+pub fn has_sqlite_extension(path: &Path) -> bool {
+    path.extension()
+        .and_then(OsStr::to_str)
+        .map_or(false, |extension| {
+            SQLITE_EXTENSIONS.contains(&extension.to_lowercase().as_str())
+        })
+}
+
+/// Read a cache from a SQLite database. The items are in the order they were
+/// written.
+pub fn read_sqlite_cache(path: &Path) -> Result<Cache> {
+    let connection =
+        Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY).map_err(|err| {
+            CacheError::FailedToOpenCacheFile(path.display().to_string(), err.to_string())
+        })?;
+
+    let wrong_format = CacheError::CacheFileWrongFormat;
+
+    let read_meta = |key: &str| -> Result<Option<String>> {
+        connection
+            .query_row("SELECT value FROM meta WHERE key = ?1", [key], |row| {
+                row.get(0)
+            })
+            .optional()
+            .map_err(|err| wrong_format(err.to_string()).into())
+    };
+
+    let mut cache = Cache::new();
+    cache.backend = CacheBackend::Sqlite;
+
+    cache.version = match read_meta("version")? {
+        Some(version) => version
+            .parse()
+            .map_err(|_| wrong_format(format!("invalid version '{}'", version)))?,
+        None => return Err(wrong_format("missing version".to_string()).into()),
+    };
+
+    if let Some(program) = read_meta("program")? {
+        cache.program =
+            serde_json::from_str(&program).map_err(|err| wrong_format(err.to_string()))?;
+    }
+
+    let mut statement = connection
+        .prepare("SELECT key, position, item FROM items ORDER BY position")
+        .map_err(|err| wrong_format(err.to_string()))?;
+    let rows = statement
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })
+        .map_err(|err| wrong_format(err.to_string()))?;
+
+    let mut written = HashMap::new();
+
+    for row in rows {
+        let (key, position, content) = row.map_err(|err| wrong_format(err.to_string()))?;
+        let item: CacheItem = serde_json::from_str(&content)
+            .map_err(|err| wrong_format(format!("item {}: {}", key, err)))?;
+        cache.items.0.insert(key.clone(), item);
+        written.insert(key, (position as usize, content));
+    }
+
+    // the following writes go to the same database
+    cache.sqlite = SqliteStore {
+        path: Some(path.to_path_buf()),
+        connection: Mutex::new(None),
+        written,
+    };
+
+    Ok(cache)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_database(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("sugar-sqlite-{}-{}.db", name, std::process::id()));
+        std::fs::remove_file(&path).ok();
+        path
+    }
+
+    fn item(name: &str) -> CacheItem {
+        serde_json::from_value(json!({
+            "name": name,
+            "image_hash": format!("{}-image", name),
+            "image_link": "",
+            "metadata_hash": format!("{}-metadata", name),
+            "metadata_link": "",
+            "onChain": false,
+        }))
+        .unwrap()
+    }
+
+    fn new_cache(path: &Path, keys: &[&str]) -> Cache {
+        let mut cache = Cache::new();
+        cache.backend = CacheBackend::Sqlite;
+        cache.file_path = path_to_string(path).unwrap();
+        cache.program.candy_machine = "candy".to_string();
+
+        for key in keys {
+            cache.items.0.insert(key.to_string(), item(key));
+        }
+
+        cache
+    }
+
+    fn items(cache: &Cache) -> Vec<(String, Value)> {
+        cache
+            .items
+            .0
+            .iter()
+            .map(|(key, item)| (key.clone(), serde_json::to_value(item).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn save_load_roundtrip() {
+        let path = temp_database("roundtrip");
+        let mut cache = new_cache(&path, &["0", "1", "2"]);
+        cache.sync_file().unwrap();
+
+        let loaded = read_sqlite_cache(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(loaded.version, CACHE_VERSION);
+        assert_eq!(loaded.backend, CacheBackend::Sqlite);
+        assert_eq!(loaded.program.candy_machine, "candy");
+        assert_eq!(items(&loaded), items(&cache));
+    }
+
+    #[test]
+    fn sync_writes_changed_and_removed_items() {
+        let path = temp_database("changes");
+        let mut cache = new_cache(&path, &["0", "1", "2"]);
+        cache.sync_file().unwrap();
+
+        // changes that are not flagged as dirty
+        cache.items.0.get_mut("1").unwrap().image_link = "https://example.com/1.png".to_string();
+        cache.items.0.shift_remove("2");
+        cache.items.0.insert("3".to_string(), item("3"));
+        cache.sync_file().unwrap();
+
+        let loaded = read_sqlite_cache(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(items(&loaded), items(&cache));
+    }
+
+    #[test]
+    fn loaded_cache_writes_dirty_items() {
+        let path = temp_database("dirty");
+        new_cache(&path, &["0", "1"]).sync_file().unwrap();
+
+        let mut cache = read_sqlite_cache(&path).unwrap();
+        cache.file_path = path_to_string(&path).unwrap();
+        cache.get_item_mut("0").unwrap().metadata_link = "https://example.com/0.json".to_string();
+        cache.checkpoint().unwrap();
+
+        let loaded = read_sqlite_cache(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(
+            loaded.items.0["0"].metadata_link,
+            "https://example.com/0.json"
+        );
+        assert_eq!(items(&loaded), items(&cache));
+    }
+}
@@ -1,12 +1,20 @@
-use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::solana_sdk::{hash::hash, pubkey::Pubkey};
 use anchor_client::Client;
 use anchor_lang::AccountDeserialize;
 use anyhow::{anyhow, Result};
 
-use mpl_candy_machine::{CandyMachine, CandyMachineData, WhitelistMintMode, WhitelistMintSettings};
+use mpl_candy_machine::{
+    CandyMachine, CandyMachineData, ConfigLine, WhitelistMintMode, WhitelistMintSettings,
+};
 
+use crate::cache::Cache;
 use crate::config::data::SugarConfig;
 use crate::config::{price_as_lamports, ConfigData};
+use crate::constants::{
+    CONFIG_ARRAY_START, CONFIG_LINE_SIZE, CONFIG_NAME_OFFSET, CONFIG_URI_OFFSET, MAX_NAME_LENGTH,
+    MAX_URI_LENGTH, STRING_LEN_SIZE,
+};
+use crate::rpc_cache;
 use crate::setup::setup_client;
 
 use crate::utils::check_spl_token;
@@ -18,9 +26,14 @@ use spl_token::id as token_program_id;
 //use solana_program::declare_id;
 //declare_id!("<CANDY MACHINE ID>");
 
+/// Status of the config line of a cache item in the candy machine.
 #[derive(Debug)]
 pub struct ConfigStatus {
     pub index: u32,
+    /// Config line at the index of the item, or None if the index is beyond
+    /// the size of the candy machine.
+    pub config_line: Option<ConfigLine>,
+    /// Whether the config line matches the name and uri of the item.
     pub on_chain: bool,
 }
 
@@ -47,7 +60,7 @@ pub fn get_candy_machine_state(
     let client = setup_client(sugar_config)?;
     let program = client.program(ID);
 
-    let data = program.rpc().get_account_data(candy_machine_id)?;
+    let data = rpc_cache::get_account_data(&program, candy_machine_id)?;
     let candy_machine: CandyMachine = CandyMachine::try_deserialize(&mut data.as_slice())?;
 
     Ok(candy_machine)
@@ -61,6 +74,105 @@ pub fn get_candy_machine_data(
     Ok(candy_machine.data)
 }
 
+/// Decode the config line at the specified index from the candy machine
+/// account data. Unwritten lines are returned with an empty name and uri.
+pub fn get_config_line(data: &[u8], index: usize) -> Result<ConfigLine> {
+    let line_start = CONFIG_ARRAY_START + STRING_LEN_SIZE + CONFIG_LINE_SIZE * index;
+
+    if data.len() < line_start + CONFIG_LINE_SIZE {
+        return Err(anyhow!("Config line {} is out of bounds", index));
+    }
+
+    let name_start = line_start + CONFIG_NAME_OFFSET;
+    let name = String::from_utf8(data[name_start..name_start + MAX_NAME_LENGTH].to_vec())
+        .map_err(|_| anyhow!("Failed to decode name for item {}", index))?
+        .trim_matches(char::from(0))
+        .to_string();
+
+    let uri_start = line_start + CONFIG_URI_OFFSET;
+    let uri = String::from_utf8(data[uri_start..uri_start + MAX_URI_LENGTH].to_vec())
+        .map_err(|_| anyhow!("Failed to decode uri for item {}", index))?
+        .trim_matches(char::from(0))
+        .to_string();
+
+    Ok(ConfigLine { name, uri })
+}
+
+/// Compare the config lines of the candy machine account data against the
+/// cache items, returning the status of each item ordered by index. Items
+/// without a numeric index (e.g., the collection) are skipped.
+pub fn scan_config_lines(data: &[u8], cache: &Cache) -> Vec<ConfigStatus> {
+    let mut statuses = Vec::with_capacity(cache.items.0.len());
+
+    for (index, item) in &cache.items.0 {
+        let index = match index.parse::<u32>() {
+            Ok(index) => index,
+            Err(_) => continue,
+        };
+
+        let config_line = get_config_line(data, index as usize).ok();
+        let on_chain = config_line
+            .as_ref()
+            .map(|config_line| {
+                config_line.name == item.name && config_line.uri == item.metadata_link
+            })
+            .unwrap_or(false);
+
+        statuses.push(ConfigStatus {
+            index,
+            config_line,
+            on_chain,
+        });
+    }
+
+    statuses.sort_by_key(|status| status.index);
+    statuses
+}
+
+/// Check the mint bitmask of the candy machine account data to determine
+/// whether the item at the specified index has been minted.
+pub fn is_item_minted(data: &[u8], items_available: usize, index: usize) -> Result<bool> {
+    if index >= items_available {
+        return Err(anyhow!("Item {} is out of bounds", index));
+    }
+
+    // the mint bitmask follows the config lines and the loaded lines bitmask
+    let bit_mask_start = CONFIG_ARRAY_START
+        + STRING_LEN_SIZE
+        + items_available * CONFIG_LINE_SIZE
+        + 4
+        + items_available / 8
+        + 4;
+    let position = bit_mask_start + index / 8;
+    let mask = 1u8 << (7 - index % 8);
+
+    match data.get(position) {
+        Some(byte) => Ok(byte & mask > 0),
+        None => Err(anyhow!("Invalid candy machine account data")),
+    }
+}
+
+/// Return the indices of the items that have not been minted yet.
+pub fn get_unminted_indices(data: &[u8], items_available: usize) -> Result<Vec<usize>> {
+    let mut unminted = Vec::new();
+
+    for index in 0..items_available {
+        if !is_item_minted(data, items_available, index)? {
+            unminted.push(index);
+        }
+    }
+
+    Ok(unminted)
+}
+
+/// Return the (anchor) discriminator of an instruction, for the instructions
+/// built by hand (the ones missing from the program crates used by Sugar).
+pub fn sighash(name: &str) -> [u8; 8] {
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&hash(format!("global:{}", name).as_bytes()).to_bytes()[..8]);
+    discriminator
+}
+
 pub fn uuid_from_pubkey(pubkey: &Pubkey) -> String {
     pubkey.to_string()[0..6].to_string()
 }
@@ -1,6 +1,14 @@
 use clap::{Parser, Subcommand};
 
-use crate::constants::{DEFAULT_ASSETS, DEFAULT_CACHE, DEFAULT_CONFIG};
+use crate::cache::CacheBackend;
+use crate::constants::{
+    DEFAULT_AIRDROP_PARALLEL, DEFAULT_AIRDROP_STATE, DEFAULT_ALLOWLIST_PROOFS, DEFAULT_ASSETS,
+    DEFAULT_BLOCKLIST, DEFAULT_CACHE, DEFAULT_CONFIG, DEFAULT_DISTRIBUTION_STATE,
+    DEFAULT_FREEZE_DAYS, DEFAULT_MANIFEST, DEFAULT_MINT_PARALLEL, DEFAULT_MONITOR_INTERVAL,
+    DEFAULT_PREVIEW, DEFAULT_PREVIEW_PORT, DEFAULT_PROPOSAL, DEFAULT_RPC_LOCAL,
+    DEFAULT_SIGN_PROGRESS, DEFAULT_TEST_KEYPAIR, DEFAULT_TEST_LEDGER,
+};
+use crate::errors::FailureClass;
 
 #[derive(Parser)]
 #[clap(author, version, about)]
@@ -9,12 +17,69 @@ pub struct Cli {
     #[clap(short, long, global = true)]
     pub log_level: Option<String>,
 
+    /// Write JSONL progress events to a file path or (unix) file descriptor
+    #[clap(long, global = true)]
+    pub progress_events: Option<String>,
+
+    /// Write JSONL events (uploaded files, transactions, errors and the result) to stdout, without console styling (unix only)
+    #[clap(long, global = true, conflicts_with = "progress-events")]
+    pub json: bool,
+
+    /// Abort the command after a wall-clock duration (e.g., '90s', '30m', '1h30m')
+    #[clap(long, global = true)]
+    pub deadline: Option<String>,
+
+    /// Price API URL used to display approximate USD values of SOL amounts
+    #[clap(long, global = true)]
+    pub price_api: Option<String>,
+
+    /// Only allow commands that do not change on-chain or local state (show, verify, report, ...)
+    #[clap(long, global = true, env = "SUGAR_READ_ONLY")]
+    pub read_only: bool,
+
+    /// Confirm transactions through a websocket subscription instead of polling (not used with a proxy)
+    #[clap(long, global = true, env = "SUGAR_WEBSOCKET_CONFIRM")]
+    pub websocket_confirm: bool,
+
+    /// Disable progress bars and prompts: confirmations are answered by --yes or fail
+    #[clap(long, global = true, env = "SUGAR_NO_INTERACTION")]
+    pub no_interaction: bool,
+
+    /// Answer yes to confirmation prompts
+    #[clap(long, global = true)]
+    pub yes: bool,
+
     #[clap(subcommand)]
     pub command: Commands,
 }
 
 #[derive(Subcommand)]
 pub enum Commands {
+    /// Scaffold a new project directory with example assets and config
+    Init {
+        /// Path to the new project directory
+        directory: String,
+
+        /// Path to the keypair file used as the creator [default: solana config or "~/.config/solana/id.json"]
+        #[clap(short, long)]
+        keypair: Option<String>,
+
+        /// Cluster the project is launched on: devnet or mainnet
+        #[clap(long, default_value = "devnet")]
+        cluster: String,
+
+        /// Upload method: bundlr, aws, pinata, nft_storage, shadow_drive or arweave
+        #[clap(long, default_value = "bundlr")]
+        upload_method: String,
+
+        /// Guard to enable: whitelist, gatekeeper or end-settings (can be repeated)
+        #[clap(long = "guard")]
+        guards: Vec<String>,
+
+        /// Token mint of the whitelist guard
+        #[clap(long)]
+        whitelist_mint: Option<String>,
+    },
     /// Interactive process to create the config file
     CreateConfig {
         /// Path to the config file
@@ -33,7 +98,12 @@ pub enum Commands {
         #[clap(default_value = DEFAULT_ASSETS)]
         assets_dir: String,
     },
-    /// Create a candy machine deployment from assets
+    /// Manage the config file
+    Config {
+        #[clap(subcommand)]
+        action: ConfigAction,
+    },
+    /// Validate, upload and deploy the assets, set the collection, add the guards and verify, continuing from the last completed stage
     Launch {
         /// Path to the directory with the assets to upload
         #[clap(default_value = DEFAULT_ASSETS)]
@@ -58,7 +128,85 @@ pub enum Commands {
         /// Strict mode: validate against JSON metadata standard exactly
         #[clap(long)]
         strict: bool,
+
+        /// Maximum number of concurrent uploads [default: upload method limit]
+        #[clap(long)]
+        concurrency: Option<usize>,
+
+        /// Maximum number of uploads started per second
+        #[clap(long)]
+        rate_limit: Option<f64>,
+
+        /// Run all stages again, instead of continuing from the last completed stage
+        #[clap(long)]
+        restart: bool,
+    },
+    /// Validate, upload and add new assets to a candy machine, creating it with room for the config number of items if needed
+    Append {
+        /// Path to the directory with the new assets (numbered from 0)
+        #[clap(long)]
+        assets: String,
+
+        /// Path to the directory with the collection assets, the new assets are copied here
+        #[clap(long, default_value = DEFAULT_ASSETS)]
+        assets_dir: String,
+
+        /// Path to the config file
+        #[clap(short, long, default_value = DEFAULT_CONFIG)]
+        config: String,
+
+        /// Path to the keypair file, uses Sol config or defaults to "~/.config/solana/id.json"
+        #[clap(short, long)]
+        keypair: Option<String>,
+
+        /// RPC Url
+        #[clap(short, long)]
+        rpc_url: Option<String>,
+
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, default_value = DEFAULT_CACHE)]
+        cache: String,
+
+        /// Strict mode: validate against JSON metadata standard exactly
+        #[clap(long)]
+        strict: bool,
+    },
+
+    /// Re-upload a single item and rewrite its config line on-chain
+    Replace {
+        /// Index of the item to replace
+        #[clap(long)]
+        asset: usize,
+
+        /// Path to the new image file
+        #[clap(long)]
+        image: Option<String>,
+
+        /// Path to the new metadata file
+        #[clap(long)]
+        metadata: Option<String>,
+
+        /// Path to the directory with the collection assets
+        #[clap(long, default_value = DEFAULT_ASSETS)]
+        assets_dir: String,
+
+        /// Path to the config file
+        #[clap(short, long, default_value = DEFAULT_CONFIG)]
+        config: String,
+
+        /// Path to the keypair file, uses Sol config or defaults to "~/.config/solana/id.json"
+        #[clap(short, long)]
+        keypair: Option<String>,
+
+        /// RPC Url
+        #[clap(short, long)]
+        rpc_url: Option<String>,
+
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, default_value = DEFAULT_CACHE)]
+        cache: String,
     },
+
     /// Mint one NFT from candy machine
     Mint {
         /// Path to the keypair file, uses Sol config or defaults to "~/.config/solana/id.json"
@@ -73,6 +221,10 @@ pub enum Commands {
         #[clap(long, default_value = DEFAULT_CACHE)]
         cache: String,
 
+        /// Path to the config file, defaults to "config.json" (used for uses settings)
+        #[clap(short, long, default_value = DEFAULT_CONFIG)]
+        config: String,
+
         /// Amount of NFTs to be minted in bulk
         #[clap(short, long)]
         number: Option<u64>,
@@ -80,6 +232,14 @@ pub enum Commands {
         /// Address of candy machine to mint from.
         #[clap(long)]
         candy_machine: Option<String>,
+
+        /// Maximum number of mint transactions sent in parallel
+        #[clap(long, default_value = DEFAULT_MINT_PARALLEL)]
+        parallel: usize,
+
+        /// Wallet to receive the NFTs, defaults to the keypair wallet (which pays for the mint)
+        #[clap(long)]
+        receiver: Option<String>,
     },
 
     /// Update the candy machine config on-chain
@@ -107,6 +267,15 @@ pub enum Commands {
         /// Address of candy machine to update.
         #[clap(long)]
         candy_machine: Option<String>,
+
+        /// Address of the multisig authority (e.g., a Squads vault): writes the transaction
+        /// message to a proposal file instead of signing it
+        #[clap(long)]
+        multisig: Option<String>,
+
+        /// Path to the multisig proposal file
+        #[clap(long, default_value = DEFAULT_PROPOSAL)]
+        proposal: String,
     },
 
     /// Deploy cache items into candy machine config on-chain
@@ -126,6 +295,10 @@ pub enum Commands {
         /// Path to the cache file, defaults to "cache.json"
         #[clap(long, default_value = DEFAULT_CACHE)]
         cache: String,
+
+        /// Overwrite all config lines from the cache (only allowed before any item is minted)
+        #[clap(long)]
+        force_rewrite: bool,
     },
 
     /// Upload assets to storage and creates the cache config
@@ -149,6 +322,62 @@ pub enum Commands {
         /// Path to the cache file
         #[clap(long, default_value = DEFAULT_CACHE)]
         cache: String,
+
+        /// Maximum number of concurrent uploads [default: upload method limit]
+        #[clap(long)]
+        concurrency: Option<usize>,
+
+        /// Maximum number of uploads started per second
+        #[clap(long)]
+        rate_limit: Option<f64>,
+
+        /// List the files that would be uploaded and check the funding, without uploading
+        #[clap(long)]
+        dry_run: bool,
+
+        /// Url of the Bundlr node [default: config file or cluster node]
+        #[clap(long)]
+        bundlr_node: Option<String>,
+
+        /// Keep watching the assets directory, uploading new and modified files (and writing
+        /// their config lines once the candy machine is deployed)
+        #[clap(long, conflicts_with = "dry-run")]
+        watch: bool,
+    },
+
+    /// Monitor the candy machine and pause the sale when anomalies are detected
+    Monitor {
+        /// Path to the keypair file, uses Sol config or defaults to "~/.config/solana/id.json"
+        #[clap(short, long)]
+        keypair: Option<String>,
+
+        /// RPC Url
+        #[clap(short, long)]
+        rpc_url: Option<String>,
+
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, default_value = DEFAULT_CACHE)]
+        cache: String,
+
+        /// Path to the config file with the expected prices, defaults to "config.json"
+        #[clap(short, long, default_value = DEFAULT_CONFIG)]
+        config: String,
+
+        /// Address of candy machine to monitor
+        #[clap(long)]
+        candy_machine: Option<String>,
+
+        /// Interval (in seconds) between checks
+        #[clap(long, default_value = DEFAULT_MONITOR_INTERVAL)]
+        interval: u64,
+
+        /// URL to POST a JSON alert to when anomalies are detected
+        #[clap(long)]
+        webhook: Option<String>,
+
+        /// Only alert on anomalies, without pausing the sale
+        #[clap(long)]
+        no_pause: bool,
     },
 
     /// Withdraw funds from candy machine account closing it
@@ -168,6 +397,19 @@ pub enum Commands {
         /// List available candy machines, no withdraw performed
         #[clap(long)]
         list: bool,
+
+        /// Drain all candy machines of the keypair, confirming each one
+        #[clap(long, conflicts_with = "candy-machine")]
+        all: bool,
+
+        /// Address of the multisig authority (e.g., a Squads vault): writes the transaction
+        /// message to a proposal file instead of signing it
+        #[clap(long, conflicts_with = "all")]
+        multisig: Option<String>,
+
+        /// Path to the multisig proposal file
+        #[clap(long, default_value = DEFAULT_PROPOSAL)]
+        proposal: String,
     },
 
     /// Validate JSON metadata files
@@ -176,9 +418,29 @@ pub enum Commands {
         #[clap(default_value = DEFAULT_ASSETS)]
         assets_dir: String,
 
-        /// Strict mode: validate against JSON metadata standard exactly
+        /// Strict mode: also require the optional fields, a known category and no unknown fields, with URLs shorter than 200 chars
         #[clap(long)]
         strict: bool,
+
+        /// Validation rule to skip, e.g. 'unknown-fields' (can be repeated)
+        #[clap(long)]
+        skip: Vec<String>,
+
+        /// Size (in MB) above which asset files are reported, defaults to 10 (0 to disable)
+        #[clap(long)]
+        max_file_size: Option<u64>,
+
+        /// Path to the config file, checks that the accounts it references exist on the cluster
+        #[clap(short, long)]
+        config: Option<String>,
+
+        /// Path to the keypair file, uses Sol config or defaults to "~/.config/solana/id.json"
+        #[clap(short, long)]
+        keypair: Option<String>,
+
+        /// RPC Url
+        #[clap(short, long)]
+        rpc_url: Option<String>,
     },
 
     /// Verify uploaded data
@@ -194,6 +456,21 @@ pub enum Commands {
         /// Path to the cache file, defaults to "cache.json"
         #[clap(long, default_value = DEFAULT_CACHE)]
         cache: String,
+        /// Path to the directory with the assets, used with --content
+        #[clap(long, default_value = DEFAULT_ASSETS)]
+        assets_dir: String,
+
+        /// Download the uploaded files and compare them against the local assets
+        #[clap(long)]
+        content: bool,
+
+        /// Redeploy the items whose config lines do not match the cache
+        #[clap(long)]
+        redeploy: bool,
+
+        /// Path to the config file, used with --redeploy
+        #[clap(short, long, default_value = DEFAULT_CONFIG)]
+        config: String,
     },
 
     /// Show the on-chain config of an existing candy machine
@@ -212,10 +489,14 @@ pub enum Commands {
 
         /// Address of candy machine
         candy_machine: Option<String>,
+
+        /// List the indices of the items that have not been minted yet
+        #[clap(long)]
+        unminted: bool,
     },
 
-    /// Interact with the bundlr network
-    Bundlr {
+    /// Sign the metadata of minted NFTs as a creator
+    Sign {
         /// Path to the keypair file, uses Sol config or defaults to "~/.config/solana/id.json"
         #[clap(short, long)]
         keypair: Option<String>,
@@ -224,15 +505,1006 @@ pub enum Commands {
         #[clap(short, long)]
         rpc_url: Option<String>,
 
-        #[clap(subcommand)]
-        action: BundlrAction,
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, default_value = DEFAULT_CACHE)]
+        cache: String,
+
+        /// Address of the candy machine whose minted NFTs are signed
+        #[clap(long)]
+        candy_machine: Option<String>,
+
+        /// Path to the mint list file (JSON array or one address per line) [default: NFTs minted by the candy machine]
+        #[clap(long)]
+        mint_list: Option<String>,
+
+        /// Sign as one of multiple creators and update the shared progress file
+        #[clap(long)]
+        creator: bool,
+
+        /// Path to the shared creators progress file
+        #[clap(long, default_value = DEFAULT_SIGN_PROGRESS)]
+        progress: String,
     },
-}
 
-#[derive(clap::Subcommand)]
-pub enum BundlrAction {
-    /// Retrieve the balance on bundlr
-    Balance,
-    /// Withdraw funds from bundlr
-    Withdraw,
+    /// Transfer minted NFTs held by the authority wallet according to an allocation file
+    Distribute {
+        /// Path to the allocations file ('address,count' or 'address,mint' per line)
+        #[clap(long)]
+        allocations: String,
+
+        /// Path to the keypair file, uses Sol config or defaults to "~/.config/solana/id.json"
+        #[clap(short, long)]
+        keypair: Option<String>,
+
+        /// RPC Url
+        #[clap(short, long)]
+        rpc_url: Option<String>,
+
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, default_value = DEFAULT_CACHE)]
+        cache: String,
+
+        /// Path to the file recording the completed transfers
+        #[clap(long, default_value = DEFAULT_DISTRIBUTION_STATE)]
+        state: String,
+
+        /// Address of the candy machine whose NFTs are distributed
+        #[clap(long)]
+        candy_machine: Option<String>,
+    },
+
+    /// Mint NFTs from the candy machine directly to a list of recipients
+    Airdrop {
+        /// Path to the recipients file (JSON object of 'address: count' pairs or 'address,count' CSV)
+        #[clap(long)]
+        recipients: String,
+
+        /// Path to the keypair file, uses Sol config or defaults to "~/.config/solana/id.json"
+        #[clap(short, long)]
+        keypair: Option<String>,
+
+        /// RPC Url
+        #[clap(short, long)]
+        rpc_url: Option<String>,
+
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, default_value = DEFAULT_CACHE)]
+        cache: String,
+
+        /// Path to the config file, defaults to "config.json" (used for uses and priority fee settings)
+        #[clap(short, long, default_value = DEFAULT_CONFIG)]
+        config: String,
+
+        /// Path to the file recording the airdrop progress
+        #[clap(long, default_value = DEFAULT_AIRDROP_STATE)]
+        state: String,
+
+        /// Address of the candy machine to mint from
+        #[clap(long)]
+        candy_machine: Option<String>,
+
+        /// Number of NFTs airdropped in parallel
+        #[clap(long, default_value = DEFAULT_AIRDROP_PARALLEL)]
+        parallel: usize,
+
+        /// Priority fee in micro-lamports per compute unit (overrides the config)
+        #[clap(long)]
+        priority_fee: Option<u64>,
+    },
+
+    /// Reveal the NFTs minted with hidden settings, updating their metadata from the cache
+    Reveal {
+        /// Path to the keypair file, uses Sol config or defaults to "~/.config/solana/id.json"
+        #[clap(short, long)]
+        keypair: Option<String>,
+
+        /// RPC Url
+        #[clap(short, long)]
+        rpc_url: Option<String>,
+
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, default_value = DEFAULT_CACHE)]
+        cache: String,
+
+        /// Path to the config file, defaults to "config.json"
+        #[clap(short, long, default_value = DEFAULT_CONFIG)]
+        config: String,
+
+        /// Address of the candy machine whose NFTs are revealed
+        #[clap(long)]
+        candy_machine: Option<String>,
+    },
+
+    /// Estimate the cost of uploading the assets with the configured upload method
+    Estimate {
+        /// Path to the directory with the assets
+        #[clap(default_value = DEFAULT_ASSETS)]
+        assets_dir: String,
+
+        /// Path to the config file, defaults to "config.json"
+        #[clap(short, long, default_value = DEFAULT_CONFIG)]
+        config: String,
+
+        /// Path to the keypair file, uses Sol config or defaults to "~/.config/solana/id.json"
+        #[clap(short, long)]
+        keypair: Option<String>,
+
+        /// RPC Url
+        #[clap(short, long)]
+        rpc_url: Option<String>,
+    },
+
+    /// Sample network activity and recommend a compute unit price (priority fee)
+    Fees {
+        /// Path to the keypair file, uses Sol config or defaults to "~/.config/solana/id.json"
+        #[clap(short, long)]
+        keypair: Option<String>,
+
+        /// RPC Url
+        #[clap(short, long)]
+        rpc_url: Option<String>,
+
+        /// Path to the config file, defaults to "config.json"
+        #[clap(short, long, default_value = DEFAULT_CONFIG)]
+        config: String,
+
+        /// Write the recommended priority fee into the config file
+        #[clap(long)]
+        write: bool,
+    },
+
+    /// Generate a local HTML gallery of the collection
+    Preview {
+        /// Path to the directory with the assets
+        #[clap(default_value = DEFAULT_ASSETS)]
+        assets_dir: String,
+
+        /// Path to the config file, defaults to "config.json"
+        #[clap(short, long, default_value = DEFAULT_CONFIG)]
+        config: String,
+
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, default_value = DEFAULT_CACHE)]
+        cache: String,
+
+        /// Use the uploaded image links from the cache instead of the local files
+        #[clap(long)]
+        use_cache: bool,
+
+        /// Path to the generated HTML file
+        #[clap(short, long, default_value = DEFAULT_PREVIEW)]
+        output: String,
+
+        /// Serve the gallery on a local port
+        #[clap(long)]
+        serve: bool,
+
+        /// Port of the local server
+        #[clap(long, default_value = DEFAULT_PREVIEW_PORT)]
+        port: u16,
+    },
+
+    /// Manage guard settings
+    Guard {
+        #[clap(subcommand)]
+        command: GuardCommand,
+    },
+
+    /// Manage a local validator test environment
+    TestEnv {
+        #[clap(subcommand)]
+        action: TestEnvAction,
+    },
+
+    /// Enable or disable freezing the minted NFTs until the mint is over
+    Freeze {
+        #[clap(subcommand)]
+        action: FreezeAction,
+    },
+
+    /// Thaw NFTs frozen by the candy machine
+    Thaw {
+        /// Mint address of the NFT to thaw
+        mint: Option<String>,
+
+        /// Thaw all the NFTs frozen by the candy machine
+        #[clap(long, conflicts_with = "mint")]
+        all: bool,
+
+        /// Path to the keypair file, uses Sol config or defaults to "~/.config/solana/id.json"
+        #[clap(short, long)]
+        keypair: Option<String>,
+
+        /// RPC Url
+        #[clap(short, long)]
+        rpc_url: Option<String>,
+
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, default_value = DEFAULT_CACHE)]
+        cache: String,
+
+        /// Address of the candy machine
+        #[clap(long)]
+        candy_machine: Option<String>,
+    },
+
+    /// Transfer the mint funds held by the freeze feature to the candy machine wallet
+    UnlockFunds {
+        /// Path to the keypair file, uses Sol config or defaults to "~/.config/solana/id.json"
+        #[clap(short, long)]
+        keypair: Option<String>,
+
+        /// RPC Url
+        #[clap(short, long)]
+        rpc_url: Option<String>,
+
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, default_value = DEFAULT_CACHE)]
+        cache: String,
+
+        /// Address of the candy machine
+        #[clap(long)]
+        candy_machine: Option<String>,
+    },
+
+    /// Export snapshots of the candy machine NFTs
+    Snapshot {
+        #[clap(subcommand)]
+        action: SnapshotAction,
+    },
+
+    /// Create, set and verify the collection of the candy machine
+    Collection {
+        #[clap(subcommand)]
+        action: CollectionAction,
+    },
+
+    /// Sign or verify an operation manifest for third-party review
+    Manifest {
+        #[clap(subcommand)]
+        action: ManifestAction,
+    },
+
+    /// Generate the metadata files of the assets from a template and a CSV file
+    GenerateMetadata {
+        /// Path to the metadata template, with {{column}}, {{index}} and {{number}} placeholders
+        #[clap(short, long)]
+        template: String,
+
+        /// CSV file with a row per asset: columns used as placeholders fill the template, the others are added as attributes
+        #[clap(long)]
+        csv: Option<String>,
+
+        /// Number of metadata files to generate, defaults to the number of CSV rows
+        #[clap(short, long)]
+        number: Option<usize>,
+
+        /// Assets directory, defaults to "assets"
+        #[clap(long, default_value = DEFAULT_ASSETS)]
+        assets_dir: String,
+
+        /// Overwrite existing metadata files
+        #[clap(long)]
+        force: bool,
+    },
+
+    /// Detect missing or duplicated asset indices and renumber the assets
+    FixAssets {
+        /// Assets directory, defaults to "assets"
+        #[clap(default_value = DEFAULT_ASSETS)]
+        assets_dir: String,
+
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, default_value = DEFAULT_CACHE)]
+        cache: String,
+
+        /// Renumber the assets contiguously from 0, updating their metadata and the cache
+        #[clap(long)]
+        renumber: bool,
+
+        /// Show the renumbering without modifying any file
+        #[clap(long)]
+        dry_run: bool,
+    },
+
+    /// Manage the cache file
+    Cache {
+        #[clap(subcommand)]
+        action: CacheAction,
+    },
+
+    /// Report on the launch of a project
+    Report {
+        #[clap(subcommand)]
+        action: ReportAction,
+    },
+
+    /// Interact with the bundlr network
+    Bundlr {
+        /// Path to the keypair file, uses Sol config or defaults to "~/.config/solana/id.json"
+        #[clap(short, long)]
+        keypair: Option<String>,
+
+        /// RPC Url
+        #[clap(short, long)]
+        rpc_url: Option<String>,
+
+        /// Path to the config file, used for its Bundlr node
+        #[clap(short, long, default_value = DEFAULT_CONFIG)]
+        config: String,
+
+        /// Url of the Bundlr node [default: config file or cluster node]
+        #[clap(long)]
+        bundlr_node: Option<String>,
+
+        #[clap(subcommand)]
+        action: BundlrAction,
+    },
+
+    /// Check the RPC endpoints of the project
+    Rpc {
+        #[clap(subcommand)]
+        action: RpcAction,
+    },
+
+    /// Manage encrypted keypair files
+    Keypair {
+        #[clap(subcommand)]
+        action: KeypairAction,
+    },
+}
+
+impl Commands {
+    /// Class of the failures of the command without a typed error, which
+    /// sets the exit code of the command.
+    pub fn failure_class(&self) -> FailureClass {
+        match self {
+            Commands::Validate { .. } => FailureClass::Validation,
+            Commands::Upload { .. } => FailureClass::Upload,
+            Commands::Deploy { .. } | Commands::Verify { .. } => FailureClass::Deploy,
+            _ => FailureClass::General,
+        }
+    }
+
+    /// Whether the command can run in read-only mode, i.e., it does not send
+    /// transactions, move funds or write project files.
+    pub fn is_read_only(&self) -> bool {
+        match self {
+            Commands::Show { .. }
+            | Commands::Validate { .. }
+            | Commands::Preview { .. }
+            | Commands::Report { .. }
+            | Commands::Estimate { .. } => true,
+            Commands::Verify { redeploy, .. } => !*redeploy,
+            Commands::Monitor { no_pause, .. } => *no_pause,
+            Commands::Withdraw {
+                list,
+                candy_machine,
+                all,
+                ..
+            } => *list || (candy_machine.is_none() && !*all),
+            Commands::Upload { dry_run, .. } => *dry_run,
+            Commands::FixAssets {
+                renumber, dry_run, ..
+            } => !*renumber || *dry_run,
+            Commands::Fees { write, .. } => !*write,
+            Commands::Cache {
+                action: CacheAction::Inspect { .. } | CacheAction::Export { .. },
+            } => true,
+            Commands::Cache {
+                action: CacheAction::Repair { dry_run, .. } | CacheAction::Prune { dry_run, .. },
+            } => *dry_run,
+            Commands::Snapshot { .. } | Commands::Rpc { .. } => true,
+            Commands::Bundlr {
+                action: BundlrAction::Balance | BundlrAction::Status { .. },
+                ..
+            } => true,
+            Commands::Manifest {
+                action: ManifestAction::Verify { .. },
+            } => true,
+            Commands::Guard {
+                command:
+                    GuardCommand::Blocklist {
+                        action: BlockListAction::Check { .. },
+                    },
+            } => true,
+            Commands::Guard {
+                command: GuardCommand::Show { .. } | GuardCommand::Proof { .. },
+            } => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(clap::Subcommand)]
+pub enum BundlrAction {
+    /// Retrieve the balance and upload price on bundlr
+    Balance,
+    /// Withdraw funds from bundlr
+    Withdraw {
+        /// Amount to withdraw in lamports [default: the whole balance]
+        #[clap(long)]
+        amount: Option<u64>,
+    },
+    /// Check that the uploaded data items have settled on Arweave
+    Status {
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, default_value = DEFAULT_CACHE)]
+        cache: String,
+    },
+}
+
+#[derive(clap::Subcommand)]
+pub enum ManifestAction {
+    /// Sign a manifest of the config file and cache items
+    Sign {
+        /// Path to the keypair file, uses Sol config or defaults to "~/.config/solana/id.json"
+        #[clap(short, long)]
+        keypair: Option<String>,
+
+        /// RPC Url
+        #[clap(short, long)]
+        rpc_url: Option<String>,
+
+        /// Path to the config file, defaults to "config.json"
+        #[clap(short, long, default_value = DEFAULT_CONFIG)]
+        config: String,
+
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, default_value = DEFAULT_CACHE)]
+        cache: String,
+
+        /// Path to the output file
+        #[clap(short, long, default_value = DEFAULT_MANIFEST)]
+        output: String,
+    },
+    /// Verify the signature of a manifest and compare it against local files
+    Verify {
+        /// Path to the signed manifest
+        #[clap(default_value = DEFAULT_MANIFEST)]
+        manifest: String,
+
+        /// Path to a config file to compare against the manifest
+        #[clap(short, long)]
+        config: Option<String>,
+
+        /// Path to a cache file to compare against the manifest
+        #[clap(long)]
+        cache: Option<String>,
+    },
+}
+
+#[derive(clap::Subcommand)]
+pub enum CacheAction {
+    /// Show the number of uploaded, deployed and pending items of the cache
+    Inspect {
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, default_value = DEFAULT_CACHE)]
+        cache: String,
+    },
+
+    /// Rebuild the cache items from the local assets and the uploaded links
+    Repair {
+        /// Path to the directory with the assets
+        #[clap(default_value = DEFAULT_ASSETS)]
+        assets_dir: String,
+
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, default_value = DEFAULT_CACHE)]
+        cache: String,
+
+        /// Path to the config file, used for its image optimization and previews
+        #[clap(short, long, default_value = DEFAULT_CONFIG)]
+        config: String,
+
+        /// Do not check that the uploaded links are still available
+        #[clap(long)]
+        skip_links: bool,
+
+        /// Report the changes without modifying the cache
+        #[clap(long)]
+        dry_run: bool,
+    },
+
+    /// Remove cache items whose assets no longer exist
+    Prune {
+        /// Path to the directory with the assets
+        #[clap(default_value = DEFAULT_ASSETS)]
+        assets_dir: String,
+
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, default_value = DEFAULT_CACHE)]
+        cache: String,
+
+        /// Report the items that would be removed without modifying the cache
+        #[clap(long)]
+        dry_run: bool,
+    },
+
+    /// Rebuild the cache from the config lines of a candy machine
+    Recover {
+        /// Address of the candy machine
+        candy_machine: String,
+
+        /// Path to the keypair file, uses Sol config or defaults to "~/.config/solana/id.json"
+        #[clap(short, long)]
+        keypair: Option<String>,
+
+        /// RPC Url
+        #[clap(short, long)]
+        rpc_url: Option<String>,
+
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, default_value = DEFAULT_CACHE)]
+        cache: String,
+
+        /// Path to the directory with the uploaded assets, used for the hashes of the files that match the uploaded content
+        #[clap(long)]
+        assets_dir: Option<String>,
+
+        /// Path to the config file, used with --assets-dir
+        #[clap(short, long, default_value = DEFAULT_CONFIG)]
+        config: String,
+
+        /// Overwrite the cache file if it exists
+        #[clap(long)]
+        force: bool,
+    },
+
+    /// Convert the cache file between the JSON and SQLite formats
+    Convert {
+        /// Format of the converted cache file ("json" or "sqlite")
+        format: CacheBackend,
+
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, default_value = DEFAULT_CACHE)]
+        cache: String,
+
+        /// Path of the converted file [default: the cache file name with a .json or .db extension]
+        #[clap(short, long)]
+        output: Option<String>,
+
+        /// Overwrite the converted file if it exists
+        #[clap(long)]
+        force: bool,
+    },
+
+    /// Export the links of the cache items for external tools
+    Export {
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, default_value = DEFAULT_CACHE)]
+        cache: String,
+
+        /// Export as CSV instead of JSON
+        #[clap(long)]
+        csv: bool,
+
+        /// Path of the exported file [default: cache-export.json or cache-export.csv]
+        #[clap(short, long)]
+        output: Option<String>,
+    },
+}
+
+#[derive(clap::Subcommand)]
+pub enum ReportAction {
+    /// Show the fees, rent and funding spent by all commands of a project
+    Spend {
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, default_value = DEFAULT_CACHE)]
+        cache: String,
+    },
+}
+
+#[derive(clap::Subcommand)]
+pub enum TestEnvAction {
+    /// Start (or connect to) a local validator and fund a test keypair, saving a Solana CLI config for them in the ledger directory (the global config is not changed)
+    Up {
+        /// RPC Url of the local validator
+        #[clap(short, long, default_value = DEFAULT_RPC_LOCAL)]
+        rpc_url: String,
+
+        /// Path to the test keypair file, created if it does not exist
+        #[clap(short, long, default_value = DEFAULT_TEST_KEYPAIR)]
+        keypair: String,
+
+        /// Path to the validator ledger directory
+        #[clap(long, default_value = DEFAULT_TEST_LEDGER)]
+        ledger: String,
+
+        /// Directory with the program files (mpl_candy_machine.so and mpl_token_metadata.so), otherwise cloned from mainnet-beta
+        #[clap(long)]
+        programs_dir: Option<String>,
+
+        /// Amount of SOL to airdrop to the test keypair
+        #[clap(long, default_value = "100")]
+        airdrop: f64,
+    },
+    /// Stop the local validator
+    Down {
+        /// Path to the validator ledger directory
+        #[clap(long, default_value = DEFAULT_TEST_LEDGER)]
+        ledger: String,
+    },
+}
+
+#[derive(clap::Subcommand)]
+pub enum CollectionAction {
+    /// Upload the collection files and create the collection NFT
+    Create {
+        /// Path to the collection metadata file
+        #[clap(long, default_value = "assets/collection.json")]
+        metadata: String,
+
+        /// Path to the collection image [default: image next to the metadata file]
+        #[clap(long)]
+        image: Option<String>,
+
+        /// Path to the keypair file, uses Sol config or defaults to "~/.config/solana/id.json"
+        #[clap(short, long)]
+        keypair: Option<String>,
+
+        /// RPC Url
+        #[clap(short, long)]
+        rpc_url: Option<String>,
+
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, default_value = DEFAULT_CACHE)]
+        cache: String,
+
+        /// Path to the config file, defaults to "config.json" (used for the upload settings)
+        #[clap(short, long, default_value = DEFAULT_CONFIG)]
+        config: String,
+    },
+    /// Set an existing collection NFT as the collection of the candy machine
+    Set {
+        /// Mint address of the collection NFT
+        collection_mint: String,
+
+        /// Path to the keypair file, uses Sol config or defaults to "~/.config/solana/id.json"
+        #[clap(short, long)]
+        keypair: Option<String>,
+
+        /// RPC Url
+        #[clap(short, long)]
+        rpc_url: Option<String>,
+
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, default_value = DEFAULT_CACHE)]
+        cache: String,
+    },
+    /// Remove the collection of the candy machine
+    Remove {
+        /// Path to the keypair file, uses Sol config or defaults to "~/.config/solana/id.json"
+        #[clap(short, long)]
+        keypair: Option<String>,
+
+        /// RPC Url
+        #[clap(short, long)]
+        rpc_url: Option<String>,
+
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, default_value = DEFAULT_CACHE)]
+        cache: String,
+    },
+    /// Add the NFTs minted before the collection was set to the collection and verify their membership
+    Verify {
+        /// Path to the keypair file, uses Sol config or defaults to "~/.config/solana/id.json"
+        #[clap(short, long)]
+        keypair: Option<String>,
+
+        /// RPC Url
+        #[clap(short, long)]
+        rpc_url: Option<String>,
+
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, default_value = DEFAULT_CACHE)]
+        cache: String,
+
+        /// Address of the candy machine whose NFTs are verified
+        #[clap(long)]
+        candy_machine: Option<String>,
+    },
+}
+
+#[derive(clap::Subcommand)]
+pub enum FreezeAction {
+    /// Freeze the NFTs minted from now on, holding the mint funds until they are thawed
+    Enable {
+        /// Path to the keypair file, uses Sol config or defaults to "~/.config/solana/id.json"
+        #[clap(short, long)]
+        keypair: Option<String>,
+
+        /// RPC Url
+        #[clap(short, long)]
+        rpc_url: Option<String>,
+
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, default_value = DEFAULT_CACHE)]
+        cache: String,
+
+        /// Address of the candy machine
+        #[clap(long)]
+        candy_machine: Option<String>,
+
+        /// Number of days the NFTs remain frozen (up to 31)
+        #[clap(long, default_value = DEFAULT_FREEZE_DAYS)]
+        freeze_days: u8,
+    },
+    /// Stop freezing minted NFTs and allow the frozen ones to be thawed
+    Disable {
+        /// Path to the keypair file, uses Sol config or defaults to "~/.config/solana/id.json"
+        #[clap(short, long)]
+        keypair: Option<String>,
+
+        /// RPC Url
+        #[clap(short, long)]
+        rpc_url: Option<String>,
+
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, default_value = DEFAULT_CACHE)]
+        cache: String,
+
+        /// Address of the candy machine
+        #[clap(long)]
+        candy_machine: Option<String>,
+    },
+}
+
+#[derive(clap::Subcommand)]
+pub enum GuardCommand {
+    /// Manage the allow list of wallets
+    Allowlist {
+        #[clap(subcommand)]
+        action: AllowListAction,
+    },
+    /// Manage the block list of (bot) wallets
+    Blocklist {
+        #[clap(subcommand)]
+        action: BlockListAction,
+    },
+    /// Print the allow list merkle proof of a wallet
+    Proof {
+        /// Wallet address
+        wallet: String,
+
+        /// Path to the allow list file, builds the proof from it instead of the proofs file
+        #[clap(long)]
+        allowlist: Option<String>,
+
+        /// Path to the proofs file
+        #[clap(long, default_value = DEFAULT_ALLOWLIST_PROOFS)]
+        proofs: String,
+    },
+    /// Create a candy guard with the config guards and wrap the candy machine with it (Candy Machine Core (v3) only, the v2 candy machines created by 'deploy' cannot be wrapped)
+    Add {
+        /// Path to the keypair file, uses Sol config or defaults to "~/.config/solana/id.json"
+        #[clap(short, long)]
+        keypair: Option<String>,
+
+        /// RPC Url
+        #[clap(short, long)]
+        rpc_url: Option<String>,
+
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, default_value = DEFAULT_CACHE)]
+        cache: String,
+
+        /// Path to the config file, defaults to "config.json"
+        #[clap(short, long, default_value = DEFAULT_CONFIG)]
+        config: String,
+
+        /// Address of the candy machine (Candy Machine Core)
+        #[clap(long)]
+        candy_machine: Option<String>,
+    },
+    /// Update the guards of the candy guard from the config file
+    Update {
+        /// Path to the keypair file, uses Sol config or defaults to "~/.config/solana/id.json"
+        #[clap(short, long)]
+        keypair: Option<String>,
+
+        /// RPC Url
+        #[clap(short, long)]
+        rpc_url: Option<String>,
+
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, default_value = DEFAULT_CACHE)]
+        cache: String,
+
+        /// Path to the config file, defaults to "config.json"
+        #[clap(short, long, default_value = DEFAULT_CONFIG)]
+        config: String,
+
+        /// Address of the candy guard
+        #[clap(long)]
+        candy_guard: Option<String>,
+    },
+    /// Unwrap the candy machine and close its candy guard
+    Remove {
+        /// Path to the keypair file, uses Sol config or defaults to "~/.config/solana/id.json"
+        #[clap(short, long)]
+        keypair: Option<String>,
+
+        /// RPC Url
+        #[clap(short, long)]
+        rpc_url: Option<String>,
+
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, default_value = DEFAULT_CACHE)]
+        cache: String,
+
+        /// Address of the candy machine (Candy Machine Core)
+        #[clap(long)]
+        candy_machine: Option<String>,
+
+        /// Address of the candy guard
+        #[clap(long)]
+        candy_guard: Option<String>,
+    },
+    /// Show the guards of the candy guard
+    Show {
+        /// Path to the keypair file, uses Sol config or defaults to "~/.config/solana/id.json"
+        #[clap(short, long)]
+        keypair: Option<String>,
+
+        /// RPC Url
+        #[clap(short, long)]
+        rpc_url: Option<String>,
+
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, default_value = DEFAULT_CACHE)]
+        cache: String,
+
+        /// Address of the candy guard
+        #[clap(long)]
+        candy_guard: Option<String>,
+    },
+}
+
+#[derive(clap::Subcommand)]
+pub enum BlockListAction {
+    /// Add or remove wallets from the block list, creating it if needed
+    Update {
+        /// Path to the block list file (one wallet address per line)
+        #[clap(long, default_value = DEFAULT_BLOCKLIST)]
+        blocklist: String,
+
+        /// Path to a file with wallets to block (can be repeated)
+        #[clap(long)]
+        add: Vec<String>,
+
+        /// Path to a file with wallets to unblock (can be repeated)
+        #[clap(long)]
+        remove: Vec<String>,
+    },
+    /// Check whether a wallet is in the block list
+    Check {
+        /// Wallet address to check
+        wallet: String,
+
+        /// Path to the block list file (one wallet address per line)
+        #[clap(long, default_value = DEFAULT_BLOCKLIST)]
+        blocklist: String,
+    },
+}
+
+#[derive(clap::Subcommand)]
+pub enum AllowListAction {
+    /// Write a JSON file mapping each wallet to its merkle proof
+    ExportProofs {
+        /// Path to the allow list file (one wallet address per line)
+        #[clap(long)]
+        allowlist: String,
+
+        /// Path to the output file
+        #[clap(long, default_value = DEFAULT_ALLOWLIST_PROOFS)]
+        output: String,
+    },
+    /// Build the allow list from the current holders of another collection
+    FromHolders {
+        /// Path to the keypair file, uses Sol config or defaults to "~/.config/solana/id.json"
+        #[clap(short, long)]
+        keypair: Option<String>,
+
+        /// RPC Url
+        #[clap(short, long)]
+        rpc_url: Option<String>,
+
+        /// Address of the (verified) first creator of the collection items, required
+        /// unless the RPC node supports the DAS API and a collection is specified
+        #[clap(long)]
+        creator: Option<String>,
+
+        /// Mint address of the verified collection
+        #[clap(long)]
+        collection: Option<String>,
+
+        /// Trait filter in the form 'trait_type=value' (can be repeated)
+        #[clap(long = "trait")]
+        traits: Vec<String>,
+
+        /// Path to the output file
+        #[clap(long, default_value = DEFAULT_ALLOWLIST_PROOFS)]
+        output: String,
+    },
+}
+
+#[derive(clap::Subcommand)]
+pub enum SnapshotAction {
+    /// Write the mint addresses of all NFTs minted by the candy machine to a file
+    Mints {
+        /// Path to the keypair file, uses Sol config or defaults to "~/.config/solana/id.json"
+        #[clap(short, long)]
+        keypair: Option<String>,
+
+        /// RPC Url
+        #[clap(short, long)]
+        rpc_url: Option<String>,
+
+        /// Path to the cache file, defaults to "cache.json"
+        #[clap(long, default_value = DEFAULT_CACHE)]
+        cache: String,
+
+        /// Address of the candy machine
+        #[clap(long)]
+        candy_machine: Option<String>,
+
+        /// Path to the output file, defaults to "<candy machine>_mint_accounts.json"
+        #[clap(short, long)]
+        output: Option<String>,
+    },
+}
+
+#[derive(clap::Subcommand)]
+pub enum ConfigAction {
+    /// Interactive wizard to create a config file, in the format of its extension
+    Create {
+        /// Path to the config file
+        #[clap(short, long)]
+        config: Option<String>,
+
+        /// RPC Url
+        #[clap(short, long)]
+        rpc_url: Option<String>,
+
+        /// Path to the keypair file [default: solana config or "~/.config/solana/id.json"]
+        #[clap(short, long)]
+        keypair: Option<String>,
+
+        /// Path to the directory with the assets
+        #[clap(default_value = DEFAULT_ASSETS)]
+        assets_dir: String,
+    },
+}
+
+#[derive(clap::Subcommand)]
+pub enum RpcAction {
+    /// Show the health, latency and slot of each RPC endpoint
+    Status {
+        /// Path to the keypair file, uses Sol config or defaults to "~/.config/solana/id.json"
+        #[clap(short, long)]
+        keypair: Option<String>,
+
+        /// RPC Url
+        #[clap(short, long)]
+        rpc_url: Option<String>,
+
+        /// Path to the config file, used for its additional RPC urls
+        #[clap(short, long, default_value = DEFAULT_CONFIG)]
+        config: String,
+    },
+}
+
+#[derive(clap::Subcommand)]
+pub enum KeypairAction {
+    /// Encrypt a keypair file (or a 'prompt://' seed phrase) with a passphrase
+    Encrypt {
+        /// Path to the keypair file to encrypt, or 'prompt://[?key=<derivation path>]' to derive it from a seed phrase
+        #[clap(short, long)]
+        keypair: String,
+
+        /// Path of the encrypted keypair file (age format, also readable with 'age --decrypt')
+        #[clap(short, long)]
+        output: String,
+    },
 }
@@ -0,0 +1,37 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum CollectionError {
+    #[error("Could not read collection metadata file '{0}': {1}")]
+    MetadataFileError(String, String),
+
+    #[error("No image found for the collection metadata '{0}', use '--image' to specify it")]
+    MissingImage(String),
+
+    #[error("Failed to upload the collection {0}")]
+    UploadFailed(String),
+
+    #[error("Invalid collection mint address '{0}'")]
+    InvalidMint(String),
+
+    #[error("Collection mint {0} has no {1} account")]
+    MissingAccount(String, String),
+
+    #[error("Keypair {0} is not the update authority of the collection {1}")]
+    NotUpdateAuthority(String, String),
+
+    #[error("No collection set in the cache, run 'collection create' or 'collection set'")]
+    MissingCollection,
+
+    #[error("Keypair {0} is not the authority of the candy machine {1}")]
+    NotCandyMachineAuthority(String, String),
+
+    #[error("The collection of candy machine {0} cannot change after the first mint")]
+    AlreadyMinted(String),
+
+    #[error("Missing candy machine id, deploy the candy machine or use '--candy-machine'")]
+    MissingCandyMachine,
+
+    #[error("{0} NFT(s) could not be verified, run 'collection verify' again to retry")]
+    Incomplete(usize),
+}
@@ -0,0 +1,210 @@
+use anchor_client::solana_sdk::instruction::Instruction;
+use anchor_lang::prelude::AccountMeta;
+use mpl_token_metadata::ID as TOKEN_METADATA_ID;
+
+use crate::candy_machine::{sighash, ID as CANDY_MACHINE_ID};
+use crate::common::*;
+use crate::mint::pdas::{get_master_edition_pda, get_metadata_pda};
+
+/// Seed prefix of the collection PDA.
+const COLLECTION_PREFIX: &str = "collection";
+
+/// Seed of the collection authority record of the token metadata program.
+const COLLECTION_AUTHORITY: &str = "collection_authority";
+
+/// Collection of a candy machine, stored in the collection PDA. The PDA is
+/// the delegated collection authority of the collection NFT, so the candy
+/// machine verifies the collection of the NFTs it mints.
+///
+/// The collection instructions were added in later versions of the candy
+/// machine program than the one of the `mpl-candy-machine` crate, so the
+/// account is decoded (and the instructions built) here.
+#[derive(Debug, PartialEq)]
+pub struct CollectionPda {
+    pub mint: Pubkey,
+    pub candy_machine: Pubkey,
+}
+
+impl CollectionPda {
+    pub fn from_account_data(data: &[u8]) -> Result<Self> {
+        // skips the account discriminator
+        match data.get(8..72) {
+            Some(data) => Ok(CollectionPda {
+                mint: Pubkey::new(&data[..32]),
+                candy_machine: Pubkey::new(&data[32..]),
+            }),
+            None => Err(anyhow!("Invalid collection account data")),
+        }
+    }
+}
+
+pub fn get_collection_pda(candy_machine: &Pubkey) -> Pubkey {
+    let seeds = &[COLLECTION_PREFIX.as_bytes(), candy_machine.as_ref()];
+    let (pda, _bump) = Pubkey::find_program_address(seeds, &CANDY_MACHINE_ID);
+
+    pda
+}
+
+/// Return the collection authority record of a delegated authority of a
+/// collection NFT.
+pub fn get_collection_authority_record(mint: &Pubkey, authority: &Pubkey) -> Pubkey {
+    let seeds = &[
+        "metadata".as_bytes(),
+        TOKEN_METADATA_ID.as_ref(),
+        mint.as_ref(),
+        COLLECTION_AUTHORITY.as_bytes(),
+        authority.as_ref(),
+    ];
+    let (pda, _bump) = Pubkey::find_program_address(seeds, &TOKEN_METADATA_ID);
+
+    pda
+}
+
+/// Return the collection of the candy machine, or None if it has none.
+pub fn get_collection_state(
+    program: &Program,
+    candy_machine: &Pubkey,
+) -> Result<Option<CollectionPda>> {
+    let collection_pda = get_collection_pda(candy_machine);
+
+    program
+        .rpc()
+        .get_account_with_commitment(&collection_pda, CommitmentConfig::confirmed())?
+        .value
+        .map(|account| CollectionPda::from_account_data(&account.data))
+        .transpose()
+}
+
+pub fn set_collection(
+    candy_machine: Pubkey,
+    authority: Pubkey,
+    collection_mint: Pubkey,
+) -> Instruction {
+    let collection_pda = get_collection_pda(&candy_machine);
+
+    Instruction {
+        program_id: CANDY_MACHINE_ID,
+        accounts: vec![
+            AccountMeta::new(candy_machine, false),
+            AccountMeta::new_readonly(authority, true),
+            AccountMeta::new(collection_pda, false),
+            AccountMeta::new(authority, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(sysvar::rent::ID, false),
+            AccountMeta::new_readonly(get_metadata_pda(&collection_mint), false),
+            AccountMeta::new_readonly(collection_mint, false),
+            AccountMeta::new_readonly(get_master_edition_pda(&collection_mint), false),
+            AccountMeta::new(
+                get_collection_authority_record(&collection_mint, &collection_pda),
+                false,
+            ),
+            AccountMeta::new_readonly(TOKEN_METADATA_ID, false),
+        ],
+        data: sighash("set_collection").to_vec(),
+    }
+}
+
+pub fn remove_collection(
+    candy_machine: Pubkey,
+    authority: Pubkey,
+    collection_mint: Pubkey,
+) -> Instruction {
+    let collection_pda = get_collection_pda(&candy_machine);
+
+    Instruction {
+        program_id: CANDY_MACHINE_ID,
+        accounts: vec![
+            AccountMeta::new(candy_machine, false),
+            // receives the rent of the collection PDA
+            AccountMeta::new(authority, true),
+            AccountMeta::new(collection_pda, false),
+            AccountMeta::new_readonly(get_metadata_pda(&collection_mint), false),
+            AccountMeta::new_readonly(collection_mint, false),
+            AccountMeta::new(
+                get_collection_authority_record(&collection_mint, &collection_pda),
+                false,
+            ),
+            AccountMeta::new_readonly(TOKEN_METADATA_ID, false),
+        ],
+        data: sighash("remove_collection").to_vec(),
+    }
+}
+
+/// Verify the collection of a minted NFT. It must follow the `mint_nft`
+/// instruction in the same transaction.
+pub fn set_collection_during_mint(
+    candy_machine: Pubkey,
+    metadata: Pubkey,
+    payer: Pubkey,
+    collection: &CollectionPda,
+    authority: Pubkey,
+) -> Instruction {
+    let collection_pda = get_collection_pda(&candy_machine);
+
+    Instruction {
+        program_id: CANDY_MACHINE_ID,
+        accounts: vec![
+            AccountMeta::new_readonly(candy_machine, false),
+            AccountMeta::new(metadata, false),
+            AccountMeta::new(payer, true),
+            AccountMeta::new(collection_pda, false),
+            AccountMeta::new_readonly(TOKEN_METADATA_ID, false),
+            AccountMeta::new_readonly(sysvar::instructions::ID, false),
+            AccountMeta::new_readonly(collection.mint, false),
+            AccountMeta::new_readonly(get_metadata_pda(&collection.mint), false),
+            AccountMeta::new_readonly(get_master_edition_pda(&collection.mint), false),
+            AccountMeta::new_readonly(authority, false),
+            AccountMeta::new_readonly(
+                get_collection_authority_record(&collection.mint, &collection_pda),
+                false,
+            ),
+        ],
+        data: sighash("set_collection_during_mint").to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collection_pda_decode() {
+        let mint = Pubkey::new_unique();
+        let candy_machine = Pubkey::new_unique();
+
+        let mut data = vec![0u8; 8];
+        data.extend_from_slice(mint.as_ref());
+        data.extend_from_slice(candy_machine.as_ref());
+
+        assert_eq!(
+            CollectionPda::from_account_data(&data).unwrap(),
+            CollectionPda {
+                mint,
+                candy_machine
+            }
+        );
+        assert!(CollectionPda::from_account_data(&data[..71]).is_err());
+    }
+
+    #[test]
+    fn collection_instructions() {
+        let candy_machine = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let collection_pda = get_collection_pda(&candy_machine);
+
+        let set = set_collection(candy_machine, authority, mint);
+        assert_eq!(set.data, sighash("set_collection"));
+        assert_eq!(set.accounts.len(), 11);
+        assert_eq!(set.accounts[2].pubkey, collection_pda);
+        assert_eq!(
+            set.accounts[9].pubkey,
+            get_collection_authority_record(&mint, &collection_pda)
+        );
+
+        let remove = remove_collection(candy_machine, authority, mint);
+        assert_eq!(remove.data, sighash("remove_collection"));
+        assert_eq!(remove.accounts.len(), 7);
+        assert!(remove.accounts[1].is_signer && remove.accounts[1].is_writable);
+    }
+}
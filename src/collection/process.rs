@@ -0,0 +1,755 @@
+use anchor_client::solana_sdk::program_pack::Pack;
+use console::style;
+use mpl_token_metadata::{
+    instruction::{
+        create_master_edition_v3, create_metadata_accounts_v2, update_metadata_accounts_v2,
+        verify_collection,
+    },
+    state::{Collection, Creator, DataV2, Key, Metadata, MAX_METADATA_LEN},
+    utils::try_from_slice_checked,
+    ID as TOKEN_METADATA_ID,
+};
+use spl_associated_token_account::{create_associated_token_account, get_associated_token_address};
+use spl_token::{
+    instruction::{initialize_mint, mint_to},
+    ID as TOKEN_PROGRAM_ID,
+};
+use std::{
+    collections::BTreeMap,
+    fs,
+    sync::{atomic::AtomicBool, Arc},
+};
+
+use crate::cache::load_cache;
+use crate::candy_machine::{get_candy_machine_state, ID as CANDY_MACHINE_ID};
+use crate::cli::CollectionAction;
+use crate::collection::*;
+use crate::common::*;
+use crate::config::{get_config_data, ConfigData, SugarConfig};
+use crate::guard::get_creator_metadata;
+use crate::mint::pdas::{get_candy_machine_creator_pda, get_master_edition_pda, get_metadata_pda};
+use crate::rpc_cache;
+use crate::spend::{record_rent, record_transaction, track_spend};
+use crate::transaction::send_with_retry;
+use crate::upload::{hash_file, initialize_handler, AssetPair, DataType};
+use crate::utils::*;
+use crate::validate::format::Metadata as MetadataJson;
+
+pub struct CollectionArgs {
+    pub action: CollectionAction,
+}
+
+pub async fn process_collection(args: CollectionArgs) -> Result<()> {
+    match args.action {
+        CollectionAction::Create {
+            metadata,
+            image,
+            keypair,
+            rpc_url,
+            cache,
+            config,
+        } => process_create_collection(keypair, rpc_url, &cache, &config, &metadata, image).await,
+        CollectionAction::Set {
+            collection_mint,
+            keypair,
+            rpc_url,
+            cache,
+        } => process_set_collection(keypair, rpc_url, &cache, &collection_mint),
+        CollectionAction::Remove {
+            keypair,
+            rpc_url,
+            cache,
+        } => process_remove_collection(keypair, rpc_url, &cache),
+        CollectionAction::Verify {
+            keypair,
+            rpc_url,
+            cache,
+            candy_machine,
+        } => process_verify_collection(keypair, rpc_url, &cache, candy_machine),
+    }
+}
+
+async fn process_create_collection(
+    keypair: Option<String>,
+    rpc_url: Option<String>,
+    cache_file: &str,
+    config: &str,
+    metadata_file: &str,
+    image: Option<String>,
+) -> Result<()> {
+    track_spend("collection", cache_file);
+
+    println!(
+        "{} {}Loading collection metadata",
+        style("[1/3]").bold().dim(),
+        ASSETS_EMOJI
+    );
+
+    let content = fs::read_to_string(metadata_file).map_err(|e| {
+        CollectionError::MetadataFileError(metadata_file.to_string(), e.to_string())
+    })?;
+    let metadata: MetadataJson = serde_json::from_str(&content).map_err(|e| {
+        CollectionError::MetadataFileError(metadata_file.to_string(), e.to_string())
+    })?;
+    metadata.clone().validate()?;
+
+    let image = match image {
+        Some(image) => image,
+        None => find_image(metadata_file)?,
+    };
+
+    println!("Collection: {} ({})", metadata.name, image);
+
+    let sugar_config = sugar_setup(keypair, rpc_url)?;
+    let config_data = get_config_data(config)?;
+    let mut cache = load_cache(cache_file, true)?;
+
+    println!(
+        "\n{} {}Uploading collection files",
+        style("[2/3]").bold().dim(),
+        UPLOAD_EMOJI
+    );
+
+    let uri = upload_collection(
+        &sugar_config,
+        &config_data,
+        metadata_file,
+        &image,
+        &metadata,
+    )
+    .await?;
+
+    println!("{} {}", style("Metadata:").bold(), uri);
+
+    println!(
+        "\n{} {}Creating collection NFT",
+        style("[3/3]").bold().dim(),
+        CANDY_EMOJI
+    );
+
+    let pb = spinner_with_style();
+    pb.set_message("Sending transaction...");
+
+    let client = setup_client(&sugar_config)?;
+    let program = client.program(TOKEN_METADATA_ID);
+    let (signature, collection_mint) =
+        create_collection_nft(&program, &sugar_config.keypair, &metadata, uri)?;
+
+    pb.finish_with_message(format!("{} {}", style("Signature:").bold(), signature));
+
+    cache.program.collection_mint = Some(collection_mint.to_string());
+    cache.sync_file()?;
+
+    println!(
+        "\n{} {}",
+        style("Collection mint:").bold(),
+        style(collection_mint).green()
+    );
+
+    set_deployed_collection(&sugar_config, &cache, &collection_mint)
+}
+
+fn process_set_collection(
+    keypair: Option<String>,
+    rpc_url: Option<String>,
+    cache_file: &str,
+    collection_mint: &str,
+) -> Result<()> {
+    println!(
+        "{} {}Checking collection",
+        style("[1/2]").bold().dim(),
+        LOOKING_GLASS_EMOJI
+    );
+
+    let collection_mint = Pubkey::from_str(collection_mint)
+        .map_err(|_| CollectionError::InvalidMint(collection_mint.to_string()))?;
+
+    let sugar_config = sugar_setup(keypair, rpc_url)?;
+    let client = setup_client(&sugar_config)?;
+    let program = client.program(TOKEN_METADATA_ID);
+    let mut cache = load_cache(cache_file, false)?;
+
+    let metadata = get_collection_metadata(&program, &collection_mint)?;
+    println!(
+        "Collection: {}",
+        metadata.data.name.trim_matches(char::from(0))
+    );
+
+    println!(
+        "\n{} {}Setting collection",
+        style("[2/2]").bold().dim(),
+        PAPER_EMOJI
+    );
+
+    cache.program.collection_mint = Some(collection_mint.to_string());
+    cache.sync_file()?;
+
+    println!(
+        "\n{} {}",
+        style("Collection mint:").bold(),
+        style(collection_mint).green()
+    );
+
+    set_deployed_collection(&sugar_config, &cache, &collection_mint)
+}
+
+fn process_remove_collection(
+    keypair: Option<String>,
+    rpc_url: Option<String>,
+    cache_file: &str,
+) -> Result<()> {
+    let mut cache = load_cache(cache_file, false)?;
+
+    if !cache.program.candy_machine.is_empty() {
+        let candy_machine = Pubkey::from_str(&cache.program.candy_machine).map_err(|_| {
+            CacheError::InvalidCandyMachineAddress(cache.program.candy_machine.clone())
+        })?;
+        let sugar_config = sugar_setup(keypair, rpc_url)?;
+
+        if let Some(signature) = remove_candy_machine_collection(&sugar_config, &candy_machine)? {
+            println!("Removed the collection of candy machine {}.", candy_machine);
+            println!("{} {}", style("Signature:").bold(), signature);
+        }
+    }
+
+    match cache.program.collection_mint.take() {
+        Some(collection_mint) => {
+            cache.sync_file()?;
+            println!("Removed collection {} from the cache.", collection_mint);
+            println!("NFTs already verified remain members of the collection.");
+        }
+        None => println!("No collection set in the cache."),
+    }
+
+    Ok(())
+}
+
+/// Set the collection on the candy machine of the cache, if it is deployed;
+/// otherwise, 'deploy' sets it when creating the candy machine.
+fn set_deployed_collection(
+    sugar_config: &SugarConfig,
+    cache: &Cache,
+    collection_mint: &Pubkey,
+) -> Result<()> {
+    if cache.program.candy_machine.is_empty() {
+        println!("The collection is set on the candy machine by 'deploy'.");
+        return Ok(());
+    }
+
+    let candy_machine = Pubkey::from_str(&cache.program.candy_machine)
+        .map_err(|_| CacheError::InvalidCandyMachineAddress(cache.program.candy_machine.clone()))?;
+
+    let pb = spinner_with_style();
+    pb.set_message("Setting the collection of the candy machine...");
+
+    match set_candy_machine_collection(sugar_config, &candy_machine, collection_mint)? {
+        Some(signature) => {
+            pb.finish_with_message(format!("{} {}", style("Signature:").bold(), signature))
+        }
+        None => pb.finish_with_message("Collection already set on the candy machine"),
+    }
+
+    println!("NFTs minted from now on are verified members of the collection.");
+
+    Ok(())
+}
+
+/// Set the collection of a candy machine, replacing its current one. Returns
+/// None if the collection is already set.
+pub fn set_candy_machine_collection(
+    sugar_config: &SugarConfig,
+    candy_machine: &Pubkey,
+    collection_mint: &Pubkey,
+) -> Result<Option<Signature>> {
+    let client = setup_client(sugar_config)?;
+    let program = client.program(CANDY_MACHINE_ID);
+    let payer = program.payer();
+
+    let state = get_candy_machine_state(sugar_config, candy_machine)?;
+    if state.authority != payer {
+        return Err(CollectionError::NotCandyMachineAuthority(
+            payer.to_string(),
+            candy_machine.to_string(),
+        )
+        .into());
+    }
+
+    let current = get_collection_state(&program, candy_machine)?;
+    if current.as_ref().map(|collection| collection.mint) == Some(*collection_mint) {
+        return Ok(None);
+    }
+
+    // the candy machine program only accepts collection changes before the
+    // first mint
+    if state.items_redeemed > 0 {
+        return Err(CollectionError::AlreadyMinted(candy_machine.to_string()).into());
+    }
+
+    let mut instructions = Vec::new();
+
+    if let Some(current) = current {
+        instructions.push(remove_collection(*candy_machine, payer, current.mint));
+    }
+    instructions.push(set_collection(*candy_machine, payer, *collection_mint));
+
+    let signature = send_with_retry(&program, &instructions, &[&sugar_config.keypair])?;
+
+    rpc_cache::invalidate(candy_machine);
+    record_transaction(1, instructions.len() as u64, None);
+    info!("Collection set! TxId: {}", signature);
+
+    Ok(Some(signature))
+}
+
+/// Remove the collection of a candy machine. Returns None if it has none.
+pub fn remove_candy_machine_collection(
+    sugar_config: &SugarConfig,
+    candy_machine: &Pubkey,
+) -> Result<Option<Signature>> {
+    let client = setup_client(sugar_config)?;
+    let program = client.program(CANDY_MACHINE_ID);
+    let payer = program.payer();
+
+    let collection = match get_collection_state(&program, candy_machine)? {
+        Some(collection) => collection,
+        None => return Ok(None),
+    };
+
+    let state = get_candy_machine_state(sugar_config, candy_machine)?;
+    if state.authority != payer {
+        return Err(CollectionError::NotCandyMachineAuthority(
+            payer.to_string(),
+            candy_machine.to_string(),
+        )
+        .into());
+    }
+
+    if state.items_redeemed > 0 {
+        return Err(CollectionError::AlreadyMinted(candy_machine.to_string()).into());
+    }
+
+    let signature = send_with_retry(
+        &program,
+        &[remove_collection(*candy_machine, payer, collection.mint)],
+        &[&sugar_config.keypair],
+    )?;
+
+    rpc_cache::invalidate(candy_machine);
+    record_transaction(1, 1, None);
+    info!("Collection removed! TxId: {}", signature);
+
+    Ok(Some(signature))
+}
+
+fn process_verify_collection(
+    keypair: Option<String>,
+    rpc_url: Option<String>,
+    cache_file: &str,
+    candy_machine: Option<String>,
+) -> Result<()> {
+    track_spend("collection", cache_file);
+
+    println!(
+        "{} {}Checking collection",
+        style("[1/4]").bold().dim(),
+        LOOKING_GLASS_EMOJI
+    );
+
+    let sugar_config = sugar_setup(keypair, rpc_url)?;
+    let client = setup_client(&sugar_config)?;
+    let program = client.program(TOKEN_METADATA_ID);
+    let payer = program.payer();
+    let cache = load_cache(cache_file, false)?;
+
+    let collection_mint = cache
+        .program
+        .collection_mint
+        .as_ref()
+        .ok_or(CollectionError::MissingCollection)?;
+    let collection_mint = Pubkey::from_str(collection_mint)
+        .map_err(|_| CollectionError::InvalidMint(collection_mint.clone()))?;
+    let collection_metadata = get_collection_metadata(&program, &collection_mint)?;
+
+    println!(
+        "Collection: {} ({})",
+        collection_metadata.data.name.trim_matches(char::from(0)),
+        collection_mint
+    );
+
+    // the candy machine specified takes precedence over the one from the cache
+    let candy_machine = match candy_machine {
+        Some(candy_machine) => candy_machine,
+        None if !cache.program.candy_machine.is_empty() => cache.program.candy_machine,
+        None => return Err(CollectionError::MissingCandyMachine.into()),
+    };
+    let candy_machine = Pubkey::from_str(&candy_machine)
+        .map_err(|_| CacheError::InvalidCandyMachineAddress(candy_machine.clone()))?;
+
+    println!(
+        "\n{} {}Retrieving minted NFTs",
+        style("[2/4]").bold().dim(),
+        LOOKING_GLASS_EMOJI
+    );
+
+    let pb = spinner_with_style();
+    pb.set_message("Connecting...");
+
+    let (creator, _) = get_candy_machine_creator_pda(&candy_machine);
+    let minted = get_creator_metadata(&program, &creator)?;
+
+    pb.finish_and_clear();
+
+    let total = minted.len();
+    let pending: Vec<(Pubkey, Metadata)> = minted
+        .into_iter()
+        .filter(|(_, metadata)| !is_verified(metadata, &collection_mint))
+        .collect();
+
+    println!(
+        "Found {} minted NFT(s), {} already verified",
+        total,
+        total - pending.len()
+    );
+
+    println!(
+        "\n{} {}Verifying collection membership",
+        style("[3/4]").bold().dim(),
+        CANDY_EMOJI
+    );
+
+    if pending.is_empty() {
+        println!("\nAll minted NFTs are verified members of the collection.");
+        return Ok(());
+    }
+
+    let collection_pda = get_metadata_pda(&collection_mint);
+    let collection_edition = get_master_edition_pda(&collection_mint);
+    let pb = progress_bar_with_style(pending.len() as u64);
+
+    for (metadata_pda, metadata) in &pending {
+        // the collection must be set (unverified) before it can be verified
+        let set_collection = metadata.collection.as_ref().map(|c| c.key) != Some(collection_mint);
+
+        let result = if set_collection && metadata.update_authority != payer {
+            Err(anyhow!("{} is not the update authority of the NFT", payer))
+        } else {
+            let mut instructions = Vec::new();
+
+            if set_collection {
+                instructions.push(update_metadata_accounts_v2(
+                    TOKEN_METADATA_ID,
+                    *metadata_pda,
+                    payer,
+                    None,
+                    Some(DataV2 {
+                        name: metadata.data.name.clone(),
+                        symbol: metadata.data.symbol.clone(),
+                        uri: metadata.data.uri.clone(),
+                        seller_fee_basis_points: metadata.data.seller_fee_basis_points,
+                        creators: metadata.data.creators.clone(),
+                        collection: Some(Collection {
+                            verified: false,
+                            key: collection_mint,
+                        }),
+                        uses: metadata.uses.clone(),
+                    }),
+                    None,
+                    None,
+                ));
+            }
+
+            instructions.push(verify_collection(
+                TOKEN_METADATA_ID,
+                *metadata_pda,
+                payer,
+                payer,
+                collection_mint,
+                collection_pda,
+                collection_edition,
+                None,
+            ));
+
+            send_with_retry(&program, &instructions, &[&sugar_config.keypair])
+        };
+
+        match result {
+            Ok(signature) => {
+                record_transaction(1, 2, None);
+                info!("Verified {}: {}", metadata.mint, signature);
+            }
+            // the verification reports the NFTs that failed
+            Err(err) => error!("Failed to verify {}: {}", metadata.mint, err),
+        }
+
+        pb.inc(1);
+    }
+
+    pb.finish_and_clear();
+
+    println!(
+        "\n{} {}Checking verified NFTs",
+        style("[4/4]").bold().dim(),
+        LOOKING_GLASS_EMOJI
+    );
+
+    let pb = spinner_with_style();
+    pb.set_message("Retrieving metadata...");
+
+    let mut failed = Vec::new();
+
+    for chunk in pending.chunks(MAX_ACCOUNTS_PER_REQUEST) {
+        let pdas: Vec<Pubkey> = chunk.iter().map(|(pda, _)| *pda).collect();
+        let accounts = program.rpc().get_multiple_accounts(&pdas)?;
+
+        for ((_, metadata), account) in chunk.iter().zip(accounts) {
+            let verified = account
+                .and_then(|account| {
+                    try_from_slice_checked::<Metadata>(
+                        &account.data,
+                        Key::MetadataV1,
+                        MAX_METADATA_LEN,
+                    )
+                    .ok()
+                })
+                .map(|metadata| is_verified(&metadata, &collection_mint))
+                .unwrap_or(false);
+
+            if !verified {
+                failed.push(metadata.mint);
+            }
+        }
+    }
+
+    pb.finish_and_clear();
+
+    if !failed.is_empty() {
+        println!("\nNFTs not verified:");
+        for mint in &failed {
+            println!("- {}", mint);
+        }
+
+        return Err(CollectionError::Incomplete(failed.len()).into());
+    }
+
+    println!(
+        "\n{}",
+        style(format!("{} NFT(s) verified.", pending.len())).bold()
+    );
+
+    Ok(())
+}
+
+/// Return the image file next to the metadata file, with the same name.
+fn find_image(metadata_file: &str) -> Result<String> {
+    let path = Path::new(metadata_file);
+
+    for extension in ["png", "jpg", "jpeg", "gif", "PNG", "JPG", "JPEG", "GIF"] {
+        let image = path.with_extension(extension);
+
+        if image.exists() {
+            return path_to_string(&image);
+        }
+    }
+
+    Err(CollectionError::MissingImage(metadata_file.to_string()).into())
+}
+
+/// Upload the image and metadata of the collection with the configured upload
+/// method, returning the link of the metadata.
+async fn upload_collection(
+    sugar_config: &SugarConfig,
+    config_data: &ConfigData,
+    metadata_file: &str,
+    image: &str,
+    metadata: &MetadataJson,
+) -> Result<String> {
+    let pair = AssetPair {
+        name: metadata.name.clone(),
+        metadata: metadata_file.to_string(),
+        metadata_hash: hash_file(metadata_file)?,
+        image: image.to_string(),
+        image_hash: hash_file(image)?,
+        animation: None,
+        animation_hash: None,
+        preview: None,
+        preview_hash: None,
+        locales: BTreeMap::new(),
+    };
+
+    // the handlers record the links in a cache, so the collection is uploaded
+    // with a temporary one to keep it out of the items of the candy machine
+    let mut cache = Cache::new();
+    cache.file_path = path_to_string(
+        &std::env::temp_dir().join(format!("sugar-collection-{}.json", std::process::id())),
+    )?;
+    cache
+        .items
+        .0
+        .insert("0".to_string(), pair.clone().into_cache_item());
+
+    let mut assets = HashMap::new();
+    assets.insert(0, pair);
+
+    let handler = initialize_handler(config_data, sugar_config).await?;
+    handler
+        .prepare(sugar_config, &assets, &[0], &[0], &[], &[], false)
+        .await?;
+
+    let interrupted = Arc::new(AtomicBool::new(false));
+
+    for (data_type, name) in [(DataType::Image, "image"), (DataType::Metadata, "metadata")] {
+        let errors = handler
+            .upload_data(
+                sugar_config,
+                &assets,
+                &mut cache,
+                &[0],
+                data_type,
+                interrupted.clone(),
+            )
+            .await?;
+
+        if !errors.is_empty() {
+            fs::remove_file(&cache.file_path).ok();
+            return Err(CollectionError::UploadFailed(name.to_string()).into());
+        }
+    }
+
+    fs::remove_file(&cache.file_path).ok();
+
+    let item = cache
+        .items
+        .0
+        .get("0")
+        .ok_or_else(|| CollectionError::UploadFailed("metadata".to_string()))?;
+
+    Ok(item.metadata_link.clone())
+}
+
+/// Create the collection NFT (with a master edition of supply 0), returning the
+/// signature of the transaction and the address of the mint.
+fn create_collection_nft(
+    program: &Program,
+    keypair: &dyn Signer,
+    metadata: &MetadataJson,
+    uri: String,
+) -> Result<(Signature, Pubkey)> {
+    let payer = program.payer();
+    let collection_mint = Keypair::new();
+    let mint_pubkey = collection_mint.pubkey();
+
+    let min_rent = program
+        .rpc()
+        .get_minimum_balance_for_rent_exemption(MINT_LAYOUT as usize)?;
+    let assoc = get_associated_token_address(&payer, &mint_pubkey);
+    let metadata_pda = get_metadata_pda(&mint_pubkey);
+    let master_edition_pda = get_master_edition_pda(&mint_pubkey);
+
+    let creators = vec![Creator {
+        address: payer,
+        verified: true,
+        share: 100,
+    }];
+
+    let instructions = vec![
+        system_instruction::create_account(
+            &payer,
+            &mint_pubkey,
+            min_rent,
+            MINT_LAYOUT,
+            &TOKEN_PROGRAM_ID,
+        ),
+        initialize_mint(&TOKEN_PROGRAM_ID, &mint_pubkey, &payer, Some(&payer), 0)?,
+        create_associated_token_account(&payer, &payer, &mint_pubkey),
+        mint_to(&TOKEN_PROGRAM_ID, &mint_pubkey, &assoc, &payer, &[], 1)?,
+        create_metadata_accounts_v2(
+            TOKEN_METADATA_ID,
+            metadata_pda,
+            mint_pubkey,
+            payer,
+            payer,
+            payer,
+            metadata.name.clone(),
+            metadata.symbol.clone(),
+            uri,
+            Some(creators),
+            metadata.seller_fee_basis_points,
+            true,
+            true,
+            None,
+            None,
+        ),
+        create_master_edition_v3(
+            TOKEN_METADATA_ID,
+            master_edition_pda,
+            mint_pubkey,
+            payer,
+            payer,
+            metadata_pda,
+            payer,
+            Some(0),
+        ),
+    ];
+
+    let signature = send_with_retry(program, &instructions, &[keypair, &collection_mint])?;
+
+    record_transaction(2, 6, None);
+    record_rent(
+        min_rent
+            + program
+                .rpc()
+                .get_minimum_balance_for_rent_exemption(spl_token::state::Account::LEN)?,
+    );
+
+    info!("Collection created! TxId: {}", signature);
+
+    Ok((signature, mint_pubkey))
+}
+
+/// Return the metadata of a collection NFT, checking that it has a master
+/// edition and that the payer is its update authority.
+fn get_collection_metadata(program: &Program, collection_mint: &Pubkey) -> Result<Metadata> {
+    let payer = program.payer();
+    let metadata_pda = get_metadata_pda(collection_mint);
+    let master_edition_pda = get_master_edition_pda(collection_mint);
+    let accounts = program
+        .rpc()
+        .get_multiple_accounts(&[metadata_pda, master_edition_pda])?;
+
+    let metadata: Metadata = match &accounts[0] {
+        Some(account) => try_from_slice_checked(&account.data, Key::MetadataV1, MAX_METADATA_LEN)?,
+        None => {
+            return Err(CollectionError::MissingAccount(
+                collection_mint.to_string(),
+                "metadata".to_string(),
+            )
+            .into())
+        }
+    };
+
+    if accounts[1].is_none() {
+        return Err(CollectionError::MissingAccount(
+            collection_mint.to_string(),
+            "master edition".to_string(),
+        )
+        .into());
+    }
+
+    if metadata.update_authority != payer {
+        return Err(CollectionError::NotUpdateAuthority(
+            payer.to_string(),
+            collection_mint.to_string(),
+        )
+        .into());
+    }
+
+    Ok(metadata)
+}
+
+fn is_verified(metadata: &Metadata, collection_mint: &Pubkey) -> bool {
+    metadata
+        .collection
+        .as_ref()
+        .map(|collection| collection.verified && collection.key == *collection_mint)
+        .unwrap_or(false)
+}
@@ -27,8 +27,9 @@ pub use mpl_candy_machine::accounts as nft_accounts;
 pub use mpl_candy_machine::instruction as nft_instruction;
 pub use mpl_candy_machine::{CandyMachine, WhitelistMintMode, ID as CANDY_MACHINE_PROGRAM_ID};
 
-pub use crate::cache::{Cache, CacheItem};
+pub use crate::cache::{Cache, CacheBackend, CacheItem, ItemStatus};
 pub use crate::constants::*;
 pub use crate::errors::*;
 pub use crate::parse::path_to_string;
-pub use crate::setup::{setup_client, sugar_setup};
+pub use crate::setup::{http_client, setup_client, sugar_setup};
+pub use crate::signer::SugarSigner;
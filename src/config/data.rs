@@ -1,11 +1,15 @@
-use anchor_client::solana_sdk::signature::Keypair;
 use anchor_client::solana_sdk::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey};
 pub use anyhow::{anyhow, Result};
 use chrono::DateTime;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
 use std::fmt::Display;
 use std::str::FromStr;
 
+use crate::cache::CacheBackend;
+use crate::retry::RetryConfig;
+use crate::signer::SugarSigner;
+
 use mpl_candy_machine::{
     Creator as CandyCreator, EndSettingType as CandyEndSettingType,
     EndSettings as CandyEndSettings, GatekeeperConfig as CandyGatekeeperConfig,
@@ -13,10 +17,13 @@ use mpl_candy_machine::{
     WhitelistMintSettings as CandyWhitelistMintSettings,
 };
 
+use mpl_token_metadata::state::{UseMethod as MetadataUseMethod, Uses as MetadataUses};
+
 use crate::config::errors::*;
+use crate::config::guards::CandyGuardData;
 
 pub struct SugarConfig {
-    pub keypair: Keypair,
+    pub keypair: SugarSigner,
     pub rpc_url: String,
 }
 
@@ -35,15 +42,15 @@ pub struct ConfigData {
     pub gatekeeper: Option<GatekeeperConfig>,
     pub creators: Vec<Creator>,
 
-    #[serde(deserialize_with = "to_option_pubkey")]
+    #[serde(default, deserialize_with = "to_option_pubkey")]
     #[serde(serialize_with = "to_option_string")]
     pub sol_treasury_account: Option<Pubkey>,
 
-    #[serde(deserialize_with = "to_option_pubkey")]
+    #[serde(default, deserialize_with = "to_option_pubkey")]
     #[serde(serialize_with = "to_option_string")]
     pub spl_token_account: Option<Pubkey>,
 
-    #[serde(deserialize_with = "to_option_pubkey")]
+    #[serde(default, deserialize_with = "to_option_pubkey")]
     #[serde(serialize_with = "to_option_string")]
     pub spl_token: Option<Pubkey>,
 
@@ -64,9 +71,80 @@ pub struct ConfigData {
     #[serde(serialize_with = "to_option_string")]
     pub aws_s3_bucket: Option<String>,
 
+    pub aws_region: Option<String>,
+
+    pub aws_key_prefix: Option<String>,
+
+    pub pinata_jwt: Option<String>,
+
+    pub pinata_api_key: Option<String>,
+
+    pub pinata_secret_api_key: Option<String>,
+
+    pub pinata_gateway: Option<String>,
+
+    pub nft_storage_token: Option<String>,
+
+    pub nft_storage_gateway: Option<String>,
+
+    pub shadow_drive_storage_account: Option<String>,
+
+    pub arweave_jwk: Option<String>,
+
+    pub arweave_gateway: Option<String>,
+
+    /// Url of the Bundlr node, replacing the default node of the cluster.
+    pub bundlr_node: Option<String>,
+
+    pub upload: Option<UploadSettings>,
+
+    /// Resizes and compresses the png and jpg images before they are uploaded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub optimize: Option<OptimizeSettings>,
+
+    /// Uploads a downscaled preview of each image, recorded as the image of
+    /// the metadata, with the full-resolution image in `properties.files`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previews: Option<PreviewSettings>,
+
+    /// Storage format of new cache files, "json" (default) or "sqlite" for
+    /// large collections. Existing cache files keep their format.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_backend: Option<CacheBackend>,
+
+    pub upload_concurrency: Option<usize>,
+
+    /// Maximum number of uploads started per second.
+    pub upload_rate_limit: Option<f64>,
+
     pub symbol: String,
 
     pub seller_fee_basis_points: u16,
+
+    pub uses: Option<Uses>,
+
+    pub priority_fee: Option<u64>,
+
+    pub proxy: Option<String>,
+
+    pub collection_manifest: Option<CollectionManifest>,
+
+    pub asset_overrides: Option<String>,
+
+    pub template_variables: Option<BTreeMap<String, String>>,
+
+    pub name_numbering: Option<NameNumbering>,
+
+    pub retry: Option<RetryConfig>,
+
+    /// Additional RPC endpoints, used together with the RPC url of the
+    /// command to balance requests and fail over.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rpc_urls: Option<Vec<String>>,
+
+    /// Guards of the Candy Guard program, used by the `guard add` command.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub guards: Option<CandyGuardData>,
 }
 
 pub fn to_string<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
@@ -112,7 +190,7 @@ pub fn price_as_lamports(price: f64) -> u64 {
     (price * LAMPORTS_PER_SOL as f64) as u64
 }
 
-fn to_pubkey<'de, D>(deserializer: D) -> Result<Pubkey, D::Error>
+pub(crate) fn to_pubkey<'de, D>(deserializer: D) -> Result<Pubkey, D::Error>
 where
     D: Deserializer<'de>,
 {
@@ -124,9 +202,10 @@ fn to_option_pubkey<'de, D>(deserializer: D) -> Result<Option<Pubkey>, D::Error>
 where
     D: Deserializer<'de>,
 {
+    // a YAML null ('~') is only read as null into an option
     let s: String = match Deserialize::deserialize(deserializer) {
-        Ok(s) => s,
-        Err(_) => return Ok(None),
+        Ok(Some(s)) => s,
+        _ => return Ok(None),
     };
 
     let pubkey = Pubkey::from_str(&s).map_err(serde::de::Error::custom)?;
@@ -270,6 +349,16 @@ impl HiddenSettings {
     pub fn new(name: String, uri: String, hash: String) -> HiddenSettings {
         HiddenSettings { name, uri, hash }
     }
+
+    /// Prefix of the names of the minted NFTs, followed by the mint number.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Placeholder URI of the minted NFTs.
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
     pub fn into_candy_format(&self) -> CandyHiddenSettings {
         CandyHiddenSettings {
             name: self.name.clone(),
@@ -283,10 +372,124 @@ impl HiddenSettings {
     }
 }
 
+/// Name and symbol overrides of an individual asset.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AssetOverride {
+    pub name: Option<String>,
+    pub symbol: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CollectionManifest {
+    pub name: String,
+    pub banner: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Uses {
+    #[serde(alias = "use_method")]
+    pub use_method: UseMethod,
+    pub remaining: u64,
+    pub total: u64,
+}
+
+impl Uses {
+    pub fn new(use_method: UseMethod, remaining: u64, total: u64) -> Uses {
+        Uses {
+            use_method,
+            remaining,
+            total,
+        }
+    }
+    pub fn into_metadata_format(&self) -> MetadataUses {
+        MetadataUses {
+            use_method: self.use_method.into_metadata_format(),
+            remaining: self.remaining,
+            total: self.total,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum UseMethod {
+    Burn,
+    Multiple,
+    Single,
+}
+
+impl UseMethod {
+    pub fn into_metadata_format(&self) -> MetadataUseMethod {
+        match self {
+            UseMethod::Burn => MetadataUseMethod::Burn,
+            UseMethod::Multiple => MetadataUseMethod::Multiple,
+            UseMethod::Single => MetadataUseMethod::Single,
+        }
+    }
+}
+
+impl FromStr for UseMethod {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "burn" => Ok(UseMethod::Burn),
+            "multiple" => Ok(UseMethod::Multiple),
+            "single" => Ok(UseMethod::Single),
+            _ => Err(anyhow::anyhow!("Invalid use method: {}", s)),
+        }
+    }
+}
+
+/// Controls how the on-chain name of each item is derived from its metadata name.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NameNumbering {
+    pub mode: NumberingMode,
+    /// Number of the first item (the item number is `start + index`).
+    #[serde(default)]
+    pub start: u64,
+    /// Width of zero-padded numbers, defaults to the digits of the last number.
+    pub width: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum NumberingMode {
+    /// Use the metadata name verbatim.
+    Verbatim,
+    /// Append `#<number>` to the metadata name.
+    Append,
+    /// Append a zero-padded `#<number>` to the metadata name.
+    ZeroPadded,
+}
+
+impl NameNumbering {
+    /// Return the on-chain name of the item at the specified index.
+    pub fn apply(&self, name: &str, index: usize, total: u64) -> String {
+        let number = self.start + index as u64;
+
+        match self.mode {
+            NumberingMode::Verbatim => name.to_string(),
+            NumberingMode::Append => format!("{} #{}", name, number),
+            NumberingMode::ZeroPadded => {
+                let last = self.start + total.saturating_sub(1);
+                let width = self.width.unwrap_or_else(|| last.to_string().len());
+                format!("{} #{:0width$}", name, number, width = width)
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize)]
 pub enum UploadMethod {
     Bundlr,
     AWS,
+    Pinata,
+    NftStorage,
+    ShadowDrive,
+    ArweaveNative,
 }
 
 impl Default for UploadMethod {
@@ -302,6 +505,10 @@ impl FromStr for UploadMethod {
         match s.to_lowercase().as_str() {
             "bundlr" => Ok(UploadMethod::Bundlr),
             "aws" => Ok(UploadMethod::AWS),
+            "pinata" => Ok(UploadMethod::Pinata),
+            "nft_storage" => Ok(UploadMethod::NftStorage),
+            "shadow_drive" => Ok(UploadMethod::ShadowDrive),
+            "arweave" => Ok(UploadMethod::ArweaveNative),
             _ => Err(ConfigError::InvalidUploadMethod(s.to_string())),
         }
     }
@@ -312,6 +519,10 @@ impl ToString for UploadMethod {
         match self {
             UploadMethod::Bundlr => "bundlr".to_string(),
             UploadMethod::AWS => "aws".to_string(),
+            UploadMethod::Pinata => "pinata".to_string(),
+            UploadMethod::NftStorage => "nft_storage".to_string(),
+            UploadMethod::ShadowDrive => "shadow_drive".to_string(),
+            UploadMethod::ArweaveNative => "arweave".to_string(),
         }
     }
 }
@@ -326,6 +537,168 @@ impl<'de> Deserialize<'de> for UploadMethod {
     }
 }
 
+/// Default maximum width or height (px) of optimized images.
+pub const DEFAULT_OPTIMIZE_MAX_DIMENSION: u32 = 2048;
+
+/// Default quality (1-100) of optimized jpg images.
+pub const DEFAULT_OPTIMIZE_QUALITY: u8 = 85;
+
+/// Settings of the image optimization before upload. The optimized images
+/// are written to a separate directory, leaving the assets untouched.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OptimizeSettings {
+    /// Maximum width or height (px), larger images are scaled down.
+    pub max_dimension: Option<u32>,
+    /// Quality (1-100) of the jpg images.
+    pub quality: Option<u8>,
+}
+
+impl OptimizeSettings {
+    pub fn max_dimension(&self) -> u32 {
+        self.max_dimension.unwrap_or(DEFAULT_OPTIMIZE_MAX_DIMENSION)
+    }
+
+    pub fn quality(&self) -> u8 {
+        self.quality.unwrap_or(DEFAULT_OPTIMIZE_QUALITY)
+    }
+}
+
+/// Default maximum width or height (px) of the image previews.
+pub const DEFAULT_PREVIEW_MAX_DIMENSION: u32 = 512;
+
+/// Default quality (1-100) of the jpg image previews.
+pub const DEFAULT_PREVIEW_QUALITY: u8 = 80;
+
+/// Settings of the image previews. Images that already fit the max
+/// dimension have no preview.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviewSettings {
+    /// Maximum width or height (px) of the previews.
+    pub max_dimension: Option<u32>,
+    /// Quality (1-100) of the jpg previews.
+    pub quality: Option<u8>,
+}
+
+impl PreviewSettings {
+    pub fn max_dimension(&self) -> u32 {
+        self.max_dimension.unwrap_or(DEFAULT_PREVIEW_MAX_DIMENSION)
+    }
+
+    pub fn quality(&self) -> u8 {
+        self.quality.unwrap_or(DEFAULT_PREVIEW_QUALITY)
+    }
+}
+
+/// Settings of the upload methods, one section per method. Values set in a
+/// section take precedence over the top-level settings of the method.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadSettings {
+    pub bundlr: Option<BundlrSettings>,
+    pub aws: Option<AwsSettings>,
+    pub pinata: Option<PinataSettings>,
+    pub nft_storage: Option<NftStorageSettings>,
+    pub shadow_drive: Option<ShadowDriveSettings>,
+    pub arweave: Option<ArweaveSettings>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundlrSettings {
+    pub node: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AwsSettings {
+    pub s3_bucket: Option<String>,
+    pub region: Option<String>,
+    pub key_prefix: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PinataSettings {
+    pub jwt: Option<String>,
+    pub api_key: Option<String>,
+    pub secret_api_key: Option<String>,
+    pub gateway: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NftStorageSettings {
+    pub token: Option<String>,
+    pub gateway: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShadowDriveSettings {
+    pub storage_account: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArweaveSettings {
+    pub jwk: Option<String>,
+    pub gateway: Option<String>,
+}
+
+/// Replace the value with the one of the section, if set.
+fn override_with(value: &mut Option<String>, section_value: &Option<String>) {
+    if section_value.is_some() {
+        *value = section_value.clone();
+    }
+}
+
+impl ConfigData {
+    /// Apply the per-method upload sections over the top-level upload
+    /// settings, so upload handlers only need to read the latter.
+    pub fn apply_upload_settings(&mut self) {
+        let upload = match self.upload.clone() {
+            Some(upload) => upload,
+            None => return,
+        };
+
+        if let Some(bundlr) = upload.bundlr {
+            override_with(&mut self.bundlr_node, &bundlr.node);
+        }
+
+        if let Some(aws) = upload.aws {
+            override_with(&mut self.aws_s3_bucket, &aws.s3_bucket);
+            override_with(&mut self.aws_region, &aws.region);
+            override_with(&mut self.aws_key_prefix, &aws.key_prefix);
+        }
+
+        if let Some(pinata) = upload.pinata {
+            override_with(&mut self.pinata_jwt, &pinata.jwt);
+            override_with(&mut self.pinata_api_key, &pinata.api_key);
+            override_with(&mut self.pinata_secret_api_key, &pinata.secret_api_key);
+            override_with(&mut self.pinata_gateway, &pinata.gateway);
+        }
+
+        if let Some(nft_storage) = upload.nft_storage {
+            override_with(&mut self.nft_storage_token, &nft_storage.token);
+            override_with(&mut self.nft_storage_gateway, &nft_storage.gateway);
+        }
+
+        if let Some(shadow_drive) = upload.shadow_drive {
+            override_with(
+                &mut self.shadow_drive_storage_account,
+                &shadow_drive.storage_account,
+            );
+        }
+
+        if let Some(arweave) = upload.arweave {
+            override_with(&mut self.arweave_jwk, &arweave.jwk);
+            override_with(&mut self.arweave_gateway, &arweave.gateway);
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Default, Serialize)]
 pub struct Creator {
     #[serde(deserialize_with = "to_pubkey")]
@@ -19,4 +19,13 @@ pub enum ConfigError {
 
     #[error("Invalid upload method '{0}'")]
     InvalidUploadMethod(String),
+
+    #[error("Invalid proxy '{0}' (supported schemes: http, https, socks5, socks5h)")]
+    InvalidProxy(String),
+
+    #[error("Invalid RPC url '{0}' (supported schemes: http, https)")]
+    InvalidRpcUrl(String),
+
+    #[error("Could not parse the asset overrides file '{0}' ({1})")]
+    InvalidAssetOverrides(String, String),
 }
@@ -0,0 +1,290 @@
+use regex::Regex;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{ffi::OsStr, path::Path};
+
+/// Format of a config file, detected from the extension of its name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl ConfigFormat {
+    /// Return the format of a config file. Files without a YAML or TOML
+    /// extension are read as JSON.
+    pub fn from_path(path: &str) -> Self {
+        match Path::new(path)
+            .extension()
+            .and_then(OsStr::to_str)
+            .map(str::to_lowercase)
+            .as_deref()
+        {
+            Some("yaml" | "yml") => ConfigFormat::Yaml,
+            Some("toml") => ConfigFormat::Toml,
+            _ => ConfigFormat::Json,
+        }
+    }
+
+    /// Parse the content of a config file, returning the message of the
+    /// parser on error.
+    pub fn parse<T: DeserializeOwned>(&self, content: &str) -> Result<T, String> {
+        match self {
+            ConfigFormat::Json => serde_json::from_str(content).map_err(|err| err.to_string()),
+            ConfigFormat::Yaml => serde_yaml::from_str(content).map_err(|err| err.to_string()),
+            ConfigFormat::Toml => toml::from_str(content).map_err(|err| err.to_string()),
+        }
+    }
+
+    /// Serialize a value into the content of a config file.
+    pub fn to_string_pretty<T: Serialize>(&self, value: &T) -> Result<String, String> {
+        match self {
+            ConfigFormat::Json => {
+                serde_json::to_string_pretty(value).map_err(|err| err.to_string())
+            }
+            ConfigFormat::Yaml => serde_yaml::to_string(value).map_err(|err| err.to_string()),
+            // going through a value writes the tables after the plain values,
+            // as TOML requires, whatever the order of the fields
+            ConfigFormat::Toml => toml::Value::try_from(value)
+                .and_then(|value| toml::to_string_pretty(&value))
+                .map_err(|err| err.to_string()),
+        }
+    }
+
+    /// Set a top-level number in the content of a config file, leaving the
+    /// other values (and comments) as they are.
+    pub fn set_number(&self, content: &str, key: &str, value: u64) -> String {
+        match self {
+            ConfigFormat::Json => {
+                if let Some(start) = find_json_key(content, key) {
+                    let entry = Regex::new(&format!(r#"^"{}"\s*:\s*[^,\n}}]*"#, key))
+                        .expect("Failed to create regex.");
+                    let end = entry
+                        .find(&content[start..])
+                        .map_or(content.len(), |entry| start + entry.end());
+
+                    return format!(
+                        r#"{}"{}": {}{}"#,
+                        &content[..start],
+                        key,
+                        value,
+                        &content[end..]
+                    );
+                }
+
+                // added as the last value of the object
+                let end = content.rfind('}').unwrap_or(content.len());
+                let body = content[..end].trim_end();
+                let separator = if body.ends_with('{') { "" } else { "," };
+                format!(
+                    "{}{}\n  \"{}\": {}\n{}",
+                    body,
+                    separator,
+                    key,
+                    value,
+                    &content[end..]
+                )
+            }
+            ConfigFormat::Yaml | ConfigFormat::Toml => {
+                let assign = if *self == ConfigFormat::Yaml {
+                    ":"
+                } else {
+                    " ="
+                };
+                let entry = Regex::new(&format!(
+                    r"(?m)^{}\s*{}[^#\n]*?(\s*#.*)?$",
+                    key,
+                    assign.trim()
+                ))
+                .expect("Failed to create regex.");
+
+                // top-level TOML values come before the first table
+                let end = match self {
+                    ConfigFormat::Toml => Regex::new(r"(?m)^\s*\[")
+                        .expect("Failed to create regex.")
+                        .find(content)
+                        .map_or(content.len(), |table| table.start()),
+                    _ => content.len(),
+                };
+                let (top, tables) = content.split_at(end);
+                let line = format!("{}{} {}", key, assign, value);
+
+                if entry.is_match(top) {
+                    let top = entry.replace(top, format!("{}${{1}}", line).as_str());
+                    format!("{}{}", top, tables)
+                } else if top.is_empty() || top.ends_with('\n') {
+                    format!("{}{}\n{}", top, line, tables)
+                } else {
+                    format!("{}\n{}\n{}", top, line, tables)
+                }
+            }
+        }
+    }
+}
+
+/// Return the position of a key of the top-level object of a JSON document,
+/// skipping the keys of nested objects and the content of strings.
+fn find_json_key(content: &str, key: &str) -> Option<usize> {
+    let quoted_key = format!("\"{}\"", key);
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (position, c) in content.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => (),
+            }
+            continue;
+        }
+
+        match c {
+            '"' => {
+                let rest = &content[position..];
+
+                // a key is followed by a colon, unlike a value
+                if depth == 1
+                    && rest.starts_with(&quoted_key)
+                    && rest[quoted_key.len()..].trim_start().starts_with(':')
+                {
+                    return Some(position);
+                }
+
+                in_string = true;
+            }
+            '{' | '[' => depth += 1,
+            '}' | ']' => depth -= 1,
+            _ => (),
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Settings {
+        number: u64,
+        symbol: String,
+        end_settings: Option<EndSettings>,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct EndSettings {
+        number: u64,
+    }
+
+    fn settings() -> Settings {
+        Settings {
+            number: 10,
+            symbol: "SGR".to_string(),
+            end_settings: Some(EndSettings { number: 5 }),
+        }
+    }
+
+    #[test]
+    fn format_from_path() {
+        assert_eq!(ConfigFormat::from_path("config.json"), ConfigFormat::Json);
+        assert_eq!(ConfigFormat::from_path("config.yaml"), ConfigFormat::Yaml);
+        assert_eq!(ConfigFormat::from_path("config.yml"), ConfigFormat::Yaml);
+        assert_eq!(ConfigFormat::from_path("config.TOML"), ConfigFormat::Toml);
+        assert_eq!(ConfigFormat::from_path("config"), ConfigFormat::Json);
+    }
+
+    #[test]
+    fn parse_each_format() {
+        let json = r#"{ "number": 10, "symbol": "SGR", "endSettings": { "number": 5 } }"#;
+        let yaml = "number: 10\nsymbol: SGR\nendSettings:\n  number: 5\n";
+        let toml = "number = 10\nsymbol = \"SGR\"\n\n[endSettings]\nnumber = 5\n";
+
+        assert_eq!(ConfigFormat::Json.parse::<Settings>(json), Ok(settings()));
+        assert_eq!(ConfigFormat::Yaml.parse::<Settings>(yaml), Ok(settings()));
+        assert_eq!(ConfigFormat::Toml.parse::<Settings>(toml), Ok(settings()));
+        assert!(ConfigFormat::Toml.parse::<Settings>(json).is_err());
+    }
+
+    #[test]
+    fn serialize_and_parse_each_format() {
+        for format in [ConfigFormat::Json, ConfigFormat::Yaml, ConfigFormat::Toml] {
+            let content = format.to_string_pretty(&settings()).unwrap();
+            assert_eq!(format.parse::<Settings>(&content), Ok(settings()));
+        }
+    }
+
+    #[test]
+    fn set_json_number() {
+        let content = "{\n  \"endSettings\": { \"number\": 5 },\n  \"name\": \"number\",\n  \"number\": 10,\n  \"symbol\": \"SGR\"\n}\n";
+        let patched = ConfigFormat::Json.set_number(content, "number", 20);
+
+        assert_eq!(
+            patched,
+            "{\n  \"endSettings\": { \"number\": 5 },\n  \"name\": \"number\",\n  \"number\": 20,\n  \"symbol\": \"SGR\"\n}\n"
+        );
+    }
+
+    #[test]
+    fn add_json_number() {
+        let content = "{\n  \"endSettings\": { \"number\": 5 }\n}\n";
+
+        assert_eq!(
+            ConfigFormat::Json.set_number(content, "number", 20),
+            "{\n  \"endSettings\": { \"number\": 5 },\n  \"number\": 20\n}\n"
+        );
+        assert_eq!(
+            ConfigFormat::Json.set_number("{}", "number", 20),
+            "{\n  \"number\": 20\n}"
+        );
+    }
+
+    #[test]
+    fn set_yaml_number() {
+        let content = "# number: 1\nendSettings:\n  number: 5\nnumber: 10 # items\nsymbol: SGR\n";
+
+        assert_eq!(
+            ConfigFormat::Yaml.set_number(content, "number", 20),
+            "# number: 1\nendSettings:\n  number: 5\nnumber: 20 # items\nsymbol: SGR\n"
+        );
+        assert_eq!(
+            ConfigFormat::Yaml.set_number("symbol: SGR", "number", 20),
+            "symbol: SGR\nnumber: 20\n"
+        );
+    }
+
+    #[test]
+    fn set_toml_number() {
+        let content =
+            "# number = 1\nnumber = 10 # items\nsymbol = \"SGR\"\n\n[endSettings]\nnumber = 5\n";
+
+        assert_eq!(
+            ConfigFormat::Toml.set_number(content, "number", 20),
+            "# number = 1\nnumber = 20 # items\nsymbol = \"SGR\"\n\n[endSettings]\nnumber = 5\n"
+        );
+    }
+
+    #[test]
+    fn add_toml_number_before_tables() {
+        let content = "symbol = \"SGR\"\n\n[endSettings]\nnumber = 5\n";
+        let patched = ConfigFormat::Toml.set_number(content, "number", 20);
+
+        assert_eq!(
+            patched,
+            "symbol = \"SGR\"\nnumber = 20\n\n[endSettings]\nnumber = 5\n"
+        );
+        assert_eq!(
+            ConfigFormat::Toml
+                .parse::<Settings>(&patched)
+                .unwrap()
+                .number,
+            20
+        );
+    }
+}
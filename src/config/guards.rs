@@ -0,0 +1,579 @@
+use anchor_client::solana_sdk::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey};
+use anyhow::{anyhow, Result};
+use data_encoding::HEXLOWER;
+use serde::{Deserialize, Serialize};
+
+use crate::config::data::{go_live_date_as_timestamp, price_as_lamports, to_pubkey, to_string};
+
+/// Maximum length of the label of a guard group.
+pub const MAX_GROUP_LABEL_LENGTH: usize = 6;
+
+/// Guards of the Candy Guard program. The default guards apply to every mint,
+/// while each group defines an alternative set of guards (e.g., a presale).
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CandyGuardData {
+    pub default: GuardSet,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub groups: Option<Vec<GuardGroup>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GuardGroup {
+    pub label: String,
+    pub guards: GuardSet,
+}
+
+/// Set of guards. The position of each guard in the struct is its position in
+/// the feature flags of the on-chain guard set, so new guards must be added
+/// in the order of the Candy Guard program.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GuardSet {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bot_tax: Option<BotTax>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sol_payment: Option<SolPayment>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_payment: Option<TokenPayment>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_date: Option<GuardDate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub third_party_signer: Option<ThirdPartySigner>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_gate: Option<TokenGate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gatekeeper: Option<Gatekeeper>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_date: Option<GuardDate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_list: Option<AllowList>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mint_limit: Option<MintLimit>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nft_payment: Option<NftPayment>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redeemed_amount: Option<RedeemedAmount>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address_gate: Option<AddressGate>,
+}
+
+/// Charge a penalty (in SOL) for invalid transactions instead of failing.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct BotTax {
+    pub value: f64,
+    pub last_instruction: bool,
+}
+
+/// Charge a price (in SOL) for each mint.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SolPayment {
+    pub value: f64,
+    #[serde(deserialize_with = "to_pubkey", serialize_with = "to_string")]
+    pub destination: Pubkey,
+}
+
+/// Charge an amount (in base units) of a token for each mint.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenPayment {
+    pub amount: u64,
+    #[serde(deserialize_with = "to_pubkey", serialize_with = "to_string")]
+    pub mint: Pubkey,
+    #[serde(deserialize_with = "to_pubkey", serialize_with = "to_string")]
+    pub destination_ata: Pubkey,
+}
+
+/// Date of the start or end date guards, in the formats of the go live date.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GuardDate {
+    pub date: String,
+}
+
+/// Require a signature of a third party on each mint.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ThirdPartySigner {
+    #[serde(deserialize_with = "to_pubkey", serialize_with = "to_string")]
+    pub signer_key: Pubkey,
+}
+
+/// Restrict the mint to holders of an amount of a token.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenGate {
+    pub amount: u64,
+    #[serde(deserialize_with = "to_pubkey", serialize_with = "to_string")]
+    pub mint: Pubkey,
+}
+
+/// Require a valid gateway token of a gatekeeper network.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Gatekeeper {
+    #[serde(deserialize_with = "to_pubkey", serialize_with = "to_string")]
+    pub gatekeeper_network: Pubkey,
+    pub expire_on_use: bool,
+}
+
+/// Restrict the mint to the wallets of an allow list, identified by the root
+/// (hex encoded) of its merkle tree.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AllowList {
+    pub merkle_root: String,
+}
+
+/// Limit the number of mints per wallet. Guards with the same id share the
+/// same counter.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MintLimit {
+    pub id: u8,
+    pub limit: u16,
+}
+
+/// Charge an NFT of a collection for each mint.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NftPayment {
+    #[serde(deserialize_with = "to_pubkey", serialize_with = "to_string")]
+    pub required_collection: Pubkey,
+    #[serde(deserialize_with = "to_pubkey", serialize_with = "to_string")]
+    pub destination: Pubkey,
+}
+
+/// End the mint once a number of items have been redeemed.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RedeemedAmount {
+    pub maximum: u64,
+}
+
+/// Restrict the mint to a single address.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AddressGate {
+    #[serde(deserialize_with = "to_pubkey", serialize_with = "to_string")]
+    pub address: Pubkey,
+}
+
+impl CandyGuardData {
+    /// Serialize the guards in the format of the Candy Guard program: the
+    /// default guard set followed by the number of groups and each group.
+    pub fn to_data(&self) -> Result<Vec<u8>> {
+        let mut data = self.default.to_data()?;
+        let groups = self.groups.as_deref().unwrap_or_default();
+
+        data.extend_from_slice(&(groups.len() as u32).to_le_bytes());
+
+        for group in groups {
+            if group.label.is_empty() || group.label.len() > MAX_GROUP_LABEL_LENGTH {
+                return Err(anyhow!(
+                    "Invalid guard group label '{}', it must have 1 to {} characters",
+                    group.label,
+                    MAX_GROUP_LABEL_LENGTH
+                ));
+            }
+
+            let mut label = [0u8; MAX_GROUP_LABEL_LENGTH];
+            label[..group.label.len()].copy_from_slice(group.label.as_bytes());
+
+            data.extend_from_slice(&label);
+            data.extend_from_slice(&group.guards.to_data()?);
+        }
+
+        Ok(data)
+    }
+
+    /// Deserialize the guards from the data of a candy guard account.
+    pub fn from_data(data: &[u8]) -> Result<Self> {
+        let mut reader = GuardReader(data);
+        let default = GuardSet::read(&mut reader)?;
+        let count = u32::from_le_bytes(reader.take(4)?.try_into()?);
+        let mut groups = Vec::with_capacity(count as usize);
+
+        for _ in 0..count {
+            let label = String::from_utf8(reader.take(MAX_GROUP_LABEL_LENGTH)?.to_vec())?
+                .trim_end_matches(char::from(0))
+                .to_string();
+            let guards = GuardSet::read(&mut reader)?;
+            groups.push(GuardGroup { label, guards });
+        }
+
+        Ok(CandyGuardData {
+            default,
+            groups: if groups.is_empty() {
+                None
+            } else {
+                Some(groups)
+            },
+        })
+    }
+}
+
+impl GuardSet {
+    /// Serialize the guard set: the feature flags of the enabled guards
+    /// followed by the data of each of them.
+    pub fn to_data(&self) -> Result<Vec<u8>> {
+        let mut features = 0u64;
+        let mut data = Vec::new();
+        let mut enable = |index: u64, guard: Vec<u8>| {
+            features |= 1 << index;
+            data.extend(guard);
+        };
+
+        if let Some(guard) = &self.bot_tax {
+            let mut bytes = price_as_lamports(guard.value).to_le_bytes().to_vec();
+            bytes.push(guard.last_instruction as u8);
+            enable(0, bytes);
+        }
+        if let Some(guard) = &self.sol_payment {
+            let mut bytes = price_as_lamports(guard.value).to_le_bytes().to_vec();
+            bytes.extend_from_slice(guard.destination.as_ref());
+            enable(1, bytes);
+        }
+        if let Some(guard) = &self.token_payment {
+            let mut bytes = guard.amount.to_le_bytes().to_vec();
+            bytes.extend_from_slice(guard.mint.as_ref());
+            bytes.extend_from_slice(guard.destination_ata.as_ref());
+            enable(2, bytes);
+        }
+        if let Some(guard) = &self.start_date {
+            enable(
+                3,
+                go_live_date_as_timestamp(&guard.date)?
+                    .to_le_bytes()
+                    .to_vec(),
+            );
+        }
+        if let Some(guard) = &self.third_party_signer {
+            enable(4, guard.signer_key.to_bytes().to_vec());
+        }
+        if let Some(guard) = &self.token_gate {
+            let mut bytes = guard.amount.to_le_bytes().to_vec();
+            bytes.extend_from_slice(guard.mint.as_ref());
+            enable(5, bytes);
+        }
+        if let Some(guard) = &self.gatekeeper {
+            let mut bytes = guard.gatekeeper_network.to_bytes().to_vec();
+            bytes.push(guard.expire_on_use as u8);
+            enable(6, bytes);
+        }
+        if let Some(guard) = &self.end_date {
+            enable(
+                7,
+                go_live_date_as_timestamp(&guard.date)?
+                    .to_le_bytes()
+                    .to_vec(),
+            );
+        }
+        if let Some(guard) = &self.allow_list {
+            let root = HEXLOWER
+                .decode(guard.merkle_root.to_lowercase().as_bytes())
+                .ok()
+                .filter(|root| root.len() == 32)
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Invalid allow list merkle root '{}', expected 32 hex encoded bytes",
+                        guard.merkle_root
+                    )
+                })?;
+            enable(8, root);
+        }
+        if let Some(guard) = &self.mint_limit {
+            let mut bytes = vec![guard.id];
+            bytes.extend_from_slice(&guard.limit.to_le_bytes());
+            enable(9, bytes);
+        }
+        if let Some(guard) = &self.nft_payment {
+            let mut bytes = guard.required_collection.to_bytes().to_vec();
+            bytes.extend_from_slice(guard.destination.as_ref());
+            enable(10, bytes);
+        }
+        if let Some(guard) = &self.redeemed_amount {
+            enable(11, guard.maximum.to_le_bytes().to_vec());
+        }
+        if let Some(guard) = &self.address_gate {
+            enable(12, guard.address.to_bytes().to_vec());
+        }
+
+        let mut bytes = features.to_le_bytes().to_vec();
+        bytes.extend(data);
+
+        Ok(bytes)
+    }
+
+    fn read(reader: &mut GuardReader) -> Result<Self> {
+        let features = u64::from_le_bytes(reader.take(8)?.try_into()?);
+
+        // the size of guards unknown to Sugar cannot be determined
+        if features >> 13 != 0 {
+            return Err(anyhow!(
+                "The guard set uses guards not supported by Sugar (flags {:#x})",
+                features
+            ));
+        }
+
+        let enabled = |index: u64| features & (1 << index) != 0;
+        let mut guards = GuardSet::default();
+
+        if enabled(0) {
+            guards.bot_tax = Some(BotTax {
+                value: reader.u64()? as f64 / LAMPORTS_PER_SOL as f64,
+                last_instruction: reader.bool()?,
+            });
+        }
+        if enabled(1) {
+            guards.sol_payment = Some(SolPayment {
+                value: reader.u64()? as f64 / LAMPORTS_PER_SOL as f64,
+                destination: reader.pubkey()?,
+            });
+        }
+        if enabled(2) {
+            guards.token_payment = Some(TokenPayment {
+                amount: reader.u64()?,
+                mint: reader.pubkey()?,
+                destination_ata: reader.pubkey()?,
+            });
+        }
+        if enabled(3) {
+            guards.start_date = Some(GuardDate {
+                date: reader.date()?,
+            });
+        }
+        if enabled(4) {
+            guards.third_party_signer = Some(ThirdPartySigner {
+                signer_key: reader.pubkey()?,
+            });
+        }
+        if enabled(5) {
+            guards.token_gate = Some(TokenGate {
+                amount: reader.u64()?,
+                mint: reader.pubkey()?,
+            });
+        }
+        if enabled(6) {
+            guards.gatekeeper = Some(Gatekeeper {
+                gatekeeper_network: reader.pubkey()?,
+                expire_on_use: reader.bool()?,
+            });
+        }
+        if enabled(7) {
+            guards.end_date = Some(GuardDate {
+                date: reader.date()?,
+            });
+        }
+        if enabled(8) {
+            guards.allow_list = Some(AllowList {
+                merkle_root: HEXLOWER.encode(reader.take(32)?),
+            });
+        }
+        if enabled(9) {
+            guards.mint_limit = Some(MintLimit {
+                id: reader.take(1)?[0],
+                limit: u16::from_le_bytes(reader.take(2)?.try_into()?),
+            });
+        }
+        if enabled(10) {
+            guards.nft_payment = Some(NftPayment {
+                required_collection: reader.pubkey()?,
+                destination: reader.pubkey()?,
+            });
+        }
+        if enabled(11) {
+            guards.redeemed_amount = Some(RedeemedAmount {
+                maximum: reader.u64()?,
+            });
+        }
+        if enabled(12) {
+            guards.address_gate = Some(AddressGate {
+                address: reader.pubkey()?,
+            });
+        }
+
+        Ok(guards)
+    }
+}
+
+/// Reader of the (borsh encoded) guard data.
+struct GuardReader<'a>(&'a [u8]);
+
+impl<'a> GuardReader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8]> {
+        if self.0.len() < length {
+            return Err(anyhow!("Invalid candy guard data"));
+        }
+
+        let (value, rest) = self.0.split_at(length);
+        self.0 = rest;
+
+        Ok(value)
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into()?))
+    }
+
+    fn bool(&mut self) -> Result<bool> {
+        Ok(self.take(1)?[0] != 0)
+    }
+
+    fn pubkey(&mut self) -> Result<Pubkey> {
+        Ok(Pubkey::new(self.take(32)?))
+    }
+
+    fn date(&mut self) -> Result<String> {
+        let timestamp = i64::from_le_bytes(self.take(8)?.try_into()?);
+
+        Ok(chrono::NaiveDateTime::from_timestamp_opt(timestamp, 0)
+            .map(|date| format!("{}Z", date.format("%Y-%m-%dT%H:%M:%S")))
+            .unwrap_or_else(|| timestamp.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pubkey(byte: u8) -> Pubkey {
+        Pubkey::new_from_array([byte; 32])
+    }
+
+    fn all_guards() -> GuardSet {
+        GuardSet {
+            bot_tax: Some(BotTax {
+                value: 0.01,
+                last_instruction: true,
+            }),
+            sol_payment: Some(SolPayment {
+                value: 1.5,
+                destination: pubkey(1),
+            }),
+            token_payment: Some(TokenPayment {
+                amount: 5,
+                mint: pubkey(2),
+                destination_ata: pubkey(3),
+            }),
+            start_date: Some(GuardDate {
+                date: "2023-01-01T00:00:00Z".to_string(),
+            }),
+            third_party_signer: Some(ThirdPartySigner {
+                signer_key: pubkey(4),
+            }),
+            token_gate: Some(TokenGate {
+                amount: 1,
+                mint: pubkey(5),
+            }),
+            gatekeeper: Some(Gatekeeper {
+                gatekeeper_network: pubkey(6),
+                expire_on_use: false,
+            }),
+            end_date: Some(GuardDate {
+                date: "2023-02-01T12:30:00Z".to_string(),
+            }),
+            allow_list: Some(AllowList {
+                merkle_root: "07".repeat(32),
+            }),
+            mint_limit: Some(MintLimit { id: 1, limit: 3 }),
+            nft_payment: Some(NftPayment {
+                required_collection: pubkey(8),
+                destination: pubkey(9),
+            }),
+            redeemed_amount: Some(RedeemedAmount { maximum: 100 }),
+            address_gate: Some(AddressGate {
+                address: pubkey(10),
+            }),
+        }
+    }
+
+    #[test]
+    fn roundtrip() {
+        let guards = CandyGuardData {
+            default: all_guards(),
+            groups: Some(vec![
+                GuardGroup {
+                    label: "wl".to_string(),
+                    guards: GuardSet {
+                        allow_list: Some(AllowList {
+                            merkle_root: "ab".repeat(32),
+                        }),
+                        ..GuardSet::default()
+                    },
+                },
+                GuardGroup {
+                    label: "public".to_string(),
+                    guards: GuardSet::default(),
+                },
+            ]),
+        };
+
+        let data = guards.to_data().unwrap();
+        assert_eq!(CandyGuardData::from_data(&data).unwrap(), guards);
+
+        let empty = CandyGuardData::default();
+        assert_eq!(empty.to_data().unwrap(), [0u8; 12]);
+        assert_eq!(CandyGuardData::from_data(&[0u8; 12]).unwrap(), empty);
+    }
+
+    #[test]
+    fn guard_order() {
+        let data = all_guards().to_data().unwrap();
+        assert_eq!(u64::from_le_bytes(data[..8].try_into().unwrap()), 0x1fff);
+
+        // the data of each guard follows the feature flags in their order:
+        // bot tax, then sol payment
+        let guards = GuardSet {
+            sol_payment: Some(SolPayment {
+                value: 1.0,
+                destination: pubkey(1),
+            }),
+            bot_tax: Some(BotTax {
+                value: 0.01,
+                last_instruction: false,
+            }),
+            ..GuardSet::default()
+        };
+        let data = guards.to_data().unwrap();
+
+        assert_eq!(data[..8], 0b11u64.to_le_bytes());
+        assert_eq!(data[8..16], 10_000_000u64.to_le_bytes());
+        assert_eq!(data[16], 0);
+        assert_eq!(data[17..25], 1_000_000_000u64.to_le_bytes());
+        assert_eq!(data[25..57], [1u8; 32]);
+        assert_eq!(data.len(), 57);
+    }
+
+    #[test]
+    fn invalid_guards() {
+        let long_label = CandyGuardData {
+            default: GuardSet::default(),
+            groups: Some(vec![GuardGroup {
+                label: "presale".to_string(),
+                guards: GuardSet::default(),
+            }]),
+        };
+        assert!(long_label.to_data().is_err());
+
+        let invalid_root = GuardSet {
+            allow_list: Some(AllowList {
+                merkle_root: "00".repeat(31),
+            }),
+            ..GuardSet::default()
+        };
+        assert!(invalid_root.to_data().is_err());
+
+        // guards unknown to Sugar
+        let mut data = (1u64 << 13).to_le_bytes().to_vec();
+        data.extend_from_slice(&[0u8; 4]);
+        assert!(CandyGuardData::from_data(&data).is_err());
+
+        // truncated guard data
+        let data = all_guards().to_data().unwrap();
+        assert!(CandyGuardData::from_data(&data[..data.len() - 1]).is_err());
+    }
+}
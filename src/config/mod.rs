@@ -1,7 +1,11 @@
 pub mod data;
 pub mod errors;
+pub mod format;
+pub mod guards;
 pub mod parser;
 
 pub use data::*;
 pub use errors::*;
+pub use format::*;
+pub use guards::*;
 pub use parser::*;
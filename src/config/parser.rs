@@ -1,19 +1,47 @@
-use anyhow::Result;
-use std::fs::metadata;
-use std::fs::OpenOptions;
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::fs;
 use std::io::ErrorKind;
+use std::path::Path;
 use tracing::error;
 
 use crate::config::data::*;
 use crate::config::errors::ConfigError;
+use crate::config::format::ConfigFormat;
+use crate::constants::{DEFAULT_CONFIG, DEFAULT_CONFIG_ALTERNATIVES};
+use crate::retry::configure_retry;
+use crate::rpc::add_endpoints;
+use crate::setup::{check_proxy, set_proxy};
 
+/// Return the path of the config file to use. When the default config file
+/// does not exist, its YAML or TOML alternative is used instead.
+pub fn resolve_config_path(config_path: &str) -> String {
+    if config_path != DEFAULT_CONFIG || Path::new(config_path).exists() {
+        return config_path.to_string();
+    }
+
+    DEFAULT_CONFIG_ALTERNATIVES
+        .iter()
+        .find(|alternative| Path::new(alternative).is_file())
+        .unwrap_or(&DEFAULT_CONFIG)
+        .to_string()
+}
+
+/// Load a config file in JSON, YAML or TOML format, detected from the
+/// extension of its name.
 pub fn get_config_data(config_path: &str) -> Result<ConfigData, ConfigError> {
+    let config_path = &resolve_config_path(config_path);
+
     // checks that the config file exists and it is readable
-    let f = match OpenOptions::new().read(true).open(config_path) {
-        Ok(f) => f,
+    let content = match fs::read_to_string(config_path) {
+        Ok(content) => content,
         Err(err) => {
             let error = match err.kind() {
                 ErrorKind::NotFound => ConfigError::MissingFileError(config_path.to_string()),
+                // the config must be a file and not a directory
+                _ if Path::new(config_path).is_dir() => {
+                    ConfigError::InvalidPathError(config_path.to_string())
+                }
                 _ => ConfigError::PermissionError(config_path.to_string()),
             };
 
@@ -21,20 +49,190 @@ pub fn get_config_data(config_path: &str) -> Result<ConfigData, ConfigError> {
             return Err(error);
         }
     };
-    // checks that the config is a file and not a directory
-    if metadata(config_path).unwrap().is_dir() {
-        let error = ConfigError::InvalidPathError(config_path.to_string());
-        error!("{:?}", error);
-        return Err(error);
-    }
 
-    let config_data: ConfigData = match serde_json::from_reader(f) {
+    let mut config_data: ConfigData = match ConfigFormat::from_path(config_path).parse(&content) {
         Ok(config_data) => config_data,
         Err(err) => {
-            let error = ConfigError::ParseError(err.to_string());
+            let error = ConfigError::ParseError(err);
             error!("{:?}", error);
             return Err(error);
         }
     };
+
+    config_data.apply_upload_settings();
+    validate_config_data(&config_data)?;
+
+    if let Some(proxy) = &config_data.proxy {
+        set_proxy(proxy)?;
+    }
+
+    if let Some(retry) = &config_data.retry {
+        configure_retry(retry);
+    }
+
+    if let Some(rpc_urls) = &config_data.rpc_urls {
+        add_endpoints(rpc_urls);
+    }
+
     Ok(config_data)
 }
+
+/// Check the settings of a config that are not enforced by its format,
+/// without applying them.
+pub fn validate_config_data(config_data: &ConfigData) -> Result<(), ConfigError> {
+    if let Some(proxy) = &config_data.proxy {
+        check_proxy(proxy)?;
+    }
+
+    if let Some(rpc_urls) = &config_data.rpc_urls {
+        for url in rpc_urls {
+            if !(url.starts_with("http://") || url.starts_with("https://")) {
+                let error = ConfigError::InvalidRpcUrl(url.to_string());
+                error!("{:?}", error);
+                return Err(error);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Set a top-level number of a config file, leaving the rest of the file
+/// unchanged. The file is read as written, without applying its upload
+/// settings.
+pub fn set_config_number(config_path: &str, key: &str, value: u64) -> Result<()> {
+    let format = ConfigFormat::from_path(config_path);
+    let content = fs::read_to_string(config_path)?;
+    let content = format.set_number(&content, key, value);
+
+    // the file must still be a valid config after the change
+    format
+        .parse::<ConfigData>(&content)
+        .map_err(|err| anyhow!(ConfigError::ParseError(err)))?;
+    fs::write(config_path, content)?;
+
+    Ok(())
+}
+
+/// Load the per-asset overrides file, mapping the asset index to its name
+/// and symbol overrides. Returns an empty map if no file is configured.
+pub fn get_asset_overrides(
+    config_data: &ConfigData,
+) -> Result<HashMap<usize, AssetOverride>, ConfigError> {
+    let path = match &config_data.asset_overrides {
+        Some(path) => path,
+        None => return Ok(HashMap::new()),
+    };
+
+    let content = fs::read_to_string(path).map_err(|err| {
+        let error = ConfigError::InvalidAssetOverrides(path.to_string(), err.to_string());
+        error!("{:?}", error);
+        error
+    })?;
+
+    serde_json::from_str::<HashMap<usize, AssetOverride>>(&content).map_err(|err| {
+        let error = ConfigError::InvalidAssetOverrides(path.to_string(), err.to_string());
+        error!("{:?}", error);
+        error
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JSON_CONFIG: &str = r#"{
+  "price": 1.0,
+  "endSettings": { "endSettingType": "Amount", "number": 5 },
+  "number": 10,
+  "symbol": "SGR",
+  "sellerFeeBasisPoints": 500,
+  "goLiveDate": "2022-01-01 00:00:00 +0000",
+  "creators": [],
+  "uploadMethod": "bundlr",
+  "retainAuthority": true,
+  "isMutable": true
+}
+"#;
+
+    const YAML_CONFIG: &str = "price: 1.0
+endSettings:
+  endSettingType: Amount
+  number: 5
+# number: 1
+number: 10 # items
+symbol: SGR
+sellerFeeBasisPoints: 500
+goLiveDate: \"2022-01-01 00:00:00 +0000\"
+creators: []
+uploadMethod: bundlr
+retainAuthority: true
+isMutable: true
+";
+
+    const TOML_CONFIG: &str = "price = 1.0
+# number = 1
+number = 10
+symbol = \"SGR\"
+sellerFeeBasisPoints = 500
+goLiveDate = \"2022-01-01 00:00:00 +0000\"
+creators = []
+uploadMethod = \"bundlr\"
+retainAuthority = true
+isMutable = true
+
+[endSettings]
+endSettingType = \"Amount\"
+number = 5
+";
+
+    fn temp_config(name: &str, content: &str) -> String {
+        let path =
+            std::env::temp_dir().join(format!("sugar-config-{}-{}", std::process::id(), name));
+        fs::write(&path, content).unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    fn set_numbers(name: &str, content: &str) -> ConfigData {
+        let path = temp_config(name, content);
+        set_config_number(&path, "number", 20).unwrap();
+        set_config_number(&path, "priorityFee", 1000).unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).ok();
+
+        ConfigFormat::from_path(&path).parse(&content).unwrap()
+    }
+
+    #[test]
+    fn set_top_level_numbers() {
+        for (name, content) in [
+            ("config.json", JSON_CONFIG),
+            ("config.yaml", YAML_CONFIG),
+            ("config.toml", TOML_CONFIG),
+        ] {
+            let config_data = set_numbers(name, content);
+
+            assert_eq!(config_data.number, 20, "{}", name);
+            assert_eq!(config_data.priority_fee, Some(1000), "{}", name);
+            assert_eq!(
+                config_data.end_settings,
+                Some(EndSettings::new(EndSettingType::Amount, 5)),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_number_is_not_written() {
+        let path = temp_config("invalid.json", JSON_CONFIG);
+        let result = set_config_number(&path, "sellerFeeBasisPoints", 70000);
+
+        let content = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).ok();
+
+        assert!(result.is_err());
+        assert_eq!(content, JSON_CONFIG);
+    }
+}
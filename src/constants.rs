@@ -57,6 +57,29 @@ pub const MINT_LAYOUT: u64 = 82;
 /// and network connections).
 pub const PARALLEL_LIMIT: usize = 45;
 
+/// Maximum number of accounts per `getMultipleAccounts` request.
+pub const MAX_ACCOUNTS_PER_REQUEST: usize = 100;
+
+/// Time (in seconds) a command has to stop after its deadline is reached before
+/// it is terminated.
+pub const DEADLINE_GRACE_PERIOD: u64 = 30;
+
+/// Time (in seconds) an account read is reused within a command run.
+pub const RPC_CACHE_TTL: u64 = 5;
+
+/// Time (in seconds) a fetched blockhash is reused for new transactions.
+pub const BLOCKHASH_REUSE_WINDOW: u64 = 20;
+
+/// Version of the cache file format.
+pub const CACHE_VERSION: u8 = 2;
+
+/// Minimum interval (in seconds) between two cache checkpoints.
+pub const CACHE_CHECKPOINT_INTERVAL: u64 = 5;
+
+/// Number of modified cache items that forces a checkpoint, independently of
+/// the checkpoint interval.
+pub const CACHE_CHECKPOINT_ITEMS: usize = 1000;
+
 /// Default path for assets folder.
 pub const DEFAULT_ASSETS: &str = "assets";
 
@@ -66,6 +89,60 @@ pub const DEFAULT_CACHE: &str = "cache.json";
 /// Default path for config file.
 pub const DEFAULT_CONFIG: &str = "config.json";
 
+/// Config files used, in order, when the default config file does not exist.
+pub const DEFAULT_CONFIG_ALTERNATIVES: [&str; 3] = ["config.yaml", "config.yml", "config.toml"];
+
+/// Default path for the preview gallery file.
+pub const DEFAULT_PREVIEW: &str = "preview.html";
+
+/// Default port for the preview gallery server.
+pub const DEFAULT_PREVIEW_PORT: &str = "8080";
+
+/// Default path for the airdrop state file.
+pub const DEFAULT_AIRDROP_STATE: &str = "airdrop-state.json";
+
+/// Default number of parallel airdrop transactions.
+pub const DEFAULT_AIRDROP_PARALLEL: &str = "1";
+
+/// Default number of parallel mint transactions.
+pub const DEFAULT_MINT_PARALLEL: &str = "1";
+
+/// Default freeze period (in days) of the minted NFTs.
+pub const DEFAULT_FREEZE_DAYS: &str = "14";
+
+/// Default path for the distribution state file.
+pub const DEFAULT_DISTRIBUTION_STATE: &str = "distribution.json";
+
+/// Suffix of the progress journal file, kept next to the cache file.
+pub const JOURNAL_SUFFIX: &str = ".journal.json";
+
+/// Default interval (in seconds) between candy machine monitor checks.
+pub const DEFAULT_MONITOR_INTERVAL: &str = "10";
+
+/// Default RPC url of a local validator.
+pub const DEFAULT_RPC_LOCAL: &str = "http://127.0.0.1:8899";
+
+/// Default ledger directory of the local test environment.
+pub const DEFAULT_TEST_LEDGER: &str = "test-ledger";
+
+/// Default keypair file of the local test environment.
+pub const DEFAULT_TEST_KEYPAIR: &str = "test-keypair.json";
+
+/// Default path for the allow list proofs file.
+pub const DEFAULT_ALLOWLIST_PROOFS: &str = "allowlist-proofs.json";
+
+/// Default path for the block list file.
+pub const DEFAULT_BLOCKLIST: &str = "blocklist.txt";
+
+/// Default path for the signed operation manifest.
+pub const DEFAULT_MANIFEST: &str = "manifest.json";
+
+/// Default path for the multisig transaction proposal.
+pub const DEFAULT_PROPOSAL: &str = "proposal.json";
+
+/// Default path for the shared creator signing progress file.
+pub const DEFAULT_SIGN_PROGRESS: &str = "sign-progress.json";
+
 /// Default path for keypair file.
 pub const DEFAULT_KEYPATH: &str = "~/.config/solana/id.json";
 
@@ -105,3 +182,5 @@ pub const COMPLETE_EMOJI: Emoji<'_, '_> = Emoji("✅ ", "");
 pub const LAUNCH_EMOJI: Emoji<'_, '_> = Emoji("💃 ", "");
 
 pub const ERROR_EMOJI: Emoji<'_, '_> = Emoji("🛑 ", "");
+
+pub const SIGNING_EMOJI: Emoji<'_, '_> = Emoji("✍️  ", "");
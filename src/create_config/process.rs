@@ -7,6 +7,7 @@ use dialoguer::{theme::ColorfulTheme, Input, MultiSelect, Select};
 use std::{
     default::Default,
     fs::{File, OpenOptions},
+    io::Write,
     path::{Path, PathBuf},
     str::FromStr,
     sync::Arc,
@@ -14,11 +15,15 @@ use std::{
 use url::Url;
 
 use crate::candy_machine::ID as CANDY_MACHINE_ID;
+use crate::cli::ConfigAction;
 use crate::config::{
-    parse_string_as_date, ConfigData, Creator, EndSettingType, EndSettings, GatekeeperConfig,
-    HiddenSettings, UploadMethod, WhitelistMintMode, WhitelistMintSettings,
+    parse_string_as_date, validate_config_data, ConfigData, ConfigError, ConfigFormat, Creator,
+    EndSettingType, EndSettings, GatekeeperConfig, HiddenSettings, UploadMethod, WhitelistMintMode,
+    WhitelistMintSettings,
 };
 use crate::constants::*;
+use crate::credentials::{NFT_STORAGE_TOKEN, PINATA_JWT};
+use crate::interaction::ensure_interactive;
 use crate::setup::{setup_client, sugar_setup};
 use crate::upload::list_files;
 use crate::utils::{check_spl_token, check_spl_token_account};
@@ -40,7 +45,29 @@ pub struct CreateConfigArgs {
     pub assets_dir: String,
 }
 
+pub struct ConfigArgs {
+    pub action: ConfigAction,
+}
+
+pub fn process_config(args: ConfigArgs) -> Result<()> {
+    match args.action {
+        ConfigAction::Create {
+            config,
+            keypair,
+            rpc_url,
+            assets_dir,
+        } => process_create_config(CreateConfigArgs {
+            keypair,
+            rpc_url,
+            config,
+            assets_dir,
+        }),
+    }
+}
+
 pub fn process_create_config(args: CreateConfigArgs) -> Result<()> {
+    ensure_interactive("the config values")?;
+
     let mut config_data: ConfigData = ConfigData::default();
     let theme = ColorfulTheme {
         prompt_style: Style::new(),
@@ -298,7 +325,7 @@ pub fn process_create_config(args: CreateConfigArgs) -> Result<()> {
                 |input: &String| -> Result<(), &str> {
                     if input.parse::<u8>().unwrap() + total_share > 100 {
                         Err("Royalty share total has exceeded 100 percent.")
-                    } else if i == num_creators && input.parse::<u8>().unwrap() + total_share != 100 {
+                    } else if i == num_creators - 1 && input.parse::<u8>().unwrap() + total_share != 100 {
                         Err("Royalty share for all creators must total 100 percent.")
                     } else {
                         Ok(())
@@ -554,7 +581,14 @@ pub fn process_create_config(args: CreateConfigArgs) -> Result<()> {
 
     // upload method
 
-    let upload_options = vec!["Bundlr", "AWS"];
+    let upload_options = vec![
+        "Bundlr",
+        "AWS",
+        "Pinata",
+        "NFT.Storage",
+        "Shadow Drive",
+        "Arweave",
+    ];
     config_data.upload_method = match Select::with_theme(&theme)
         .with_prompt("What upload method do you want to use?")
         .items(&upload_options)
@@ -564,9 +598,32 @@ pub fn process_create_config(args: CreateConfigArgs) -> Result<()> {
     {
         0 => UploadMethod::Bundlr,
         1 => UploadMethod::AWS,
+        2 => UploadMethod::Pinata,
+        3 => UploadMethod::NftStorage,
+        4 => UploadMethod::ShadowDrive,
+        5 => UploadMethod::ArweaveNative,
         _ => UploadMethod::Bundlr,
     };
 
+    if config_data.upload_method == UploadMethod::Bundlr {
+        let node: String = Input::with_theme(&theme)
+            .with_prompt("What Bundlr node do you want to use? Leave empty for the default node.")
+            .allow_empty(true)
+            .validate_with(|input: &String| -> Result<(), String> {
+                if input.is_empty() || Url::parse(input).is_ok() {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "Couldn't parse input of '{}' to a valid uri.",
+                        input
+                    ))
+                }
+            })
+            .interact()
+            .unwrap();
+        config_data.bundlr_node = Some(node).filter(|node| !node.is_empty());
+    }
+
     if config_data.upload_method == UploadMethod::AWS {
         config_data.aws_s3_bucket = Some(
             Input::with_theme(&theme)
@@ -574,6 +631,78 @@ pub fn process_create_config(args: CreateConfigArgs) -> Result<()> {
                 .interact()
                 .unwrap(),
         );
+
+        let region: String = Input::with_theme(&theme)
+            .with_prompt(
+                "What is the AWS region of the bucket? Leave empty to use the AWS environment.",
+            )
+            .allow_empty(true)
+            .interact()
+            .unwrap();
+        config_data.aws_region = Some(region).filter(|region| !region.is_empty());
+
+        let prefix: String = Input::with_theme(&theme)
+            .with_prompt(
+                "What key prefix should the files be uploaded under? Leave empty for none.",
+            )
+            .allow_empty(true)
+            .interact()
+            .unwrap();
+        config_data.aws_key_prefix = Some(prefix).filter(|prefix| !prefix.is_empty());
+    }
+
+    if config_data.upload_method == UploadMethod::Pinata {
+        let jwt: String = Input::with_theme(&theme)
+            .with_prompt(format!(
+                "What is your Pinata JWT? Leave empty to read it from {} or a prompt.",
+                PINATA_JWT.env_var
+            ))
+            .allow_empty(true)
+            .interact()
+            .unwrap();
+        config_data.pinata_jwt = Some(jwt).filter(|jwt| !jwt.is_empty());
+    }
+
+    if config_data.upload_method == UploadMethod::NftStorage {
+        let token: String = Input::with_theme(&theme)
+            .with_prompt(format!(
+                "What is your NFT.Storage API token? Leave empty to read it from {} or a prompt.",
+                NFT_STORAGE_TOKEN.env_var
+            ))
+            .allow_empty(true)
+            .interact()
+            .unwrap();
+        config_data.nft_storage_token = Some(token).filter(|token| !token.is_empty());
+    }
+
+    if config_data.upload_method == UploadMethod::ShadowDrive {
+        config_data.shadow_drive_storage_account = Some(
+            Input::with_theme(&theme)
+                .with_prompt("What is the address of your Shadow Drive storage account?")
+                .validate_with(|input: &String| -> Result<(), String> {
+                    Pubkey::from_str(input)
+                        .map(|_| ())
+                        .map_err(|_| "Invalid storage account address.".to_string())
+                })
+                .interact()
+                .unwrap(),
+        );
+    }
+
+    if config_data.upload_method == UploadMethod::ArweaveNative {
+        config_data.arweave_jwk = Some(
+            Input::with_theme(&theme)
+                .with_prompt("What is the path to your Arweave wallet (JWK) file?")
+                .validate_with(|input: &String| -> Result<(), String> {
+                    if Path::new(input).is_file() {
+                        Ok(())
+                    } else {
+                        Err(format!("Couldn't find the file: {}", input))
+                    }
+                })
+                .interact()
+                .unwrap(),
+        );
     }
 
     // retain authority
@@ -612,6 +741,19 @@ pub fn process_create_config(args: CreateConfigArgs) -> Result<()> {
         println!();
     }
 
+    // the format of the config follows the extension of the file name
+    let format = ConfigFormat::from_path(&file_path);
+    let content = format
+        .to_string_pretty(&config_data)
+        .expect("Unable to convert config.");
+
+    // the config goes through the same checks as when it is used, before
+    // anything is written
+    format
+        .parse::<ConfigData>(&content)
+        .map_err(ConfigError::ParseError)?;
+    validate_config_data(&config_data)?;
+
     if save_file {
         let file = OpenOptions::new()
             .write(true)
@@ -620,13 +762,13 @@ pub fn process_create_config(args: CreateConfigArgs) -> Result<()> {
             .open(Path::new(&file_path));
 
         match file {
-            Ok(f) => {
+            Ok(mut f) => {
                 println!(
                     "{}",
                     style(format!("Saving config to file: \"{}\"\n", file_path))
                 );
-                serde_json::to_writer_pretty(f, &config_data)
-                    .expect("Unable to convert config to JSON!");
+                f.write_all(content.as_bytes())
+                    .expect("Unable to write config file!");
 
                 println!(
                     "{} {}",
@@ -644,22 +786,12 @@ pub fn process_create_config(args: CreateConfigArgs) -> Result<()> {
                         .bold()
                         .red()
                 );
-                println!(
-                    "{}",
-                    style(
-                        serde_json::to_string_pretty(&config_data)
-                            .expect("Unable to convert config to JSON.")
-                    )
-                    .red()
-                );
+                println!("{}", style(&content).red());
             }
         }
     } else {
         println!("{}\n", style("Logging config to console:").dim());
-        println!(
-            "{}",
-            serde_json::to_string_pretty(&config_data).expect("Unable to convert config to JSON.")
-        );
+        println!("{}", content);
     }
 
     Ok(())
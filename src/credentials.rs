@@ -0,0 +1,124 @@
+use console::user_attended;
+use dialoguer::{theme::ColorfulTheme, Password};
+use std::{
+    collections::HashMap,
+    env,
+    sync::{Mutex, OnceLock},
+};
+use thiserror::Error;
+
+use crate::common::*;
+use crate::interaction::is_interactive;
+
+/// Secrets entered at a prompt, reused for the rest of the command run.
+static PROMPTED: OnceLock<Mutex<HashMap<&'static str, String>>> = OnceLock::new();
+
+#[derive(Debug, Error)]
+pub enum CredentialError {
+    #[error("Missing {0}: set {1}")]
+    Missing(String, String),
+}
+
+/// Credential of an upload method. It is read from the config file, then
+/// from its environment variable and, as a last resort, from a hidden
+/// prompt when running in a terminal.
+pub struct Secret {
+    /// Description of the secret, used in prompts and errors.
+    pub name: &'static str,
+    /// Key of the secret in the config file, if it can be set there.
+    pub config_key: Option<&'static str>,
+    pub env_var: &'static str,
+}
+
+pub const PINATA_JWT: Secret = Secret {
+    name: "Pinata JWT",
+    config_key: Some("pinataJwt"),
+    env_var: "SUGAR_PINATA_JWT",
+};
+
+pub const PINATA_API_KEY: Secret = Secret {
+    name: "Pinata API key",
+    config_key: Some("pinataApiKey"),
+    env_var: "SUGAR_PINATA_API_KEY",
+};
+
+pub const PINATA_SECRET_API_KEY: Secret = Secret {
+    name: "Pinata secret API key",
+    config_key: Some("pinataSecretApiKey"),
+    env_var: "SUGAR_PINATA_SECRET_API_KEY",
+};
+
+pub const NFT_STORAGE_TOKEN: Secret = Secret {
+    name: "NFT.Storage API token",
+    config_key: Some("nftStorageToken"),
+    env_var: "SUGAR_NFT_STORAGE_TOKEN",
+};
+
+/// AWS credentials are read from the environment and shared profiles by the
+/// AWS SDK itself, so they are only prompted for.
+pub const AWS_ACCESS_KEY_ID: Secret = Secret {
+    name: "AWS access key id",
+    config_key: None,
+    env_var: "AWS_ACCESS_KEY_ID",
+};
+
+pub const AWS_SECRET_ACCESS_KEY: Secret = Secret {
+    name: "AWS secret access key",
+    config_key: None,
+    env_var: "AWS_SECRET_ACCESS_KEY",
+};
+
+impl Secret {
+    /// Return the secret from the config value or the environment, without
+    /// prompting for it.
+    pub fn lookup(&self, config_value: &Option<String>) -> Option<String> {
+        let non_empty = |value: &String| !value.is_empty();
+
+        config_value
+            .clone()
+            .filter(non_empty)
+            .or_else(|| env::var(self.env_var).ok().filter(non_empty))
+            .or_else(|| prompted().lock().unwrap().get(self.env_var).cloned())
+    }
+
+    /// Return the secret from the config value or the environment, prompting
+    /// for it if it is not set.
+    pub fn resolve(&self, config_value: &Option<String>) -> Result<String> {
+        match self.lookup(config_value) {
+            Some(value) => Ok(value),
+            None => self.prompt(),
+        }
+    }
+
+    /// Prompt for the secret, failing when there is no terminal to prompt on.
+    pub fn prompt(&self) -> Result<String> {
+        if !user_attended() || !is_interactive() {
+            return Err(CredentialError::Missing(self.name.to_string(), self.sources()).into());
+        }
+
+        let value = Password::with_theme(&ColorfulTheme::default())
+            .with_prompt(self.name)
+            .interact()?;
+        prompted()
+            .lock()
+            .unwrap()
+            .insert(self.env_var, value.clone());
+
+        Ok(value)
+    }
+
+    /// Describe where the secret can be set.
+    fn sources(&self) -> String {
+        match self.config_key {
+            Some(config_key) => format!(
+                "'{}' in the config file or the {} environment variable",
+                config_key, self.env_var
+            ),
+            None => format!("the {} environment variable", self.env_var),
+        }
+    }
+}
+
+fn prompted() -> &'static Mutex<HashMap<&'static str, String>> {
+    PROMPTED.get_or_init(|| Mutex::new(HashMap::new()))
+}
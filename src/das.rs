@@ -0,0 +1,176 @@
+use serde::Deserialize;
+
+use crate::common::*;
+
+/// Number of assets per page of a DAS request (maximum supported by the API).
+const DAS_PAGE_LIMIT: usize = 1000;
+
+/// JSON-RPC error code returned by nodes that do not support a method.
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// Client of the Digital Asset Standard (DAS) API, which indexes both regular
+/// and compressed NFTs. The API is served by the RPC node itself when supported.
+pub struct DasClient {
+    url: String,
+    http_client: HttpClient,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DasAsset {
+    pub id: String,
+    pub ownership: DasOwnership,
+    #[serde(default)]
+    pub compression: Option<DasCompression>,
+    #[serde(default)]
+    pub content: Option<DasContent>,
+    #[serde(default)]
+    pub creators: Vec<DasCreator>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DasOwnership {
+    pub owner: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DasCompression {
+    pub compressed: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DasContent {
+    #[serde(default)]
+    pub json_uri: String,
+    #[serde(default)]
+    pub metadata: Value,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DasCreator {
+    pub address: String,
+    pub verified: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct DasPage {
+    items: Vec<DasAsset>,
+}
+
+impl DasAsset {
+    pub fn is_compressed(&self) -> bool {
+        self.compression
+            .as_ref()
+            .map(|c| c.compressed)
+            .unwrap_or(false)
+    }
+}
+
+impl DasClient {
+    pub fn new(url: &str) -> DasClient {
+        DasClient {
+            url: url.to_string(),
+            http_client: http_client(),
+        }
+    }
+
+    /// Return a DAS client for the RPC url if the node supports the DAS API.
+    pub async fn connect(url: &str) -> Option<DasClient> {
+        let client = DasClient::new(url);
+
+        match client
+            .request::<DasPage>(
+                "getAssetsByCreator",
+                json!({
+                    "creatorAddress": Pubkey::default().to_string(),
+                    "page": 1,
+                    "limit": 1
+                }),
+            )
+            .await
+        {
+            Ok(_) => Some(client),
+            Err(err) => {
+                debug!("DAS API not available: {}", err);
+                None
+            }
+        }
+    }
+
+    /// Return all assets with the specified (verified) creator.
+    pub async fn get_assets_by_creator(&self, creator: &Pubkey) -> Result<Vec<DasAsset>> {
+        self.paginate(
+            "getAssetsByCreator",
+            json!({
+                "creatorAddress": creator.to_string(),
+                "onlyVerified": true,
+            }),
+        )
+        .await
+    }
+
+    /// Return all assets of the specified collection.
+    pub async fn get_assets_by_collection(&self, collection: &Pubkey) -> Result<Vec<DasAsset>> {
+        self.paginate(
+            "getAssetsByGroup",
+            json!({
+                "groupKey": "collection",
+                "groupValue": collection.to_string(),
+            }),
+        )
+        .await
+    }
+
+    async fn paginate(&self, method: &str, params: Value) -> Result<Vec<DasAsset>> {
+        let mut assets = Vec::new();
+        let mut page = 1;
+
+        loop {
+            let mut params = params.clone();
+            params["page"] = json!(page);
+            params["limit"] = json!(DAS_PAGE_LIMIT);
+
+            let result: DasPage = self.request(method, params).await?;
+            let count = result.items.len();
+            assets.extend(result.items);
+
+            if count < DAS_PAGE_LIMIT {
+                break;
+            }
+
+            page += 1;
+        }
+
+        Ok(assets)
+    }
+
+    async fn request<T: for<'de> Deserialize<'de>>(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> Result<T> {
+        let response: Value = self
+            .http_client
+            .post(&self.url)
+            .json(&json!({
+                "jsonrpc": "2.0",
+                "id": "sugar",
+                "method": method,
+                "params": params,
+            }))
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        if let Some(error) = response.get("error") {
+            let code = error["code"].as_i64().unwrap_or_default();
+            return if code == METHOD_NOT_FOUND {
+                Err(anyhow!("Method '{}' not supported by the RPC node", method))
+            } else {
+                Err(anyhow!("DAS request '{}' failed: {}", method, error))
+            };
+        }
+
+        Ok(serde_json::from_value(response["result"].clone())?)
+    }
+}
@@ -5,5 +5,9 @@ pub struct DeployArgs {
     pub cache: String,
     pub keypair: Option<String>,
     pub rpc_url: Option<String>,
+    pub force_rewrite: bool,
+    /// Allow fewer cache items than the number of items of the candy machine,
+    /// whose remaining config lines are added by later appends.
+    pub append: bool,
     pub interrupted: Arc<AtomicBool>,
 }
@@ -12,4 +12,6 @@ pub enum DeployError {
         "Your current wallet balance of {0} SOL is not enough. {1} SOL is needed to deploy the candy machine."
     )]
     BalanceTooLow(String, String),
+    #[error("Cannot rewrite config lines: {0} item(s) have already been minted")]
+    ItemsAlreadyRedeemed(u64),
 }
@@ -6,13 +6,16 @@ use anchor_client::solana_sdk::{
 use anchor_lang::prelude::AccountMeta;
 use anyhow::Result;
 use console::style;
+use data_encoding::HEXLOWER;
 use futures::future::select_all;
 use rand::rngs::OsRng;
+use ring::digest::{Context, SHA256};
 use solana_program::native_token::LAMPORTS_PER_SOL;
 use spl_associated_token_account::get_associated_token_address;
 use std::{
     cmp,
     collections::HashSet,
+    fs,
     str::FromStr,
     sync::{
         atomic::{AtomicBool, Ordering},
@@ -27,17 +30,34 @@ pub use mpl_token_metadata::state::{
     MAX_CREATOR_LIMIT, MAX_NAME_LENGTH, MAX_SYMBOL_LENGTH, MAX_URI_LENGTH,
 };
 
-use crate::candy_machine::uuid_from_pubkey;
 use crate::candy_machine::ID as CANDY_MACHINE_ID;
+use crate::candy_machine::{
+    get_candy_machine_state, scan_config_lines, uuid_from_pubkey, ConfigStatus,
+};
+use crate::collection::{set_candy_machine_collection, CollectionError};
 use crate::common::*;
-use crate::config::{data::*, parser::get_config_data};
+use crate::config::{
+    data::*,
+    parser::{get_config_data, resolve_config_path},
+};
 use crate::deploy::data::*;
 use crate::deploy::errors::*;
+use crate::events::{error_event, transaction_event};
+use crate::interaction::confirm;
+use crate::journal::{Journal, StageStatus};
+use crate::rpc_cache;
 use crate::setup::{setup_client, sugar_setup};
+use crate::spend::{record_rent, record_transaction, track_spend};
+use crate::transaction::send_with_retry;
 use crate::utils::*;
-use crate::validate::parser::{check_name, check_seller_fee_basis_points, check_symbol, check_url};
+use crate::validate::parser::{
+    check_name, check_seller_fee_basis_points, check_symbol, check_url, check_uses,
+};
 use crate::{cache::*, candy_machine::parse_config_price};
 
+/// Journal stage of the deploy command.
+const STAGE_DEPLOY: &str = "deploy";
+
 /// The maximum config line bytes per transaction.
 const MAX_TRANSACTION_BYTES: usize = 1000;
 
@@ -46,11 +66,38 @@ const MAX_TRANSACTION_LINES: usize = 17;
 
 struct TxInfo {
     candy_pubkey: Pubkey,
-    payer: Keypair,
+    payer: SugarSigner,
     chunk: Vec<(u32, ConfigLine)>,
+    priority_fee: Option<u64>,
 }
 
 pub async fn process_deploy(args: DeployArgs) -> Result<()> {
+    track_spend("deploy", &args.cache);
+
+    let mut journal = Journal::load_for_cache(&args.cache)?;
+
+    match journal.status(STAGE_DEPLOY) {
+        StageStatus::InProgress | StageStatus::Failed => {
+            info!(
+                "Resuming deploy, {} batch(es) previously completed",
+                journal.completed_batches(STAGE_DEPLOY)
+            );
+        }
+        _ => (),
+    }
+
+    journal.start(STAGE_DEPLOY)?;
+
+    match deploy(args, &mut journal).await {
+        Ok(()) => journal.complete(STAGE_DEPLOY),
+        Err(err) => {
+            journal.fail(STAGE_DEPLOY, &err.to_string())?;
+            Err(err)
+        }
+    }
+}
+
+async fn deploy(args: DeployArgs, journal: &mut Journal) -> Result<()> {
     // loads the cache file (this needs to have been created by
     // the upload command)
     let mut cache = load_cache(&args.cache, false)?;
@@ -95,7 +142,11 @@ pub async fn process_deploy(args: DeployArgs) -> Result<()> {
     let num_items = config_data.number;
     let hidden = config_data.hidden_settings.is_some();
 
-    if num_items != (cache.items.0.len() as u64) {
+    // when appending, the cache can have fewer items than the candy machine
+    // and the remaining config lines are added by later appends
+    let cache_items = cache.items.0.len() as u64;
+
+    if cache_items > num_items || ((hidden || !args.append) && cache_items != num_items) {
         return Err(anyhow!(
             "Number of items ({}) do not match cache items ({})",
             num_items,
@@ -104,8 +155,34 @@ pub async fn process_deploy(args: DeployArgs) -> Result<()> {
     } else {
         check_symbol(&config_data.symbol)?;
         check_seller_fee_basis_points(config_data.seller_fee_basis_points)?;
+
+        if let Some(uses) = &config_data.uses {
+            check_uses(uses)?;
+        }
     }
 
+    let fingerprint = deploy_fingerprint(&args.config, &cache)?;
+
+    // nothing to do if neither the config nor the config lines changed since
+    // the last deploy and the on-chain state still matches the cache
+    if !args.force_rewrite
+        && cache.program.deploy_fingerprint.as_ref() == Some(&fingerprint)
+        && is_up_to_date(&client, candy_machine_address, &cache, &config_data)?
+    {
+        println!(
+            "{} {}",
+            style("Candy machine ID:").bold(),
+            candy_machine_address
+        );
+        println!(
+            "
+Candy machine is up to date, nothing to deploy."
+        );
+        return Ok(());
+    }
+
+    let resuming = !candy_machine_address.is_empty();
+
     let candy_pubkey = if candy_machine_address.is_empty() {
         println!(
             "{} {}Creating candy machine",
@@ -163,15 +240,27 @@ pub async fn process_deploy(args: DeployArgs) -> Result<()> {
             candy_data,
             treasury_wallet,
             program,
+            &sugar_config.keypair,
         )?;
         info!("Candy machine initialized with sig: {}", sig);
+        transaction_event(
+            "deploy:initialize",
+            Some(&candy_pubkey.to_string()),
+            &sig.to_string(),
+            1,
+            1,
+        );
         info!(
             "Candy machine created with address: {}",
             &candy_pubkey.to_string()
         );
 
-        cache.program = CacheProgram::new_from_cm(&candy_pubkey);
+        // keeps the upload information of the cache program
+        let new_program = CacheProgram::new_from_cm(&candy_pubkey);
+        cache.program.candy_machine = new_program.candy_machine;
+        cache.program.candy_machine_creator = new_program.candy_machine_creator;
         cache.sync_file()?;
+        journal.complete_batch(STAGE_DEPLOY, "initialize")?;
 
         spinner.finish_and_clear();
 
@@ -200,6 +289,20 @@ pub async fn process_deploy(args: DeployArgs) -> Result<()> {
 
     println!("{} {}", style("Candy machine ID:").bold(), candy_pubkey);
 
+    // also retried by a resumed deploy, since the candy machine is recorded
+    // in the cache before its collection is set
+    if let Some(collection_mint) = &cache.program.collection_mint {
+        let collection_mint = Pubkey::from_str(collection_mint)
+            .map_err(|_| CollectionError::InvalidMint(collection_mint.clone()))?;
+
+        if let Some(signature) =
+            set_candy_machine_collection(&sugar_config, &candy_pubkey, &collection_mint)?
+        {
+            info!("Collection {} set with sig: {}", collection_mint, signature);
+            println!("{} {}", style("Collection mint:").bold(), collection_mint);
+        }
+    }
+
     if !hidden {
         println!(
             "\n{} {}Writing config lines",
@@ -207,7 +310,34 @@ pub async fn process_deploy(args: DeployArgs) -> Result<()> {
             PAPER_EMOJI
         );
 
-        let config_lines = generate_config_lines(num_items, &cache.items)?;
+        if args.force_rewrite {
+            if !confirm_rewrite(&sugar_config, &candy_pubkey, &mut cache)? {
+                println!("\n{}", style("Rewrite aborted.").red().bold().dim());
+                return Ok(());
+            }
+        } else if resuming {
+            // a previous deploy might have failed after sending some of the
+            // config lines, so the cache status is checked against the chain
+            let data = client
+                .program(CANDY_MACHINE_ID)
+                .rpc()
+                .get_account_data(&candy_pubkey)?;
+            let statuses = scan_config_lines(&data, &cache);
+            reconcile_config_lines(&mut cache, &statuses)?;
+        }
+
+        if cache_items < num_items {
+            println!(
+                "{}",
+                style(format!(
+                    "Cache has {} of {} item(s), use 'append' to add the remaining items before minting",
+                    cache_items, num_items
+                ))
+                .yellow()
+            );
+        }
+
+        let config_lines = generate_config_lines(cache_items, &cache.items)?;
 
         if config_lines.is_empty() {
             println!("\nAll config lines deployed.");
@@ -221,6 +351,8 @@ pub async fn process_deploy(args: DeployArgs) -> Result<()> {
                 candy_pubkey,
                 &mut cache,
                 config_lines,
+                config_data.priority_fee,
+                journal,
                 args.interrupted,
             )
             .await?;
@@ -250,9 +382,143 @@ pub async fn process_deploy(args: DeployArgs) -> Result<()> {
         println!("\nCandy machine with hidden settings deployed.");
     }
 
+    cache.program.deploy_fingerprint = Some(fingerprint);
+    cache.sync_file()?;
+
+    Ok(())
+}
+
+/// Return the fingerprint of a deploy, which is the hash of the config file
+/// and the config lines of the cache.
+fn deploy_fingerprint(config: &str, cache: &Cache) -> Result<String> {
+    let mut context = Context::new(&SHA256);
+    context.update(&fs::read(resolve_config_path(config))?);
+
+    for (index, item) in &cache.items.0 {
+        context.update(index.as_bytes());
+        context.update(&[0]);
+        context.update(item.name.as_bytes());
+        context.update(&[0]);
+        context.update(item.metadata_link.as_bytes());
+        context.update(&[0]);
+    }
+
+    Ok(HEXLOWER.encode(context.finish().as_ref()))
+}
+
+/// Check that the candy machine exists and its config lines (or hidden
+/// settings) match the cache (or config).
+fn is_up_to_date(
+    client: &Client,
+    candy_machine_address: &str,
+    cache: &Cache,
+    config_data: &ConfigData,
+) -> Result<bool> {
+    let candy_pubkey = match Pubkey::from_str(candy_machine_address) {
+        Ok(pubkey) => pubkey,
+        Err(_) => return Ok(false),
+    };
+
+    let program = client.program(CANDY_MACHINE_ID);
+    let data = match program.rpc().get_account_data(&candy_pubkey) {
+        Ok(data) => data,
+        Err(_) => return Ok(false),
+    };
+
+    if let Some(hidden_settings) = &config_data.hidden_settings {
+        let expected = hidden_settings.into_candy_format();
+        let candy_machine = CandyMachine::try_deserialize(&mut data.as_slice())?;
+
+        return Ok(candy_machine
+            .data
+            .hidden_settings
+            .map(|current| {
+                current.name == expected.name
+                    && current.uri == expected.uri
+                    && current.hash == expected.hash
+            })
+            .unwrap_or(false));
+    }
+
+    let statuses = scan_config_lines(&data, cache);
+
+    Ok(statuses.len() == cache.items.0.len()
+        && statuses.iter().all(|status| {
+            status.on_chain
+                && cache
+                    .items
+                    .0
+                    .get(&status.index.to_string())
+                    .map(|item| item.on_chain)
+                    .unwrap_or(false)
+        }))
+}
+
+/// Update the status of the cache items from the on-chain config lines, so
+/// only the missing lines are sent.
+fn reconcile_config_lines(cache: &mut Cache, statuses: &[ConfigStatus]) -> Result<()> {
+    let mut changed = false;
+    let mut missing = Vec::new();
+
+    for status in statuses {
+        if let Some(item) = cache.items.0.get_mut(&status.index.to_string()) {
+            if item.on_chain != status.on_chain {
+                item.on_chain = status.on_chain;
+                changed = true;
+            }
+        }
+
+        if !status.on_chain {
+            missing.push(status.index as usize);
+        }
+    }
+
+    if changed {
+        cache.sync_file()?;
+    }
+
+    if !missing.is_empty() && missing.len() < statuses.len() {
+        missing.sort_unstable();
+        println!(
+            "Resuming deploy: {} of {} config line(s) already on-chain, missing {}",
+            statuses.len() - missing.len(),
+            statuses.len(),
+            format_ranges(&missing)
+        );
+    }
+
     Ok(())
 }
 
+/// Check that no item has been minted and ask for confirmation before flagging
+/// all cache items to be written again. Returns `false` if the user aborts.
+fn confirm_rewrite(
+    sugar_config: &SugarConfig,
+    candy_pubkey: &Pubkey,
+    cache: &mut Cache,
+) -> Result<bool> {
+    let state = get_candy_machine_state(sugar_config, candy_pubkey)?;
+
+    if state.items_redeemed > 0 {
+        return Err(DeployError::ItemsAlreadyRedeemed(state.items_redeemed).into());
+    }
+
+    println!("\n+---------------------------------------------------------+");
+    println!("| WARNING: This will overwrite all config lines on-chain. |");
+    println!("+---------------------------------------------------------+");
+
+    if !confirm("Continue?")? {
+        return Ok(false);
+    }
+
+    for item in cache.items.0.values_mut() {
+        item.on_chain = false;
+    }
+    cache.sync_file()?;
+
+    Ok(true)
+}
+
 /// Create the candy machine data struct.
 fn create_candy_machine_data(
     client: &Client,
@@ -385,6 +651,7 @@ fn initialize_candy_machine(
     candy_machine_data: CandyMachineData,
     treasury_wallet: Pubkey,
     program: Program,
+    payer_keypair: &dyn Signer,
 ) -> Result<Signature> {
     let payer = program.payer();
     let items_available = candy_machine_data.items_available;
@@ -415,8 +682,13 @@ fn initialize_candy_machine(
         .into());
     }
 
-    let mut tx = program
-        .request()
+    let mut tx = program.request();
+
+    if let Some(priority_fee) = config_data.priority_fee {
+        tx = tx.instruction(compute_unit_price_instruction(priority_fee));
+    }
+
+    tx = tx
         .instruction(system_instruction::create_account(
             &payer,
             &candy_account.pubkey(),
@@ -424,7 +696,6 @@ fn initialize_candy_machine(
             candy_account_size as u64,
             &program.id(),
         ))
-        .signer(candy_account)
         .accounts(nft_accounts::InitializeCandyMachine {
             candy_machine: candy_account.pubkey(),
             wallet: treasury_wallet,
@@ -445,18 +716,36 @@ fn initialize_candy_machine(
         });
     }
 
-    let sig = tx.send()?;
+    let sig = send_with_retry(
+        &program,
+        &tx.instructions()?,
+        &[payer_keypair, candy_account],
+    )?;
+
+    record_rent(lamports);
+    record_transaction(2, 2, config_data.priority_fee);
 
     Ok(sig)
 }
 
+/// Return the journal batch of a chunk of config lines.
+fn lines_batch(chunk: &[(u32, ConfigLine)]) -> String {
+    match (chunk.first(), chunk.last()) {
+        (Some((first, _)), Some((last, _))) => format!("lines:{}-{}", first, last),
+        _ => String::from("lines:"),
+    }
+}
+
 /// Send the config lines to the candy machine program.
+#[allow(clippy::too_many_arguments)]
 async fn upload_config_lines(
     client: Arc<Client>,
     sugar_config: &SugarConfig,
     candy_pubkey: Pubkey,
     cache: &mut Cache,
     config_lines: Vec<Vec<(u32, ConfigLine)>>,
+    priority_fee: Option<u64>,
+    journal: &mut Journal,
     interrupted: Arc<AtomicBool>,
 ) -> Result<Vec<DeployError>> {
     println!(
@@ -472,13 +761,20 @@ async fn upload_config_lines(
     let mut transactions = Vec::new();
 
     for chunk in config_lines {
-        let keypair = bs58::encode(sugar_config.keypair.to_bytes()).into_string();
-        let payer = Keypair::from_base58_string(&keypair);
+        // a batch sent before an interruption may not be in the cache yet
+        if journal.is_batch_completed(STAGE_DEPLOY, &lines_batch(&chunk)) {
+            for (index, _) in &chunk {
+                cache.get_item_mut(&index.to_string()).unwrap().on_chain = true;
+            }
+            pb.inc(1);
+            continue;
+        }
 
         transactions.push(TxInfo {
             candy_pubkey,
-            payer,
+            payer: sugar_config.keypair.clone(),
             chunk,
+            priority_fee,
         });
     }
 
@@ -486,9 +782,7 @@ async fn upload_config_lines(
 
     for tx in transactions.drain(0..cmp::min(transactions.len(), PARALLEL_LIMIT)) {
         let tx_client = client.clone();
-        handles.push(tokio::spawn(async move {
-            add_config_lines(tx_client, tx).await
-        }));
+        handles.push(tokio::spawn(send_config_lines(tx_client, tx)));
     }
 
     let mut errors = Vec::new();
@@ -500,45 +794,59 @@ async fn upload_config_lines(
                 // we continue to try the remaining ones
                 handles = remaining;
 
-                if res.is_ok() {
-                    let indices = res?;
-
-                    for index in indices {
-                        let item = cache.items.0.get_mut(&index.to_string()).unwrap();
-                        item.on_chain = true;
+                match res {
+                    Ok((indices, signature)) => {
+                        let item_range = match (indices.first(), indices.last()) {
+                            (Some(first), Some(last)) => format!("{}-{}", first, last),
+                            _ => String::new(),
+                        };
+                        journal.complete_batch(STAGE_DEPLOY, &format!("lines:{}", item_range))?;
+
+                        for index in indices {
+                            let item = cache.get_item_mut(&index.to_string()).unwrap();
+                            item.on_chain = true;
+                        }
+                        // updates the progress bar
+                        pb.inc(1);
+                        transaction_event(
+                            STAGE_DEPLOY,
+                            Some(&item_range),
+                            &signature.to_string(),
+                            pb.position(),
+                            pb.length(),
+                        );
+                    }
+                    Err((indices, err)) => {
+                        // user will need to retry the upload
+                        let error = format!("Transaction error: {:?}", err);
+                        error_event(STAGE_DEPLOY, None, pb.position(), pb.length(), &error);
+
+                        for index in indices {
+                            cache.record_error(&index.to_string(), &error);
+                        }
+                        errors.push(DeployError::AddConfigLineFailed(error));
                     }
-                    // updates the progress bar
-                    pb.inc(1);
-                } else {
-                    // user will need to retry the upload
-                    errors.push(DeployError::AddConfigLineFailed(format!(
-                        "Transaction error: {:?}",
-                        res.err().unwrap()
-                    )));
                 }
             }
             (Err(err), _index, remaining) => {
                 // user will need to retry the upload
-                errors.push(DeployError::AddConfigLineFailed(format!(
-                    "Transaction error: {:?}",
-                    err
-                )));
+                let error = format!("Transaction error: {:?}", err);
+                error_event(STAGE_DEPLOY, None, pb.position(), pb.length(), &error);
+                errors.push(DeployError::AddConfigLineFailed(error));
                 // ignoring all errors
                 handles = remaining;
             }
         }
 
+        // saves the progress to the cache file (checkpoint)
+        cache.checkpoint()?;
+
         if !transactions.is_empty() {
             // if we are half way through, let spawn more transactions
             if (PARALLEL_LIMIT - handles.len()) > (PARALLEL_LIMIT / 2) {
-                // saves the progress to the cache file
-                cache.sync_file()?;
-
                 for tx in transactions.drain(0..cmp::min(transactions.len(), PARALLEL_LIMIT / 2)) {
                     let tx_client = client.clone();
-                    handles.push(tokio::spawn(async move {
-                        add_config_lines(tx_client, tx).await
-                    }));
+                    handles.push(tokio::spawn(send_config_lines(tx_client, tx)));
                 }
             }
         }
@@ -563,7 +871,24 @@ async fn upload_config_lines(
 }
 
 /// Send the `add_config_lines` instruction to the candy machine program.
-async fn add_config_lines(client: Arc<Client>, tx_info: TxInfo) -> Result<Vec<u32>> {
+/// Send a chunk of config lines, returning the indices of the chunk with the
+/// signature of the transaction, or with the error of a failed transaction.
+async fn send_config_lines(
+    client: Arc<Client>,
+    tx_info: TxInfo,
+) -> std::result::Result<(Vec<u32>, Signature), (Vec<u32>, anyhow::Error)> {
+    let indices: Vec<u32> = tx_info.chunk.iter().map(|(index, _)| *index).collect();
+
+    // sending the transaction blocks, including the backoff between retries,
+    // so it runs on the blocking thread pool instead of a runtime worker
+    tokio::task::spawn_blocking(move || add_config_lines(client, tx_info))
+        .await
+        .map_err(|err| anyhow!(err))
+        .and_then(|result| result)
+        .map_err(|err| (indices, err))
+}
+
+fn add_config_lines(client: Arc<Client>, tx_info: TxInfo) -> Result<(Vec<u32>, Signature)> {
     let program = client.program(CANDY_MACHINE_ID);
 
     // this will be used to update the cache
@@ -578,8 +903,13 @@ async fn add_config_lines(client: Arc<Client>, tx_info: TxInfo) -> Result<Vec<u3
         config_lines.push(line);
     }
 
-    let _sig = program
-        .request()
+    let mut builder = program.request();
+
+    if let Some(priority_fee) = tx_info.priority_fee {
+        builder = builder.instruction(compute_unit_price_instruction(priority_fee));
+    }
+
+    let instructions = builder
         .accounts(nft_accounts::AddConfigLines {
             candy_machine: tx_info.candy_pubkey,
             authority: program.payer(),
@@ -588,8 +918,13 @@ async fn add_config_lines(client: Arc<Client>, tx_info: TxInfo) -> Result<Vec<u3
             index: start_index,
             config_lines,
         })
-        .signer(&tx_info.payer)
-        .send()?;
+        .instructions()?;
+
+    let signature = send_with_retry(&program, &instructions, &[&tx_info.payer])?;
+
+    record_transaction(1, 1, tx_info.priority_fee);
+
+    rpc_cache::invalidate(&tx_info.candy_pubkey);
 
-    Ok(indices)
+    Ok((indices, signature))
 }
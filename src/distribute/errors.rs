@@ -0,0 +1,19 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum DistributeError {
+    #[error("Could not read allocations file '{0}': {1}")]
+    AllocationsFileError(String, String),
+
+    #[error("Invalid allocation at line {0}: {1}")]
+    InvalidAllocation(usize, String),
+
+    #[error("Mint {0} is not held by the authority wallet")]
+    MintNotHeld(String),
+
+    #[error("Not enough NFTs held by the authority wallet: {0} required, {1} available")]
+    NotEnoughNfts(usize, usize),
+
+    #[error("Failed to parse distribution state file: {0}")]
+    StateFileWrongFormat(String),
+}
@@ -0,0 +1,567 @@
+use anchor_client::solana_sdk::{hash::Hash, program_pack::Pack};
+use console::style;
+use serde::Serialize;
+use solana_account_decoder::UiAccountData;
+use solana_client::rpc_request::TokenAccountsFilter;
+use spl_associated_token_account::{create_associated_token_account, get_associated_token_address};
+use spl_token::{
+    instruction::{close_account, transfer},
+    state::Account as TokenAccount,
+    ID as TOKEN_PROGRAM_ID,
+};
+use std::{
+    collections::HashSet,
+    fs::{self, OpenOptions},
+};
+
+use mpl_token_metadata::{
+    state::{Key, Metadata, MAX_METADATA_LEN},
+    utils::try_from_slice_checked,
+};
+
+use crate::cache::load_cache;
+use crate::candy_machine::ID as CANDY_MACHINE_ID;
+use crate::common::*;
+use crate::distribute::DistributeError;
+use crate::events::{error_event, progress_event};
+use crate::interaction::confirm;
+use crate::mint::pdas::{get_candy_machine_creator_pda, get_metadata_pda};
+use crate::spend::{record_rent, record_transaction, track_spend};
+use crate::transaction::{check_pending, send_with_callback};
+use crate::utils::*;
+
+pub struct DistributeArgs {
+    pub keypair: Option<String>,
+    pub rpc_url: Option<String>,
+    pub cache: String,
+    pub allocations: String,
+    pub state: String,
+    pub candy_machine: Option<String>,
+}
+
+/// A line of the allocations file.
+enum Allocation {
+    Count(Pubkey, usize),
+    Mint(Pubkey, Pubkey),
+}
+
+/// An NFT held by the authority wallet.
+pub struct HeldNft {
+    pub mint: Pubkey,
+    pub token_account: Pubkey,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct DistributionState {
+    pub transfers: Vec<Transfer>,
+    /// Transfer sent but not confirmed yet, checked on the next run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pending: Option<PendingTransfer>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Transfer {
+    pub recipient: String,
+    pub mint: String,
+    /// None when the recipient is the authority wallet, which already holds
+    /// the NFT.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PendingTransfer {
+    pub recipient: String,
+    pub mint: String,
+    pub signature: String,
+    pub blockhash: String,
+}
+
+pub fn process_distribute(args: DistributeArgs) -> Result<()> {
+    track_spend("distribute", &args.cache);
+
+    let sugar_config = sugar_setup(args.keypair, args.rpc_url)?;
+    let client = setup_client(&sugar_config)?;
+    let program = client.program(CANDY_MACHINE_ID);
+    let payer = program.payer();
+
+    println!(
+        "{} {}Loading allocations",
+        style("[1/3]").bold().dim(),
+        PAPER_EMOJI
+    );
+
+    let allocations = read_allocations(&args.allocations)?;
+    let mut state = load_state(&args.state)?;
+
+    // a transfer sent by an interrupted run is only sent again if it did not land
+    if let Some(pending) = state.pending.clone() {
+        println!(
+            "Checking the pending transfer of {} to {}...",
+            pending.mint, pending.recipient
+        );
+
+        if confirm_pending(&program, &mut state)? {
+            println!("Transfer confirmed: {}", pending.signature);
+        } else {
+            println!("Transfer did not land, it will be sent again");
+        }
+        save_state(&args.state, &state)?;
+    }
+
+    println!(
+        "Found {} allocation(s), {} transfer(s) already completed",
+        allocations.len(),
+        state.transfers.len()
+    );
+
+    // the candy machine specified takes precedence over the one from the cache;
+    // when there is none, any NFT held by the wallet can be distributed
+    let candy_machine = match args.candy_machine {
+        Some(candy_machine) => Some(candy_machine),
+        None => load_cache(&args.cache, false)
+            .ok()
+            .map(|cache| cache.program.candy_machine)
+            .filter(|candy_machine| !candy_machine.is_empty()),
+    };
+
+    let creator = match candy_machine {
+        Some(candy_machine) => {
+            let candy_machine = Pubkey::from_str(&candy_machine)
+                .map_err(|_| CacheError::InvalidCandyMachineAddress(candy_machine.clone()))?;
+            Some(get_candy_machine_creator_pda(&candy_machine).0)
+        }
+        None => None,
+    };
+
+    println!(
+        "\n{} {}Looking up NFTs held by {}",
+        style("[2/3]").bold().dim(),
+        LOOKING_GLASS_EMOJI,
+        payer
+    );
+
+    let pb = spinner_with_style();
+    pb.set_message("Connecting...");
+
+    let held = get_held_nfts(&program, &payer, creator.as_ref())?;
+
+    pb.finish_and_clear();
+    println!("Found {} NFT(s) available for distribution", held.len());
+
+    let plan = plan_transfers(&allocations, &state, held)?;
+
+    println!(
+        "\n{} {}Distributing NFTs",
+        style("[3/3]").bold().dim(),
+        PAYMENT_EMOJI
+    );
+
+    if plan.is_empty() {
+        println!("\nAll allocations have been distributed.");
+        return Ok(());
+    }
+
+    if !confirm(&format!("Transfer {} NFT(s)?", plan.len()))? {
+        println!("\n{}", style("Distribution aborted.").red().bold().dim());
+        return Ok(());
+    }
+
+    let pb = progress_bar_with_style(plan.len() as u64);
+    let mut errors = Vec::new();
+
+    for (recipient, nft) in plan {
+        let result = if recipient == payer {
+            // the authority wallet already holds the NFT
+            Ok(None)
+        } else {
+            let state_file = &args.state;
+            let state = &mut state;
+
+            transfer_nft(
+                &program,
+                &sugar_config.keypair,
+                &recipient,
+                &nft,
+                None,
+                |signature, blockhash| {
+                    state.pending = Some(PendingTransfer {
+                        recipient: recipient.to_string(),
+                        mint: nft.mint.to_string(),
+                        signature: signature.to_string(),
+                        blockhash: blockhash.to_string(),
+                    });
+                    save_state(state_file, state)
+                },
+            )
+            .map(Some)
+        };
+
+        match result {
+            Ok(signature) => {
+                state.pending = None;
+                state.transfers.push(Transfer {
+                    recipient: recipient.to_string(),
+                    mint: nft.mint.to_string(),
+                    signature: signature.map(|signature| signature.to_string()),
+                });
+                // saves the progress after each transfer
+                save_state(&args.state, &state)?;
+            }
+            // the last attempt can still land after the error
+            Err(_) if state.pending.is_some() && confirm_pending(&program, &mut state)? => {
+                save_state(&args.state, &state)?;
+            }
+            Err(err) => {
+                save_state(&args.state, &state)?;
+                error!("Failed to transfer {} to {}: {}", nft.mint, recipient, err);
+                let error = format!("{} -> {}: {}", nft.mint, recipient, err);
+                error_event(
+                    "distribute",
+                    Some(&nft.mint.to_string()),
+                    pb.position(),
+                    pb.length(),
+                    &error,
+                );
+                errors.push(error);
+            }
+        }
+        pb.inc(1);
+        progress_event(
+            "distribute",
+            Some(&nft.mint.to_string()),
+            pb.position(),
+            pb.length(),
+        );
+    }
+
+    if errors.is_empty() {
+        pb.finish_with_message(format!(
+            "{}",
+            style("Distribution successful ").green().bold()
+        ));
+    } else {
+        pb.abandon_with_message(format!("{}", style("Distribution failed ").red().bold()));
+
+        let mut message = format!(
+            "Failed to complete the distribution, {} error(s) occurred (re-run to resume):",
+            errors.len()
+        );
+        for error in errors {
+            message.push_str(&style("\n=> ").dim().to_string());
+            message.push_str(&error);
+        }
+
+        return Err(anyhow!(message));
+    }
+
+    Ok(())
+}
+
+/// Parse the allocations file, where each line is either `address,count`
+/// or `address,mint`. A header line is ignored.
+fn read_allocations(path: &str) -> Result<Vec<Allocation>> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| DistributeError::AllocationsFileError(path.to_string(), e.to_string()))?;
+
+    let mut allocations = Vec::new();
+
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+
+        if fields.len() != 2 {
+            return Err(DistributeError::InvalidAllocation(
+                number + 1,
+                "expected 'address,count' or 'address,mint'".to_string(),
+            )
+            .into());
+        }
+
+        let address = match Pubkey::from_str(fields[0]) {
+            Ok(address) => address,
+            // header line
+            Err(_) if number == 0 => continue,
+            Err(_) => {
+                return Err(DistributeError::InvalidAllocation(
+                    number + 1,
+                    format!("invalid address '{}'", fields[0]),
+                )
+                .into())
+            }
+        };
+
+        let allocation = if let Ok(count) = fields[1].parse::<usize>() {
+            Allocation::Count(address, count)
+        } else if let Ok(mint) = Pubkey::from_str(fields[1]) {
+            Allocation::Mint(address, mint)
+        } else {
+            return Err(DistributeError::InvalidAllocation(
+                number + 1,
+                format!("invalid count or mint '{}'", fields[1]),
+            )
+            .into());
+        };
+
+        allocations.push(allocation);
+    }
+
+    Ok(allocations)
+}
+
+fn load_state(path: &str) -> Result<DistributionState> {
+    if !Path::new(path).exists() {
+        return Ok(DistributionState::default());
+    }
+
+    let file = File::open(path)?;
+    serde_json::from_reader(file)
+        .map_err(|e| DistributeError::StateFileWrongFormat(e.to_string()).into())
+}
+
+/// Check the pending transfer of the state, moving it to the completed
+/// transfers if it landed. Returns true if it did.
+fn confirm_pending(program: &Program, state: &mut DistributionState) -> Result<bool> {
+    let pending = match state.pending.clone() {
+        Some(pending) => pending,
+        None => return Ok(false),
+    };
+
+    let signature = Signature::from_str(&pending.signature)
+        .map_err(|e| DistributeError::StateFileWrongFormat(e.to_string()))?;
+    let blockhash = Hash::from_str(&pending.blockhash)
+        .map_err(|e| DistributeError::StateFileWrongFormat(e.to_string()))?;
+    let landed = check_pending(program, &signature, &blockhash)?;
+
+    state.pending = None;
+
+    if landed {
+        info!(
+            "Transferred {} to {}: {}",
+            pending.mint, pending.recipient, signature
+        );
+        state.transfers.push(Transfer {
+            recipient: pending.recipient,
+            mint: pending.mint,
+            signature: Some(pending.signature),
+        });
+    }
+
+    Ok(landed)
+}
+
+fn save_state(path: &str, state: &DistributionState) -> Result<()> {
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)?;
+    serde_json::to_writer_pretty(file, state)?;
+
+    Ok(())
+}
+
+/// Return the NFTs held by the owner, optionally restricted to the ones
+/// created by the specified (verified) creator.
+fn get_held_nfts(
+    program: &Program,
+    owner: &Pubkey,
+    creator: Option<&Pubkey>,
+) -> Result<Vec<HeldNft>> {
+    let accounts = program
+        .rpc()
+        .get_token_accounts_by_owner(owner, TokenAccountsFilter::ProgramId(TOKEN_PROGRAM_ID))?;
+
+    let mut held = Vec::new();
+
+    for keyed_account in accounts {
+        if let UiAccountData::Json(parsed) = keyed_account.account.data {
+            let info = &parsed.parsed["info"];
+            let amount = info["tokenAmount"]["amount"].as_str().unwrap_or_default();
+            let decimals = info["tokenAmount"]["decimals"].as_u64().unwrap_or_default();
+
+            if amount == "1" && decimals == 0 {
+                if let Some(mint) = info["mint"].as_str() {
+                    held.push(HeldNft {
+                        mint: Pubkey::from_str(mint)?,
+                        token_account: Pubkey::from_str(&keyed_account.pubkey)?,
+                    });
+                }
+            }
+        }
+    }
+
+    if let Some(creator) = creator {
+        let mut filtered = Vec::new();
+
+        for chunk in held.chunks(MAX_ACCOUNTS_PER_REQUEST) {
+            let metadata_pdas: Vec<Pubkey> =
+                chunk.iter().map(|n| get_metadata_pda(&n.mint)).collect();
+            let accounts = program.rpc().get_multiple_accounts(&metadata_pdas)?;
+
+            for (nft, account) in chunk.iter().zip(accounts) {
+                let metadata: Metadata = match account {
+                    Some(account) => {
+                        match try_from_slice_checked(
+                            &account.data,
+                            Key::MetadataV1,
+                            MAX_METADATA_LEN,
+                        ) {
+                            Ok(metadata) => metadata,
+                            Err(_) => continue,
+                        }
+                    }
+                    None => continue,
+                };
+
+                let created = metadata
+                    .data
+                    .creators
+                    .as_ref()
+                    .and_then(|creators| creators.first())
+                    .map(|first| first.verified && first.address == *creator)
+                    .unwrap_or(false);
+
+                if created {
+                    filtered.push(HeldNft {
+                        mint: nft.mint,
+                        token_account: nft.token_account,
+                    });
+                }
+            }
+        }
+
+        held = filtered;
+    }
+
+    held.sort_by_key(|nft| nft.mint.to_string());
+
+    Ok(held)
+}
+
+/// Determine the transfers still needed to fulfil the allocations.
+fn plan_transfers(
+    allocations: &[Allocation],
+    state: &DistributionState,
+    held: Vec<HeldNft>,
+) -> Result<Vec<(Pubkey, HeldNft)>> {
+    let transferred: HashSet<&str> = state.transfers.iter().map(|t| t.mint.as_str()).collect();
+    let mut available: Vec<HeldNft> = held
+        .into_iter()
+        .filter(|nft| !transferred.contains(nft.mint.to_string().as_str()))
+        .collect();
+    let mut plan = Vec::new();
+
+    // specific mint assignments first, so they are not used to fulfil counts
+    let mut assigned = HashSet::new();
+
+    for allocation in allocations {
+        if let Allocation::Mint(recipient, mint) = allocation {
+            assigned.insert(mint.to_string());
+
+            if transferred.contains(mint.to_string().as_str()) {
+                continue;
+            }
+
+            match available.iter().position(|nft| nft.mint == *mint) {
+                Some(position) => plan.push((*recipient, available.remove(position))),
+                None => return Err(DistributeError::MintNotHeld(mint.to_string()).into()),
+            }
+        }
+    }
+
+    for allocation in allocations {
+        if let Allocation::Count(recipient, count) = allocation {
+            let recipient_str = recipient.to_string();
+            let completed = state
+                .transfers
+                .iter()
+                .filter(|t| t.recipient == recipient_str && !assigned.contains(&t.mint))
+                .count();
+            let pending = count.saturating_sub(completed);
+
+            if pending > available.len() {
+                let required = pending + plan.len();
+                return Err(
+                    DistributeError::NotEnoughNfts(required, available.len() + plan.len()).into(),
+                );
+            }
+
+            for nft in available.drain(0..pending) {
+                plan.push((*recipient, nft));
+            }
+        }
+    }
+
+    Ok(plan)
+}
+
+/// Transfer an NFT to the recipient, creating its associated token account if
+/// needed and closing the source token account. `on_signed` is called with
+/// the signature and blockhash of each attempt before it is sent.
+pub fn transfer_nft<F: FnMut(&Signature, &Hash) -> Result<()>>(
+    program: &Program,
+    keypair: &dyn Signer,
+    recipient: &Pubkey,
+    nft: &HeldNft,
+    priority_fee: Option<u64>,
+    on_signed: F,
+) -> Result<Signature> {
+    let payer = &keypair.pubkey();
+    let destination = get_associated_token_address(recipient, &nft.mint);
+    let mut builder = program.request();
+
+    if let Some(priority_fee) = priority_fee {
+        builder = builder.instruction(compute_unit_price_instruction(priority_fee));
+    }
+
+    let exists = match program.rpc().get_account_data(&destination) {
+        Ok(data) => TokenAccount::unpack(&data).is_ok(),
+        Err(_) => false,
+    };
+
+    if !exists {
+        builder = builder.instruction(create_associated_token_account(payer, recipient, &nft.mint));
+    }
+
+    let instructions = builder
+        .instruction(transfer(
+            &TOKEN_PROGRAM_ID,
+            &nft.token_account,
+            &destination,
+            payer,
+            &[],
+            1,
+        )?)
+        .instruction(close_account(
+            &TOKEN_PROGRAM_ID,
+            &nft.token_account,
+            payer,
+            payer,
+            &[],
+        )?)
+        .instructions()?;
+    let commitment = program.rpc().commitment();
+    let signature = send_with_callback(program, &instructions, &[keypair], commitment, on_signed)?;
+
+    // the rent of the source token account is refunded when it is closed
+    if exists {
+        record_transaction(1, 2, priority_fee);
+    } else {
+        record_transaction(1, 3, priority_fee);
+        record_rent(
+            program
+                .rpc()
+                .get_minimum_balance_for_rent_exemption(TokenAccount::LEN)?,
+        );
+    }
+
+    info!("Transferred {} to {}: {}", nft.mint, recipient, signature);
+
+    Ok(signature)
+}
@@ -1,6 +1,14 @@
-use std::path::PathBuf;
+use std::{
+    fmt::{self, Display, Formatter},
+    path::PathBuf,
+};
 use thiserror::Error;
 
+use crate::config::ConfigError;
+use crate::deploy::DeployError;
+use crate::upload::UploadError;
+use crate::validate::ValidateError;
+
 #[derive(Debug, Error)]
 pub enum SetupError {
     #[error("Error setting up sugar: {0}")]
@@ -20,6 +28,15 @@ pub enum CacheError {
 
     #[error("Failed to parse cache file with error: {0}")]
     CacheFileWrongFormat(String),
+
+    #[error("Cache file version {0} is not supported (latest supported version is {1}), update Sugar to use it.")]
+    UnsupportedVersion(u8, u8),
+
+    #[error("Cache file '{0}' is in use by another Sugar process. Wait for it to finish before running this command.")]
+    CacheFileLocked(String),
+
+    #[error("Cache file '{0}' already exists, use --force to overwrite it.")]
+    CacheFileExists(String),
 }
 
 #[derive(Debug, Error)]
@@ -48,3 +65,70 @@ pub struct FileOpenError<'a> {
     pub path: &'a PathBuf,
     pub error: std::io::Error,
 }
+
+/// Class of the failure of a command, reported as its exit code so that
+/// scripts can tell them apart.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FailureClass {
+    General,
+    /// Invalid config file or assets.
+    Validation,
+    /// Failed upload of the assets.
+    Upload,
+    /// Failed deploy (or verification) of the candy machine.
+    Deploy,
+}
+
+impl FailureClass {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            FailureClass::General => 1,
+            FailureClass::Validation => 3,
+            FailureClass::Upload => 4,
+            FailureClass::Deploy => 5,
+        }
+    }
+
+    /// Return the class of the error of a command, from the typed errors in
+    /// its chain or, if there are none, the class of the failed stage.
+    pub fn of(error: &anyhow::Error, stage: FailureClass) -> FailureClass {
+        if let Some(stage_error) = error.downcast_ref::<StageError>() {
+            return FailureClass::of(&stage_error.error, stage_error.class);
+        }
+
+        for cause in error.chain() {
+            if cause.is::<ValidateError>()
+                || cause.is::<ConfigError>()
+                || cause.is::<ReadFilesError>()
+            {
+                return FailureClass::Validation;
+            } else if cause.is::<UploadError>() {
+                return FailureClass::Upload;
+            } else if cause.is::<DeployError>() {
+                return FailureClass::Deploy;
+            }
+        }
+
+        stage
+    }
+}
+
+/// Error of a stage of a command that runs several stages, e.g., the upload
+/// of a launch.
+#[derive(Debug)]
+pub struct StageError {
+    pub class: FailureClass,
+    pub error: anyhow::Error,
+}
+
+impl Display for StageError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl std::error::Error for StageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.source()
+    }
+}
@@ -0,0 +1,170 @@
+use console::style;
+
+use crate::common::*;
+use crate::config::get_config_data;
+use crate::upload::{
+    format_bytes, get_uploaded_asset_pairs, initialize_handler, upload_file_sizes, DataType,
+    UploadCost,
+};
+use crate::utils::*;
+
+pub struct EstimateArgs {
+    pub assets_dir: String,
+    pub config: String,
+    pub keypair: Option<String>,
+    pub rpc_url: Option<String>,
+}
+
+pub async fn process_estimate(args: EstimateArgs) -> Result<()> {
+    let sugar_config = sugar_setup(args.keypair, args.rpc_url)?;
+    let config_data = get_config_data(&args.config)?;
+
+    println!(
+        "{} {}Loading assets",
+        style("[1/2]").bold().dim(),
+        ASSETS_EMOJI
+    );
+
+    let pb = spinner_with_style();
+    pb.enable_steady_tick(120);
+    pb.set_message("Reading files...");
+
+    // the sizes of the files that are uploaded, i.e., the optimized images
+    // and previews when the config file enables them
+    let asset_pairs = get_uploaded_asset_pairs(&args.assets_dir, &args.config)?;
+    let mut indices: Vec<usize> = asset_pairs.keys().copied().collect();
+    indices.sort_unstable();
+
+    let animation_indices: Vec<usize> = indices
+        .iter()
+        .filter(|index| asset_pairs[index].animation.is_some())
+        .copied()
+        .collect();
+    let preview_indices: Vec<usize> = indices
+        .iter()
+        .filter(|index| asset_pairs[index].preview.is_some())
+        .copied()
+        .collect();
+
+    let sizes = vec![
+        (
+            "image",
+            upload_file_sizes(&asset_pairs, &indices, &DataType::Image)?,
+        ),
+        (
+            "animation",
+            upload_file_sizes(&asset_pairs, &animation_indices, &DataType::Animation)?,
+        ),
+        (
+            "preview",
+            upload_file_sizes(&asset_pairs, &preview_indices, &DataType::Preview)?,
+        ),
+        (
+            "metadata",
+            upload_file_sizes(&asset_pairs, &indices, &DataType::Metadata)?,
+        ),
+    ];
+
+    pb.finish_and_clear();
+
+    println!("Found {} asset pair(s)", asset_pairs.len());
+
+    println!(
+        "\n{} {}Estimating upload cost ({})",
+        style("[2/2]").bold().dim(),
+        PAYMENT_EMOJI,
+        config_data.upload_method.to_string()
+    );
+
+    let pb = spinner_with_style();
+    pb.set_message("Querying storage pricing...");
+
+    // the storage can only be queried with valid settings, but the sizes are
+    // still useful without them
+    let handler = match initialize_handler(&config_data, &sugar_config).await {
+        Ok(handler) => Some(handler),
+        Err(err) => {
+            warn!("Failed to initialize the upload handler: {}", err);
+            None
+        }
+    };
+
+    let mut rows = Vec::new();
+
+    for (data_type, file_sizes) in &sizes {
+        let cost = match &handler {
+            Some(handler) => handler.estimate_cost(file_sizes).await?,
+            None => None,
+        };
+        rows.push((
+            data_type,
+            file_sizes.len(),
+            file_sizes.iter().sum::<u64>(),
+            cost,
+        ));
+    }
+
+    pb.finish_and_clear();
+
+    if handler.is_none() {
+        println!(
+            "{}",
+            style(
+                "Could not query the storage pricing, check the upload settings of the config file"
+            )
+            .yellow()
+        );
+    }
+
+    println!("\n  {:<10}{:>8}{:>12}  Cost", "Type", "Files", "Bytes");
+
+    let mut total_files = 0;
+    let mut total_bytes = 0;
+    let mut total_cost: Option<UploadCost> = None;
+    let mut priced = handler.is_some();
+
+    for (data_type, files, bytes, cost) in rows {
+        if files == 0 {
+            continue;
+        }
+
+        println!(
+            "  {:<10}{:>8}{:>12}  {}",
+            data_type,
+            files,
+            format_bytes(bytes),
+            cost.map(|cost| cost.to_string())
+                .unwrap_or_else(|| "-".to_string())
+        );
+
+        total_files += files;
+        total_bytes += bytes;
+        total_cost = match (total_cost, cost) {
+            (None, Some(cost)) if priced => Some(cost),
+            (Some(total), Some(cost)) => total.checked_add(cost),
+            _ => {
+                priced = false;
+                None
+            }
+        };
+    }
+
+    println!(
+        "  {:<10}{:>8}{:>12}  {}",
+        style("Total").bold(),
+        total_files,
+        format_bytes(total_bytes),
+        total_cost
+            .map(|cost| style(cost.to_string()).bold().to_string())
+            .unwrap_or_else(|| "-".to_string())
+    );
+
+    if handler.is_some() && total_cost.is_none() {
+        println!(
+            "\nThe '{}' upload method does not charge a fee per upload.",
+            config_data.upload_method.to_string()
+        );
+    }
+
+    Ok(())
+}
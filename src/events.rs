@@ -0,0 +1,226 @@
+use chrono::Utc;
+use serde::Serialize;
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, OnceLock,
+    },
+};
+
+use crate::common::*;
+
+/// Destination of the progress events, set by the `--progress-events` option.
+static EVENTS: OnceLock<Mutex<Box<dyn Write + Send>>> = OnceLock::new();
+
+/// Whether the output is machine-readable, set by the `--json` option.
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+/// A machine-readable progress event, written as a JSON line.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgressEvent<'a> {
+    pub stage: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item: Option<&'a str>,
+    /// Link of an uploaded file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link: Option<&'a str>,
+    /// Signature of a sent transaction.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<&'a str>,
+    pub completed: u64,
+    pub total: u64,
+    pub percent: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<&'a str>,
+    pub timestamp: String,
+}
+
+/// Set up the progress events output. The target is either a file path or,
+/// on unix, a numeric file descriptor (e.g., '3').
+pub fn init_progress_events(target: &str) -> Result<()> {
+    let writer: Box<dyn Write + Send> = match target.parse::<i32>() {
+        #[cfg(unix)]
+        Ok(fd) => {
+            use std::{io, os::unix::io::FromRawFd};
+
+            if fd < 0 {
+                return Err(anyhow!("Invalid progress events file descriptor {}", fd));
+            }
+
+            // only a descriptor open in this process can be owned by a file
+            if unsafe { libc::fcntl(fd, libc::F_GETFD) } < 0 {
+                return Err(anyhow!(
+                    "Progress events file descriptor {} is not open: {}",
+                    fd,
+                    io::Error::last_os_error()
+                ));
+            }
+
+            // safety: the descriptor is open (checked above), provided by the
+            // parent process and owned by sugar from this point on
+            Box::new(unsafe { File::from_raw_fd(fd) })
+        }
+        _ => Box::new(
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(target)
+                .map_err(|e| anyhow!("Could not open progress events file '{}': {}", target, e))?,
+        ),
+    };
+
+    EVENTS
+        .set(Mutex::new(writer))
+        .map_err(|_| anyhow!("Progress events output already initialized"))
+}
+
+/// Set up the JSON output: the events are written to stdout, with the
+/// console styling and progress bars disabled. The messages of the commands
+/// are moved to stderr, so stdout only has the events (unix only).
+pub fn init_json_output() -> Result<()> {
+    let writer = stdout_events()?;
+
+    JSON_OUTPUT.store(true, Ordering::SeqCst);
+    console::set_colors_enabled(false);
+    console::set_colors_enabled_stderr(false);
+
+    EVENTS
+        .set(Mutex::new(writer))
+        .map_err(|_| anyhow!("Progress events output already initialized"))
+}
+
+/// Return whether the output is machine-readable JSON.
+pub fn json_output() -> bool {
+    JSON_OUTPUT.load(Ordering::SeqCst)
+}
+
+#[cfg(unix)]
+fn stdout_events() -> Result<Box<dyn Write + Send>> {
+    use std::{io, os::unix::io::FromRawFd};
+
+    io::stdout().flush()?;
+
+    // safety: the duplicate of stdout is owned by the events writer, while
+    // stdout itself is replaced by stderr
+    unsafe {
+        let fd = libc::dup(libc::STDOUT_FILENO);
+
+        if fd < 0 || libc::dup2(libc::STDERR_FILENO, libc::STDOUT_FILENO) < 0 {
+            return Err(anyhow!(
+                "Could not redirect the output to stderr: {}",
+                io::Error::last_os_error()
+            ));
+        }
+
+        Ok(Box::new(File::from_raw_fd(fd)))
+    }
+}
+
+/// The messages of the commands cannot be moved to stderr, so they would be
+/// mixed with the events on stdout.
+#[cfg(not(unix))]
+fn stdout_events() -> Result<Box<dyn Write + Send>> {
+    Err(anyhow!(
+        "--json is only supported on unix, use --progress-events <FILE> instead"
+    ))
+}
+
+/// Write a progress event for the completion of an item of a stage.
+pub fn progress_event(stage: &str, item: Option<&str>, completed: u64, total: u64) {
+    emit(stage, item, None, None, completed, total, None);
+}
+
+/// Write a progress event for an uploaded file.
+pub fn upload_event(stage: &str, item: &str, link: &str, completed: u64, total: u64) {
+    emit(stage, Some(item), Some(link), None, completed, total, None);
+}
+
+/// Write a progress event for a transaction sent in a stage.
+pub fn transaction_event(
+    stage: &str,
+    item: Option<&str>,
+    signature: &str,
+    completed: u64,
+    total: u64,
+) {
+    emit(stage, item, None, Some(signature), completed, total, None);
+}
+
+/// Write a progress event for a failure in a stage.
+pub fn error_event(stage: &str, item: Option<&str>, completed: u64, total: u64, error: &str) {
+    emit(stage, item, None, None, completed, total, Some(error));
+}
+
+/// Write the result of the command, the last event of the output.
+pub fn result_event(error: Option<&str>) {
+    let mut event = json!({
+        "stage": "result",
+        "success": error.is_none(),
+        "timestamp": Utc::now().to_rfc3339(),
+    });
+
+    if let Some(error) = error {
+        event["error"] = json!(error);
+    }
+
+    write_event(&event);
+}
+
+/// Write a summary of a stage, e.g., the metrics of an upload.
+pub fn summary_event<T: Serialize>(stage: &str, summary: &T) {
+    write_event(&json!({
+        "stage": stage,
+        "summary": summary,
+        "timestamp": Utc::now().to_rfc3339(),
+    }));
+}
+
+fn emit(
+    stage: &str,
+    item: Option<&str>,
+    link: Option<&str>,
+    signature: Option<&str>,
+    completed: u64,
+    total: u64,
+    error: Option<&str>,
+) {
+    if EVENTS.get().is_none() {
+        return;
+    }
+
+    write_event(&ProgressEvent {
+        stage,
+        item,
+        link,
+        signature,
+        completed,
+        total,
+        percent: if total > 0 {
+            (completed as f64 * 100.0) / total as f64
+        } else {
+            100.0
+        },
+        error,
+        timestamp: Utc::now().to_rfc3339(),
+    });
+}
+
+fn write_event<T: Serialize>(event: &T) {
+    let events = match EVENTS.get() {
+        Some(events) => events,
+        None => return,
+    };
+
+    if let (Ok(mut writer), Ok(line)) = (events.lock(), serde_json::to_string(event)) {
+        // events are best effort, failing to write them must not stop a command
+        if writeln!(writer, "{}", line)
+            .and_then(|_| writer.flush())
+            .is_err()
+        {
+            warn!("Failed to write progress event");
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use console::style;
+use serde::Deserialize;
+use solana_client::rpc_request::RpcRequest;
+
+use crate::candy_machine::ID as CANDY_MACHINE_ID;
+use crate::common::*;
+use crate::config::parser::{resolve_config_path, set_config_number};
+use crate::utils::*;
+
+/// Number of performance samples (one per minute) used to compute slot timing.
+const PERFORMANCE_SAMPLES: usize = 10;
+
+/// Percentile of the recent prioritization fees used for the recommendation.
+const RECOMMENDED_PERCENTILE: usize = 75;
+
+pub struct FeesArgs {
+    pub keypair: Option<String>,
+    pub rpc_url: Option<String>,
+    pub config: String,
+    pub write: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PrioritizationFee {
+    prioritization_fee: u64,
+}
+
+pub fn process_fees(args: FeesArgs) -> Result<()> {
+    let sugar_config = sugar_setup(args.keypair, args.rpc_url)?;
+    let client = setup_client(&sugar_config)?;
+    let program = client.program(CANDY_MACHINE_ID);
+
+    println!(
+        "{} {}Sampling network activity",
+        style(if args.write { "[1/2]" } else { "[1/1]" })
+            .bold()
+            .dim(),
+        LOOKING_GLASS_EMOJI
+    );
+
+    let pb = spinner_with_style();
+    pb.set_message("Connecting...");
+
+    let samples = program
+        .rpc()
+        .get_recent_performance_samples(Some(PERFORMANCE_SAMPLES))?;
+
+    // not all RPC nodes support prioritization fees, in which case there is
+    // no fee to recommend
+    let fees: Vec<u64> = match program.rpc().send::<Vec<PrioritizationFee>>(
+        RpcRequest::Custom {
+            method: "getRecentPrioritizationFees",
+        },
+        json!([[CANDY_MACHINE_ID.to_string()]]),
+    ) {
+        Ok(fees) => fees.into_iter().map(|f| f.prioritization_fee).collect(),
+        Err(err) => {
+            warn!("Could not retrieve prioritization fees: {}", err);
+            Vec::new()
+        }
+    };
+
+    pb.finish_and_clear();
+
+    let (slots, transactions, seconds) = samples.iter().fold((0, 0, 0), |acc, s| {
+        (
+            acc.0 + s.num_slots,
+            acc.1 + s.num_transactions,
+            acc.2 + s.sample_period_secs as u64,
+        )
+    });
+
+    if slots > 0 && seconds > 0 {
+        println!(
+            "{} {:.0} ms",
+            style("Average slot time:").bold(),
+            (seconds as f64 * 1000.0) / slots as f64
+        );
+        println!(
+            "{} {:.0}",
+            style("Transactions per second:").bold(),
+            transactions as f64 / seconds as f64
+        );
+    } else {
+        println!("{}", style("No performance samples available").dim());
+    }
+
+    if fees.is_empty() {
+        println!("{}", style("No prioritization fee samples available").dim());
+
+        if args.write {
+            println!(
+                "\n{}",
+                style("No fee to recommend, the config file was not changed").yellow()
+            );
+        }
+
+        return Ok(());
+    }
+
+    let mut sorted = fees;
+    sorted.sort_unstable();
+
+    println!(
+        "{} {} / {} / {} (min / median / max)",
+        style("Prioritization fees (micro-lamports per CU):").bold(),
+        sorted[0],
+        percentile(&sorted, 50),
+        sorted[sorted.len() - 1]
+    );
+
+    let recommended = percentile(&sorted, RECOMMENDED_PERCENTILE);
+
+    println!(
+        "\n{} {} micro-lamports per compute unit",
+        style("Recommended compute unit price:").bold(),
+        style(recommended).green()
+    );
+
+    if args.write {
+        println!(
+            "\n{} {}Writing priority fee to config",
+            style("[2/2]").bold().dim(),
+            PAPER_EMOJI
+        );
+
+        // only the priority fee changes, the rest of the file is kept as written
+        let config_path = resolve_config_path(&args.config);
+        set_config_number(&config_path, "priorityFee", recommended)?;
+
+        println!("Priority fee saved to '{}'", config_path);
+    }
+
+    Ok(())
+}
+
+/// Return the value at the given percentile of a sorted list.
+fn percentile(sorted: &[u64], percentile: usize) -> u64 {
+    let index = (sorted.len() * percentile / 100).min(sorted.len() - 1);
+    sorted[index]
+}
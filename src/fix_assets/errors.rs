@@ -0,0 +1,13 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum FixAssetsError {
+    #[error("Asset {0} has more than one {1} file: {2}")]
+    AmbiguousAsset(String, String, String),
+
+    #[error("Asset(s) {0} are missing their metadata or image file, fix them before renumbering")]
+    IncompleteAssets(String),
+
+    #[error("Cache item {0} has its config line on-chain and cannot be renumbered to {1}")]
+    OnChainItem(String, usize),
+}
@@ -0,0 +1,386 @@
+use console::style;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+};
+
+use crate::cache::{load_cache, CacheItems};
+use crate::common::*;
+use crate::fix_assets::FixAssetsError;
+use crate::upload::{list_files, parse_locale_filename};
+
+/// Extensions of the image files of an asset.
+const IMAGE_EXTENSIONS: [&str; 3] = ["png", "jpg", "gif"];
+
+/// Extensions of the animation files of an asset.
+const ANIMATION_EXTENSIONS: [&str; 3] = ["mp4", "mov", "webm"];
+
+/// Prefix of the files while they are being renamed, so that renumbering
+/// never overwrites a file that has not been moved yet.
+const RENAME_PREFIX: &str = ".fix-assets-";
+
+pub struct FixAssetsArgs {
+    pub assets_dir: String,
+    pub cache: String,
+    pub renumber: bool,
+    pub dry_run: bool,
+}
+
+/// Files of an asset, i.e., the files named after the same index.
+struct Asset {
+    /// File name prefix of the asset (e.g., '7' or '007').
+    stem: String,
+    index: usize,
+    files: Vec<String>,
+    /// Key of the asset in the cache, which is its index for the first asset
+    /// of an index.
+    cache_key: Option<String>,
+}
+
+impl Asset {
+    fn files_with_extensions(&self, extensions: &[&str]) -> Vec<&String> {
+        self.files
+            .iter()
+            .filter(|file| {
+                let rest = &file[self.stem.len()..];
+                extensions
+                    .iter()
+                    .any(|extension| rest.eq_ignore_ascii_case(&format!(".{}", extension)))
+            })
+            .collect()
+    }
+
+    fn is_complete(&self) -> bool {
+        !self.files_with_extensions(&["json"]).is_empty()
+            && !self.files_with_extensions(&IMAGE_EXTENSIONS).is_empty()
+    }
+
+    /// Return the name of a file of the asset under a new index.
+    fn renamed(&self, file: &str, index: usize) -> String {
+        format!("{}{}", index, &file[self.stem.len()..])
+    }
+}
+
+pub fn process_fix_assets(args: FixAssetsArgs) -> Result<()> {
+    println!(
+        "{} {}Checking asset indices",
+        style(if args.renumber { "[1/2]" } else { "[1/1]" })
+            .bold()
+            .dim(),
+        LOOKING_GLASS_EMOJI
+    );
+
+    let mut assets = read_assets(&args.assets_dir)?;
+    // the canonical file name of an index comes first, then the padded ones
+    assets.sort_by_key(|asset| {
+        (
+            asset.index,
+            asset.stem != asset.index.to_string(),
+            asset.stem.clone(),
+        )
+    });
+
+    let mut previous = None;
+    for asset in &mut assets {
+        if previous != Some(asset.index) {
+            asset.cache_key = Some(asset.index.to_string());
+        }
+        previous = Some(asset.index);
+    }
+
+    for asset in &assets {
+        for (kind, extensions) in [
+            ("image", &IMAGE_EXTENSIONS),
+            ("animation", &ANIMATION_EXTENSIONS),
+        ] {
+            let files = asset.files_with_extensions(extensions);
+            if files.len() > 1 {
+                let files: Vec<&str> = files.iter().map(|file| file.as_str()).collect();
+                return Err(FixAssetsError::AmbiguousAsset(
+                    asset.stem.clone(),
+                    kind.to_string(),
+                    files.join(", "),
+                )
+                .into());
+            }
+        }
+    }
+
+    let indices: BTreeSet<usize> = assets.iter().map(|asset| asset.index).collect();
+    let gaps: Vec<usize> = match indices.iter().next_back() {
+        Some(last) => (0..*last)
+            .filter(|index| !indices.contains(index))
+            .collect(),
+        None => Vec::new(),
+    };
+
+    let mut stems: BTreeMap<usize, Vec<&str>> = BTreeMap::new();
+    for asset in &assets {
+        stems.entry(asset.index).or_default().push(&asset.stem);
+    }
+    let duplicates: Vec<(usize, Vec<&str>)> = stems
+        .into_iter()
+        .filter(|(_, stems)| stems.len() > 1)
+        .collect();
+
+    let incomplete: Vec<&str> = assets
+        .iter()
+        .filter(|asset| !asset.is_complete())
+        .map(|asset| asset.stem.as_str())
+        .collect();
+
+    println!("Found {} asset(s).", assets.len());
+
+    if !gaps.is_empty() {
+        println!(
+            "{}",
+            style(format!("Missing indices: {}", format_indices(&gaps))).yellow()
+        );
+    }
+
+    for (index, stems) in &duplicates {
+        println!(
+            "{}",
+            style(format!(
+                "Duplicated index {}: {}",
+                index,
+                stems
+                    .iter()
+                    .map(|stem| format!("'{}'", stem))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ))
+            .yellow()
+        );
+    }
+
+    if !incomplete.is_empty() {
+        println!(
+            "{}",
+            style(format!(
+                "Incomplete assets (missing metadata or image): {}",
+                incomplete.join(", ")
+            ))
+            .yellow()
+        );
+    }
+
+    if gaps.is_empty() && duplicates.is_empty() {
+        println!("\nAsset indices are contiguous, nothing to fix.");
+        return Ok(());
+    }
+
+    if !args.renumber {
+        println!("\nRun with --renumber to renumber the assets contiguously.");
+        return Ok(());
+    }
+
+    if !incomplete.is_empty() {
+        return Err(FixAssetsError::IncompleteAssets(incomplete.join(", ")).into());
+    }
+
+    println!(
+        "\n{} {}Renumbering assets",
+        style("[2/2]").bold().dim(),
+        PAPER_EMOJI
+    );
+
+    // new index of the assets that change
+    let renumbered: Vec<(&Asset, usize)> = assets
+        .iter()
+        .enumerate()
+        .filter(|(index, asset)| asset.stem != index.to_string())
+        .map(|(index, asset)| (asset, index))
+        .collect();
+
+    let mut cache = if Path::new(&args.cache).exists() {
+        Some(load_cache(&args.cache, false)?)
+    } else {
+        None
+    };
+
+    // config lines on-chain are written by index, so their items cannot move
+    if let Some(cache) = &cache {
+        for (asset, index) in &renumbered {
+            if let Some(key) = &asset.cache_key {
+                if cache.items.0.get(key).map_or(false, |item| item.on_chain) {
+                    return Err(FixAssetsError::OnChainItem(key.clone(), *index).into());
+                }
+            }
+        }
+    }
+
+    for (asset, index) in &renumbered {
+        println!("  {} -> {}", asset.stem, index);
+    }
+
+    if args.dry_run {
+        println!("\nDry run, no files were modified.");
+        return Ok(());
+    }
+
+    rename_assets(&args.assets_dir, &renumbered)?;
+
+    if let Some(cache) = &mut cache {
+        let moved = renumber_cache(&mut cache.items, &renumbered);
+        cache.sync_file()?;
+        println!("Moved {} cache item(s) to their new index.", moved);
+    }
+
+    println!(
+        "\n{}Renumbered {} asset(s), the indices now run from 0 to {}.",
+        COMPLETE_EMOJI,
+        renumbered.len(),
+        assets.len() - 1
+    );
+    println!("Run 'sugar upload' to upload the updated metadata files.");
+
+    Ok(())
+}
+
+/// Read the assets of a directory, grouping the files by index. Files that are
+/// not named after an index (e.g., the collection files) are ignored.
+fn read_assets(assets_dir: &str) -> Result<Vec<Asset>> {
+    let mut assets: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    for entry in list_files(assets_dir)? {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let stem = match file_name.split('.').next() {
+            Some(stem) if !stem.is_empty() && stem.chars().all(|c| c.is_ascii_digit()) => stem,
+            _ => continue,
+        };
+
+        assets
+            .entry(stem.to_string())
+            .or_default()
+            .push(file_name.clone());
+    }
+
+    Ok(assets
+        .into_iter()
+        .filter_map(|(stem, mut files)| {
+            files.sort();
+            stem.parse().ok().map(|index| Asset {
+                stem,
+                index,
+                files,
+                cache_key: None,
+            })
+        })
+        .collect())
+}
+
+/// Rename the files of the assets to their new index and update the
+/// references to the renamed files in their metadata.
+fn rename_assets(assets_dir: &str, renumbered: &[(&Asset, usize)]) -> Result<()> {
+    let dir = Path::new(assets_dir);
+
+    // files are moved out of the way first, since the new name of a file can
+    // be the current name of another one
+    for (asset, _) in renumbered {
+        for file in &asset.files {
+            fs::rename(
+                dir.join(file),
+                dir.join(format!("{}{}", RENAME_PREFIX, file)),
+            )?;
+        }
+    }
+
+    for (asset, index) in renumbered {
+        for file in &asset.files {
+            let renamed = asset.renamed(file, *index);
+            fs::rename(
+                dir.join(format!("{}{}", RENAME_PREFIX, file)),
+                dir.join(&renamed),
+            )?;
+
+            if renamed.to_lowercase().ends_with(".json") {
+                update_references(&dir.join(&renamed), asset, *index)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Replace the file names of the asset (e.g., in 'image' and
+/// 'properties.files') in a metadata file, preserving its formatting.
+fn update_references(path: &Path, asset: &Asset, index: usize) -> Result<()> {
+    let mut content = fs::read_to_string(path)?;
+    let original = content.clone();
+
+    for file in &asset.files {
+        // localized variants are not referenced by file name
+        if parse_locale_filename(file).is_some() {
+            continue;
+        }
+
+        content = content.replace(
+            &format!("\"{}\"", file),
+            &format!("\"{}\"", asset.renamed(file, index)),
+        );
+    }
+
+    if content != original {
+        fs::write(path, content)?;
+    }
+
+    Ok(())
+}
+
+/// Move the cache items of the renumbered assets to their new index,
+/// returning the number of items moved. Items without assets whose index is
+/// taken by a renumbered asset are dropped.
+fn renumber_cache(items: &mut CacheItems, renumbered: &[(&Asset, usize)]) -> usize {
+    let mut moved = Vec::new();
+
+    for (asset, index) in renumbered {
+        if let Some(item) = asset
+            .cache_key
+            .as_ref()
+            .and_then(|key| items.0.shift_remove(key))
+        {
+            moved.push((index.to_string(), item));
+        }
+    }
+
+    let count = moved.len();
+
+    for (key, item) in moved {
+        if items.0.insert(key.clone(), item).is_some() {
+            warn!("Replaced stale cache item {}", key);
+        }
+    }
+
+    items.0.sort_by(|a, _, b, _| {
+        let index = |key: &str| key.parse::<i64>().unwrap_or(i64::MAX);
+        index(a).cmp(&index(b))
+    });
+
+    count
+}
+
+/// Format a sorted list of indices, collapsing consecutive ones into ranges
+/// (e.g., '3, 7-9').
+fn format_indices(indices: &[usize]) -> String {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+
+    for index in indices {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == *index => *end = *index,
+            _ => ranges.push((*index, *index)),
+        }
+    }
+
+    ranges
+        .iter()
+        .map(|(start, end)| {
+            if start == end {
+                start.to_string()
+            } else {
+                format!("{}-{}", start, end)
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
@@ -0,0 +1,34 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum FreezeError {
+    #[error("Invalid freeze period of {0} day(s), it must be between 1 and {1} days")]
+    InvalidFreezeDays(u8, u8),
+
+    #[error("Freeze is already enabled on candy machine {0}")]
+    AlreadyEnabled(String),
+
+    #[error("Freeze is not enabled on candy machine {0}")]
+    NotEnabled(String),
+
+    #[error("Keypair {0} is not the authority of candy machine {1}")]
+    NotAuthority(String, String),
+
+    #[error("Frozen NFTs cannot be thawed yet: the freeze period has not ended and the candy machine is not sold out")]
+    ThawNotAllowed,
+
+    #[error("Invalid mint address '{0}'")]
+    InvalidMint(String),
+
+    #[error("NFT {0} is not frozen by candy machine {1}")]
+    NotFrozen(String, String),
+
+    #[error("Specify the mint of the NFT to thaw or use '--all'")]
+    MissingMint,
+
+    #[error("{0} NFT(s) are still frozen, run 'thaw --all' before unlocking the funds")]
+    FrozenNfts(u64),
+
+    #[error("{0} NFT(s) could not be thawed, run 'thaw --all' again to retry")]
+    Incomplete(usize),
+}
@@ -0,0 +1,303 @@
+use anchor_client::solana_sdk::instruction::Instruction;
+use anchor_lang::prelude::AccountMeta;
+use spl_token::ID as TOKEN_PROGRAM_ID;
+
+use crate::candy_machine::{sighash, ID as CANDY_MACHINE_ID};
+use crate::common::*;
+use crate::mint::pdas::get_master_edition_pda;
+
+/// Position of the freeze flag in the uuid of the candy machine.
+pub const FREEZE_FEATURE_INDEX: usize = 0;
+
+/// Maximum freeze period (in days) accepted by the candy machine program.
+pub const MAX_FREEZE_DAYS: u8 = 31;
+
+/// Seed prefix of the freeze PDA.
+const FREEZE_PREFIX: &str = "freeze";
+
+/// State of the freeze feature of a candy machine, stored in the freeze PDA.
+///
+/// The freeze instructions were added in later versions of the candy machine
+/// program than the one of the `mpl-candy-machine` crate, so the account is
+/// decoded (and the instructions built) here.
+#[derive(Debug)]
+pub struct FreezeState {
+    pub candy_machine: Pubkey,
+    pub allow_thaw: bool,
+    pub frozen_count: u64,
+    pub mint_start: Option<i64>,
+    pub freeze_time: i64,
+    pub freeze_fee: u64,
+}
+
+impl FreezeState {
+    pub fn from_account_data(data: &[u8]) -> Result<Self> {
+        // skips the account discriminator
+        let mut cursor = data
+            .get(8..)
+            .ok_or_else(|| anyhow!("Invalid freeze account data"))?;
+
+        let candy_machine = Pubkey::new(take(&mut cursor, 32)?);
+        let allow_thaw = take(&mut cursor, 1)?[0] != 0;
+        let frozen_count = u64::from_le_bytes(take(&mut cursor, 8)?.try_into()?);
+        let mint_start = match take(&mut cursor, 1)?[0] {
+            0 => None,
+            _ => Some(i64::from_le_bytes(take(&mut cursor, 8)?.try_into()?)),
+        };
+        let freeze_time = i64::from_le_bytes(take(&mut cursor, 8)?.try_into()?);
+        let freeze_fee = u64::from_le_bytes(take(&mut cursor, 8)?.try_into()?);
+
+        Ok(FreezeState {
+            candy_machine,
+            allow_thaw,
+            frozen_count,
+            mint_start,
+            freeze_time,
+            freeze_fee,
+        })
+    }
+
+    /// Return whether the frozen NFTs can be thawed at the specified time.
+    pub fn thaw_allowed(&self, candy_machine: &CandyMachine, now: i64) -> bool {
+        self.allow_thaw
+            || candy_machine.items_redeemed >= candy_machine.data.items_available
+            || self
+                .mint_start
+                .map(|start| start + self.freeze_time <= now)
+                .unwrap_or(false)
+    }
+}
+
+fn take<'a>(cursor: &mut &'a [u8], length: usize) -> Result<&'a [u8]> {
+    if cursor.len() < length {
+        return Err(anyhow!("Invalid freeze account data"));
+    }
+
+    let (value, rest) = cursor.split_at(length);
+    *cursor = rest;
+
+    Ok(value)
+}
+
+pub fn get_freeze_pda(candy_machine: &Pubkey) -> Pubkey {
+    let seeds = &[FREEZE_PREFIX.as_bytes(), candy_machine.as_ref()];
+    let (pda, _bump) = Pubkey::find_program_address(seeds, &CANDY_MACHINE_ID);
+
+    pda
+}
+
+/// Return the state of the freeze feature, or None if it is not enabled.
+pub fn get_freeze_state(program: &Program, candy_machine: &Pubkey) -> Result<Option<FreezeState>> {
+    let freeze_pda = get_freeze_pda(candy_machine);
+
+    program
+        .rpc()
+        .get_account_with_commitment(&freeze_pda, CommitmentConfig::confirmed())?
+        .value
+        .map(|account| FreezeState::from_account_data(&account.data))
+        .transpose()
+}
+
+/// Return whether a feature flag is set in the uuid of the candy machine.
+pub fn is_feature_active(uuid: &str, feature_index: usize) -> bool {
+    uuid.as_bytes().get(feature_index) == Some(&b'1')
+}
+
+pub fn set_freeze(
+    candy_machine: Pubkey,
+    authority: Pubkey,
+    freeze_time: i64,
+    freeze_ata: Option<Pubkey>,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(candy_machine, false),
+        AccountMeta::new(authority, true),
+        AccountMeta::new(get_freeze_pda(&candy_machine), false),
+        AccountMeta::new_readonly(system_program::ID, false),
+    ];
+
+    if let Some(freeze_ata) = freeze_ata {
+        accounts.push(AccountMeta::new_readonly(freeze_ata, false));
+    }
+
+    let mut data = sighash("set_freeze").to_vec();
+    data.extend_from_slice(&freeze_time.to_le_bytes());
+
+    Instruction {
+        program_id: CANDY_MACHINE_ID,
+        accounts,
+        data,
+    }
+}
+
+pub fn remove_freeze(candy_machine: Pubkey, authority: Pubkey) -> Instruction {
+    Instruction {
+        program_id: CANDY_MACHINE_ID,
+        accounts: vec![
+            AccountMeta::new(candy_machine, false),
+            AccountMeta::new(authority, true),
+            AccountMeta::new(get_freeze_pda(&candy_machine), false),
+        ],
+        data: sighash("remove_freeze").to_vec(),
+    }
+}
+
+pub fn thaw_nft(
+    candy_machine: Pubkey,
+    token_account: Pubkey,
+    owner: Pubkey,
+    mint: Pubkey,
+    payer: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: CANDY_MACHINE_ID,
+        accounts: vec![
+            AccountMeta::new(get_freeze_pda(&candy_machine), false),
+            AccountMeta::new(candy_machine, false),
+            AccountMeta::new(token_account, false),
+            AccountMeta::new_readonly(owner, false),
+            AccountMeta::new_readonly(mint, false),
+            AccountMeta::new_readonly(get_master_edition_pda(&mint), false),
+            AccountMeta::new(payer, true),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(mpl_token_metadata::ID, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: sighash("thaw_nft").to_vec(),
+    }
+}
+
+pub fn unlock_funds(
+    candy_machine: Pubkey,
+    wallet: Pubkey,
+    authority: Pubkey,
+    freeze_ata: Option<Pubkey>,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(candy_machine, false),
+        AccountMeta::new(wallet, false),
+        AccountMeta::new(authority, true),
+        AccountMeta::new(get_freeze_pda(&candy_machine), false),
+        AccountMeta::new_readonly(system_program::ID, false),
+    ];
+
+    if let Some(freeze_ata) = freeze_ata {
+        accounts.push(AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false));
+        accounts.push(AccountMeta::new(freeze_ata, false));
+    }
+
+    Instruction {
+        program_id: CANDY_MACHINE_ID,
+        accounts,
+        data: sighash("unlock_funds").to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use data_encoding::HEXLOWER;
+
+    use super::*;
+
+    fn freeze_account(mint_start: &str) -> Vec<u8> {
+        let candy_machine = "01".repeat(32);
+        let parts: [&str; 7] = [
+            // discriminator of the FreezePDA account
+            "f2bafcf8812f71ee",
+            &candy_machine,
+            // allow_thaw
+            "00",
+            // frozen_count: 3
+            "0300000000000000",
+            mint_start,
+            // freeze_time: 1 day
+            "8051010000000000",
+            // freeze_fee: 1_000_000 lamports
+            "40420f0000000000",
+        ];
+
+        HEXLOWER.decode(parts.concat().as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn freeze_state() {
+        // mint_start: Some(2023-01-01T00:00:00Z)
+        let state = FreezeState::from_account_data(&freeze_account("0100cdb06300000000")).unwrap();
+
+        assert_eq!(state.candy_machine, Pubkey::new_from_array([1; 32]));
+        assert!(!state.allow_thaw);
+        assert_eq!(state.frozen_count, 3);
+        assert_eq!(state.mint_start, Some(1_672_531_200));
+        assert_eq!(state.freeze_time, 86_400);
+        assert_eq!(state.freeze_fee, 1_000_000);
+
+        // mint_start: None
+        let data = freeze_account("00");
+        let state = FreezeState::from_account_data(&data).unwrap();
+
+        assert_eq!(state.mint_start, None);
+        assert_eq!(state.freeze_time, 86_400);
+        assert_eq!(state.freeze_fee, 1_000_000);
+
+        assert!(FreezeState::from_account_data(&data[..data.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn freeze_instructions() {
+        let candy_machine = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let freeze_pda = get_freeze_pda(&candy_machine);
+
+        let instruction = set_freeze(candy_machine, authority, 86_400, None);
+        assert_eq!(
+            HEXLOWER.encode(&instruction.data),
+            "ca506dd082901ae98051010000000000"
+        );
+        assert_eq!(instruction.accounts.len(), 4);
+        assert_eq!(instruction.accounts[2].pubkey, freeze_pda);
+
+        let freeze_ata = Pubkey::new_unique();
+        let instruction = set_freeze(candy_machine, authority, 86_400, Some(freeze_ata));
+        assert_eq!(instruction.accounts.len(), 5);
+        assert_eq!(instruction.accounts[4].pubkey, freeze_ata);
+
+        let instruction = remove_freeze(candy_machine, authority);
+        assert_eq!(HEXLOWER.encode(&instruction.data), "01d450a8813c2efb");
+
+        let mint = Pubkey::new_unique();
+        let instruction = thaw_nft(
+            candy_machine,
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            mint,
+            authority,
+        );
+        assert_eq!(HEXLOWER.encode(&instruction.data), "5c2cd2bbac0640b7");
+        assert_eq!(instruction.accounts[0].pubkey, freeze_pda);
+        assert_eq!(
+            instruction.accounts[5].pubkey,
+            get_master_edition_pda(&mint)
+        );
+
+        let instruction = unlock_funds(candy_machine, Pubkey::new_unique(), authority, None);
+        assert_eq!(HEXLOWER.encode(&instruction.data), "af7710f58d37ff2b");
+        assert_eq!(instruction.accounts.len(), 5);
+
+        let instruction = unlock_funds(
+            candy_machine,
+            Pubkey::new_unique(),
+            authority,
+            Some(freeze_ata),
+        );
+        assert_eq!(instruction.accounts.len(), 7);
+        assert_eq!(instruction.accounts[5].pubkey, TOKEN_PROGRAM_ID);
+        assert_eq!(instruction.accounts[6].pubkey, freeze_ata);
+    }
+
+    #[test]
+    fn feature_flags() {
+        assert!(is_feature_active("100000", FREEZE_FEATURE_INDEX));
+        assert!(!is_feature_active("000000", FREEZE_FEATURE_INDEX));
+        assert!(!is_feature_active("", FREEZE_FEATURE_INDEX));
+    }
+}
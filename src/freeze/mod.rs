@@ -0,0 +1,7 @@
+pub mod errors;
+pub mod instructions;
+pub mod process;
+
+pub use errors::*;
+pub use instructions::*;
+pub use process::*;
@@ -0,0 +1,483 @@
+use anchor_client::solana_sdk::{native_token::LAMPORTS_PER_SOL, program_pack::Pack};
+use chrono::Utc;
+use console::style;
+use rayon::prelude::*;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, MemcmpEncodedBytes, RpcFilterType},
+};
+use spl_associated_token_account::{create_associated_token_account, get_associated_token_address};
+use spl_token::{
+    state::{Account as TokenAccount, AccountState},
+    ID as TOKEN_PROGRAM_ID,
+};
+use std::sync::Mutex;
+
+use crate::cache::load_cache;
+use crate::candy_machine::{get_candy_machine_state, ID as CANDY_MACHINE_ID};
+use crate::cli::FreezeAction;
+use crate::common::*;
+use crate::freeze::*;
+use crate::price::usd_suffix;
+use crate::rpc_cache;
+use crate::spend::{record_transaction, track_spend};
+use crate::transaction::send_with_retry;
+use crate::utils::*;
+
+/// Offset of the delegate address in a token account.
+const TOKEN_DELEGATE_OFFSET: usize = 76;
+
+/// Offset of the state in a token account.
+const TOKEN_STATE_OFFSET: usize = 108;
+
+pub struct FreezeArgs {
+    pub action: FreezeAction,
+}
+
+pub struct ThawArgs {
+    pub keypair: Option<String>,
+    pub rpc_url: Option<String>,
+    pub cache: String,
+    pub candy_machine: Option<String>,
+    pub mint: Option<String>,
+    pub all: bool,
+}
+
+pub struct UnlockFundsArgs {
+    pub keypair: Option<String>,
+    pub rpc_url: Option<String>,
+    pub cache: String,
+    pub candy_machine: Option<String>,
+}
+
+/// A token account holding a frozen NFT.
+struct FrozenNft {
+    token_account: Pubkey,
+    owner: Pubkey,
+    mint: Pubkey,
+}
+
+pub fn process_freeze(args: FreezeArgs) -> Result<()> {
+    match args.action {
+        FreezeAction::Enable {
+            keypair,
+            rpc_url,
+            cache,
+            candy_machine,
+            freeze_days,
+        } => enable_freeze(keypair, rpc_url, &cache, candy_machine, freeze_days),
+        FreezeAction::Disable {
+            keypair,
+            rpc_url,
+            cache,
+            candy_machine,
+        } => disable_freeze(keypair, rpc_url, &cache, candy_machine),
+    }
+}
+
+fn enable_freeze(
+    keypair: Option<String>,
+    rpc_url: Option<String>,
+    cache: &str,
+    candy_machine: Option<String>,
+    freeze_days: u8,
+) -> Result<()> {
+    track_spend("freeze", cache);
+
+    if freeze_days == 0 || freeze_days > MAX_FREEZE_DAYS {
+        return Err(FreezeError::InvalidFreezeDays(freeze_days, MAX_FREEZE_DAYS).into());
+    }
+
+    println!(
+        "{} {}Loading candy machine",
+        style("[1/2]").bold().dim(),
+        LOOKING_GLASS_EMOJI
+    );
+
+    let candy_pubkey = get_candy_machine(candy_machine, cache)?;
+    let sugar_config = sugar_setup(keypair, rpc_url)?;
+    let client = setup_client(&sugar_config)?;
+    let program = client.program(CANDY_MACHINE_ID);
+    let payer = program.payer();
+
+    let candy_machine_state = get_candy_machine_state(&sugar_config, &candy_pubkey)?;
+    check_authority(&candy_machine_state, &payer, &candy_pubkey)?;
+
+    if get_freeze_state(&program, &candy_pubkey)?.is_some() {
+        return Err(FreezeError::AlreadyEnabled(candy_pubkey.to_string()).into());
+    }
+
+    println!("Candy machine ID: {}", candy_pubkey);
+
+    println!(
+        "\n{} {}Enabling freeze",
+        style("[2/2]").bold().dim(),
+        CANDY_EMOJI
+    );
+
+    let pb = spinner_with_style();
+    pb.set_message("Sending transaction...");
+
+    let freeze_pda = get_freeze_pda(&candy_pubkey);
+    let freeze_time = freeze_days as i64 * 24 * 60 * 60;
+    let mut builder = program.request();
+
+    // the token payments are held by an account of the freeze PDA
+    let freeze_ata = match candy_machine_state.token_mint {
+        Some(token_mint) => {
+            builder = builder.instruction(create_associated_token_account(
+                &payer,
+                &freeze_pda,
+                &token_mint,
+            ));
+            Some(get_associated_token_address(&freeze_pda, &token_mint))
+        }
+        None => None,
+    };
+
+    let instructions = builder
+        .instruction(set_freeze(candy_pubkey, payer, freeze_time, freeze_ata))
+        .instructions()?;
+    let signature = send_with_retry(&program, &instructions, &[&sugar_config.keypair])?;
+
+    rpc_cache::invalidate(&candy_pubkey);
+    record_transaction(1, if freeze_ata.is_some() { 2 } else { 1 }, None);
+
+    pb.finish_with_message(format!("{} {}", style("Signature:").bold(), signature));
+
+    println!(
+        "\nNFTs minted will be frozen for {} day(s) or until the candy machine is sold out.",
+        freeze_days
+    );
+    println!("Mint funds are held until all NFTs are thawed and 'unlock-funds' is run.");
+
+    Ok(())
+}
+
+fn disable_freeze(
+    keypair: Option<String>,
+    rpc_url: Option<String>,
+    cache: &str,
+    candy_machine: Option<String>,
+) -> Result<()> {
+    track_spend("freeze", cache);
+
+    println!(
+        "{} {}Loading candy machine",
+        style("[1/2]").bold().dim(),
+        LOOKING_GLASS_EMOJI
+    );
+
+    let candy_pubkey = get_candy_machine(candy_machine, cache)?;
+    let sugar_config = sugar_setup(keypair, rpc_url)?;
+    let client = setup_client(&sugar_config)?;
+    let program = client.program(CANDY_MACHINE_ID);
+    let payer = program.payer();
+
+    let candy_machine_state = get_candy_machine_state(&sugar_config, &candy_pubkey)?;
+    check_authority(&candy_machine_state, &payer, &candy_pubkey)?;
+
+    let freeze_state = get_freeze_state(&program, &candy_pubkey)?
+        .ok_or_else(|| FreezeError::NotEnabled(candy_pubkey.to_string()))?;
+
+    println!("Candy machine ID: {}", candy_pubkey);
+
+    println!(
+        "\n{} {}Disabling freeze",
+        style("[2/2]").bold().dim(),
+        CANDY_EMOJI
+    );
+
+    let pb = spinner_with_style();
+    pb.set_message("Sending transaction...");
+
+    let signature = send_with_retry(
+        &program,
+        &[remove_freeze(candy_pubkey, payer)],
+        &[&sugar_config.keypair],
+    )?;
+
+    rpc_cache::invalidate(&candy_pubkey);
+    record_transaction(1, 1, None);
+
+    pb.finish_with_message(format!("{} {}", style("Signature:").bold(), signature));
+
+    if freeze_state.frozen_count > 0 {
+        println!(
+            "\n{} NFT(s) remain frozen, run 'sugar thaw --all' to thaw them.",
+            freeze_state.frozen_count
+        );
+    }
+
+    Ok(())
+}
+
+pub fn process_thaw(args: ThawArgs) -> Result<()> {
+    track_spend("thaw", &args.cache);
+
+    if args.mint.is_none() && !args.all {
+        return Err(FreezeError::MissingMint.into());
+    }
+
+    println!(
+        "{} {}Loading freeze state",
+        style("[1/3]").bold().dim(),
+        LOOKING_GLASS_EMOJI
+    );
+
+    let candy_pubkey = get_candy_machine(args.candy_machine, &args.cache)?;
+    let sugar_config = sugar_setup(args.keypair, args.rpc_url)?;
+    let client = setup_client(&sugar_config)?;
+    let program = client.program(CANDY_MACHINE_ID);
+    let payer = program.payer();
+
+    let candy_machine_state = get_candy_machine_state(&sugar_config, &candy_pubkey)?;
+    let freeze_state = get_freeze_state(&program, &candy_pubkey)?
+        .ok_or_else(|| FreezeError::NotEnabled(candy_pubkey.to_string()))?;
+
+    println!(
+        "Candy machine ID: {}\n{} NFT(s) frozen",
+        candy_pubkey, freeze_state.frozen_count
+    );
+
+    if !freeze_state.thaw_allowed(&candy_machine_state, Utc::now().timestamp()) {
+        return Err(FreezeError::ThawNotAllowed.into());
+    }
+
+    println!(
+        "\n{} {}Looking up frozen NFTs",
+        style("[2/3]").bold().dim(),
+        LOOKING_GLASS_EMOJI
+    );
+
+    let pb = spinner_with_style();
+    pb.set_message("Connecting...");
+
+    let frozen = match args.mint {
+        Some(mint) => {
+            let mint = Pubkey::from_str(&mint).map_err(|_| FreezeError::InvalidMint(mint))?;
+            let frozen = get_frozen_nfts(&program, &candy_pubkey, Some(&mint))?;
+
+            if frozen.is_empty() {
+                return Err(
+                    FreezeError::NotFrozen(mint.to_string(), candy_pubkey.to_string()).into(),
+                );
+            }
+
+            frozen
+        }
+        None => get_frozen_nfts(&program, &candy_pubkey, None)?,
+    };
+
+    pb.finish_and_clear();
+    println!("Found {} frozen NFT(s)", frozen.len());
+
+    println!(
+        "\n{} {}Thawing NFTs",
+        style("[3/3]").bold().dim(),
+        CANDY_EMOJI
+    );
+
+    if frozen.is_empty() {
+        println!("\nAll NFTs have been thawed.");
+        return Ok(());
+    }
+
+    let pb = progress_bar_with_style(frozen.len() as u64);
+    let failed = Mutex::new(Vec::new());
+
+    frozen.par_iter().for_each(|nft| {
+        let instruction = thaw_nft(candy_pubkey, nft.token_account, nft.owner, nft.mint, payer);
+        let result = send_with_retry(&program, &[instruction], &[&sugar_config.keypair]);
+
+        match result {
+            Ok(signature) => {
+                record_transaction(1, 1, None);
+                info!("Thawed {}: {}", nft.mint, signature);
+            }
+            Err(err) => {
+                error!("Failed to thaw {}: {}", nft.mint, err);
+                failed.lock().unwrap().push(nft.mint);
+            }
+        }
+
+        pb.inc(1);
+    });
+
+    let failed = failed.into_inner().unwrap();
+
+    if !failed.is_empty() {
+        pb.abandon_with_message(format!("{}", style("Thaw failed ").red().bold()));
+
+        println!("\nNFTs not thawed:");
+        for mint in &failed {
+            println!("- {}", mint);
+        }
+
+        return Err(FreezeError::Incomplete(failed.len()).into());
+    }
+
+    pb.finish_with_message(format!("{}", style("Thaw successful ").green().bold()));
+
+    Ok(())
+}
+
+pub fn process_unlock_funds(args: UnlockFundsArgs) -> Result<()> {
+    track_spend("unlock-funds", &args.cache);
+
+    println!(
+        "{} {}Loading freeze state",
+        style("[1/2]").bold().dim(),
+        LOOKING_GLASS_EMOJI
+    );
+
+    let candy_pubkey = get_candy_machine(args.candy_machine, &args.cache)?;
+    let sugar_config = sugar_setup(args.keypair, args.rpc_url)?;
+    let client = setup_client(&sugar_config)?;
+    let program = client.program(CANDY_MACHINE_ID);
+    let payer = program.payer();
+
+    let candy_machine_state = get_candy_machine_state(&sugar_config, &candy_pubkey)?;
+    check_authority(&candy_machine_state, &payer, &candy_pubkey)?;
+
+    let freeze_state = get_freeze_state(&program, &candy_pubkey)?
+        .ok_or_else(|| FreezeError::NotEnabled(candy_pubkey.to_string()))?;
+
+    if freeze_state.frozen_count > 0 {
+        return Err(FreezeError::FrozenNfts(freeze_state.frozen_count).into());
+    }
+
+    let freeze_pda = get_freeze_pda(&candy_pubkey);
+    let freeze_ata = candy_machine_state
+        .token_mint
+        .map(|token_mint| get_associated_token_address(&freeze_pda, &token_mint));
+
+    let funds = match freeze_ata {
+        Some(freeze_ata) => {
+            let balance = program.rpc().get_token_account_balance(&freeze_ata)?;
+            format!("{} token(s)", balance.ui_amount_string)
+        }
+        None => {
+            let balance = program.rpc().get_balance(&freeze_pda)?;
+            format!(
+                "◎ {}{}",
+                balance as f64 / LAMPORTS_PER_SOL as f64,
+                usd_suffix(balance)
+            )
+        }
+    };
+
+    println!("Candy machine ID: {}\nFunds held: {}", candy_pubkey, funds);
+
+    println!(
+        "\n{} {}Unlocking funds",
+        style("[2/2]").bold().dim(),
+        WITHDRAW_EMOJI
+    );
+
+    let pb = spinner_with_style();
+    pb.set_message("Sending transaction...");
+
+    let instruction = unlock_funds(candy_pubkey, candy_machine_state.wallet, payer, freeze_ata);
+    let signature = send_with_retry(&program, &[instruction], &[&sugar_config.keypair])?;
+
+    rpc_cache::invalidate(&candy_pubkey);
+    record_transaction(1, 1, None);
+
+    pb.finish_with_message(format!("{} {}", style("Signature:").bold(), signature));
+
+    println!(
+        "\nFunds transferred to the candy machine wallet {}.",
+        candy_machine_state.wallet
+    );
+
+    Ok(())
+}
+
+/// Return the candy machine specified, falling back to the one from the cache.
+fn get_candy_machine(candy_machine: Option<String>, cache: &str) -> Result<Pubkey> {
+    let candy_machine = match candy_machine {
+        Some(candy_machine) => candy_machine,
+        None => load_cache(cache, false)?.program.candy_machine,
+    };
+
+    Pubkey::from_str(&candy_machine)
+        .map_err(|_| CacheError::InvalidCandyMachineAddress(candy_machine).into())
+}
+
+fn check_authority(
+    candy_machine_state: &CandyMachine,
+    payer: &Pubkey,
+    candy_pubkey: &Pubkey,
+) -> Result<()> {
+    if candy_machine_state.authority != *payer {
+        return Err(FreezeError::NotAuthority(payer.to_string(), candy_pubkey.to_string()).into());
+    }
+
+    Ok(())
+}
+
+/// Return the token accounts frozen by the candy machine, which are the frozen
+/// accounts delegated to its freeze PDA, optionally restricted to a mint.
+fn get_frozen_nfts(
+    program: &Program,
+    candy_pubkey: &Pubkey,
+    mint: Option<&Pubkey>,
+) -> Result<Vec<FrozenNft>> {
+    let freeze_pda = get_freeze_pda(candy_pubkey);
+
+    let mut filters = vec![
+        RpcFilterType::DataSize(TokenAccount::LEN as u64),
+        RpcFilterType::Memcmp(Memcmp {
+            offset: TOKEN_DELEGATE_OFFSET,
+            bytes: MemcmpEncodedBytes::Base58(freeze_pda.to_string()),
+            encoding: None,
+        }),
+        RpcFilterType::Memcmp(Memcmp {
+            offset: TOKEN_STATE_OFFSET,
+            bytes: MemcmpEncodedBytes::Base58(
+                bs58::encode([AccountState::Frozen as u8]).into_string(),
+            ),
+            encoding: None,
+        }),
+    ];
+
+    if let Some(mint) = mint {
+        filters.push(RpcFilterType::Memcmp(Memcmp {
+            offset: 0,
+            bytes: MemcmpEncodedBytes::Base58(mint.to_string()),
+            encoding: None,
+        }));
+    }
+
+    let config = RpcProgramAccountsConfig {
+        filters: Some(filters),
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            data_slice: None,
+            commitment: Some(CommitmentConfig::confirmed()),
+        },
+        with_context: None,
+    };
+
+    let accounts = program
+        .rpc()
+        .get_program_accounts_with_config(&TOKEN_PROGRAM_ID, config)?;
+
+    let mut frozen = Vec::new();
+
+    for (token_account, account) in accounts {
+        if let Ok(account) = TokenAccount::unpack(&account.data) {
+            if account.amount == 1 {
+                frozen.push(FrozenNft {
+                    token_account,
+                    owner: account.owner,
+                    mint: account.mint,
+                });
+            }
+        }
+    }
+
+    Ok(frozen)
+}
@@ -0,0 +1,28 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum GenerateMetadataError {
+    #[error("Template '{0}' is not valid JSON: {1}")]
+    InvalidTemplate(String, String),
+
+    #[error("Invalid CSV file '{0}': {1}")]
+    InvalidTable(String, String),
+
+    #[error("Column '{0}' is defined more than once in the CSV header")]
+    DuplicatedColumn(String),
+
+    #[error("Metadata of asset {0} is not valid JSON once expanded: {1}")]
+    InvalidMetadata(usize, String),
+
+    #[error("Template 'attributes' must be an array to add the CSV attribute columns")]
+    InvalidAttributes,
+
+    #[error("Cannot generate {0} metadata file(s) from a CSV file with {1} row(s)")]
+    NotEnoughRows(usize, usize),
+
+    #[error("Missing number of metadata files: use --number or --csv")]
+    MissingNumber,
+
+    #[error("{0} metadata file(s) already exist (e.g., '{1}'), use --force to overwrite them")]
+    FilesExist(usize, String),
+}
@@ -0,0 +1,7 @@
+pub mod errors;
+pub mod process;
+pub mod table;
+
+pub use errors::*;
+pub use process::*;
+pub use table::*;
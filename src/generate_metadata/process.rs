@@ -0,0 +1,207 @@
+use console::style;
+use serde_json::{json, Value};
+use std::{collections::BTreeSet, fs};
+
+use crate::common::*;
+use crate::generate_metadata::{GenerateMetadataError, Table};
+use crate::upload::{expand_template, list_files, TemplateVariables};
+use crate::utils::*;
+
+pub struct GenerateMetadataArgs {
+    pub template: String,
+    pub csv: Option<String>,
+    pub number: Option<usize>,
+    pub assets_dir: String,
+    pub force: bool,
+}
+
+pub fn process_generate_metadata(args: GenerateMetadataArgs) -> Result<()> {
+    println!(
+        "{} {}Loading metadata template",
+        style("[1/2]").bold().dim(),
+        LOOKING_GLASS_EMOJI
+    );
+
+    let template = fs::read_to_string(&args.template)
+        .map_err(|err| anyhow!("Could not read template '{}': {}", args.template, err))?;
+
+    let table = match &args.csv {
+        Some(csv) => Some(Table::read(csv)?),
+        None => None,
+    };
+
+    // columns used as placeholders fill the template, the others are added
+    // to the attributes of the asset
+    let (placeholders, attributes): (Vec<&String>, Vec<&String>) = match &table {
+        Some(table) => table
+            .columns
+            .iter()
+            .partition(|column| template.contains(&format!("{{{{{}}}}}", column))),
+        None => (Vec::new(), Vec::new()),
+    };
+
+    let rows = table.as_ref().map(|table| table.rows.len());
+    let number = match (args.number, rows) {
+        (Some(number), Some(rows)) if number > rows => {
+            return Err(GenerateMetadataError::NotEnoughRows(number, rows).into())
+        }
+        (Some(number), _) => number,
+        (None, Some(rows)) => rows,
+        (None, None) => return Err(GenerateMetadataError::MissingNumber.into()),
+    };
+
+    if let Some(table) = &table {
+        println!(
+            "CSV file with {} row(s): {} placeholder column(s), {} attribute column(s).",
+            table.rows.len(),
+            placeholders.len(),
+            attributes.len()
+        );
+    }
+
+    let assets_dir = Path::new(&args.assets_dir);
+    let existing: Vec<String> = (0..number)
+        .map(|index| format!("{}.json", index))
+        .filter(|file_name| assets_dir.join(file_name).exists())
+        .collect();
+
+    if !existing.is_empty() && !args.force {
+        return Err(GenerateMetadataError::FilesExist(existing.len(), existing[0].clone()).into());
+    }
+
+    // every asset is expanded before writing, so an invalid row writes no files
+    let metadata = (0..number)
+        .map(|index| {
+            let mut variables = TemplateVariables::new();
+
+            if let Some(table) = &table {
+                for column in &placeholders {
+                    let value = table.value(index, column).unwrap_or_default();
+                    variables.insert(column.to_string(), value.to_string());
+                }
+            }
+
+            let expanded = expand_template(&template, index, &variables);
+            let mut metadata: Value = serde_json::from_str(&expanded).map_err(|err| {
+                if index == 0 {
+                    GenerateMetadataError::InvalidTemplate(args.template.clone(), err.to_string())
+                } else {
+                    GenerateMetadataError::InvalidMetadata(index, err.to_string())
+                }
+            })?;
+
+            if let Some(table) = &table {
+                add_attributes(&mut metadata, table, index, &attributes)?;
+            }
+
+            Ok(metadata)
+        })
+        .collect::<Result<Vec<Value>>>()?;
+
+    println!(
+        "\n{} {}Writing metadata files",
+        style("[2/2]").bold().dim(),
+        PAPER_EMOJI
+    );
+
+    fs::create_dir_all(assets_dir)?;
+
+    let pb = progress_bar_with_style(number as u64);
+
+    for (index, metadata) in metadata.iter().enumerate() {
+        fs::write(
+            assets_dir.join(format!("{}.json", index)),
+            serde_json::to_string_pretty(metadata)?,
+        )?;
+        pb.inc(1);
+    }
+
+    pb.finish_and_clear();
+
+    println!(
+        "\n{}Generated {} metadata file(s) in '{}'{}.",
+        COMPLETE_EMOJI,
+        number,
+        args.assets_dir,
+        if existing.is_empty() {
+            String::new()
+        } else {
+            format!(", overwriting {}", existing.len())
+        }
+    );
+
+    // metadata files are usually generated before the images are in place
+    let images = image_indices(&args.assets_dir)?;
+    let missing: Vec<usize> = (0..number)
+        .filter(|index| !images.contains(index))
+        .collect();
+
+    if !missing.is_empty() {
+        println!(
+            "{}",
+            style(format!(
+                "Asset(s) without an image file yet: {}",
+                format_ranges(&missing)
+            ))
+            .yellow()
+        );
+        println!("Add the images and run 'sugar validate' before uploading.");
+    }
+
+    Ok(())
+}
+
+/// Append the values of the attribute columns of a row to the attributes of
+/// the metadata. Empty cells are skipped, so assets can have different traits.
+fn add_attributes(
+    metadata: &mut Value,
+    table: &Table,
+    index: usize,
+    columns: &[&String],
+) -> Result<()> {
+    let traits: Vec<Value> = columns
+        .iter()
+        .filter_map(|column| {
+            table
+                .value(index, column)
+                .filter(|value| !value.trim().is_empty())
+                .map(|value| json!({ "trait_type": column, "value": value.trim() }))
+        })
+        .collect();
+
+    if traits.is_empty() {
+        return Ok(());
+    }
+
+    let metadata = metadata
+        .as_object_mut()
+        .ok_or(GenerateMetadataError::InvalidAttributes)?;
+
+    match metadata
+        .entry("attributes")
+        .or_insert_with(|| Value::Array(Vec::new()))
+    {
+        Value::Array(attributes) => attributes.extend(traits),
+        value @ Value::Null => *value = Value::Array(traits),
+        _ => return Err(GenerateMetadataError::InvalidAttributes.into()),
+    }
+
+    Ok(())
+}
+
+/// Return the indices of the assets with a non-metadata file (e.g., an image).
+fn image_indices(assets_dir: &str) -> Result<BTreeSet<usize>> {
+    Ok(list_files(assets_dir)?
+        .iter()
+        .filter_map(|entry| {
+            let file_name = entry.file_name().to_string_lossy().to_lowercase();
+            let (stem, _) = file_name.split_once('.')?;
+
+            if file_name.ends_with(".json") {
+                None
+            } else {
+                stem.parse().ok()
+            }
+        })
+        .collect())
+}
@@ -0,0 +1,245 @@
+use std::fs;
+
+use crate::common::*;
+use crate::generate_metadata::GenerateMetadataError;
+
+/// Rows of a CSV file, with the column names from its header.
+pub struct Table {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    /// Read a CSV file exported from a spreadsheet. The first line is the
+    /// header, and empty lines are ignored.
+    pub fn read(path: &str) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| anyhow!("Could not read CSV file '{}': {}", path, err))?;
+        let invalid =
+            |message: String| GenerateMetadataError::InvalidTable(path.to_string(), message);
+
+        // spreadsheets often write a byte order mark
+        let mut records = parse_csv(content.trim_start_matches('\u{feff}')).map_err(invalid)?;
+
+        if records.is_empty() {
+            return Err(invalid("missing header".to_string()).into());
+        }
+
+        let columns: Vec<String> = records
+            .remove(0)
+            .into_iter()
+            .map(|column| column.trim().to_string())
+            .collect();
+
+        for (position, column) in columns.iter().enumerate() {
+            if column.is_empty() {
+                return Err(invalid(format!("column {} has no name", position + 1)).into());
+            }
+            if columns[..position].contains(column) {
+                return Err(GenerateMetadataError::DuplicatedColumn(column.clone()).into());
+            }
+        }
+
+        let mut rows = Vec::with_capacity(records.len());
+
+        for (line, mut record) in records.into_iter().enumerate() {
+            if record.len() > columns.len() {
+                return Err(invalid(format!(
+                    "row {} has {} values, but the header has {} columns",
+                    line + 1,
+                    record.len(),
+                    columns.len()
+                ))
+                .into());
+            }
+            // trailing empty cells are often dropped on export
+            record.resize(columns.len(), String::new());
+            rows.push(record);
+        }
+
+        Ok(Table { columns, rows })
+    }
+
+    /// Return the value of a column in a row.
+    pub fn value(&self, row: usize, column: &str) -> Option<&str> {
+        let position = self.columns.iter().position(|name| name == column)?;
+        self.rows
+            .get(row)
+            .and_then(|values| values.get(position))
+            .map(String::as_str)
+    }
+}
+
+/// Parse CSV content (RFC 4180): values are separated by commas and can be
+/// quoted to contain commas, line breaks and escaped quotes (`""`). The
+/// whitespace around a value is ignored, unless it is inside the quotes.
+pub fn parse_csv(content: &str) -> std::result::Result<Vec<Vec<String>>, String> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut value = String::new();
+    let mut quoted = false;
+    // whether the current value was quoted, to keep empty quoted values
+    let mut was_quoted = false;
+    let mut line = 1;
+    let mut chars = content.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    value.push('"');
+                }
+                '"' => quoted = false,
+                '\n' => {
+                    line += 1;
+                    value.push(c);
+                }
+                _ => value.push(c),
+            }
+            continue;
+        }
+
+        match c {
+            '"' if value.trim().is_empty() && !was_quoted => {
+                value.clear();
+                quoted = true;
+                was_quoted = true;
+            }
+            '"' => return Err(format!("unexpected quote in line {}", line)),
+            ',' => {
+                record.push(take_value(&mut value, was_quoted));
+                was_quoted = false;
+            }
+            '\r' if chars.peek() == Some(&'\n') => (),
+            '\n' => {
+                end_record(&mut records, &mut record, &mut value, was_quoted);
+                was_quoted = false;
+                line += 1;
+            }
+            _ if was_quoted && c.is_whitespace() => (),
+            _ if was_quoted => {
+                return Err(format!("unexpected value after a quote in line {}", line))
+            }
+            _ => value.push(c),
+        }
+    }
+
+    if quoted {
+        return Err(format!("unterminated quote in line {}", line));
+    }
+
+    end_record(&mut records, &mut record, &mut value, was_quoted);
+
+    Ok(records)
+}
+
+fn end_record(
+    records: &mut Vec<Vec<String>>,
+    record: &mut Vec<String>,
+    value: &mut String,
+    was_quoted: bool,
+) {
+    // a line without separators or values is an empty line
+    if record.is_empty() && value.trim().is_empty() && !was_quoted {
+        value.clear();
+        return;
+    }
+
+    record.push(take_value(value, was_quoted));
+    records.push(std::mem::take(record));
+}
+
+/// Return the current value, without the whitespace around an unquoted value.
+fn take_value(value: &mut String, was_quoted: bool) -> String {
+    let value = std::mem::take(value);
+
+    if was_quoted {
+        value
+    } else {
+        value.trim().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn records(rows: &[&[&str]]) -> Vec<Vec<String>> {
+        rows.iter()
+            .map(|row| row.iter().map(|value| value.to_string()).collect())
+            .collect()
+    }
+
+    fn read_table(name: &str, content: &str) -> Result<Table> {
+        let path =
+            std::env::temp_dir().join(format!("sugar-table-{}-{}.csv", name, std::process::id()));
+        fs::write(&path, content).unwrap();
+        let table = Table::read(&path.to_string_lossy());
+        fs::remove_file(&path).ok();
+        table
+    }
+
+    #[test]
+    fn parse_quoted_values() {
+        let content = "name,description\n\"Sugar, #1\",\"a \"\"sweet\"\"\nNFT\"\n\"\",x\n";
+
+        assert_eq!(
+            parse_csv(content).unwrap(),
+            records(&[
+                &["name", "description"],
+                &["Sugar, #1", "a \"sweet\"\nNFT"],
+                &["", "x"],
+            ])
+        );
+    }
+
+    #[test]
+    fn parse_whitespace() {
+        let content = " name , color \r\n  Sugar #1 ,  \" red \"  \r\n   \r\n\nSugar #2,blue";
+
+        assert_eq!(
+            parse_csv(content).unwrap(),
+            records(&[
+                &["name", "color"],
+                &["Sugar #1", " red "],
+                &["Sugar #2", "blue"],
+            ])
+        );
+    }
+
+    #[test]
+    fn parse_invalid_quotes() {
+        assert_eq!(
+            parse_csv("name\nSugar \"1\"").unwrap_err(),
+            "unexpected quote in line 2"
+        );
+        assert_eq!(
+            parse_csv("name\n\"Sugar\" 1").unwrap_err(),
+            "unexpected value after a quote in line 2"
+        );
+        assert_eq!(
+            parse_csv("name\n\"Sugar\n").unwrap_err(),
+            "unterminated quote in line 3"
+        );
+    }
+
+    #[test]
+    fn read_header() {
+        let table = read_table("header", "\u{feff}name, color\nSugar #1,red\nSugar #2\n").unwrap();
+
+        assert_eq!(table.columns, vec!["name", "color"]);
+        assert_eq!(table.value(0, "color"), Some("red"));
+        // missing trailing values are empty
+        assert_eq!(table.value(1, "color"), Some(""));
+        assert_eq!(table.value(0, "size"), None);
+    }
+
+    #[test]
+    fn read_invalid_header() {
+        assert!(read_table("empty", "\n\n").is_err());
+        assert!(read_table("unnamed", "name,,color\n").is_err());
+        assert!(read_table("duplicated", "name,color,name\n").is_err());
+        assert!(read_table("long-row", "name\nSugar #1,red\n").is_err());
+    }
+}
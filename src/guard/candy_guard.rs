@@ -0,0 +1,257 @@
+use anchor_client::solana_sdk::instruction::Instruction;
+use anchor_lang::prelude::AccountMeta;
+
+use crate::candy_machine::sighash;
+use crate::common::*;
+use crate::config::CandyGuardData;
+
+/// Candy Guard program, which wraps the mint instruction of Candy Machine Core
+/// (v3) machines with the configured guards.
+pub mod candy_guard_program {
+    solana_program::declare_id!("Guard1JwRhJkVH6XZhzoYxeBVQe872VH6QggF4BWmS9g");
+}
+
+/// Candy Machine Core (v3) program, the only one that can be wrapped by a
+/// candy guard.
+pub mod candy_machine_core_program {
+    solana_program::declare_id!("CndyV3LdqHUfDLmE5naZjVN8rBZz4tqhdefbAnjHG3JR");
+}
+
+/// Seed prefix of the candy guard PDA.
+const CANDY_GUARD_PREFIX: &str = "candy_guard";
+
+/// Offset of the guards in the candy guard account: discriminator, base,
+/// bump and authority.
+const CANDY_GUARD_DATA_OFFSET: usize = 8 + 32 + 1 + 32;
+
+/// Offset of the authority in a Candy Machine Core account, followed by the
+/// mint authority, which is the candy guard when the candy machine is wrapped.
+const CANDY_MACHINE_AUTHORITY_OFFSET: usize = 8 + 8;
+
+/// Candy guard account. The guard program is not a dependency of Sugar, so
+/// the account is decoded (and the instructions built) here.
+#[derive(Debug)]
+pub struct CandyGuard {
+    pub base: Pubkey,
+    pub authority: Pubkey,
+    pub guards: CandyGuardData,
+}
+
+impl CandyGuard {
+    pub fn from_account_data(data: &[u8]) -> Result<Self> {
+        if data.len() < CANDY_GUARD_DATA_OFFSET {
+            return Err(anyhow!("Invalid candy guard account data"));
+        }
+
+        Ok(CandyGuard {
+            base: Pubkey::new(&data[8..40]),
+            authority: Pubkey::new(&data[41..73]),
+            guards: CandyGuardData::from_data(&data[CANDY_GUARD_DATA_OFFSET..])?,
+        })
+    }
+}
+
+pub fn get_candy_guard_pda(base: &Pubkey) -> Pubkey {
+    let seeds = &[CANDY_GUARD_PREFIX.as_bytes(), base.as_ref()];
+    let (pda, _bump) = Pubkey::find_program_address(seeds, &candy_guard_program::ID);
+
+    pda
+}
+
+/// Return the candy guard account, or None if it does not exist.
+pub fn get_candy_guard(program: &Program, candy_guard: &Pubkey) -> Result<Option<CandyGuard>> {
+    program
+        .rpc()
+        .get_account_with_commitment(candy_guard, CommitmentConfig::confirmed())?
+        .value
+        .map(|account| CandyGuard::from_account_data(&account.data))
+        .transpose()
+}
+
+/// Return the authority and mint authority of a Candy Machine Core account.
+pub fn get_candy_machine_authorities(candy_machine_data: &[u8]) -> Result<(Pubkey, Pubkey)> {
+    let authorities = candy_machine_data
+        .get(CANDY_MACHINE_AUTHORITY_OFFSET..CANDY_MACHINE_AUTHORITY_OFFSET + 64)
+        .ok_or_else(|| anyhow!("Invalid candy machine account data"))?;
+
+    Ok((
+        Pubkey::new(&authorities[..32]),
+        Pubkey::new(&authorities[32..]),
+    ))
+}
+
+/// Return the data of an instruction with the guards as its (vec) argument.
+fn guards_instruction_data(name: &str, guards: &[u8]) -> Vec<u8> {
+    let mut data = sighash(name).to_vec();
+    data.extend_from_slice(&(guards.len() as u32).to_le_bytes());
+    data.extend_from_slice(guards);
+    data
+}
+
+pub fn initialize(base: Pubkey, authority: Pubkey, payer: Pubkey, guards: &[u8]) -> Instruction {
+    Instruction {
+        program_id: candy_guard_program::ID,
+        accounts: vec![
+            AccountMeta::new(get_candy_guard_pda(&base), false),
+            AccountMeta::new_readonly(base, true),
+            AccountMeta::new_readonly(authority, false),
+            AccountMeta::new(payer, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: guards_instruction_data("initialize", guards),
+    }
+}
+
+pub fn update(candy_guard: Pubkey, authority: Pubkey, payer: Pubkey, guards: &[u8]) -> Instruction {
+    Instruction {
+        program_id: candy_guard_program::ID,
+        accounts: vec![
+            AccountMeta::new(candy_guard, false),
+            AccountMeta::new_readonly(authority, true),
+            AccountMeta::new(payer, true),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data: guards_instruction_data("update", guards),
+    }
+}
+
+pub fn wrap(candy_guard: Pubkey, authority: Pubkey, candy_machine: Pubkey) -> Instruction {
+    Instruction {
+        program_id: candy_guard_program::ID,
+        accounts: vec![
+            AccountMeta::new_readonly(candy_guard, false),
+            AccountMeta::new_readonly(authority, true),
+            AccountMeta::new(candy_machine, false),
+            AccountMeta::new_readonly(candy_machine_core_program::ID, false),
+            AccountMeta::new_readonly(authority, true),
+        ],
+        data: sighash("wrap").to_vec(),
+    }
+}
+
+pub fn unwrap(candy_guard: Pubkey, authority: Pubkey, candy_machine: Pubkey) -> Instruction {
+    Instruction {
+        program_id: candy_guard_program::ID,
+        accounts: vec![
+            AccountMeta::new_readonly(candy_guard, false),
+            AccountMeta::new_readonly(authority, true),
+            AccountMeta::new(candy_machine, false),
+            AccountMeta::new_readonly(authority, true),
+            AccountMeta::new_readonly(candy_machine_core_program::ID, false),
+        ],
+        data: sighash("unwrap").to_vec(),
+    }
+}
+
+/// Close the candy guard account, transferring its rent to the authority.
+pub fn withdraw(candy_guard: Pubkey, authority: Pubkey) -> Instruction {
+    Instruction {
+        program_id: candy_guard_program::ID,
+        accounts: vec![
+            AccountMeta::new(candy_guard, false),
+            AccountMeta::new(authority, true),
+        ],
+        data: sighash("withdraw").to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use data_encoding::HEXLOWER;
+
+    use super::*;
+    use crate::config::{AllowList, GuardDate, GuardGroup, GuardSet, SolPayment};
+
+    /// Candy guard account with a sol payment and a start date as default
+    /// guards and a 'wl' group with an allow list, laid out as the Candy
+    /// Guard program stores it.
+    fn fixture() -> Vec<u8> {
+        let base = "01".repeat(32);
+        let authority = "02".repeat(32);
+        let destination = "03".repeat(32);
+        let merkle_root = "04".repeat(32);
+
+        let parts: [&str; 12] = [
+            // discriminator of the CandyGuard account
+            "2ccfc7b8706722b5",
+            &base,
+            // bump
+            "fe",
+            &authority,
+            // features: solPayment and startDate
+            "0a00000000000000",
+            // solPayment: 1 SOL and its destination
+            "00ca9a3b00000000",
+            &destination,
+            // startDate: 2023-01-01T00:00:00Z
+            "00cdb06300000000",
+            // one group, labeled 'wl'
+            "01000000",
+            "776c00000000",
+            // features: allowList
+            "0001000000000000",
+            &merkle_root,
+        ];
+
+        HEXLOWER.decode(parts.concat().as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn candy_guard_account() {
+        let data = fixture();
+        let candy_guard = CandyGuard::from_account_data(&data).unwrap();
+
+        assert_eq!(CANDY_GUARD_DATA_OFFSET, 73);
+        assert_eq!(candy_guard.base, Pubkey::new_from_array([1; 32]));
+        assert_eq!(candy_guard.authority, Pubkey::new_from_array([2; 32]));
+        assert_eq!(
+            candy_guard.guards,
+            CandyGuardData {
+                default: GuardSet {
+                    sol_payment: Some(SolPayment {
+                        value: 1.0,
+                        destination: Pubkey::new_from_array([3; 32]),
+                    }),
+                    start_date: Some(GuardDate {
+                        date: "2023-01-01T00:00:00Z".to_string(),
+                    }),
+                    ..GuardSet::default()
+                },
+                groups: Some(vec![GuardGroup {
+                    label: "wl".to_string(),
+                    guards: GuardSet {
+                        allow_list: Some(AllowList {
+                            merkle_root: "04".repeat(32),
+                        }),
+                        ..GuardSet::default()
+                    },
+                }]),
+            }
+        );
+
+        // the guards are encoded back to the same account data
+        assert_eq!(
+            candy_guard.guards.to_data().unwrap(),
+            data[CANDY_GUARD_DATA_OFFSET..]
+        );
+
+        assert!(CandyGuard::from_account_data(&data[..CANDY_GUARD_DATA_OFFSET - 1]).is_err());
+    }
+
+    #[test]
+    fn guards_instruction() {
+        let guards = [1u8, 2, 3];
+        let instruction = update(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            &guards,
+        );
+
+        assert_eq!(instruction.program_id, candy_guard_program::ID);
+        assert_eq!(
+            HEXLOWER.encode(&instruction.data),
+            "dbc858b09e3ffd7f03000000010203"
+        );
+    }
+}
@@ -0,0 +1,61 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum GuardError {
+    #[error("Could not read wallet file '{0}': {1}")]
+    AllowListFileError(String, String),
+
+    #[error("Invalid wallet address at line {0}: '{1}'")]
+    InvalidAddress(usize, String),
+
+    #[error("Could not write block list file '{0}': {1}")]
+    BlockListWriteError(String, String),
+
+    #[error("Invalid wallet address: '{0}'")]
+    InvalidWallet(String),
+
+    #[error("Allow list '{0}' is empty")]
+    EmptyAllowList(String),
+
+    #[error("Invalid trait filter '{0}' (expected 'trait_type=value')")]
+    InvalidTraitFilter(String),
+
+    #[error("No holders found for the specified collection")]
+    NoHolders,
+
+    #[error("A creator address is required when the RPC node does not support the DAS API")]
+    MissingCreator,
+
+    #[error("Could not read proofs file '{0}': {1}")]
+    ProofsFileError(String, String),
+
+    #[error("Wallet {0} is not in the allow list")]
+    NotInAllowList(String),
+
+    #[error("The merkle proof of wallet {0} does not match the root of the allow list")]
+    InvalidProof(String),
+
+    #[error("The config file has no 'guards' section")]
+    MissingGuards,
+
+    #[error("Account {0} is not a Candy Machine Core (v3) candy machine, candy guards cannot wrap the candy machines created by 'deploy'")]
+    NotCandyMachineCore(String),
+
+    #[error("Keypair {0} is not the authority of candy machine {1}")]
+    NotCandyMachineAuthority(String, String),
+
+    #[error("Candy machine {0} is already wrapped by candy guard {1}, run 'guard update' to change the guards")]
+    AlreadyGuarded(String, String),
+
+    #[error("No candy guard found in the cache, run 'guard add' first or specify '--candy-guard'")]
+    MissingCandyGuard,
+
+    #[error("Invalid candy guard address '{0}'")]
+    InvalidCandyGuard(String),
+
+    #[error("Candy guard {0} does not exist")]
+    CandyGuardNotFound(String),
+
+    #[error("Keypair {0} is not the authority of candy guard {1}")]
+    NotGuardAuthority(String, String),
+}
@@ -0,0 +1,164 @@
+use anchor_client::solana_sdk::{account::Account, commitment_config::CommitmentConfig};
+use mpl_token_metadata::{
+    state::{Key, Metadata, MAX_METADATA_LEN},
+    utils::try_from_slice_checked,
+};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, MemcmpEncodedBytes, RpcFilterType},
+    rpc_request::RpcRequest,
+    rpc_response::{Response, RpcTokenAccountBalance},
+};
+use std::str::FromStr;
+
+use crate::common::*;
+use crate::guard::GuardError;
+
+/// Offset of the first creator address in a metadata account.
+const FIRST_CREATOR_OFFSET: usize = 326;
+
+/// An NFT of a collection, identified by its mint and metadata URI.
+pub struct CollectionItem {
+    pub mint: Pubkey,
+    pub uri: String,
+}
+
+/// Trait filter in the form 'trait_type=value'.
+pub struct TraitFilter {
+    pub trait_type: String,
+    pub value: String,
+}
+
+impl FromStr for TraitFilter {
+    type Err = GuardError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('=') {
+            Some((trait_type, value)) if !trait_type.trim().is_empty() => Ok(TraitFilter {
+                trait_type: trait_type.trim().to_string(),
+                value: value.trim().to_string(),
+            }),
+            _ => Err(GuardError::InvalidTraitFilter(s.to_string())),
+        }
+    }
+}
+
+impl TraitFilter {
+    /// Check whether the attributes of an off-chain metadata match the filter.
+    pub fn matches(&self, metadata: &Value) -> bool {
+        metadata["attributes"]
+            .as_array()
+            .map(|attributes| {
+                attributes.iter().any(|attribute| {
+                    attribute["trait_type"].as_str() == Some(&self.trait_type)
+                        && match &attribute["value"] {
+                            Value::String(value) => *value == self.value,
+                            // numeric and boolean values are compared against the
+                            // filter value parsed as JSON
+                            value => self.value.parse::<Value>().ok().as_ref() == Some(value),
+                        }
+                })
+            })
+            .unwrap_or(false)
+    }
+}
+
+/// Return the metadata accounts (address and data) of the NFTs with the
+/// specified (verified) first creator.
+pub fn get_creator_metadata(
+    program: &Program,
+    creator: &Pubkey,
+) -> Result<Vec<(Pubkey, Metadata)>> {
+    let config = RpcProgramAccountsConfig {
+        filters: Some(vec![RpcFilterType::Memcmp(Memcmp {
+            offset: FIRST_CREATOR_OFFSET,
+            bytes: MemcmpEncodedBytes::Base58(creator.to_string()),
+            encoding: None,
+        })]),
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            data_slice: None,
+            commitment: Some(CommitmentConfig::confirmed()),
+        },
+        with_context: None,
+    };
+
+    let accounts: Vec<(Pubkey, Account)> = program
+        .rpc()
+        .get_program_accounts_with_config(&mpl_token_metadata::id(), config)?;
+
+    let mut nfts = Vec::new();
+
+    for (address, account) in accounts {
+        let metadata: Metadata =
+            match try_from_slice_checked(&account.data, Key::MetadataV1, MAX_METADATA_LEN) {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+
+        let verified_creator = metadata
+            .data
+            .creators
+            .as_ref()
+            .and_then(|creators| creators.first())
+            .map(|first| first.verified && first.address == *creator)
+            .unwrap_or(false);
+
+        if verified_creator {
+            nfts.push((address, metadata));
+        }
+    }
+
+    Ok(nfts)
+}
+
+/// Return the NFTs with the specified (verified) first creator, optionally
+/// restricted to a verified collection.
+pub fn get_collection_items(
+    program: &Program,
+    creator: &Pubkey,
+    collection: Option<&Pubkey>,
+) -> Result<Vec<CollectionItem>> {
+    let mut items = Vec::new();
+
+    for (_, metadata) in get_creator_metadata(program, creator)? {
+        let in_collection = match collection {
+            Some(collection) => metadata
+                .collection
+                .as_ref()
+                .map(|c| c.verified && c.key == *collection)
+                .unwrap_or(false),
+            None => true,
+        };
+
+        if in_collection {
+            items.push(CollectionItem {
+                mint: metadata.mint,
+                uri: metadata.data.uri.trim_matches(char::from(0)).to_string(),
+            });
+        }
+    }
+
+    Ok(items)
+}
+
+/// Return the wallet currently holding an NFT, if any.
+pub fn get_holder(program: &Program, mint: &Pubkey) -> Result<Option<Pubkey>> {
+    let largest: Response<Vec<RpcTokenAccountBalance>> = program.rpc().send(
+        RpcRequest::Custom {
+            method: "getTokenLargestAccounts",
+        },
+        json!([mint.to_string()]),
+    )?;
+
+    let token_account = match largest.value.iter().find(|b| b.amount.amount == "1") {
+        Some(balance) => Pubkey::from_str(&balance.address)?,
+        None => return Ok(None),
+    };
+
+    match program.rpc().get_token_account(&token_account)? {
+        Some(account) => Ok(Some(Pubkey::from_str(&account.owner)?)),
+        None => Ok(None),
+    }
+}
@@ -0,0 +1,153 @@
+use solana_program::keccak::{hashv, Hash};
+
+use crate::common::*;
+
+/// Merkle tree of an allow list. Leaves are the keccak hash of each wallet
+/// address and pairs are hashed in sorted order, matching the verification
+/// done by the allow list guard.
+pub struct MerkleTree {
+    /// Tree levels, from the leaves (index 0) to the root.
+    levels: Vec<Vec<Hash>>,
+}
+
+impl MerkleTree {
+    pub fn new(wallets: &[Pubkey]) -> MerkleTree {
+        let mut levels = vec![wallets
+            .iter()
+            .map(|wallet| hashv(&[wallet.as_ref()]))
+            .collect::<Vec<Hash>>()];
+
+        while levels.last().map(|l| l.len()).unwrap_or(0) > 1 {
+            let level = levels.last().unwrap();
+            let next = level
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => hash_pair(left, right),
+                    // odd node is promoted to the next level
+                    [node] => *node,
+                    _ => unreachable!(),
+                })
+                .collect();
+            levels.push(next);
+        }
+
+        MerkleTree { levels }
+    }
+
+    pub fn root(&self) -> Hash {
+        self.levels
+            .last()
+            .and_then(|level| level.first())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Return the proof for the leaf at the specified index.
+    pub fn proof(&self, index: usize) -> Vec<Hash> {
+        let mut proof = Vec::new();
+        let mut index = index;
+
+        for level in &self.levels[..self.levels.len() - 1] {
+            let sibling = if index % 2 == 0 { index + 1 } else { index - 1 };
+
+            if let Some(hash) = level.get(sibling) {
+                proof.push(*hash);
+            }
+
+            index /= 2;
+        }
+
+        proof
+    }
+}
+
+/// Hash two nodes in sorted order.
+fn hash_pair(a: &Hash, b: &Hash) -> Hash {
+    if a.as_ref() <= b.as_ref() {
+        hashv(&[a.as_ref(), b.as_ref()])
+    } else {
+        hashv(&[b.as_ref(), a.as_ref()])
+    }
+}
+
+/// Check a proof against a merkle root.
+pub fn verify_proof(wallet: &Pubkey, proof: &[Hash], root: &Hash) -> bool {
+    let computed = proof
+        .iter()
+        .fold(hashv(&[wallet.as_ref()]), |computed, node| {
+            hash_pair(&computed, node)
+        });
+
+    computed == *root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wallets(count: usize) -> Vec<Pubkey> {
+        (0..count).map(|_| Pubkey::new_unique()).collect()
+    }
+
+    #[test]
+    fn single_wallet_root_is_its_leaf() {
+        let wallets = wallets(1);
+        let tree = MerkleTree::new(&wallets);
+
+        assert_eq!(tree.root(), hashv(&[wallets[0].as_ref()]));
+        assert!(tree.proof(0).is_empty());
+        assert!(verify_proof(&wallets[0], &[], &tree.root()));
+    }
+
+    #[test]
+    fn two_wallets_root() {
+        let wallets = wallets(2);
+        let tree = MerkleTree::new(&wallets);
+        let left = hashv(&[wallets[0].as_ref()]);
+        let right = hashv(&[wallets[1].as_ref()]);
+
+        // the order of the pair does not change its hash
+        assert_eq!(tree.root(), hash_pair(&left, &right));
+        assert_eq!(tree.root(), hash_pair(&right, &left));
+        assert_eq!(tree.proof(0), vec![right]);
+        assert_eq!(tree.proof(1), vec![left]);
+    }
+
+    #[test]
+    fn every_proof_verifies() {
+        // odd sizes promote the last node of a level
+        for count in [2, 3, 5, 8, 13] {
+            let wallets = wallets(count);
+            let tree = MerkleTree::new(&wallets);
+
+            for (index, wallet) in wallets.iter().enumerate() {
+                assert!(
+                    verify_proof(wallet, &tree.proof(index), &tree.root()),
+                    "wallet {} of {}",
+                    index,
+                    count
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_proofs_are_rejected() {
+        let wallets = wallets(5);
+        let tree = MerkleTree::new(&wallets);
+        let root = tree.root();
+
+        // a wallet outside of the list
+        assert!(!verify_proof(&Pubkey::new_unique(), &tree.proof(0), &root));
+        // the proof of another wallet
+        assert!(!verify_proof(&wallets[0], &tree.proof(1), &root));
+        // a proof missing a node
+        assert!(!verify_proof(&wallets[0], &tree.proof(0)[1..], &root));
+        // the root of another list
+        assert!(!verify_proof(
+            &wallets[0],
+            &tree.proof(0),
+            &MerkleTree::new(&wallets[..4]).root()
+        ));
+    }
+}
@@ -0,0 +1,11 @@
+pub mod candy_guard;
+pub mod errors;
+pub mod holders;
+pub mod merkle;
+pub mod process;
+
+pub use candy_guard::*;
+pub use errors::*;
+pub use holders::*;
+pub use merkle::*;
+pub use process::*;
@@ -0,0 +1,939 @@
+use console::style;
+use data_encoding::HEXLOWER;
+use serde::Serialize;
+use solana_program::keccak::Hash;
+use std::{
+    collections::{BTreeSet, HashSet},
+    fs,
+    str::FromStr,
+};
+
+use crate::cache::load_cache;
+use crate::cli::{AllowListAction, BlockListAction, GuardCommand};
+use crate::common::*;
+use crate::config::{get_config_data, CandyGuardData};
+use crate::das::DasClient;
+use crate::guard::*;
+use crate::rpc_cache;
+use crate::spend::{record_rent, record_transaction, track_spend};
+use crate::transaction::send_with_retry;
+use crate::utils::*;
+
+pub struct GuardArgs {
+    pub command: GuardCommand,
+}
+
+/// Allow list proofs file, mapping each wallet to its merkle proof.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AllowListProofs {
+    pub root: String,
+    pub proofs: IndexMap<String, Vec<String>>,
+}
+
+pub async fn process_guard(args: GuardArgs) -> Result<()> {
+    match args.command {
+        GuardCommand::Allowlist { action } => match action {
+            AllowListAction::ExportProofs { allowlist, output } => {
+                process_export_proofs(&allowlist, &output)
+            }
+            AllowListAction::FromHolders {
+                keypair,
+                rpc_url,
+                creator,
+                collection,
+                traits,
+                output,
+            } => {
+                process_from_holders(keypair, rpc_url, creator, collection, &traits, &output).await
+            }
+        },
+        GuardCommand::Blocklist { action } => match action {
+            BlockListAction::Update {
+                blocklist,
+                add,
+                remove,
+            } => process_update_blocklist(&blocklist, &add, &remove),
+            BlockListAction::Check { wallet, blocklist } => {
+                process_check_blocklist(&wallet, &blocklist)
+            }
+        },
+        GuardCommand::Proof {
+            wallet,
+            allowlist,
+            proofs,
+        } => process_guard_proof(&wallet, allowlist, &proofs),
+        GuardCommand::Add {
+            keypair,
+            rpc_url,
+            cache,
+            config,
+            candy_machine,
+        } => process_add_guard(keypair, rpc_url, &cache, &config, candy_machine),
+        GuardCommand::Update {
+            keypair,
+            rpc_url,
+            cache,
+            config,
+            candy_guard,
+        } => process_update_guard(keypair, rpc_url, &cache, &config, candy_guard),
+        GuardCommand::Remove {
+            keypair,
+            rpc_url,
+            cache,
+            candy_machine,
+            candy_guard,
+        } => process_remove_guard(keypair, rpc_url, &cache, candy_machine, candy_guard),
+        GuardCommand::Show {
+            keypair,
+            rpc_url,
+            cache,
+            candy_guard,
+        } => process_show_guard(keypair, rpc_url, &cache, candy_guard),
+    }
+}
+
+fn process_export_proofs(allowlist: &str, output: &str) -> Result<()> {
+    println!(
+        "{} {}Building allow list merkle tree",
+        style("[1/2]").bold().dim(),
+        LOOKING_GLASS_EMOJI
+    );
+
+    let wallets = read_allow_list(allowlist)?;
+
+    println!(
+        "\n{} {}Writing proofs",
+        style("[2/2]").bold().dim(),
+        PAPER_EMOJI
+    );
+
+    write_allow_list_proofs(&wallets, output)
+}
+
+async fn process_from_holders(
+    keypair: Option<String>,
+    rpc_url: Option<String>,
+    creator: Option<String>,
+    collection: Option<String>,
+    traits: &[String],
+    output: &str,
+) -> Result<()> {
+    let sugar_config = sugar_setup(keypair, rpc_url)?;
+    let client = setup_client(&sugar_config)?;
+    let program = client.program(mpl_token_metadata::id());
+
+    let creator = creator.map(|c| Pubkey::from_str(&c)).transpose()?;
+    let collection = collection.map(|c| Pubkey::from_str(&c)).transpose()?;
+    let filters = traits
+        .iter()
+        .map(|t| TraitFilter::from_str(t))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    println!(
+        "{} {}Retrieving collection holders",
+        style("[1/2]").bold().dim(),
+        LOOKING_GLASS_EMOJI
+    );
+
+    // the DAS API returns the owner and metadata of every item in a single
+    // indexed source, otherwise items and holders are looked up individually
+    let wallets = match DasClient::connect(&sugar_config.rpc_url).await {
+        Some(das) => {
+            info!("Using DAS API to retrieve holders");
+            holders_from_das(&das, creator.as_ref(), collection.as_ref(), &filters).await?
+        }
+        None => {
+            let creator = creator.ok_or(GuardError::MissingCreator)?;
+            holders_from_rpc(&program, &creator, collection.as_ref(), &filters).await?
+        }
+    };
+
+    println!("{} unique holder(s)", wallets.len());
+
+    if wallets.is_empty() {
+        return Err(GuardError::NoHolders.into());
+    }
+
+    println!(
+        "\n{} {}Writing proofs",
+        style("[2/2]").bold().dim(),
+        PAPER_EMOJI
+    );
+
+    write_allow_list_proofs(&wallets, output)
+}
+
+/// Retrieve the holders of a collection from the DAS API.
+async fn holders_from_das(
+    das: &DasClient,
+    creator: Option<&Pubkey>,
+    collection: Option<&Pubkey>,
+    filters: &[TraitFilter],
+) -> Result<Vec<Pubkey>> {
+    let pb = spinner_with_style();
+    pb.set_message("Retrieving assets...");
+
+    let assets = match (collection, creator) {
+        (Some(collection), _) => das.get_assets_by_collection(collection).await?,
+        (None, Some(creator)) => das.get_assets_by_creator(creator).await?,
+        (None, None) => return Err(GuardError::MissingCreator.into()),
+    };
+
+    pb.finish_and_clear();
+    println!("Found {} item(s)", assets.len());
+
+    let mut wallets = Vec::new();
+    let mut seen = HashSet::new();
+    let mut matching = 0;
+
+    for asset in assets {
+        let created = match creator {
+            Some(creator) => asset
+                .creators
+                .first()
+                .map(|first| first.verified && first.address == creator.to_string())
+                .unwrap_or(false),
+            None => true,
+        };
+
+        let metadata = asset
+            .content
+            .as_ref()
+            .map(|content| &content.metadata)
+            .unwrap_or(&Value::Null);
+
+        if created && filters.iter().all(|filter| filter.matches(metadata)) {
+            matching += 1;
+            let holder = Pubkey::from_str(&asset.ownership.owner)?;
+
+            if seen.insert(holder) {
+                wallets.push(holder);
+            }
+        }
+    }
+
+    if !filters.is_empty() {
+        println!("{} item(s) match the trait filter", matching);
+    }
+
+    Ok(wallets)
+}
+
+/// Retrieve the holders of a collection using RPC requests.
+async fn holders_from_rpc(
+    program: &Program,
+    creator: &Pubkey,
+    collection: Option<&Pubkey>,
+    filters: &[TraitFilter],
+) -> Result<Vec<Pubkey>> {
+    let pb = spinner_with_style();
+    pb.set_message("Retrieving items...");
+
+    let mut items = get_collection_items(program, creator, collection)?;
+
+    pb.finish_and_clear();
+    println!("Found {} item(s)", items.len());
+
+    if !filters.is_empty() {
+        let pb = progress_bar_with_style(items.len() as u64);
+        pb.set_message("Checking traits ");
+
+        let http_client = http_client();
+        let mut matching = Vec::new();
+
+        for item in items {
+            let metadata: Value = match http_client.get(&item.uri).send().await {
+                Ok(response) => match response.json().await {
+                    Ok(metadata) => metadata,
+                    Err(err) => {
+                        warn!("Could not parse metadata of {}: {}", item.mint, err);
+                        pb.inc(1);
+                        continue;
+                    }
+                },
+                Err(err) => {
+                    warn!("Could not retrieve metadata of {}: {}", item.mint, err);
+                    pb.inc(1);
+                    continue;
+                }
+            };
+
+            if filters.iter().all(|filter| filter.matches(&metadata)) {
+                matching.push(item);
+            }
+            pb.inc(1);
+        }
+
+        pb.finish_and_clear();
+        println!("{} item(s) match the trait filter", matching.len());
+        items = matching;
+    }
+
+    let pb = progress_bar_with_style(items.len() as u64);
+    pb.set_message("Retrieving holders ");
+
+    let mut wallets = Vec::new();
+    let mut seen = HashSet::new();
+
+    for item in &items {
+        if let Some(holder) = get_holder(program, &item.mint)? {
+            if seen.insert(holder) {
+                wallets.push(holder);
+            }
+        }
+        pb.inc(1);
+    }
+
+    pb.finish_and_clear();
+
+    Ok(wallets)
+}
+
+/// Build the merkle tree of the wallets and write the proof of each wallet
+/// to the output file.
+pub fn write_allow_list_proofs(wallets: &[Pubkey], output: &str) -> Result<()> {
+    let tree = MerkleTree::new(wallets);
+    let root = tree.root();
+
+    println!("Allow list with {} wallet(s)", wallets.len());
+    println!(
+        "{} {}",
+        style("Merkle root:").bold(),
+        HEXLOWER.encode(root.as_ref())
+    );
+
+    let mut proofs = IndexMap::new();
+
+    for (index, wallet) in wallets.iter().enumerate() {
+        let proof = tree.proof(index);
+
+        // sanity check: every proof must verify against the root
+        if !verify_proof(wallet, &proof, &root) {
+            return Err(anyhow!("Invalid merkle proof generated for {}", wallet));
+        }
+
+        proofs.insert(
+            wallet.to_string(),
+            proof.iter().map(|h| HEXLOWER.encode(h.as_ref())).collect(),
+        );
+    }
+
+    let file = File::create(output)?;
+    serde_json::to_writer_pretty(
+        file,
+        &AllowListProofs {
+            root: HEXLOWER.encode(root.as_ref()),
+            proofs,
+        },
+    )?;
+
+    println!("Proofs saved to '{}'", output);
+    println!("Set the root as the 'merkleRoot' of the 'allowList' guard in the config file.");
+
+    Ok(())
+}
+
+/// Print the merkle proof of a wallet, either from the proofs file or from a
+/// tree built from the allow list file.
+fn process_guard_proof(wallet: &str, allowlist: Option<String>, proofs: &str) -> Result<()> {
+    let wallet =
+        Pubkey::from_str(wallet).map_err(|_| GuardError::InvalidWallet(wallet.to_string()))?;
+
+    let (root, proof) = match allowlist {
+        Some(allowlist) => {
+            let wallets = read_allow_list(&allowlist)?;
+            let index = wallets
+                .iter()
+                .position(|w| *w == wallet)
+                .ok_or_else(|| GuardError::NotInAllowList(wallet.to_string()))?;
+            let tree = MerkleTree::new(&wallets);
+
+            (tree.root(), tree.proof(index))
+        }
+        None => {
+            let file = File::open(proofs)
+                .map_err(|e| GuardError::ProofsFileError(proofs.to_string(), e.to_string()))?;
+            let allow_list: AllowListProofs = serde_json::from_reader(file)
+                .map_err(|e| GuardError::ProofsFileError(proofs.to_string(), e.to_string()))?;
+            let proof = allow_list
+                .proofs
+                .get(&wallet.to_string())
+                .ok_or_else(|| GuardError::NotInAllowList(wallet.to_string()))?;
+
+            (
+                decode_hash(&allow_list.root, proofs)?,
+                proof
+                    .iter()
+                    .map(|node| decode_hash(node, proofs))
+                    .collect::<Result<Vec<Hash>>>()?,
+            )
+        }
+    };
+
+    if !verify_proof(&wallet, &proof, &root) {
+        return Err(GuardError::InvalidProof(wallet.to_string()).into());
+    }
+
+    // printed as JSON so it can be consumed by front-end tooling
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&json!({
+            "wallet": wallet.to_string(),
+            "root": HEXLOWER.encode(root.as_ref()),
+            "proof": proof
+                .iter()
+                .map(|node| HEXLOWER.encode(node.as_ref()))
+                .collect::<Vec<String>>(),
+        }))?
+    );
+
+    Ok(())
+}
+
+/// Decode a hex encoded node of the proofs file.
+fn decode_hash(value: &str, proofs: &str) -> Result<Hash> {
+    HEXLOWER
+        .decode(value.as_bytes())
+        .ok()
+        .filter(|bytes| bytes.len() == 32)
+        .map(|bytes| Hash::new(&bytes))
+        .ok_or_else(|| {
+            GuardError::ProofsFileError(proofs.to_string(), format!("invalid hash '{}'", value))
+                .into()
+        })
+}
+
+/// Merge the wallets of the 'add' files into the block list and remove the
+/// wallets of the 'remove' files. The list is kept sorted so updates between
+/// sale phases produce minimal diffs.
+fn process_update_blocklist(blocklist: &str, add: &[String], remove: &[String]) -> Result<()> {
+    println!(
+        "{} {}Loading block list",
+        style("[1/2]").bold().dim(),
+        LOOKING_GLASS_EMOJI
+    );
+
+    let mut wallets: BTreeSet<Pubkey> = if Path::new(blocklist).exists() {
+        read_wallets(blocklist)?.into_iter().collect()
+    } else {
+        BTreeSet::new()
+    };
+
+    println!("Block list with {} wallet(s)", wallets.len());
+
+    let mut added = 0;
+    for path in add {
+        for wallet in read_wallets(path)? {
+            if wallets.insert(wallet) {
+                added += 1;
+            }
+        }
+    }
+
+    let mut removed = 0;
+    for path in remove {
+        for wallet in read_wallets(path)? {
+            if wallets.remove(&wallet) {
+                removed += 1;
+            }
+        }
+    }
+
+    println!(
+        "\n{} {}Writing block list",
+        style("[2/2]").bold().dim(),
+        PAPER_EMOJI
+    );
+
+    let content: String = wallets.iter().map(|w| format!("{}\n", w)).collect();
+    fs::write(blocklist, content)
+        .map_err(|e| GuardError::BlockListWriteError(blocklist.to_string(), e.to_string()))?;
+
+    println!(
+        "{} wallet(s) added, {} wallet(s) removed, {} wallet(s) blocked",
+        added,
+        removed,
+        wallets.len()
+    );
+
+    if !wallets.is_empty() {
+        let wallets: Vec<Pubkey> = wallets.into_iter().collect();
+        println!(
+            "{} {}",
+            style("Merkle root:").bold(),
+            HEXLOWER.encode(MerkleTree::new(&wallets).root().as_ref())
+        );
+    }
+
+    println!("Block list saved to '{}'", blocklist);
+
+    Ok(())
+}
+
+fn process_check_blocklist(wallet: &str, blocklist: &str) -> Result<()> {
+    let wallet =
+        Pubkey::from_str(wallet).map_err(|_| GuardError::InvalidWallet(wallet.to_string()))?;
+    let wallets = read_wallets(blocklist)?;
+
+    if wallets.contains(&wallet) {
+        println!("{} {}", wallet, style("is blocked").red().bold());
+    } else {
+        println!("{} {}", wallet, style("is not blocked").green().bold());
+    }
+
+    Ok(())
+}
+
+fn process_add_guard(
+    keypair: Option<String>,
+    rpc_url: Option<String>,
+    cache_file: &str,
+    config: &str,
+    candy_machine: Option<String>,
+) -> Result<()> {
+    track_spend("guard", cache_file);
+
+    println!(
+        "{} {}Loading candy machine",
+        style("[1/3]").bold().dim(),
+        LOOKING_GLASS_EMOJI
+    );
+
+    let guard_data = get_config_guards(config)?;
+    let mut cache = load_cache(cache_file, true)?;
+    let candy_pubkey = get_candy_machine_core(candy_machine, &cache)?;
+
+    let sugar_config = sugar_setup(keypair, rpc_url)?;
+    let client = setup_client(&sugar_config)?;
+    let program = client.program(candy_guard_program::ID);
+    let payer = program.payer();
+
+    let account = program.rpc().get_account(&candy_pubkey)?;
+
+    if account.owner != candy_machine_core_program::ID {
+        return Err(GuardError::NotCandyMachineCore(candy_pubkey.to_string()).into());
+    }
+
+    let (authority, mint_authority) = get_candy_machine_authorities(&account.data)?;
+
+    if authority != payer {
+        return Err(GuardError::NotCandyMachineAuthority(
+            payer.to_string(),
+            candy_pubkey.to_string(),
+        )
+        .into());
+    }
+
+    if mint_authority != authority {
+        return Err(GuardError::AlreadyGuarded(
+            candy_pubkey.to_string(),
+            mint_authority.to_string(),
+        )
+        .into());
+    }
+
+    println!("Candy machine ID: {}", candy_pubkey);
+
+    println!(
+        "\n{} {}Creating candy guard",
+        style("[2/3]").bold().dim(),
+        CANDY_EMOJI
+    );
+
+    let pb = spinner_with_style();
+    pb.set_message("Sending transaction...");
+
+    // a candy guard left by a previous (interrupted) run is reused
+    let existing = match cache.program.candy_guard.as_ref() {
+        Some(candy_guard) => {
+            let candy_guard = parse_candy_guard(candy_guard)?;
+            get_candy_guard(&program, &candy_guard)?
+                .filter(|state| state.authority == payer)
+                .map(|_| candy_guard)
+        }
+        None => None,
+    };
+
+    let candy_guard = match existing {
+        Some(candy_guard) => {
+            let signature = send_with_retry(
+                &program,
+                &[update(candy_guard, payer, payer, &guard_data)],
+                &[&sugar_config.keypair],
+            )?;
+
+            record_transaction(1, 1, None);
+            pb.finish_with_message(format!("{} {}", style("Signature:").bold(), signature));
+
+            candy_guard
+        }
+        None => {
+            let base = Keypair::new();
+            let candy_guard = get_candy_guard_pda(&base.pubkey());
+
+            let signature = send_with_retry(
+                &program,
+                &[initialize(base.pubkey(), payer, payer, &guard_data)],
+                &[&sugar_config.keypair as &dyn Signer, &base],
+            )?;
+
+            record_transaction(2, 1, None);
+            record_rent(program.rpc().get_balance(&candy_guard)?);
+            pb.finish_with_message(format!("{} {}", style("Signature:").bold(), signature));
+
+            cache.program.candy_guard = Some(candy_guard.to_string());
+            cache.sync_file()?;
+
+            candy_guard
+        }
+    };
+
+    println!(
+        "\n{} {}Wrapping candy machine",
+        style("[3/3]").bold().dim(),
+        PAPER_EMOJI
+    );
+
+    let pb = spinner_with_style();
+    pb.set_message("Sending transaction...");
+
+    let signature = send_with_retry(
+        &program,
+        &[wrap(candy_guard, payer, candy_pubkey)],
+        &[&sugar_config.keypair],
+    )?;
+
+    rpc_cache::invalidate(&candy_pubkey);
+    record_transaction(1, 1, None);
+
+    pb.finish_with_message(format!("{} {}", style("Signature:").bold(), signature));
+
+    println!(
+        "\n{} {}",
+        style("Candy guard ID:").bold(),
+        style(candy_guard).green()
+    );
+
+    Ok(())
+}
+
+fn process_update_guard(
+    keypair: Option<String>,
+    rpc_url: Option<String>,
+    cache_file: &str,
+    config: &str,
+    candy_guard: Option<String>,
+) -> Result<()> {
+    track_spend("guard", cache_file);
+
+    println!(
+        "{} {}Loading candy guard",
+        style("[1/2]").bold().dim(),
+        LOOKING_GLASS_EMOJI
+    );
+
+    let guard_data = get_config_guards(config)?;
+    let candy_guard = get_candy_guard_address(candy_guard, cache_file)?;
+
+    let sugar_config = sugar_setup(keypair, rpc_url)?;
+    let client = setup_client(&sugar_config)?;
+    let program = client.program(candy_guard_program::ID);
+    let payer = program.payer();
+
+    load_candy_guard(&program, &candy_guard, &payer)?;
+
+    println!("Candy guard ID: {}", candy_guard);
+
+    println!(
+        "\n{} {}Updating guards",
+        style("[2/2]").bold().dim(),
+        PAPER_EMOJI
+    );
+
+    let pb = spinner_with_style();
+    pb.set_message("Sending transaction...");
+
+    let signature = send_with_retry(
+        &program,
+        &[update(candy_guard, payer, payer, &guard_data)],
+        &[&sugar_config.keypair],
+    )?;
+
+    record_transaction(1, 1, None);
+
+    pb.finish_with_message(format!("{} {}", style("Signature:").bold(), signature));
+
+    Ok(())
+}
+
+fn process_remove_guard(
+    keypair: Option<String>,
+    rpc_url: Option<String>,
+    cache_file: &str,
+    candy_machine: Option<String>,
+    candy_guard: Option<String>,
+) -> Result<()> {
+    track_spend("guard", cache_file);
+
+    println!(
+        "{} {}Loading candy guard",
+        style("[1/2]").bold().dim(),
+        LOOKING_GLASS_EMOJI
+    );
+
+    let mut cache = load_cache(cache_file, true)?;
+    let candy_guard = match candy_guard {
+        Some(candy_guard) => parse_candy_guard(&candy_guard)?,
+        None => parse_candy_guard(
+            cache
+                .program
+                .candy_guard
+                .as_ref()
+                .ok_or(GuardError::MissingCandyGuard)?,
+        )?,
+    };
+
+    let sugar_config = sugar_setup(keypair, rpc_url)?;
+    let client = setup_client(&sugar_config)?;
+    let program = client.program(candy_guard_program::ID);
+    let payer = program.payer();
+
+    load_candy_guard(&program, &candy_guard, &payer)?;
+
+    // the candy machine is only unwrapped if the candy guard is its mint authority
+    let wrapped = match candy_machine {
+        Some(candy_machine) => {
+            let candy_pubkey = Pubkey::from_str(&candy_machine)
+                .map_err(|_| CacheError::InvalidCandyMachineAddress(candy_machine.clone()))?;
+            let account = program.rpc().get_account(&candy_pubkey)?;
+            let wrapped = account.owner == candy_machine_core_program::ID
+                && get_candy_machine_authorities(&account.data)?.1 == candy_guard;
+
+            wrapped.then_some(candy_pubkey)
+        }
+        None => None,
+    };
+
+    println!("Candy guard ID: {}", candy_guard);
+
+    println!(
+        "\n{} {}Removing candy guard",
+        style("[2/2]").bold().dim(),
+        WITHDRAW_EMOJI
+    );
+
+    let pb = spinner_with_style();
+    pb.set_message("Sending transaction...");
+
+    let mut instructions = Vec::new();
+
+    if let Some(candy_pubkey) = wrapped {
+        instructions.push(unwrap(candy_guard, payer, candy_pubkey));
+    }
+
+    instructions.push(withdraw(candy_guard, payer));
+
+    let signature = send_with_retry(&program, &instructions, &[&sugar_config.keypair])?;
+
+    if let Some(candy_pubkey) = wrapped {
+        rpc_cache::invalidate(&candy_pubkey);
+    }
+    record_transaction(1, if wrapped.is_some() { 2 } else { 1 }, None);
+
+    pb.finish_with_message(format!("{} {}", style("Signature:").bold(), signature));
+
+    if cache.program.candy_guard == Some(candy_guard.to_string()) {
+        cache.program.candy_guard = None;
+        cache.sync_file()?;
+    }
+
+    if wrapped.is_none() {
+        println!("\nNo wrapped candy machine specified, use '--candy-machine' to unwrap it.");
+    }
+
+    Ok(())
+}
+
+fn process_show_guard(
+    keypair: Option<String>,
+    rpc_url: Option<String>,
+    cache_file: &str,
+    candy_guard: Option<String>,
+) -> Result<()> {
+    println!(
+        "{} {}Loading candy guard",
+        style("[1/1]").bold().dim(),
+        LOOKING_GLASS_EMOJI
+    );
+
+    let candy_guard = get_candy_guard_address(candy_guard, cache_file)?;
+
+    let sugar_config = sugar_setup(keypair, rpc_url)?;
+    let client = setup_client(&sugar_config)?;
+    let program = client.program(candy_guard_program::ID);
+
+    let pb = spinner_with_style();
+    pb.set_message("Connecting...");
+
+    let state = get_candy_guard(&program, &candy_guard)?
+        .ok_or_else(|| GuardError::CandyGuardNotFound(candy_guard.to_string()))?;
+
+    pb.finish_and_clear();
+
+    println!("\n{} {}", style("Candy guard ID:").bold(), candy_guard);
+    println!("{} {}", style("Base:").bold(), state.base);
+    println!("{} {}", style("Authority:").bold(), state.authority);
+    println!(
+        "\n{}\n{}",
+        style("Guards:").bold(),
+        serde_json::to_string_pretty(&state.guards)?
+    );
+
+    Ok(())
+}
+
+/// Serialize the guards of the config file.
+fn get_config_guards(config: &str) -> Result<Vec<u8>> {
+    let guards: CandyGuardData = get_config_data(config)?
+        .guards
+        .ok_or(GuardError::MissingGuards)?;
+
+    guards.to_data()
+}
+
+/// Return the candy machine specified, or the one from the cache.
+fn get_candy_machine_core(candy_machine: Option<String>, cache: &Cache) -> Result<Pubkey> {
+    let candy_machine = candy_machine.unwrap_or_else(|| cache.program.candy_machine.clone());
+
+    Pubkey::from_str(&candy_machine)
+        .map_err(|_| CacheError::InvalidCandyMachineAddress(candy_machine).into())
+}
+
+/// Return the candy guard specified, or the one from the cache.
+fn get_candy_guard_address(candy_guard: Option<String>, cache_file: &str) -> Result<Pubkey> {
+    match candy_guard {
+        Some(candy_guard) => parse_candy_guard(&candy_guard),
+        None => parse_candy_guard(
+            &load_cache(cache_file, false)?
+                .program
+                .candy_guard
+                .ok_or(GuardError::MissingCandyGuard)?,
+        ),
+    }
+}
+
+fn parse_candy_guard(candy_guard: &str) -> Result<Pubkey> {
+    Pubkey::from_str(candy_guard)
+        .map_err(|_| GuardError::InvalidCandyGuard(candy_guard.to_string()).into())
+}
+
+/// Check that the candy guard exists and that the payer is its authority.
+fn load_candy_guard(program: &Program, candy_guard: &Pubkey, payer: &Pubkey) -> Result<()> {
+    let state = get_candy_guard(program, candy_guard)?
+        .ok_or_else(|| GuardError::CandyGuardNotFound(candy_guard.to_string()))?;
+
+    if state.authority != *payer {
+        return Err(
+            GuardError::NotGuardAuthority(payer.to_string(), candy_guard.to_string()).into(),
+        );
+    }
+
+    Ok(())
+}
+
+/// Read an allow list file with one wallet address per line (or the first
+/// column of a CSV file). Duplicated addresses are ignored.
+pub fn read_allow_list(path: &str) -> Result<Vec<Pubkey>> {
+    let wallets = read_wallets(path)?;
+
+    if wallets.is_empty() {
+        return Err(GuardError::EmptyAllowList(path.to_string()).into());
+    }
+
+    Ok(wallets)
+}
+
+/// Read a file with one wallet address per line (or the first column of a CSV
+/// file). Duplicated addresses are ignored.
+pub fn read_wallets(path: &str) -> Result<Vec<Pubkey>> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| GuardError::AllowListFileError(path.to_string(), e.to_string()))?;
+
+    parse_wallets(&content)
+}
+
+/// Parse the wallet addresses of a file, skipping empty lines, comments (#)
+/// and a header line. Addresses can be quoted, as spreadsheets export them.
+fn parse_wallets(content: &str) -> Result<Vec<Pubkey>> {
+    let mut wallets = Vec::new();
+    let mut seen = HashSet::new();
+
+    // spreadsheets often write a byte order mark
+    for (number, line) in content.trim_start_matches('\u{feff}').lines().enumerate() {
+        let address = line
+            .split(',')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .trim_matches('"')
+            .trim();
+
+        if address.is_empty() || address.starts_with('#') {
+            continue;
+        }
+
+        match Pubkey::from_str(address) {
+            Ok(wallet) => {
+                if seen.insert(wallet) {
+                    wallets.push(wallet);
+                }
+            }
+            // header line
+            Err(_) if number == 0 => continue,
+            Err(_) => {
+                return Err(GuardError::InvalidAddress(number + 1, address.to_string()).into())
+            }
+        }
+    }
+
+    Ok(wallets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_wallets_of_a_csv_file() {
+        let first = Pubkey::new_unique();
+        let second = Pubkey::new_unique();
+        let content = format!(
+            "\u{feff}address,amount\r\n{},1\r\n\r\n# team\r\n  \"{}\" , 2\r\n{}\r\n",
+            first, second, first
+        );
+
+        assert_eq!(parse_wallets(&content).unwrap(), vec![first, second]);
+    }
+
+    #[test]
+    fn parse_wallets_without_header() {
+        let wallet = Pubkey::new_unique();
+
+        assert_eq!(
+            parse_wallets(&format!("{}\n", wallet)).unwrap(),
+            vec![wallet]
+        );
+        assert!(parse_wallets("# no wallets\n\n").unwrap().is_empty());
+    }
+
+    #[test]
+    fn parse_invalid_wallet() {
+        let content = format!("address\n{}\nnot-a-wallet\n", Pubkey::new_unique());
+        let error = parse_wallets(&content).unwrap_err();
+
+        assert!(matches!(
+            error.downcast_ref::<GuardError>(),
+            Some(GuardError::InvalidAddress(3, address)) if address == "not-a-wallet"
+        ));
+    }
+}
@@ -0,0 +1,13 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum InitError {
+    #[error("Directory '{0}' is not empty, choose a new project directory")]
+    DirectoryNotEmpty(String),
+
+    #[error("Invalid guard '{0}' (supported guards: whitelist, gatekeeper, end-settings)")]
+    InvalidGuard(String),
+
+    #[error("The whitelist guard requires a whitelist token mint (--whitelist-mint)")]
+    MissingWhitelistMint,
+}
@@ -0,0 +1,249 @@
+use anchor_client::solana_sdk::signer::Signer;
+use chrono::{Duration, Utc};
+use console::style;
+use serde_json::json;
+use std::{fs, path::Path, str::FromStr};
+
+use crate::common::*;
+use crate::config::{
+    Cluster, ConfigData, Creator, EndSettingType, EndSettings, GatekeeperConfig, UploadMethod,
+    WhitelistMintMode, WhitelistMintSettings,
+};
+use crate::credentials::{NFT_STORAGE_TOKEN, PINATA_JWT};
+use crate::init::InitError;
+use crate::setup::sugar_setup;
+
+/// Placeholder image (1x1 PNG) of the example assets.
+const PLACEHOLDER_PNG: [u8; 69] = [
+    0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44, 0x52,
+    0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x02, 0x00, 0x00, 0x00, 0x90, 0x77, 0x53,
+    0xde, 0x00, 0x00, 0x00, 0x0c, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9c, 0x63, 0x78, 0x29, 0x97, 0x0c,
+    0x00, 0x03, 0x5e, 0x01, 0x6b, 0xc6, 0x24, 0x78, 0x59, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e,
+    0x44, 0xae, 0x42, 0x60, 0x82,
+];
+
+/// Civic captcha gatekeeper network.
+const CAPTCHA_GATEKEEPER_NETWORK: &str = "ignREusXmGrscGNUesoU9mxfds9AiYTezUKex2PsZV6";
+
+/// Public RPC endpoints suggested for each cluster.
+const PUBLIC_RPC_DEVNET: &str = "https://api.devnet.solana.com";
+const PUBLIC_RPC_MAINNET: &str = "https://api.mainnet-beta.solana.com";
+
+/// Files that should not be committed: cache, journal, logs, keypairs and
+/// command state files.
+const GITIGNORE: &str = "\
+# sugar cache and state files
+cache.json
+cache.db
+*.json.lock
+*.db.lock
+*.journal.json
+*.uploads/
+distribution.json
+sign-progress.json
+preview.html
+.sugar/
+sugar.log
+
+# keypairs
+*keypair*.json
+*wallet*.json
+id.json
+
+# local validator
+test-ledger/
+";
+
+pub struct InitArgs {
+    pub directory: String,
+    pub keypair: Option<String>,
+    pub cluster: String,
+    pub upload_method: String,
+    pub guards: Vec<String>,
+    pub whitelist_mint: Option<String>,
+}
+
+pub fn process_init(args: InitArgs) -> Result<()> {
+    let cluster = Cluster::from_str(&args.cluster)?;
+    let upload_method = UploadMethod::from_str(&args.upload_method)?;
+
+    let project_dir = Path::new(&args.directory);
+
+    if project_dir.exists() && fs::read_dir(project_dir)?.next().is_some() {
+        return Err(InitError::DirectoryNotEmpty(args.directory).into());
+    }
+
+    println!(
+        "{} {}Creating project configuration",
+        style("[1/2]").bold().dim(),
+        PAPER_EMOJI
+    );
+
+    let sugar_config = sugar_setup(args.keypair, None)?;
+
+    let mut config_data = ConfigData {
+        price: 0.1,
+        number: 1,
+        symbol: "SUGAR".to_string(),
+        seller_fee_basis_points: 500,
+        retain_authority: true,
+        is_mutable: true,
+        creators: vec![Creator {
+            address: sugar_config.keypair.pubkey(),
+            share: 100,
+        }],
+        ..Default::default()
+    };
+
+    // devnet projects can go live straight away, while mainnet projects are
+    // given a week to be reviewed before the sale starts
+    let go_live_date = match cluster {
+        Cluster::Devnet => Utc::now(),
+        Cluster::Mainnet => Utc::now() + Duration::days(7),
+    };
+    config_data.go_live_date = go_live_date.format("%d %b %Y %H:%M:%S %z").to_string();
+
+    match upload_method {
+        UploadMethod::AWS => config_data.aws_s3_bucket = Some("<bucket-name>".to_string()),
+        // API tokens are left out so they can be read from the environment
+        UploadMethod::Pinata | UploadMethod::NftStorage => (),
+        UploadMethod::ShadowDrive => {
+            config_data.shadow_drive_storage_account = Some("<storage-account>".to_string())
+        }
+        UploadMethod::ArweaveNative => {
+            config_data.arweave_jwk = Some("<arweave-wallet.json>".to_string())
+        }
+        UploadMethod::Bundlr => (),
+    }
+    config_data.upload_method = upload_method.clone();
+
+    for guard in &args.guards {
+        match guard.to_lowercase().as_str() {
+            "whitelist" => {
+                let mint = match &args.whitelist_mint {
+                    Some(mint) => Pubkey::from_str(mint)
+                        .map_err(|_| anyhow!("Invalid whitelist mint: {}", mint))?,
+                    None => return Err(InitError::MissingWhitelistMint.into()),
+                };
+                config_data.whitelist_mint_settings = Some(WhitelistMintSettings::new(
+                    WhitelistMintMode::BurnEveryTime,
+                    mint,
+                    true,
+                    None,
+                ));
+            }
+            "gatekeeper" => {
+                config_data.gatekeeper = Some(GatekeeperConfig::new(
+                    Pubkey::from_str(CAPTCHA_GATEKEEPER_NETWORK)?,
+                    true,
+                ));
+            }
+            "end-settings" => {
+                config_data.end_settings =
+                    Some(EndSettings::new(EndSettingType::Amount, config_data.number));
+            }
+            _ => return Err(InitError::InvalidGuard(guard.to_string()).into()),
+        }
+    }
+
+    println!(
+        "\n{} {}Writing project files",
+        style("[2/2]").bold().dim(),
+        ASSETS_EMOJI
+    );
+
+    let assets_dir = project_dir.join(DEFAULT_ASSETS);
+    fs::create_dir_all(&assets_dir)?;
+
+    fs::write(
+        project_dir.join(DEFAULT_CONFIG),
+        serde_json::to_string_pretty(&config_data)?,
+    )?;
+    fs::write(project_dir.join(".gitignore"), GITIGNORE)?;
+
+    let example = json!({
+        "name": "Sugar #1",
+        "symbol": config_data.symbol,
+        "description": "An example item, replace it with your own assets.",
+        "seller_fee_basis_points": config_data.seller_fee_basis_points,
+        "image": "0.png",
+        "attributes": [{ "trait_type": "Background", "value": "Pink" }],
+        "properties": {
+            "files": [{ "uri": "0.png", "type": "image/png" }],
+            "category": "image"
+        }
+    });
+    let collection = json!({
+        "name": "Sugar Collection",
+        "symbol": config_data.symbol,
+        "description": "The collection of the project.",
+        "seller_fee_basis_points": config_data.seller_fee_basis_points,
+        "image": "collection.png",
+        "attributes": [],
+        "properties": {
+            "files": [{ "uri": "collection.png", "type": "image/png" }],
+            "category": "image"
+        }
+    });
+
+    fs::write(assets_dir.join("0.png"), PLACEHOLDER_PNG)?;
+    fs::write(
+        assets_dir.join("0.json"),
+        serde_json::to_string_pretty(&example)?,
+    )?;
+    fs::write(assets_dir.join("collection.png"), PLACEHOLDER_PNG)?;
+    fs::write(
+        assets_dir.join("collection.json"),
+        serde_json::to_string_pretty(&collection)?,
+    )?;
+
+    for file in [
+        DEFAULT_CONFIG.to_string(),
+        ".gitignore".to_string(),
+        format!("{}/0.png", DEFAULT_ASSETS),
+        format!("{}/0.json", DEFAULT_ASSETS),
+        format!("{}/collection.png", DEFAULT_ASSETS),
+        format!("{}/collection.json", DEFAULT_ASSETS),
+    ] {
+        println!("  {}", project_dir.join(file).display());
+    }
+
+    println!(
+        "\n{} {}",
+        style("Project created.").green().bold(),
+        CONFETTI_EMOJI
+    );
+
+    match upload_method {
+        UploadMethod::AWS => println!("\nSet 'awsS3Bucket' in {} to your bucket.", DEFAULT_CONFIG),
+        UploadMethod::Pinata => println!(
+            "\nSet your JWT in the {} environment variable (or 'pinataJwt' in {}).",
+            PINATA_JWT.env_var, DEFAULT_CONFIG
+        ),
+        UploadMethod::NftStorage => println!(
+            "\nSet your API token in the {} environment variable (or 'nftStorageToken' in {}).",
+            NFT_STORAGE_TOKEN.env_var, DEFAULT_CONFIG
+        ),
+        UploadMethod::ShadowDrive => println!(
+            "\nSet 'shadowDriveStorageAccount' in {} to your storage account.",
+            DEFAULT_CONFIG
+        ),
+        UploadMethod::ArweaveNative => println!(
+            "\nSet 'arweaveJwk' in {} to the path of your Arweave wallet.",
+            DEFAULT_CONFIG
+        ),
+        UploadMethod::Bundlr => (),
+    }
+
+    let rpc_url = match cluster {
+        Cluster::Devnet => PUBLIC_RPC_DEVNET,
+        Cluster::Mainnet => PUBLIC_RPC_MAINNET,
+    };
+
+    println!("\nNext steps:");
+    println!("  cd {}", project_dir.display());
+    println!("  sugar validate");
+    println!("  sugar launch -r {}", rpc_url);
+
+    Ok(())
+}
@@ -0,0 +1,69 @@
+use std::{
+    io::{stdin, stdout, Write},
+    sync::atomic::{AtomicBool, Ordering},
+};
+use thiserror::Error;
+
+use crate::common::*;
+
+/// Whether the user can be prompted, cleared by the `--no-interaction` option.
+static INTERACTIVE: AtomicBool = AtomicBool::new(true);
+
+/// Whether confirmation prompts are answered with yes, set by the `--yes`
+/// option.
+static ASSUME_YES: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Error)]
+pub enum InteractionError {
+    #[error("Confirmation required ({0}), run with --yes to confirm it without a prompt.")]
+    ConfirmationRequired(String),
+
+    #[error("Cannot prompt for {0} with --no-interaction.")]
+    PromptDisabled(String),
+}
+
+/// Disable the prompts and progress bars for the current process.
+pub fn disable_interaction() {
+    INTERACTIVE.store(false, Ordering::SeqCst);
+}
+
+/// Answer the confirmation prompts with yes for the current process.
+pub fn enable_assume_yes() {
+    ASSUME_YES.store(true, Ordering::SeqCst);
+}
+
+/// Return whether the user can be prompted.
+pub fn is_interactive() -> bool {
+    INTERACTIVE.load(Ordering::SeqCst)
+}
+
+/// Fail if the user cannot be prompted for a value.
+pub fn ensure_interactive(value: &str) -> Result<()> {
+    if is_interactive() {
+        Ok(())
+    } else {
+        Err(InteractionError::PromptDisabled(value.to_string()).into())
+    }
+}
+
+/// Ask the user to confirm an action (defaulting to no). With `--yes` the
+/// action is confirmed without a prompt, while without interaction the
+/// confirmation fails.
+pub fn confirm(prompt: &str) -> Result<bool> {
+    if ASSUME_YES.load(Ordering::SeqCst) {
+        println!("\n{} [Y/n] Y (--yes)", prompt);
+        return Ok(true);
+    }
+
+    if !is_interactive() {
+        return Err(InteractionError::ConfirmationRequired(prompt.to_string()).into());
+    }
+
+    print!("\n{} [Y/n] (default \'n\'): ", prompt);
+    stdout().flush().ok();
+
+    let mut s = String::new();
+    stdin().read_line(&mut s).expect("Error reading input.");
+
+    Ok(matches!(s.chars().next(), Some('Y')))
+}
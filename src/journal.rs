@@ -0,0 +1,180 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::{fs, io::Write};
+
+use crate::common::*;
+use crate::spend::SpendSummary;
+
+/// Progress journal recording the completion state of each pipeline stage
+/// (and its sub-batches), used to resume a command after a crash.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Journal {
+    pub stages: IndexMap<String, JournalStage>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub spend: Vec<SpendRecord>,
+    #[serde(skip_deserializing, skip_serializing)]
+    pub file_path: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalStage {
+    pub status: StageStatus,
+    pub updated_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub batches: IndexMap<String, StageStatus>,
+}
+
+/// Lamports spent by a command run.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpendRecord {
+    pub command: String,
+    pub recorded_at: String,
+    #[serde(flatten)]
+    pub summary: SpendSummary,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum StageStatus {
+    #[default]
+    Pending,
+    InProgress,
+    Completed,
+    Failed,
+}
+
+impl Journal {
+    /// Load the journal kept next to the specified cache file, or create an
+    /// empty one if it does not exist.
+    pub fn load_for_cache(cache_file_path: &str) -> Result<Journal> {
+        let file_path = journal_path(cache_file_path);
+
+        let mut journal = if Path::new(&file_path).exists() {
+            let file = File::open(&file_path)?;
+            serde_json::from_reader(file)
+                .map_err(|e| anyhow!("Failed to parse journal file '{}': {}", file_path, e))?
+        } else {
+            Journal::default()
+        };
+
+        journal.file_path = file_path;
+
+        Ok(journal)
+    }
+
+    pub fn sync_file(&self) -> Result<()> {
+        let content = serde_json::to_string_pretty(&self)?;
+        let mut file = fs::File::create(&self.file_path)?;
+        file.write_all(content.as_bytes())?;
+
+        Ok(())
+    }
+
+    pub fn status(&self, stage: &str) -> StageStatus {
+        self.stages.get(stage).map(|s| s.status).unwrap_or_default()
+    }
+
+    pub fn is_completed(&self, stage: &str) -> bool {
+        self.status(stage) == StageStatus::Completed
+    }
+
+    pub fn start(&mut self, stage: &str) -> Result<()> {
+        self.set_status(stage, StageStatus::InProgress, None)
+    }
+
+    pub fn complete(&mut self, stage: &str) -> Result<()> {
+        self.set_status(stage, StageStatus::Completed, None)
+    }
+
+    pub fn fail(&mut self, stage: &str, error: &str) -> Result<()> {
+        self.set_status(stage, StageStatus::Failed, Some(error.to_string()))
+    }
+
+    /// Record the completion of a sub-batch of a stage.
+    pub fn complete_batch(&mut self, stage: &str, batch: &str) -> Result<()> {
+        let entry = self.stages.entry(stage.to_string()).or_default();
+        entry
+            .batches
+            .insert(batch.to_string(), StageStatus::Completed);
+        entry.updated_at = Utc::now().to_rfc3339();
+
+        self.sync_file()
+    }
+
+    pub fn is_batch_completed(&self, stage: &str, batch: &str) -> bool {
+        self.stages
+            .get(stage)
+            .and_then(|s| s.batches.get(batch))
+            .map(|status| *status == StageStatus::Completed)
+            .unwrap_or(false)
+    }
+
+    /// Number of completed sub-batches of a stage.
+    pub fn completed_batches(&self, stage: &str) -> usize {
+        self.stages
+            .get(stage)
+            .map(|s| {
+                s.batches
+                    .values()
+                    .filter(|status| **status == StageStatus::Completed)
+                    .count()
+            })
+            .unwrap_or(0)
+    }
+
+    /// Record the lamports spent by a command run. The spend history is kept
+    /// when the progress is reset.
+    pub fn record_spend(&mut self, command: &str, summary: SpendSummary) -> Result<()> {
+        self.spend.push(SpendRecord {
+            command: command.to_string(),
+            recorded_at: Utc::now().to_rfc3339(),
+            summary,
+        });
+
+        self.sync_file()
+    }
+
+    /// Remove all recorded progress.
+    pub fn reset(&mut self) -> Result<()> {
+        self.stages.clear();
+        self.sync_file()
+    }
+
+    fn set_status(
+        &mut self,
+        stage: &str,
+        status: StageStatus,
+        error: Option<String>,
+    ) -> Result<()> {
+        let entry = self.stages.entry(stage.to_string()).or_default();
+        entry.status = status;
+        entry.error = error;
+        entry.updated_at = Utc::now().to_rfc3339();
+
+        // sub-batches are only relevant while a stage is not complete
+        if status == StageStatus::Completed {
+            entry.batches.clear();
+        }
+
+        self.sync_file()
+    }
+}
+
+/// Return the path of the journal file for the specified cache file (e.g.,
+/// 'cache.json' uses 'cache.journal.json').
+pub fn journal_path(cache_file_path: &str) -> String {
+    let cache_path = Path::new(cache_file_path);
+    let stem = cache_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("cache");
+
+    cache_path
+        .with_file_name(format!("{}{}", stem, JOURNAL_SUFFIX))
+        .to_string_lossy()
+        .to_string()
+}
@@ -0,0 +1,212 @@
+use age::{
+    armor::{ArmoredReader, ArmoredWriter, Format},
+    DecryptError, Decryptor, Encryptor,
+};
+use anchor_client::solana_sdk::signature::read_keypair;
+use dialoguer::{theme::ColorfulTheme, Password};
+use secrecy::SecretString;
+use std::{
+    env, fs,
+    io::{Cursor, Read, Write},
+};
+
+use crate::common::*;
+use crate::interaction::ensure_interactive;
+use crate::keypair::KeypairError;
+
+/// Environment variable with the passphrase of encrypted keypairs, used
+/// instead of prompting for it (e.g., on CI machines).
+pub const KEYPAIR_PASSPHRASE_ENV: &str = "SUGAR_KEYPAIR_PASSPHRASE";
+
+/// First line of binary age files.
+const AGE_HEADER: &[u8] = b"age-encryption.org/";
+
+/// First line of ASCII-armored age files.
+const AGE_ARMOR_HEADER: &[u8] = b"-----BEGIN AGE ENCRYPTED FILE-----";
+
+/// Header stanza of files encrypted with a passphrase.
+const SCRYPT_STANZA: &str = "-> scrypt ";
+
+/// Minimum scrypt work factor (log2 of N) accepted when decrypting. age picks
+/// a work factor that takes around 1 second, which is well above this on any
+/// machine able to run sugar; lower values come from tampered or weak files.
+pub const MIN_SCRYPT_WORK_FACTOR: u8 = 15;
+
+/// Return true if the contents of a file are an age encrypted file, either
+/// binary or ASCII-armored.
+pub fn is_encrypted_keypair(contents: &[u8]) -> bool {
+    let start = contents
+        .iter()
+        .position(|byte| !byte.is_ascii_whitespace())
+        .unwrap_or(contents.len());
+
+    contents.starts_with(AGE_HEADER) || contents[start..].starts_with(AGE_ARMOR_HEADER)
+}
+
+/// Encrypt a keypair with a passphrase as an ASCII-armored age file (scrypt
+/// recipient). The plaintext is the keypair JSON byte array, so the file can
+/// also be decrypted with `age --decrypt`.
+pub fn encrypt_keypair(keypair: &Keypair, passphrase: &str) -> Result<Vec<u8>> {
+    let plaintext = serde_json::to_vec(&keypair.to_bytes().to_vec())?;
+    let encryptor = Encryptor::with_user_passphrase(SecretString::new(passphrase.to_string()));
+
+    let mut output = Vec::new();
+    let armored = ArmoredWriter::wrap_output(&mut output, Format::AsciiArmor)?;
+    let mut writer = encryptor
+        .wrap_output(armored)
+        .map_err(|err| anyhow!("Could not encrypt the keypair: {}", err))?;
+    writer.write_all(&plaintext)?;
+    writer.finish()?.finish()?;
+
+    Ok(output)
+}
+
+/// Decrypt an age encrypted keypair with a passphrase. The path is only used
+/// in the error messages.
+pub fn decrypt_keypair(contents: &[u8], passphrase: &str, path: &str) -> Result<Keypair> {
+    let mut encrypted = Vec::new();
+    ArmoredReader::new(contents)
+        .read_to_end(&mut encrypted)
+        .map_err(|err| KeypairError::InvalidFormat(err.to_string()))?;
+
+    let work_factor = scrypt_work_factor(&encrypted)?;
+    if work_factor < MIN_SCRYPT_WORK_FACTOR {
+        return Err(KeypairError::WeakWorkFactor(work_factor, MIN_SCRYPT_WORK_FACTOR).into());
+    }
+
+    let decryptor = match Decryptor::new(&encrypted[..]) {
+        Ok(Decryptor::Passphrase(decryptor)) => decryptor,
+        Ok(Decryptor::Recipients(_)) => {
+            return Err(KeypairError::Unsupported(
+                "recipient".to_string(),
+                "public key".to_string(),
+            )
+            .into())
+        }
+        Err(err) => return Err(KeypairError::InvalidFormat(err.to_string()).into()),
+    };
+
+    let mut reader = decryptor
+        .decrypt(&SecretString::new(passphrase.to_string()), None)
+        .map_err(|err| match err {
+            DecryptError::DecryptionFailed
+            | DecryptError::KeyDecryptionFailed
+            | DecryptError::InvalidMac
+            | DecryptError::NoMatchingKeys => KeypairError::DecryptionFailed(path.to_string()),
+            err => KeypairError::InvalidFormat(err.to_string()),
+        })?;
+
+    let mut plaintext = Vec::new();
+    reader
+        .read_to_end(&mut plaintext)
+        .map_err(|_| KeypairError::DecryptionFailed(path.to_string()))?;
+
+    read_keypair(&mut Cursor::new(plaintext))
+        .map_err(|err| KeypairError::InvalidFormat(format!("keypair: {}", err)).into())
+}
+
+/// Return the scrypt work factor from the header of a (binary) age file
+/// encrypted with a passphrase.
+fn scrypt_work_factor(encrypted: &[u8]) -> Result<u8> {
+    let header = String::from_utf8_lossy(encrypted);
+
+    for line in header.lines().take_while(|line| !line.starts_with("---")) {
+        if let Some(args) = line.strip_prefix(SCRYPT_STANZA) {
+            return args
+                .split(' ')
+                .nth(1)
+                .and_then(|log_n| log_n.parse::<u8>().ok())
+                .ok_or_else(|| {
+                    KeypairError::InvalidFormat(format!("scrypt stanza: {}", line)).into()
+                });
+        }
+    }
+
+    Err(KeypairError::Unsupported("recipient".to_string(), "public key".to_string()).into())
+}
+
+/// Read a keypair file, either a plain JSON byte array or an age encrypted
+/// keypair, asking for its passphrase.
+pub fn read_keypair_path(path: &str) -> Result<Keypair> {
+    let contents = fs::read(&*shellexpand::tilde(path))
+        .map_err(|err| anyhow!("Failed to read keypair file: {}, {}", path, err))?;
+
+    if is_encrypted_keypair(&contents) {
+        let passphrase = keypair_passphrase(&format!("Passphrase of {}", path), false)?;
+        decrypt_keypair(&contents, &passphrase, path)
+    } else {
+        read_keypair(&mut Cursor::new(contents))
+            .map_err(|err| anyhow!("Failed to read keypair file: {}, {}", path, err))
+    }
+}
+
+/// Return the passphrase of an encrypted keypair, from the environment or a
+/// hidden prompt (confirmed twice when creating a new one).
+pub fn keypair_passphrase(prompt: &str, confirm: bool) -> Result<String> {
+    if let Ok(passphrase) = env::var(KEYPAIR_PASSPHRASE_ENV) {
+        return Ok(passphrase);
+    }
+
+    ensure_interactive(&format!(
+        "the keypair passphrase (set it with {})",
+        KEYPAIR_PASSPHRASE_ENV
+    ))?;
+
+    let theme = ColorfulTheme::default();
+    let mut password = Password::with_theme(&theme);
+    password.with_prompt(prompt);
+
+    if confirm {
+        password.with_confirmation("Repeat the passphrase", "Passphrases do not match");
+    }
+
+    Ok(password.interact()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encrypt_decrypt_roundtrip() {
+        let keypair = Keypair::new();
+        let encrypted = encrypt_keypair(&keypair, "correct horse").unwrap();
+
+        assert!(is_encrypted_keypair(&encrypted));
+        assert!(encrypted.starts_with(AGE_ARMOR_HEADER));
+
+        let decrypted = decrypt_keypair(&encrypted, "correct horse", "test.json").unwrap();
+        assert_eq!(decrypted.to_bytes(), keypair.to_bytes());
+    }
+
+    #[test]
+    fn wrong_passphrase() {
+        let encrypted = encrypt_keypair(&Keypair::new(), "correct horse").unwrap();
+        let err = decrypt_keypair(&encrypted, "battery staple", "test.json").unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<KeypairError>(),
+            Some(KeypairError::DecryptionFailed(path)) if path == "test.json"
+        ));
+    }
+
+    #[test]
+    fn weak_work_factor() {
+        let encrypted =
+            b"age-encryption.org/v1\n-> scrypt c2FsdHNhbHRzYWx0c2FsdA 10\nAAAA\n--- AAAA\n";
+        let err = decrypt_keypair(encrypted, "correct horse", "test.json").unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<KeypairError>(),
+            Some(KeypairError::WeakWorkFactor(10, MIN_SCRYPT_WORK_FACTOR))
+        ));
+    }
+
+    #[test]
+    fn plain_keypair_is_not_encrypted() {
+        let keypair = Keypair::new();
+        let contents = serde_json::to_vec(&keypair.to_bytes().to_vec()).unwrap();
+
+        assert!(!is_encrypted_keypair(&contents));
+    }
+}
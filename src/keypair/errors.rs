@@ -0,0 +1,22 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum KeypairError {
+    #[error("Unsupported encrypted keypair {0} '{1}'")]
+    Unsupported(String, String),
+
+    #[error("Invalid encrypted keypair: {0}")]
+    InvalidFormat(String),
+
+    #[error("Could not decrypt keypair '{0}': wrong passphrase or corrupted file")]
+    DecryptionFailed(String),
+
+    #[error("Encrypted keypair work factor {0} is below the minimum {1}")]
+    WeakWorkFactor(u8, u8),
+
+    #[error("Invalid seed phrase: {0}")]
+    InvalidSeedPhrase(String),
+
+    #[error("Invalid derivation path '{0}' ({1})")]
+    InvalidDerivationPath(String, String),
+}
@@ -0,0 +1,9 @@
+pub mod encrypted;
+pub mod errors;
+pub mod process;
+pub mod seed_phrase;
+
+pub use encrypted::*;
+pub use errors::*;
+pub use process::*;
+pub use seed_phrase::*;
@@ -0,0 +1,69 @@
+use console::style;
+use std::fs;
+
+use crate::cli::KeypairAction;
+use crate::common::*;
+use crate::keypair::{
+    encrypt_keypair, keypair_passphrase, read_keypair_path, read_seed_phrase_keypair,
+    SEED_PHRASE_SCHEME,
+};
+
+pub struct KeypairArgs {
+    pub action: KeypairAction,
+}
+
+pub fn process_keypair(args: KeypairArgs) -> Result<()> {
+    match args.action {
+        KeypairAction::Encrypt { keypair, output } => process_keypair_encrypt(&keypair, &output),
+    }
+}
+
+fn process_keypair_encrypt(keypair: &str, output: &str) -> Result<()> {
+    let output_path = shellexpand::tilde(output).to_string();
+
+    if Path::new(&output_path).exists() {
+        return Err(anyhow!(
+            "File '{}' already exists, choose another output path",
+            output
+        ));
+    }
+
+    println!(
+        "{} {}Loading keypair",
+        style("[1/2]").bold().dim(),
+        LOOKING_GLASS_EMOJI
+    );
+
+    let keypair = if keypair.starts_with(SEED_PHRASE_SCHEME) {
+        read_seed_phrase_keypair(keypair)?
+    } else {
+        read_keypair_path(keypair)?
+    };
+    println!("Public key: {}", keypair.pubkey());
+
+    println!(
+        "\n{} {}Encrypting keypair",
+        style("[2/2]").bold().dim(),
+        SIGNING_EMOJI
+    );
+
+    let passphrase = keypair_passphrase("Passphrase", true)?;
+    if passphrase.is_empty() {
+        return Err(anyhow!("The passphrase cannot be empty"));
+    }
+
+    let encrypted = encrypt_keypair(&keypair, &passphrase)?;
+    fs::write(&output_path, encrypted)?;
+
+    println!(
+        "{}Encrypted keypair written to {}",
+        COMPLETE_EMOJI,
+        style(output).bold()
+    );
+    println!(
+        "Use it with --keypair {}; remember to remove the plaintext keypair file.",
+        output
+    );
+
+    Ok(())
+}
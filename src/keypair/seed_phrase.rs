@@ -0,0 +1,52 @@
+use anchor_client::solana_sdk::{derivation_path::DerivationPath, signer::keypair};
+use bip39::{Language, Mnemonic, Seed};
+use dialoguer::{theme::ColorfulTheme, Password};
+
+use crate::common::*;
+use crate::interaction::ensure_interactive;
+use crate::keypair::KeypairError;
+
+/// Scheme of the seed phrase keypair paths, e.g. `prompt://?key=0/0`.
+pub const SEED_PHRASE_SCHEME: &str = "prompt://";
+
+/// Derive the keypair of a `prompt://` path from a BIP39 seed phrase (and
+/// optional passphrase) entered at hidden prompts. The `key` query selects
+/// the derivation path, as in the Solana CLI; without it, the keypair is
+/// derived with the default `m/44'/501'` path.
+pub fn read_seed_phrase_keypair(path: &str) -> Result<Keypair> {
+    let derivation_path = parse_derivation_path(path)?;
+    ensure_interactive("the seed phrase")?;
+    let theme = ColorfulTheme::default();
+
+    let phrase = Password::with_theme(&theme)
+        .with_prompt("Seed phrase")
+        .interact()?;
+    let phrase = phrase.split_whitespace().collect::<Vec<&str>>().join(" ");
+    let mnemonic = Mnemonic::from_phrase(&phrase, Language::English)
+        .map_err(|err| KeypairError::InvalidSeedPhrase(err.to_string()))?;
+
+    let passphrase = Password::with_theme(&theme)
+        .with_prompt("BIP39 passphrase (empty for none)")
+        .allow_empty_password(true)
+        .interact()?;
+
+    let seed = Seed::new(&mnemonic, &passphrase);
+    keypair::keypair_from_seed_and_derivation_path(seed.as_bytes(), derivation_path)
+        .map_err(|err| KeypairError::InvalidSeedPhrase(err.to_string()).into())
+}
+
+fn parse_derivation_path(path: &str) -> Result<Option<DerivationPath>> {
+    let invalid = |err: String| KeypairError::InvalidDerivationPath(path.to_string(), err);
+    let query = path
+        .strip_prefix(SEED_PHRASE_SCHEME)
+        .unwrap_or(path)
+        .trim_start_matches('?');
+
+    match query.strip_prefix("key=") {
+        Some(key) => Ok(Some(
+            DerivationPath::from_key_str(key).map_err(|err| invalid(err.to_string()))?,
+        )),
+        None if query.is_empty() => Ok(None),
+        None => Err(invalid(format!("unsupported query '{}'", query)).into()),
+    }
+}
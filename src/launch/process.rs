@@ -2,16 +2,51 @@ use anyhow::Result;
 use console::{style, Style};
 use dialoguer::theme::ColorfulTheme;
 use dialoguer::Confirm;
-use std::sync::{atomic::AtomicBool, Arc};
+use serde::Serialize;
+use std::{
+    future::Future,
+    path::Path,
+    sync::{atomic::AtomicBool, Arc},
+    time::{Duration, Instant},
+};
 
+use crate::cache::load_cache;
+use crate::cli::{CollectionAction, GuardCommand};
+use crate::collection::{process_collection, CollectionArgs};
 use crate::common::LAUNCH_EMOJI;
 use crate::config::parser::get_config_data;
 use crate::create_config::{process_create_config, CreateConfigArgs};
 use crate::deploy::{process_deploy, DeployArgs};
+use crate::errors::{FailureClass, StageError};
+use crate::events::summary_event;
+use crate::guard::{process_guard, GuardArgs};
+use crate::interaction::is_interactive;
+use crate::journal::Journal;
+use crate::spend::{format_lamports, spend_summary, track_spend};
 use crate::upload::{process_upload, UploadArgs};
 use crate::validate::{process_validate, ValidateArgs};
 use crate::verify::{process_verify, VerifyArgs};
 
+const STAGE_VALIDATE: &str = "launch.validate";
+const STAGE_UPLOAD: &str = "launch.upload";
+const STAGE_DEPLOY: &str = "launch.deploy";
+const STAGE_COLLECTION: &str = "launch.collection";
+const STAGE_GUARD: &str = "launch.guard";
+const STAGE_VERIFY: &str = "launch.verify";
+
+/// Launch stages, in the order they are executed.
+const LAUNCH_STAGES: [&str; 6] = [
+    STAGE_VALIDATE,
+    STAGE_UPLOAD,
+    STAGE_DEPLOY,
+    STAGE_COLLECTION,
+    STAGE_GUARD,
+    STAGE_VERIFY,
+];
+
+/// Collection metadata file in the assets folder, created by 'init'.
+const COLLECTION_METADATA: &str = "collection.json";
+
 pub struct LaunchArgs {
     pub assets_dir: String,
     pub config: String,
@@ -19,10 +54,45 @@ pub struct LaunchArgs {
     pub rpc_url: Option<String>,
     pub cache: String,
     pub strict: bool,
+    pub concurrency: Option<usize>,
+    pub rate_limit: Option<f64>,
+    pub restart: bool,
     pub interrupted: Arc<AtomicBool>,
 }
 
+/// Outcome of a launch stage.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+enum StageOutcome {
+    Completed,
+    /// Completed by a previous launch.
+    Skipped,
+    Failed,
+}
+
+/// Outcome, elapsed time (seconds) and cost (lamports) of a launch stage.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct StageSummary {
+    command: &'static str,
+    outcome: StageOutcome,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    elapsed: Option<f64>,
+    cost: u64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LaunchSummary<'a> {
+    candy_machine: Option<String>,
+    stages: &'a [StageSummary],
+    elapsed: f64,
+    cost: u64,
+}
+
 pub async fn process_launch(args: LaunchArgs) -> Result<()> {
+    track_spend("launch", &args.cache);
+
     println!("Starting Sugar launch... {}", LAUNCH_EMOJI);
 
     let theme = ColorfulTheme {
@@ -31,13 +101,18 @@ pub async fn process_launch(args: LaunchArgs) -> Result<()> {
     };
 
     if let Err(err) = get_config_data(&args.config) {
+        // the config can only be created interactively
+        if !is_interactive() {
+            return Err(err.into());
+        }
+
         // padding
         println!();
         if Confirm::with_theme(&theme)
             .with_prompt("Could not load config file. Would you like to create a new config file?")
             .interact()?
         {
-            println!("\n{} sugar create-config\n", style(">>>").magenta());
+            println!("\n{} sugar config create\n", style(">>>").magenta());
 
             let create_config_args = CreateConfigArgs {
                 config: Some(args.config.clone()),
@@ -52,16 +127,48 @@ pub async fn process_launch(args: LaunchArgs) -> Result<()> {
         }
     }
 
-    println!("\n{} sugar validate\n", style(">>>").magenta());
+    let mut journal = Journal::load_for_cache(&args.cache)?;
+
+    // a completed launch starts from scratch, otherwise it resumes from the
+    // first stage that has not completed (unless restarted)
+    if args.restart
+        || LAUNCH_STAGES
+            .iter()
+            .all(|stage| journal.is_completed(stage))
+    {
+        journal.reset()?;
+    }
+
+    let started = Instant::now();
+    let mut stages = Vec::with_capacity(LAUNCH_STAGES.len());
+    let result = run_stages(&args, &mut stages).await;
 
+    print_summary(&args.cache, &stages, started.elapsed());
+
+    result
+}
+
+/// Run the launch stages, stopping at the first failure.
+async fn run_stages(args: &LaunchArgs, stages: &mut Vec<StageSummary>) -> Result<()> {
     let validate_args = ValidateArgs {
         assets_dir: args.assets_dir.clone(),
         strict: args.strict,
+        skip: Vec::new(),
+        max_file_size: None,
+        config: Some(args.config.clone()),
+        keypair: args.keypair.clone(),
+        rpc_url: args.rpc_url.clone(),
     };
 
-    process_validate(validate_args)?;
-
-    println!("\n{} sugar upload\n", style(">>>").magenta());
+    run_stage(
+        &args.cache,
+        STAGE_VALIDATE,
+        "validate",
+        FailureClass::Validation,
+        stages,
+        async { process_validate(validate_args) },
+    )
+    .await?;
 
     let upload_args = UploadArgs {
         assets_dir: args.assets_dir.clone(),
@@ -69,32 +176,267 @@ pub async fn process_launch(args: LaunchArgs) -> Result<()> {
         keypair: args.keypair.clone(),
         rpc_url: args.rpc_url.clone(),
         cache: args.cache.clone(),
+        concurrency: args.concurrency,
+        rate_limit: args.rate_limit,
+        dry_run: false,
+        bundlr_node: None,
         interrupted: args.interrupted.clone(),
     };
 
-    process_upload(upload_args).await?;
-
-    println!("\n{} sugar deploy\n", style(">>>").magenta());
+    run_stage(
+        &args.cache,
+        STAGE_UPLOAD,
+        "upload",
+        FailureClass::Upload,
+        stages,
+        process_upload(upload_args),
+    )
+    .await?;
 
     let deploy_args = DeployArgs {
         config: args.config.clone(),
         keypair: args.keypair.clone(),
         rpc_url: args.rpc_url.clone(),
         cache: args.cache.clone(),
+        force_rewrite: false,
+        append: false,
         interrupted: args.interrupted.clone(),
     };
 
-    process_deploy(deploy_args).await?;
+    run_stage(
+        &args.cache,
+        STAGE_DEPLOY,
+        "deploy",
+        FailureClass::Deploy,
+        stages,
+        process_deploy(deploy_args),
+    )
+    .await?;
+
+    run_stage(
+        &args.cache,
+        STAGE_COLLECTION,
+        "collection set",
+        FailureClass::Deploy,
+        stages,
+        launch_collection(args),
+    )
+    .await?;
 
-    println!("\n{} sugar verify\n", style(">>>").magenta());
+    run_stage(
+        &args.cache,
+        STAGE_GUARD,
+        "guard add",
+        FailureClass::Deploy,
+        stages,
+        launch_guard(args),
+    )
+    .await?;
 
     let verify_args = VerifyArgs {
         keypair: args.keypair.clone(),
         rpc_url: args.rpc_url.clone(),
         cache: args.cache.clone(),
+        assets_dir: args.assets_dir.clone(),
+        content: false,
+        read_only: false,
+        redeploy: false,
+        config: args.config.clone(),
+        interrupted: args.interrupted.clone(),
+    };
+
+    run_stage(
+        &args.cache,
+        STAGE_VERIFY,
+        "verify",
+        FailureClass::Deploy,
+        stages,
+        process_verify(verify_args),
+    )
+    .await
+}
+
+/// Set the collection of the candy machine: the collection of the cache (set
+/// by a previous 'collection' command), or a new collection NFT created from
+/// the collection metadata of the assets folder. Without either, the candy
+/// machine has no collection.
+async fn launch_collection(args: &LaunchArgs) -> Result<()> {
+    let metadata = Path::new(&args.assets_dir).join(COLLECTION_METADATA);
+    let collection_mint = load_cache(&args.cache, false)?.program.collection_mint;
+
+    let action = match collection_mint {
+        Some(collection_mint) => CollectionAction::Set {
+            collection_mint,
+            keypair: args.keypair.clone(),
+            rpc_url: args.rpc_url.clone(),
+            cache: args.cache.clone(),
+        },
+        None if metadata.exists() => CollectionAction::Create {
+            metadata: metadata.to_string_lossy().to_string(),
+            image: None,
+            keypair: args.keypair.clone(),
+            rpc_url: args.rpc_url.clone(),
+            cache: args.cache.clone(),
+            config: args.config.clone(),
+        },
+        None => {
+            println!(
+                "No collection in the cache and no {} file, skipping.",
+                metadata.display()
+            );
+            return Ok(());
+        }
     };
 
-    process_verify(verify_args)?;
+    process_collection(CollectionArgs { action }).await
+}
+
+/// Wrap the candy machine with a candy guard if the config file has guards.
+/// Candy guards can only wrap Candy Machine Core (v3) candy machines, so this
+/// fails for the v2 candy machines created by 'deploy' (see 'guard add').
+async fn launch_guard(args: &LaunchArgs) -> Result<()> {
+    if get_config_data(&args.config)?.guards.is_none() {
+        println!("No guards in the config file, skipping.");
+        return Ok(());
+    }
+
+    process_guard(GuardArgs {
+        command: GuardCommand::Add {
+            keypair: args.keypair.clone(),
+            rpc_url: args.rpc_url.clone(),
+            cache: args.cache.clone(),
+            config: args.config.clone(),
+            candy_machine: None,
+        },
+    })
+    .await
+}
+
+/// Run a stage, unless it was completed by a previous launch, recording its
+/// outcome in the journal. The error of a failed stage keeps its class for
+/// the exit code of the launch.
+async fn run_stage<F: Future<Output = Result<()>>>(
+    cache: &str,
+    stage: &str,
+    command: &'static str,
+    class: FailureClass,
+    stages: &mut Vec<StageSummary>,
+    run: F,
+) -> Result<()> {
+    // the journal is reloaded around each stage, since the commands also
+    // record their progress in it
+    let mut journal = Journal::load_for_cache(cache)?;
 
-    Ok(())
+    if journal.is_completed(stage) {
+        println!(
+            "\n{} sugar {} {}",
+            style(">>>").magenta(),
+            command,
+            style("(completed, skipping)").dim()
+        );
+        stages.push(StageSummary {
+            command,
+            outcome: StageOutcome::Skipped,
+            elapsed: None,
+            cost: 0,
+        });
+        return Ok(());
+    }
+
+    println!("\n{} sugar {}\n", style(">>>").magenta(), command);
+    journal.start(stage)?;
+
+    let spent = spend_summary().total();
+    let started = Instant::now();
+    let result = run.await;
+
+    stages.push(StageSummary {
+        command,
+        outcome: if result.is_ok() {
+            StageOutcome::Completed
+        } else {
+            StageOutcome::Failed
+        },
+        elapsed: Some(started.elapsed().as_secs_f64()),
+        cost: spend_summary().total() - spent,
+    });
+
+    let mut journal = Journal::load_for_cache(cache)?;
+
+    match result {
+        Ok(()) => journal.complete(stage),
+        Err(error) => {
+            journal.fail(stage, &error.to_string())?;
+            Err(StageError { class, error }.into())
+        }
+    }
+}
+
+fn print_summary(cache: &str, stages: &[StageSummary], elapsed: Duration) {
+    let candy_machine = if Path::new(cache).exists() {
+        load_cache(cache, false)
+            .ok()
+            .map(|cache| cache.program.candy_machine)
+            .filter(|candy_machine| !candy_machine.is_empty())
+    } else {
+        None
+    };
+    let cost = stages.iter().map(|stage| stage.cost).sum();
+
+    println!("\n{}", style("Launch summary").bold());
+    println!(
+        "  {:<16}{}",
+        "Candy machine:",
+        candy_machine.as_deref().unwrap_or("not deployed")
+    );
+
+    for stage in stages {
+        let label = format!("{}:", stage.command);
+
+        match stage.outcome {
+            StageOutcome::Skipped => println!(
+                "  {:<16}{}",
+                label,
+                style("skipped (completed by a previous launch)").dim()
+            ),
+            StageOutcome::Completed | StageOutcome::Failed => println!(
+                "  {:<16}{:<16}{}{}",
+                label,
+                format_elapsed(Duration::from_secs_f64(stage.elapsed.unwrap_or_default())),
+                format_lamports(stage.cost),
+                if matches!(stage.outcome, StageOutcome::Failed) {
+                    style(" (failed)").red().to_string()
+                } else {
+                    String::new()
+                }
+            ),
+        }
+    }
+
+    println!(
+        "  {:<16}{:<16}{}",
+        "Total:",
+        format_elapsed(elapsed),
+        style(format_lamports(cost)).bold()
+    );
+
+    summary_event(
+        "launch:summary",
+        &LaunchSummary {
+            candy_machine,
+            stages,
+            elapsed: elapsed.as_secs_f64(),
+            cost,
+        },
+    );
+}
+
+fn format_elapsed(elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs();
+
+    if seconds < 60 {
+        format!("{:.1}s", elapsed.as_secs_f64())
+    } else {
+        format!("{}m {:02}s", seconds / 60, seconds % 60)
+    }
 }
@@ -1,18 +1,52 @@
+pub mod airdrop;
+pub mod append;
 pub mod bundlr;
 pub mod cache;
 pub mod candy_machine;
 pub mod cli;
+pub mod collection;
 pub mod common;
 pub mod config;
 pub mod constants;
 pub mod create_config;
+pub mod credentials;
+pub mod das;
 pub mod deploy;
+pub mod distribute;
 pub mod errors;
+pub mod estimate;
+pub mod events;
+pub mod fees;
+pub mod fix_assets;
+pub mod freeze;
+pub mod generate_metadata;
+pub mod guard;
+pub mod init;
+pub mod interaction;
+pub mod journal;
+pub mod keypair;
 pub mod launch;
+pub mod manifest;
 pub mod mint;
+pub mod monitor;
+pub mod multisig;
 pub mod parse;
+pub mod preview;
+pub mod price;
+pub mod replace;
+pub mod report;
+pub mod retry;
+pub mod reveal;
+pub mod rpc;
+pub mod rpc_cache;
 pub mod setup;
 pub mod show;
+pub mod sign;
+pub mod signer;
+pub mod snapshot;
+pub mod spend;
+pub mod test_env;
+pub mod transaction;
 pub mod update;
 pub mod upload;
 pub mod utils;
@@ -9,21 +9,56 @@ use std::{
         atomic::{AtomicBool, Ordering},
         Arc,
     },
+    time::Duration,
 };
 use tracing::subscriber::set_global_default;
 use tracing_bunyan_formatter::{BunyanFormattingLayer, JsonStorageLayer};
 use tracing_subscriber::{self, filter::LevelFilter, prelude::*, EnvFilter};
 
+use sugar_cli::airdrop::{process_airdrop, AirdropArgs};
+use sugar_cli::append::{process_append, AppendArgs};
 use sugar_cli::bundlr::{process_bundlr, BundlrArgs};
+use sugar_cli::cache::{disable_cache_lock, process_cache, CacheArgs};
 use sugar_cli::cli::{Cli, Commands};
-use sugar_cli::constants::{COMPLETE_EMOJI, ERROR_EMOJI};
-use sugar_cli::create_config::{process_create_config, CreateConfigArgs};
+use sugar_cli::collection::{process_collection, CollectionArgs};
+use sugar_cli::constants::{COMPLETE_EMOJI, DEADLINE_GRACE_PERIOD, ERROR_EMOJI};
+use sugar_cli::create_config::{
+    process_config, process_create_config, ConfigArgs, CreateConfigArgs,
+};
 use sugar_cli::deploy::{process_deploy, DeployArgs};
+use sugar_cli::distribute::{process_distribute, DistributeArgs};
+use sugar_cli::errors::FailureClass;
+use sugar_cli::estimate::{process_estimate, EstimateArgs};
+use sugar_cli::events::{init_json_output, init_progress_events, result_event};
+use sugar_cli::fees::{process_fees, FeesArgs};
+use sugar_cli::fix_assets::{process_fix_assets, FixAssetsArgs};
+use sugar_cli::freeze::{
+    process_freeze, process_thaw, process_unlock_funds, FreezeArgs, ThawArgs, UnlockFundsArgs,
+};
+use sugar_cli::generate_metadata::{process_generate_metadata, GenerateMetadataArgs};
+use sugar_cli::guard::{process_guard, GuardArgs};
+use sugar_cli::init::{process_init, InitArgs};
+use sugar_cli::interaction::{disable_interaction, enable_assume_yes};
+use sugar_cli::keypair::{process_keypair, KeypairArgs};
 use sugar_cli::launch::{process_launch, LaunchArgs};
+use sugar_cli::manifest::{process_manifest, ManifestArgs};
 use sugar_cli::mint::{process_mint, MintArgs};
+use sugar_cli::monitor::{process_monitor, MonitorArgs};
+use sugar_cli::parse::parse_duration;
+use sugar_cli::preview::{process_preview, PreviewArgs};
+use sugar_cli::price::init_price_oracle;
+use sugar_cli::replace::{process_replace, ReplaceArgs};
+use sugar_cli::report::{process_report, ReportArgs};
+use sugar_cli::reveal::{process_reveal, RevealArgs};
+use sugar_cli::rpc::{process_rpc, RpcArgs};
 use sugar_cli::show::{process_show, ShowArgs};
+use sugar_cli::sign::{process_sign, SignArgs};
+use sugar_cli::snapshot::{process_snapshot, SnapshotArgs};
+use sugar_cli::spend::finish_spend_report;
+use sugar_cli::test_env::{process_test_env, TestEnvArgs};
+use sugar_cli::transaction::enable_websocket_confirmation;
 use sugar_cli::update::{process_update, UpdateArgs};
-use sugar_cli::upload::{process_upload, UploadArgs};
+use sugar_cli::upload::{process_upload, process_upload_watch, UploadArgs};
 use sugar_cli::validate::{process_validate, ValidateArgs};
 use sugar_cli::verify::{process_verify, VerifyArgs};
 use sugar_cli::withdraw::{process_withdraw, WithdrawArgs};
@@ -60,8 +95,19 @@ fn setup_logging(level: Option<EnvFilter>) -> Result<()> {
 
 #[tokio::main(worker_threads = 4)]
 async fn main() {
-    match run().await {
+    let cli = Cli::parse();
+    let stage = cli.command.failure_class();
+    let result = run(cli).await;
+
+    // costs are reported even when the command fails, since the transactions
+    // sent up to that point were paid for
+    if let Err(err) = finish_spend_report() {
+        println!("{}Could not record the spend report: {}", ERROR_EMOJI, err);
+    }
+
+    match result {
         Ok(()) => {
+            result_event(None);
             println!(
                 "\n{}{}",
                 COMPLETE_EMOJI,
@@ -69,21 +115,49 @@ async fn main() {
             );
         }
         Err(err) => {
+            result_event(Some(&err.to_string()));
             println!(
                 "\n{}{} {}",
                 ERROR_EMOJI,
                 style("Error running command (re-run needed):").red(),
                 err,
             );
-            // finished the program with an error code to the OS
-            std::process::exit(1);
+            // finished the program with an error code to the OS, which
+            // tells the class of the failure
+            std::process::exit(FailureClass::of(&err, stage).exit_code());
         }
     }
 }
 
-async fn run() -> Result<()> {
-    let cli = Cli::parse();
+/// Stop the command once the deadline is reached. Commands that handle
+/// interruptions are signalled to stop (checkpointing their progress) and
+/// terminated if they do not finish within the grace period.
+fn spawn_deadline(deadline: Duration, interrupted: Arc<AtomicBool>) {
+    tokio::spawn(async move {
+        tokio::time::sleep(deadline).await;
+
+        if !interrupted.load(Ordering::SeqCst) {
+            println!(
+                "\n\n{}{} Stopping...",
+                ERROR_EMOJI,
+                style("Deadline reached:").red(),
+            );
+            interrupted.store(true, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_secs(DEADLINE_GRACE_PERIOD)).await;
+        }
+
+        result_event(Some("Deadline reached, operation aborted"));
+        println!(
+            "\n\n{}{} Operation aborted.",
+            ERROR_EMOJI,
+            style("Deadline reached (re-run needed):").red(),
+        );
+        // finished the program with an error code to the OS
+        std::process::exit(1);
+    });
+}
 
+async fn run(cli: Cli) -> Result<()> {
     let log_level_error: Result<()> = Err(anyhow!(
         "Invalid log level: {:?}.\n Valid levels are: trace, debug, info, warn, error.",
         cli.log_level
@@ -101,12 +175,35 @@ async fn run() -> Result<()> {
 
     tracing::info!("Lend me some sugar, I am your neighbor.");
 
+    if cli.json {
+        init_json_output()?;
+    } else if let Some(target) = &cli.progress_events {
+        init_progress_events(target)?;
+    }
+
+    if let Some(url) = &cli.price_api {
+        init_price_oracle(url).await?;
+    }
+
+    if cli.websocket_confirm {
+        enable_websocket_confirmation();
+    }
+
+    if cli.no_interaction {
+        disable_interaction();
+    }
+
+    if cli.yes {
+        enable_assume_yes();
+    }
+
     let interrupted = Arc::new(AtomicBool::new(true));
     let ctrl_handler = interrupted.clone();
 
     ctrlc::set_handler(move || {
         if ctrl_handler.load(Ordering::SeqCst) {
             // we really need to exit
+            result_event(Some("Operation aborted"));
             println!(
                 "\n\n{}{} Operation aborted.",
                 ERROR_EMOJI,
@@ -120,7 +217,38 @@ async fn run() -> Result<()> {
     })
     .expect("Error setting Ctrl-C handler");
 
+    if let Some(deadline) = &cli.deadline {
+        spawn_deadline(parse_duration(deadline)?, interrupted.clone());
+    }
+
+    if cli.read_only && !cli.command.is_read_only() {
+        return Err(anyhow!(
+            "Command not allowed in read-only mode (only show, verify, validate, preview, report \
+             and other commands that do not change state can run)"
+        ));
+    }
+
+    // commands that do not write the cache can run alongside one that does
+    if cli.command.is_read_only() {
+        disable_cache_lock();
+    }
+
     match cli.command {
+        Commands::Init {
+            directory,
+            keypair,
+            cluster,
+            upload_method,
+            guards,
+            whitelist_mint,
+        } => process_init(InitArgs {
+            directory,
+            keypair,
+            cluster,
+            upload_method,
+            guards,
+            whitelist_mint,
+        })?,
         Commands::CreateConfig {
             config,
             keypair,
@@ -132,6 +260,7 @@ async fn run() -> Result<()> {
             rpc_url,
             assets_dir,
         })?,
+        Commands::Config { action } => process_config(ConfigArgs { action })?,
         Commands::Launch {
             assets_dir,
             config,
@@ -139,6 +268,9 @@ async fn run() -> Result<()> {
             rpc_url,
             cache,
             strict,
+            concurrency,
+            rate_limit,
+            restart,
         } => {
             process_launch(LaunchArgs {
                 assets_dir,
@@ -147,6 +279,53 @@ async fn run() -> Result<()> {
                 rpc_url,
                 cache,
                 strict,
+                concurrency,
+                rate_limit,
+                restart,
+                interrupted: interrupted.clone(),
+            })
+            .await?
+        }
+        Commands::Append {
+            assets,
+            assets_dir,
+            config,
+            keypair,
+            rpc_url,
+            cache,
+            strict,
+        } => {
+            process_append(AppendArgs {
+                new_assets_dir: assets,
+                assets_dir,
+                config,
+                keypair,
+                rpc_url,
+                cache,
+                strict,
+                interrupted: interrupted.clone(),
+            })
+            .await?
+        }
+        Commands::Replace {
+            asset,
+            image,
+            metadata,
+            assets_dir,
+            config,
+            keypair,
+            rpc_url,
+            cache,
+        } => {
+            process_replace(ReplaceArgs {
+                index: asset,
+                image,
+                metadata,
+                assets_dir,
+                config,
+                keypair,
+                rpc_url,
+                cache,
                 interrupted: interrupted.clone(),
             })
             .await?
@@ -155,14 +334,20 @@ async fn run() -> Result<()> {
             keypair,
             rpc_url,
             cache,
+            config,
             number,
             candy_machine,
+            parallel,
+            receiver,
         } => process_mint(MintArgs {
             keypair,
             rpc_url,
             cache,
+            config,
             number,
             candy_machine,
+            parallel,
+            receiver,
         })?,
         Commands::Update {
             config,
@@ -171,6 +356,8 @@ async fn run() -> Result<()> {
             cache,
             new_authority,
             candy_machine,
+            multisig,
+            proposal,
         } => process_update(UpdateArgs {
             config,
             keypair,
@@ -178,18 +365,23 @@ async fn run() -> Result<()> {
             cache,
             new_authority,
             candy_machine,
+            multisig,
+            proposal,
         })?,
         Commands::Deploy {
             config,
             keypair,
             rpc_url,
             cache,
+            force_rewrite,
         } => {
             process_deploy(DeployArgs {
                 config,
                 keypair,
                 rpc_url,
                 cache,
+                force_rewrite,
+                append: false,
                 interrupted: interrupted.clone(),
             })
             .await?
@@ -200,59 +392,305 @@ async fn run() -> Result<()> {
             keypair,
             rpc_url,
             cache,
+            concurrency,
+            rate_limit,
+            dry_run,
+            bundlr_node,
+            watch,
         } => {
-            process_upload(UploadArgs {
+            let args = UploadArgs {
                 assets_dir,
                 config,
                 keypair,
                 rpc_url,
                 cache,
+                concurrency,
+                rate_limit,
+                dry_run,
+                bundlr_node,
                 interrupted: interrupted.clone(),
+            };
+
+            if watch {
+                process_upload_watch(args).await?
+            } else {
+                process_upload(args).await?
+            }
+        }
+        Commands::Validate {
+            assets_dir,
+            strict,
+            skip,
+            max_file_size,
+            config,
+            keypair,
+            rpc_url,
+        } => process_validate(ValidateArgs {
+            assets_dir,
+            strict,
+            skip,
+            max_file_size,
+            config,
+            keypair,
+            rpc_url,
+        })?,
+        Commands::Monitor {
+            keypair,
+            rpc_url,
+            cache,
+            config,
+            candy_machine,
+            interval,
+            webhook,
+            no_pause,
+        } => {
+            process_monitor(MonitorArgs {
+                keypair,
+                rpc_url,
+                cache,
+                config,
+                candy_machine,
+                interval,
+                webhook,
+                no_pause,
             })
             .await?
         }
-        Commands::Validate { assets_dir, strict } => {
-            process_validate(ValidateArgs { assets_dir, strict })?
-        }
         Commands::Withdraw {
             candy_machine,
             keypair,
             rpc_url,
             list,
+            all,
+            multisig,
+            proposal,
         } => process_withdraw(WithdrawArgs {
             candy_machine,
             keypair,
             rpc_url,
             list,
+            all,
+            multisig,
+            proposal,
         })?,
         Commands::Verify {
             keypair,
             rpc_url,
             cache,
-        } => process_verify(VerifyArgs {
+            assets_dir,
+            content,
+            redeploy,
+            config,
+        } => {
+            process_verify(VerifyArgs {
+                keypair,
+                rpc_url,
+                cache,
+                assets_dir,
+                content,
+                read_only: cli.read_only,
+                redeploy,
+                config,
+                interrupted: interrupted.clone(),
+            })
+            .await?
+        }
+        Commands::Show {
             keypair,
             rpc_url,
             cache,
+            candy_machine,
+            unminted,
+        } => {
+            process_show(ShowArgs {
+                keypair,
+                rpc_url,
+                cache,
+                candy_machine,
+                unminted,
+            })
+            .await?
+        }
+        Commands::Sign {
+            keypair,
+            rpc_url,
+            cache,
+            candy_machine,
+            mint_list,
+            creator,
+            progress,
+        } => process_sign(SignArgs {
+            keypair,
+            rpc_url,
+            cache,
+            candy_machine,
+            mint_list,
+            creator,
+            progress,
         })?,
-        Commands::Show {
+        Commands::Distribute {
+            allocations,
+            keypair,
+            rpc_url,
+            cache,
+            state,
+            candy_machine,
+        } => process_distribute(DistributeArgs {
+            keypair,
+            rpc_url,
+            cache,
+            allocations,
+            state,
+            candy_machine,
+        })?,
+        Commands::Airdrop {
+            recipients,
+            keypair,
+            rpc_url,
+            cache,
+            config,
+            state,
+            candy_machine,
+            parallel,
+            priority_fee,
+        } => process_airdrop(AirdropArgs {
+            keypair,
+            rpc_url,
+            cache,
+            config,
+            recipients,
+            state,
+            candy_machine,
+            parallel,
+            priority_fee,
+        })?,
+        Commands::Reveal {
+            keypair,
+            rpc_url,
+            cache,
+            config,
+            candy_machine,
+        } => process_reveal(RevealArgs {
+            keypair,
+            rpc_url,
+            cache,
+            config,
+            candy_machine,
+        })?,
+        Commands::Estimate {
+            assets_dir,
+            config,
+            keypair,
+            rpc_url,
+        } => {
+            process_estimate(EstimateArgs {
+                assets_dir,
+                config,
+                keypair,
+                rpc_url,
+            })
+            .await?
+        }
+        Commands::Fees {
+            keypair,
+            rpc_url,
+            config,
+            write,
+        } => process_fees(FeesArgs {
+            keypair,
+            rpc_url,
+            config,
+            write,
+        })?,
+        Commands::Preview {
+            assets_dir,
+            config,
+            cache,
+            use_cache,
+            output,
+            serve,
+            port,
+        } => process_preview(PreviewArgs {
+            assets_dir,
+            config,
+            cache,
+            use_cache,
+            output,
+            serve,
+            port,
+        })?,
+        Commands::Guard { command } => process_guard(GuardArgs { command }).await?,
+        Commands::TestEnv { action } => process_test_env(TestEnvArgs { action })?,
+        Commands::Freeze { action } => process_freeze(FreezeArgs { action })?,
+        Commands::Thaw {
+            mint,
+            all,
+            keypair,
+            rpc_url,
+            cache,
+            candy_machine,
+        } => process_thaw(ThawArgs {
+            keypair,
+            rpc_url,
+            cache,
+            candy_machine,
+            mint,
+            all,
+        })?,
+        Commands::UnlockFunds {
             keypair,
             rpc_url,
             cache,
             candy_machine,
-        } => process_show(ShowArgs {
+        } => process_unlock_funds(UnlockFundsArgs {
             keypair,
             rpc_url,
             cache,
             candy_machine,
         })?,
+        Commands::Snapshot { action } => process_snapshot(SnapshotArgs { action })?,
+        Commands::Collection { action } => process_collection(CollectionArgs { action }).await?,
+        Commands::Manifest { action } => process_manifest(ManifestArgs { action })?,
+        Commands::Cache { action } => process_cache(CacheArgs { action }).await?,
+        Commands::Report { action } => process_report(ReportArgs { action })?,
+        Commands::GenerateMetadata {
+            template,
+            csv,
+            number,
+            assets_dir,
+            force,
+        } => process_generate_metadata(GenerateMetadataArgs {
+            template,
+            csv,
+            number,
+            assets_dir,
+            force,
+        })?,
+        Commands::FixAssets {
+            assets_dir,
+            cache,
+            renumber,
+            dry_run,
+        } => process_fix_assets(FixAssetsArgs {
+            assets_dir,
+            cache,
+            renumber,
+            dry_run,
+        })?,
+        Commands::Rpc { action } => process_rpc(RpcArgs { action })?,
+        Commands::Keypair { action } => process_keypair(KeypairArgs { action })?,
         Commands::Bundlr {
             keypair,
             rpc_url,
+            config,
+            bundlr_node,
             action,
         } => {
             process_bundlr(BundlrArgs {
                 keypair,
                 rpc_url,
+                config,
+                bundlr_node,
                 action,
             })
             .await?
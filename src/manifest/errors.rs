@@ -0,0 +1,16 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ManifestError {
+    #[error("Invalid manifest signature")]
+    InvalidSignature,
+
+    #[error("Config file hash mismatch (manifest: {0}, local: {1})")]
+    ConfigMismatch(String, String),
+
+    #[error("Cache item {0} does not match the manifest")]
+    ItemMismatch(String),
+
+    #[error("Item count mismatch (manifest: {0}, cache: {1})")]
+    ItemCountMismatch(usize, usize),
+}
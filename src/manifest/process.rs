@@ -0,0 +1,195 @@
+use anchor_client::solana_sdk::signature::Signature;
+use chrono::Utc;
+use console::style;
+use data_encoding::HEXLOWER;
+use ring::digest::{digest, SHA256};
+use serde::{Deserialize, Serialize};
+use std::{fs, str::FromStr};
+
+use crate::cache::load_cache;
+use crate::cli::ManifestAction;
+use crate::common::*;
+use crate::manifest::ManifestError;
+
+/// Version of the manifest format.
+const MANIFEST_VERSION: u8 = 1;
+
+pub struct ManifestArgs {
+    pub action: ManifestAction,
+}
+
+/// Operation manifest, signed by the keypair that will deploy the candy machine.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedManifest {
+    pub manifest: Manifest,
+    pub signer: String,
+    pub signature: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    pub version: u8,
+    pub created_at: String,
+    pub config_hash: String,
+    pub items: Vec<ManifestItem>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestItem {
+    pub index: String,
+    pub name: String,
+    pub image_hash: String,
+    pub image_link: String,
+    pub metadata_hash: String,
+    pub metadata_link: String,
+}
+
+pub fn process_manifest(args: ManifestArgs) -> Result<()> {
+    match args.action {
+        ManifestAction::Sign {
+            keypair,
+            rpc_url,
+            config,
+            cache,
+            output,
+        } => process_sign(keypair, rpc_url, &config, &cache, &output),
+        ManifestAction::Verify {
+            manifest,
+            config,
+            cache,
+        } => process_verify(&manifest, config, cache),
+    }
+}
+
+fn process_sign(
+    keypair: Option<String>,
+    rpc_url: Option<String>,
+    config: &str,
+    cache: &str,
+    output: &str,
+) -> Result<()> {
+    let sugar_config = sugar_setup(keypair, rpc_url)?;
+
+    println!(
+        "{} {}Building manifest",
+        style("[1/2]").bold().dim(),
+        LOOKING_GLASS_EMOJI
+    );
+
+    let manifest = Manifest {
+        version: MANIFEST_VERSION,
+        created_at: Utc::now().to_rfc3339(),
+        config_hash: hash_file(config)?,
+        items: manifest_items(cache)?,
+    };
+
+    println!("Manifest with {} item(s)", manifest.items.len());
+
+    println!(
+        "\n{} {}Signing manifest",
+        style("[2/2]").bold().dim(),
+        SIGNING_EMOJI
+    );
+
+    let message = serde_json::to_vec(&manifest)?;
+    let signature = sugar_config.keypair.sign_message(&message);
+
+    let signed = SignedManifest {
+        manifest,
+        signer: sugar_config.keypair.pubkey().to_string(),
+        signature: signature.to_string(),
+    };
+
+    let file = File::create(output)?;
+    serde_json::to_writer_pretty(file, &signed)?;
+
+    println!("{} {}", style("Signer:").bold(), signed.signer);
+    println!("Signed manifest saved to '{}'", output);
+
+    Ok(())
+}
+
+fn process_verify(manifest: &str, config: Option<String>, cache: Option<String>) -> Result<()> {
+    println!(
+        "{} {}Verifying signature",
+        style("[1/2]").bold().dim(),
+        SIGNING_EMOJI
+    );
+
+    let signed: SignedManifest = serde_json::from_str(&fs::read_to_string(manifest)?)?;
+    let signer = Pubkey::from_str(&signed.signer)?;
+    let signature = Signature::from_str(&signed.signature)?;
+    let message = serde_json::to_vec(&signed.manifest)?;
+
+    if !signature.verify(signer.as_ref(), &message) {
+        return Err(ManifestError::InvalidSignature.into());
+    }
+
+    println!("{} {}", style("Signed by:").bold(), signer);
+    println!(
+        "{} {}",
+        style("Created at:").bold(),
+        signed.manifest.created_at
+    );
+
+    println!(
+        "\n{} {}Comparing local files",
+        style("[2/2]").bold().dim(),
+        LOOKING_GLASS_EMOJI
+    );
+
+    if let Some(config) = config {
+        let local = hash_file(&config)?;
+        if local != signed.manifest.config_hash {
+            return Err(ManifestError::ConfigMismatch(signed.manifest.config_hash, local).into());
+        }
+        println!("Config file matches the manifest");
+    }
+
+    if let Some(cache) = cache {
+        let items = manifest_items(&cache)?;
+
+        if items.len() != signed.manifest.items.len() {
+            return Err(
+                ManifestError::ItemCountMismatch(signed.manifest.items.len(), items.len()).into(),
+            );
+        }
+
+        for (local, signed) in items.iter().zip(signed.manifest.items.iter()) {
+            if local != signed {
+                return Err(ManifestError::ItemMismatch(local.index.clone()).into());
+            }
+        }
+        println!("Cache file matches the manifest");
+    }
+
+    Ok(())
+}
+
+/// Return the SHA-256 hash of a file as a hex string.
+fn hash_file(path: &str) -> Result<String> {
+    let content = fs::read(path)?;
+    Ok(HEXLOWER.encode(digest(&SHA256, &content).as_ref()))
+}
+
+/// Return the manifest items of the cache, in cache order.
+fn manifest_items(cache: &str) -> Result<Vec<ManifestItem>> {
+    let cache = load_cache(cache, false)?;
+
+    Ok(cache
+        .items
+        .0
+        .iter()
+        .map(|(index, item)| ManifestItem {
+            index: index.clone(),
+            name: item.name.clone(),
+            image_hash: item.image_hash.clone(),
+            image_link: item.image_link.clone(),
+            metadata_hash: item.metadata_hash.clone(),
+            metadata_link: item.metadata_link.clone(),
+        })
+        .collect())
+}
@@ -11,34 +11,81 @@ use anchor_lang::prelude::AccountMeta;
 use anyhow::Result;
 use chrono::Utc;
 use console::style;
+use rayon::prelude::*;
+use solana_program::native_token::LAMPORTS_PER_SOL;
 use spl_associated_token_account::{create_associated_token_account, get_associated_token_address};
 use spl_token::{
     instruction::{initialize_mint, mint_to},
     state::Account,
     ID as TOKEN_PROGRAM_ID,
 };
-use std::{str::FromStr, sync::Arc};
+use std::{
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
 
 use mpl_candy_machine::accounts as nft_accounts;
 use mpl_candy_machine::instruction as nft_instruction;
 use mpl_candy_machine::{CandyMachine, EndSettingType, ErrorCode, WhitelistMintMode};
+use mpl_token_metadata::{
+    instruction::update_metadata_accounts_v2,
+    state::{DataV2, Key, Metadata, Uses, MAX_MASTER_EDITION_LEN, MAX_METADATA_LEN},
+    utils::try_from_slice_checked,
+};
 
 use crate::cache::load_cache;
 use crate::candy_machine::ID as CANDY_MACHINE_ID;
 use crate::candy_machine::*;
+use crate::collection::{get_collection_state, set_collection_during_mint};
 use crate::common::*;
+use crate::config::parser::{get_config_data, resolve_config_path};
+use crate::freeze::{get_freeze_pda, is_feature_active, FREEZE_FEATURE_INDEX};
 use crate::mint::pdas::*;
+use crate::price::usd_suffix;
+use crate::rpc_cache;
+use crate::spend::{
+    record_mint_price, record_rent, record_transaction, spend_summary, track_spend,
+};
+use crate::transaction::{send_with_callback, send_with_retry};
 use crate::utils::*;
+use crate::validate::parser::check_uses;
 
 pub struct MintArgs {
     pub keypair: Option<String>,
     pub rpc_url: Option<String>,
     pub cache: String,
+    pub config: String,
     pub number: Option<u64>,
     pub candy_machine: Option<String>,
+    pub parallel: usize,
+    pub receiver: Option<String>,
+}
+
+/// Return the uses and priority fee settings used when minting. These settings
+/// are optional, so the config file is only read if present.
+pub fn get_mint_settings(config: &str) -> Result<(Option<Uses>, Option<u64>)> {
+    if !Path::new(&resolve_config_path(config)).exists() {
+        return Ok((None, None));
+    }
+
+    let config_data = get_config_data(config)?;
+
+    let uses = match config_data.uses {
+        Some(uses) => {
+            check_uses(&uses)?;
+            Some(uses.into_metadata_format())
+        }
+        None => None,
+    };
+
+    Ok((uses, config_data.priority_fee))
 }
 
 pub fn process_mint(args: MintArgs) -> Result<()> {
+    track_spend("mint", &args.cache);
+
+    let (uses, priority_fee) = get_mint_settings(&args.config)?;
+
     let sugar_config = sugar_setup(args.keypair, args.rpc_url)?;
     let client = Arc::new(setup_client(&sugar_config)?);
 
@@ -78,9 +125,38 @@ pub fn process_mint(args: MintArgs) -> Result<()> {
         return Err(error);
     }
 
+    let receiver = args
+        .receiver
+        .as_ref()
+        .map(|receiver| {
+            Pubkey::from_str(receiver)
+                .map_err(|_| anyhow!("Failed to parse receiver address: {}", receiver))
+        })
+        .transpose()?;
+
+    if let Some(receiver) = receiver {
+        println!("Receiver: {}", receiver);
+    }
+
+    if args.parallel == 0 {
+        return Err(anyhow!(
+            "Invalid parallelism '{}', it must be greater than 0",
+            args.parallel
+        ));
+    }
+
     info!("Minting NFT from candy machine: {}", &candy_machine_id);
     info!("Candy machine program id: {:?}", CANDY_MACHINE_ID);
 
+    let program = client.program(CANDY_MACHINE_ID);
+
+    // checked once upfront, so that no mint is paid for when the uses cannot be set
+    if uses.is_some() {
+        check_uses_authority(&candy_machine_state, &program.payer())?;
+    }
+
+    let mint_spend = MintSpend::start(&program)?;
+
     if number == 1 {
         let pb = spinner_with_style();
         pb.set_message(format!(
@@ -90,51 +166,247 @@ pub fn process_mint(args: MintArgs) -> Result<()> {
 
         let result = match mint(
             Arc::clone(&client),
+            &sugar_config.keypair,
             candy_pubkey,
             Arc::clone(&candy_machine_state),
+            uses,
+            priority_fee,
+            receiver,
         ) {
-            Ok(signature) => format!("{} {}", style("Signature:").bold(), signature),
+            Ok(minted) => {
+                if let Some(err) = minted.uses_error {
+                    uses_warning(&[err]);
+                }
+                format!("{} {}", style("Signature:").bold(), minted.signature)
+            }
             Err(err) => {
                 pb.abandon_with_message(format!("{}", style("Mint failed ").red().bold()));
                 error!("{:?}", err);
+                mint_spend.finish(&program).ok();
                 return Err(err);
             }
         };
 
         pb.finish_with_message(result);
+        mint_spend.finish(&program)?;
     } else {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(args.parallel)
+            .build()?;
+
         let pb = progress_bar_with_style(number);
+        let errors = Mutex::new(Vec::new());
+        let uses_errors = Mutex::new(Vec::new());
+
+        // each mint is independent, so a failure does not stop the others
+        let minted = pool.install(|| {
+            (0..number)
+                .into_par_iter()
+                .filter_map(|_| {
+                    let result = mint(
+                        Arc::clone(&client),
+                        &sugar_config.keypair,
+                        candy_pubkey,
+                        Arc::clone(&candy_machine_state),
+                        uses.clone(),
+                        priority_fee,
+                        receiver,
+                    );
+                    pb.inc(1);
+
+                    match result {
+                        Ok(minted) => {
+                            if let Some(err) = minted.uses_error {
+                                uses_errors.lock().unwrap().push(err);
+                            }
+                            Some(minted.signature)
+                        }
+                        Err(err) => {
+                            error!("{:?}", err);
+                            errors.lock().unwrap().push(err.to_string());
+                            None
+                        }
+                    }
+                })
+                .count()
+        });
 
-        for _i in 0..number {
-            if let Err(err) = mint(
-                Arc::clone(&client),
-                candy_pubkey,
-                Arc::clone(&candy_machine_state),
-            ) {
-                pb.abandon_with_message(format!("{}", style("Mint failed ").red().bold()));
-                error!("{:?}", err);
-                return Err(err);
+        let errors = errors.into_inner().unwrap();
+
+        if errors.is_empty() {
+            pb.finish_with_message(format!("{}", style("Mint successful ").green().bold()));
+        } else {
+            pb.abandon_with_message(format!("{}", style("Mint failed ").red().bold()));
+        }
+
+        let spent = mint_spend.finish(&program)?;
+
+        println!(
+            "\n{} {} of {} NFT(s)",
+            style("Minted:").bold(),
+            minted,
+            number
+        );
+        println!(
+            "{} ◎ {:.9}{}",
+            style("Total cost:").bold(),
+            spent as f64 / LAMPORTS_PER_SOL as f64,
+            usd_suffix(spent)
+        );
+
+        let uses_errors = uses_errors.into_inner().unwrap();
+        if !uses_errors.is_empty() {
+            uses_warning(&uses_errors);
+        }
+
+        if !errors.is_empty() {
+            let mut message = format!("{} mint(s) failed:", errors.len());
+            for error in errors {
+                message.push_str(&style("\n=> ").dim().to_string());
+                message.push_str(&error);
             }
 
-            pb.inc(1);
+            return Err(anyhow!(message));
         }
+    }
+
+    Ok(())
+}
 
-        pb.finish();
+/// Print the uses updates that failed for NFTs that were minted.
+fn uses_warning(errors: &[String]) {
+    let mut message = format!(
+        "\n{} uses update(s) failed, the NFT(s) were minted without uses:",
+        errors.len()
+    );
+    for error in errors {
+        message.push_str(&style("\n=> ").dim().to_string());
+        message.push_str(error);
+    }
+
+    println!("{}", style(message).yellow());
+}
+
+/// Spend of a batch of mints. The mint price is not known upfront (e.g.,
+/// whitelist discounts), so it is the part of the balance change of the payer
+/// not recorded as fees or rent. This is measured over the whole batch since
+/// mints running in parallel change the balance at the same time.
+pub struct MintSpend {
+    balance: u64,
+    recorded: u64,
+}
+
+impl MintSpend {
+    pub fn start(program: &Program) -> Result<Self> {
+        Ok(MintSpend {
+            balance: program.rpc().get_balance(&program.payer())?,
+            recorded: spend_summary().total(),
+        })
+    }
+
+    /// Record the mint price of the batch, returning the lamports spent.
+    pub fn finish(self, program: &Program) -> Result<u64> {
+        let spent = self
+            .balance
+            .saturating_sub(program.rpc().get_balance(&program.payer())?);
+        let recorded = spend_summary().total().saturating_sub(self.recorded);
+
+        record_mint_price(spent.saturating_sub(recorded));
+
+        Ok(spent)
+    }
+}
+
+/// An NFT minted from the candy machine.
+pub struct MintedNft {
+    /// Signature of the mint transaction.
+    pub signature: Signature,
+    /// Address of the new mint.
+    pub mint: Pubkey,
+    /// Error of the uses update, which does not undo the mint.
+    pub uses_error: Option<String>,
+}
+
+/// Check that the uses can be set on the minted NFTs, which requires the payer
+/// to be their update authority (i.e., the candy machine authority) and the
+/// NFTs to be mutable.
+pub fn check_uses_authority(candy_machine_state: &CandyMachine, payer: &Pubkey) -> Result<()> {
+    if candy_machine_state.authority != *payer {
+        return Err(anyhow!(
+            "Cannot set uses: {} is not the candy machine authority",
+            payer
+        ));
+    }
+
+    if !candy_machine_state.data.is_mutable {
+        return Err(anyhow!(
+            "Cannot set uses: the candy machine mints immutable NFTs"
+        ));
     }
 
     Ok(())
 }
 
+/// Mint an NFT from the candy machine to the payer. Uses are set in a second
+/// transaction, whose failure is returned with the minted NFT. A failed mint
+/// reports the signature of its last transaction attempt, or the address of
+/// the mint when no transaction was sent.
+#[allow(clippy::too_many_arguments)]
 pub fn mint(
     client: Arc<Client>,
+    keypair: &dyn Signer,
     candy_machine_id: Pubkey,
     candy_machine_state: Arc<CandyMachine>,
-) -> Result<Signature> {
+    uses: Option<Uses>,
+    priority_fee: Option<u64>,
+    receiver: Option<Pubkey>,
+) -> Result<MintedNft> {
+    let nft_mint = Keypair::new();
+    let mut signature = None;
+
+    mint_nft(
+        client,
+        keypair,
+        candy_machine_id,
+        candy_machine_state,
+        uses,
+        priority_fee,
+        receiver,
+        &nft_mint,
+        &mut signature,
+    )
+    .map_err(|err| match signature {
+        Some(signature) => anyhow!("{} (signature {})", err, signature),
+        None => anyhow!("{} (mint {})", err, nft_mint.pubkey()),
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+fn mint_nft(
+    client: Arc<Client>,
+    keypair: &dyn Signer,
+    candy_machine_id: Pubkey,
+    candy_machine_state: Arc<CandyMachine>,
+    uses: Option<Uses>,
+    priority_fee: Option<u64>,
+    receiver: Option<Pubkey>,
+    nft_mint: &Keypair,
+    signature: &mut Option<Signature>,
+) -> Result<MintedNft> {
     let program = client.program(CANDY_MACHINE_ID);
     let payer = program.payer();
     let wallet = candy_machine_state.wallet;
+    let receiver = receiver.unwrap_or(payer);
 
     let candy_machine_data = &candy_machine_state.data;
+    let freeze_enabled = is_feature_active(&candy_machine_data.uuid, FREEZE_FEATURE_INDEX);
+
+    // the freeze feature delegates the token account of the payer
+    if freeze_enabled && receiver != payer {
+        return Err(anyhow!(
+            "Minting to a receiver is not supported when freeze is enabled"
+        ));
+    }
 
     if let Some(_gatekeeper) = &candy_machine_data.gatekeeper {
         return Err(anyhow!(
@@ -144,6 +416,10 @@ pub fn mint(
         return Err(anyhow!(ErrorCode::CandyMachineEmpty));
     }
 
+    if uses.is_some() {
+        check_uses_authority(&candy_machine_state, &payer)?;
+    }
+
     if candy_machine_state.authority != payer {
         // we are not authority, we need to follow the rules
         // 1. go_live_date
@@ -193,7 +469,6 @@ pub fn mint(
         }
     }
 
-    let nft_mint = Keypair::new();
     let metaplex_program_id = Pubkey::from_str(METAPLEX_PROGRAM_ID)?;
 
     // Allocate memory for the account
@@ -219,12 +494,12 @@ pub fn mint(
         0,
     )?;
 
-    // Derive associated token account
-    let assoc = get_associated_token_address(&payer, &nft_mint.pubkey());
+    // Derive associated token account of the receiver
+    let assoc = get_associated_token_address(&receiver, &nft_mint.pubkey());
 
-    // Create associated account instruction
+    // Create associated account instruction, paid by the payer
     let create_assoc_account_ix =
-        create_associated_token_account(&payer, &payer, &nft_mint.pubkey());
+        create_associated_token_account(&payer, &receiver, &nft_mint.pubkey());
 
     // Mint to instruction
     let mint_to_ix = mint_to(
@@ -298,18 +573,47 @@ pub fn mint(
         })
     }
 
+    // minted NFTs are frozen (and the payment held) by the freeze PDA
+    if freeze_enabled {
+        let freeze_pda = get_freeze_pda(&candy_machine_id);
+
+        additional_accounts.push(AccountMeta {
+            pubkey: freeze_pda,
+            is_signer: false,
+            is_writable: true,
+        });
+
+        additional_accounts.push(AccountMeta {
+            pubkey: assoc,
+            is_signer: false,
+            is_writable: true,
+        });
+
+        if let Some(token_mint) = candy_machine_state.token_mint {
+            additional_accounts.push(AccountMeta {
+                pubkey: get_associated_token_address(&freeze_pda, &token_mint),
+                is_signer: false,
+                is_writable: true,
+            });
+        }
+    }
+
     let metadata_pda = get_metadata_pda(&nft_mint.pubkey());
     let master_edition_pda = get_master_edition_pda(&nft_mint.pubkey());
     let (candy_machine_creator_pda, creator_bump) =
         get_candy_machine_creator_pda(&candy_machine_id);
 
-    let mut builder = program
-        .request()
+    let mut builder = program.request();
+
+    if let Some(priority_fee) = priority_fee {
+        builder = builder.instruction(compute_unit_price_instruction(priority_fee));
+    }
+
+    builder = builder
         .instruction(create_mint_account_ix)
         .instruction(init_mint_ix)
         .instruction(create_assoc_account_ix)
         .instruction(mint_to_ix)
-        .signer(&nft_mint)
         .accounts(nft_accounts::MintNFT {
             candy_machine: candy_machine_id,
             candy_machine_creator: candy_machine_creator_pda,
@@ -336,9 +640,114 @@ pub fn mint(
         }
     }
 
-    let sig = builder.send()?;
+    let mut instructions = builder.instructions()?;
+
+    // the collection of the candy machine is verified in the mint transaction
+    if let Some(collection) = get_collection_state(&program, &candy_machine_id)? {
+        instructions.push(set_collection_during_mint(
+            candy_machine_id,
+            metadata_pda,
+            payer,
+            &collection,
+            candy_machine_state.authority,
+        ));
+    }
+
+    let sig = send_with_callback(
+        &program,
+        &instructions,
+        &[keypair, nft_mint],
+        program.rpc().commitment(),
+        |attempt, _| {
+            *signature = Some(*attempt);
+            Ok(())
+        },
+    )?;
+    rpc_cache::invalidate(&candy_machine_id);
+
+    // the mint price is recorded by the caller (see MintSpend)
+    record_transaction(2, 5, priority_fee);
+    let rent = min_rent
+        + program
+            .rpc()
+            .get_minimum_balance_for_rent_exemption(Account::LEN)?
+        + program
+            .rpc()
+            .get_minimum_balance_for_rent_exemption(MAX_METADATA_LEN)?
+        + program
+            .rpc()
+            .get_minimum_balance_for_rent_exemption(MAX_MASTER_EDITION_LEN)?;
+
+    record_rent(rent);
 
     info!("Minted! TxId: {}", sig);
 
+    // the NFT exists at this point, so a failure only affects its uses
+    let uses_error = match uses {
+        Some(uses) => match set_uses(&program, keypair, &metadata_pda, uses) {
+            Ok(_) => None,
+            Err(err) => {
+                warn!(
+                    "Failed to set uses of minted NFT {}: {:?}",
+                    nft_mint.pubkey(),
+                    err
+                );
+                Some(format!("{}: {}", nft_mint.pubkey(), err))
+            }
+        },
+        None => None,
+    };
+
+    Ok(MintedNft {
+        signature: sig,
+        mint: nft_mint.pubkey(),
+        uses_error,
+    })
+}
+
+/// Set the uses settings of a minted NFT. This requires the payer to be the
+/// update authority of the NFT (see `check_uses_authority`).
+fn set_uses(
+    program: &Program,
+    keypair: &dyn Signer,
+    metadata_pda: &Pubkey,
+    uses: Uses,
+) -> Result<Signature> {
+    let payer = program.payer();
+    let account = program.rpc().get_account_data(metadata_pda)?;
+    let metadata: Metadata = try_from_slice_checked(&account, Key::MetadataV1, MAX_METADATA_LEN)?;
+
+    if metadata.update_authority != payer {
+        return Err(anyhow!(
+            "Cannot set uses: {} is not the update authority of the minted NFT",
+            payer
+        ));
+    }
+
+    let data = DataV2 {
+        name: metadata.data.name,
+        symbol: metadata.data.symbol,
+        uri: metadata.data.uri,
+        seller_fee_basis_points: metadata.data.seller_fee_basis_points,
+        creators: metadata.data.creators,
+        collection: metadata.collection,
+        uses: Some(uses),
+    };
+
+    let instruction = update_metadata_accounts_v2(
+        mpl_token_metadata::ID,
+        *metadata_pda,
+        payer,
+        None,
+        Some(data),
+        None,
+        None,
+    );
+    let sig = send_with_retry(program, &[instruction], &[keypair])?;
+
+    record_transaction(1, 1, None);
+
+    info!("Uses set! TxId: {}", sig);
+
     Ok(sig)
 }
@@ -0,0 +1,13 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum MonitorError {
+    #[error("Invalid monitor interval '{0}': must be at least 1 second")]
+    InvalidInterval(u64),
+
+    #[error("Keypair {0} is not the candy machine authority and cannot pause the sale")]
+    NotAuthority(String),
+
+    #[error("Failed to send webhook alert: {0}")]
+    WebhookFailed(String),
+}
@@ -0,0 +1,366 @@
+use anchor_lang::prelude::AccountMeta;
+use chrono::Utc;
+use console::style;
+use serde::Serialize;
+use std::{str::FromStr, time::Duration};
+
+use mpl_candy_machine::CandyMachineData;
+
+use crate::cache::load_cache;
+use crate::candy_machine::ID as CANDY_MACHINE_ID;
+use crate::candy_machine::{parse_config_price, scan_config_lines};
+use crate::common::*;
+use crate::config::parser::{get_config_data, resolve_config_path};
+use crate::monitor::MonitorError;
+use crate::rpc_cache;
+use crate::spend::record_transaction;
+use crate::transaction::send_with_retry;
+use crate::utils::*;
+
+pub struct MonitorArgs {
+    pub keypair: Option<String>,
+    pub rpc_url: Option<String>,
+    pub cache: String,
+    pub config: String,
+    pub candy_machine: Option<String>,
+    pub interval: u64,
+    pub webhook: Option<String>,
+    pub no_pause: bool,
+}
+
+/// Alert sent to the webhook when an anomaly is detected.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MonitorAlert<'a> {
+    candy_machine: String,
+    anomalies: &'a [String],
+    paused: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signature: Option<String>,
+    timestamp: String,
+}
+
+/// State of the candy machine when the monitor started, used as the
+/// reference for the checks.
+struct Baseline {
+    authority: Pubkey,
+    price: u64,
+    discount_price: Option<u64>,
+    items_redeemed: u64,
+    treasury_balance: Option<u64>,
+}
+
+pub async fn process_monitor(args: MonitorArgs) -> Result<()> {
+    if args.interval == 0 {
+        return Err(MonitorError::InvalidInterval(args.interval).into());
+    }
+
+    let sugar_config = sugar_setup(args.keypair, args.rpc_url)?;
+    let client = setup_client(&sugar_config)?;
+    let program = client.program(CANDY_MACHINE_ID);
+    let cache = load_cache(&args.cache, false)?;
+
+    let candy_machine_id = match args.candy_machine {
+        Some(candy_machine_id) => candy_machine_id,
+        None => cache.program.candy_machine.clone(),
+    };
+
+    let candy_pubkey = match Pubkey::from_str(&candy_machine_id) {
+        Ok(candy_pubkey) => candy_pubkey,
+        Err(_) => {
+            let error = anyhow!("Failed to parse candy machine id: {}", candy_machine_id);
+            error!("{:?}", error);
+            return Err(error);
+        }
+    };
+
+    println!(
+        "{} {}Loading candy machine",
+        style("[1/2]").bold().dim(),
+        LOOKING_GLASS_EMOJI
+    );
+    println!("{} {}", style("Candy machine ID:").bold(), candy_machine_id);
+
+    let pb = spinner_with_style();
+    pb.set_message("Connecting...");
+
+    let (candy_machine, _) = fetch_candy_machine(&program, &candy_pubkey)?;
+
+    // the configured prices are the reference, falling back to the on-chain
+    // values when there is no config file
+    let (price, discount_price) = if Path::new(&resolve_config_path(&args.config)).exists() {
+        let config_data = get_config_data(&args.config)?;
+        (
+            parse_config_price(&client, &config_data)?,
+            config_data
+                .whitelist_mint_settings
+                .and_then(|settings| settings.into_candy_format().discount_price),
+        )
+    } else {
+        (
+            candy_machine.data.price,
+            candy_machine
+                .data
+                .whitelist_mint_settings
+                .as_ref()
+                .and_then(|settings| settings.discount_price),
+        )
+    };
+
+    let mut baseline = Baseline {
+        authority: candy_machine.authority,
+        price,
+        discount_price,
+        items_redeemed: candy_machine.items_redeemed,
+        // mint proceeds can only be checked when paid in SOL
+        treasury_balance: match candy_machine.token_mint {
+            Some(_) => None,
+            None => Some(program.rpc().get_balance(&candy_machine.wallet)?),
+        },
+    };
+
+    pb.finish_with_message("Done");
+
+    if !args.no_pause && candy_machine.authority != program.payer() {
+        return Err(MonitorError::NotAuthority(program.payer().to_string()).into());
+    }
+
+    println!(
+        "\n{} {}Monitoring every {}s (Ctrl+C to stop)",
+        style("[2/2]").bold().dim(),
+        COMPUTER_EMOJI,
+        args.interval
+    );
+
+    // the candy machine account is polled: its state (not its transactions)
+    // is what is checked, so a poll misses nothing between intervals, and a
+    // Geyser stream would need a gRPC client built for a newer Solana SDK
+    loop {
+        tokio::time::sleep(Duration::from_secs(args.interval)).await;
+
+        let anomalies = match check_candy_machine(&program, &candy_pubkey, &cache, &mut baseline) {
+            Ok(anomalies) => anomalies,
+            Err(err) => {
+                // transient RPC failures should not stop the monitor
+                warn!("Failed to check candy machine: {}", err);
+                continue;
+            }
+        };
+
+        if anomalies.is_empty() {
+            info!("No anomalies detected");
+            continue;
+        }
+
+        println!(
+            "\n{}{}",
+            ERROR_EMOJI,
+            style("Anomalies detected:").red().bold()
+        );
+        for anomaly in &anomalies {
+            println!("  - {}", anomaly);
+        }
+
+        let signature = if args.no_pause {
+            None
+        } else {
+            match pause_candy_machine(&program, &sugar_config.keypair, &candy_pubkey) {
+                Ok(signature) => {
+                    println!("{} {}", style("Sale paused:").bold(), signature);
+                    Some(signature.to_string())
+                }
+                Err(err) => {
+                    println!("{} {}", style("Failed to pause the sale:").red(), err);
+                    None
+                }
+            }
+        };
+
+        if let Some(webhook) = &args.webhook {
+            let alert = MonitorAlert {
+                candy_machine: candy_machine_id.clone(),
+                anomalies: &anomalies,
+                paused: signature.is_some(),
+                signature,
+                timestamp: Utc::now().to_rfc3339(),
+            };
+
+            if let Err(err) = send_alert(webhook, &alert).await {
+                println!("{} {}", style("Alert not sent:").red(), err);
+            }
+        }
+
+        if !args.no_pause {
+            println!("\nMonitor stopped. Use 'sugar update' to resume the sale.");
+            return Ok(());
+        }
+    }
+}
+
+fn fetch_candy_machine(
+    program: &Program,
+    candy_pubkey: &Pubkey,
+) -> Result<(CandyMachine, Vec<u8>)> {
+    rpc_cache::invalidate(candy_pubkey);
+    let data = rpc_cache::get_account_data(program, candy_pubkey)?;
+    let candy_machine = CandyMachine::try_deserialize(&mut data.as_slice())?;
+
+    Ok((candy_machine, data))
+}
+
+/// Check the candy machine for anomalous conditions, returning a description
+/// of each one found.
+fn check_candy_machine(
+    program: &Program,
+    candy_pubkey: &Pubkey,
+    cache: &Cache,
+    baseline: &mut Baseline,
+) -> Result<Vec<String>> {
+    let (candy_machine, data) = fetch_candy_machine(program, candy_pubkey)?;
+    let mut anomalies = Vec::new();
+
+    if candy_machine.authority != baseline.authority {
+        anomalies.push(format!(
+            "Authority changed from {} to {}",
+            baseline.authority, candy_machine.authority
+        ));
+    }
+
+    if candy_machine.data.price < baseline.price {
+        anomalies.push(format!(
+            "Price lowered from {} to {}",
+            baseline.price, candy_machine.data.price
+        ));
+    }
+
+    let discount_price = candy_machine
+        .data
+        .whitelist_mint_settings
+        .as_ref()
+        .and_then(|settings| settings.discount_price);
+
+    if let Some(discount_price) = discount_price {
+        if discount_price < baseline.discount_price.unwrap_or(baseline.price) {
+            anomalies.push(format!("Discount price lowered to {}", discount_price));
+        }
+    }
+
+    // proceeds of the new mints must cover the lowest configured price
+    let minted = candy_machine
+        .items_redeemed
+        .saturating_sub(baseline.items_redeemed);
+
+    if let Some(previous_balance) = baseline.treasury_balance {
+        let balance = program.rpc().get_balance(&candy_machine.wallet)?;
+        let lowest_price = baseline
+            .discount_price
+            .map(|discount| discount.min(baseline.price))
+            .unwrap_or(baseline.price);
+
+        // a lower balance means funds were moved out of the treasury, in which
+        // case the proceeds cannot be checked for this interval
+        if minted > 0 && balance >= previous_balance {
+            let proceeds = balance - previous_balance;
+
+            if proceeds < minted * lowest_price {
+                anomalies.push(format!(
+                    "{} mint(s) paid {} lamports, below the expected {}",
+                    minted,
+                    proceeds,
+                    minted * lowest_price
+                ));
+            }
+        }
+
+        baseline.treasury_balance = Some(balance);
+    }
+
+    baseline.items_redeemed = candy_machine.items_redeemed;
+
+    if candy_machine.data.hidden_settings.is_none() {
+        // only the items deployed according to the cache are expected on-chain
+        let mismatched: Vec<String> = scan_config_lines(&data, cache)
+            .into_iter()
+            .map(|status| (status.index.to_string(), status.on_chain))
+            .filter(|(index, on_chain)| {
+                !on_chain
+                    && cache
+                        .items
+                        .0
+                        .get(index)
+                        .map(|item| item.on_chain)
+                        .unwrap_or(false)
+            })
+            .map(|(index, _)| index)
+            .collect();
+
+        if !mismatched.is_empty() {
+            anomalies.push(format!(
+                "{} config line(s) do not match the cache: {}",
+                mismatched.len(),
+                mismatched.join(", ")
+            ));
+        }
+    }
+
+    Ok(anomalies)
+}
+
+/// Pause the sale by removing the go live date and disabling the whitelist
+/// presale, so only the authority is able to mint.
+pub fn pause_candy_machine(
+    program: &Program,
+    payer: &dyn Signer,
+    candy_pubkey: &Pubkey,
+) -> Result<Signature> {
+    let (candy_machine, _) = fetch_candy_machine(program, candy_pubkey)?;
+
+    if candy_machine.authority != program.payer() {
+        return Err(MonitorError::NotAuthority(program.payer().to_string()).into());
+    }
+
+    let mut data: CandyMachineData = candy_machine.data;
+    data.go_live_date = None;
+
+    if let Some(settings) = data.whitelist_mint_settings.as_mut() {
+        settings.presale = false;
+    }
+
+    let mut builder = program
+        .request()
+        .accounts(nft_accounts::UpdateCandyMachine {
+            candy_machine: *candy_pubkey,
+            authority: program.payer(),
+            wallet: candy_machine.wallet,
+        })
+        .args(nft_instruction::UpdateCandyMachine { data });
+
+    if let Some(token_mint) = candy_machine.token_mint {
+        builder = builder.accounts(AccountMeta {
+            pubkey: token_mint,
+            is_signer: false,
+            is_writable: false,
+        });
+    }
+
+    let signature = send_with_retry(program, &builder.instructions()?, &[payer])?;
+    rpc_cache::invalidate(candy_pubkey);
+    record_transaction(1, 1, None);
+
+    Ok(signature)
+}
+
+async fn send_alert(webhook: &str, alert: &MonitorAlert<'_>) -> Result<()> {
+    let response = http_client()
+        .post(webhook)
+        .json(alert)
+        .send()
+        .await
+        .map_err(|err| MonitorError::WebhookFailed(err.to_string()))?;
+
+    if !response.status().is_success() {
+        return Err(MonitorError::WebhookFailed(response.status().to_string()).into());
+    }
+
+    Ok(())
+}
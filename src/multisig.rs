@@ -0,0 +1,84 @@
+use anchor_client::solana_sdk::{instruction::Instruction, message::Message};
+use data_encoding::BASE64;
+use serde::Serialize;
+use std::fs::File;
+
+use crate::common::*;
+
+/// Transaction proposal for a multisig authority (e.g., a Squads vault),
+/// written instead of sending the transaction.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MultisigProposal {
+    pub description: String,
+    pub authority: String,
+    /// Serialized transaction message, in the encodings accepted by the
+    /// multisig UIs.
+    pub message: EncodedMessage,
+    pub instructions: Vec<ProposalInstruction>,
+}
+
+#[derive(Serialize)]
+pub struct EncodedMessage {
+    pub base58: String,
+    pub base64: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProposalInstruction {
+    pub program_id: String,
+    pub accounts: Vec<ProposalAccount>,
+    /// Instruction data (base58).
+    pub data: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProposalAccount {
+    pub pubkey: String,
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+impl MultisigProposal {
+    /// Create a proposal for the instructions, with the multisig authority as
+    /// the fee payer. The blockhash is set by the multisig when executing it.
+    pub fn new(description: &str, authority: &Pubkey, instructions: &[Instruction]) -> Self {
+        let message = Message::new(instructions, Some(authority)).serialize();
+
+        MultisigProposal {
+            description: description.to_string(),
+            authority: authority.to_string(),
+            message: EncodedMessage {
+                base58: bs58::encode(&message).into_string(),
+                base64: BASE64.encode(&message),
+            },
+            instructions: instructions
+                .iter()
+                .map(|instruction| ProposalInstruction {
+                    program_id: instruction.program_id.to_string(),
+                    accounts: instruction
+                        .accounts
+                        .iter()
+                        .map(|account| ProposalAccount {
+                            pubkey: account.pubkey.to_string(),
+                            is_signer: account.is_signer,
+                            is_writable: account.is_writable,
+                        })
+                        .collect(),
+                    data: bs58::encode(&instruction.data).into_string(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Write the proposal to a file.
+    pub fn write(&self, path: &str) -> Result<()> {
+        let file = File::create(path)
+            .map_err(|err| anyhow!("Could not create proposal file '{}': {}", path, err))?;
+        serde_json::to_writer_pretty(file, self)?;
+
+        Ok(())
+    }
+}
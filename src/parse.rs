@@ -1,5 +1,5 @@
 use anyhow::{anyhow, Result};
-use std::{env, fs::File, path::Path};
+use std::{env, fs::File, path::Path, time::Duration};
 
 use crate::config::data::*;
 
@@ -35,3 +35,41 @@ pub fn path_to_string(path: &Path) -> Result<String> {
         None => Err(anyhow!("Couldn't convert path to string.")),
     }
 }
+
+/// Parse a duration in the form '90s', '30m', '2h' or a combination of them
+/// (e.g., '1h30m'). A number without unit is interpreted as seconds.
+pub fn parse_duration(input: &str) -> Result<Duration> {
+    let input = input.trim();
+
+    if let Ok(seconds) = input.parse::<u64>() {
+        return Ok(Duration::from_secs(seconds));
+    }
+
+    let mut total = 0u64;
+    let mut value = String::new();
+
+    for c in input.chars() {
+        if c.is_ascii_digit() {
+            value.push(c);
+        } else {
+            let amount = value
+                .parse::<u64>()
+                .map_err(|_| anyhow!("Invalid duration '{}'", input))?;
+            let unit = match c {
+                's' => 1,
+                'm' => 60,
+                'h' => 3600,
+                'd' => 86400,
+                _ => return Err(anyhow!("Invalid duration unit '{}' in '{}'", c, input)),
+            };
+            total += amount * unit;
+            value.clear();
+        }
+    }
+
+    if !value.is_empty() || total == 0 {
+        return Err(anyhow!("Invalid duration '{}'", input));
+    }
+
+    Ok(Duration::from_secs(total))
+}
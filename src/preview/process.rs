@@ -0,0 +1,369 @@
+use console::style;
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    path::{Path, PathBuf},
+};
+
+use crate::cache::load_cache;
+use crate::common::*;
+use crate::upload::get_uploaded_asset_pairs;
+use crate::utils::*;
+use crate::validate::format::Metadata;
+
+pub struct PreviewArgs {
+    pub assets_dir: String,
+    pub config: String,
+    pub cache: String,
+    pub use_cache: bool,
+    pub output: String,
+    pub serve: bool,
+    pub port: u16,
+}
+
+struct PreviewItem {
+    index: usize,
+    name: String,
+    image: String,
+    attributes: Vec<(String, String)>,
+    score: f64,
+}
+
+/// Rarity tiers as (label, colour, top fraction of the collection).
+const RARITY_TIERS: [(&str, &str, f64); 4] = [
+    ("Legendary", "#f5a623", 0.01),
+    ("Epic", "#9b59b6", 0.05),
+    ("Rare", "#3498db", 0.15),
+    ("Uncommon", "#2ecc71", 0.40),
+];
+
+pub fn process_preview(args: PreviewArgs) -> Result<()> {
+    println!(
+        "{} {}Loading assets",
+        style(if args.serve { "[1/2]" } else { "[1/1]" })
+            .bold()
+            .dim(),
+        ASSETS_EMOJI
+    );
+
+    let pb = spinner_with_style();
+    pb.set_message("Reading files...");
+
+    // shows the images that are uploaded (optimized, if enabled)
+    let asset_pairs = get_uploaded_asset_pairs(&args.assets_dir, &args.config)?;
+
+    let cache = if args.use_cache {
+        Some(load_cache(&args.cache, false)?)
+    } else {
+        None
+    };
+
+    let mut items = Vec::with_capacity(asset_pairs.len());
+
+    for (index, pair) in &asset_pairs {
+        let f = File::open(&pair.metadata)?;
+        let metadata: Metadata = serde_json::from_reader(f).map_err(|e| {
+            anyhow!(
+                "Failed to read metadata file '{}' with error: {}",
+                pair.metadata,
+                e
+            )
+        })?;
+
+        let image = if let Some(cache) = &cache {
+            match cache.items.0.get(&index.to_string()) {
+                Some(item) if !item.image_link.is_empty() => item.image_link.clone(),
+                _ => return Err(anyhow!("Missing image link for cache item {}", index)),
+            }
+        } else {
+            pair.image.clone()
+        };
+
+        items.push(PreviewItem {
+            index: *index,
+            name: metadata.name,
+            image,
+            attributes: metadata
+                .attributes
+                .into_iter()
+                .map(|a| {
+                    let value = match a.value {
+                        Value::String(value) => value,
+                        value => value.to_string(),
+                    };
+                    (a.trait_type, value)
+                })
+                .collect(),
+            score: 0.0,
+        });
+    }
+
+    items.sort_by_key(|item| item.index);
+    compute_rarity(&mut items);
+
+    let html = render_gallery(&items, |image| image.to_string());
+    let mut file = File::create(&args.output)?;
+    file.write_all(html.as_bytes())?;
+
+    pb.finish_and_clear();
+
+    println!(
+        "Gallery with {} item(s) saved to: {}",
+        items.len(),
+        style(&args.output).bold()
+    );
+
+    if args.serve {
+        println!(
+            "\n{} {}Serving gallery (Ctrl+C to stop)",
+            style("[2/2]").bold().dim(),
+            COMPUTER_EMOJI
+        );
+
+        // only the assets directory is served, so the local images are
+        // linked by their path inside it; optimized copies (outside of it)
+        // are shown by their source image, which has the same file name
+        let root = Path::new(&args.assets_dir).canonicalize()?;
+        let html = render_gallery(&items, |image| served_path(image, &root));
+        serve(&html, &root, args.port)?;
+    }
+
+    Ok(())
+}
+
+/// Compute a rarity score for each item as the sum of the inverse frequency
+/// of each of its trait values.
+fn compute_rarity(items: &mut [PreviewItem]) {
+    let mut frequency: HashMap<(String, String), usize> = HashMap::new();
+
+    for item in items.iter() {
+        for attribute in &item.attributes {
+            *frequency.entry(attribute.clone()).or_insert(0) += 1;
+        }
+    }
+
+    let total = items.len() as f64;
+
+    for item in items.iter_mut() {
+        item.score = item
+            .attributes
+            .iter()
+            .map(|attribute| total / frequency[attribute] as f64)
+            .sum();
+    }
+}
+
+fn rarity_tier(rank: usize, total: usize) -> Option<(&'static str, &'static str)> {
+    let position = (rank + 1) as f64 / total as f64;
+
+    RARITY_TIERS
+        .iter()
+        .find(|(_, _, fraction)| position <= *fraction)
+        .map(|(label, colour, _)| (*label, *colour))
+}
+
+/// Return the URL path of a local image served from the assets directory.
+/// Image links (from the cache) are returned unchanged.
+fn served_path(image: &str, root: &Path) -> String {
+    if image.starts_with("http://") || image.starts_with("https://") {
+        return image.to_string();
+    }
+
+    let path = Path::new(image);
+    let relative = path
+        .canonicalize()
+        .ok()
+        .and_then(|path| path.strip_prefix(root).ok().map(Path::to_path_buf))
+        .unwrap_or_else(|| PathBuf::from(path.file_name().unwrap_or_default()));
+
+    let segments: Vec<String> = relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy().to_string())
+        .collect();
+
+    format!("/{}", segments.join("/"))
+}
+
+/// Render the gallery, with the `src` of each image returned by `image_src`.
+fn render_gallery<F: Fn(&str) -> String>(items: &[PreviewItem], image_src: F) -> String {
+    // rank of each item by descending rarity score
+    let mut ranked: Vec<usize> = (0..items.len()).collect();
+    ranked.sort_by(|a, b| items[*b].score.total_cmp(&items[*a].score));
+
+    let mut ranks = vec![0; items.len()];
+    for (rank, position) in ranked.into_iter().enumerate() {
+        ranks[position] = rank;
+    }
+
+    let mut cards = String::new();
+
+    for (position, item) in items.iter().enumerate() {
+        let badge = match rarity_tier(ranks[position], items.len()) {
+            Some((label, colour)) => format!(
+                "<span class=\"badge\" style=\"background:{}\">{}</span>",
+                colour, label
+            ),
+            None => String::new(),
+        };
+
+        let attributes: String = item
+            .attributes
+            .iter()
+            .map(|(trait_type, value)| {
+                format!(
+                    "<li><b>{}</b>: {}</li>",
+                    escape_html(trait_type),
+                    escape_html(value)
+                )
+            })
+            .collect();
+
+        cards.push_str(&format!(
+            "<div class=\"card\"><img src=\"{}\" loading=\"lazy\"><div class=\"info\">\
+             <div class=\"title\">#{} {} {}</div><div class=\"rank\">rank {} / {}</div>\
+             <ul>{}</ul></div></div>\n",
+            escape_html(&image_src(&item.image)),
+            item.index,
+            escape_html(&item.name),
+            badge,
+            ranks[position] + 1,
+            items.len(),
+            attributes
+        ));
+    }
+
+    format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Sugar preview</title>\n\
+         <style>body{{font-family:sans-serif;background:#111;color:#eee;margin:20px}}\
+         .grid{{display:grid;grid-template-columns:repeat(auto-fill,minmax(200px,1fr));gap:16px}}\
+         .card{{background:#222;border-radius:8px;overflow:hidden}}\
+         .card img{{width:100%;aspect-ratio:1;object-fit:cover;background:#333}}\
+         .info{{padding:8px;font-size:13px}}.title{{font-weight:bold}}.rank{{color:#999}}\
+         .badge{{border-radius:4px;padding:1px 6px;font-size:11px;color:#fff}}\
+         ul{{padding-left:16px;margin:4px 0}}</style></head>\n\
+         <body><h1>Collection preview ({} items)</h1>\n<div class=\"grid\">\n{}</div></body></html>\n",
+        items.len(),
+        cards
+    )
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Serve the gallery and the files under the assets directory.
+fn serve(gallery: &str, root: &Path, port: u16) -> Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port))?;
+
+    println!(
+        "Gallery available at: {}",
+        style(format!("http://127.0.0.1:{}/", port)).bold()
+    );
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                if let Err(err) = handle_request(stream, root, gallery) {
+                    debug!("Preview request failed: {}", err);
+                }
+            }
+            Err(err) => debug!("Preview connection failed: {}", err),
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_request(mut stream: TcpStream, root: &Path, gallery: &str) -> Result<()> {
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+
+    let target = request_line.split_whitespace().nth(1).unwrap_or("/");
+    let target = target.split('?').next().unwrap_or("/");
+
+    let response = if target == "/" {
+        Some(("text/html; charset=utf-8", gallery.as_bytes().to_vec()))
+    } else {
+        let path = root.join(percent_decode(target.trim_start_matches('/')));
+
+        // only files under the assets directory are served
+        match path.canonicalize() {
+            Ok(path) if path.starts_with(root) && path.is_file() => {
+                Some((content_type(&path), fs::read(&path)?))
+            }
+            _ => None,
+        }
+    };
+
+    match response {
+        Some((content_type, body)) => {
+            let header = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                content_type,
+                body.len()
+            );
+            stream.write_all(header.as_bytes())?;
+            stream.write_all(&body)?;
+        }
+        None => {
+            stream.write_all(
+                b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Decode the percent-encoded bytes of a URL path (e.g., spaces in file names).
+fn percent_decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| path.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+fn content_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+
+    match extension.as_str() {
+        "html" => "text/html; charset=utf-8",
+        "json" => "application/json",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "mp4" => "video/mp4",
+        "mov" => "video/quicktime",
+        "webm" => "video/webm",
+        _ => "application/octet-stream",
+    }
+}
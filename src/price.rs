@@ -0,0 +1,63 @@
+use anchor_client::solana_sdk::native_token::LAMPORTS_PER_SOL;
+use std::sync::OnceLock;
+
+use crate::common::*;
+
+/// SOL price in USD, set by the `--price-api` option.
+static SOL_PRICE: OnceLock<f64> = OnceLock::new();
+
+/// Retrieve the SOL price from the specified API. The response can be either
+/// a number, a CoinGecko simple price response (`{"solana":{"usd":<price>}}`)
+/// or an object with a `price` field.
+///
+/// The USD values are only informative, so a failure to retrieve the price
+/// is logged and the command continues without them.
+pub async fn init_price_oracle(url: &str) -> Result<()> {
+    let response: Value = match http_client().get(url).send().await {
+        Ok(response) => match response.json().await {
+            Ok(value) => value,
+            Err(err) => {
+                warn!("Could not parse the price API response: {}", err);
+                return Ok(());
+            }
+        },
+        Err(err) => {
+            warn!("Could not retrieve the SOL price: {}", err);
+            return Ok(());
+        }
+    };
+
+    let price = [&response, &response["solana"]["usd"], &response["price"]]
+        .iter()
+        .find_map(|value| match value {
+            Value::Number(number) => number.as_f64(),
+            Value::String(string) => string.parse::<f64>().ok(),
+            _ => None,
+        });
+
+    match price {
+        Some(price) if price > 0.0 => {
+            info!("SOL price: ${}", price);
+            SOL_PRICE.set(price).ok();
+        }
+        _ => warn!("Could not find the SOL price in the price API response"),
+    }
+
+    Ok(())
+}
+
+/// Return the approximate USD value of the specified lamports amount.
+pub fn usd_value(lamports: u64) -> Option<f64> {
+    SOL_PRICE
+        .get()
+        .map(|price| lamports as f64 / LAMPORTS_PER_SOL as f64 * price)
+}
+
+/// Return the approximate USD value to be appended to a lamports amount, or
+/// an empty string if no price is available.
+pub fn usd_suffix(lamports: u64) -> String {
+    match usd_value(lamports) {
+        Some(value) => format!(" ~${:.2}", value),
+        None => String::new(),
+    }
+}
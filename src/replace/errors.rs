@@ -0,0 +1,19 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ReplaceError {
+    #[error("Candy machine not deployed - run 'deploy' first")]
+    CandyMachineNotDeployed,
+
+    #[error("Item {0} not found in the cache")]
+    ItemNotFound(usize),
+
+    #[error("Item {0} has already been minted")]
+    AlreadyMinted(usize),
+
+    #[error("Nothing to replace: specify an image and/or metadata file")]
+    NothingToReplace,
+
+    #[error("Invalid {0} file '{1}'")]
+    InvalidFile(String, String),
+}
@@ -0,0 +1,152 @@
+use anchor_lang::AccountDeserialize;
+use console::style;
+use mpl_candy_machine::CandyMachine;
+use std::{
+    fs,
+    str::FromStr,
+    sync::{atomic::AtomicBool, Arc},
+};
+
+use crate::cache::load_cache;
+use crate::candy_machine::{is_item_minted, ID as CANDY_MACHINE_ID};
+use crate::common::*;
+use crate::deploy::{process_deploy, DeployArgs};
+use crate::replace::ReplaceError;
+use crate::spend::track_spend;
+use crate::upload::{process_upload, UploadArgs};
+use crate::validate::format::Metadata;
+
+/// Image extensions supported for an asset.
+const IMAGE_EXTENSIONS: [&str; 3] = ["png", "jpg", "gif"];
+
+pub struct ReplaceArgs {
+    pub index: usize,
+    pub image: Option<String>,
+    pub metadata: Option<String>,
+    pub assets_dir: String,
+    pub config: String,
+    pub keypair: Option<String>,
+    pub rpc_url: Option<String>,
+    pub cache: String,
+    pub interrupted: Arc<AtomicBool>,
+}
+
+pub async fn process_replace(args: ReplaceArgs) -> Result<()> {
+    track_spend("replace", &args.cache);
+
+    if args.image.is_none() && args.metadata.is_none() {
+        return Err(ReplaceError::NothingToReplace.into());
+    }
+
+    let cache = load_cache(&args.cache, false)?;
+
+    if cache.program.candy_machine.is_empty() {
+        return Err(ReplaceError::CandyMachineNotDeployed.into());
+    }
+
+    if !cache.items.0.contains_key(&args.index.to_string()) {
+        return Err(ReplaceError::ItemNotFound(args.index).into());
+    }
+
+    let candy_pubkey = Pubkey::from_str(&cache.program.candy_machine)
+        .map_err(|_| CacheError::InvalidCandyMachineAddress(cache.program.candy_machine.clone()))?;
+
+    println!(
+        "{} {}Checking item {}",
+        style(">>>").magenta(),
+        LOOKING_GLASS_EMOJI,
+        args.index
+    );
+
+    let sugar_config = sugar_setup(args.keypair.clone(), args.rpc_url.clone())?;
+    let client = setup_client(&sugar_config)?;
+    let program = client.program(CANDY_MACHINE_ID);
+
+    if is_minted(&program, &candy_pubkey, args.index)? {
+        return Err(ReplaceError::AlreadyMinted(args.index).into());
+    }
+
+    // validates the new metadata before touching the assets
+    if let Some(metadata) = &args.metadata {
+        let file = File::open(metadata)
+            .map_err(|_| ReplaceError::InvalidFile("metadata".to_string(), metadata.clone()))?;
+        let parsed: Metadata = serde_json::from_reader(file)
+            .map_err(|_| ReplaceError::InvalidFile("metadata".to_string(), metadata.clone()))?;
+        parsed.validate()?;
+    }
+
+    if let Some(image) = &args.image {
+        let extension = file_extension(image);
+
+        if !IMAGE_EXTENSIONS.contains(&extension.as_str()) {
+            return Err(ReplaceError::InvalidFile("image".to_string(), image.clone()).into());
+        }
+
+        // removes the current image, which might have a different extension
+        for extension in IMAGE_EXTENSIONS {
+            let current = Path::new(&args.assets_dir).join(format!("{}.{}", args.index, extension));
+            if current.exists() {
+                fs::remove_file(current)?;
+            }
+        }
+
+        let target = Path::new(&args.assets_dir).join(format!("{}.{}", args.index, extension));
+        fs::copy(image, target)?;
+    }
+
+    if let Some(metadata) = &args.metadata {
+        let target = Path::new(&args.assets_dir).join(format!("{}.json", args.index));
+        fs::copy(metadata, target)?;
+    }
+
+    println!("\n{} sugar upload\n", style(">>>").magenta());
+
+    process_upload(UploadArgs {
+        assets_dir: args.assets_dir,
+        config: args.config.clone(),
+        keypair: args.keypair.clone(),
+        rpc_url: args.rpc_url.clone(),
+        cache: args.cache.clone(),
+        concurrency: None,
+        rate_limit: None,
+        dry_run: false,
+        bundlr_node: None,
+        interrupted: args.interrupted.clone(),
+    })
+    .await?;
+
+    println!("\n{} sugar deploy\n", style(">>>").magenta());
+
+    process_deploy(DeployArgs {
+        config: args.config,
+        keypair: args.keypair,
+        rpc_url: args.rpc_url,
+        cache: args.cache,
+        force_rewrite: false,
+        append: false,
+        interrupted: args.interrupted,
+    })
+    .await
+}
+
+/// Check the candy machine mint bitmask to determine whether the item at the
+/// specified index has been minted.
+fn is_minted(program: &Program, candy_pubkey: &Pubkey, index: usize) -> Result<bool> {
+    let data = program.rpc().get_account_data(candy_pubkey)?;
+    let candy_machine = CandyMachine::try_deserialize(&mut data.as_slice())?;
+    let items_available = candy_machine.data.items_available as usize;
+
+    if index >= items_available {
+        return Err(ReplaceError::ItemNotFound(index).into());
+    }
+
+    is_item_minted(&data, items_available, index)
+}
+
+fn file_extension(file: &str) -> String {
+    Path::new(file)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+        .to_lowercase()
+}
@@ -0,0 +1,73 @@
+use anchor_client::solana_sdk::native_token::LAMPORTS_PER_SOL;
+use console::style;
+
+use crate::cli::ReportAction;
+use crate::common::*;
+use crate::journal::{journal_path, Journal};
+use crate::price::usd_suffix;
+use crate::spend::{print_spend_summary, SpendSummary};
+
+pub struct ReportArgs {
+    pub action: ReportAction,
+}
+
+pub fn process_report(args: ReportArgs) -> Result<()> {
+    match args.action {
+        ReportAction::Spend { cache } => process_spend_report(&cache),
+    }
+}
+
+fn process_spend_report(cache: &str) -> Result<()> {
+    let file_path = journal_path(cache);
+
+    if !Path::new(&file_path).exists() {
+        return Err(anyhow!(
+            "Journal file '{}' not found, no spend recorded for this project",
+            file_path
+        ));
+    }
+
+    let journal = Journal::load_for_cache(cache)?;
+
+    println!(
+        "{} {}Spend report",
+        style("[1/1]").bold().dim(),
+        PAYMENT_EMOJI
+    );
+
+    if journal.spend.is_empty() {
+        println!("\nNo spend recorded for this project.");
+        return Ok(());
+    }
+
+    let mut total = SpendSummary::default();
+    let mut commands: IndexMap<String, SpendSummary> = IndexMap::new();
+
+    println!();
+
+    for record in &journal.spend {
+        println!(
+            "{} {:<12}◎ {:.9}{}",
+            style(&record.recorded_at).dim(),
+            record.command,
+            record.summary.total() as f64 / LAMPORTS_PER_SOL as f64,
+            usd_suffix(record.summary.total())
+        );
+
+        total.add(&record.summary);
+        commands
+            .entry(record.command.clone())
+            .or_default()
+            .add(&record.summary);
+    }
+
+    for (command, summary) in &commands {
+        println!("\n{}", style(format!("{} command", command)).bold());
+        print_spend_summary(summary);
+    }
+
+    println!("\n{}", style("Project total").bold());
+    print_spend_summary(&total);
+
+    Ok(())
+}
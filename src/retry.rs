@@ -0,0 +1,300 @@
+use rand::Rng;
+use regex::Regex;
+use serde::Serialize;
+use solana_client::client_error::{ClientError, ClientErrorKind};
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        OnceLock,
+    },
+    thread,
+    time::Duration,
+};
+use thiserror::Error;
+
+use crate::common::*;
+use crate::transaction::SendError;
+
+/// Retry settings from the config file, set when the config file is loaded.
+static RETRY_CONFIG: OnceLock<RetryConfig> = OnceLock::new();
+
+/// Number of retries of each subsystem since the start of the command.
+static RETRIES: [AtomicU64; 3] = [AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0)];
+
+/// Subsystems with an independent retry policy.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Subsystem {
+    /// Uploads to the storage (Bundlr, AWS).
+    Upload,
+    /// Requests and transactions sent to the RPC node.
+    Rpc,
+    /// Polling for a confirmation (e.g., a funded balance).
+    Confirmation,
+}
+
+/// Retry settings of the config file. Values not specified for a subsystem
+/// fall back to the `default` values and then to the built-in policy.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryConfig {
+    pub default: Option<RetrySettings>,
+    pub upload: Option<RetrySettings>,
+    pub rpc: Option<RetrySettings>,
+    pub confirmation: Option<RetrySettings>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetrySettings {
+    /// Maximum number of attempts (including the first one).
+    pub max_attempts: Option<u32>,
+    /// Delay (ms) before the first retry.
+    pub initial_delay: Option<u64>,
+    /// Maximum delay (ms) between retries.
+    pub max_delay: Option<u64>,
+    /// Factor applied to the delay after each retry.
+    pub multiplier: Option<f64>,
+    /// Randomize the delays to avoid retrying in lockstep.
+    pub jitter: Option<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_delay: u64,
+    pub max_delay: u64,
+    pub multiplier: f64,
+    pub jitter: bool,
+}
+
+impl RetryPolicy {
+    /// Return the built-in policy of a subsystem.
+    pub fn default_for(subsystem: Subsystem) -> RetryPolicy {
+        match subsystem {
+            Subsystem::Upload => RetryPolicy {
+                max_attempts: 3,
+                initial_delay: 1000,
+                max_delay: 10_000,
+                multiplier: 2.0,
+                jitter: true,
+            },
+            Subsystem::Rpc => RetryPolicy {
+                max_attempts: 3,
+                initial_delay: 500,
+                max_delay: 5000,
+                multiplier: 2.0,
+                jitter: true,
+            },
+            Subsystem::Confirmation => RetryPolicy {
+                max_attempts: 120,
+                initial_delay: 1000,
+                max_delay: 1000,
+                multiplier: 1.0,
+                jitter: false,
+            },
+        }
+    }
+
+    fn merge(&mut self, settings: &RetrySettings) {
+        if let Some(max_attempts) = settings.max_attempts {
+            self.max_attempts = max_attempts.max(1);
+        }
+        if let Some(initial_delay) = settings.initial_delay {
+            self.initial_delay = initial_delay;
+        }
+        if let Some(max_delay) = settings.max_delay {
+            self.max_delay = max_delay;
+        }
+        if let Some(multiplier) = settings.multiplier {
+            self.multiplier = multiplier;
+        }
+        if let Some(jitter) = settings.jitter {
+            self.jitter = jitter;
+        }
+    }
+
+    /// Return the delay before the specified retry (starting at 1).
+    pub fn delay(&self, retry: u32) -> Duration {
+        let delay = (self.initial_delay as f64 * self.multiplier.powi(retry as i32 - 1))
+            .min(self.max_delay as f64) as u64;
+
+        let delay = if self.jitter && delay > 0 {
+            // between half and the full delay
+            rand::thread_rng().gen_range(delay / 2, delay + 1)
+        } else {
+            delay
+        };
+
+        Duration::from_millis(delay)
+    }
+}
+
+/// Set the retry settings from the config file.
+pub fn configure_retry(config: &RetryConfig) {
+    RETRY_CONFIG.set(config.clone()).ok();
+}
+
+/// Return the retry policy of a subsystem.
+pub fn policy(subsystem: Subsystem) -> RetryPolicy {
+    let mut policy = RetryPolicy::default_for(subsystem);
+
+    if let Some(config) = RETRY_CONFIG.get() {
+        if let Some(default) = &config.default {
+            policy.merge(default);
+        }
+
+        let settings = match subsystem {
+            Subsystem::Upload => &config.upload,
+            Subsystem::Rpc => &config.rpc,
+            Subsystem::Confirmation => &config.confirmation,
+        };
+
+        if let Some(settings) = settings {
+            policy.merge(settings);
+        }
+    }
+
+    policy
+}
+
+/// Failed request of a client that does not expose a `reqwest::Error`.
+#[derive(Debug, Error)]
+#[error("Request failed: {message}")]
+pub struct RequestError {
+    /// HTTP status of the response, `None` if no response was received.
+    pub status: Option<u16>,
+    pub message: String,
+}
+
+/// Check whether an HTTP status is a transient failure (timeout, rate limit or
+/// server error).
+fn is_retryable_status(status: u16) -> bool {
+    status == 408 || status == 429 || status >= 500
+}
+
+/// Errors messages of transient failures.
+const RETRYABLE_MESSAGES: [&str; 9] = [
+    "timed out",
+    "timeout",
+    "connection",
+    "too many requests",
+    "bad gateway",
+    "service unavailable",
+    "blockhash not found",
+    "node is behind",
+    "temporarily unavailable",
+];
+
+/// HTTP statuses of transient failures in error messages. The word boundaries
+/// keep the pattern from matching digits of a signature or an address.
+static RETRYABLE_STATUS: OnceLock<Regex> = OnceLock::new();
+
+/// Check whether a reqwest error is a transient failure, `None` if it does not
+/// tell (e.g., an error decoding the response).
+fn is_retryable_reqwest(error: &reqwest::Error) -> Option<bool> {
+    if error.is_timeout() || error.is_connect() {
+        return Some(true);
+    }
+
+    error
+        .status()
+        .map(|status| is_retryable_status(status.as_u16()))
+}
+
+/// Check whether an error is a transient failure worth retrying. Typed errors
+/// in the chain are classified by their kind or HTTP status, falling back to
+/// the error message otherwise.
+pub fn is_retryable(error: &anyhow::Error) -> bool {
+    for cause in error.chain() {
+        if let Some(error) = cause.downcast_ref::<reqwest::Error>() {
+            if let Some(retryable) = is_retryable_reqwest(error) {
+                return retryable;
+            }
+        }
+
+        if let Some(error) = cause.downcast_ref::<ClientError>() {
+            match error.kind() {
+                ClientErrorKind::Io(_) => return true,
+                ClientErrorKind::Reqwest(error) => {
+                    if let Some(retryable) = is_retryable_reqwest(error) {
+                        return retryable;
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        if let Some(error) = cause.downcast_ref::<SendError>() {
+            return matches!(error, SendError::Expired(_));
+        }
+
+        if let Some(error) = cause.downcast_ref::<RequestError>() {
+            return error.status.map_or(true, is_retryable_status);
+        }
+    }
+
+    let message = format!("{:#}", error).to_lowercase();
+    let status = RETRYABLE_STATUS
+        .get_or_init(|| Regex::new(r"\b(429|502|503|504)\b").expect("Failed to create regex."));
+
+    RETRYABLE_MESSAGES.iter().any(|m| message.contains(m)) || status.is_match(&message)
+}
+
+/// Return the number of retries of a subsystem since the start of the command.
+pub fn retry_count(subsystem: Subsystem) -> u64 {
+    RETRIES[subsystem as usize].load(Ordering::Relaxed)
+}
+
+/// Run an operation, retrying transient failures according to the policy of
+/// the subsystem. The thread sleeps between attempts, so tokio tasks use
+/// [`retry_async`] or call it from `spawn_blocking`.
+pub fn retry<T, F>(subsystem: Subsystem, mut operation: F) -> Result<T>
+where
+    F: FnMut() -> Result<T>,
+{
+    let policy = policy(subsystem);
+    let mut attempt = 1;
+
+    loop {
+        match operation() {
+            Ok(value) => return Ok(value),
+            Err(error) if attempt < policy.max_attempts && is_retryable(&error) => {
+                warn!(
+                    "{:?} attempt {} of {} failed, retrying: {}",
+                    subsystem, attempt, policy.max_attempts, error
+                );
+                RETRIES[subsystem as usize].fetch_add(1, Ordering::Relaxed);
+                thread::sleep(policy.delay(attempt));
+                attempt += 1;
+            }
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+/// Async version of [`retry`].
+pub async fn retry_async<T, F, Fut>(subsystem: Subsystem, mut operation: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let policy = policy(subsystem);
+    let mut attempt = 1;
+
+    loop {
+        match operation().await {
+            Ok(value) => return Ok(value),
+            Err(error) if attempt < policy.max_attempts && is_retryable(&error) => {
+                warn!(
+                    "{:?} attempt {} of {} failed, retrying: {}",
+                    subsystem, attempt, policy.max_attempts, error
+                );
+                RETRIES[subsystem as usize].fetch_add(1, Ordering::Relaxed);
+                tokio::time::sleep(policy.delay(attempt)).await;
+                attempt += 1;
+            }
+            Err(error) => return Err(error),
+        }
+    }
+}
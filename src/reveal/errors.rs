@@ -0,0 +1,19 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum RevealError {
+    #[error("The config file has no hidden settings, there is nothing to reveal")]
+    NoHiddenSettings,
+
+    #[error("Missing candy machine id, deploy the candy machine or use '--candy-machine'")]
+    MissingCandyMachine,
+
+    #[error("Item {0} has no metadata link in the cache, run 'upload' before revealing")]
+    MissingMetadataLink(String),
+
+    #[error("Keypair {0} is not the update authority of NFT {1}")]
+    NotUpdateAuthority(String, String),
+
+    #[error("{0} NFT(s) could not be revealed, run 'reveal' again to retry")]
+    Incomplete(usize),
+}
@@ -0,0 +1,276 @@
+use console::style;
+use mpl_token_metadata::{
+    instruction::update_metadata_accounts_v2,
+    state::{DataV2, Key, Metadata, MAX_METADATA_LEN},
+    utils::try_from_slice_checked,
+    ID as TOKEN_METADATA_ID,
+};
+use std::str::FromStr;
+
+use crate::cache::load_cache;
+use crate::common::*;
+use crate::config::get_config_data;
+use crate::guard::get_creator_metadata;
+use crate::journal::Journal;
+use crate::mint::pdas::get_candy_machine_creator_pda;
+use crate::reveal::RevealError;
+use crate::spend::{record_transaction, track_spend};
+use crate::transaction::send_with_retry;
+use crate::utils::*;
+
+pub struct RevealArgs {
+    pub keypair: Option<String>,
+    pub rpc_url: Option<String>,
+    pub cache: String,
+    pub config: String,
+    pub candy_machine: Option<String>,
+}
+
+/// Journal stage of the reveal command.
+const STAGE_REVEAL: &str = "reveal";
+
+/// A minted NFT to reveal with the name and URI of its cache item.
+struct Reveal {
+    metadata_pda: Pubkey,
+    metadata: Metadata,
+    name: String,
+    uri: String,
+}
+
+pub fn process_reveal(args: RevealArgs) -> Result<()> {
+    track_spend("reveal", &args.cache);
+
+    let mut journal = Journal::load_for_cache(&args.cache)?;
+    journal.start(STAGE_REVEAL)?;
+
+    match reveal(args, &mut journal) {
+        Ok(()) => journal.complete(STAGE_REVEAL),
+        Err(err) => {
+            journal.fail(STAGE_REVEAL, &err.to_string())?;
+            Err(err)
+        }
+    }
+}
+
+fn reveal(args: RevealArgs, journal: &mut Journal) -> Result<()> {
+    let sugar_config = sugar_setup(args.keypair, args.rpc_url)?;
+    let client = setup_client(&sugar_config)?;
+    let program = client.program(TOKEN_METADATA_ID);
+    let payer = program.payer();
+
+    println!(
+        "{} {}Loading items from the cache",
+        style("[1/4]").bold().dim(),
+        ASSETS_EMOJI
+    );
+
+    let config_data = get_config_data(&args.config)?;
+    let hidden_settings = config_data
+        .hidden_settings
+        .as_ref()
+        .ok_or(RevealError::NoHiddenSettings)?;
+    let cache = load_cache(&args.cache, false)?;
+
+    // the candy machine specified takes precedence over the one from the cache
+    let candy_machine = match args.candy_machine {
+        Some(candy_machine) => candy_machine,
+        None if !cache.program.candy_machine.is_empty() => cache.program.candy_machine,
+        None => return Err(RevealError::MissingCandyMachine.into()),
+    };
+    let candy_machine = Pubkey::from_str(&candy_machine)
+        .map_err(|_| CacheError::InvalidCandyMachineAddress(candy_machine.clone()))?;
+
+    // the mint number (index + 1) of each cache item
+    let mut items = HashMap::new();
+
+    for (index, item) in &cache.items.0 {
+        let index = match index.parse::<u64>() {
+            Ok(index) => index,
+            // skips the collection item (index -1)
+            Err(_) => continue,
+        };
+
+        if item.metadata_link.is_empty() {
+            return Err(RevealError::MissingMetadataLink(index.to_string()).into());
+        }
+
+        items.insert(index + 1, (item.name.clone(), item.metadata_link.clone()));
+    }
+
+    println!("Found {} item(s) in the cache", items.len());
+
+    println!(
+        "\n{} {}Retrieving minted NFTs",
+        style("[2/4]").bold().dim(),
+        LOOKING_GLASS_EMOJI
+    );
+
+    let pb = spinner_with_style();
+    pb.set_message("Connecting...");
+
+    let (creator, _) = get_candy_machine_creator_pda(&candy_machine);
+    let minted = get_creator_metadata(&program, &creator)?;
+
+    pb.finish_and_clear();
+
+    let mut reveals = Vec::new();
+    let mut revealed = 0;
+
+    for (metadata_pda, metadata) in minted {
+        let name = metadata.data.name.trim_matches(char::from(0)).to_string();
+        let uri = metadata.data.uri.trim_matches(char::from(0)).to_string();
+
+        let (item_name, item_uri) = match mint_number(&name, hidden_settings.name())
+            .and_then(|number| items.get(&number))
+        {
+            Some(item) => item,
+            None => {
+                if uri == hidden_settings.uri() {
+                    warn!(
+                        "No cache item for the hidden NFT {} ({})",
+                        metadata.mint, name
+                    );
+                } else {
+                    revealed += 1;
+                }
+                continue;
+            }
+        };
+
+        if name == *item_name && uri == *item_uri {
+            revealed += 1;
+            continue;
+        }
+
+        if metadata.update_authority != payer {
+            return Err(RevealError::NotUpdateAuthority(
+                payer.to_string(),
+                metadata.mint.to_string(),
+            )
+            .into());
+        }
+
+        reveals.push(Reveal {
+            metadata_pda,
+            metadata,
+            name: item_name.clone(),
+            uri: item_uri.clone(),
+        });
+    }
+
+    println!(
+        "Found {} minted NFT(s), {} already revealed",
+        reveals.len() + revealed,
+        revealed
+    );
+
+    println!(
+        "\n{} {}Revealing NFTs",
+        style("[3/4]").bold().dim(),
+        CANDY_EMOJI
+    );
+
+    if reveals.is_empty() {
+        println!("\nAll minted NFTs have been revealed.");
+        return Ok(());
+    }
+
+    let pb = progress_bar_with_style(reveals.len() as u64);
+
+    for reveal in &reveals {
+        let instruction = update_metadata_accounts_v2(
+            TOKEN_METADATA_ID,
+            reveal.metadata_pda,
+            payer,
+            None,
+            Some(DataV2 {
+                name: reveal.name.clone(),
+                symbol: reveal.metadata.data.symbol.clone(),
+                uri: reveal.uri.clone(),
+                seller_fee_basis_points: reveal.metadata.data.seller_fee_basis_points,
+                creators: reveal.metadata.data.creators.clone(),
+                collection: reveal.metadata.collection.clone(),
+                uses: reveal.metadata.uses.clone(),
+            }),
+            None,
+            None,
+        );
+
+        let result = send_with_retry(&program, &[instruction], &[&sugar_config.keypair]);
+
+        match result {
+            Ok(signature) => {
+                record_transaction(1, 1, None);
+                journal.complete_batch(STAGE_REVEAL, &reveal.metadata.mint.to_string())?;
+                info!("Revealed {}: {}", reveal.metadata.mint, signature);
+            }
+            // the verification reports the NFTs that failed
+            Err(err) => error!("Failed to reveal {}: {}", reveal.metadata.mint, err),
+        }
+
+        pb.inc(1);
+    }
+
+    pb.finish_and_clear();
+
+    println!(
+        "\n{} {}Verifying revealed NFTs",
+        style("[4/4]").bold().dim(),
+        LOOKING_GLASS_EMOJI
+    );
+
+    let pb = spinner_with_style();
+    pb.set_message("Retrieving metadata...");
+
+    let mut failed = Vec::new();
+
+    for chunk in reveals.chunks(MAX_ACCOUNTS_PER_REQUEST) {
+        let pdas: Vec<Pubkey> = chunk.iter().map(|reveal| reveal.metadata_pda).collect();
+        let accounts = program.rpc().get_multiple_accounts(&pdas)?;
+
+        for (reveal, account) in chunk.iter().zip(accounts) {
+            let metadata: Option<Metadata> = account.and_then(|account| {
+                try_from_slice_checked(&account.data, Key::MetadataV1, MAX_METADATA_LEN).ok()
+            });
+
+            let verified = metadata
+                .map(|metadata| {
+                    metadata.data.name.trim_matches(char::from(0)) == reveal.name
+                        && metadata.data.uri.trim_matches(char::from(0)) == reveal.uri
+                })
+                .unwrap_or(false);
+
+            if !verified {
+                failed.push(reveal.metadata.mint);
+            }
+        }
+    }
+
+    pb.finish_and_clear();
+
+    if !failed.is_empty() {
+        println!("\nNFTs not revealed:");
+        for mint in &failed {
+            println!("- {}", mint);
+        }
+
+        return Err(RevealError::Incomplete(failed.len()).into());
+    }
+
+    println!(
+        "\n{}",
+        style(format!("{} NFT(s) revealed.", reveals.len())).bold()
+    );
+
+    Ok(())
+}
+
+/// Return the mint number of an NFT minted with hidden settings. The name of
+/// these NFTs is the prefix of the hidden settings followed by the number.
+fn mint_number(name: &str, prefix: &str) -> Option<u64> {
+    name.strip_prefix(prefix)?
+        .trim()
+        .trim_start_matches('#')
+        .parse::<u64>()
+        .ok()
+}
@@ -0,0 +1,5 @@
+pub mod pool;
+pub mod process;
+
+pub use pool::*;
+pub use process::*;
@@ -0,0 +1,224 @@
+use solana_client::rpc_client::RpcClient;
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::common::*;
+use crate::retry::is_retryable;
+
+/// Endpoints of the RPC pool: the RPC url of the command, followed by the
+/// `rpcUrls` of the config file.
+static ENDPOINTS: OnceLock<Mutex<Vec<Endpoint>>> = OnceLock::new();
+
+/// Position of the next endpoint to receive a request.
+static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+/// Time an endpoint is skipped after a rate limit or timeout.
+const ENDPOINT_COOLDOWN: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Default)]
+pub struct EndpointStats {
+    pub requests: u64,
+    pub failures: u64,
+    pub rate_limited: u64,
+    pub latency: Duration,
+}
+
+impl EndpointStats {
+    /// Return the average latency of the successful requests.
+    pub fn average_latency(&self) -> Option<Duration> {
+        let successful = self.requests - self.failures;
+
+        if successful > 0 {
+            Some(self.latency / successful as u32)
+        } else {
+            None
+        }
+    }
+}
+
+pub struct Endpoint {
+    pub url: String,
+    pub stats: EndpointStats,
+    client: Arc<RpcClient>,
+    cooldown_until: Option<Instant>,
+}
+
+impl Endpoint {
+    fn new(url: &str) -> Self {
+        Endpoint {
+            url: url.to_string(),
+            stats: EndpointStats::default(),
+            client: Arc::new(RpcClient::new_with_commitment(
+                url.to_string(),
+                CommitmentConfig::confirmed(),
+            )),
+            cooldown_until: None,
+        }
+    }
+
+    fn is_cooling_down(&self, now: Instant) -> bool {
+        self.cooldown_until.map_or(false, |until| until > now)
+    }
+}
+
+fn endpoints() -> &'static Mutex<Vec<Endpoint>> {
+    ENDPOINTS.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+/// Set the RPC url of the command as the first endpoint of the pool.
+pub fn set_primary_endpoint(url: &str) {
+    let mut endpoints = endpoints().lock().unwrap();
+
+    match endpoints.iter().position(|endpoint| endpoint.url == url) {
+        Some(0) => (),
+        Some(index) => {
+            let endpoint = endpoints.remove(index);
+            endpoints.insert(0, endpoint);
+        }
+        None => endpoints.insert(0, Endpoint::new(url)),
+    }
+}
+
+/// Add endpoints to the pool, ignoring the ones already present.
+pub fn add_endpoints(urls: &[String]) {
+    let mut endpoints = endpoints().lock().unwrap();
+
+    for url in urls {
+        if !endpoints.iter().any(|endpoint| &endpoint.url == url) {
+            endpoints.push(Endpoint::new(url));
+        }
+    }
+}
+
+/// Return the url and stats of the endpoints of the pool.
+pub fn endpoint_stats() -> Vec<(String, EndpointStats)> {
+    endpoints()
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|endpoint| (endpoint.url.clone(), endpoint.stats.clone()))
+        .collect()
+}
+
+/// Return the websocket url of the primary endpoint, derived from its RPC url
+/// as the client does.
+pub fn primary_websocket_url() -> Option<String> {
+    endpoints()
+        .lock()
+        .unwrap()
+        .first()
+        .map(|endpoint| endpoint.url.replace("http", "ws"))
+}
+
+/// Return the order in which the endpoints are tried for the next request,
+/// rotating the starting endpoint and leaving the ones cooling down last.
+fn next_endpoints() -> Vec<(String, Arc<RpcClient>)> {
+    let endpoints = endpoints().lock().unwrap();
+    let len = endpoints.len();
+
+    if len == 0 {
+        return Vec::new();
+    }
+
+    let start = NEXT.fetch_add(1, Ordering::Relaxed) % len;
+    let now = Instant::now();
+    let (mut available, cooling): (Vec<_>, Vec<_>) = (0..len)
+        .map(|offset| (start + offset) % len)
+        .partition(|index| !endpoints[*index].is_cooling_down(now));
+
+    available.extend(cooling);
+    available
+        .into_iter()
+        .map(|index| {
+            let endpoint = &endpoints[index];
+            (endpoint.url.clone(), endpoint.client.clone())
+        })
+        .collect()
+}
+
+fn record(url: &str, latency: Duration, error: Option<&anyhow::Error>) {
+    let mut endpoints = endpoints().lock().unwrap();
+
+    if let Some(endpoint) = endpoints.iter_mut().find(|endpoint| endpoint.url == url) {
+        endpoint.stats.requests += 1;
+
+        match error {
+            Some(error) => {
+                endpoint.stats.failures += 1;
+
+                let message = error.to_string().to_lowercase();
+                if message.contains("429") || message.contains("too many requests") {
+                    endpoint.stats.rate_limited += 1;
+                }
+
+                endpoint.cooldown_until = Some(Instant::now() + ENDPOINT_COOLDOWN);
+            }
+            None => endpoint.stats.latency += latency,
+        }
+    }
+}
+
+/// Run a request against a specific endpoint of the pool, recording it in the
+/// endpoint stats.
+pub fn with_endpoint<T, F>(url: &str, operation: F) -> Result<T>
+where
+    F: FnOnce(&RpcClient) -> Result<T>,
+{
+    let client = endpoints()
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|endpoint| endpoint.url == url)
+        .map(|endpoint| endpoint.client.clone())
+        .ok_or_else(|| anyhow!("RPC endpoint {} is not in the pool", url))?;
+
+    let start = Instant::now();
+    let result = operation(&client);
+    record(url, start.elapsed(), result.as_ref().err());
+
+    result
+}
+
+/// Run a request against the endpoints of the pool, failing over to the next
+/// endpoint on rate limits, timeouts and connection errors. Falls back to the
+/// client of the program when the pool is empty.
+pub fn with_rpc<T, F>(program: &Program, operation: F) -> Result<T>
+where
+    F: Fn(&RpcClient) -> Result<T>,
+{
+    let candidates = next_endpoints();
+
+    if candidates.is_empty() {
+        return operation(&program.rpc());
+    }
+
+    let mut last_error = None;
+
+    for (url, client) in candidates {
+        let start = Instant::now();
+
+        match operation(&client) {
+            Ok(value) => {
+                record(&url, start.elapsed(), None);
+                return Ok(value);
+            }
+            Err(error) if is_retryable(&error) => {
+                warn!("RPC endpoint {} failed, failing over: {}", url, error);
+                record(&url, start.elapsed(), Some(&error));
+                last_error = Some(error);
+            }
+            // errors of the request itself are not a failure of the endpoint
+            Err(error) => {
+                record(&url, start.elapsed(), None);
+                return Err(error);
+            }
+        }
+    }
+
+    Err(last_error.unwrap())
+}
@@ -0,0 +1,158 @@
+use console::style;
+
+use crate::cli::RpcAction;
+use crate::common::*;
+use crate::config::get_config_data;
+use crate::rpc::{endpoint_stats, set_primary_endpoint, with_endpoint};
+use crate::utils::*;
+
+/// Number of slot requests used to measure the latency of an endpoint.
+const LATENCY_SAMPLES: u32 = 3;
+
+pub struct RpcArgs {
+    pub action: RpcAction,
+}
+
+pub fn process_rpc(args: RpcArgs) -> Result<()> {
+    match args.action {
+        RpcAction::Status {
+            keypair,
+            rpc_url,
+            config,
+        } => process_rpc_status(keypair, rpc_url, &config),
+    }
+}
+
+/// Health of an endpoint, as reported by the probe requests.
+struct EndpointHealth {
+    healthy: bool,
+    error: Option<String>,
+    version: Option<String>,
+    slot: Option<u64>,
+}
+
+fn process_rpc_status(
+    keypair: Option<String>,
+    rpc_url: Option<String>,
+    config: &str,
+) -> Result<()> {
+    println!(
+        "{} {}Checking RPC endpoints",
+        style("[1/1]").bold().dim(),
+        COMPUTER_EMOJI
+    );
+
+    let sugar_config = sugar_setup(keypair, rpc_url)?;
+
+    // loading the config adds its rpc urls to the pool; they are optional, so
+    // a missing config file is fine
+    if Path::new(config).exists() {
+        get_config_data(config)?;
+    }
+    set_primary_endpoint(&sugar_config.rpc_url);
+
+    let endpoints = endpoint_stats();
+    let pb = spinner_with_style();
+    pb.set_message(format!("Probing {} endpoint(s)...", endpoints.len()));
+
+    let results: Vec<(String, EndpointHealth)> = endpoints
+        .into_iter()
+        .map(|(url, _)| {
+            let health = probe_endpoint(&url);
+            (url, health)
+        })
+        .collect();
+
+    pb.finish_and_clear();
+
+    let stats = endpoint_stats();
+
+    let mut healthy = 0;
+
+    for (index, (url, health)) in results.iter().enumerate() {
+        println!(
+            "\n{} {}{}",
+            style(format!("{}.", index + 1)).bold(),
+            url,
+            if index == 0 {
+                style(" (primary)").dim().to_string()
+            } else {
+                String::new()
+            }
+        );
+
+        if health.healthy {
+            healthy += 1;
+            print_with_style("status", style("healthy").green().to_string());
+        } else {
+            print_with_style("status", style("unhealthy").red().to_string());
+        }
+
+        if let Some(error) = &health.error {
+            print_with_style("error", error.to_string());
+        }
+        if let Some(version) = &health.version {
+            print_with_style("version", version.to_string());
+        }
+        if let Some(slot) = health.slot {
+            print_with_style("slot", slot.to_string());
+        }
+        if let Some((_, stats)) = stats.iter().find(|(endpoint, _)| endpoint == url) {
+            if let Some(latency) = stats.average_latency() {
+                print_with_style("latency", format!("{} ms", latency.as_millis()));
+            }
+            print_with_style(
+                "requests",
+                format!(
+                    "{} ({} failed, {} rate limited)",
+                    stats.requests, stats.failures, stats.rate_limited
+                ),
+            );
+        }
+    }
+
+    println!("\n{} of {} endpoint(s) healthy", healthy, results.len());
+
+    if healthy == 0 {
+        return Err(anyhow!("No healthy RPC endpoint available"));
+    }
+
+    Ok(())
+}
+
+/// Send probe requests to an endpoint through the pool, so their latency and
+/// failures are recorded in the endpoint stats.
+fn probe_endpoint(url: &str) -> EndpointHealth {
+    let mut health = EndpointHealth {
+        healthy: false,
+        error: None,
+        version: None,
+        slot: None,
+    };
+
+    if let Err(err) = with_endpoint(url, |rpc| Ok(rpc.get_health()?)) {
+        health.error = Some(err.to_string());
+        return health;
+    }
+
+    health.version = with_endpoint(url, |rpc| Ok(rpc.get_version()?))
+        .ok()
+        .map(|version| version.solana_core);
+
+    for _ in 0..LATENCY_SAMPLES {
+        match with_endpoint(url, |rpc| Ok(rpc.get_slot()?)) {
+            Ok(slot) => health.slot = Some(slot),
+            Err(err) => {
+                health.error = Some(err.to_string());
+                return health;
+            }
+        }
+    }
+
+    health.healthy = true;
+    health
+}
+
+fn print_with_style(key: &str, value: String) {
+    println!(" {} {}", style(format!(":.. {}:", key)).dim(), value);
+}
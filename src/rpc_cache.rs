@@ -0,0 +1,71 @@
+use anchor_client::solana_sdk::hash::Hash;
+use std::{
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+use crate::common::*;
+use crate::rpc::with_rpc;
+
+/// Account data keyed by address, with the time it was fetched.
+type AccountCache = HashMap<Pubkey, (Instant, Vec<u8>)>;
+
+/// Account data fetched during the current command run.
+static ACCOUNTS: OnceLock<Mutex<AccountCache>> = OnceLock::new();
+
+/// Last blockhash fetched during the current command run.
+static BLOCKHASH: OnceLock<Mutex<Option<(Instant, Hash)>>> = OnceLock::new();
+
+fn accounts() -> &'static Mutex<AccountCache> {
+    ACCOUNTS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Return the data of an account, reusing the result of a previous request
+/// if it is within the cache TTL.
+pub fn get_account_data(program: &Program, pubkey: &Pubkey) -> Result<Vec<u8>> {
+    if let Some((fetched_at, data)) = accounts().lock().unwrap().get(pubkey) {
+        if fetched_at.elapsed() < Duration::from_secs(RPC_CACHE_TTL) {
+            debug!("Using cached account data for {}", pubkey);
+            return Ok(data.clone());
+        }
+    }
+
+    let data = with_rpc(program, |rpc| Ok(rpc.get_account_data(pubkey)?))?;
+    accounts()
+        .lock()
+        .unwrap()
+        .insert(*pubkey, (Instant::now(), data.clone()));
+
+    Ok(data)
+}
+
+/// Remove an account from the cache. This must be called after a transaction
+/// modifies an account that might be read again in the same run.
+pub fn invalidate(pubkey: &Pubkey) {
+    accounts().lock().unwrap().remove(pubkey);
+}
+
+fn blockhash() -> &'static Mutex<Option<(Instant, Hash)>> {
+    BLOCKHASH.get_or_init(|| Mutex::new(None))
+}
+
+/// Return a recent blockhash, reusing the last one fetched if it is within
+/// the reuse window.
+pub fn get_latest_blockhash(program: &Program) -> Result<Hash> {
+    if let Some((fetched_at, hash)) = *blockhash().lock().unwrap() {
+        if fetched_at.elapsed() < Duration::from_secs(BLOCKHASH_REUSE_WINDOW) {
+            return Ok(hash);
+        }
+    }
+
+    refresh_blockhash(program)
+}
+
+/// Fetch a new blockhash, replacing the one in the cache. This is used when
+/// a transaction with the cached blockhash could not be confirmed.
+pub fn refresh_blockhash(program: &Program) -> Result<Hash> {
+    let hash = with_rpc(program, |rpc| Ok(rpc.get_latest_blockhash()?))?;
+    *blockhash().lock().unwrap() = Some((Instant::now(), hash));
+
+    Ok(hash)
+}
@@ -1,24 +1,131 @@
-use anchor_client::{
-    solana_sdk::{
-        commitment_config::CommitmentConfig,
-        signature::{keypair::Keypair, read_keypair_file},
-    },
-    Client, Cluster,
-};
-use anyhow::{anyhow, Result};
+use anchor_client::{solana_sdk::commitment_config::CommitmentConfig, Client, Cluster};
+use anyhow::Result;
+use serde::Deserialize;
+use std::{env, fs, sync::Mutex};
 use tracing::error;
 
-use crate::config::data::SugarConfig;
-use crate::constants::{DEFAULT_KEYPATH, DEFAULT_RPC_DEVNET};
+use crate::common::HttpClient;
+use crate::config::parser::resolve_config_path;
+use crate::config::{data::SugarConfig, errors::ConfigError, format::ConfigFormat};
+use crate::constants::{DEFAULT_CONFIG, DEFAULT_KEYPATH, DEFAULT_RPC_DEVNET};
 use crate::parse::*;
+use crate::rpc::set_primary_endpoint;
+use crate::signer::SugarSigner;
+
+/// Proxy of the HTTP and RPC traffic and the HTTP client using it, created
+/// on first use.
+static PROXY: Mutex<Option<(String, Option<HttpClient>)>> = Mutex::new(None);
+
+/// Proxy setting of a config file.
+#[derive(Deserialize)]
+struct ProxySetting {
+    proxy: Option<String>,
+}
+
+/// Route the HTTP and RPC traffic through the specified proxy.
+///
+/// HTTP clients are created by [`http_client`], which uses the proxy. The RPC
+/// clients create their own HTTP client, so the proxy is also set as
+/// environment variables; it must be applied before the first RPC client is
+/// created, which [`sugar_setup`] does for the proxy of the default config
+/// file.
+pub fn set_proxy(proxy: &str) -> Result<(), ConfigError> {
+    check_proxy(proxy)?;
+
+    for key in [
+        "HTTP_PROXY",
+        "HTTPS_PROXY",
+        "ALL_PROXY",
+        "http_proxy",
+        "https_proxy",
+        "all_proxy",
+    ] {
+        env::set_var(key, proxy);
+    }
+
+    let mut current = PROXY.lock().unwrap();
+    if current.as_ref().map(|(current, _)| current.as_str()) != Some(proxy) {
+        *current = Some((proxy.to_string(), None));
+    }
+
+    Ok(())
+}
+
+/// Check that the proxy has a supported scheme, without applying it.
+pub fn check_proxy(proxy: &str) -> Result<(), ConfigError> {
+    let scheme = proxy.split("://").next().unwrap_or_default();
+
+    if !matches!(scheme, "http" | "https" | "socks5" | "socks5h")
+        || reqwest::Proxy::all(proxy).is_err()
+    {
+        let error = ConfigError::InvalidProxy(proxy.to_string());
+        error!("{:?}", error);
+        return Err(error);
+    }
+
+    Ok(())
+}
+
+/// Return the proxy of the HTTP and RPC traffic, if any.
+pub fn proxy() -> Option<String> {
+    PROXY
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|(proxy, _)| proxy.clone())
+}
+
+/// Return the HTTP client of the command, which goes through the proxy when
+/// one is configured.
+pub fn http_client() -> HttpClient {
+    let mut current = PROXY.lock().unwrap();
+
+    match current.as_mut() {
+        Some((proxy, client)) => client
+            .get_or_insert_with(|| {
+                HttpClient::builder()
+                    // validated by set_proxy
+                    .proxy(reqwest::Proxy::all(proxy.as_str()).unwrap())
+                    .build()
+                    .expect("Failed to create HTTP client.")
+            })
+            .clone(),
+        None => HttpClient::new(),
+    }
+}
+
+/// Apply the proxy of the default config file, unless a proxy was already
+/// set. Only the proxy is read, so commands that do not use the config file
+/// are not affected by its other settings.
+fn apply_default_proxy() -> Result<()> {
+    if proxy().is_some() {
+        return Ok(());
+    }
+
+    let config_path = resolve_config_path(DEFAULT_CONFIG);
+    let content = match fs::read_to_string(&config_path) {
+        Ok(content) => content,
+        Err(_) => return Ok(()),
+    };
+
+    // an invalid config file is reported by the commands that use it
+    if let Ok(ProxySetting { proxy: Some(proxy) }) =
+        ConfigFormat::from_path(&config_path).parse::<ProxySetting>(&content)
+    {
+        set_proxy(&proxy)?;
+    }
+
+    Ok(())
+}
 
 pub fn setup_client(sugar_config: &SugarConfig) -> Result<Client> {
     let rpc_url = sugar_config.rpc_url.clone();
+    set_primary_endpoint(&rpc_url);
+
     let ws_url = rpc_url.replace("http", "ws");
     let cluster = Cluster::Custom(rpc_url, ws_url);
 
-    let key_bytes = sugar_config.keypair.to_bytes();
-    let payer = Keypair::from_bytes(&key_bytes)?;
+    let payer = sugar_config.keypair.client_keypair()?;
 
     let opts = CommitmentConfig::confirmed();
     Ok(Client::new_with_options(cluster, payer, opts))
@@ -28,6 +135,9 @@ pub fn sugar_setup(
     keypair_opt: Option<String>,
     rpc_url_opt: Option<String>,
 ) -> Result<SugarConfig> {
+    // the proxy must be set before the first RPC client is created
+    apply_default_proxy()?;
+
     let sol_config_option = parse_solana_config();
 
     let rpc_url = match rpc_url_opt {
@@ -38,43 +148,21 @@ pub fn sugar_setup(
         },
     };
 
-    let keypair = match keypair_opt {
-        Some(keypair_path) => match read_keypair_file(&keypair_path) {
-            Ok(keypair) => keypair,
-            Err(e) => {
-                error!("Failed to read keypair file: {}", e);
-                return Err(anyhow!("Failed to read keypair file: {}", e));
-            }
-        },
-
+    let keypair_path = match keypair_opt {
+        Some(keypair_path) => keypair_path,
         None => match sol_config_option {
-            Some(ref sol_config) => match read_keypair_file(&sol_config.keypair_path) {
-                Ok(keypair) => keypair,
-                Err(e) => {
-                    error!(
-                        "Failed to read keypair file: {}, {}",
-                        &sol_config.keypair_path, e
-                    );
-                    return Err(anyhow!(
-                        "Failed to read keypair file: {}, {}",
-                        &sol_config.keypair_path,
-                        e
-                    ));
-                }
-            },
-            None => match read_keypair_file(&*shellexpand::tilde(DEFAULT_KEYPATH)) {
-                Ok(keypair) => keypair,
-                Err(e) => {
-                    error!("Failed to read keypair file: {}, {}", DEFAULT_KEYPATH, e);
-                    return Err(anyhow!(
-                        "Failed to read keypair file: {}, {}",
-                        DEFAULT_KEYPATH,
-                        e
-                    ));
-                }
-            },
+            Some(ref sol_config) => sol_config.keypair_path.clone(),
+            None => DEFAULT_KEYPATH.to_string(),
         },
     };
 
+    let keypair = match SugarSigner::load(&keypair_path) {
+        Ok(keypair) => keypair,
+        Err(e) => {
+            error!("{}", e);
+            return Err(e);
+        }
+    };
+
     Ok(SugarConfig { rpc_url, keypair })
 }
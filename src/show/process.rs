@@ -1,13 +1,20 @@
 use anchor_client::solana_sdk::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey};
+use anchor_lang::AccountDeserialize;
 use anyhow::Result;
-use chrono::NaiveDateTime;
+use chrono::{NaiveDateTime, Utc};
 use console::style;
-use mpl_candy_machine::{EndSettingType, WhitelistMintMode};
-use std::str::FromStr;
+use mpl_candy_machine::{CandyMachine, EndSettingType, WhitelistMintMode};
+use std::{collections::HashSet, str::FromStr};
 
 use crate::cache::load_cache;
-use crate::candy_machine::*;
+use crate::candy_machine::{get_unminted_indices, ID as CANDY_MACHINE_ID};
 use crate::common::*;
+use crate::das::DasClient;
+use crate::freeze::get_freeze_state;
+use crate::mint::pdas::get_candy_machine_creator_pda;
+use crate::price::usd_suffix;
+use crate::rpc_cache;
+use crate::setup::setup_client;
 use crate::utils::*;
 
 pub struct ShowArgs {
@@ -15,9 +22,10 @@ pub struct ShowArgs {
     pub rpc_url: Option<String>,
     pub cache: String,
     pub candy_machine: Option<String>,
+    pub unminted: bool,
 }
 
-pub fn process_show(args: ShowArgs) -> Result<()> {
+pub async fn process_show(args: ShowArgs) -> Result<()> {
     println!(
         "{} {}Looking up candy machine",
         style("[1/1]").bold().dim(),
@@ -47,8 +55,33 @@ pub fn process_show(args: ShowArgs) -> Result<()> {
         }
     };
 
-    let cndy_state = get_candy_machine_state(&sugar_config, &candy_machine_id)?;
+    let client = setup_client(&sugar_config)?;
+    let program = client.program(CANDY_MACHINE_ID);
+    let account_data = rpc_cache::get_account_data(&program, &candy_machine_id)?;
+    let cndy_state = CandyMachine::try_deserialize(&mut account_data.as_slice())?;
+    // the freeze PDA only exists when freeze is enabled for the candy machine
+    let freeze = get_freeze_state(&program, &candy_machine_id)?.map(|freeze_state| {
+        let thaw_allowed = freeze_state.thaw_allowed(&cndy_state, Utc::now().timestamp());
+        (freeze_state, thaw_allowed)
+    });
     let cndy_data = cndy_state.data;
+    // hidden settings machines do not use the mint bitmask
+    let tracks_mints = cndy_data.hidden_settings.is_none();
+
+    // minted NFTs and their holders are only shown when the RPC node supports
+    // the DAS API, since they would require a lookup per NFT otherwise
+    let indexed = match DasClient::connect(&sugar_config.rpc_url).await {
+        Some(das) => {
+            let (creator, _) = get_candy_machine_creator_pda(&candy_machine_id);
+            let assets = das.get_assets_by_creator(&creator).await?;
+            let holders: HashSet<&str> = assets
+                .iter()
+                .map(|asset| asset.ownership.owner.as_str())
+                .collect();
+            Some((assets.len(), holders.len()))
+        }
+        None => None,
+    };
 
     pb.finish_and_clear();
 
@@ -75,14 +108,20 @@ pub fn process_show(args: ShowArgs) -> Result<()> {
     print_with_style("", "items redeemed", cndy_state.items_redeemed.to_string());
     print_with_style("", "items available", cndy_data.items_available.to_string());
 
+    if let Some((minted, holders)) = indexed {
+        print_with_style("", "indexed mints", minted.to_string());
+        print_with_style("", "unique holders", holders.to_string());
+    }
+
     print_with_style("", "uuid", cndy_data.uuid.to_string());
     print_with_style(
         "",
         "price",
         format!(
-            "◎ {} ({})",
+            "◎ {} ({}){}",
             cndy_data.price as f64 / LAMPORTS_PER_SOL as f64,
-            cndy_data.price
+            cndy_data.price,
+            usd_suffix(cndy_data.price)
         ),
     );
     print_with_style("", "symbol", cndy_data.symbol.to_string());
@@ -177,7 +216,12 @@ pub fn process_show(args: ShowArgs) -> Result<()> {
             ":   ",
             "discount price",
             if let Some(value) = whitelist_settings.discount_price {
-                format!("◎ {} ({})", value as f64 / LAMPORTS_PER_SOL as f64, value)
+                format!(
+                    "◎ {} ({}){}",
+                    value as f64 / LAMPORTS_PER_SOL as f64,
+                    value,
+                    usd_suffix(value)
+                )
             } else {
                 "none".to_string()
             },
@@ -203,6 +247,57 @@ pub fn process_show(args: ShowArgs) -> Result<()> {
         print_with_style("", "gatekeeper", "none".to_string());
     }
 
+    // freeze settings
+    if let Some((freeze_state, thaw_allowed)) = freeze {
+        print_with_style("", "freeze", "".to_string());
+        print_with_style(
+            ":   ",
+            "frozen count",
+            freeze_state.frozen_count.to_string(),
+        );
+        print_with_style(":   ", "allow thaw", freeze_state.allow_thaw.to_string());
+        if let Some(mint_start) = freeze_state.mint_start {
+            let date = NaiveDateTime::from_timestamp(mint_start, 0);
+            print_with_style(
+                ":   ",
+                "mint start",
+                date.format("%a %B %e %Y %H:%M:%S UTC").to_string(),
+            );
+        } else {
+            print_with_style(":   ", "mint start", "none".to_string());
+        }
+        print_with_style(
+            ":   ",
+            "freeze time",
+            format!(
+                "{} day(s) ({}s)",
+                freeze_state.freeze_time / (24 * 60 * 60),
+                freeze_state.freeze_time
+            ),
+        );
+        print_with_style(":   ", "thaw available", thaw_allowed.to_string());
+    } else {
+        print_with_style("", "freeze", "none".to_string());
+    }
+
+    // unminted items
+    if args.unminted {
+        if !tracks_mints {
+            print_with_style(
+                "",
+                "unminted",
+                "not tracked for hidden settings".to_string(),
+            );
+        } else {
+            let unminted = get_unminted_indices(&account_data, cndy_data.items_available as usize)?;
+            print_with_style("", "unminted", unminted.len().to_string());
+
+            if !unminted.is_empty() {
+                print_with_style(":   ", "indices", format_ranges(&unminted));
+            }
+        }
+    }
+
     Ok(())
 }
 
@@ -0,0 +1,16 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum SignError {
+    #[error("Could not read mint list file '{0}': {1}")]
+    MintListFileError(String, String),
+
+    #[error("Invalid mint address in mint list: '{0}'")]
+    InvalidMint(String),
+
+    #[error("Keypair {0} is not a creator of any of the listed mints")]
+    NotACreator(String),
+
+    #[error("Missing candy machine id, use '--candy-machine' or '--mint-list'")]
+    MissingCandyMachine,
+}
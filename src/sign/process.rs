@@ -0,0 +1,284 @@
+use console::style;
+use mpl_token_metadata::{
+    instruction::sign_metadata,
+    state::{Key, Metadata, MAX_METADATA_LEN},
+    utils::try_from_slice_checked,
+    ID as TOKEN_METADATA_ID,
+};
+use serde::Serialize;
+use std::{fs, str::FromStr};
+
+use crate::cache::load_cache;
+use crate::common::*;
+use crate::guard::get_creator_metadata;
+use crate::mint::pdas::{get_candy_machine_creator_pda, get_metadata_pda};
+use crate::sign::SignError;
+use crate::spend::record_transaction;
+use crate::transaction::send_with_retry;
+use crate::utils::*;
+
+/// Number of `sign_metadata` instructions per transaction.
+const SIGN_BATCH_SIZE: usize = 8;
+
+pub struct SignArgs {
+    pub keypair: Option<String>,
+    pub rpc_url: Option<String>,
+    pub cache: String,
+    pub candy_machine: Option<String>,
+    pub mint_list: Option<String>,
+    pub creator: bool,
+    pub progress: String,
+}
+
+/// Verification progress of all creators of the mint list.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignProgress {
+    pub total: usize,
+    pub creators: IndexMap<String, CreatorProgress>,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatorProgress {
+    pub verified: usize,
+    pub unverified: Vec<String>,
+}
+
+pub fn process_sign(args: SignArgs) -> Result<()> {
+    let sugar_config = sugar_setup(args.keypair, args.rpc_url)?;
+    let client = setup_client(&sugar_config)?;
+    let program = client.program(TOKEN_METADATA_ID);
+    let creator = program.payer();
+
+    let steps = if args.creator { 3 } else { 2 };
+
+    println!(
+        "{} {}{}",
+        style(format!("[1/{}]", steps)).bold().dim(),
+        LOOKING_GLASS_EMOJI,
+        if args.mint_list.is_some() {
+            "Loading mint list"
+        } else {
+            "Retrieving minted NFTs"
+        }
+    );
+
+    let pb = spinner_with_style();
+    pb.set_message("Retrieving metadata...");
+
+    // without a mint list, the mints are the NFTs minted by the candy machine
+    let (mints, metadata) = match &args.mint_list {
+        Some(mint_list) => {
+            let mints = read_mint_list(mint_list)?;
+            let metadata = get_metadata(&program, &mints)?;
+            (mints, metadata)
+        }
+        None => {
+            let candy_machine = get_candy_machine(args.candy_machine, &args.cache)?;
+            let (candy_machine_creator, _) = get_candy_machine_creator_pda(&candy_machine);
+
+            get_creator_metadata(&program, &candy_machine_creator)?
+                .into_iter()
+                .map(|(_, metadata)| (metadata.mint, Some(metadata)))
+                .unzip()
+        }
+    };
+    let mut to_sign = Vec::new();
+    let mut is_creator = false;
+
+    for (mint, metadata) in mints.iter().zip(&metadata) {
+        let entry = metadata.as_ref().and_then(|metadata| {
+            metadata
+                .data
+                .creators
+                .as_ref()
+                .and_then(|creators| creators.iter().find(|c| c.address == creator))
+        });
+
+        if let Some(entry) = entry {
+            is_creator = true;
+            if !entry.verified {
+                to_sign.push(*mint);
+            }
+        }
+    }
+
+    pb.finish_and_clear();
+
+    if !is_creator {
+        return Err(SignError::NotACreator(creator.to_string()).into());
+    }
+
+    println!(
+        "Found {} mint(s), {} pending signature",
+        mints.len(),
+        to_sign.len()
+    );
+
+    println!(
+        "\n{} {}Signing metadata",
+        style(format!("[2/{}]", steps)).bold().dim(),
+        SIGNING_EMOJI
+    );
+
+    if to_sign.is_empty() {
+        println!("All mints already signed by {}", creator);
+    } else {
+        let pb = progress_bar_with_style(to_sign.len() as u64);
+        let mut errors = 0;
+
+        for chunk in to_sign.chunks(SIGN_BATCH_SIZE) {
+            let instructions: Vec<_> = chunk
+                .iter()
+                .map(|mint| sign_metadata(TOKEN_METADATA_ID, get_metadata_pda(mint), creator))
+                .collect();
+
+            let result = send_with_retry(&program, &instructions, &[&sugar_config.keypair]);
+
+            match result {
+                Ok(signature) => {
+                    record_transaction(1, chunk.len() as u64, None);
+                    info!("Signed {} mint(s): {}", chunk.len(), signature)
+                }
+                Err(err) => {
+                    error!("Failed to sign batch: {}", err);
+                    errors += chunk.len();
+                }
+            }
+
+            pb.inc(chunk.len() as u64);
+        }
+
+        pb.finish_and_clear();
+
+        if errors > 0 {
+            println!(
+                "{}",
+                style(format!(
+                    "Failed to sign {} mint(s), re-run the command to retry",
+                    errors
+                ))
+                .red()
+            );
+        } else {
+            println!("Signed {} mint(s)", to_sign.len());
+        }
+    }
+
+    if args.creator {
+        println!(
+            "\n{} {}Updating creators progress",
+            style(format!("[3/{}]", steps)).bold().dim(),
+            PAPER_EMOJI
+        );
+
+        let metadata = get_metadata(&program, &mints)?;
+        let progress = creators_progress(&mints, &metadata);
+
+        for (address, creator_progress) in &progress.creators {
+            println!(
+                "{} {}/{} verified",
+                style(address).bold(),
+                creator_progress.verified,
+                creator_progress.verified + creator_progress.unverified.len()
+            );
+        }
+
+        let file = File::create(&args.progress)?;
+        serde_json::to_writer_pretty(file, &progress)?;
+
+        println!("\nProgress saved to '{}'", args.progress);
+
+        if progress
+            .creators
+            .values()
+            .all(|creator| creator.unverified.is_empty())
+        {
+            println!("All creators have verified the collection.");
+        }
+    }
+
+    Ok(())
+}
+
+/// Read the mint list file, either a JSON array or one address per line.
+fn read_mint_list(path: &str) -> Result<Vec<Pubkey>> {
+    let content = fs::read_to_string(path)
+        .map_err(|err| SignError::MintListFileError(path.to_string(), err.to_string()))?;
+
+    let addresses: Vec<String> = match serde_json::from_str(&content) {
+        Ok(addresses) => addresses,
+        Err(_) => content
+            .lines()
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect(),
+    };
+
+    addresses
+        .iter()
+        .map(|address| {
+            Pubkey::from_str(address).map_err(|_| SignError::InvalidMint(address.clone()).into())
+        })
+        .collect()
+}
+
+/// Return the candy machine specified, falling back to the one from the cache.
+fn get_candy_machine(candy_machine: Option<String>, cache: &str) -> Result<Pubkey> {
+    let candy_machine = match candy_machine {
+        Some(candy_machine) => candy_machine,
+        None => match load_cache(cache, false) {
+            Ok(cache) if !cache.program.candy_machine.is_empty() => cache.program.candy_machine,
+            _ => return Err(SignError::MissingCandyMachine.into()),
+        },
+    };
+
+    Pubkey::from_str(&candy_machine)
+        .map_err(|_| CacheError::InvalidCandyMachineAddress(candy_machine).into())
+}
+
+/// Retrieve the metadata of the specified mints.
+fn get_metadata(program: &Program, mints: &[Pubkey]) -> Result<Vec<Option<Metadata>>> {
+    let mut metadata = Vec::with_capacity(mints.len());
+
+    for chunk in mints.chunks(MAX_ACCOUNTS_PER_REQUEST) {
+        let pdas: Vec<Pubkey> = chunk.iter().map(get_metadata_pda).collect();
+        let accounts = program.rpc().get_multiple_accounts(&pdas)?;
+
+        for account in accounts {
+            metadata.push(account.and_then(|account| {
+                try_from_slice_checked(&account.data, Key::MetadataV1, MAX_METADATA_LEN).ok()
+            }));
+        }
+    }
+
+    Ok(metadata)
+}
+
+/// Compute the verification progress of each creator.
+fn creators_progress(mints: &[Pubkey], metadata: &[Option<Metadata>]) -> SignProgress {
+    let mut creators: IndexMap<String, CreatorProgress> = IndexMap::new();
+
+    for (mint, metadata) in mints.iter().zip(metadata) {
+        let metadata_creators = match metadata.as_ref().and_then(|m| m.data.creators.as_ref()) {
+            Some(creators) => creators,
+            None => continue,
+        };
+
+        for creator in metadata_creators {
+            let progress = creators.entry(creator.address.to_string()).or_default();
+
+            if creator.verified {
+                progress.verified += 1;
+            } else {
+                progress.unverified.push(mint.to_string());
+            }
+        }
+    }
+
+    SignProgress {
+        total: mints.len(),
+        creators,
+    }
+}
@@ -0,0 +1,233 @@
+use anchor_client::solana_sdk::{
+    derivation_path::DerivationPath,
+    signer::{Signer, SignerError},
+};
+use solana_remote_wallet::{
+    locator::Locator,
+    remote_keypair::{generate_remote_keypair, RemoteKeypair},
+    remote_wallet::{maybe_wallet_manager, RemoteWalletError},
+};
+use std::{
+    sync::{
+        mpsc::{channel, Sender},
+        Mutex, OnceLock,
+    },
+    thread,
+};
+use thiserror::Error;
+
+use crate::common::*;
+use crate::keypair::{read_keypair_path, read_seed_phrase_keypair, SEED_PHRASE_SCHEME};
+
+/// Scheme of the hardware wallet keypair paths, e.g. `usb://ledger?key=0`.
+const REMOTE_WALLET_SCHEME: &str = "usb://";
+
+/// Signers loaded by the process, by keypair path. Commands call
+/// [`crate::setup::sugar_setup`] more than once (e.g., launch stages and
+/// watch rounds), and loading a signer can prompt for a passphrase or seed
+/// phrase, or connect to a hardware wallet.
+static SIGNERS: OnceLock<Mutex<HashMap<String, SugarSigner>>> = OnceLock::new();
+
+#[derive(Debug, Error)]
+pub enum SugarSignerError {
+    #[error("Invalid hardware wallet path '{0}' ({1})")]
+    InvalidPath(String, String),
+
+    #[error("Could not connect to the hardware wallet '{0}' ({1})")]
+    DeviceError(String, String),
+
+    #[error("{0} requires a keypair file, hardware wallets are not supported")]
+    KeypairRequired(String),
+}
+
+/// Signer of the commands: a keypair (from a file or a seed phrase) or a
+/// hardware wallet.
+#[allow(clippy::large_enum_variant)]
+pub enum SugarSigner {
+    Keypair(Keypair),
+    Remote(RemoteSigner),
+}
+
+impl SugarSigner {
+    /// Load the signer of a keypair path: a hardware wallet for `usb://`
+    /// uris, a seed phrase prompt for `prompt://` uris, or a (possibly
+    /// encrypted) keypair file.
+    pub fn from_path(path: &str) -> Result<Self> {
+        if path.starts_with(REMOTE_WALLET_SCHEME) {
+            Ok(SugarSigner::Remote(RemoteSigner::connect(path)?))
+        } else if path.starts_with(SEED_PHRASE_SCHEME) {
+            Ok(SugarSigner::Keypair(read_seed_phrase_keypair(path)?))
+        } else {
+            Ok(SugarSigner::Keypair(read_keypair_path(path)?))
+        }
+    }
+
+    /// Load the signer of a keypair path once per process: later calls with
+    /// the same path return a copy of the first signer, so the user is only
+    /// prompted once and the hardware wallet connection is shared.
+    pub fn load(path: &str) -> Result<Self> {
+        let mut signers = SIGNERS
+            .get_or_init(|| Mutex::new(HashMap::new()))
+            .lock()
+            .unwrap();
+
+        if let Some(signer) = signers.get(path) {
+            return Ok(signer.clone());
+        }
+
+        let signer = SugarSigner::from_path(path)?;
+        signers.insert(path.to_string(), signer.clone());
+
+        Ok(signer)
+    }
+
+    /// Return the keypair of the signer, for operations that need the secret
+    /// key itself (e.g., signing Bundlr uploads).
+    pub fn keypair(&self, operation: &str) -> Result<&Keypair> {
+        match self {
+            SugarSigner::Keypair(keypair) => Ok(keypair),
+            SugarSigner::Remote(_) => {
+                Err(SugarSignerError::KeypairRequired(operation.to_string()).into())
+            }
+        }
+    }
+
+    /// Return the payer keypair of the Anchor client. The client needs a
+    /// keypair, but it is only used for its public key: transactions are
+    /// signed by the signer itself (see [`crate::transaction`]), so a
+    /// hardware wallet gets a keypair with the device public key and a
+    /// zeroed secret key.
+    pub fn client_keypair(&self) -> Result<Keypair> {
+        let mut bytes = match self {
+            SugarSigner::Keypair(keypair) => return Ok(Keypair::from_bytes(&keypair.to_bytes())?),
+            SugarSigner::Remote(_) => [0u8; 64],
+        };
+        bytes[32..].copy_from_slice(self.pubkey().as_ref());
+
+        Ok(Keypair::from_bytes(&bytes)?)
+    }
+}
+
+impl Clone for SugarSigner {
+    fn clone(&self) -> Self {
+        match self {
+            SugarSigner::Keypair(keypair) => {
+                SugarSigner::Keypair(Keypair::from_bytes(&keypair.to_bytes()).unwrap())
+            }
+            SugarSigner::Remote(remote) => SugarSigner::Remote(remote.clone()),
+        }
+    }
+}
+
+impl Signer for SugarSigner {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        match self {
+            SugarSigner::Keypair(keypair) => keypair.try_pubkey(),
+            SugarSigner::Remote(remote) => Ok(remote.pubkey),
+        }
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        match self {
+            SugarSigner::Keypair(keypair) => keypair.try_sign_message(message),
+            SugarSigner::Remote(remote) => remote.sign(message),
+        }
+    }
+
+    fn is_interactive(&self) -> bool {
+        matches!(self, SugarSigner::Remote(_))
+    }
+}
+
+/// Request to sign a message, with the channel for the signature.
+type SignRequest = (Vec<u8>, Sender<Result<Signature, SignerError>>);
+
+/// Signer of a hardware wallet. The device handle cannot be shared between
+/// threads, so it is owned by a dedicated thread that signs the requests
+/// it receives, one at a time; each one is confirmed on the device.
+pub struct RemoteSigner {
+    pubkey: Pubkey,
+    path: String,
+    requests: Mutex<Sender<SignRequest>>,
+}
+
+impl RemoteSigner {
+    fn connect(path: &str) -> Result<Self> {
+        let (locator, derivation_path) = parse_remote_path(path)?;
+        let (requests, receiver) = channel::<SignRequest>();
+        let (ready, connected) = channel();
+
+        thread::spawn(move || {
+            let keypair = match connect_device(locator, derivation_path) {
+                Ok(keypair) => keypair,
+                Err(err) => {
+                    ready.send(Err(err.to_string())).ok();
+                    return;
+                }
+            };
+            ready.send(Ok((keypair.pubkey, keypair.path.clone()))).ok();
+
+            for (message, reply) in receiver {
+                reply.send(keypair.try_sign_message(&message)).ok();
+            }
+        });
+
+        let (pubkey, device_path) = connected
+            .recv()?
+            .map_err(|err| SugarSignerError::DeviceError(path.to_string(), err))?;
+        info!("Connected to hardware wallet {} ({})", device_path, pubkey);
+
+        Ok(RemoteSigner {
+            pubkey,
+            path: device_path,
+            requests: Mutex::new(requests),
+        })
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        let (reply, signature) = channel();
+        let disconnected = || SignerError::Connection(format!("{} disconnected", self.path));
+
+        self.requests
+            .lock()
+            .unwrap()
+            .send((message.to_vec(), reply))
+            .map_err(|_| disconnected())?;
+
+        signature.recv().map_err(|_| disconnected())?
+    }
+}
+
+impl Clone for RemoteSigner {
+    fn clone(&self) -> Self {
+        RemoteSigner {
+            pubkey: self.pubkey,
+            path: self.path.clone(),
+            requests: Mutex::new(self.requests.lock().unwrap().clone()),
+        }
+    }
+}
+
+/// Parse a hardware wallet path into the device locator and the derivation
+/// path of its `key` query (e.g., `usb://ledger?key=0/0`).
+fn parse_remote_path(path: &str) -> Result<(Locator, DerivationPath)> {
+    let invalid = |err: String| SugarSignerError::InvalidPath(path.to_string(), err);
+    let (device, query) = path.split_once('?').unwrap_or((path, ""));
+
+    let locator = Locator::new_from_path(device).map_err(|err| invalid(err.to_string()))?;
+    let derivation_path = match query.strip_prefix("key=") {
+        Some(key) => DerivationPath::from_key_str(key).map_err(|err| invalid(err.to_string()))?,
+        None if query.is_empty() => DerivationPath::default(),
+        None => return Err(invalid(format!("unsupported query '{}'", query)).into()),
+    };
+
+    Ok((locator, derivation_path))
+}
+
+fn connect_device(
+    locator: Locator,
+    derivation_path: DerivationPath,
+) -> Result<RemoteKeypair, RemoteWalletError> {
+    let wallet_manager = maybe_wallet_manager()?.ok_or(RemoteWalletError::NoDeviceFound)?;
+    generate_remote_keypair(locator, derivation_path, &wallet_manager, false, "keypair")
+}
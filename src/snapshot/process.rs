@@ -0,0 +1,78 @@
+use console::style;
+use std::fs;
+
+use crate::cache::load_cache;
+use crate::cli::SnapshotAction;
+use crate::common::*;
+use crate::guard::get_creator_metadata;
+use crate::mint::pdas::get_candy_machine_creator_pda;
+use crate::utils::*;
+
+pub struct SnapshotArgs {
+    pub action: SnapshotAction,
+}
+
+pub fn process_snapshot(args: SnapshotArgs) -> Result<()> {
+    match args.action {
+        SnapshotAction::Mints {
+            keypair,
+            rpc_url,
+            cache,
+            candy_machine,
+            output,
+        } => process_snapshot_mints(keypair, rpc_url, &cache, candy_machine, output),
+    }
+}
+
+fn process_snapshot_mints(
+    keypair: Option<String>,
+    rpc_url: Option<String>,
+    cache: &str,
+    candy_machine: Option<String>,
+    output: Option<String>,
+) -> Result<()> {
+    println!(
+        "{} {}Retrieving minted NFTs",
+        style("[1/2]").bold().dim(),
+        LOOKING_GLASS_EMOJI
+    );
+
+    // the candy machine specified takes precedence over the one from the cache
+    let candy_machine = match candy_machine {
+        Some(candy_machine) => candy_machine,
+        None => load_cache(cache, false)?.program.candy_machine,
+    };
+    let candy_pubkey = Pubkey::from_str(&candy_machine)
+        .map_err(|_| CacheError::InvalidCandyMachineAddress(candy_machine.clone()))?;
+
+    let sugar_config = sugar_setup(keypair, rpc_url)?;
+    let client = setup_client(&sugar_config)?;
+    let program = client.program(mpl_token_metadata::id());
+
+    let pb = spinner_with_style();
+    pb.set_message("Connecting...");
+
+    // NFTs minted by the candy machine have its creator PDA as verified first creator
+    let (creator, _) = get_candy_machine_creator_pda(&candy_pubkey);
+    let mut mints: Vec<String> = get_creator_metadata(&program, &creator)?
+        .into_iter()
+        .map(|(_, metadata)| metadata.mint.to_string())
+        .collect();
+    mints.sort();
+
+    pb.finish_and_clear();
+    println!("Found {} minted NFT(s)", mints.len());
+
+    println!(
+        "\n{} {}Writing mint list",
+        style("[2/2]").bold().dim(),
+        PAPER_EMOJI
+    );
+
+    let output = output.unwrap_or_else(|| format!("{}_mint_accounts.json", candy_pubkey));
+    fs::write(&output, serde_json::to_string_pretty(&mints)?)?;
+
+    println!("Mint list saved to '{}'", output);
+
+    Ok(())
+}
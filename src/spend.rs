@@ -0,0 +1,166 @@
+use anchor_client::solana_sdk::native_token::LAMPORTS_PER_SOL;
+use console::style;
+use serde::Serialize;
+use std::sync::{Mutex, OnceLock};
+
+use crate::common::*;
+use crate::journal::Journal;
+use crate::price::usd_suffix;
+
+/// Base fee (in lamports) charged per transaction signature.
+pub const LAMPORTS_PER_SIGNATURE: u64 = 5000;
+
+/// Compute units requested by default for each instruction of a transaction,
+/// used to compute the prioritization fee.
+const DEFAULT_INSTRUCTION_COMPUTE_UNITS: u64 = 200_000;
+
+/// Lamports spent during the current command.
+static SPEND: OnceLock<Mutex<SpendSummary>> = OnceLock::new();
+
+/// Command name and cache file of the project the spend is recorded to.
+static TRACKING: OnceLock<(String, String)> = OnceLock::new();
+
+/// Lamports spent, grouped by category.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SpendSummary {
+    pub transactions: u64,
+    pub transaction_fees: u64,
+    pub priority_fees: u64,
+    pub rent: u64,
+    pub funding: u64,
+    pub mint_price: u64,
+}
+
+impl SpendSummary {
+    pub fn total(&self) -> u64 {
+        self.transaction_fees + self.priority_fees + self.rent + self.funding + self.mint_price
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transactions == 0 && self.total() == 0
+    }
+
+    pub fn add(&mut self, other: &SpendSummary) {
+        self.transactions += other.transactions;
+        self.transaction_fees += other.transaction_fees;
+        self.priority_fees += other.priority_fees;
+        self.rent += other.rent;
+        self.funding += other.funding;
+        self.mint_price += other.mint_price;
+    }
+}
+
+fn update<F: FnOnce(&mut SpendSummary)>(f: F) {
+    let spend = SPEND.get_or_init(|| Mutex::new(SpendSummary::default()));
+
+    if let Ok(mut summary) = spend.lock() {
+        f(&mut summary);
+    }
+}
+
+/// Record the spend of the current command in the journal of the specified
+/// cache file. Only the first call has effect, so commands running other
+/// commands (e.g., launch) record their spend under their own name.
+pub fn track_spend(command: &str, cache_file_path: &str) {
+    TRACKING
+        .set((command.to_string(), cache_file_path.to_string()))
+        .ok();
+}
+
+/// Record the fees of a transaction, returning the total fee. The
+/// prioritization fee is charged on the compute units requested by its (non
+/// compute budget) instructions.
+pub fn record_transaction(signatures: u64, instructions: u64, priority_fee: Option<u64>) -> u64 {
+    let priority_fees = priority_fee
+        .map(|micro_lamports| {
+            (micro_lamports * DEFAULT_INSTRUCTION_COMPUTE_UNITS * instructions + 999_999)
+                / 1_000_000
+        })
+        .unwrap_or(0);
+
+    let transaction_fees = signatures * LAMPORTS_PER_SIGNATURE;
+
+    update(|summary| {
+        summary.transactions += 1;
+        summary.transaction_fees += transaction_fees;
+        summary.priority_fees += priority_fees;
+    });
+
+    transaction_fees + priority_fees
+}
+
+pub fn record_rent(lamports: u64) {
+    update(|summary| summary.rent += lamports);
+}
+
+pub fn record_funding(lamports: u64) {
+    update(|summary| summary.funding += lamports);
+}
+
+pub fn record_mint_price(lamports: u64) {
+    update(|summary| summary.mint_price += lamports);
+}
+
+/// Return the lamports spent during the current command.
+pub fn spend_summary() -> SpendSummary {
+    SPEND
+        .get()
+        .and_then(|spend| spend.lock().ok().map(|summary| summary.clone()))
+        .unwrap_or_default()
+}
+
+/// Print the spend of the current command and record it in the project
+/// journal, if tracked.
+pub fn finish_spend_report() -> Result<()> {
+    let summary = spend_summary();
+
+    if summary.is_empty() {
+        return Ok(());
+    }
+
+    println!("\n{}", style("Spend report").bold());
+    print_spend_summary(&summary);
+
+    if let Some((command, cache_file_path)) = TRACKING.get() {
+        let mut journal = Journal::load_for_cache(cache_file_path)?;
+        journal.record_spend(command, summary)?;
+    }
+
+    Ok(())
+}
+
+pub fn print_spend_summary(summary: &SpendSummary) {
+    let lines = [
+        ("Transaction fees", summary.transaction_fees),
+        ("Priority fees", summary.priority_fees),
+        ("Rent", summary.rent),
+        ("Funding", summary.funding),
+        ("Mint price", summary.mint_price),
+    ];
+
+    for (label, lamports) in lines {
+        if lamports > 0 {
+            println!(
+                "  {:<18}{}",
+                format!("{}:", label),
+                format_lamports(lamports)
+            );
+        }
+    }
+
+    println!(
+        "  {:<18}{} ({} transaction(s))",
+        "Total:",
+        style(format_lamports(summary.total())).bold(),
+        summary.transactions
+    );
+}
+
+pub fn format_lamports(lamports: u64) -> String {
+    format!(
+        "◎ {:.9}{}",
+        lamports as f64 / LAMPORTS_PER_SOL as f64,
+        usd_suffix(lamports)
+    )
+}
@@ -0,0 +1,271 @@
+use anchor_client::solana_sdk::{
+    native_token::LAMPORTS_PER_SOL,
+    signature::{read_keypair_file, write_keypair_file},
+};
+use console::style;
+use solana_client::rpc_client::RpcClient;
+use std::{
+    fs,
+    process::{Command, Stdio},
+    str::FromStr,
+    thread,
+    time::Duration,
+};
+
+use crate::candy_machine::ID as CANDY_MACHINE_ID;
+use crate::cli::TestEnvAction;
+use crate::common::*;
+use crate::config::data::SolanaConfig;
+use crate::utils::*;
+
+/// Name of the file (inside the ledger directory) storing the validator pid.
+const PID_FILE: &str = "sugar-validator.pid";
+
+/// Name of the file (inside the ledger directory) with the Solana CLI config
+/// of the test environment.
+const CONFIG_FILE: &str = "config.yml";
+
+/// Maximum number of seconds to wait for the validator to start.
+const STARTUP_TIMEOUT: u64 = 60;
+
+/// Candy machine program file name, when loading programs from a directory.
+const CANDY_MACHINE_PROGRAM_FILE: &str = "mpl_candy_machine.so";
+
+/// Token metadata program file name, when loading programs from a directory.
+const TOKEN_METADATA_PROGRAM_FILE: &str = "mpl_token_metadata.so";
+
+pub struct TestEnvArgs {
+    pub action: TestEnvAction,
+}
+
+pub fn process_test_env(args: TestEnvArgs) -> Result<()> {
+    match args.action {
+        TestEnvAction::Up {
+            rpc_url,
+            keypair,
+            ledger,
+            programs_dir,
+            airdrop,
+        } => up(rpc_url, keypair, ledger, programs_dir, airdrop),
+        TestEnvAction::Down { ledger } => down(ledger),
+    }
+}
+
+fn up(
+    rpc_url: String,
+    keypair: String,
+    ledger: String,
+    programs_dir: Option<String>,
+    airdrop: f64,
+) -> Result<()> {
+    println!(
+        "{} {}Starting local validator",
+        style("[1/3]").bold().dim(),
+        COMPUTER_EMOJI
+    );
+
+    let rpc_client = RpcClient::new(rpc_url.clone());
+
+    if rpc_client.get_health().is_ok() {
+        println!("Connected to running validator at {}", rpc_url);
+    } else {
+        let pb = spinner_with_style();
+        pb.enable_steady_tick(120);
+        pb.set_message("Starting solana-test-validator...");
+
+        start_validator(&ledger, programs_dir)?;
+
+        let mut elapsed = 0;
+
+        while rpc_client.get_health().is_err() {
+            if elapsed >= STARTUP_TIMEOUT {
+                pb.abandon_with_message(format!("{}", style("Validator failed ").red().bold()));
+                return Err(anyhow!(
+                    "Validator did not start after {} seconds (see '{}/validator.log')",
+                    STARTUP_TIMEOUT,
+                    ledger
+                ));
+            }
+            thread::sleep(Duration::from_secs(1));
+            elapsed += 1;
+        }
+
+        pb.finish_with_message(format!("Validator running at {}", rpc_url));
+    }
+
+    // checks that the required programs are loaded
+    let metadata_program_id = Pubkey::from_str(METAPLEX_PROGRAM_ID)?;
+
+    for (name, program_id) in [
+        ("Candy machine", CANDY_MACHINE_ID),
+        ("Token metadata", metadata_program_id),
+    ] {
+        match rpc_client.get_account(&program_id) {
+            Ok(account) if account.executable => {
+                println!("{} program loaded: {}", name, program_id)
+            }
+            _ => {
+                return Err(anyhow!(
+                    "{} program ({}) is not loaded on the validator",
+                    name,
+                    program_id
+                ))
+            }
+        }
+    }
+
+    println!(
+        "\n{} {}Funding test keypair",
+        style("[2/3]").bold().dim(),
+        PAYMENT_EMOJI
+    );
+
+    let test_keypair = if Path::new(&keypair).exists() {
+        read_keypair_file(&keypair).map_err(|e| anyhow!("Failed to read keypair file: {}", e))?
+    } else {
+        let test_keypair = Keypair::new();
+        write_keypair_file(&test_keypair, &keypair)
+            .map_err(|e| anyhow!("Failed to write keypair file: {}", e))?;
+        test_keypair
+    };
+
+    let lamports = (airdrop * LAMPORTS_PER_SOL as f64) as u64;
+    let signature = rpc_client.request_airdrop(&test_keypair.pubkey(), lamports)?;
+    let mut elapsed = 0;
+
+    while !rpc_client.confirm_transaction(&signature)? {
+        if elapsed >= STARTUP_TIMEOUT {
+            return Err(anyhow!("Airdrop not confirmed: {}", signature));
+        }
+        thread::sleep(Duration::from_secs(1));
+        elapsed += 1;
+    }
+
+    println!(
+        "Funded {} with {} SOL",
+        test_keypair.pubkey(),
+        rpc_client.get_balance(&test_keypair.pubkey())? as f64 / LAMPORTS_PER_SOL as f64
+    );
+
+    println!(
+        "\n{} {}Writing test environment config",
+        style("[3/3]").bold().dim(),
+        PAPER_EMOJI
+    );
+
+    // the global Solana CLI config is left untouched, the test environment
+    // has its own config inside the ledger directory
+    let config_path = Path::new(&ledger).join(CONFIG_FILE);
+    let keypair_path = path_to_string(&fs::canonicalize(&keypair)?)?;
+    let solana_config = SolanaConfig {
+        json_rpc_url: rpc_url.clone(),
+        keypair_path: keypair_path.clone(),
+        commitment: "confirmed".to_string(),
+    };
+
+    fs::create_dir_all(&ledger)?;
+    fs::write(&config_path, serde_yaml::to_string(&solana_config)?)?;
+
+    println!(
+        "Solana CLI config saved to '{}'",
+        path_to_string(&config_path)?
+    );
+    println!(
+        "\nRun sugar commands against the test environment with:\n  {}",
+        style(format!("--keypair {} --rpc-url {}", keypair_path, rpc_url)).bold()
+    );
+    println!(
+        "and the Solana CLI with:\n  {}",
+        style(format!("--config {}", path_to_string(&config_path)?)).bold()
+    );
+    println!(
+        "\n{}",
+        style("Run 'sugar test-env down' to stop the validator.").dim()
+    );
+
+    Ok(())
+}
+
+fn down(ledger: String) -> Result<()> {
+    println!(
+        "{} {}Stopping local validator",
+        style("[1/1]").bold().dim(),
+        COMPUTER_EMOJI
+    );
+
+    let pid_path = Path::new(&ledger).join(PID_FILE);
+
+    if pid_path.exists() {
+        let pid = fs::read_to_string(&pid_path)?.trim().to_string();
+
+        let status = if cfg!(windows) {
+            Command::new("taskkill")
+                .args(["/PID", &pid, "/F"])
+                .status()?
+        } else {
+            Command::new("kill").arg(&pid).status()?
+        };
+
+        if status.success() {
+            println!("Validator stopped (pid {})", pid);
+        } else {
+            println!(
+                "{}",
+                style(format!("Could not stop validator (pid {})", pid)).yellow()
+            );
+        }
+
+        fs::remove_file(pid_path)?;
+    } else {
+        println!("No validator started by sugar found in '{}'", ledger);
+    }
+
+    Ok(())
+}
+
+/// Start `solana-test-validator` in the background with the candy machine and
+/// token metadata programs loaded, either from local files or cloned from
+/// mainnet-beta.
+fn start_validator(ledger: &str, programs_dir: Option<String>) -> Result<()> {
+    fs::create_dir_all(ledger)?;
+
+    let mut command = Command::new("solana-test-validator");
+    command.args(["--reset", "--quiet", "--ledger", ledger]);
+
+    let candy_machine_id = CANDY_MACHINE_ID.to_string();
+
+    match programs_dir {
+        Some(programs_dir) => {
+            let programs_dir = Path::new(&programs_dir);
+            command
+                .arg("--bpf-program")
+                .arg(&candy_machine_id)
+                .arg(programs_dir.join(CANDY_MACHINE_PROGRAM_FILE))
+                .arg("--bpf-program")
+                .arg(METAPLEX_PROGRAM_ID)
+                .arg(programs_dir.join(TOKEN_METADATA_PROGRAM_FILE));
+        }
+        None => {
+            command.args([
+                "--url",
+                "mainnet-beta",
+                "--clone-upgradeable-program",
+                &candy_machine_id,
+                "--clone-upgradeable-program",
+                METAPLEX_PROGRAM_ID,
+            ]);
+        }
+    }
+
+    let log = fs::File::create(Path::new(ledger).join("validator.log"))?;
+
+    let child = command
+        .stdout(Stdio::null())
+        .stderr(log)
+        .spawn()
+        .map_err(|e| anyhow!("Failed to start solana-test-validator: {}", e))?;
+
+    fs::write(Path::new(ledger).join(PID_FILE), child.id().to_string())?;
+
+    Ok(())
+}
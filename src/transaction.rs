@@ -0,0 +1,284 @@
+use anchor_client::solana_sdk::{hash::Hash, instruction::Instruction, signer::signers::Signers};
+use solana_client::{
+    pubsub_client::{PubsubClient, SignatureSubscription},
+    rpc_config::RpcSignatureSubscribeConfig,
+    rpc_response::{ProcessedSignatureResult, RpcSignatureResult},
+};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::RecvTimeoutError,
+        Once,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use thiserror::Error;
+
+use crate::common::*;
+use crate::retry::{retry, Subsystem};
+use crate::rpc::{primary_websocket_url, with_rpc};
+use crate::rpc_cache;
+use crate::setup::proxy;
+
+/// Whether transactions are confirmed through a websocket subscription
+/// instead of polling their status.
+static WEBSOCKET_CONFIRMATION: AtomicBool = AtomicBool::new(false);
+
+/// Interval between confirmation checks of a sent transaction.
+const CONFIRMATION_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Maximum time to wait for a confirmation, in case the blockhash validity
+/// cannot be checked.
+const CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// Interval between blockhash validity checks while waiting for a websocket
+/// notification.
+const WEBSOCKET_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Error)]
+pub enum SendError {
+    #[error("Transaction {0} was not confirmed before its blockhash expired")]
+    Expired(Signature),
+
+    #[error("Transaction {0} failed: {1}")]
+    Failed(Signature, String),
+}
+
+/// Confirm transactions through a websocket signature subscription on the
+/// primary endpoint, instead of polling their status.
+pub fn enable_websocket_confirmation() {
+    WEBSOCKET_CONFIRMATION.store(true, Ordering::Relaxed);
+}
+
+/// Send a transaction with the specified instructions and confirm it with the
+/// commitment of the program client.
+///
+/// See [`send_with_commitment`].
+pub fn send_with_retry<T: Signers>(
+    program: &Program,
+    instructions: &[Instruction],
+    signers: &T,
+) -> Result<Signature> {
+    let commitment = program.rpc().commitment();
+    send_with_commitment(program, instructions, signers, commitment)
+}
+
+/// Send a transaction with the specified instructions, paid by the payer of
+/// the program client (which must be one of the signers), and wait until it
+/// reaches the specified commitment.
+///
+/// Transient failures (dropped transactions, expired blockhashes, timeouts)
+/// are retried according to the RPC retry policy, signing the transaction
+/// with a new blockhash on each attempt. Before resending, the status of the
+/// previous attempt is checked so a transaction that landed late is not sent
+/// twice.
+pub fn send_with_commitment<T: Signers>(
+    program: &Program,
+    instructions: &[Instruction],
+    signers: &T,
+    commitment: CommitmentConfig,
+) -> Result<Signature> {
+    send_with_callback(program, instructions, signers, commitment, |_, _| Ok(()))
+}
+
+/// Send a transaction like [`send_with_commitment`], calling `on_signed` with
+/// the signature and blockhash of each attempt before it is sent. Commands
+/// that resume use it to persist the pending signature, so a transaction
+/// that lands after an interruption is found with [`check_pending`] instead
+/// of being sent again.
+pub fn send_with_callback<T: Signers, F: FnMut(&Signature, &Hash) -> Result<()>>(
+    program: &Program,
+    instructions: &[Instruction],
+    signers: &T,
+    commitment: CommitmentConfig,
+    mut on_signed: F,
+) -> Result<Signature> {
+    let mut previous: Option<(Signature, Hash)> = None;
+    let mut attempt = 0;
+
+    retry(Subsystem::Rpc, || {
+        attempt += 1;
+
+        // a previous attempt that landed is confirmed instead of resent
+        if let Some((signature, blockhash)) = previous {
+            if is_processed(program, &signature)? {
+                confirm_transaction(program, &signature, &blockhash, commitment)?;
+                return Ok(signature);
+            }
+        }
+
+        // the cached blockhash is only used on the first attempt
+        let blockhash = if attempt == 1 {
+            rpc_cache::get_latest_blockhash(program)?
+        } else {
+            rpc_cache::refresh_blockhash(program)?
+        };
+        let mut transaction = Transaction::new_with_payer(instructions, Some(&program.payer()));
+        transaction.try_sign(signers, blockhash)?;
+        on_signed(&transaction.signatures[0], &blockhash)?;
+
+        // subscribing before sending ensures the notification is not missed
+        let subscription = if WEBSOCKET_CONFIRMATION.load(Ordering::Relaxed) {
+            subscribe_signature(&transaction.signatures[0], commitment)
+        } else {
+            None
+        };
+
+        let signature = with_rpc(program, |rpc| Ok(rpc.send_transaction(&transaction)?))?;
+        previous = Some((signature, blockhash));
+        debug!("Sent transaction {}", signature);
+
+        match subscription {
+            Some(subscription) => {
+                confirm_with_websocket(program, subscription, &signature, &blockhash, commitment)?
+            }
+            None => confirm_transaction(program, &signature, &blockhash, commitment)?,
+        }
+
+        Ok(signature)
+    })
+}
+
+/// Check the outcome of a transaction sent by an interrupted command, waiting
+/// until it is confirmed or its blockhash expires. Returns true if the
+/// transaction succeeded, and false if it failed or never landed (so it is
+/// safe to send again).
+pub fn check_pending(program: &Program, signature: &Signature, blockhash: &Hash) -> Result<bool> {
+    let commitment = program.rpc().commitment();
+
+    match confirm_transaction(program, signature, blockhash, commitment) {
+        Ok(()) => Ok(true),
+        Err(err) if err.downcast_ref::<SendError>().is_some() => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// Check whether a transaction has been processed by the cluster.
+fn is_processed(program: &Program, signature: &Signature) -> Result<bool> {
+    with_rpc(program, |rpc| {
+        Ok(rpc
+            .get_signature_status_with_commitment(signature, CommitmentConfig::processed())?
+            .is_some())
+    })
+}
+
+/// Check whether the blockhash of a transaction expired before the
+/// transaction was processed, or the confirmation timed out.
+fn is_expired(
+    program: &Program,
+    signature: &Signature,
+    blockhash: &Hash,
+    start: Instant,
+) -> Result<bool> {
+    let expired = !with_rpc(program, |rpc| {
+        Ok(rpc.is_blockhash_valid(blockhash, CommitmentConfig::processed())?)
+    })
+    .unwrap_or(true);
+
+    // a processed transaction can still reach the commitment after its
+    // blockhash expires
+    Ok((expired && !is_processed(program, signature)?) || start.elapsed() > CONFIRMATION_TIMEOUT)
+}
+
+/// Subscribe to the status of a signature on the primary endpoint. Returns
+/// None if the websocket is not available, in which case the status is
+/// polled instead.
+fn subscribe_signature(
+    signature: &Signature,
+    commitment: CommitmentConfig,
+) -> Option<SignatureSubscription> {
+    // the websocket client does not go through the proxy, so the status is
+    // polled to keep all the traffic on the proxy
+    if proxy().is_some() {
+        static PROXY_WARNING: Once = Once::new();
+        PROXY_WARNING.call_once(|| {
+            warn!("Websocket confirmation is not supported with a proxy, polling instead")
+        });
+        return None;
+    }
+
+    let url = primary_websocket_url()?;
+    let config = RpcSignatureSubscribeConfig {
+        commitment: Some(commitment),
+        enable_received_notification: Some(false),
+    };
+
+    match PubsubClient::signature_subscribe(&url, signature, Some(config)) {
+        Ok(subscription) => Some(subscription),
+        Err(err) => {
+            warn!("Could not subscribe to {} ({}), polling instead", url, err);
+            None
+        }
+    }
+}
+
+/// Wait for the websocket notification of a transaction, failing when its
+/// blockhash expires. Falls back to polling if the websocket disconnects.
+fn confirm_with_websocket(
+    program: &Program,
+    subscription: SignatureSubscription,
+    signature: &Signature,
+    blockhash: &Hash,
+    commitment: CommitmentConfig,
+) -> Result<()> {
+    let (client_subscription, receiver) = subscription;
+    let start = Instant::now();
+
+    let result = loop {
+        match receiver.recv_timeout(WEBSOCKET_CHECK_INTERVAL) {
+            Ok(response) => match response.value {
+                RpcSignatureResult::ProcessedSignature(ProcessedSignatureResult { err: None }) => {
+                    break Ok(())
+                }
+                RpcSignatureResult::ProcessedSignature(ProcessedSignatureResult {
+                    err: Some(err),
+                }) => break Err(SendError::Failed(*signature, err.to_string()).into()),
+                RpcSignatureResult::ReceivedSignature(_) => (),
+            },
+            Err(RecvTimeoutError::Timeout) => {
+                if is_expired(program, signature, blockhash, start)? {
+                    break Err(SendError::Expired(*signature).into());
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                warn!("Websocket disconnected, polling for {}", signature);
+                break confirm_transaction(program, signature, blockhash, commitment);
+            }
+        }
+    };
+
+    // unsubscribing waits for the socket reader, which only returns on the
+    // next message, so the subscription is dropped in the background
+    thread::spawn(move || drop(client_subscription));
+
+    result
+}
+
+/// Wait until a transaction reaches the specified commitment, failing when
+/// its blockhash expires.
+fn confirm_transaction(
+    program: &Program,
+    signature: &Signature,
+    blockhash: &Hash,
+    commitment: CommitmentConfig,
+) -> Result<()> {
+    let start = Instant::now();
+
+    loop {
+        if let Some(result) = with_rpc(program, |rpc| {
+            Ok(rpc.get_signature_status_with_commitment(signature, commitment)?)
+        })? {
+            return match result {
+                Ok(()) => Ok(()),
+                Err(err) => Err(SendError::Failed(*signature, err.to_string()).into()),
+            };
+        }
+
+        if is_expired(program, signature, blockhash, start)? {
+            return Err(SendError::Expired(*signature).into());
+        }
+
+        thread::sleep(CONFIRMATION_POLL_INTERVAL);
+    }
+}
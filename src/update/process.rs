@@ -1,17 +1,25 @@
-use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_client::solana_sdk::{native_token::LAMPORTS_PER_SOL, pubkey::Pubkey};
 use anchor_lang::prelude::AccountMeta;
 use anyhow::Result;
+use chrono::NaiveDateTime;
 use console::style;
 use spl_associated_token_account::get_associated_token_address;
 use std::str::FromStr;
 
 use mpl_candy_machine::instruction as nft_instruction;
-use mpl_candy_machine::{accounts as nft_accounts, CandyMachineData};
+use mpl_candy_machine::{
+    accounts as nft_accounts, CandyMachineData, EndSettingType, WhitelistMintMode,
+};
 
 use crate::candy_machine::ID as CANDY_MACHINE_ID;
 use crate::candy_machine::{get_candy_machine_state, parse_config_price};
 use crate::common::*;
 use crate::config::{data::*, parser::get_config_data};
+use crate::interaction::confirm;
+use crate::multisig::MultisigProposal;
+use crate::rpc_cache;
+use crate::spend::{record_transaction, track_spend};
+use crate::transaction::send_with_retry;
 use crate::utils::{check_spl_token, check_spl_token_account, spinner_with_style};
 use crate::{cache::load_cache, config::data::ConfigData};
 
@@ -22,9 +30,13 @@ pub struct UpdateArgs {
     pub new_authority: Option<String>,
     pub config: String,
     pub candy_machine: Option<String>,
+    pub multisig: Option<String>,
+    pub proposal: String,
 }
 
 pub fn process_update(args: UpdateArgs) -> Result<()> {
+    track_spend("update", &args.cache);
+
     let sugar_config = sugar_setup(args.keypair, args.rpc_url)?;
     let client = setup_client(&sugar_config)?;
     let config_data = get_config_data(&args.config)?;
@@ -48,9 +60,20 @@ pub fn process_update(args: UpdateArgs) -> Result<()> {
         }
     };
 
+    // with a multisig authority, the transaction is written as a proposal
+    // instead of being signed by the keypair
+    let multisig = args
+        .multisig
+        .map(|multisig| {
+            Pubkey::from_str(&multisig)
+                .map_err(|_| anyhow!("Failed to parse multisig address: {}", multisig))
+        })
+        .transpose()?;
+    let authority = multisig.unwrap_or_else(|| sugar_config.keypair.pubkey());
+
     println!(
         "{} {}Loading candy machine",
-        style("[1/2]").bold().dim(),
+        style("[1/3]").bold().dim(),
         LOOKING_GLASS_EMOJI
     );
     println!("{} {}", style("Candy machine ID:").bold(), candy_machine_id);
@@ -60,14 +83,22 @@ pub fn process_update(args: UpdateArgs) -> Result<()> {
 
     let candy_machine_state = get_candy_machine_state(&sugar_config, &candy_pubkey)?;
     let candy_machine_data =
-        create_candy_machine_data(&client, &config_data, candy_machine_state.data)?;
+        create_candy_machine_data(&client, &config_data, candy_machine_state.data.clone())?;
 
     pb.finish_with_message("Done");
 
+    if multisig.is_some() && candy_machine_state.authority != authority {
+        return Err(anyhow!(
+            "The candy machine authority is {}, not the multisig {}",
+            candy_machine_state.authority,
+            authority
+        ));
+    }
+
     println!(
-        "\n{} {}Updating configuration",
-        style("[2/2]").bold().dim(),
-        COMPUTER_EMOJI
+        "\n{} {}Comparing configuration",
+        style("[2/3]").bold().dim(),
+        LOOKING_GLASS_EMOJI
     );
 
     let mut remaining_accounts: Vec<AccountMeta> = Vec::new();
@@ -89,7 +120,7 @@ pub fn process_update(args: UpdateArgs) -> Result<()> {
             let spl_token_account_figured = if config_data.spl_token_account.is_some() {
                 config_data.spl_token_account
             } else {
-                Some(get_associated_token_address(&program.payer(), &spl_token))
+                Some(get_associated_token_address(&authority, &spl_token))
             };
 
             if config_data.sol_treasury_account.is_some() {
@@ -111,55 +142,153 @@ pub fn process_update(args: UpdateArgs) -> Result<()> {
         }
         None => match config_data.sol_treasury_account {
             Some(sol_treasury_account) => sol_treasury_account,
-            None => sugar_config.keypair.pubkey(),
+            None => authority,
         },
     };
 
-    let mut builder = program
-        .request()
-        .accounts(nft_accounts::UpdateCandyMachine {
-            candy_machine: candy_pubkey,
-            authority: program.payer(),
-            wallet: treasury_account,
-        })
-        .args(nft_instruction::UpdateCandyMachine {
-            data: candy_machine_data,
-        });
+    let current = describe_candy_machine(
+        &candy_machine_state.data,
+        &candy_machine_state.wallet,
+        candy_machine_state.token_mint.as_ref(),
+    );
+    let updated = describe_candy_machine(
+        &candy_machine_data,
+        &treasury_account,
+        config_data.spl_token.as_ref(),
+    );
 
-    if !remaining_accounts.is_empty() {
-        for account in remaining_accounts {
-            builder = builder.accounts(account);
-        }
+    let changes: Vec<(&String, &String, &String)> = current
+        .iter()
+        .zip(updated.values())
+        .filter(|((_, old), new)| old != new)
+        .map(|((field, old), new)| (field, old, new))
+        .collect();
+
+    let new_authority = args
+        .new_authority
+        .map(|new_authority| Pubkey::from_str(&new_authority))
+        .transpose()?
+        .filter(|new_authority| *new_authority != candy_machine_state.authority);
+
+    if changes.is_empty() && new_authority.is_none() {
+        println!("\nNo changes to the candy machine configuration.");
+        return Ok(());
     }
 
-    let pb = spinner_with_style();
-    pb.set_message("Sending update transaction...");
+    println!();
 
-    let update_signature = builder.send()?;
+    for (field, old, new) in &changes {
+        println!(
+            "{}: {} {} {}",
+            style(field).bold(),
+            style(old).red(),
+            style("->").dim(),
+            style(new).green()
+        );
+    }
 
-    pb.finish_with_message(format!(
-        "{} {}",
-        style("Update signature:").bold(),
-        update_signature
-    ));
+    if let Some(new_authority) = new_authority {
+        println!(
+            "{}: {} {} {}",
+            style("authority").bold(),
+            style(candy_machine_state.authority).red(),
+            style("->").dim(),
+            style(new_authority).green()
+        );
+    }
 
-    if let Some(new_authority) = args.new_authority {
-        let pb = spinner_with_style();
-        pb.set_message("Sending update authority transaction...");
+    if !confirm("Apply the changes?")? {
+        println!("\n{}", style("Update aborted.").red().bold().dim());
+        return Ok(());
+    }
 
-        let new_authority_pubkey = Pubkey::from_str(&new_authority)?;
-        let builder = program
+    println!(
+        "\n{} {}Updating configuration",
+        style("[3/3]").bold().dim(),
+        COMPUTER_EMOJI
+    );
+
+    let mut update_instructions = Vec::new();
+
+    if !changes.is_empty() {
+        let mut builder = program
             .request()
             .accounts(nft_accounts::UpdateCandyMachine {
                 candy_machine: candy_pubkey,
-                authority: program.payer(),
+                authority,
                 wallet: treasury_account,
             })
-            .args(nft_instruction::UpdateAuthority {
-                new_authority: Some(new_authority_pubkey),
+            .args(nft_instruction::UpdateCandyMachine {
+                data: candy_machine_data,
             });
 
-        let authority_signature = builder.send()?;
+        if !remaining_accounts.is_empty() {
+            for account in remaining_accounts {
+                builder = builder.accounts(account);
+            }
+        }
+
+        update_instructions = builder.instructions()?;
+    }
+
+    let mut authority_instructions = Vec::new();
+
+    if let Some(new_authority) = new_authority {
+        authority_instructions = program
+            .request()
+            .accounts(nft_accounts::UpdateCandyMachine {
+                candy_machine: candy_pubkey,
+                authority,
+                wallet: candy_machine_state.wallet,
+            })
+            .args(nft_instruction::UpdateAuthority {
+                new_authority: Some(new_authority),
+            })
+            .instructions()?;
+    }
+
+    if let Some(multisig) = multisig {
+        let mut instructions = update_instructions;
+        instructions.extend(authority_instructions);
+
+        MultisigProposal::new(
+            &format!("Update candy machine {}", candy_pubkey),
+            &multisig,
+            &instructions,
+        )
+        .write(&args.proposal)?;
+
+        println!("{} {}", style("Multisig proposal:").bold(), args.proposal);
+        println!("Propose the transaction message in the multisig UI to apply the changes.");
+
+        return Ok(());
+    }
+
+    if !update_instructions.is_empty() {
+        let pb = spinner_with_style();
+        pb.set_message("Sending update transaction...");
+
+        let update_signature =
+            send_with_retry(&program, &update_instructions, &[&sugar_config.keypair])?;
+        rpc_cache::invalidate(&candy_pubkey);
+        record_transaction(1, 1, None);
+
+        pb.finish_with_message(format!(
+            "{} {}",
+            style("Update signature:").bold(),
+            update_signature
+        ));
+    }
+
+    if !authority_instructions.is_empty() {
+        let pb = spinner_with_style();
+        pb.set_message("Sending update authority transaction...");
+
+        let authority_signature =
+            send_with_retry(&program, &authority_instructions, &[&sugar_config.keypair])?;
+        rpc_cache::invalidate(&candy_pubkey);
+        record_transaction(1, 1, None);
+
         pb.finish_with_message(format!(
             "{} {}",
             style("Authority signature:").bold(),
@@ -170,6 +299,107 @@ pub fn process_update(args: UpdateArgs) -> Result<()> {
     Ok(())
 }
 
+/// Describe the updatable settings of a candy machine, in the same order for
+/// any candy machine so two descriptions can be compared field by field.
+fn describe_candy_machine(
+    data: &CandyMachineData,
+    wallet: &Pubkey,
+    token_mint: Option<&Pubkey>,
+) -> IndexMap<String, String> {
+    let sol = |lamports: u64| format!("◎ {}", lamports as f64 / LAMPORTS_PER_SOL as f64);
+    let date = |timestamp: i64| {
+        NaiveDateTime::from_timestamp(timestamp, 0)
+            .format("%a %B %e %Y %H:%M:%S UTC")
+            .to_string()
+    };
+    let none = || "none".to_string();
+
+    let mut fields = IndexMap::new();
+
+    fields.insert(
+        "price".to_string(),
+        match token_mint {
+            Some(_) => data.price.to_string(),
+            None => sol(data.price),
+        },
+    );
+    fields.insert(
+        "token mint".to_string(),
+        token_mint.map(|mint| mint.to_string()).unwrap_or_else(none),
+    );
+    fields.insert("wallet".to_string(), wallet.to_string());
+    fields.insert(
+        "items available".to_string(),
+        data.items_available.to_string(),
+    );
+    fields.insert(
+        "go live date".to_string(),
+        data.go_live_date.map(date).unwrap_or_else(none),
+    );
+    fields.insert("is mutable".to_string(), data.is_mutable.to_string());
+    fields.insert(
+        "retain authority".to_string(),
+        data.retain_authority.to_string(),
+    );
+    fields.insert(
+        "end settings".to_string(),
+        data.end_settings
+            .as_ref()
+            .map(|settings| match settings.end_setting_type {
+                EndSettingType::Date => format!("date {}", date(settings.number as i64)),
+                EndSettingType::Amount => format!("amount {}", settings.number),
+            })
+            .unwrap_or_else(none),
+    );
+    fields.insert(
+        "whitelist mint settings".to_string(),
+        data.whitelist_mint_settings
+            .as_ref()
+            .map(|settings| {
+                format!(
+                    "mint {}, mode {}, presale {}, discount price {}",
+                    settings.mint,
+                    if settings.mode == WhitelistMintMode::BurnEveryTime {
+                        "burn every time"
+                    } else {
+                        "never burn"
+                    },
+                    settings.presale,
+                    settings.discount_price.map(sol).unwrap_or_else(none)
+                )
+            })
+            .unwrap_or_else(none),
+    );
+    fields.insert(
+        "hidden settings".to_string(),
+        data.hidden_settings
+            .as_ref()
+            .map(|settings| {
+                format!(
+                    "name '{}', uri '{}', hash '{}'",
+                    settings.name,
+                    settings.uri,
+                    String::from_utf8_lossy(&settings.hash)
+                )
+            })
+            .unwrap_or_else(none),
+    );
+    fields.insert(
+        "gatekeeper".to_string(),
+        data.gatekeeper
+            .as_ref()
+            .map(|gatekeeper| {
+                format!(
+                    "network {}, expire on use {}",
+                    gatekeeper.gatekeeper_network, gatekeeper.expire_on_use
+                )
+            })
+            .unwrap_or_else(none),
+    );
+
+    fields
+}
+
 fn create_candy_machine_data(
     client: &Client,
     config: &ConfigData,
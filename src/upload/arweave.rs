@@ -0,0 +1,825 @@
+use async_trait::async_trait;
+use clap::crate_version;
+use console::style;
+use data_encoding::BASE64URL_NOPAD;
+use reqwest::StatusCode;
+use ring::{
+    digest::{digest, SHA256, SHA384},
+    rand::SystemRandom,
+    signature::{RsaKeyPair, RSA_PSS_SHA256},
+};
+use std::{
+    collections::BTreeMap,
+    ffi::OsStr,
+    fs,
+    sync::{atomic::AtomicBool, Arc},
+    time::Duration,
+};
+use tokio::time::sleep;
+
+use crate::events::{error_event, upload_event};
+use crate::retry::{retry_async, Subsystem};
+use crate::{common::*, config::*, constants::PARALLEL_LIMIT, upload::*, utils::*};
+
+/// Default Arweave gateway.
+const ARWEAVE_GATEWAY: &str = "https://arweave.net";
+
+/// Maximum size of a chunk of transaction data.
+const MAX_CHUNK_SIZE: usize = 256 * 1024;
+
+/// Minimum size of a chunk of transaction data (except for the last one).
+const MIN_CHUNK_SIZE: usize = 32 * 1024;
+
+/// Size of the byte range notes of the data merkle tree.
+const NOTE_SIZE: usize = 32;
+
+/// Interval between the confirmation checks of a transaction.
+const CONFIRMATION_INTERVAL: Duration = Duration::from_secs(20);
+
+/// Number of confirmation checks before giving up on a transaction (30 minutes).
+const CONFIRMATION_ATTEMPTS: u32 = 90;
+
+/// Number of winston in one AR.
+pub const WINSTON_PER_AR: f64 = 1_000_000_000_000.0;
+
+/// Private key of an Arweave wallet in JWK format.
+#[derive(Debug, Deserialize)]
+struct Jwk {
+    n: String,
+    e: String,
+    d: String,
+    p: String,
+    q: String,
+    dp: String,
+    dq: String,
+    qi: String,
+}
+
+/// Arweave wallet used to sign the transactions.
+struct ArweaveWallet {
+    key_pair: RsaKeyPair,
+    owner: Vec<u8>,
+}
+
+impl ArweaveWallet {
+    /// Load a wallet from a JWK file.
+    fn load(path: &str) -> Result<ArweaveWallet> {
+        let jwk: Jwk = serde_json::from_str(
+            &fs::read_to_string(path)
+                .map_err(|err| anyhow!("Failed to read Arweave JWK file {}: {}", path, err))?,
+        )?;
+
+        let decode = |value: &str| -> Result<Vec<u8>> {
+            BASE64URL_NOPAD
+                .decode(value.trim_end_matches('=').as_bytes())
+                .map_err(|err| anyhow!("Invalid Arweave JWK value: {}", err))
+        };
+
+        let owner = decode(&jwk.n)?;
+        let mut components = vec![der_integer(&[0]), der_integer(&owner)];
+
+        for value in [&jwk.e, &jwk.d, &jwk.p, &jwk.q, &jwk.dp, &jwk.dq, &jwk.qi] {
+            components.push(der_integer(&decode(value)?));
+        }
+
+        // ring expects the key as a DER encoded PKCS#1 RSAPrivateKey
+        let key_pair = RsaKeyPair::from_der(&der_tlv(0x30, &components.concat()))
+            .map_err(|err| anyhow!("Invalid Arweave JWK: {}", err))?;
+
+        Ok(ArweaveWallet { key_pair, owner })
+    }
+
+    /// Return the address of the wallet.
+    fn address(&self) -> String {
+        BASE64URL_NOPAD.encode(digest(&SHA256, &self.owner).as_ref())
+    }
+
+    /// Sign a message with RSA-PSS (SHA-256).
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>> {
+        let mut signature = vec![0; self.key_pair.public_modulus_len()];
+        self.key_pair
+            .sign(
+                &RSA_PSS_SHA256,
+                &SystemRandom::new(),
+                message,
+                &mut signature,
+            )
+            .map_err(|_| anyhow!("Failed to sign Arweave transaction."))?;
+
+        Ok(signature)
+    }
+}
+
+/// Encode a DER element.
+fn der_tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut encoded = vec![tag];
+
+    if content.len() < 0x80 {
+        encoded.push(content.len() as u8);
+    } else {
+        let length = content.len().to_be_bytes();
+        let length: Vec<u8> = length
+            .iter()
+            .copied()
+            .skip_while(|byte| *byte == 0)
+            .collect();
+        encoded.push(0x80 | length.len() as u8);
+        encoded.extend_from_slice(&length);
+    }
+
+    encoded.extend_from_slice(content);
+    encoded
+}
+
+/// Encode an unsigned big-endian integer as a DER INTEGER.
+fn der_integer(value: &[u8]) -> Vec<u8> {
+    let start = value
+        .iter()
+        .position(|byte| *byte != 0)
+        .unwrap_or(value.len().saturating_sub(1));
+    let value = &value[start..];
+
+    let mut content = Vec::with_capacity(value.len() + 1);
+    if value.first().map(|byte| byte & 0x80 != 0).unwrap_or(true) {
+        content.push(0);
+    }
+    content.extend_from_slice(value);
+
+    der_tlv(0x02, &content)
+}
+
+/// Item of the deep hash used as the signature data of a transaction.
+enum DeepHashItem<'a> {
+    Blob(&'a [u8]),
+    List(Vec<DeepHashItem<'a>>),
+}
+
+fn sha384(parts: &[&[u8]]) -> Vec<u8> {
+    digest(&SHA384, &parts.concat()).as_ref().to_vec()
+}
+
+fn deep_hash(item: &DeepHashItem) -> Vec<u8> {
+    match item {
+        DeepHashItem::Blob(data) => {
+            let tag = sha384(&[b"blob", data.len().to_string().as_bytes()]);
+            sha384(&[&tag, &sha384(&[data])])
+        }
+        DeepHashItem::List(items) => {
+            let mut hash = sha384(&[b"list", items.len().to_string().as_bytes()]);
+            for item in items {
+                hash = sha384(&[&hash, &deep_hash(item)]);
+            }
+            hash
+        }
+    }
+}
+
+fn sha256(parts: &[&[u8]]) -> Vec<u8> {
+    digest(&SHA256, &parts.concat()).as_ref().to_vec()
+}
+
+/// Return the byte range note of the data merkle tree.
+fn note(value: usize) -> [u8; NOTE_SIZE] {
+    let mut note = [0; NOTE_SIZE];
+    note[NOTE_SIZE - 8..].copy_from_slice(&(value as u64).to_be_bytes());
+    note
+}
+
+/// Node of the merkle tree of the transaction data.
+enum MerkleNode {
+    Leaf {
+        id: Vec<u8>,
+        data_hash: Vec<u8>,
+        max_byte_range: usize,
+    },
+    Branch {
+        id: Vec<u8>,
+        byte_range: usize,
+        max_byte_range: usize,
+        left: Box<MerkleNode>,
+        right: Box<MerkleNode>,
+    },
+}
+
+impl MerkleNode {
+    fn id(&self) -> &[u8] {
+        match self {
+            MerkleNode::Leaf { id, .. } | MerkleNode::Branch { id, .. } => id,
+        }
+    }
+
+    fn max_byte_range(&self) -> usize {
+        match self {
+            MerkleNode::Leaf { max_byte_range, .. } | MerkleNode::Branch { max_byte_range, .. } => {
+                *max_byte_range
+            }
+        }
+    }
+
+    /// Collect the proof and offset of each leaf, in order.
+    fn proofs(&self, proof: Vec<u8>, proofs: &mut Vec<(Vec<u8>, usize)>) {
+        match self {
+            MerkleNode::Leaf {
+                data_hash,
+                max_byte_range,
+                ..
+            } => {
+                let proof = [&proof, data_hash.as_slice(), &note(*max_byte_range)].concat();
+                proofs.push((proof, max_byte_range.saturating_sub(1)));
+            }
+            MerkleNode::Branch {
+                byte_range,
+                left,
+                right,
+                ..
+            } => {
+                let proof = [&proof, left.id(), right.id(), &note(*byte_range)].concat();
+                left.proofs(proof.clone(), proofs);
+                right.proofs(proof, proofs);
+            }
+        }
+    }
+}
+
+/// Data of a transaction split in chunks, with the merkle root and proofs
+/// needed to upload each chunk.
+struct ChunkedData {
+    data_root: Vec<u8>,
+    chunks: Vec<(usize, usize)>,
+    proofs: Vec<(Vec<u8>, usize)>,
+}
+
+impl ChunkedData {
+    /// Split the data in chunks, following the chunking of the reference
+    /// Arweave client so the data root matches the one computed by nodes.
+    fn new(data: &[u8]) -> ChunkedData {
+        let mut chunks = Vec::new();
+        let mut cursor = 0;
+
+        while data.len() - cursor >= MAX_CHUNK_SIZE {
+            let rest = data.len() - cursor;
+            let mut chunk_size = MAX_CHUNK_SIZE;
+            // avoids a last chunk smaller than the minimum size
+            if rest - MAX_CHUNK_SIZE > 0 && rest - MAX_CHUNK_SIZE < MIN_CHUNK_SIZE {
+                chunk_size = (rest + 1) / 2;
+            }
+            chunks.push((cursor, cursor + chunk_size));
+            cursor += chunk_size;
+        }
+        chunks.push((cursor, data.len()));
+
+        let mut layer: Vec<MerkleNode> = chunks
+            .iter()
+            .map(|(start, end)| {
+                let data_hash = sha256(&[&data[*start..*end]]);
+                MerkleNode::Leaf {
+                    id: sha256(&[&sha256(&[&data_hash]), &sha256(&[&note(*end)])]),
+                    data_hash,
+                    max_byte_range: *end,
+                }
+            })
+            .collect();
+
+        while layer.len() > 1 {
+            let mut nodes = layer.into_iter();
+            let mut next = Vec::new();
+
+            while let Some(left) = nodes.next() {
+                match nodes.next() {
+                    Some(right) => next.push(MerkleNode::Branch {
+                        id: sha256(&[
+                            &sha256(&[left.id()]),
+                            &sha256(&[right.id()]),
+                            &sha256(&[&note(left.max_byte_range())]),
+                        ]),
+                        byte_range: left.max_byte_range(),
+                        max_byte_range: right.max_byte_range(),
+                        left: Box::new(left),
+                        right: Box::new(right),
+                    }),
+                    None => next.push(left),
+                }
+            }
+
+            layer = next;
+        }
+
+        let root = layer.pop().unwrap();
+        let mut proofs = Vec::new();
+        root.proofs(Vec::new(), &mut proofs);
+
+        // an empty last chunk (data size multiple of the chunk size) is part
+        // of the data root, but it is not uploaded
+        if let Some((start, end)) = chunks.last() {
+            if start == end && chunks.len() > 1 {
+                chunks.pop();
+                proofs.pop();
+            }
+        }
+
+        ChunkedData {
+            data_root: root.id().to_vec(),
+            chunks,
+            proofs,
+        }
+    }
+}
+
+struct ArweaveInfo {
+    asset_id: String,
+    file_path: String,
+    image_link: String,
+    data_type: DataType,
+    tags: Vec<(String, String)>,
+    animation_link: Option<String>,
+    preview_link: Option<String>,
+    template_variables: TemplateVariables,
+    locales: BTreeMap<String, String>,
+}
+
+pub struct ArweaveNativeHandler {
+    client: Arc<HttpClient>,
+    wallet: Arc<ArweaveWallet>,
+    gateway: Arc<String>,
+}
+
+impl ArweaveNativeHandler {
+    /// Initialize a new ArweaveNativeHandler.
+    pub async fn initialize(config_data: &ConfigData) -> Result<ArweaveNativeHandler> {
+        let wallet = match &config_data.arweave_jwk {
+            Some(path) => ArweaveWallet::load(path)?,
+            None => return Err(anyhow!("Missing 'arweaveJwk' value in config file.")),
+        };
+
+        Ok(ArweaveNativeHandler {
+            client: Arc::new(http_client()),
+            wallet: Arc::new(wallet),
+            gateway: Arc::new(
+                config_data
+                    .arweave_gateway
+                    .as_deref()
+                    .unwrap_or(ARWEAVE_GATEWAY)
+                    .trim_end_matches('/')
+                    .to_string(),
+            ),
+        })
+    }
+
+    /// Return the result of a GET request to the gateway as text.
+    async fn get_text(client: &HttpClient, url: &str) -> Result<String> {
+        retry_async(Subsystem::Rpc, || async {
+            Ok(client
+                .get(url)
+                .send()
+                .await?
+                .error_for_status()?
+                .text()
+                .await?)
+        })
+        .await
+    }
+
+    /// Return the fee (in winston) to store the specified number of bytes.
+    async fn get_price(client: &HttpClient, gateway: &str, data_size: usize) -> Result<u64> {
+        let price =
+            ArweaveNativeHandler::get_text(client, &format!("{}/price/{}", gateway, data_size))
+                .await?;
+        Ok(price.trim().parse::<u64>()?)
+    }
+
+    /// Return the fee (in winston) to upload files of the specified sizes, one
+    /// transaction per file.
+    async fn get_fee(&self, file_sizes: &[u64]) -> Result<u64> {
+        if file_sizes.is_empty() {
+            return Ok(0);
+        }
+
+        // the fee of each transaction has a base cost on top of the data size
+        let total_size = file_sizes.iter().sum::<u64>() as usize;
+        let base_fee = ArweaveNativeHandler::get_price(&self.client, &self.gateway, 0).await?;
+
+        Ok(
+            ArweaveNativeHandler::get_price(&self.client, &self.gateway, total_size).await?
+                + base_fee * (file_sizes.len() as u64 - 1),
+        )
+    }
+
+    /// Return the balance (in winston) of the wallet.
+    async fn get_balance(&self) -> Result<u64> {
+        let url = format!("{}/wallet/{}/balance", self.gateway, self.wallet.address());
+        let balance = ArweaveNativeHandler::get_text(&self.client, &url).await?;
+        Ok(balance.trim().parse::<u64>()?)
+    }
+
+    /// Upload a file to Arweave and wait for its confirmation.
+    async fn send_to_arweave(
+        client: Arc<HttpClient>,
+        wallet: Arc<ArweaveWallet>,
+        gateway: Arc<String>,
+        info: ArweaveInfo,
+    ) -> Result<(String, String)> {
+        let data = match info.data_type {
+            DataType::Image => fs::read(&info.file_path)?,
+            DataType::Metadata => {
+                // replaces the image link without modifying the original file to avoid
+                // changing the hash of the metadata file
+                let metadata = get_updated_metadata(
+                    &info.file_path,
+                    &info.image_link,
+                    info.animation_link.clone(),
+                    info.preview_link.clone(),
+                    &info.template_variables,
+                )?;
+
+                if info.locales.is_empty() {
+                    metadata.into_bytes()
+                } else {
+                    // the localized variants are uploaded first so that their
+                    // links can be recorded in the metadata
+                    let mut localization = BTreeMap::new();
+
+                    for (locale, file_path) in &info.locales {
+                        let variant = get_updated_metadata(
+                            file_path,
+                            &info.image_link,
+                            info.animation_link.clone(),
+                            info.preview_link.clone(),
+                            &info.template_variables,
+                        )?;
+                        let id = ArweaveNativeHandler::send_transaction(
+                            &client,
+                            &wallet,
+                            &gateway,
+                            variant.into_bytes(),
+                            &info.tags,
+                        )
+                        .await?;
+                        localization.insert(locale.clone(), format!("{}/{}", gateway, id));
+                    }
+
+                    set_localization(&metadata, localization)?.into_bytes()
+                }
+            }
+            DataType::Animation | DataType::Preview => fs::read(&info.file_path)?,
+        };
+
+        let id =
+            ArweaveNativeHandler::send_transaction(&client, &wallet, &gateway, data, &info.tags)
+                .await?;
+
+        Ok((info.asset_id, format!("{}/{}", gateway, id)))
+    }
+
+    /// Sign and post a transaction with the data, returning its id once the
+    /// transaction is confirmed.
+    async fn send_transaction(
+        client: &HttpClient,
+        wallet: &ArweaveWallet,
+        gateway: &str,
+        data: Vec<u8>,
+        tags: &[(String, String)],
+    ) -> Result<String> {
+        let chunked = ChunkedData::new(&data);
+        let last_tx = ArweaveNativeHandler::get_text(client, &format!("{}/tx_anchor", gateway))
+            .await?
+            .trim()
+            .to_string();
+        let reward = ArweaveNativeHandler::get_price(client, gateway, data.len())
+            .await?
+            .to_string();
+        let data_size = data.len().to_string();
+
+        let last_tx_bytes = BASE64URL_NOPAD
+            .decode(last_tx.as_bytes())
+            .map_err(|err| anyhow!("Invalid transaction anchor: {}", err))?;
+        let tag_items = tags
+            .iter()
+            .map(|(name, value)| {
+                DeepHashItem::List(vec![
+                    DeepHashItem::Blob(name.as_bytes()),
+                    DeepHashItem::Blob(value.as_bytes()),
+                ])
+            })
+            .collect();
+
+        let signature_data = deep_hash(&DeepHashItem::List(vec![
+            DeepHashItem::Blob(b"2"),
+            DeepHashItem::Blob(&wallet.owner),
+            DeepHashItem::Blob(&[]),
+            DeepHashItem::Blob(b"0"),
+            DeepHashItem::Blob(reward.as_bytes()),
+            DeepHashItem::Blob(&last_tx_bytes),
+            DeepHashItem::List(tag_items),
+            DeepHashItem::Blob(data_size.as_bytes()),
+            DeepHashItem::Blob(&chunked.data_root),
+        ]));
+
+        let signature = wallet.sign(&signature_data)?;
+        let id = BASE64URL_NOPAD.encode(&sha256(&[&signature]));
+
+        // small files are sent with the transaction, larger ones in chunks
+        let inline = chunked.chunks.len() == 1;
+
+        let transaction = json!({
+            "format": 2,
+            "id": id,
+            "last_tx": last_tx,
+            "owner": BASE64URL_NOPAD.encode(&wallet.owner),
+            "tags": tags
+                .iter()
+                .map(|(name, value)| json!({
+                    "name": BASE64URL_NOPAD.encode(name.as_bytes()),
+                    "value": BASE64URL_NOPAD.encode(value.as_bytes()),
+                }))
+                .collect::<Vec<Value>>(),
+            "target": "",
+            "quantity": "0",
+            "data": if inline { BASE64URL_NOPAD.encode(&data) } else { String::new() },
+            "data_size": data_size,
+            "data_root": BASE64URL_NOPAD.encode(&chunked.data_root),
+            "reward": reward,
+            "signature": BASE64URL_NOPAD.encode(&signature),
+        });
+
+        ArweaveNativeHandler::post(client, &format!("{}/tx", gateway), &transaction).await?;
+
+        if !inline {
+            for ((start, end), (proof, offset)) in chunked.chunks.iter().zip(&chunked.proofs) {
+                let chunk = json!({
+                    "data_root": BASE64URL_NOPAD.encode(&chunked.data_root),
+                    "data_size": data_size,
+                    "data_path": BASE64URL_NOPAD.encode(proof),
+                    "offset": offset.to_string(),
+                    "chunk": BASE64URL_NOPAD.encode(&data[*start..*end]),
+                });
+                ArweaveNativeHandler::post(client, &format!("{}/chunk", gateway), &chunk).await?;
+            }
+        }
+
+        ArweaveNativeHandler::wait_for_confirmation(client, gateway, &id).await?;
+
+        Ok(id)
+    }
+
+    /// Post a JSON body to the gateway.
+    async fn post(client: &HttpClient, url: &str, body: &Value) -> Result<()> {
+        retry_async(Subsystem::Upload, || async {
+            let response = client.post(url).json(body).send().await?;
+            let status = response.status();
+
+            // 208: the transaction was already received by the gateway
+            if status.is_success() {
+                Ok(())
+            } else {
+                Err(anyhow!(
+                    "Arweave gateway returned {}: {}",
+                    status,
+                    response.text().await.unwrap_or_default()
+                ))
+            }
+        })
+        .await
+    }
+
+    /// Poll the gateway until the transaction is included in a block.
+    async fn wait_for_confirmation(client: &HttpClient, gateway: &str, id: &str) -> Result<()> {
+        let url = format!("{}/tx/{}/status", gateway, id);
+
+        for _ in 0..CONFIRMATION_ATTEMPTS {
+            // pending (202) and not yet propagated (404) transactions are
+            // checked again after the interval
+            if let Ok(response) = client.get(&url).send().await {
+                if response.status() == StatusCode::OK {
+                    return Ok(());
+                }
+            }
+
+            sleep(CONFIRMATION_INTERVAL).await;
+        }
+
+        Err(anyhow!(
+            "Arweave transaction {} was not confirmed in time, check its status at {}",
+            id,
+            url
+        ))
+    }
+}
+
+#[async_trait]
+impl UploadHandler for ArweaveNativeHandler {
+    /// Check that the wallet balance covers the fees of the upload.
+    async fn prepare(
+        &self,
+        _sugar_config: &SugarConfig,
+        assets: &HashMap<usize, AssetPair>,
+        image_indices: &[usize],
+        metadata_indices: &[usize],
+        animation_indices: &[usize],
+        preview_indices: &[usize],
+        _dry_run: bool,
+    ) -> Result<()> {
+        let mut file_sizes = Vec::new();
+
+        for (indices, data_type) in [
+            (image_indices, DataType::Image),
+            (animation_indices, DataType::Animation),
+            (preview_indices, DataType::Preview),
+            (metadata_indices, DataType::Metadata),
+        ] {
+            file_sizes.extend(upload_file_sizes(assets, indices, &data_type)?);
+        }
+
+        if file_sizes.is_empty() {
+            return Ok(());
+        }
+
+        let required = self.get_fee(&file_sizes).await?;
+        let balance = self.get_balance().await?;
+
+        info!(
+            "Arweave balance {} winston, require {} winston",
+            balance, required
+        );
+
+        if balance < required {
+            let error = UploadError::NoArweaveBalance(
+                self.wallet.address(),
+                format!("{:.6}", balance as f64 / WINSTON_PER_AR),
+                format!("{:.6}", required as f64 / WINSTON_PER_AR),
+            )
+            .into();
+            error!("{error}");
+            return Err(error);
+        }
+
+        Ok(())
+    }
+
+    /// Return the fee to upload files of the specified sizes.
+    async fn estimate_cost(&self, file_sizes: &[u64]) -> Result<Option<UploadCost>> {
+        Ok(Some(UploadCost::Winston(self.get_fee(file_sizes).await?)))
+    }
+
+    /// Upload the data to Arweave.
+    async fn upload_data(
+        &self,
+        _sugar_config: &SugarConfig,
+        assets: &HashMap<usize, AssetPair>,
+        cache: &mut Cache,
+        indices: &[usize],
+        data_type: DataType,
+        interrupted: Arc<AtomicBool>,
+    ) -> Result<Vec<UploadError>> {
+        let mut paths = Vec::new();
+
+        for index in indices {
+            let item = assets.get(index).unwrap();
+            // chooses the file path based on the data type
+            let file_path = match data_type {
+                DataType::Image => item.image.clone(),
+                DataType::Metadata => item.metadata.clone(),
+                DataType::Animation => item.animation.clone().unwrap(),
+                DataType::Preview => item.preview.clone().unwrap(),
+            };
+
+            let locales = match data_type {
+                DataType::Metadata => item.locales.clone(),
+                _ => BTreeMap::new(),
+            };
+
+            paths.push((file_path, locales));
+        }
+
+        println!("\nSending data: (Ctrl+C to abort)");
+
+        let pb = progress_bar_with_style(paths.len() as u64);
+        let mut transactions = Vec::new();
+
+        for (file_path, locales) in paths {
+            // path to the image/metadata file
+            let path = Path::new(&file_path);
+            // id of the asset (to be used to update the cache link)
+            let asset_id = String::from(
+                path.file_stem()
+                    .and_then(OsStr::to_str)
+                    .expect("Failed to get convert path file ext to valid unicode."),
+            );
+            let cache_item = match cache.items.0.get(&asset_id) {
+                Some(item) => item,
+                None => {
+                    return Err(anyhow::anyhow!(
+                        "Failed to get config item at index: {}",
+                        asset_id
+                    ))
+                }
+            };
+
+            let tags = vec![
+                (
+                    "App-Name".to_string(),
+                    format!("Sugar {}", crate_version!()),
+                ),
+                (
+                    "Content-Type".to_string(),
+                    content_type(&file_path, &data_type)?,
+                ),
+            ];
+
+            transactions.push(ArweaveInfo {
+                asset_id: asset_id.to_string(),
+                file_path: String::from(
+                    path.to_str().expect("Failed to convert path from unicode."),
+                ),
+                image_link: cache_item.image_link.clone(),
+                data_type: data_type.clone(),
+                tags,
+                animation_link: cache_item.animation_link.clone(),
+                preview_link: cache_item.preview_link.clone(),
+                template_variables: cache.program.template_variables.clone(),
+                locales,
+            });
+        }
+
+        let mut errors = Vec::new();
+        let (client, wallet, gateway) = (
+            self.client.clone(),
+            self.wallet.clone(),
+            self.gateway.clone(),
+        );
+        let transactions = transactions
+            .into_iter()
+            .map(|tx| (tx.asset_id.clone(), tx))
+            .collect();
+
+        let not_started = run_bounded(
+            transactions,
+            upload_concurrency(PARALLEL_LIMIT),
+            &interrupted,
+            |tx| {
+                ArweaveNativeHandler::send_to_arweave(
+                    client.clone(),
+                    wallet.clone(),
+                    gateway.clone(),
+                    tx,
+                )
+            },
+            |asset_id, result| {
+                match result {
+                    Ok((_, link)) => {
+                        // cache item to update
+                        let item = cache.get_item_mut(&asset_id).unwrap();
+
+                        match data_type {
+                            DataType::Image => item.image_link = link.clone(),
+                            DataType::Metadata => item.metadata_link = link.clone(),
+                            DataType::Animation => item.animation_link = Some(link.clone()),
+                            DataType::Preview => item.preview_link = Some(link.clone()),
+                        }
+                        // updates the progress bar
+                        pb.inc(1);
+                        upload_event(
+                            data_type.stage(),
+                            &asset_id,
+                            &link,
+                            pb.position(),
+                            pb.length(),
+                        );
+                    }
+                    Err(err) => {
+                        // user will need to retry the upload
+                        let error = format!("Arweave upload error: {:?}", err);
+                        error_event(
+                            data_type.stage(),
+                            Some(&asset_id),
+                            pb.position(),
+                            pb.length(),
+                            &error,
+                        );
+                        cache.record_error(&asset_id, &error);
+                        errors.push(UploadError::SendDataFailed(error));
+                    }
+                }
+
+                // syncs cache (checkpoint)
+                cache.checkpoint()
+            },
+        )
+        .await?;
+
+        if !errors.is_empty() {
+            pb.abandon_with_message(format!("{}", style("Upload failed ").red().bold()));
+        } else if not_started > 0 {
+            pb.abandon_with_message(format!("{}", style("Upload aborted ").red().bold()));
+            return Err(
+                UploadError::SendDataFailed("Not all files were uploaded.".to_string()).into(),
+            );
+        } else {
+            pb.finish_with_message(format!("{}", style("Upload successful ").green().bold()));
+        }
+
+        // makes sure the cache file is updated
+        cache.sync_file()?;
+
+        Ok(errors)
+    }
+}
@@ -2,16 +2,20 @@ use bundlr_sdk::{tags::Tag, Bundlr, SolanaSigner};
 use data_encoding::HEXLOWER;
 use glob::glob;
 use regex::{Regex, RegexBuilder};
-use ring::digest::{Context, SHA256};
+use ring::digest::{digest, Context, SHA256};
 use serde::Serialize;
 use serde_json;
 use std::{
-    fs::{self, DirEntry, File, OpenOptions},
+    collections::BTreeMap,
+    fs::{self, DirEntry, File},
     io::{BufReader, Read},
     sync::Arc,
 };
 
 use crate::common::*;
+use crate::config::get_config_data;
+use crate::upload::template::{expand_template, TemplateVariables};
+use crate::upload::{generate_previews, optimize_images};
 use crate::validate::format::Metadata;
 
 pub struct UploadDataArgs<'a> {
@@ -27,6 +31,30 @@ pub enum DataType {
     Image,
     Metadata,
     Animation,
+    Preview,
+}
+
+impl DataType {
+    /// Name of the progress events stage for the data type.
+    pub fn stage(&self) -> &'static str {
+        match self {
+            DataType::Image => "upload:image",
+            DataType::Metadata => "upload:metadata",
+            DataType::Animation => "upload:animation",
+            DataType::Preview => "upload:preview",
+        }
+    }
+}
+
+/// Collection manifest data item, grouping the uploads of a collection.
+#[derive(Debug, Clone, Serialize)]
+pub struct CollectionManifestData {
+    pub name: String,
+    pub symbol: String,
+    pub count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub banner: Option<String>,
+    pub items: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -38,6 +66,11 @@ pub struct AssetPair {
     pub image_hash: String,
     pub animation: Option<String>,
     pub animation_hash: Option<String>,
+    /// Downscaled preview of the image, see `generate_previews`.
+    pub preview: Option<String>,
+    pub preview_hash: Option<String>,
+    /// Localized metadata files, keyed by locale.
+    pub locales: BTreeMap<String, String>,
 }
 
 impl AssetPair {
@@ -50,7 +83,14 @@ impl AssetPair {
             metadata_link: String::new(),
             on_chain: false,
             animation_hash: self.animation_hash,
-            animation_link: self.animation,
+            animation_link: self.animation.map(|_| String::new()),
+            preview_hash: self.preview_hash,
+            preview_link: self.preview.map(|_| String::new()),
+            pending_upload: None,
+            status: ItemStatus::Pending,
+            uploaded_at: None,
+            on_chain_at: None,
+            last_error: None,
         }
     }
 }
@@ -75,6 +115,62 @@ pub fn get_data_size(assets_dir: &Path, extension: &str) -> Result<u64> {
     Ok(total_size)
 }
 
+/// Size of the mock URIs used to estimate the size of metadata files.
+const MOCK_URI_SIZE: usize = 100;
+
+/// Return the size of each file to upload for the assets and data type.
+/// Metadata sizes are estimates, since the links they record are only known
+/// after the images are uploaded; localized variants count as separate files.
+pub fn upload_file_sizes(
+    assets: &HashMap<usize, AssetPair>,
+    indices: &[usize],
+    data_type: &DataType,
+) -> Result<Vec<u64>> {
+    let mock_uri = "x".repeat(MOCK_URI_SIZE);
+    let mut sizes = Vec::with_capacity(indices.len());
+
+    for index in indices {
+        let item = assets
+            .get(index)
+            .ok_or_else(|| anyhow!("Failed to get asset at index {}", index))?;
+
+        match data_type {
+            DataType::Image => sizes.push(fs::metadata(&item.image)?.len()),
+            DataType::Animation => {
+                if let Some(animation) = &item.animation {
+                    sizes.push(fs::metadata(animation)?.len());
+                }
+            }
+            DataType::Preview => {
+                if let Some(preview) = &item.preview {
+                    sizes.push(fs::metadata(preview)?.len());
+                }
+            }
+            DataType::Metadata => {
+                let mock_animation_uri = item.animation.as_ref().map(|_| mock_uri.clone());
+                let mock_preview_uri = item.preview.as_ref().map(|_| mock_uri.clone());
+                // template placeholders are not expanded, the size is an estimate
+                let updated_metadata = get_updated_metadata(
+                    &item.metadata,
+                    &mock_uri,
+                    mock_animation_uri,
+                    mock_preview_uri,
+                    &TemplateVariables::new(),
+                )?;
+
+                // the metadata also records the links of its localized variants
+                sizes.push((updated_metadata.len() + item.locales.len() * MOCK_URI_SIZE) as u64);
+
+                for file_path in item.locales.values() {
+                    sizes.push(fs::metadata(file_path)?.len() + MOCK_URI_SIZE as u64);
+                }
+            }
+        }
+    }
+
+    Ok(sizes)
+}
+
 pub fn list_files(assets_dir: &str) -> Result<Vec<DirEntry>> {
     let files = fs::read_dir(assets_dir)
         .map_err(|_| anyhow!("Failed to read assets directory"))?
@@ -94,6 +190,28 @@ pub fn list_files(assets_dir: &str) -> Result<Vec<DirEntry>> {
     Ok(files.collect())
 }
 
+/// Return the asset pairs with the files that are uploaded, i.e., the
+/// optimized images and previews when the config file enables them.
+pub fn get_uploaded_asset_pairs(
+    assets_dir: &str,
+    config: &str,
+) -> Result<HashMap<usize, AssetPair>> {
+    let mut asset_pairs = get_asset_pairs(assets_dir)?;
+
+    if Path::new(config).exists() {
+        let config_data = get_config_data(config)?;
+
+        if let Some(settings) = &config_data.optimize {
+            optimize_images(&mut asset_pairs, settings)?;
+        }
+        if let Some(settings) = &config_data.previews {
+            generate_previews(&mut asset_pairs, settings)?;
+        }
+    }
+
+    Ok(asset_pairs)
+}
+
 pub fn get_asset_pairs(assets_dir: &str) -> Result<HashMap<usize, AssetPair>> {
     // filters out directories and hidden files
     let filtered_files = list_files(assets_dir)?;
@@ -125,10 +243,29 @@ pub fn get_asset_pairs(assets_dir: &str) -> Result<HashMap<usize, AssetPair>> {
         }
     }
 
+    // localized variants (e.g., '1.ja.json') are uploaded together with the
+    // metadata file of the same index
+    let mut locales: HashMap<usize, BTreeMap<String, String>> = HashMap::new();
+
+    for x in paths_ref {
+        if let Some((index, locale)) = parse_locale_filename(x) {
+            let locale_filepath = Path::new(assets_dir)
+                .join(x)
+                .to_str()
+                .expect("Failed to convert metadata path from unicode.")
+                .to_string();
+
+            locales
+                .entry(index)
+                .or_default()
+                .insert(locale, locale_filepath);
+        }
+    }
+
     let metadata_filenames = paths_ref
         .clone()
         .into_iter()
-        .filter(|p| p.to_lowercase().ends_with(".json"))
+        .filter(|p| p.to_lowercase().ends_with(".json") && parse_locale_filename(p).is_none())
         .collect::<Vec<String>>();
 
     for metadata_filename in metadata_filenames {
@@ -224,14 +361,58 @@ pub fn get_asset_pairs(assets_dir: &str) -> Result<HashMap<usize, AssetPair>> {
             image_hash: encode(&img_filepath)?,
             animation_hash,
             animation: animation_filename,
+            preview: None,
+            preview_hash: None,
+            locales: BTreeMap::new(),
         };
 
         asset_pairs.insert(i.parse::<usize>().unwrap(), asset_pair);
     }
 
+    for (index, variants) in locales {
+        let pair = match asset_pairs.get_mut(&index) {
+            Some(pair) => pair,
+            None => {
+                let error = anyhow!(
+                    "Localized metadata file(s) {:?} without a metadata file at index {}",
+                    variants.values().collect::<Vec<_>>(),
+                    index
+                );
+                error!("{:?}", error);
+                return Err(error);
+            }
+        };
+
+        // the variants are part of the metadata hash, so changing a variant
+        // triggers the metadata upload
+        let mut hashes = pair.metadata_hash.clone();
+        for path in variants.values() {
+            hashes.push_str(&encode(path)?);
+        }
+
+        pair.metadata_hash = hash_data(hashes.as_bytes());
+        pair.locales = variants;
+    }
+
     Ok(asset_pairs)
 }
 
+/// Return the index and locale of a localized metadata file name (e.g.,
+/// '1.ja.json' or '1.pt-BR.json'), or `None` if it is not a localized variant.
+pub fn parse_locale_filename(file_name: &str) -> Option<(usize, String)> {
+    let locale_regex = RegexBuilder::new("^([0-9]+)\\.([a-z]{2,3}(-[a-z0-9]{2,8})*)\\.json$")
+        .case_insensitive(true)
+        .build()
+        .expect("Failed to create regex.");
+
+    locale_regex.captures(file_name).and_then(|captures| {
+        captures[1]
+            .parse::<usize>()
+            .ok()
+            .map(|index| (index, captures[2].to_string()))
+    })
+}
+
 fn encode(file: &str) -> Result<String> {
     let input = File::open(file)?;
     let mut reader = BufReader::new(input);
@@ -249,19 +430,41 @@ fn encode(file: &str) -> Result<String> {
     Ok(HEXLOWER.encode(context.finish().as_ref()))
 }
 
+/// Return the SHA-256 hash (hex encoded) of the data, in the same format used
+/// for the file hashes in the cache.
+pub fn hash_data(data: &[u8]) -> String {
+    HEXLOWER.encode(digest(&SHA256, data).as_ref())
+}
+
+/// Return the SHA-256 hash (hex encoded) of a file.
+pub fn hash_file(file: &str) -> Result<String> {
+    encode(file)
+}
+
+/// Return the metadata with the links of its files. With a preview, the
+/// preview is the image of the metadata and the full-resolution image is
+/// only linked from `properties.files`.
 pub fn get_updated_metadata(
     metadata_file: &str,
     image_link: &str,
     animation_link: Option<String>,
+    preview_link: Option<String>,
+    variables: &TemplateVariables,
 ) -> Result<String> {
     let mut metadata: Metadata = {
-        let m = OpenOptions::new()
-            .read(true)
-            .open(metadata_file)
-            .map_err(|e| {
-                anyhow!("Failed to read metadata file '{metadata_file}' with error: {e}")
-            })?;
-        serde_json::from_reader(&m)?
+        let content = fs::read_to_string(metadata_file).map_err(|e| {
+            anyhow!("Failed to read metadata file '{metadata_file}' with error: {e}")
+        })?;
+        // the index of the asset is the name of the metadata file (without
+        // the locale of localized variants)
+        let index = Path::new(metadata_file)
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.split('.').next())
+            .and_then(|stem| stem.parse::<usize>().ok())
+            .unwrap_or_default();
+
+        serde_json::from_str(&expand_template(&content, index, variables))?
     };
 
     for file in &mut metadata.properties.files {
@@ -277,8 +480,17 @@ pub fn get_updated_metadata(
         }
     }
 
-    metadata.image = image_link.to_string();
+    metadata.image = preview_link.unwrap_or_else(|| image_link.to_string());
     metadata.animation_url = animation_link;
 
     Ok(serde_json::to_string(&metadata).unwrap())
 }
+
+/// Record the URIs of the localized variants in the properties of the
+/// metadata.
+pub fn set_localization(metadata: &str, localization: BTreeMap<String, String>) -> Result<String> {
+    let mut metadata: Metadata = serde_json::from_str(metadata)?;
+    metadata.properties.localization = Some(localization);
+
+    Ok(serde_json::to_string(&metadata)?)
+}
@@ -1,20 +1,19 @@
 use async_trait::async_trait;
-use aws_sdk_s3::{types::ByteStream, Client};
+use aws_sdk_s3::{types::ByteStream, Client, Credentials, Region};
+use aws_types::{credentials::ProvideCredentials, SdkConfig};
 use bs58;
 use console::style;
-use futures::future::select_all;
 use std::{
-    cmp,
-    collections::HashSet,
+    collections::BTreeMap,
     ffi::OsStr,
     fs,
     path::Path,
-    sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc,
-    },
+    sync::{atomic::AtomicBool, Arc},
 };
 
+use crate::credentials::{AWS_ACCESS_KEY_ID, AWS_SECRET_ACCESS_KEY};
+use crate::events::{error_event, upload_event};
+use crate::retry::{retry_async, Subsystem};
 use crate::{common::*, config::*, constants::PARALLEL_LIMIT, upload::*, utils::*};
 
 struct ObjectInfo {
@@ -24,27 +23,86 @@ struct ObjectInfo {
     data_type: DataType,
     content_type: String,
     bucket: String,
+    prefix: Option<String>,
+    base_url: String,
     animation_link: Option<String>,
+    preview_link: Option<String>,
+    template_variables: TemplateVariables,
+    locales: BTreeMap<String, String>,
+}
+
+/// Check whether the AWS environment (environment variables, shared
+/// profiles, instance metadata) provides credentials.
+async fn has_credentials(shared_config: &SdkConfig) -> bool {
+    match shared_config.credentials_provider() {
+        Some(provider) => provider.provide_credentials().await.is_ok(),
+        None => false,
+    }
 }
 
 pub struct AWSHandler {
     client: Arc<Client>,
     bucket: String,
+    prefix: Option<String>,
+    base_url: String,
 }
 
 impl AWSHandler {
     /// Initialize a new AWSHandler.
     pub async fn initialize(config_data: &ConfigData) -> Result<AWSHandler> {
-        let shared_config = aws_config::load_from_env().await;
-        let client = Client::new(&shared_config);
-
-        if let Some(aws_s3_bucket) = &config_data.aws_s3_bucket {
-            Ok(AWSHandler {
-                client: Arc::new(client),
-                bucket: aws_s3_bucket.to_string(),
-            })
-        } else {
-            Err(anyhow!("Missing 'awsS3Bucket' value in config file."))
+        let bucket = match &config_data.aws_s3_bucket {
+            Some(aws_s3_bucket) => aws_s3_bucket.to_string(),
+            None => return Err(anyhow!("Missing 'awsS3Bucket' value in config file.")),
+        };
+
+        // the region in the config file takes precedence over the environment
+        let mut loader = aws_config::from_env();
+        if let Some(region) = &config_data.aws_region {
+            loader = loader.region(Region::new(region.to_string()));
+        }
+        let mut shared_config = loader.load().await;
+
+        // the access keys are prompted for if the AWS environment has no
+        // credentials
+        if !has_credentials(&shared_config).await {
+            let credentials = Credentials::new(
+                AWS_ACCESS_KEY_ID.prompt()?,
+                AWS_SECRET_ACCESS_KEY.prompt()?,
+                None,
+                None,
+                "sugar-prompt",
+            );
+            shared_config = aws_config::from_env()
+                .region(shared_config.region().cloned())
+                .credentials_provider(credentials)
+                .load()
+                .await;
+        }
+
+        let base_url = match shared_config.region() {
+            Some(region) => format!("https://{}.s3.{}.amazonaws.com", bucket, region),
+            None => format!("https://{}.s3.amazonaws.com", bucket),
+        };
+
+        Ok(AWSHandler {
+            client: Arc::new(Client::new(&shared_config)),
+            bucket,
+            prefix: config_data
+                .aws_key_prefix
+                .as_ref()
+                .map(|prefix| prefix.trim_matches('/').to_string())
+                .filter(|prefix| !prefix.is_empty()),
+            base_url,
+        })
+    }
+
+    /// Return the key of an object, under the key prefix if one is set.
+    fn object_key(prefix: &Option<String>, file_path: &str) -> String {
+        let key = bs58::encode(file_path).into_string();
+
+        match prefix {
+            Some(prefix) => format!("{}/{}", prefix, key),
+            None => key,
         }
     }
 
@@ -55,24 +113,65 @@ impl AWSHandler {
             DataType::Metadata => {
                 // replaces the image link without modifying the original file to avoid
                 // changing the hash of the metadata file
-                get_updated_metadata(&info.file_path, &info.image_link, info.animation_link)?
-                    .into_bytes()
+                let metadata = get_updated_metadata(
+                    &info.file_path,
+                    &info.image_link,
+                    info.animation_link.clone(),
+                    info.preview_link.clone(),
+                    &info.template_variables,
+                )?;
+
+                if info.locales.is_empty() {
+                    metadata.into_bytes()
+                } else {
+                    // the localized variants are uploaded first so that their
+                    // links can be recorded in the metadata
+                    let mut localization = BTreeMap::new();
+
+                    for (locale, file_path) in &info.locales {
+                        let variant = get_updated_metadata(
+                            file_path,
+                            &info.image_link,
+                            info.animation_link.clone(),
+                            info.preview_link.clone(),
+                            &info.template_variables,
+                        )?;
+                        let key = AWSHandler::object_key(&info.prefix, file_path);
+                        AWSHandler::put_object(&aws_client, &info, &key, variant.into_bytes())
+                            .await?;
+                        localization.insert(locale.clone(), format!("{}/{}", info.base_url, key));
+                    }
+
+                    set_localization(&metadata, localization)?.into_bytes()
+                }
             }
-            DataType::Animation => fs::read(&info.file_path)?,
+            DataType::Animation | DataType::Preview => fs::read(&info.file_path)?,
         };
 
-        let key = bs58::encode(&info.file_path).into_string();
+        let key = AWSHandler::object_key(&info.prefix, &info.file_path);
+        AWSHandler::put_object(&aws_client, &info, &key, data).await?;
 
-        aws_client
-            .put_object()
-            .bucket(info.bucket)
-            .key(&key)
-            .body(ByteStream::from(data))
-            .content_type(info.content_type)
-            .send()
-            .await?;
+        Ok((info.asset_id, format!("{}/{}", info.base_url, key)))
+    }
 
-        Ok((info.asset_id, key))
+    async fn put_object(
+        aws_client: &Client,
+        info: &ObjectInfo,
+        key: &str,
+        data: Vec<u8>,
+    ) -> Result<()> {
+        retry_async(Subsystem::Upload, || async {
+            aws_client
+                .put_object()
+                .bucket(&info.bucket)
+                .key(key)
+                .body(ByteStream::from(data.clone()))
+                .content_type(&info.content_type)
+                .send()
+                .await?;
+            Ok(())
+        })
+        .await
     }
 }
 
@@ -86,6 +185,8 @@ impl UploadHandler for AWSHandler {
         _image_indices: &[usize],
         _metadata_indices: &[usize],
         _animation_indices: &[usize],
+        _preview_indices: &[usize],
+        _dry_run: bool,
     ) -> Result<()> {
         Ok(())
     }
@@ -100,7 +201,6 @@ impl UploadHandler for AWSHandler {
         data_type: DataType,
         interrupted: Arc<AtomicBool>,
     ) -> Result<Vec<UploadError>> {
-        let mut extension = HashSet::with_capacity(1);
         let mut paths = Vec::new();
 
         for index in indices {
@@ -110,37 +210,23 @@ impl UploadHandler for AWSHandler {
                 DataType::Image => item.image.clone(),
                 DataType::Metadata => item.metadata.clone(),
                 DataType::Animation => item.animation.clone().unwrap(),
+                DataType::Preview => item.preview.clone().unwrap(),
             };
 
-            let path = Path::new(&file_path);
-            let ext = path
-                .extension()
-                .and_then(OsStr::to_str)
-                .expect("Failed to convert path extension to valid unicode.");
-            extension.insert(String::from(ext));
+            let locales = match data_type {
+                DataType::Metadata => item.locales.clone(),
+                _ => BTreeMap::new(),
+            };
 
-            paths.push(file_path);
+            paths.push((file_path, locales));
         }
 
-        // validates that all files have the same extension
-        let extension = if extension.len() == 1 {
-            extension.iter().next().unwrap()
-        } else {
-            return Err(anyhow!("Invalid file extension: {:?}", extension));
-        };
-
-        let content_type = match data_type {
-            DataType::Image => format!("image/{}", extension),
-            DataType::Metadata => "application/json".to_string(),
-            DataType::Animation => format!("video/{}", extension),
-        };
-
         println!("\nSending data: (Ctrl+C to abort)");
 
         let pb = progress_bar_with_style(paths.len() as u64);
         let mut objects = Vec::new();
 
-        for file_path in paths {
+        for (file_path, locales) in paths {
             // path to the image/metadata file
             let path = Path::new(&file_path);
             // id of the asset (to be used to update the cache link)
@@ -166,80 +252,75 @@ impl UploadHandler for AWSHandler {
                 ),
                 image_link: cache_item.image_link.clone(),
                 data_type: data_type.clone(),
-                content_type: content_type.clone(),
+                content_type: content_type(&file_path, &data_type)?,
                 bucket: self.bucket.clone(),
+                prefix: self.prefix.clone(),
+                base_url: self.base_url.clone(),
                 animation_link: cache_item.animation_link.clone(),
+                preview_link: cache_item.preview_link.clone(),
+                template_variables: cache.program.template_variables.clone(),
+                locales,
             });
         }
 
-        let mut handles = Vec::new();
-
-        for object in objects.drain(0..cmp::min(objects.len(), PARALLEL_LIMIT)) {
-            let aws_client = self.client.clone();
-            handles.push(tokio::spawn(async move {
-                AWSHandler::send_to_aws(aws_client, object).await
-            }));
-        }
-
         let mut errors = Vec::new();
+        let aws_client = self.client.clone();
+        let objects = objects
+            .into_iter()
+            .map(|object| (object.asset_id.clone(), object))
+            .collect();
 
-        while !interrupted.load(Ordering::SeqCst) && !handles.is_empty() {
-            match select_all(handles).await {
-                (Ok(res), _index, remaining) => {
-                    // independently if the upload was successful or not
-                    // we continue to try the remaining ones
-                    handles = remaining;
-
-                    if res.is_ok() {
-                        let val = res?;
-                        let link = format!("https://{}.s3.amazonaws.com/{}", self.bucket, val.1);
+        let not_started = run_bounded(
+            objects,
+            upload_concurrency(PARALLEL_LIMIT),
+            &interrupted,
+            |object| AWSHandler::send_to_aws(aws_client.clone(), object),
+            |asset_id, result| {
+                match result {
+                    Ok((_, link)) => {
                         // cache item to update
-                        let item = cache.items.0.get_mut(&val.0).unwrap();
+                        let item = cache.get_item_mut(&asset_id).unwrap();
 
                         match data_type {
-                            DataType::Image => item.image_link = link,
-                            DataType::Metadata => item.metadata_link = link,
-                            DataType::Animation => item.animation_link = Some(link),
+                            DataType::Image => item.image_link = link.clone(),
+                            DataType::Metadata => item.metadata_link = link.clone(),
+                            DataType::Animation => item.animation_link = Some(link.clone()),
+                            DataType::Preview => item.preview_link = Some(link.clone()),
                         }
                         // updates the progress bar
                         pb.inc(1);
-                    } else {
+                        upload_event(
+                            data_type.stage(),
+                            &asset_id,
+                            &link,
+                            pb.position(),
+                            pb.length(),
+                        );
+                    }
+                    Err(err) => {
                         // user will need to retry the upload
-                        errors.push(UploadError::SendDataFailed(format!(
-                            "AWS upload error: {:?}",
-                            res.err().unwrap()
-                        )));
+                        let error = format!("AWS upload error: {:?}", err);
+                        error_event(
+                            data_type.stage(),
+                            Some(&asset_id),
+                            pb.position(),
+                            pb.length(),
+                            &error,
+                        );
+                        cache.record_error(&asset_id, &error);
+                        errors.push(UploadError::SendDataFailed(error));
                     }
                 }
-                (Err(err), _index, remaining) => {
-                    errors.push(UploadError::SendDataFailed(format!(
-                        "AWS upload error: {:?}",
-                        err
-                    )));
-                    // ignoring all errors
-                    handles = remaining;
-                }
-            }
 
-            if !objects.is_empty() {
-                // if we are half way through, let spawn more transactions
-                if (PARALLEL_LIMIT - handles.len()) > (PARALLEL_LIMIT / 2) {
-                    // syncs cache (checkpoint)
-                    cache.sync_file()?;
-
-                    for object in objects.drain(0..cmp::min(objects.len(), PARALLEL_LIMIT / 2)) {
-                        let aws_client = self.client.clone();
-                        handles.push(tokio::spawn(async move {
-                            AWSHandler::send_to_aws(aws_client, object).await
-                        }));
-                    }
-                }
-            }
-        }
+                // syncs cache (checkpoint)
+                cache.checkpoint()
+            },
+        )
+        .await?;
 
         if !errors.is_empty() {
             pb.abandon_with_message(format!("{}", style("Upload failed ").red().bold()));
-        } else if !objects.is_empty() {
+        } else if not_started > 0 {
             pb.abandon_with_message(format!("{}", style("Upload aborted ").red().bold()));
             return Err(
                 UploadError::SendDataFailed("Not all files were uploaded.".to_string()).into(),
@@ -1,47 +1,60 @@
 pub use anchor_client::solana_sdk::native_token::LAMPORTS_PER_SOL;
 use async_trait::async_trait;
-use bundlr_sdk::{tags::Tag, Bundlr, SolanaSigner};
+use bundlr_sdk::{error::BundlrError, tags::Tag, Bundlr, SolanaSigner};
 use clap::crate_version;
 use console::style;
-use futures::future::select_all;
+use reqwest::StatusCode;
 use std::{
     cmp,
-    collections::HashSet,
+    collections::{BTreeMap, HashSet},
     ffi::OsStr,
     fs,
+    io::{Read, Seek, SeekFrom},
     path::Path,
-    sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc,
-    },
+    sync::{atomic::AtomicBool, Arc},
 };
-use tokio::time::{sleep, Duration};
+use tokio::time::sleep;
+use url::Url;
 
+use crate::cache::PendingUpload;
 use crate::candy_machine::ID as CANDY_MACHINE_ID;
+use crate::events::{error_event, upload_event};
+use crate::price::usd_suffix;
+use crate::retry::{self, retry_async, RequestError, Subsystem};
+use crate::spend::{record_funding, record_transaction};
+use crate::transaction::send_with_commitment;
 use crate::{common::*, config::*, constants::PARALLEL_LIMIT, upload::*, utils::*};
 
-/// The number os retries to fetch the Bundlr balance (MAX_RETRY * DELAY_UNTIL_RETRY ms limit)
-const MAX_RETRY: u64 = 120;
-
-/// Time (ms) to wait until next try
-const DELAY_UNTIL_RETRY: u64 = 1000;
-
 /// Size of Bundlr transaction header
 const HEADER_SIZE: u64 = 2000;
 
 /// Minimum file size for cost calculation
 const MINIMUM_SIZE: u64 = 10000;
 
-/// Size of the mock image URI for cost calculation
-const MOCK_URI_SIZE: usize = 100;
+/// Value of the 'Type' tag of collection manifest data items.
+const COLLECTION_MANIFEST_TYPE: &str = "collection-manifest";
+
+/// Files larger than this are uploaded in chunks
+const CHUNKED_UPLOAD_THRESHOLD: u64 = 50 * 1024 * 1024;
+
+/// Preferred size of the chunks of a chunked upload
+const CHUNK_SIZE: u64 = 25 * 1024 * 1024;
+
+/// Suffix of the directory (next to the cache file) holding the signed data
+/// items of chunked uploads
+const PENDING_UPLOADS_SUFFIX: &str = ".uploads";
 
 struct TxInfo {
     asset_id: String,
     file_path: String,
     image_link: String,
     animation_link: Option<String>,
+    preview_link: Option<String>,
     data_type: DataType,
     tag: Vec<Tag>,
+    template_variables: TemplateVariables,
+    locales: BTreeMap<String, String>,
+    pending: Option<PendingUpload>,
 }
 
 pub struct BundlrHandler {
@@ -58,32 +71,25 @@ impl BundlrHandler {
     ) -> Result<BundlrHandler> {
         let client = setup_client(sugar_config)?;
         let program = client.program(CANDY_MACHINE_ID);
-        let solana_cluster: Cluster = get_cluster(program.rpc())?;
-
-        let bundlr_node = match config_data.upload_method {
-            UploadMethod::Bundlr => match solana_cluster {
-                Cluster::Devnet => BUNDLR_DEVNET,
-                Cluster::Mainnet => BUNDLR_MAINNET,
-            },
-            _ => {
-                return Err(anyhow!(format!(
-                    "Upload method '{}' currently unsupported!",
-                    &config_data.upload_method.to_string()
-                )))
-            }
-        };
+        let bundlr_node = BundlrHandler::get_node(&program, config_data.bundlr_node.as_deref())?;
 
-        let http_client = reqwest::Client::new();
+        let http_client = http_client();
         let bundlr_address =
-            BundlrHandler::get_bundlr_solana_address(&http_client, bundlr_node).await?;
+            BundlrHandler::get_bundlr_solana_address(&http_client, &bundlr_node).await?;
 
         let bundlr_pubkey = Pubkey::from_str(&bundlr_address)?;
         // get keypair as base58 string for Bundlr
-        let keypair = bs58::encode(sugar_config.keypair.to_bytes()).into_string();
+        let keypair = bs58::encode(
+            sugar_config
+                .keypair
+                .keypair("Uploading to Bundlr")?
+                .to_bytes(),
+        )
+        .into_string();
         let signer = SolanaSigner::from_base58(&keypair);
 
         let bundlr_client = Bundlr::new(
-            bundlr_node.to_string(),
+            bundlr_node.clone(),
             "solana".to_string(),
             "sol".to_string(),
             signer,
@@ -92,25 +98,51 @@ impl BundlrHandler {
         Ok(BundlrHandler {
             client: Arc::new(bundlr_client),
             pubkey: bundlr_pubkey,
-            node: bundlr_node.to_string(),
+            node: bundlr_node,
         })
     }
 
-    /// Return the solana address for Bundlr.
+    /// Return the url of the Bundlr node: the custom node, if set, or the
+    /// default node of the cluster.
+    pub fn get_node(program: &Program, custom_node: Option<&str>) -> Result<String> {
+        if let Some(node) = custom_node {
+            let node = node.trim_end_matches('/');
+            return match Url::parse(node) {
+                Ok(url) if url.scheme() == "http" || url.scheme() == "https" => {
+                    Ok(node.to_string())
+                }
+                _ => Err(anyhow!("Invalid Bundlr node url: {}", node)),
+            };
+        }
+
+        Ok(match get_cluster(program.rpc())? {
+            Cluster::Devnet => BUNDLR_DEVNET,
+            Cluster::Mainnet => BUNDLR_MAINNET,
+        }
+        .to_string())
+    }
+
+    /// Return the solana address for Bundlr. Fails if the node does not
+    /// support the solana currency.
     pub async fn get_bundlr_solana_address(http_client: &HttpClient, node: &str) -> Result<String> {
         let url = format!("{}/info", node);
-        let data = http_client.get(&url).send().await?.json::<Value>().await?;
-        let addresses = data
-            .get("addresses")
-            .expect("Failed to get bundlr addresses.");
+        let data = retry_async(Subsystem::Rpc, || async {
+            Ok(http_client.get(&url).send().await?.json::<Value>().await?)
+        })
+        .await
+        .map_err(|err| anyhow!("Failed to get the info of Bundlr node {}: {}", node, err))?;
 
-        let solana_address = addresses
-            .get("solana")
-            .expect("Failed to get Solana address from bundlr.")
-            .as_str()
-            .expect("Solana bundlr address is not of type string.")
-            .to_string();
-        Ok(solana_address)
+        match data
+            .get("addresses")
+            .and_then(|addresses| addresses.get("solana"))
+            .and_then(Value::as_str)
+        {
+            Some(solana_address) => Ok(solana_address.to_string()),
+            None => Err(anyhow!(
+                "Bundlr node {} does not support the solana currency",
+                node
+            )),
+        }
     }
 
     /// Add fund to the Bundlr address.
@@ -119,37 +151,28 @@ impl BundlrHandler {
         http_client: &HttpClient,
         bundlr_address: &Pubkey,
         node: &str,
-        payer: &Keypair,
+        payer: &SugarSigner,
         amount: u64,
     ) -> Result<Response> {
         let ix = system_instruction::transfer(&payer.pubkey(), bundlr_address, amount);
-        let recent_blockhash = program.rpc().get_latest_blockhash()?;
         let payer_pubkey = payer.pubkey();
 
-        let tx = Transaction::new_signed_with_payer(
-            &[ix],
-            Some(&payer_pubkey),
-            &[payer],
-            recent_blockhash,
-        );
-
         println!("Funding address:");
         println!("  -> pubkey: {}", payer_pubkey);
         println!(
-            "  -> lamports: {} (◎ {})",
+            "  -> lamports: {} (◎ {}{})",
             amount,
-            amount as f64 / LAMPORTS_PER_SOL as f64
+            amount as f64 / LAMPORTS_PER_SOL as f64,
+            usd_suffix(amount)
         );
 
-        let sig = program
-            .rpc()
-            .send_and_confirm_transaction_with_spinner_and_commitment(
-                &tx,
-                CommitmentConfig::confirmed(),
-            )?;
+        let sig = send_with_commitment(program, &[ix], &[payer], CommitmentConfig::confirmed())?;
 
         println!("{} {sig}", style("Signature:").bold());
 
+        record_funding(amount);
+        record_transaction(1, 1, None);
+
         let mut map = HashMap::new();
         map.insert("tx_id", sig.to_string());
         let url = format!("{}/account/balance/solana", node);
@@ -166,7 +189,10 @@ impl BundlrHandler {
     ) -> Result<u64> {
         debug!("Getting balance for address: {address}");
         let url = format!("{}/account/balance/solana/?address={}", node, address);
-        let response = http_client.get(&url).send().await?.json::<Value>().await?;
+        let response = retry_async(Subsystem::Rpc, || async {
+            Ok(http_client.get(&url).send().await?.json::<Value>().await?)
+        })
+        .await?;
         let value = response
             .get("balance")
             .expect("Failed to get balance from bundlr.");
@@ -178,6 +204,11 @@ impl BundlrHandler {
             .expect("Failed to parse bundlr balance."))
     }
 
+    /// Return the size of the data item of a file, used for the fee calculation.
+    fn data_item_size(file_size: u64) -> u64 {
+        HEADER_SIZE + cmp::max(MINIMUM_SIZE, file_size)
+    }
+
     /// Return the Bundlr fee for upload based on the data size.
     pub async fn get_bundlr_fee(
         http_client: &HttpClient,
@@ -194,35 +225,388 @@ impl BundlrHandler {
         Ok(required_amount)
     }
 
+    /// Make sure the Bundlr balance covers the specified fee, funding the
+    /// Bundlr address if needed.
+    async fn fund(&self, sugar_config: &SugarConfig, lamports_fee: u64) -> Result<()> {
+        let http_client = http_client();
+
+        let address = sugar_config.keypair.pubkey().to_string();
+        let mut balance =
+            BundlrHandler::get_bundlr_balance(&http_client, &address, &self.node).await?;
+
+        info!(
+            "Bundlr balance {} lamports, require {} lamports",
+            balance, lamports_fee
+        );
+
+        // funds the bundlr wallet for the upload
+
+        let client = setup_client(sugar_config)?;
+        let program = client.program(CANDY_MACHINE_ID);
+
+        if lamports_fee > balance {
+            BundlrHandler::fund_bundlr_address(
+                &program,
+                &http_client,
+                &self.pubkey,
+                &self.node,
+                &sugar_config.keypair,
+                lamports_fee - balance,
+            )
+            .await?;
+
+            let policy = retry::policy(Subsystem::Confirmation);
+
+            let pb = ProgressBar::new(policy.max_attempts as u64);
+            pb.set_style(ProgressStyle::default_bar().template("{spinner} {msg} {wide_bar}"));
+            hide_progress_bar(&pb);
+            pb.enable_steady_tick(60);
+            pb.set_message("Verifying balance:");
+
+            // waits until the balance can be verified, otherwise the upload
+            // will fail
+            for attempt in 1..=policy.max_attempts {
+                let res =
+                    BundlrHandler::get_bundlr_balance(&http_client, &address, &self.node).await;
+
+                if let Ok(value) = res {
+                    balance = value;
+                }
+
+                if balance >= lamports_fee {
+                    break;
+                }
+
+                sleep(policy.delay(attempt)).await;
+                pb.inc(1);
+            }
+
+            pb.finish_and_clear();
+
+            if balance < lamports_fee {
+                let error = UploadError::NoBundlrBalance(address).into();
+                error!("{error}");
+                return Err(error);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Upload a collection manifest data item, tagged so gateways can group
+    /// the uploads of the collection. Returns the id of the data item.
+    pub async fn upload_collection_manifest(
+        &self,
+        sugar_config: &SugarConfig,
+        manifest: &CollectionManifestData,
+    ) -> Result<String> {
+        let data = serde_json::to_vec(manifest)?;
+        let http_client = http_client();
+
+        let lamports_fee = BundlrHandler::get_bundlr_fee(
+            &http_client,
+            &self.node,
+            BundlrHandler::data_item_size(data.len() as u64),
+        )
+        .await?;
+
+        self.fund(sugar_config, lamports_fee).await?;
+
+        let tags = vec![
+            Tag::new("App-Name".into(), format!("Sugar {}", crate_version!())),
+            Tag::new("Content-Type".into(), "application/json".to_string()),
+            Tag::new("Type".into(), COLLECTION_MANIFEST_TYPE.to_string()),
+            Tag::new("Collection-Name".into(), manifest.name.clone()),
+            Tag::new("Collection-Symbol".into(), manifest.symbol.clone()),
+        ];
+
+        BundlrHandler::send_data(&self.client, data, &tags).await
+    }
+
     /// Send a transaction to Bundlr and wait for a response.
     async fn send_bundlr_tx(
         bundlr_client: Arc<Bundlr<SolanaSigner>>,
+        node: String,
         tx_info: TxInfo,
     ) -> Result<(String, String)> {
+        if let Some(pending) = &tx_info.pending {
+            let id = BundlrHandler::send_chunked(&node, pending).await?;
+            return Ok((tx_info.asset_id, id));
+        }
+
         let data = match tx_info.data_type {
             DataType::Image => fs::read(&tx_info.file_path)?,
             DataType::Metadata => {
                 // replaces the image link without modifying the original file to avoid
                 // changing the hash of the metadata file
-                get_updated_metadata(
+                let metadata = get_updated_metadata(
                     &tx_info.file_path,
                     &tx_info.image_link,
-                    tx_info.animation_link,
-                )?
-                .into_bytes()
+                    tx_info.animation_link.clone(),
+                    tx_info.preview_link.clone(),
+                    &tx_info.template_variables,
+                )?;
+
+                if tx_info.locales.is_empty() {
+                    metadata.into_bytes()
+                } else {
+                    // the localized variants are uploaded first so that their
+                    // links can be recorded in the metadata
+                    let mut localization = BTreeMap::new();
+
+                    for (locale, file_path) in &tx_info.locales {
+                        let variant = get_updated_metadata(
+                            file_path,
+                            &tx_info.image_link,
+                            tx_info.animation_link.clone(),
+                            tx_info.preview_link.clone(),
+                            &tx_info.template_variables,
+                        )?;
+                        let id = BundlrHandler::send_data(
+                            &bundlr_client,
+                            variant.into_bytes(),
+                            &tx_info.tag,
+                        )
+                        .await?;
+                        localization.insert(locale.clone(), format!("https://arweave.net/{}", id));
+                    }
+
+                    set_localization(&metadata, localization)?.into_bytes()
+                }
             }
-            DataType::Animation => fs::read(&tx_info.file_path)?,
+            DataType::Animation | DataType::Preview => fs::read(&tx_info.file_path)?,
         };
 
-        let tx = bundlr_client.create_transaction_with_tags(data, tx_info.tag);
-        let response = bundlr_client.send_transaction(tx).await?;
-        let id = response
+        let id = BundlrHandler::send_data(&bundlr_client, data, &tx_info.tag).await?;
+
+        Ok((tx_info.asset_id, id))
+    }
+
+    /// Start a chunked upload on the node, returning its id and chunk size.
+    async fn start_chunked_upload(http_client: &HttpClient, node: &str) -> Result<(String, u64)> {
+        let url = format!("{}/chunks/solana/-1/-1", node);
+        let info = retry_async(Subsystem::Rpc, || async {
+            Ok(http_client
+                .get(&url)
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<Value>()
+                .await?)
+        })
+        .await?;
+
+        let upload_id = info
             .get("id")
-            .expect("Failed to convert transaction id to string.")
-            .as_str()
-            .expect("Failed to get an id from bundlr transaction.");
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("Failed to get an upload id from bundlr."))?;
+        let min = info.get("min").and_then(Value::as_u64).unwrap_or(0);
+        let max = info.get("max").and_then(Value::as_u64).unwrap_or(u64::MAX);
+
+        Ok((
+            upload_id.to_string(),
+            CHUNK_SIZE.clamp(min, cmp::max(min, max)),
+        ))
+    }
+
+    /// Sign the data item of a large file and start its chunked upload,
+    /// recording a resume marker in the cache. A matching marker from a
+    /// previous run is reused so the upload continues where it stopped.
+    async fn prepare_chunked(
+        &self,
+        cache: &mut Cache,
+        asset_id: &str,
+        data_type: &DataType,
+        file_path: &Path,
+        tags: Vec<Tag>,
+    ) -> Result<PendingUpload> {
+        let item = cache
+            .items
+            .0
+            .get(asset_id)
+            .ok_or_else(|| anyhow!("Failed to get config item at index {}", asset_id))?;
+        let (type_name, hash) = match data_type {
+            DataType::Animation => ("animation", item.animation_hash.clone().unwrap_or_default()),
+            DataType::Preview => ("preview", item.preview_hash.clone().unwrap_or_default()),
+            _ => ("image", item.image_hash.clone()),
+        };
 
-        Ok((tx_info.asset_id, id.to_string()))
+        if let Some(pending) = &item.pending_upload {
+            if pending.data_type == type_name
+                && pending.hash == hash
+                && Path::new(&pending.data_path).exists()
+            {
+                return Ok(pending.clone());
+            }
+            // the file changed since the upload started
+            fs::remove_file(&pending.data_path).ok();
+        }
+
+        let data = fs::read(file_path)?;
+        let data_item = self
+            .client
+            .create_transaction_with_tags(data, tags)
+            .into_inner();
+
+        let cache_path = Path::new(&cache.file_path);
+        let stem = cache_path
+            .file_stem()
+            .and_then(OsStr::to_str)
+            .unwrap_or("cache");
+        let directory = cache_path.with_file_name(format!("{}{}", stem, PENDING_UPLOADS_SUFFIX));
+        fs::create_dir_all(&directory)?;
+
+        let data_path = directory.join(format!("{}-{}.bin", asset_id, type_name));
+        fs::write(&data_path, data_item)?;
+
+        let (upload_id, chunk_size) =
+            BundlrHandler::start_chunked_upload(&http_client(), &self.node).await?;
+
+        let pending = PendingUpload {
+            data_type: type_name.to_string(),
+            hash,
+            upload_id,
+            chunk_size,
+            data_path: path_to_string(&data_path)?,
+        };
+
+        cache.get_item_mut(asset_id).unwrap().pending_upload = Some(pending.clone());
+        cache.sync_file()?;
+
+        Ok(pending)
+    }
+
+    /// Upload a signed data item in chunks, skipping the chunks the node
+    /// already received, and return the id of the data item.
+    async fn send_chunked(node: &str, pending: &PendingUpload) -> Result<String> {
+        let http_client = http_client();
+        let mut upload_id = pending.upload_id.clone();
+        let mut chunk_size = pending.chunk_size;
+
+        let info_url = format!("{}/chunks/solana/{}/-1", node, upload_id);
+        let response = retry_async(Subsystem::Rpc, || async {
+            Ok(http_client.get(&info_url).send().await?)
+        })
+        .await?;
+
+        let uploaded: HashSet<u64> = if response.status() == StatusCode::NOT_FOUND {
+            // the upload expired on the node, starts it again
+            warn!("Bundlr upload {} expired, restarting it", upload_id);
+            (upload_id, chunk_size) =
+                BundlrHandler::start_chunked_upload(&http_client, node).await?;
+            HashSet::new()
+        } else {
+            let info: Value = response.error_for_status()?.json().await?;
+            info.get("chunks")
+                .and_then(Value::as_array)
+                .map(|chunks| {
+                    chunks
+                        .iter()
+                        .filter_map(|chunk| chunk.get(0))
+                        .filter_map(|offset| {
+                            offset
+                                .as_u64()
+                                .or_else(|| offset.as_str().and_then(|o| o.parse().ok()))
+                        })
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        let url = format!("{}/chunks/solana/{}", node, upload_id);
+        let mut file = File::open(&pending.data_path)?;
+        let size = file.metadata()?.len();
+        let mut offset = 0;
+
+        while offset < size {
+            let length = cmp::min(chunk_size, size - offset);
+
+            if !uploaded.contains(&offset) {
+                let mut chunk = vec![0; length as usize];
+                file.seek(SeekFrom::Start(offset))?;
+                file.read_exact(&mut chunk)?;
+
+                let chunk_url = format!("{}/{}", url, offset);
+                retry_async(Subsystem::Upload, || async {
+                    http_client
+                        .post(&chunk_url)
+                        .header("Content-Type", "application/octet-stream")
+                        .body(chunk.clone())
+                        .send()
+                        .await?
+                        .error_for_status()?;
+                    Ok(())
+                })
+                .await?;
+            }
+
+            offset += length;
+        }
+
+        let finish_url = format!("{}/-1", url);
+        let response = retry_async(Subsystem::Upload, || async {
+            Ok(http_client
+                .post(&finish_url)
+                .header("Content-Type", "application/octet-stream")
+                .send()
+                .await?)
+        })
+        .await?;
+
+        if response.status() == StatusCode::PAYMENT_REQUIRED {
+            return Err(anyhow!("Not enough Bundlr balance to finish the upload."));
+        }
+
+        let response: Value = response.error_for_status()?.json().await?;
+        response
+            .get("id")
+            .and_then(Value::as_str)
+            .map(|id| id.to_string())
+            .ok_or_else(|| anyhow!("Failed to get an id from bundlr transaction."))
+    }
+
+    /// Send a data item to Bundlr, returning its id.
+    async fn send_data(
+        bundlr_client: &Bundlr<SolanaSigner>,
+        data: Vec<u8>,
+        tags: &[Tag],
+    ) -> Result<String> {
+        let response = retry_async(Subsystem::Upload, || {
+            let tx = bundlr_client.create_transaction_with_tags(data.clone(), tags.to_vec());
+            async move {
+                bundlr_client
+                    .send_transaction(tx)
+                    .await
+                    .map_err(BundlrHandler::request_error)
+            }
+        })
+        .await?;
+        response
+            .get("id")
+            .and_then(|id| id.as_str())
+            .map(|id| id.to_string())
+            .ok_or_else(|| anyhow!("Failed to get an id from bundlr transaction."))
+    }
+
+    /// Convert an error of the Bundlr client, exposing the status of failed
+    /// requests so that rate limits and server errors are retried.
+    fn request_error(error: BundlrError) -> anyhow::Error {
+        match error {
+            BundlrError::ResponseError(message) => {
+                // the client reports failed responses as "Status: <code>" and
+                // requests without a response as "Unknown Error"
+                let status = match message.strip_prefix("Status: ") {
+                    Some(status) => status
+                        .split_whitespace()
+                        .next()
+                        .and_then(|code| code.parse::<u16>().ok()),
+                    None if message == "Unknown Error" => None,
+                    None => return anyhow!(BundlrError::ResponseError(message)),
+                };
+                RequestError { status, message }.into()
+            }
+            error => error.into(),
+        }
     }
 }
 
@@ -236,110 +620,82 @@ impl UploadHandler for BundlrHandler {
         image_indices: &[usize],
         metadata_indices: &[usize],
         animation_indices: &[usize],
+        preview_indices: &[usize],
+        dry_run: bool,
     ) -> Result<()> {
         // calculates the size of the files to upload
         let mut total_size = 0;
 
-        for index in image_indices {
-            let item = assets.get(index).unwrap();
-            let path = Path::new(&item.image);
-            total_size += HEADER_SIZE + cmp::max(MINIMUM_SIZE, std::fs::metadata(path)?.len());
-        }
-
-        let mock_uri = "x".repeat(MOCK_URI_SIZE);
-
-        if !animation_indices.is_empty() {
-            for index in animation_indices {
-                let item = assets.get(index).unwrap();
-                let path = Path::new(item.animation.as_ref().unwrap());
-                total_size += HEADER_SIZE + cmp::max(MINIMUM_SIZE, std::fs::metadata(path)?.len());
+        for (indices, data_type) in [
+            (image_indices, DataType::Image),
+            (animation_indices, DataType::Animation),
+            (preview_indices, DataType::Preview),
+            (metadata_indices, DataType::Metadata),
+        ] {
+            for size in upload_file_sizes(assets, indices, &data_type)? {
+                total_size += BundlrHandler::data_item_size(size);
             }
         }
 
-        for index in metadata_indices {
-            let item = assets.get(index).unwrap();
-
-            let mock_animation_uri = if item.animation.is_some() {
-                Some("x".repeat(MOCK_URI_SIZE))
-            } else {
-                None
-            };
-
-            let updated_metadata =
-                match get_updated_metadata(&item.metadata, &mock_uri, mock_animation_uri.clone()) {
-                    Ok(metadata) => metadata.into_bytes().len() as u64,
-                    Err(err) => return Err(err),
-                };
-
-            total_size += HEADER_SIZE + cmp::max(MINIMUM_SIZE, updated_metadata);
-        }
-
         info!("Total upload size: {}", total_size);
 
-        let http_client = reqwest::Client::new();
+        let http_client = http_client();
 
         let lamports_fee = BundlrHandler::get_bundlr_fee(&http_client, &self.node, total_size)
             .await?
             * (1.1 as u64);
 
-        let address = sugar_config.keypair.pubkey().to_string();
-        let mut balance =
-            BundlrHandler::get_bundlr_balance(&http_client, &address, &self.node).await?;
-
-        info!(
-            "Bundlr balance {} lamports, require {} lamports",
-            balance, lamports_fee
-        );
-
-        // funds the bundlr wallet for image upload
-
-        let client = setup_client(sugar_config)?;
-        let program = client.program(CANDY_MACHINE_ID);
-
-        if lamports_fee > balance {
-            BundlrHandler::fund_bundlr_address(
-                &program,
+        if dry_run {
+            let balance = BundlrHandler::get_bundlr_balance(
                 &http_client,
-                &self.pubkey,
+                &sugar_config.keypair.pubkey().to_string(),
                 &self.node,
-                &sugar_config.keypair,
-                lamports_fee - balance,
             )
             .await?;
 
-            let pb = ProgressBar::new(MAX_RETRY);
-            pb.set_style(ProgressStyle::default_bar().template("{spinner} {msg} {wide_bar}"));
-            pb.enable_steady_tick(60);
-            pb.set_message("Verifying balance:");
+            println!(
+                "Bundlr balance: {} lamports, required: {} lamports",
+                balance, lamports_fee
+            );
 
-            // waits until the balance can be verified, otherwise the upload
-            // will fail
-            for _i in 0..MAX_RETRY {
-                let res =
-                    BundlrHandler::get_bundlr_balance(&http_client, &address, &self.node).await;
+            if lamports_fee > balance {
+                let amount = lamports_fee - balance;
+                println!(
+                    "Would fund the Bundlr address with {} lamports (◎ {}{})",
+                    amount,
+                    amount as f64 / LAMPORTS_PER_SOL as f64,
+                    usd_suffix(amount)
+                );
+            }
 
-                if let Ok(value) = res {
-                    balance = value;
-                }
+            return Ok(());
+        }
 
-                if balance >= lamports_fee {
-                    break;
-                }
+        self.fund(sugar_config, lamports_fee).await
+    }
 
-                sleep(Duration::from_millis(DELAY_UNTIL_RETRY)).await;
-                pb.inc(1);
-            }
+    /// Return the Bundlr fee to upload files of the specified sizes.
+    async fn estimate_cost(&self, file_sizes: &[u64]) -> Result<Option<UploadCost>> {
+        if file_sizes.is_empty() {
+            return Ok(Some(UploadCost::Lamports(0)));
+        }
 
-            pb.finish_and_clear();
+        let total_size = file_sizes
+            .iter()
+            .map(|size| BundlrHandler::data_item_size(*size))
+            .sum();
+        let fee = BundlrHandler::get_bundlr_fee(&http_client(), &self.node, total_size).await?;
 
-            if balance < lamports_fee {
-                let error = UploadError::NoBundlrBalance(address).into();
-                error!("{error}");
-                return Err(error);
-            }
-        }
+        Ok(Some(UploadCost::Lamports(fee)))
+    }
 
-        Ok(())
+    /// Return the balance of the payer on Bundlr.
+    async fn balance(&self) -> Result<Option<u64>> {
+        let balance =
+            BundlrHandler::get_bundlr_balance(&http_client(), &self.pubkey.to_string(), &self.node)
+                .await?;
+
+        Ok(Some(balance))
     }
 
     /// Upload the data to Bundlr.
@@ -352,7 +708,6 @@ impl UploadHandler for BundlrHandler {
         data_type: DataType,
         interrupted: Arc<AtomicBool>,
     ) -> Result<Vec<UploadError>> {
-        let mut extension = HashSet::with_capacity(1);
         let mut paths = Vec::new();
 
         for index in indices {
@@ -365,33 +720,19 @@ impl UploadHandler for BundlrHandler {
                 DataType::Image => item.image.clone(),
                 DataType::Metadata => item.metadata.clone(),
                 DataType::Animation => item.animation.clone().unwrap(),
+                DataType::Preview => item.preview.clone().unwrap(),
             };
 
-            let path = Path::new(&file_path);
-            let ext = path
-                .extension()
-                .and_then(OsStr::to_str)
-                .expect("Failed to convert extension from unicode");
-            extension.insert(String::from(ext));
+            let locales = match data_type {
+                DataType::Metadata => item.locales.clone(),
+                _ => BTreeMap::new(),
+            };
 
-            paths.push(file_path);
+            paths.push((file_path, locales));
         }
 
-        // validates that all files have the same extension
-        let extension = if extension.len() == 1 {
-            extension.iter().next().unwrap()
-        } else {
-            return Err(anyhow!("Invalid file extension: {:?}", extension));
-        };
-
         let sugar_tag = Tag::new("App-Name".into(), format!("Sugar {}", crate_version!()));
 
-        let image_tag = match data_type {
-            DataType::Image => Tag::new("Content-Type".into(), format!("image/{extension}")),
-            DataType::Metadata => Tag::new("Content-Type".into(), "application/json".to_string()),
-            DataType::Animation => Tag::new("Content-Type".into(), format!("video/{extension}")),
-        };
-
         // upload data to bundlr
 
         println!("\nSending data: (Ctrl+C to abort)");
@@ -399,7 +740,7 @@ impl UploadHandler for BundlrHandler {
         let pb = progress_bar_with_style(paths.len() as u64);
         let mut transactions = Vec::new();
 
-        for file_path in paths {
+        for (file_path, locales) in paths {
             // path to the image/metadata file
             let path = Path::new(&file_path);
 
@@ -410,6 +751,28 @@ impl UploadHandler for BundlrHandler {
                     .expect("Failed to convert path to unicode."),
             );
 
+            let content_tag =
+                Tag::new("Content-Type".into(), content_type(&file_path, &data_type)?);
+
+            // large files are uploaded in chunks that can be resumed
+            let pending = match data_type {
+                DataType::Image | DataType::Animation
+                    if fs::metadata(path)?.len() >= CHUNKED_UPLOAD_THRESHOLD =>
+                {
+                    Some(
+                        self.prepare_chunked(
+                            cache,
+                            &asset_id,
+                            &data_type,
+                            path,
+                            vec![sugar_tag.clone(), content_tag.clone()],
+                        )
+                        .await?,
+                    )
+                }
+                _ => None,
+            };
+
             let cache_item = match cache.items.0.get(&asset_id) {
                 Some(item) => item,
                 None => return Err(anyhow!("Failed to get config item at index {}", asset_id)),
@@ -422,81 +785,78 @@ impl UploadHandler for BundlrHandler {
                 file_path: String::from(path.to_str().expect("Failed to parse path from unicode.")),
                 image_link: cache_item.image_link.clone(),
                 data_type: data_type.clone(),
-                tag: vec![sugar_tag.clone(), image_tag.clone()],
+                tag: vec![sugar_tag.clone(), content_tag.clone()],
                 animation_link: cache_item.animation_link.clone(),
+                preview_link: cache_item.preview_link.clone(),
+                template_variables: cache.program.template_variables.clone(),
+                locales,
+                pending,
             });
         }
 
-        let mut handles = Vec::new();
-
-        for tx in transactions.drain(0..cmp::min(transactions.len(), PARALLEL_LIMIT)) {
-            let bundlr_client = self.client.clone();
-            handles.push(tokio::spawn(async move {
-                BundlrHandler::send_bundlr_tx(bundlr_client, tx).await
-            }));
-        }
-
         let mut errors = Vec::new();
-
-        while !interrupted.load(Ordering::SeqCst) && !handles.is_empty() {
-            match select_all(handles).await {
-                (Ok(res), _index, remaining) => {
-                    // independently if the upload was successful or not
-                    // we continue to try the remaining ones
-                    handles = remaining;
-
-                    if res.is_ok() {
-                        let val = res?;
-                        let link = format!("https://arweave.net/{}", val.clone().1);
+        let (bundlr_client, node) = (self.client.clone(), &self.node);
+        let transactions = transactions
+            .into_iter()
+            .map(|tx| (tx.asset_id.clone(), tx))
+            .collect();
+
+        let not_started = run_bounded(
+            transactions,
+            upload_concurrency(PARALLEL_LIMIT),
+            &interrupted,
+            |tx| BundlrHandler::send_bundlr_tx(bundlr_client.clone(), node.clone(), tx),
+            |asset_id, result| {
+                match result {
+                    Ok((_, id)) => {
+                        let link = format!("https://arweave.net/{}", id);
                         // cache item to update
-                        let item = cache.items.0.get_mut(&val.0).unwrap();
+                        let item = cache.get_item_mut(&asset_id).unwrap();
 
                         match data_type {
-                            DataType::Image => item.image_link = link,
-                            DataType::Metadata => item.metadata_link = link,
-                            DataType::Animation => item.animation_link = Some(link),
+                            DataType::Image => item.image_link = link.clone(),
+                            DataType::Metadata => item.metadata_link = link.clone(),
+                            DataType::Animation => item.animation_link = Some(link.clone()),
+                            DataType::Preview => item.preview_link = Some(link.clone()),
+                        }
+                        // the chunked upload is complete
+                        if let Some(pending) = item.pending_upload.take() {
+                            fs::remove_file(pending.data_path).ok();
                         }
                         // updates the progress bar
                         pb.inc(1);
-                    } else {
+                        upload_event(
+                            data_type.stage(),
+                            &asset_id,
+                            &link,
+                            pb.position(),
+                            pb.length(),
+                        );
+                    }
+                    Err(err) => {
                         // user will need to retry the upload
-                        errors.push(UploadError::SendDataFailed(format!(
-                            "Bundlr upload error: {:?}",
-                            res.err().unwrap()
-                        )));
+                        let error = format!("Bundlr upload error: {:?}", err);
+                        error_event(
+                            data_type.stage(),
+                            Some(&asset_id),
+                            pb.position(),
+                            pb.length(),
+                            &error,
+                        );
+                        cache.record_error(&asset_id, &error);
+                        errors.push(UploadError::SendDataFailed(error));
                     }
                 }
-                (Err(err), _index, remaining) => {
-                    errors.push(UploadError::SendDataFailed(format!(
-                        "Bundlr upload error: {:?}",
-                        err
-                    )));
-                    // ignoring all errors
-                    handles = remaining;
-                }
-            }
 
-            if !transactions.is_empty() {
-                // if we are half way through, let spawn more transactions
-                if (PARALLEL_LIMIT - handles.len()) > (PARALLEL_LIMIT / 2) {
-                    // syncs cache (checkpoint)
-                    cache.sync_file()?;
-
-                    for tx in
-                        transactions.drain(0..cmp::min(transactions.len(), PARALLEL_LIMIT / 2))
-                    {
-                        let bundlr_client = self.client.clone();
-                        handles.push(tokio::spawn(async move {
-                            BundlrHandler::send_bundlr_tx(bundlr_client, tx).await
-                        }));
-                    }
-                }
-            }
-        }
+                // syncs cache (checkpoint)
+                cache.checkpoint()
+            },
+        )
+        .await?;
 
         if !errors.is_empty() {
             pb.abandon_with_message(format!("{}", style("Upload failed ").red().bold()));
-        } else if !transactions.is_empty() {
+        } else if not_started > 0 {
             pb.abandon_with_message(format!("{}", style("Upload aborted ").red().bold()));
             return Err(
                 UploadError::SendDataFailed("Not all files were uploaded.".to_string()).into(),
@@ -17,6 +17,9 @@ pub enum UploadError {
     #[error("No Bundlr balance found for address: {0}, check Bundlr cluster and address balance")]
     NoBundlrBalance(String),
 
+    #[error("Not enough AR in Arweave wallet {0}: {1} AR available, {2} AR required")]
+    NoArweaveBalance(String, String, String),
+
     #[error("Invalid Bundlr cluster: {0} Use 'devnet' or 'mainnet'")]
     InvalidBundlrCluster(String),
 
@@ -33,4 +36,7 @@ pub enum UploadError {
 
     #[error("Metadata file {0} is not formatted correctly for animations.")]
     AnimationFileError(String),
+
+    #[error("Name of asset {0} ('{1}') is longer than {2} bytes")]
+    NameTooLong(String, String, usize),
 }
@@ -0,0 +1,217 @@
+use console::style;
+use serde::Serialize;
+use std::{
+    fs,
+    sync::{Mutex, OnceLock},
+    time::Duration,
+};
+
+use crate::common::*;
+use crate::events::summary_event;
+use crate::retry::{retry_count, Subsystem};
+use crate::spend::format_lamports;
+use crate::upload::{AssetPair, DataType, UploadHandler};
+
+/// Number of requests and total latency of the tasks run by the upload pool.
+static LATENCY: OnceLock<Mutex<(u64, Duration)>> = OnceLock::new();
+
+/// Metrics of the upload of a data type.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadMetrics {
+    pub data_type: &'static str,
+    pub attempted: u64,
+    pub succeeded: u64,
+    pub failed: u64,
+    pub retried: u64,
+    pub bytes: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub average_latency_ms: Option<u64>,
+    /// Lamports spent on the upload, when the storage reports a balance.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost: Option<u64>,
+}
+
+/// Record the latency of an upload request.
+pub fn record_latency(elapsed: Duration) {
+    let latency = LATENCY.get_or_init(|| Mutex::new((0, Duration::ZERO)));
+
+    if let Ok(mut latency) = latency.lock() {
+        latency.0 += 1;
+        latency.1 += elapsed;
+    }
+}
+
+fn latency() -> (u64, Duration) {
+    LATENCY
+        .get()
+        .and_then(|latency| latency.lock().ok().map(|latency| *latency))
+        .unwrap_or((0, Duration::ZERO))
+}
+
+/// Collects the metrics of the upload of a data type, comparing the
+/// counters before and after the upload.
+pub struct MetricsRecorder {
+    data_type: DataType,
+    retries: u64,
+    latency: (u64, Duration),
+    balance: Option<u64>,
+}
+
+impl MetricsRecorder {
+    pub async fn start(handler: &dyn UploadHandler, data_type: DataType) -> MetricsRecorder {
+        MetricsRecorder {
+            data_type,
+            retries: retry_count(Subsystem::Upload),
+            latency: latency(),
+            balance: storage_balance(handler).await,
+        }
+    }
+
+    pub async fn finish(
+        self,
+        handler: &dyn UploadHandler,
+        assets: &HashMap<usize, AssetPair>,
+        cache: &Cache,
+        indices: &[usize],
+    ) -> Result<UploadMetrics> {
+        let mut succeeded = 0;
+        let mut bytes = 0;
+
+        for index in indices {
+            let item = match cache.items.0.get(&index.to_string()) {
+                Some(item) => item,
+                None => continue,
+            };
+            let asset = assets.get(index).unwrap();
+
+            let (uploaded, files) = match self.data_type {
+                DataType::Image => (!item.image_link.is_empty(), vec![&asset.image]),
+                DataType::Animation => (
+                    item.animation_link
+                        .as_ref()
+                        .map(|link| !link.is_empty())
+                        .unwrap_or(false),
+                    asset.animation.iter().collect(),
+                ),
+                DataType::Preview => (
+                    item.preview_link
+                        .as_ref()
+                        .map(|link| !link.is_empty())
+                        .unwrap_or(false),
+                    asset.preview.iter().collect(),
+                ),
+                DataType::Metadata => (
+                    !item.metadata_link.is_empty(),
+                    std::iter::once(&asset.metadata)
+                        .chain(asset.locales.values())
+                        .collect(),
+                ),
+            };
+
+            if uploaded {
+                succeeded += 1;
+                for file in files {
+                    bytes += fs::metadata(file)?.len();
+                }
+            }
+        }
+
+        let (requests, elapsed) = latency();
+        let requests = requests - self.latency.0;
+        let elapsed = elapsed - self.latency.1;
+
+        let cost = match (self.balance, storage_balance(handler).await) {
+            (Some(before), Some(after)) => Some(before.saturating_sub(after)),
+            _ => None,
+        };
+
+        Ok(UploadMetrics {
+            data_type: match self.data_type {
+                DataType::Image => "image",
+                DataType::Metadata => "metadata",
+                DataType::Animation => "animation",
+                DataType::Preview => "preview",
+            },
+            attempted: indices.len() as u64,
+            succeeded,
+            failed: indices.len() as u64 - succeeded,
+            retried: retry_count(Subsystem::Upload) - self.retries,
+            bytes,
+            average_latency_ms: if requests > 0 {
+                Some((elapsed / requests as u32).as_millis() as u64)
+            } else {
+                None
+            },
+            cost,
+        })
+    }
+}
+
+async fn storage_balance(handler: &dyn UploadHandler) -> Option<u64> {
+    match handler.balance().await {
+        Ok(balance) => balance,
+        Err(err) => {
+            warn!("Failed to get the storage balance: {}", err);
+            None
+        }
+    }
+}
+
+/// Print the summary table of the upload and write it as a progress event.
+pub fn print_upload_metrics(metrics: &[UploadMetrics]) {
+    if metrics.is_empty() {
+        return;
+    }
+
+    println!("\n{}", style("Upload summary").bold());
+    println!(
+        "  {:<10}{:>10}{:>10}{:>8}{:>9}{:>12}{:>13}  Cost",
+        "Type", "Attempted", "Succeeded", "Failed", "Retried", "Bytes", "Avg latency"
+    );
+
+    for metric in metrics {
+        let failed = format!("{:>8}", metric.failed);
+        println!(
+            "  {:<10}{:>10}{:>10}{}{:>9}{:>12}{:>13}  {}",
+            metric.data_type,
+            metric.attempted,
+            metric.succeeded,
+            if metric.failed > 0 {
+                style(failed).red()
+            } else {
+                style(failed)
+            },
+            metric.retried,
+            format_bytes(metric.bytes),
+            metric
+                .average_latency_ms
+                .map(|latency| format!("{}ms", latency))
+                .unwrap_or_else(|| "-".to_string()),
+            metric
+                .cost
+                .map(format_lamports)
+                .unwrap_or_else(|| "-".to_string()),
+        );
+    }
+
+    summary_event("upload:summary", &metrics);
+}
+
+/// Format a number of bytes with a binary unit.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
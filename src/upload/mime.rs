@@ -0,0 +1,108 @@
+use std::{ffi::OsStr, fs::File, io::Read, path::Path};
+
+use crate::common::*;
+use crate::upload::DataType;
+
+/// Number of bytes read to detect the type of a file.
+const SNIFF_SIZE: usize = 16;
+
+/// Content type used when the type of a file cannot be detected.
+const DEFAULT_CONTENT_TYPE: &str = "application/octet-stream";
+
+/// Content types of the extensions that are not (correctly) covered by
+/// `mime_guess`, mostly the formats of 3D and interactive assets.
+const EXTENSION_TYPES: [(&str, &str); 9] = [
+    ("glb", "model/gltf-binary"),
+    ("gltf", "model/gltf+json"),
+    ("usdz", "model/vnd.usdz+zip"),
+    ("html", "text/html"),
+    ("htm", "text/html"),
+    ("mp3", "audio/mpeg"),
+    ("flac", "audio/flac"),
+    ("wav", "audio/wav"),
+    ("webp", "image/webp"),
+];
+
+/// Return the content type of a file to upload. The type is detected from
+/// the first bytes of the file, falling back to its extension. Metadata files
+/// are always JSON.
+pub fn content_type(file_path: &str, data_type: &DataType) -> Result<String> {
+    if let DataType::Metadata = data_type {
+        return Ok("application/json".to_string());
+    }
+
+    let mut header = Vec::with_capacity(SNIFF_SIZE);
+    File::open(file_path)?
+        .take(SNIFF_SIZE as u64)
+        .read_to_end(&mut header)?;
+
+    if let Some(content_type) = sniff_content_type(&header) {
+        return Ok(content_type.to_string());
+    }
+
+    let extension = Path::new(file_path)
+        .extension()
+        .and_then(OsStr::to_str)
+        .map(|extension| extension.to_lowercase())
+        .unwrap_or_default();
+
+    Ok(extension_content_type(&extension))
+}
+
+/// Return the content type of a file extension.
+pub fn extension_content_type(extension: &str) -> String {
+    let extension = extension.to_lowercase();
+
+    if let Some((_, content_type)) = EXTENSION_TYPES
+        .iter()
+        .find(|(candidate, _)| *candidate == extension)
+    {
+        return content_type.to_string();
+    }
+
+    mime_guess::from_ext(&extension)
+        .first()
+        .map(|mime| mime.to_string())
+        .unwrap_or_else(|| DEFAULT_CONTENT_TYPE.to_string())
+}
+
+/// Detect the content type of a file from its magic bytes. Returns None for
+/// unknown (or text) formats, which are detected by their extension.
+fn sniff_content_type(header: &[u8]) -> Option<&'static str> {
+    let starts_with = |magic: &[u8]| header.starts_with(magic);
+    let at = |offset: usize, magic: &[u8]| {
+        header.len() >= offset + magic.len() && &header[offset..offset + magic.len()] == magic
+    };
+
+    if starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if starts_with(b"\xff\xd8\xff") {
+        Some("image/jpeg")
+    } else if starts_with(b"GIF87a") || starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if starts_with(b"RIFF") && at(8, b"WEBP") {
+        Some("image/webp")
+    } else if starts_with(b"RIFF") && at(8, b"WAVE") {
+        Some("audio/wav")
+    } else if starts_with(b"glTF") {
+        Some("model/gltf-binary")
+    } else if starts_with(b"ID3")
+        || (header.len() >= 2 && header[0] == 0xff && header[1] & 0xe0 == 0xe0)
+    {
+        Some("audio/mpeg")
+    } else if starts_with(b"fLaC") {
+        Some("audio/flac")
+    } else if starts_with(b"\x1a\x45\xdf\xa3") {
+        Some("video/webm")
+    } else if at(4, b"ftyp") {
+        if at(8, b"M4A ") {
+            Some("audio/mp4")
+        } else if at(8, b"qt  ") {
+            Some("video/quicktime")
+        } else {
+            Some("video/mp4")
+        }
+    } else {
+        None
+    }
+}
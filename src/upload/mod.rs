@@ -1,11 +1,35 @@
+pub mod arweave;
 pub mod assets;
 pub mod aws;
 pub mod bundlr;
 pub mod errors;
+pub mod metrics;
+pub mod mime;
+pub mod nft_storage;
+pub mod optimize;
+pub mod pinata;
+pub mod pool;
+pub mod previews;
 pub mod process;
+pub mod registry;
+pub mod shadow_drive;
+pub mod template;
+pub mod watch;
 
+pub use arweave::*;
 pub use assets::*;
 pub use aws::*;
 pub use bundlr::*;
 pub use errors::*;
+pub use metrics::*;
+pub use mime::*;
+pub use nft_storage::*;
+pub use optimize::*;
+pub use pinata::*;
+pub use pool::*;
+pub use previews::*;
 pub use process::*;
+pub use registry::*;
+pub use shadow_drive::*;
+pub use template::*;
+pub use watch::*;
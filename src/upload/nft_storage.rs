@@ -0,0 +1,747 @@
+use async_trait::async_trait;
+use console::style;
+use data_encoding::BASE32_NOPAD;
+use ring::digest::{digest, SHA256};
+use std::{
+    collections::BTreeMap,
+    ffi::OsStr,
+    fs,
+    path::Path,
+    sync::{atomic::AtomicBool, Arc},
+};
+
+use crate::credentials::NFT_STORAGE_TOKEN;
+use crate::events::{error_event, upload_event};
+use crate::retry::{retry_async, Subsystem};
+use crate::{common::*, config::*, upload::*, utils::*};
+
+/// NFT.Storage endpoint to upload CAR files.
+const NFT_STORAGE_UPLOAD: &str = "https://api.nft.storage/upload";
+
+/// Maximum size of a CAR file sent in a single request.
+const MAX_CAR_SIZE: usize = 10 * 1024 * 1024;
+
+/// Size of the blocks files are split into.
+const BLOCK_SIZE: usize = 256 * 1024;
+
+/// Maximum number of links of a file node, as in the balanced layout of
+/// `ipfs add`.
+const MAX_LINKS: usize = 174;
+
+/// Number of CAR uploads running in parallel.
+const NFT_STORAGE_PARALLEL_LIMIT: usize = 4;
+
+/// Multicodec codes of the block formats.
+const RAW_CODEC: u64 = 0x55;
+const DAG_PB_CODEC: u64 = 0x70;
+
+/// Multihash code and length of SHA-256 digests.
+const SHA2_256: u64 = 0x12;
+const SHA2_256_LENGTH: u64 = 32;
+
+/// UnixFS node types.
+const UNIXFS_DIRECTORY: u64 = 1;
+const UNIXFS_FILE: u64 = 2;
+
+/// A block of the DAG, addressed by its CID.
+struct Block {
+    cid: Vec<u8>,
+    data: Vec<u8>,
+    leaf: bool,
+}
+
+/// Root of an imported file or directory.
+struct Node {
+    cid: Vec<u8>,
+    /// Cumulative size of the blocks of the node.
+    size: u64,
+    /// Size of the file content of the node.
+    file_size: u64,
+}
+
+struct BatchItem {
+    asset_id: String,
+    file_path: String,
+    image_link: String,
+    data_type: DataType,
+    animation_link: Option<String>,
+    preview_link: Option<String>,
+    template_variables: TemplateVariables,
+    locales: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UploadResponse {
+    value: UploadValue,
+}
+
+#[derive(Debug, Deserialize)]
+struct UploadValue {
+    cid: String,
+}
+
+pub struct NftStorageHandler {
+    client: Arc<HttpClient>,
+    token: Arc<String>,
+    gateway: Option<String>,
+}
+
+impl NftStorageHandler {
+    /// Initialize a new NftStorageHandler.
+    pub async fn initialize(config_data: &ConfigData) -> Result<NftStorageHandler> {
+        let token = NFT_STORAGE_TOKEN.resolve(&config_data.nft_storage_token)?;
+
+        Ok(NftStorageHandler {
+            client: Arc::new(http_client()),
+            token: Arc::new(token),
+            gateway: config_data
+                .nft_storage_gateway
+                .as_ref()
+                .map(|gateway| gateway.trim_end_matches('/').to_string()),
+        })
+    }
+
+    /// Return the link of an uploaded path, using the gateway if one is set.
+    fn link(gateway: &Option<String>, path: &str) -> String {
+        match gateway {
+            Some(gateway) => format!("{}/ipfs/{}", gateway, path),
+            None => format!("ipfs://{}", path),
+        }
+    }
+
+    /// Add the files of a batch to a directory and upload it as CAR file(s),
+    /// returning the link of each asset.
+    async fn send_batch(
+        client: Arc<HttpClient>,
+        token: Arc<String>,
+        gateway: Option<String>,
+        batch: Vec<BatchItem>,
+    ) -> Result<Vec<(String, String)>> {
+        let mut blocks = Vec::new();
+        let mut entries = Vec::new();
+        let mut names = Vec::new();
+
+        for item in &batch {
+            let data = match item.data_type {
+                DataType::Image | DataType::Animation | DataType::Preview => {
+                    fs::read(&item.file_path)?
+                }
+                DataType::Metadata => {
+                    // replaces the image link without modifying the original file to avoid
+                    // changing the hash of the metadata file
+                    let metadata = get_updated_metadata(
+                        &item.file_path,
+                        &item.image_link,
+                        item.animation_link.clone(),
+                        item.preview_link.clone(),
+                        &item.template_variables,
+                    )?;
+
+                    if item.locales.is_empty() {
+                        metadata.into_bytes()
+                    } else {
+                        // the localized variants are linked by their own CID, since the
+                        // CID of the directory depends on the content of the metadata
+                        let mut localization = BTreeMap::new();
+
+                        for (locale, file_path) in &item.locales {
+                            let variant = get_updated_metadata(
+                                file_path,
+                                &item.image_link,
+                                item.animation_link.clone(),
+                                item.preview_link.clone(),
+                                &item.template_variables,
+                            )?;
+                            let node = import_file(variant.as_bytes(), &mut blocks);
+                            localization.insert(
+                                locale.clone(),
+                                NftStorageHandler::link(&gateway, &cid_to_string(&node.cid)),
+                            );
+                            entries.push((file_name(file_path), node));
+                        }
+
+                        set_localization(&metadata, localization)?.into_bytes()
+                    }
+                }
+            };
+
+            let name = file_name(&item.file_path);
+            entries.push((name.clone(), import_file(&data, &mut blocks)));
+            names.push(name);
+        }
+
+        let root = import_directory(entries, &mut blocks);
+        let root_cid = cid_to_string(&root.cid);
+
+        for car in encode_cars(&root.cid, &blocks) {
+            let response: UploadResponse = retry_async(Subsystem::Upload, || async {
+                Ok(client
+                    .post(NFT_STORAGE_UPLOAD)
+                    .bearer_auth(token.as_str())
+                    .header("Content-Type", "application/car")
+                    .body(car.clone())
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?)
+            })
+            .await?;
+
+            if response.value.cid != root_cid {
+                return Err(anyhow!(
+                    "Unexpected root CID '{}' (expected '{}')",
+                    response.value.cid,
+                    root_cid
+                ));
+            }
+        }
+
+        Ok(batch
+            .into_iter()
+            .zip(names)
+            .map(|(item, name)| {
+                (
+                    item.asset_id,
+                    NftStorageHandler::link(&gateway, &format!("{}/{}", root_cid, name)),
+                )
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl UploadHandler for NftStorageHandler {
+    /// Nothing to do, NFT.Storage client ready for the upload.
+    async fn prepare(
+        &self,
+        _sugar_config: &SugarConfig,
+        _assets: &HashMap<usize, AssetPair>,
+        _image_indices: &[usize],
+        _metadata_indices: &[usize],
+        _animation_indices: &[usize],
+        _preview_indices: &[usize],
+        _dry_run: bool,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Upload the data in batches of CAR files.
+    async fn upload_data(
+        &self,
+        _sugar_config: &SugarConfig,
+        assets: &HashMap<usize, AssetPair>,
+        cache: &mut Cache,
+        indices: &[usize],
+        data_type: DataType,
+        interrupted: Arc<AtomicBool>,
+    ) -> Result<Vec<UploadError>> {
+        let mut batches: Vec<Vec<BatchItem>> = Vec::new();
+        let mut batch_size = 0;
+
+        for index in indices {
+            let item = assets.get(index).unwrap();
+            // chooses the file path based on the data type
+            let file_path = match data_type {
+                DataType::Image => item.image.clone(),
+                DataType::Metadata => item.metadata.clone(),
+                DataType::Animation => item.animation.clone().unwrap(),
+                DataType::Preview => item.preview.clone().unwrap(),
+            };
+
+            let path = Path::new(&file_path);
+            // id of the asset (to be used to update the cache link)
+            let asset_id = String::from(
+                path.file_stem()
+                    .and_then(OsStr::to_str)
+                    .expect("Failed to get convert path file ext to valid unicode."),
+            );
+            let cache_item = match cache.items.0.get(&asset_id) {
+                Some(item) => item,
+                None => {
+                    return Err(anyhow::anyhow!(
+                        "Failed to get config item at index: {}",
+                        asset_id
+                    ))
+                }
+            };
+
+            let locales = match data_type {
+                DataType::Metadata => item.locales.clone(),
+                _ => BTreeMap::new(),
+            };
+
+            let mut size = fs::metadata(&file_path)?.len() as usize;
+            for file_path in locales.values() {
+                size += fs::metadata(file_path)?.len() as usize;
+            }
+
+            // files are grouped until the batch reaches the size of a CAR file
+            if batches.is_empty() || batch_size + size > MAX_CAR_SIZE {
+                batches.push(Vec::new());
+                batch_size = 0;
+            }
+            batch_size += size;
+
+            batches.last_mut().unwrap().push(BatchItem {
+                asset_id,
+                file_path: file_path.clone(),
+                image_link: cache_item.image_link.clone(),
+                data_type: data_type.clone(),
+                animation_link: cache_item.animation_link.clone(),
+                preview_link: cache_item.preview_link.clone(),
+                template_variables: cache.program.template_variables.clone(),
+                locales,
+            });
+        }
+
+        println!(
+            "\nSending data in {} batch(es): (Ctrl+C to abort)",
+            batches.len()
+        );
+
+        let pb = progress_bar_with_style(indices.len() as u64);
+        let mut errors = Vec::new();
+        let (client, token, gateway) = (self.client.clone(), self.token.clone(), &self.gateway);
+        let batches = batches
+            .into_iter()
+            .map(|batch| {
+                // batches are identified by the range of their assets
+                let first = &batch[0].asset_id;
+                let last = &batch[batch.len() - 1].asset_id;
+                (format!("{}-{}", first, last), batch)
+            })
+            .collect::<Vec<(String, Vec<BatchItem>)>>();
+        // assets of each batch, to record the error of a failed batch
+        let batch_assets: HashMap<String, Vec<String>> = batches
+            .iter()
+            .map(|(batch_id, batch)| {
+                let assets = batch.iter().map(|item| item.asset_id.clone()).collect();
+                (batch_id.clone(), assets)
+            })
+            .collect();
+
+        let not_started = run_bounded(
+            batches,
+            upload_concurrency(NFT_STORAGE_PARALLEL_LIMIT),
+            &interrupted,
+            |batch| {
+                NftStorageHandler::send_batch(client.clone(), token.clone(), gateway.clone(), batch)
+            },
+            |batch_id, result| {
+                match result {
+                    Ok(links) => {
+                        for (asset_id, link) in links {
+                            // cache item to update
+                            let item = cache.get_item_mut(&asset_id).unwrap();
+
+                            match data_type {
+                                DataType::Image => item.image_link = link.clone(),
+                                DataType::Metadata => item.metadata_link = link.clone(),
+                                DataType::Animation => item.animation_link = Some(link.clone()),
+                                DataType::Preview => item.preview_link = Some(link.clone()),
+                            }
+                            // updates the progress bar
+                            pb.inc(1);
+                            upload_event(
+                                data_type.stage(),
+                                &asset_id,
+                                &link,
+                                pb.position(),
+                                pb.length(),
+                            );
+                        }
+                    }
+                    Err(err) => {
+                        // user will need to retry the upload
+                        let error =
+                            format!("NFT.Storage upload error (batch {}): {:?}", batch_id, err);
+                        error_event(data_type.stage(), None, pb.position(), pb.length(), &error);
+
+                        for asset_id in batch_assets.get(&batch_id).into_iter().flatten() {
+                            cache.record_error(asset_id, &error);
+                        }
+                        errors.push(UploadError::SendDataFailed(error));
+                    }
+                }
+
+                // syncs cache (checkpoint)
+                cache.checkpoint()
+            },
+        )
+        .await?;
+
+        if !errors.is_empty() {
+            pb.abandon_with_message(format!("{}", style("Upload failed ").red().bold()));
+        } else if not_started > 0 {
+            pb.abandon_with_message(format!("{}", style("Upload aborted ").red().bold()));
+            return Err(
+                UploadError::SendDataFailed("Not all files were uploaded.".to_string()).into(),
+            );
+        } else {
+            pb.finish_with_message(format!("{}", style("Upload successful ").green().bold()));
+        }
+
+        // makes sure the cache file is updated
+        cache.sync_file()?;
+
+        Ok(errors)
+    }
+}
+
+fn file_name(file_path: &str) -> String {
+    Path::new(file_path)
+        .file_name()
+        .and_then(OsStr::to_str)
+        .unwrap_or(file_path)
+        .to_string()
+}
+
+fn write_varint(mut value: u64, out: &mut Vec<u8>) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Write a varint field of a protobuf message.
+fn write_pb_varint(field: u64, value: u64, out: &mut Vec<u8>) {
+    write_varint(field << 3, out);
+    write_varint(value, out);
+}
+
+/// Write a length-delimited field of a protobuf message.
+fn write_pb_bytes(field: u64, value: &[u8], out: &mut Vec<u8>) {
+    write_varint((field << 3) | 2, out);
+    write_varint(value.len() as u64, out);
+    out.extend_from_slice(value);
+}
+
+/// Return the binary CIDv1 (SHA-256) of a block.
+fn cid(codec: u64, data: &[u8]) -> Vec<u8> {
+    let mut cid = Vec::with_capacity(36);
+    write_varint(1, &mut cid);
+    write_varint(codec, &mut cid);
+    write_varint(SHA2_256, &mut cid);
+    write_varint(SHA2_256_LENGTH, &mut cid);
+    cid.extend_from_slice(digest(&SHA256, data).as_ref());
+    cid
+}
+
+/// Return the base32 multibase representation of a CID.
+fn cid_to_string(cid: &[u8]) -> String {
+    format!("b{}", BASE32_NOPAD.encode(cid).to_lowercase())
+}
+
+/// Encode a dag-pb node with the specified links (CID, name and size) and
+/// UnixFS data, adding it to the blocks.
+fn add_dag_pb_node(
+    links: &[(&[u8], &str, u64)],
+    unixfs: &[u8],
+    file_size: u64,
+    blocks: &mut Vec<Block>,
+) -> Node {
+    let mut data = Vec::new();
+
+    // links are encoded before the data to follow the canonical dag-pb form;
+    // the name is always present, even if empty, as in `ipfs add`
+    for (cid, name, size) in links {
+        let mut link = Vec::new();
+        write_pb_bytes(1, cid, &mut link);
+        write_pb_bytes(2, name.as_bytes(), &mut link);
+        write_pb_varint(3, *size, &mut link);
+        write_pb_bytes(2, &link, &mut data);
+    }
+    write_pb_bytes(1, unixfs, &mut data);
+
+    let node = Node {
+        cid: cid(DAG_PB_CODEC, &data),
+        size: data.len() as u64 + links.iter().map(|(_, _, size)| size).sum::<u64>(),
+        file_size,
+    };
+    blocks.push(Block {
+        cid: node.cid.clone(),
+        data,
+        leaf: false,
+    });
+
+    node
+}
+
+/// Add a raw block with (part of) the content of a file to the blocks.
+fn add_raw_leaf(data: &[u8], blocks: &mut Vec<Block>) -> Node {
+    let node = Node {
+        cid: cid(RAW_CODEC, data),
+        size: data.len() as u64,
+        file_size: data.len() as u64,
+    };
+    blocks.push(Block {
+        cid: node.cid.clone(),
+        data: data.to_vec(),
+        leaf: true,
+    });
+
+    node
+}
+
+/// Add a file node linking to the specified nodes of a file.
+fn add_file_node(children: &[Node], blocks: &mut Vec<Block>) -> Node {
+    let file_size = children.iter().map(|child| child.file_size).sum();
+
+    let mut unixfs = Vec::new();
+    write_pb_varint(1, UNIXFS_FILE, &mut unixfs);
+    write_pb_varint(3, file_size, &mut unixfs);
+    for child in children {
+        write_pb_varint(4, child.file_size, &mut unixfs);
+    }
+
+    let links: Vec<(&[u8], &str, u64)> = children
+        .iter()
+        .map(|child| (child.cid.as_slice(), "", child.size))
+        .collect();
+
+    add_dag_pb_node(&links, &unixfs, file_size, blocks)
+}
+
+/// Add a file to the blocks, split into raw blocks when larger than the
+/// block size. The blocks are linked in a balanced tree of file nodes, so
+/// the CID is the same as the one of `ipfs add --cid-version=1 --raw-leaves`.
+fn import_file(data: &[u8], blocks: &mut Vec<Block>) -> Node {
+    if data.len() <= BLOCK_SIZE {
+        return add_raw_leaf(data, blocks);
+    }
+
+    let mut nodes: Vec<Node> = data
+        .chunks(BLOCK_SIZE)
+        .map(|chunk| add_raw_leaf(chunk, blocks))
+        .collect();
+
+    while nodes.len() > 1 {
+        nodes = nodes
+            .chunks(MAX_LINKS)
+            .map(|children| add_file_node(children, blocks))
+            .collect();
+    }
+
+    nodes.pop().unwrap()
+}
+
+/// Add a directory with the specified entries to the blocks.
+fn import_directory(mut entries: Vec<(String, Node)>, blocks: &mut Vec<Block>) -> Node {
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    entries.dedup_by(|a, b| a.0 == b.0);
+
+    let mut unixfs = Vec::new();
+    write_pb_varint(1, UNIXFS_DIRECTORY, &mut unixfs);
+
+    let links: Vec<(&[u8], &str, u64)> = entries
+        .iter()
+        .map(|(name, node)| (node.cid.as_slice(), name.as_str(), node.size))
+        .collect();
+
+    add_dag_pb_node(&links, &unixfs, 0, blocks)
+}
+
+/// Encode the blocks as CAR files of up to the maximum CAR size. Each CAR
+/// file has the same root and contains all the (small) dag-pb nodes, so it
+/// can be traversed from the root.
+fn encode_cars(root: &[u8], blocks: &[Block]) -> Vec<Vec<u8>> {
+    // CAR header: dag-cbor map { roots: [CID(root)], version: 1 }
+    let mut header_cbor = vec![0xa2, 0x65];
+    header_cbor.extend_from_slice(b"roots");
+    header_cbor.extend_from_slice(&[0x81, 0xd8, 0x2a, 0x58, root.len() as u8 + 1, 0x00]);
+    header_cbor.extend_from_slice(root);
+    header_cbor.push(0x67);
+    header_cbor.extend_from_slice(b"version");
+    header_cbor.push(0x01);
+
+    let mut header = Vec::new();
+    write_varint(header_cbor.len() as u64, &mut header);
+    header.extend_from_slice(&header_cbor);
+
+    let encode = |block: &Block, out: &mut Vec<u8>| {
+        write_varint((block.cid.len() + block.data.len()) as u64, out);
+        out.extend_from_slice(&block.cid);
+        out.extend_from_slice(&block.data);
+    };
+
+    let mut nodes = header;
+    for block in blocks.iter().filter(|block| !block.leaf) {
+        encode(block, &mut nodes);
+    }
+
+    let mut cars = vec![nodes.clone()];
+
+    for block in blocks.iter().filter(|block| block.leaf) {
+        let car = cars.last_mut().unwrap();
+
+        if car.len() > nodes.len() && car.len() + block.data.len() > MAX_CAR_SIZE {
+            cars.push(nodes.clone());
+        }
+
+        encode(block, cars.last_mut().unwrap());
+    }
+
+    cars
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// CIDs of `ipfs add --cid-version=1 --raw-leaves`.
+    const EMPTY_FILE_CID: &str = "bafkreihdwdcefgh4dqkjv67uzcmw7ojee6xedzdetojuzjevtenxquvyku";
+    const HELLO_WORLD_CID: &str = "bafkreifjjcie6lypi6ny7amxnfftagclbuxndqonfipmb64f2km2devei4";
+    const EMPTY_DIRECTORY_CID: &str = "bafybeiczsscdsbs7ffqz55asqdf3smv6klcw3gofszvwlyarci47bgf354";
+
+    #[test]
+    fn varint() {
+        let mut out = Vec::new();
+        write_varint(1, &mut out);
+        write_varint(127, &mut out);
+        write_varint(128, &mut out);
+        write_varint(300, &mut out);
+        write_varint(BLOCK_SIZE as u64, &mut out);
+
+        assert_eq!(out, [0x01, 0x7f, 0x80, 0x01, 0xac, 0x02, 0x80, 0x80, 0x10]);
+    }
+
+    #[test]
+    fn small_file_cid() {
+        let mut blocks = Vec::new();
+
+        let node = import_file(b"", &mut blocks);
+        assert_eq!(cid_to_string(&node.cid), EMPTY_FILE_CID);
+
+        let node = import_file(b"hello world\n", &mut blocks);
+        assert_eq!(cid_to_string(&node.cid), HELLO_WORLD_CID);
+        assert_eq!(node.size, 12);
+
+        assert_eq!(blocks.len(), 2);
+        assert!(blocks.iter().all(|block| block.leaf));
+    }
+
+    #[test]
+    fn empty_directory_cid() {
+        let mut blocks = Vec::new();
+        let node = import_directory(Vec::new(), &mut blocks);
+
+        assert_eq!(cid_to_string(&node.cid), EMPTY_DIRECTORY_CID);
+        assert_eq!(blocks[0].data, [0x0a, 0x02, 0x08, 0x01]);
+    }
+
+    #[test]
+    fn directory_links() {
+        let mut blocks = Vec::new();
+        let hello = import_file(b"hello world\n", &mut blocks);
+        let empty = import_file(b"", &mut blocks);
+        let node = import_directory(
+            vec![("b.txt".to_string(), hello), ("a.txt".to_string(), empty)],
+            &mut blocks,
+        );
+
+        // links are sorted by name, with the cumulative size of each entry
+        let mut expected = Vec::new();
+        for (cid, name, size) in [(EMPTY_FILE_CID, "a.txt", 0), (HELLO_WORLD_CID, "b.txt", 12)] {
+            let cid = BASE32_NOPAD
+                .decode(cid[1..].to_uppercase().as_bytes())
+                .unwrap();
+            expected.extend_from_slice(&[0x12, 0x2f, 0x0a, 0x24]);
+            expected.extend_from_slice(&cid);
+            expected.extend_from_slice(&[0x12, 0x05]);
+            expected.extend_from_slice(name.as_bytes());
+            expected.extend_from_slice(&[0x18, size]);
+        }
+        expected.extend_from_slice(&[0x0a, 0x02, 0x08, 0x01]);
+
+        assert_eq!(blocks.last().unwrap().data, expected);
+        assert_eq!(node.size, expected.len() as u64 + 12);
+    }
+
+    #[test]
+    fn chunked_file() {
+        let data = vec![7u8; 2 * BLOCK_SIZE + 1];
+        let mut blocks = Vec::new();
+        let node = import_file(&data, &mut blocks);
+
+        assert_eq!(blocks.len(), 4);
+        assert_eq!(node.file_size, data.len() as u64);
+
+        let root = blocks.last().unwrap();
+        assert!(!root.leaf);
+        assert_eq!(root.cid, node.cid);
+
+        // links to the raw blocks have an empty name, followed by the UnixFS
+        // data with the file size and the size of each block
+        let mut expected = Vec::new();
+        for (block, size) in blocks[..3].iter().zip([BLOCK_SIZE, BLOCK_SIZE, 1]) {
+            let mut link = Vec::new();
+            write_pb_bytes(1, &block.cid, &mut link);
+            link.extend_from_slice(&[0x12, 0x00]);
+            write_pb_varint(3, size as u64, &mut link);
+            write_pb_bytes(2, &link, &mut expected);
+        }
+        let unixfs = [
+            0x08, 0x02, 0x18, 0x81, 0x80, 0x20, 0x20, 0x80, 0x80, 0x10, 0x20, 0x80, 0x80, 0x10,
+            0x20, 0x01,
+        ];
+        write_pb_bytes(1, &unixfs, &mut expected);
+
+        assert_eq!(root.data, expected);
+        assert_eq!(node.size, expected.len() as u64 + data.len() as u64);
+    }
+
+    #[test]
+    fn balanced_file() {
+        let data = vec![0u8; (MAX_LINKS + 1) * BLOCK_SIZE];
+        let mut blocks = Vec::new();
+        let node = import_file(&data, &mut blocks);
+
+        // the blocks do not fit in one node, so the root links to a full node
+        // and a node with the last block
+        let nodes: Vec<&Block> = blocks.iter().filter(|block| !block.leaf).collect();
+        assert_eq!(nodes.len(), 3);
+        assert_eq!(nodes[2].cid, node.cid);
+        assert_eq!(node.file_size, data.len() as u64);
+    }
+
+    #[test]
+    fn car_header() {
+        let mut blocks = Vec::new();
+        let root = import_directory(Vec::new(), &mut blocks);
+        let cars = encode_cars(&root.cid, &blocks);
+
+        // dag-cbor { "roots": [CID], "version": 1 } followed by the root block
+        let mut expected = vec![0x3a, 0xa2, 0x65];
+        expected.extend_from_slice(b"roots");
+        expected.extend_from_slice(&[0x81, 0xd8, 0x2a, 0x58, 0x25, 0x00]);
+        expected.extend_from_slice(&root.cid);
+        expected.push(0x67);
+        expected.extend_from_slice(b"version");
+        expected.push(0x01);
+        expected.push(0x28);
+        expected.extend_from_slice(&root.cid);
+        expected.extend_from_slice(&blocks[0].data);
+
+        assert_eq!(cars, [expected]);
+    }
+
+    #[test]
+    fn car_split() {
+        let data = vec![1u8; MAX_CAR_SIZE + BLOCK_SIZE];
+        let mut blocks = Vec::new();
+        let file = import_file(&data, &mut blocks);
+        let root = import_directory(vec![("0.png".to_string(), file)], &mut blocks);
+        let cars = encode_cars(&root.cid, &blocks);
+
+        // every CAR file starts with the same header and dag-pb nodes, followed
+        // by as many raw blocks (varint, CID and data) as fit in the maximum size
+        let leaf_len = 3 + 36 + BLOCK_SIZE;
+        assert_eq!(cars.len(), 2);
+        let nodes_len = cars[1].len() - 2 * leaf_len;
+        assert_eq!(cars[0].len(), nodes_len + 39 * leaf_len);
+        assert_eq!(cars[0][..nodes_len], cars[1][..nodes_len]);
+        assert!(cars[0].len() <= MAX_CAR_SIZE);
+    }
+}
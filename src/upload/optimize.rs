@@ -0,0 +1,249 @@
+use image::{
+    codecs::{
+        jpeg::JpegEncoder,
+        png::{CompressionType, FilterType as PngFilterType, PngEncoder},
+    },
+    imageops::FilterType,
+    io::Reader as ImageReader,
+    DynamicImage, ImageEncoder,
+};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    ffi::OsStr,
+    fs,
+    path::Path,
+};
+
+use crate::common::*;
+use crate::config::OptimizeSettings;
+use crate::upload::{hash_file, AssetPair};
+
+/// Directory of the optimized images, relative to the working directory.
+pub const OPTIMIZED_DIR: &str = ".sugar/optimized";
+
+/// Record of the resized images of a directory, so that the images that did
+/// not change are not resized again.
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Resized copy of an image, keyed by its file name in the manifest.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ResizedImage {
+    /// Hash of the original image.
+    source_hash: String,
+    max_dimension: u32,
+    quality: u8,
+    /// Whether the copy is used instead of the original; unused copies are
+    /// not kept.
+    used: bool,
+}
+
+/// Sizes of the images before and after the optimization.
+#[derive(Debug, Default)]
+pub struct OptimizeSummary {
+    pub images: usize,
+    pub optimized: usize,
+    pub original_size: u64,
+    pub optimized_size: u64,
+}
+
+/// Resize and compress the png and jpg images of the assets, replacing the
+/// image of each asset pair with its optimized copy when it is smaller. The
+/// original files are not modified.
+pub fn optimize_images(
+    asset_pairs: &mut HashMap<usize, AssetPair>,
+    settings: &OptimizeSettings,
+) -> Result<OptimizeSummary> {
+    let copies = resize_images(
+        OPTIMIZED_DIR,
+        asset_pairs,
+        settings.max_dimension(),
+        settings.quality(),
+        |original_size, copy_size, _| copy_size < original_size,
+    )?;
+
+    let mut summary = OptimizeSummary::default();
+
+    for (index, copy) in copies {
+        let pair = asset_pairs
+            .get_mut(&index)
+            .expect("Optimized image of a missing asset");
+        let original_size = fs::metadata(&pair.image)?.len();
+
+        summary.images += 1;
+        summary.original_size += original_size;
+
+        match copy {
+            Some(copy) => {
+                summary.optimized += 1;
+                summary.optimized_size += fs::metadata(&copy)?.len();
+                pair.image_hash = hash_file(&copy)?;
+                pair.image = copy;
+            }
+            None => summary.optimized_size += original_size,
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Write resized copies of the png and jpg images of the assets to a
+/// directory, in the same format, reusing the copies of the images that did
+/// not change. Returns the path of the copy of each image, if the copy is
+/// used as decided by `use_copy(original_size, copy_size, resized)`.
+pub fn resize_images(
+    dir: &str,
+    asset_pairs: &HashMap<usize, AssetPair>,
+    max_dimension: u32,
+    quality: u8,
+    use_copy: fn(u64, u64, bool) -> bool,
+) -> Result<Vec<(usize, Option<String>)>> {
+    if !(1..=100).contains(&quality) {
+        return Err(anyhow!(
+            "Invalid image quality {}, it must be between 1 and 100",
+            quality
+        ));
+    }
+
+    if max_dimension == 0 {
+        return Err(anyhow!("Invalid image max dimension, it must be positive"));
+    }
+
+    let dir_path = Path::new(dir);
+    fs::create_dir_all(dir_path)
+        .map_err(|err| anyhow!("Could not create directory '{}': {}", dir, err))?;
+
+    let manifest_path = dir_path.join(MANIFEST_FILE);
+    let manifest: BTreeMap<String, ResizedImage> = match fs::read_to_string(&manifest_path) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_default(),
+        Err(_) => BTreeMap::new(),
+    };
+
+    let images: Vec<(usize, &AssetPair)> = asset_pairs
+        .iter()
+        .filter(|(_, pair)| is_resizable(&pair.image))
+        .map(|(index, pair)| (*index, pair))
+        .collect();
+
+    let results = images
+        .par_iter()
+        .map(|(index, pair)| {
+            let file_name = Path::new(&pair.image)
+                .file_name()
+                .and_then(OsStr::to_str)
+                .unwrap_or_default()
+                .to_string();
+            let output = dir_path.join(&file_name);
+
+            let expected = ResizedImage {
+                source_hash: pair.image_hash.clone(),
+                max_dimension,
+                quality,
+                used: false,
+            };
+
+            let resized = match manifest.get(&file_name) {
+                // only the images that changed are resized again
+                Some(previous)
+                    if previous.source_hash == expected.source_hash
+                        && previous.max_dimension == expected.max_dimension
+                        && previous.quality == expected.quality
+                        && (!previous.used || output.exists()) =>
+                {
+                    previous.clone()
+                }
+                _ => {
+                    let source = Path::new(&pair.image);
+                    let (data, resized) = resize_image(source, max_dimension, quality)?;
+                    let used = use_copy(fs::metadata(source)?.len(), data.len() as u64, resized);
+
+                    if used {
+                        fs::write(&output, data)?;
+                    } else {
+                        let _ = fs::remove_file(&output);
+                    }
+
+                    ResizedImage { used, ..expected }
+                }
+            };
+
+            let copy = resized.used.then(|| output.to_string_lossy().to_string());
+
+            Ok((*index, file_name, copy, resized))
+        })
+        .collect::<Result<Vec<(usize, String, Option<String>, ResizedImage)>>>()?;
+
+    let mut updated = BTreeMap::new();
+    let mut copies = Vec::with_capacity(results.len());
+
+    for (index, file_name, copy, resized) in results {
+        updated.insert(file_name, resized);
+        copies.push((index, copy));
+    }
+
+    // copies of removed assets are deleted with their entry
+    for file_name in manifest.keys() {
+        if !updated.contains_key(file_name) {
+            let _ = fs::remove_file(dir_path.join(file_name));
+        }
+    }
+
+    fs::write(manifest_path, serde_json::to_string_pretty(&updated)?)?;
+
+    Ok(copies)
+}
+
+fn is_resizable(image: &str) -> bool {
+    Path::new(image)
+        .extension()
+        .and_then(OsStr::to_str)
+        .map(|extension| matches!(extension.to_lowercase().as_str(), "png" | "jpg" | "jpeg"))
+        .unwrap_or(false)
+}
+
+/// Encode an image in its format, scaled down to fit the max dimension,
+/// returning the data and whether the image was scaled down.
+fn resize_image(source: &Path, max_dimension: u32, quality: u8) -> Result<(Vec<u8>, bool)> {
+    let error = |err: String| anyhow!("Could not resize image '{}': {}", source.display(), err);
+
+    let mut image = ImageReader::open(source)
+        .map_err(|err| error(err.to_string()))?
+        .with_guessed_format()
+        .map_err(|err| error(err.to_string()))?
+        .decode()
+        .map_err(|err| error(err.to_string()))?;
+
+    let resized = image.width() > max_dimension || image.height() > max_dimension;
+
+    if resized {
+        // scales the image to fit, keeping its aspect ratio
+        image = image.resize(max_dimension, max_dimension, FilterType::Lanczos3);
+    }
+
+    let mut data = Vec::new();
+    let is_png = source
+        .extension()
+        .and_then(OsStr::to_str)
+        .map_or(false, |extension| extension.eq_ignore_ascii_case("png"));
+
+    if is_png {
+        PngEncoder::new_with_quality(&mut data, CompressionType::Best, PngFilterType::Adaptive)
+            .write_image(
+                image.as_bytes(),
+                image.width(),
+                image.height(),
+                image.color(),
+            )
+            .map_err(|err| error(err.to_string()))?;
+    } else {
+        // jpg images have no transparency
+        let image = DynamicImage::ImageRgb8(image.to_rgb8());
+        JpegEncoder::new_with_quality(&mut data, quality)
+            .encode_image(&image)
+            .map_err(|err| error(err.to_string()))?;
+    }
+
+    Ok((data, resized))
+}
@@ -0,0 +1,336 @@
+use async_trait::async_trait;
+use console::style;
+use reqwest::multipart::{Form, Part};
+use std::{
+    collections::BTreeMap,
+    ffi::OsStr,
+    fs,
+    path::Path,
+    sync::{atomic::AtomicBool, Arc},
+};
+
+use crate::credentials::{PINATA_API_KEY, PINATA_JWT, PINATA_SECRET_API_KEY};
+use crate::events::{error_event, upload_event};
+use crate::retry::{retry_async, Subsystem};
+use crate::{common::*, config::*, constants::PARALLEL_LIMIT, upload::*, utils::*};
+
+/// Pinata endpoint to pin a file to IPFS.
+const PINATA_PIN_FILE: &str = "https://api.pinata.cloud/pinning/pinFileToIPFS";
+
+/// Pinata credentials, either a JWT or an API key pair.
+#[derive(Debug, Clone)]
+enum PinataAuth {
+    Jwt(String),
+    ApiKey { key: String, secret: String },
+}
+
+struct PinInfo {
+    asset_id: String,
+    file_path: String,
+    image_link: String,
+    data_type: DataType,
+    content_type: String,
+    animation_link: Option<String>,
+    preview_link: Option<String>,
+    template_variables: TemplateVariables,
+    locales: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct PinResponse {
+    ipfs_hash: String,
+}
+
+pub struct PinataHandler {
+    client: Arc<HttpClient>,
+    auth: Arc<PinataAuth>,
+    gateway: Option<String>,
+}
+
+impl PinataHandler {
+    /// Initialize a new PinataHandler.
+    pub async fn initialize(config_data: &ConfigData) -> Result<PinataHandler> {
+        // without a JWT or an API key pair, the JWT is prompted for
+        let auth = match (
+            PINATA_JWT.lookup(&config_data.pinata_jwt),
+            PINATA_API_KEY.lookup(&config_data.pinata_api_key),
+            PINATA_SECRET_API_KEY.lookup(&config_data.pinata_secret_api_key),
+        ) {
+            (Some(jwt), _, _) => PinataAuth::Jwt(jwt),
+            (None, Some(key), Some(secret)) => PinataAuth::ApiKey { key, secret },
+            _ => PinataAuth::Jwt(PINATA_JWT.prompt()?),
+        };
+
+        Ok(PinataHandler {
+            client: Arc::new(http_client()),
+            auth: Arc::new(auth),
+            gateway: config_data
+                .pinata_gateway
+                .as_ref()
+                .map(|gateway| gateway.trim_end_matches('/').to_string()),
+        })
+    }
+
+    /// Return the link of a pinned file, using the gateway if one is set.
+    fn link(gateway: &Option<String>, cid: &str) -> String {
+        match gateway {
+            Some(gateway) => format!("{}/ipfs/{}", gateway, cid),
+            None => format!("ipfs://{}", cid),
+        }
+    }
+
+    /// Pin a file to IPFS and wait for a response.
+    async fn send_to_pinata(
+        client: Arc<HttpClient>,
+        auth: Arc<PinataAuth>,
+        gateway: Option<String>,
+        info: PinInfo,
+    ) -> Result<(String, String)> {
+        let data = match info.data_type {
+            DataType::Image => fs::read(&info.file_path)?,
+            DataType::Metadata => {
+                // replaces the image link without modifying the original file to avoid
+                // changing the hash of the metadata file
+                let metadata = get_updated_metadata(
+                    &info.file_path,
+                    &info.image_link,
+                    info.animation_link.clone(),
+                    info.preview_link.clone(),
+                    &info.template_variables,
+                )?;
+
+                if info.locales.is_empty() {
+                    metadata.into_bytes()
+                } else {
+                    // the localized variants are pinned first so that their
+                    // links can be recorded in the metadata
+                    let mut localization = BTreeMap::new();
+
+                    for (locale, file_path) in &info.locales {
+                        let variant = get_updated_metadata(
+                            file_path,
+                            &info.image_link,
+                            info.animation_link.clone(),
+                            info.preview_link.clone(),
+                            &info.template_variables,
+                        )?;
+                        let cid = PinataHandler::pin_file(
+                            &client,
+                            &auth,
+                            file_path,
+                            &info.content_type,
+                            variant.into_bytes(),
+                        )
+                        .await?;
+                        localization.insert(locale.clone(), PinataHandler::link(&gateway, &cid));
+                    }
+
+                    set_localization(&metadata, localization)?.into_bytes()
+                }
+            }
+            DataType::Animation | DataType::Preview => fs::read(&info.file_path)?,
+        };
+
+        let cid =
+            PinataHandler::pin_file(&client, &auth, &info.file_path, &info.content_type, data)
+                .await?;
+
+        Ok((info.asset_id, PinataHandler::link(&gateway, &cid)))
+    }
+
+    /// Pin the data of a file, returning its CID.
+    async fn pin_file(
+        client: &HttpClient,
+        auth: &PinataAuth,
+        file_path: &str,
+        content_type: &str,
+        data: Vec<u8>,
+    ) -> Result<String> {
+        let file_name = Path::new(file_path)
+            .file_name()
+            .and_then(OsStr::to_str)
+            .unwrap_or(file_path)
+            .to_string();
+
+        retry_async(Subsystem::Upload, || async {
+            let part = Part::bytes(data.clone())
+                .file_name(file_name.clone())
+                .mime_str(content_type)?;
+
+            let request = client
+                .post(PINATA_PIN_FILE)
+                .multipart(Form::new().part("file", part));
+
+            let request = match auth {
+                PinataAuth::Jwt(jwt) => request.bearer_auth(jwt),
+                PinataAuth::ApiKey { key, secret } => request
+                    .header("pinata_api_key", key)
+                    .header("pinata_secret_api_key", secret),
+            };
+
+            let response: PinResponse = request.send().await?.error_for_status()?.json().await?;
+
+            Ok(response.ipfs_hash)
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl UploadHandler for PinataHandler {
+    /// Nothing to do, Pinata client ready for the upload.
+    async fn prepare(
+        &self,
+        _sugar_config: &SugarConfig,
+        _assets: &HashMap<usize, AssetPair>,
+        _image_indices: &[usize],
+        _metadata_indices: &[usize],
+        _animation_indices: &[usize],
+        _preview_indices: &[usize],
+        _dry_run: bool,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Pin the data to IPFS.
+    async fn upload_data(
+        &self,
+        _sugar_config: &SugarConfig,
+        assets: &HashMap<usize, AssetPair>,
+        cache: &mut Cache,
+        indices: &[usize],
+        data_type: DataType,
+        interrupted: Arc<AtomicBool>,
+    ) -> Result<Vec<UploadError>> {
+        let mut paths = Vec::new();
+
+        for index in indices {
+            let item = assets.get(index).unwrap();
+            // chooses the file path based on the data type
+            let file_path = match data_type {
+                DataType::Image => item.image.clone(),
+                DataType::Metadata => item.metadata.clone(),
+                DataType::Animation => item.animation.clone().unwrap(),
+                DataType::Preview => item.preview.clone().unwrap(),
+            };
+
+            let locales = match data_type {
+                DataType::Metadata => item.locales.clone(),
+                _ => BTreeMap::new(),
+            };
+
+            paths.push((file_path, locales));
+        }
+
+        println!("\nSending data: (Ctrl+C to abort)");
+
+        let pb = progress_bar_with_style(paths.len() as u64);
+        let mut pins = Vec::new();
+
+        for (file_path, locales) in paths {
+            // path to the image/metadata file
+            let path = Path::new(&file_path);
+            // id of the asset (to be used to update the cache link)
+            let asset_id = String::from(
+                path.file_stem()
+                    .and_then(OsStr::to_str)
+                    .expect("Failed to get convert path file ext to valid unicode."),
+            );
+            let cache_item = match cache.items.0.get(&asset_id) {
+                Some(item) => item,
+                None => {
+                    return Err(anyhow::anyhow!(
+                        "Failed to get config item at index: {}",
+                        asset_id
+                    ))
+                }
+            };
+
+            pins.push(PinInfo {
+                asset_id: asset_id.to_string(),
+                file_path: String::from(
+                    path.to_str().expect("Failed to convert path from unicode."),
+                ),
+                image_link: cache_item.image_link.clone(),
+                data_type: data_type.clone(),
+                content_type: content_type(&file_path, &data_type)?,
+                animation_link: cache_item.animation_link.clone(),
+                preview_link: cache_item.preview_link.clone(),
+                template_variables: cache.program.template_variables.clone(),
+                locales,
+            });
+        }
+
+        let mut errors = Vec::new();
+        let (client, auth, gateway) = (self.client.clone(), self.auth.clone(), &self.gateway);
+        let pins = pins
+            .into_iter()
+            .map(|pin| (pin.asset_id.clone(), pin))
+            .collect();
+
+        let not_started = run_bounded(
+            pins,
+            upload_concurrency(PARALLEL_LIMIT),
+            &interrupted,
+            |pin| PinataHandler::send_to_pinata(client.clone(), auth.clone(), gateway.clone(), pin),
+            |asset_id, result| {
+                match result {
+                    Ok((_, link)) => {
+                        // cache item to update
+                        let item = cache.get_item_mut(&asset_id).unwrap();
+
+                        match data_type {
+                            DataType::Image => item.image_link = link.clone(),
+                            DataType::Metadata => item.metadata_link = link.clone(),
+                            DataType::Animation => item.animation_link = Some(link.clone()),
+                            DataType::Preview => item.preview_link = Some(link.clone()),
+                        }
+                        // updates the progress bar
+                        pb.inc(1);
+                        upload_event(
+                            data_type.stage(),
+                            &asset_id,
+                            &link,
+                            pb.position(),
+                            pb.length(),
+                        );
+                    }
+                    Err(err) => {
+                        // user will need to retry the upload
+                        let error = format!("Pinata upload error: {:?}", err);
+                        error_event(
+                            data_type.stage(),
+                            Some(&asset_id),
+                            pb.position(),
+                            pb.length(),
+                            &error,
+                        );
+                        cache.record_error(&asset_id, &error);
+                        errors.push(UploadError::SendDataFailed(error));
+                    }
+                }
+
+                // syncs cache (checkpoint)
+                cache.checkpoint()
+            },
+        )
+        .await?;
+
+        if !errors.is_empty() {
+            pb.abandon_with_message(format!("{}", style("Upload failed ").red().bold()));
+        } else if not_started > 0 {
+            pb.abandon_with_message(format!("{}", style("Upload aborted ").red().bold()));
+            return Err(
+                UploadError::SendDataFailed("Not all files were uploaded.".to_string()).into(),
+            );
+        } else {
+            pb.finish_with_message(format!("{}", style("Upload successful ").green().bold()));
+        }
+
+        // makes sure the cache file is updated
+        cache.sync_file()?;
+
+        Ok(errors)
+    }
+}
@@ -0,0 +1,143 @@
+use futures::{stream::FuturesUnordered, Future, StreamExt};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, OnceLock,
+    },
+    time::{Duration, Instant},
+};
+use tokio::time::sleep_until;
+
+use crate::common::*;
+use crate::upload::record_latency;
+
+/// Concurrency and rate limit of the uploads, set from the command line or
+/// the config file.
+static LIMITS: OnceLock<Mutex<UploadLimits>> = OnceLock::new();
+
+#[derive(Debug, Default)]
+struct UploadLimits {
+    concurrency: Option<usize>,
+    /// Minimum interval between the start of two upload tasks.
+    interval: Option<Duration>,
+    /// Earliest time the next upload task can start.
+    next_start: Option<Instant>,
+}
+
+/// Set the maximum number of concurrent uploads and the maximum number of
+/// uploads started per second.
+pub fn configure_upload_limits(concurrency: Option<usize>, rate_limit: Option<f64>) -> Result<()> {
+    if concurrency == Some(0) {
+        return Err(anyhow!("Upload concurrency must be greater than zero."));
+    }
+
+    let interval = match rate_limit {
+        Some(rate) if rate.is_finite() && rate > 0.0 => Some(Duration::from_secs_f64(1.0 / rate)),
+        Some(rate) => {
+            return Err(anyhow!(
+                "Upload rate limit must be greater than zero, found {}.",
+                rate
+            ))
+        }
+        None => None,
+    };
+
+    let limits = LIMITS.get_or_init(|| Mutex::new(UploadLimits::default()));
+
+    if let Ok(mut limits) = limits.lock() {
+        *limits = UploadLimits {
+            concurrency,
+            interval,
+            next_start: None,
+        };
+    }
+
+    Ok(())
+}
+
+/// Return the configured upload concurrency, or the default of the caller.
+pub fn upload_concurrency(default: usize) -> usize {
+    LIMITS
+        .get()
+        .and_then(|limits| limits.lock().ok().and_then(|limits| limits.concurrency))
+        .unwrap_or(default)
+}
+
+/// Wait until the rate limit allows a new upload task to start.
+async fn throttle() {
+    let start = match LIMITS.get().and_then(|limits| limits.lock().ok()) {
+        Some(mut limits) => match limits.interval {
+            Some(interval) => {
+                let now = Instant::now();
+                let start = limits.next_start.map_or(now, |next| next.max(now));
+                limits.next_start = Some(start + interval);
+                start
+            }
+            None => return,
+        },
+        None => return,
+    };
+
+    sleep_until(start.into()).await;
+}
+
+/// Run a task for each item with at most `limit` tasks in flight, started no
+/// faster than the configured rate limit. The result of each task is passed to
+/// `on_result`, together with the id of its item, as soon as it completes and
+/// a new task is started in its place, so failed tasks do not reduce the
+/// concurrency of the remaining ones.
+///
+/// No new tasks are started once `interrupted` is set, but the tasks in flight
+/// are awaited so their results are not lost. Returns the number of items that
+/// were not started.
+pub async fn run_bounded<I, T, F, Fut, R>(
+    items: Vec<(String, I)>,
+    limit: usize,
+    interrupted: &AtomicBool,
+    task: F,
+    mut on_result: R,
+) -> Result<usize>
+where
+    F: Fn(I) -> Fut,
+    Fut: Future<Output = Result<T>> + Send + 'static,
+    T: Send + 'static,
+    R: FnMut(String, Result<T>) -> Result<()>,
+{
+    let spawn = |(id, item): (String, I)| {
+        let started = Instant::now();
+        let handle = tokio::spawn(task(item));
+        async move {
+            let joined = handle.await;
+            record_latency(started.elapsed());
+            (id, joined)
+        }
+    };
+
+    let mut pending = items.into_iter();
+    let mut running = FuturesUnordered::new();
+
+    if !interrupted.load(Ordering::SeqCst) {
+        for item in pending.by_ref().take(limit.max(1)) {
+            throttle().await;
+            running.push(spawn(item));
+        }
+    }
+
+    while let Some((id, joined)) = running.next().await {
+        let result = match joined {
+            Ok(result) => result,
+            Err(err) => Err(anyhow!("Task failed: {}", err)),
+        };
+
+        on_result(id, result)?;
+
+        if !interrupted.load(Ordering::SeqCst) {
+            if let Some(item) = pending.next() {
+                throttle().await;
+                running.push(spawn(item));
+            }
+        }
+    }
+
+    Ok(pending.count())
+}
@@ -0,0 +1,36 @@
+use std::collections::HashMap;
+
+use crate::common::*;
+use crate::config::PreviewSettings;
+use crate::upload::{hash_file, resize_images, AssetPair};
+
+/// Directory of the image previews, relative to the working directory.
+pub const PREVIEWS_DIR: &str = ".sugar/previews";
+
+/// Generate a downscaled preview of the png and jpg images of the assets,
+/// recording it in each asset pair. Returns the number of previews.
+pub fn generate_previews(
+    asset_pairs: &mut HashMap<usize, AssetPair>,
+    settings: &PreviewSettings,
+) -> Result<usize> {
+    let copies = resize_images(
+        PREVIEWS_DIR,
+        asset_pairs,
+        settings.max_dimension(),
+        settings.quality(),
+        // images that already fit are their own preview
+        |_, _, resized| resized,
+    )?;
+
+    let mut count = 0;
+
+    for (index, copy) in copies {
+        if let (Some(copy), Some(pair)) = (copy, asset_pairs.get_mut(&index)) {
+            pair.preview_hash = Some(hash_file(&copy)?);
+            pair.preview = Some(copy);
+            count += 1;
+        }
+    }
+
+    Ok(count)
+}
@@ -1,25 +1,37 @@
 use async_trait::async_trait;
 use console::style;
 use std::{
-    collections::HashSet,
+    collections::{BTreeMap, HashSet},
+    ffi::OsStr,
+    fmt::{self, Display, Formatter},
+    fs,
+    path::Path,
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
     },
 };
 
-use crate::cache::{load_cache, Cache};
+use crate::cache::{load_cache, Cache, CacheItem};
 use crate::common::*;
-use crate::config::{data::SugarConfig, get_config_data, UploadMethod};
+use crate::config::{
+    data::{CollectionManifest, ConfigData, SugarConfig},
+    get_asset_overrides, get_config_data, UploadMethod,
+};
+use crate::price::usd_suffix;
+use crate::spend::track_spend;
 use crate::upload::bundlr::BundlrHandler;
 use crate::upload::*;
 use crate::utils::*;
-use crate::validate::format::Metadata;
+use crate::validate::{format::Metadata, parser::check_name};
 
 /// A trait for storage upload handlers.
 #[async_trait]
-pub trait UploadHandler {
-    /// Prepares the upload of the specified image/metadata files.
+pub trait UploadHandler: Send + Sync {
+    /// Prepares the upload of the specified image/metadata files. On a dry
+    /// run, the handler only reports what it would do (e.g., fund the
+    /// storage) without writing anything.
+    #[allow(clippy::too_many_arguments)]
     async fn prepare(
         &self,
         sugar_config: &SugarConfig,
@@ -27,6 +39,8 @@ pub trait UploadHandler {
         image_indices: &[usize],
         metadata_indices: &[usize],
         animation_indices: &[usize],
+        preview_indices: &[usize],
+        dry_run: bool,
     ) -> Result<()>;
 
     /// Upload the data to a (permanent) storage.
@@ -39,6 +53,52 @@ pub trait UploadHandler {
         data_type: DataType,
         interrupted: Arc<AtomicBool>,
     ) -> Result<Vec<UploadError>>;
+
+    /// Return the balance (in lamports) funding the uploads, if the storage
+    /// is paid from a balance.
+    async fn balance(&self) -> Result<Option<u64>> {
+        Ok(None)
+    }
+
+    /// Return the cost of uploading files of the specified sizes, if the
+    /// storage charges a fee per upload.
+    async fn estimate_cost(&self, _file_sizes: &[u64]) -> Result<Option<UploadCost>> {
+        Ok(None)
+    }
+}
+
+/// Cost of an upload, in the currency charged by the storage.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UploadCost {
+    Lamports(u64),
+    Winston(u64),
+}
+
+impl UploadCost {
+    /// Add two costs in the same currency.
+    pub fn checked_add(self, other: UploadCost) -> Option<UploadCost> {
+        match (self, other) {
+            (UploadCost::Lamports(a), UploadCost::Lamports(b)) => Some(UploadCost::Lamports(a + b)),
+            (UploadCost::Winston(a), UploadCost::Winston(b)) => Some(UploadCost::Winston(a + b)),
+            _ => None,
+        }
+    }
+}
+
+impl Display for UploadCost {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            UploadCost::Lamports(lamports) => write!(
+                f,
+                "{:.6} SOL{}",
+                *lamports as f64 / LAMPORTS_PER_SOL as f64,
+                usd_suffix(*lamports)
+            ),
+            UploadCost::Winston(winston) => {
+                write!(f, "{:.6} AR", *winston as f64 / WINSTON_PER_AR)
+            }
+        }
+    }
 }
 
 pub struct UploadArgs {
@@ -47,6 +107,10 @@ pub struct UploadArgs {
     pub keypair: Option<String>,
     pub rpc_url: Option<String>,
     pub cache: String,
+    pub concurrency: Option<usize>,
+    pub rate_limit: Option<f64>,
+    pub dry_run: bool,
+    pub bundlr_node: Option<String>,
     pub interrupted: Arc<AtomicBool>,
 }
 
@@ -54,16 +118,34 @@ pub struct AssetType {
     pub image: Vec<usize>,
     pub metadata: Vec<usize>,
     pub animation: Vec<usize>,
+    pub preview: Vec<usize>,
 }
 
 pub async fn process_upload(args: UploadArgs) -> Result<()> {
+    if !args.dry_run {
+        track_spend("upload", &args.cache);
+    }
+
     let sugar_config = sugar_setup(args.keypair, args.rpc_url)?;
-    let config_data = get_config_data(&args.config)?;
+    let mut config_data = get_config_data(&args.config)?;
+
+    // command line values take precedence over the config file
+    if args.bundlr_node.is_some() {
+        config_data.bundlr_node = args.bundlr_node;
+    }
+    configure_upload_limits(
+        args.concurrency.or(config_data.upload_concurrency),
+        args.rate_limit.or(config_data.upload_rate_limit),
+    )?;
 
     // loading assets
     println!(
         "{} {}Loading assets",
-        style("[1/4]").bold().dim(),
+        if args.dry_run {
+            style("[1/2]").bold().dim()
+        } else {
+            style("[1/4]").bold().dim()
+        },
         ASSETS_EMOJI
     );
 
@@ -71,10 +153,98 @@ pub async fn process_upload(args: UploadArgs) -> Result<()> {
     pb.enable_steady_tick(120);
     pb.set_message("Reading files...");
 
-    let asset_pairs = get_asset_pairs(&args.assets_dir)?;
+    let mut asset_pairs = get_asset_pairs(&args.assets_dir)?;
+
+    // the optimized images replace the originals in the upload
+    if let Some(settings) = &config_data.optimize {
+        pb.set_message("Optimizing images (unchanged images are skipped)...");
+        let summary = optimize_images(&mut asset_pairs, settings)?;
+        pb.println(format!(
+            "Optimized {} of {} image(s) into '{}': {:.1} MB -> {:.1} MB",
+            summary.optimized,
+            summary.images,
+            OPTIMIZED_DIR,
+            summary.original_size as f64 / (1024.0 * 1024.0),
+            summary.optimized_size as f64 / (1024.0 * 1024.0),
+        ));
+        pb.set_message("Reading files...");
+    }
+
+    // the previews are uploaded alongside the (full-resolution) images
+    if let Some(settings) = &config_data.previews {
+        pb.set_message("Generating previews (unchanged images are skipped)...");
+        let previews = generate_previews(&mut asset_pairs, settings)?;
+        pb.println(format!(
+            "Generated {} image preview(s) into '{}'",
+            previews, PREVIEWS_DIR
+        ));
+        pb.set_message("Reading files...");
+    }
+
     // creates/loads the cache
+    let new_cache = !Path::new(&args.cache).exists();
     let mut cache = load_cache(&args.cache, true)?;
 
+    // the backend of the config only applies to new cache files
+    if let Some(backend) = config_data.cache_backend {
+        if new_cache {
+            cache.backend = backend;
+        } else if backend != cache.backend {
+            pb.println(
+                style(format!(
+                    "The cache file uses the {} backend, run 'sugar cache convert {}' to \
+                    convert it.",
+                    cache.backend, backend
+                ))
+                .yellow()
+                .to_string(),
+            );
+        }
+    }
+
+    let variables = get_template_variables(&config_data, &cache, &asset_pairs);
+
+    let variables_changed = variables != cache.program.template_variables;
+
+    if variables_changed {
+        // the metadata files need to be uploaded again with the new values
+        for item in cache.items.0.values_mut() {
+            item.metadata_link = String::new();
+        }
+        cache.program.template_variables = variables;
+    }
+
+    // expands the placeholders and numbering of the names used on-chain
+    for (index, pair) in asset_pairs.iter_mut() {
+        pair.name = expand_template(&pair.name, *index, &cache.program.template_variables);
+
+        if let Some(numbering) = &config_data.name_numbering {
+            pair.name = numbering.apply(&pair.name, *index, config_data.number);
+
+            if check_name(&pair.name).is_err() {
+                return Err(UploadError::NameTooLong(
+                    index.to_string(),
+                    pair.name.clone(),
+                    MAX_NAME_LENGTH,
+                )
+                .into());
+            }
+        }
+    }
+
+    let overrides = get_asset_overrides(&config_data)?;
+
+    // applies the name overrides, which replace the name used on-chain
+    for (index, asset_override) in &overrides {
+        if let Some(pair) = asset_pairs.get_mut(index) {
+            if let Some(name) = &asset_override.name {
+                pair.name = name.clone();
+            }
+        } else {
+            warn!("Asset override for missing asset at index {}", index);
+        }
+    }
+
     // list of indices to upload
     // 0: image
     // 1: metadata
@@ -82,57 +252,122 @@ pub async fn process_upload(args: UploadArgs) -> Result<()> {
         image: Vec::new(),
         metadata: Vec::new(),
         animation: Vec::new(),
+        preview: Vec::new(),
     };
+    // why each asset needs to be uploaded, reported on a dry run
+    let mut reasons: HashMap<usize, &str> = HashMap::new();
 
     for (index, pair) in &asset_pairs {
         match cache.items.0.get_mut(&index.to_string()) {
             Some(item) => {
-                // determining animation condition
-                let animation_conditon =
-                    if item.animation_hash.is_some() && item.animation_link.as_ref().is_some() {
-                        !item.animation_hash.eq(&pair.animation_hash)
-                            || item.animation_link.as_ref().unwrap().is_empty()
-                    } else {
-                        false
-                    };
-
-                // has the image file changed?
-                if !&item.image_hash.eq(&pair.image_hash) || item.image_link.is_empty() {
-                    // we replace the entire item to trigger the image and metadata upload
-                    let item_clone = item.clone();
-                    cache
-                        .items
-                        .0
-                        .insert(index.to_string(), pair.clone().into_cache_item());
-                    // we need to upload both image/metadata
+                // each file is compared with the hash of the uploaded content, so
+                // only the files that changed (or failed to upload) are uploaded
+                let image_changed =
+                    !item.image_hash.eq(&pair.image_hash) || item.image_link.is_empty();
+                let animation_changed = !item.animation_hash.eq(&pair.animation_hash)
+                    || (pair.animation.is_some()
+                        && item
+                            .animation_link
+                            .as_ref()
+                            .map(|link| link.is_empty())
+                            .unwrap_or(true));
+                let preview_changed = !item.preview_hash.eq(&pair.preview_hash)
+                    || (pair.preview.is_some()
+                        && item
+                            .preview_link
+                            .as_ref()
+                            .map(|link| link.is_empty())
+                            .unwrap_or(true));
+                let metadata_changed =
+                    !item.metadata_hash.eq(&pair.metadata_hash) || item.metadata_link.is_empty();
+
+                // the error of the last upload of the item is cleared once
+                // all of its files are uploaded
+                if item.status == ItemStatus::Pending && item.last_error.is_some() {
+                    reasons.insert(*index, "previous upload failed");
+                }
+
+                if image_changed {
+                    reasons
+                        .entry(*index)
+                        .or_insert(if item.image_link.is_empty() {
+                            "missing image link"
+                        } else {
+                            "image changed"
+                        });
+                    item.image_hash = pair.image_hash.clone();
+                    item.image_link = String::new();
                     indices.image.push(*index);
-                    indices.metadata.push(*index);
+                }
+
+                if animation_changed {
+                    let missing = item
+                        .animation_link
+                        .as_ref()
+                        .map(|link| link.is_empty())
+                        .unwrap_or(false);
+                    reasons
+                        .entry(*index)
+                        .or_insert(if pair.animation.is_none() {
+                            "animation removed"
+                        } else if missing {
+                            "missing animation link"
+                        } else {
+                            "animation changed"
+                        });
+                    item.animation_hash = pair.animation_hash.clone();
 
-                    if item_clone.animation_hash.is_some() || item_clone.animation_link.is_some() {
+                    if pair.animation.is_some() {
+                        item.animation_link = Some(String::new());
                         indices.animation.push(*index);
+                    } else {
+                        item.animation_link = None;
                     }
-                } else if animation_conditon {
-                    // we replace the entire item to trigger the image and metadata upload
-                    cache
-                        .items
-                        .0
-                        .insert(index.to_string(), pair.clone().into_cache_item());
-                    // we need to upload both image/metadata
-                    indices.animation.push(*index);
-                    indices.image.push(*index);
-                    indices.metadata.push(*index);
-                } else if !item.metadata_hash.eq(&pair.metadata_hash)
-                    || item.metadata_link.is_empty()
-                {
-                    // triggers the metadata upload
+                }
+
+                if preview_changed {
+                    reasons.entry(*index).or_insert(if pair.preview.is_none() {
+                        "preview removed"
+                    } else {
+                        "preview changed"
+                    });
+                    item.preview_hash = pair.preview_hash.clone();
+
+                    if pair.preview.is_some() {
+                        item.preview_link = Some(String::new());
+                        indices.preview.push(*index);
+                    } else {
+                        item.preview_link = None;
+                    }
+                }
+
+                // the metadata records the links of the image, animation and
+                // preview, so it is uploaded again when any of them changes
+                if image_changed || animation_changed || preview_changed || metadata_changed {
+                    reasons.entry(*index).or_insert(
+                        if !item.metadata_hash.eq(&pair.metadata_hash) {
+                            "metadata changed"
+                        } else if variables_changed {
+                            "template variables changed"
+                        } else {
+                            "missing metadata link"
+                        },
+                    );
                     item.metadata_hash = pair.metadata_hash.clone();
                     item.metadata_link = String::new();
                     item.on_chain = false;
-                    // we need to upload metadata only
                     indices.metadata.push(*index);
                 }
+
+                if !item.name.eq(&pair.name) {
+                    // the name changed (e.g., an override), the config line
+                    // needs to be written again
+                    item.name = pair.name.clone();
+                    item.on_chain = false;
+                }
             }
             None => {
+                reasons.insert(*index, "new asset");
                 cache
                     .items
                     .0
@@ -144,20 +379,33 @@ pub async fn process_upload(args: UploadArgs) -> Result<()> {
                 if pair.animation_hash.clone().is_some() {
                     indices.animation.push(*index);
                 };
+
+                if pair.preview.is_some() {
+                    indices.preview.push(*index);
+                }
             }
         }
         // sanity check: verifies that both symbol and seller-fee-basis-points are the
         // same as the ones in the config file
-        let f = File::open(Path::new(&pair.metadata))?;
-        match serde_json::from_reader(f) {
+        let content = expand_template(
+            &fs::read_to_string(&pair.metadata)?,
+            *index,
+            &cache.program.template_variables,
+        );
+        match serde_json::from_str(&content) {
             Ok(metadata) => {
                 let metadata: Metadata = metadata;
-                // symbol check
-                if config_data.symbol.ne(&metadata.symbol) {
+                // symbol check (overridden assets can use a different symbol)
+                let symbol = overrides
+                    .get(index)
+                    .and_then(|o| o.symbol.as_ref())
+                    .unwrap_or(&config_data.symbol);
+
+                if symbol.ne(&metadata.symbol) {
                     return Err(UploadError::MismatchValue(
                         "symbol".to_string(),
                         pair.metadata.clone(),
-                        config_data.symbol,
+                        symbol.clone(),
                         metadata.symbol,
                     )
                     .into());
@@ -193,6 +441,9 @@ pub async fn process_upload(args: UploadArgs) -> Result<()> {
     if !indices.animation.is_empty() {
         println!("| animation | {:>6} |", indices.animation.len());
     }
+    if !indices.preview.is_empty() {
+        println!("| previews  | {:>6} |", indices.preview.len());
+    }
     println!("+--------------------+");
 
     // this should never happen, since every time we update the image file we
@@ -205,36 +456,43 @@ pub async fn process_upload(args: UploadArgs) -> Result<()> {
         )));
     }
 
-    let need_upload =
-        !indices.image.is_empty() || !indices.metadata.is_empty() || !indices.animation.is_empty();
+    let need_upload = !indices.image.is_empty()
+        || !indices.metadata.is_empty()
+        || !indices.animation.is_empty()
+        || !indices.preview.is_empty();
+
+    if args.dry_run {
+        return dry_run_upload(
+            &sugar_config,
+            &config_data,
+            &asset_pairs,
+            &indices,
+            &reasons,
+        )
+        .await;
+    }
 
     // ready to upload data
 
     let mut errors = Vec::new();
 
     if need_upload {
+        // animation and preview files are uploaded in their own steps
+        let steps = 4
+            + usize::from(!indices.animation.is_empty())
+            + usize::from(!indices.preview.is_empty());
+        let mut step = 2;
+
         println!(
             "\n{} {}Initializing upload",
-            if !indices.animation.is_empty() {
-                style("[2/5]").bold().dim()
-            } else {
-                style("[2/4]").bold().dim()
-            },
+            style(format!("[{}/{}]", step, steps)).bold().dim(),
             COMPUTER_EMOJI
         );
 
         let pb = spinner_with_style();
         pb.set_message("Connecting...");
 
-        let handler = match config_data.upload_method {
-            UploadMethod::Bundlr => Box::new(
-                BundlrHandler::initialize(&get_config_data(&args.config)?, &sugar_config).await?,
-            ) as Box<dyn UploadHandler>,
-            UploadMethod::AWS => {
-                Box::new(AWSHandler::initialize(&get_config_data(&args.config)?).await?)
-                    as Box<dyn UploadHandler>
-            }
-        };
+        let handler = initialize_handler(&config_data, &sugar_config).await?;
 
         pb.finish_with_message("Connected");
 
@@ -245,19 +503,20 @@ pub async fn process_upload(args: UploadArgs) -> Result<()> {
                 &indices.image,
                 &indices.metadata,
                 &indices.animation,
+                &indices.preview,
+                false,
             )
             .await?;
 
         // clear the interruption handler value ahead of the upload
         args.interrupted.store(false, Ordering::SeqCst);
 
+        let mut metrics = Vec::new();
+
+        step += 1;
         println!(
             "\n{} {}Uploading image files {}",
-            if !indices.animation.is_empty() {
-                style("[3/5]").bold().dim()
-            } else {
-                style("[3/4]").bold().dim()
-            },
+            style(format!("[{}/{}]", step, steps)).bold().dim(),
             UPLOAD_EMOJI,
             if indices.image.is_empty() {
                 "(skipping)"
@@ -267,18 +526,31 @@ pub async fn process_upload(args: UploadArgs) -> Result<()> {
         );
 
         if !indices.image.is_empty() {
-            errors.extend(
-                handler
-                    .upload_data(
-                        &sugar_config,
-                        &asset_pairs,
-                        &mut cache,
-                        &indices.image,
-                        DataType::Image,
-                        args.interrupted.clone(),
-                    )
-                    .await?,
-            );
+            let (uploads, duplicates) =
+                deduplicate_uploads(&mut cache, &indices.image, &DataType::Image);
+
+            if !uploads.is_empty() {
+                let recorder = MetricsRecorder::start(handler.as_ref(), DataType::Image).await;
+                errors.extend(
+                    handler
+                        .upload_data(
+                            &sugar_config,
+                            &asset_pairs,
+                            &mut cache,
+                            &uploads,
+                            DataType::Image,
+                            args.interrupted.clone(),
+                        )
+                        .await?,
+                );
+                metrics.push(
+                    recorder
+                        .finish(handler.as_ref(), &asset_pairs, &cache, &uploads)
+                        .await?,
+                );
+            }
+
+            copy_links(&mut cache, &duplicates, &DataType::Image);
 
             // updates the list of metadata indices since the image upload
             // might fail - removes any index that the image upload failed
@@ -294,32 +566,85 @@ pub async fn process_upload(args: UploadArgs) -> Result<()> {
             }
         }
 
-        if !indices.animation.is_empty() {
+        if !indices.preview.is_empty() {
+            step += 1;
             println!(
-                "\n{} {}Uploading animation files {}",
-                style("[4/5]").bold().dim(),
-                UPLOAD_EMOJI,
-                if indices.animation.is_empty() {
-                    "(skipping)"
-                } else {
-                    ""
-                }
+                "\n{} {}Uploading preview files",
+                style(format!("[{}/{}]", step, steps)).bold().dim(),
+                UPLOAD_EMOJI
             );
+
+            let (uploads, duplicates) =
+                deduplicate_uploads(&mut cache, &indices.preview, &DataType::Preview);
+
+            if !uploads.is_empty() {
+                let recorder = MetricsRecorder::start(handler.as_ref(), DataType::Preview).await;
+                errors.extend(
+                    handler
+                        .upload_data(
+                            &sugar_config,
+                            &asset_pairs,
+                            &mut cache,
+                            &uploads,
+                            DataType::Preview,
+                            args.interrupted.clone(),
+                        )
+                        .await?,
+                );
+                metrics.push(
+                    recorder
+                        .finish(handler.as_ref(), &asset_pairs, &cache, &uploads)
+                        .await?,
+                );
+            }
+
+            copy_links(&mut cache, &duplicates, &DataType::Preview);
+
+            // the metadata of a failed preview upload is not ready for upload
+            for index in &indices.preview {
+                let item = cache.items.0.get(&index.to_string()).unwrap();
+
+                if item.preview_link.as_ref().unwrap().is_empty() {
+                    indices.metadata.retain(|x| x != index);
+                }
+            }
         }
 
         if !indices.animation.is_empty() {
-            errors.extend(
-                handler
-                    .upload_data(
-                        &sugar_config,
-                        &asset_pairs,
-                        &mut cache,
-                        &indices.animation,
-                        DataType::Animation,
-                        args.interrupted.clone(),
-                    )
-                    .await?,
+            step += 1;
+            println!(
+                "\n{} {}Uploading animation files",
+                style(format!("[{}/{}]", step, steps)).bold().dim(),
+                UPLOAD_EMOJI
             );
+        }
+
+        if !indices.animation.is_empty() {
+            let (uploads, duplicates) =
+                deduplicate_uploads(&mut cache, &indices.animation, &DataType::Animation);
+
+            if !uploads.is_empty() {
+                let recorder = MetricsRecorder::start(handler.as_ref(), DataType::Animation).await;
+                errors.extend(
+                    handler
+                        .upload_data(
+                            &sugar_config,
+                            &asset_pairs,
+                            &mut cache,
+                            &uploads,
+                            DataType::Animation,
+                            args.interrupted.clone(),
+                        )
+                        .await?,
+                );
+                metrics.push(
+                    recorder
+                        .finish(handler.as_ref(), &asset_pairs, &cache, &uploads)
+                        .await?,
+                );
+            }
+
+            copy_links(&mut cache, &duplicates, &DataType::Animation);
 
             // updates the list of metadata indices since the image upload
             // might fail - removes any index that the image upload failed
@@ -335,13 +660,10 @@ pub async fn process_upload(args: UploadArgs) -> Result<()> {
             }
         }
 
+        step += 1;
         println!(
             "\n{} {}Uploading metadata files {}",
-            if !indices.animation.is_empty() {
-                style("[5/5]").bold().dim()
-            } else {
-                style("[4/4]").bold().dim()
-            },
+            style(format!("[{}/{}]", step, steps)).bold().dim(),
             UPLOAD_EMOJI,
             if indices.metadata.is_empty() {
                 "(skipping)"
@@ -351,6 +673,7 @@ pub async fn process_upload(args: UploadArgs) -> Result<()> {
         );
 
         if !indices.metadata.is_empty() {
+            let recorder = MetricsRecorder::start(handler.as_ref(), DataType::Metadata).await;
             errors.extend(
                 handler
                     .upload_data(
@@ -363,7 +686,14 @@ pub async fn process_upload(args: UploadArgs) -> Result<()> {
                     )
                     .await?,
             );
+            metrics.push(
+                recorder
+                    .finish(handler.as_ref(), &asset_pairs, &cache, &indices.metadata)
+                    .await?,
+            );
         }
+
+        print_upload_metrics(&metrics);
     } else {
         println!("\n....no files need uploading, skipping remaining steps.");
     }
@@ -375,14 +705,8 @@ pub async fn process_upload(args: UploadArgs) -> Result<()> {
 
     let mut count = 0;
 
-    for (_index, item) in cache.items.0 {
-        let has_animation = if let Some(animation_link) = item.animation_link {
-            animation_link.is_empty()
-        } else {
-            false
-        };
-
-        if !(item.image_link.is_empty() || item.metadata_link.is_empty() || has_animation) {
+    for (_index, item) in &cache.items.0 {
+        if item.is_uploaded() {
             count += 1;
         }
     }
@@ -433,5 +757,303 @@ pub async fn process_upload(args: UploadArgs) -> Result<()> {
         return Err(UploadError::Incomplete(message).into());
     }
 
+    // the manifest is (re-)uploaded when missing or the collection has changed
+    if let Some(manifest) = &config_data.collection_manifest {
+        if need_upload || cache.program.collection_manifest.is_none() {
+            upload_collection_manifest(&sugar_config, &config_data, manifest, &mut cache).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Return the hash and link of a file of the cache item.
+fn file_link<'a>(item: &'a CacheItem, data_type: &DataType) -> (Option<&'a str>, Option<&'a str>) {
+    match data_type {
+        DataType::Image => (Some(&item.image_hash), Some(&item.image_link)),
+        DataType::Animation => (
+            item.animation_hash.as_deref(),
+            item.animation_link.as_deref(),
+        ),
+        DataType::Preview => (item.preview_hash.as_deref(), item.preview_link.as_deref()),
+        DataType::Metadata => (Some(&item.metadata_hash), Some(&item.metadata_link)),
+    }
+}
+
+/// Split the indices to upload into the files that need to be uploaded and
+/// the files whose content is uploaded by another asset, paired with the
+/// index of that asset. Files whose content was already uploaded reuse the
+/// link straight away. Metadata files are always uploaded, since their
+/// content depends on the links of the asset.
+fn deduplicate_uploads(
+    cache: &mut Cache,
+    indices: &[usize],
+    data_type: &DataType,
+) -> (Vec<usize>, Vec<(usize, usize)>) {
+    if matches!(data_type, DataType::Metadata) {
+        return (indices.to_vec(), Vec::new());
+    }
+
+    let mut links: HashMap<String, String> = HashMap::new();
+
+    for item in cache.items.0.values() {
+        if let (Some(hash), Some(link)) = file_link(item, data_type) {
+            if !link.is_empty() {
+                links.insert(hash.to_string(), link.to_string());
+            }
+        }
+    }
+
+    let mut uploads = Vec::new();
+    let mut duplicates = Vec::new();
+    let mut sources: HashMap<String, usize> = HashMap::new();
+
+    for index in indices {
+        let item = match cache.items.0.get_mut(&index.to_string()) {
+            Some(item) => item,
+            None => continue,
+        };
+        let hash = match file_link(item, data_type).0 {
+            Some(hash) => hash.to_string(),
+            None => continue,
+        };
+
+        if let Some(link) = links.get(&hash) {
+            info!("Reusing the uploaded {} for index {}", link, index);
+            match data_type {
+                DataType::Image => item.image_link = link.clone(),
+                DataType::Preview => item.preview_link = Some(link.clone()),
+                _ => item.animation_link = Some(link.clone()),
+            }
+        } else if let Some(source) = sources.get(&hash) {
+            duplicates.push((*index, *source));
+        } else {
+            sources.insert(hash, *index);
+            uploads.push(*index);
+        }
+    }
+
+    (uploads, duplicates)
+}
+
+/// Copy the links of the uploaded files to the assets with the same content.
+fn copy_links(cache: &mut Cache, duplicates: &[(usize, usize)], data_type: &DataType) {
+    for (index, source) in duplicates {
+        let link = cache
+            .items
+            .0
+            .get(&source.to_string())
+            .and_then(|item| file_link(item, data_type).1)
+            .map(str::to_string);
+
+        if let (Some(link), Some(item)) = (link, cache.items.0.get_mut(&index.to_string())) {
+            match data_type {
+                DataType::Image => item.image_link = link,
+                DataType::Preview => item.preview_link = Some(link),
+                _ => item.animation_link = Some(link),
+            }
+        }
+    }
+}
+
+/// Report the files that would be uploaded and run the checks of the upload,
+/// without writing to the storage or the cache.
+async fn dry_run_upload(
+    sugar_config: &SugarConfig,
+    config_data: &ConfigData,
+    asset_pairs: &HashMap<usize, AssetPair>,
+    indices: &AssetType,
+    reasons: &HashMap<usize, &str>,
+) -> Result<()> {
+    println!(
+        "\n{} {}Checking upload (dry run)",
+        style("[2/2]").bold().dim(),
+        COMPUTER_EMOJI
+    );
+
+    for (name, data_type, type_indices) in [
+        ("Image", DataType::Image, &indices.image),
+        ("Animation", DataType::Animation, &indices.animation),
+        ("Preview", DataType::Preview, &indices.preview),
+        ("Metadata", DataType::Metadata, &indices.metadata),
+    ] {
+        if type_indices.is_empty() {
+            continue;
+        }
+
+        let groups = extension_groups(asset_pairs, type_indices, &data_type)
+            .iter()
+            .map(|(extension, count)| format!("{}: {}", extension, count))
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        let mut sorted = type_indices.clone();
+        sorted.sort_unstable();
+
+        println!("\n{} file(s) to upload ({}):", name, groups);
+
+        for index in sorted {
+            let pair = asset_pairs.get(&index).unwrap();
+            let file = match data_type {
+                DataType::Image => &pair.image,
+                DataType::Metadata => &pair.metadata,
+                DataType::Animation => pair.animation.as_ref().unwrap(),
+                DataType::Preview => pair.preview.as_ref().unwrap(),
+            };
+            println!(
+                "  {} {} ({})",
+                style(format!("#{index}")).dim(),
+                file,
+                reasons.get(&index).unwrap_or(&"missing link")
+            );
+        }
+    }
+
+    if indices.image.is_empty()
+        && indices.metadata.is_empty()
+        && indices.animation.is_empty()
+        && indices.preview.is_empty()
+    {
+        println!("\n....no files need uploading.");
+        return Ok(());
+    }
+
+    println!();
+
+    let handler = initialize_handler(config_data, sugar_config).await?;
+    handler
+        .prepare(
+            sugar_config,
+            asset_pairs,
+            &indices.image,
+            &indices.metadata,
+            &indices.animation,
+            &indices.preview,
+            true,
+        )
+        .await?;
+
+    println!(
+        "\n{}",
+        style("Dry run complete, no files were uploaded.").bold()
+    );
+
+    Ok(())
+}
+
+/// Return the number of files of a data type per extension. Files of
+/// different formats are uploaded together, each with its own content type.
+fn extension_groups(
+    asset_pairs: &HashMap<usize, AssetPair>,
+    indices: &[usize],
+    data_type: &DataType,
+) -> BTreeMap<String, usize> {
+    let mut groups = BTreeMap::new();
+
+    for index in indices {
+        let pair = asset_pairs.get(index).unwrap();
+        let file = match data_type {
+            DataType::Image => &pair.image,
+            DataType::Metadata => &pair.metadata,
+            DataType::Animation => pair.animation.as_ref().unwrap(),
+            DataType::Preview => pair.preview.as_ref().unwrap(),
+        };
+        let extension = Path::new(file)
+            .extension()
+            .and_then(OsStr::to_str)
+            .map(|extension| extension.to_lowercase())
+            .unwrap_or_else(|| "none".to_string());
+
+        *groups.entry(extension).or_insert(0) += 1;
+    }
+
+    groups
+}
+
+/// Upload the collection manifest and record its id in the cache.
+/// Return the values of the metadata template placeholders. The values are
+/// only set when the metadata files use placeholders.
+fn get_template_variables(
+    config_data: &ConfigData,
+    cache: &Cache,
+    asset_pairs: &HashMap<usize, AssetPair>,
+) -> TemplateVariables {
+    let uses_templates = asset_pairs.values().any(|pair| {
+        fs::read_to_string(&pair.metadata)
+            .map(|content| content.contains("{{"))
+            .unwrap_or(false)
+    });
+
+    if !uses_templates {
+        return TemplateVariables::new();
+    }
+
+    let mut variables = config_data.template_variables.clone().unwrap_or_default();
+
+    variables
+        .entry("symbol".to_string())
+        .or_insert_with(|| config_data.symbol.clone());
+
+    if let Some(manifest) = &config_data.collection_manifest {
+        variables
+            .entry("collection_name".to_string())
+            .or_insert_with(|| manifest.name.clone());
+    }
+
+    // the date of the first upload is kept so that resuming an upload on a
+    // different day does not change the metadata already uploaded
+    let date = cache
+        .program
+        .template_variables
+        .get(DATE_VARIABLE)
+        .cloned()
+        .unwrap_or_else(template_date);
+
+    variables.entry(DATE_VARIABLE.to_string()).or_insert(date);
+
+    variables
+}
+
+async fn upload_collection_manifest(
+    sugar_config: &SugarConfig,
+    config_data: &ConfigData,
+    manifest: &CollectionManifest,
+    cache: &mut Cache,
+) -> Result<()> {
+    if config_data.upload_method != UploadMethod::Bundlr {
+        warn!("Collection manifest is only supported with Bundlr uploads");
+        println!(
+            "\n{}",
+            style("Collection manifest skipped (only supported with Bundlr)").yellow()
+        );
+        return Ok(());
+    }
+
+    let pb = spinner_with_style();
+    pb.set_message("Uploading collection manifest...");
+
+    let data = CollectionManifestData {
+        name: manifest.name.clone(),
+        symbol: config_data.symbol.clone(),
+        count: cache.items.0.len(),
+        banner: manifest.banner.clone(),
+        items: cache
+            .items
+            .0
+            .values()
+            .map(|item| item.metadata_link.clone())
+            .collect(),
+    };
+
+    let handler = BundlrHandler::initialize(config_data, sugar_config).await?;
+    let id = handler
+        .upload_collection_manifest(sugar_config, &data)
+        .await?;
+
+    cache.program.collection_manifest = Some(id.clone());
+    cache.sync_file()?;
+
+    pb.finish_with_message(format!("{} {}", style("Collection manifest:").bold(), id));
+
     Ok(())
 }
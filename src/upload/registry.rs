@@ -0,0 +1,101 @@
+use futures::Future;
+use std::pin::Pin;
+
+use crate::common::*;
+use crate::config::{ConfigData, SugarConfig, UploadMethod};
+use crate::upload::*;
+
+/// Future returned by the constructor of an upload handler.
+pub type HandlerFuture<'a> = Pin<Box<dyn Future<Output = Result<Box<dyn UploadHandler>>> + 'a>>;
+
+/// Constructor of the upload handler of a method.
+pub type HandlerConstructor = for<'a> fn(&'a ConfigData, &'a SugarConfig) -> HandlerFuture<'a>;
+
+/// An upload backend: the upload method and the constructor of its handler.
+pub struct UploadBackend {
+    pub method: UploadMethod,
+    pub constructor: HandlerConstructor,
+}
+
+/// Registered upload backends. Adding a backend only requires a new entry
+/// here; commands create handlers through `initialize_handler`.
+static BACKENDS: &[UploadBackend] = &[
+    UploadBackend {
+        method: UploadMethod::Bundlr,
+        constructor: |config_data, sugar_config| {
+            Box::pin(async move {
+                Ok(
+                    Box::new(BundlrHandler::initialize(config_data, sugar_config).await?)
+                        as Box<dyn UploadHandler>,
+                )
+            })
+        },
+    },
+    UploadBackend {
+        method: UploadMethod::AWS,
+        constructor: |config_data, _sugar_config| {
+            Box::pin(async move {
+                Ok(Box::new(AWSHandler::initialize(config_data).await?) as Box<dyn UploadHandler>)
+            })
+        },
+    },
+    UploadBackend {
+        method: UploadMethod::Pinata,
+        constructor: |config_data, _sugar_config| {
+            Box::pin(async move {
+                Ok(Box::new(PinataHandler::initialize(config_data).await?)
+                    as Box<dyn UploadHandler>)
+            })
+        },
+    },
+    UploadBackend {
+        method: UploadMethod::NftStorage,
+        constructor: |config_data, _sugar_config| {
+            Box::pin(async move {
+                Ok(Box::new(NftStorageHandler::initialize(config_data).await?)
+                    as Box<dyn UploadHandler>)
+            })
+        },
+    },
+    UploadBackend {
+        method: UploadMethod::ShadowDrive,
+        constructor: |config_data, sugar_config| {
+            Box::pin(async move {
+                Ok(
+                    Box::new(ShadowDriveHandler::initialize(config_data, sugar_config).await?)
+                        as Box<dyn UploadHandler>,
+                )
+            })
+        },
+    },
+    UploadBackend {
+        method: UploadMethod::ArweaveNative,
+        constructor: |config_data, _sugar_config| {
+            Box::pin(async move {
+                Ok(
+                    Box::new(ArweaveNativeHandler::initialize(config_data).await?)
+                        as Box<dyn UploadHandler>,
+                )
+            })
+        },
+    },
+];
+
+/// Return the backend registered for the upload method.
+pub fn backend(method: &UploadMethod) -> Option<&'static UploadBackend> {
+    BACKENDS.iter().find(|backend| &backend.method == method)
+}
+
+/// Create the upload handler of the upload method of the config.
+pub async fn initialize_handler(
+    config_data: &ConfigData,
+    sugar_config: &SugarConfig,
+) -> Result<Box<dyn UploadHandler>> {
+    match backend(&config_data.upload_method) {
+        Some(backend) => (backend.constructor)(config_data, sugar_config).await,
+        None => Err(anyhow!(
+            "Upload method '{}' currently unsupported!",
+            config_data.upload_method.to_string()
+        )),
+    }
+}
@@ -0,0 +1,452 @@
+use async_trait::async_trait;
+use console::style;
+use reqwest::multipart::{Form, Part};
+use std::{
+    collections::BTreeMap,
+    ffi::OsStr,
+    fs,
+    sync::{atomic::AtomicBool, Arc},
+};
+
+use crate::events::{error_event, upload_event};
+use crate::retry::{retry_async, Subsystem};
+use crate::{common::*, config::*, constants::PARALLEL_LIMIT, upload::*, utils::*};
+
+/// Shadow Drive uploader endpoint.
+const SHADOW_DRIVE_ENDPOINT: &str = "https://shadow-storage.genesysgo.net";
+
+/// Shadow Drive public URL of the stored files.
+const SHADOW_DRIVE_URL: &str = "https://shdw-drive.genesysgo.net";
+
+/// Number of hex digits of the content hash used to prefix file names.
+const HASH_PREFIX_LENGTH: usize = 16;
+
+struct ShadowInfo {
+    asset_id: String,
+    file_path: String,
+    image_link: String,
+    data_type: DataType,
+    content_type: String,
+    animation_link: Option<String>,
+    preview_link: Option<String>,
+    template_variables: TemplateVariables,
+    locales: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StorageAccountInfo {
+    reserved_bytes: u64,
+    current_usage: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct UploadResponse {
+    #[serde(default)]
+    finalized_locations: Vec<String>,
+    #[serde(default)]
+    upload_errors: Vec<Value>,
+}
+
+pub struct ShadowDriveHandler {
+    client: Arc<HttpClient>,
+    keypair: Arc<Keypair>,
+    storage_account: Pubkey,
+}
+
+impl ShadowDriveHandler {
+    /// Initialize a new ShadowDriveHandler.
+    pub async fn initialize(
+        config_data: &ConfigData,
+        sugar_config: &SugarConfig,
+    ) -> Result<ShadowDriveHandler> {
+        let storage_account = match &config_data.shadow_drive_storage_account {
+            Some(account) => Pubkey::from_str(account)
+                .map_err(|_| anyhow!("Invalid Shadow Drive storage account: {}", account))?,
+            None => {
+                return Err(anyhow!(
+                    "Missing 'shadowDriveStorageAccount' value in config file. Create a storage \
+                    account funded with SHDW (e.g. 'shdw-drive create-storage-account') and set \
+                    its address in the config file."
+                ))
+            }
+        };
+
+        Ok(ShadowDriveHandler {
+            client: Arc::new(http_client()),
+            keypair: Arc::new(Keypair::from_bytes(
+                &sugar_config
+                    .keypair
+                    .keypair("Uploading to Shadow Drive")?
+                    .to_bytes(),
+            )?),
+            storage_account,
+        })
+    }
+
+    /// Return the reserved and used bytes of the storage account.
+    async fn get_storage_account_info(&self) -> Result<StorageAccountInfo> {
+        let url = format!("{}/storage-account-info", SHADOW_DRIVE_ENDPOINT);
+        let body = json!({ "storage_account": self.storage_account.to_string() });
+
+        retry_async(Subsystem::Rpc, || async {
+            Ok(self
+                .client
+                .post(&url)
+                .json(&body)
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<StorageAccountInfo>()
+                .await?)
+        })
+        .await
+    }
+
+    /// Return the public link of a file in the storage account.
+    fn link(storage_account: &Pubkey, file_name: &str) -> String {
+        format!("{}/{}/{}", SHADOW_DRIVE_URL, storage_account, file_name)
+    }
+
+    /// Upload a file to the storage account and wait for a response.
+    async fn send_to_shadow_drive(
+        client: Arc<HttpClient>,
+        keypair: Arc<Keypair>,
+        storage_account: Pubkey,
+        info: ShadowInfo,
+    ) -> Result<(String, String)> {
+        let data = match info.data_type {
+            DataType::Image => fs::read(&info.file_path)?,
+            DataType::Metadata => {
+                // replaces the image link without modifying the original file to avoid
+                // changing the hash of the metadata file
+                let metadata = get_updated_metadata(
+                    &info.file_path,
+                    &info.image_link,
+                    info.animation_link.clone(),
+                    info.preview_link.clone(),
+                    &info.template_variables,
+                )?;
+
+                if info.locales.is_empty() {
+                    metadata.into_bytes()
+                } else {
+                    // the localized variants are uploaded first so that their
+                    // links can be recorded in the metadata
+                    let mut localization = BTreeMap::new();
+
+                    for (locale, file_path) in &info.locales {
+                        let variant = get_updated_metadata(
+                            file_path,
+                            &info.image_link,
+                            info.animation_link.clone(),
+                            info.preview_link.clone(),
+                            &info.template_variables,
+                        )?;
+                        let link = ShadowDriveHandler::upload_file(
+                            &client,
+                            &keypair,
+                            &storage_account,
+                            file_path,
+                            &info.content_type,
+                            variant.into_bytes(),
+                        )
+                        .await?;
+                        localization.insert(locale.clone(), link);
+                    }
+
+                    set_localization(&metadata, localization)?.into_bytes()
+                }
+            }
+            DataType::Animation | DataType::Preview => fs::read(&info.file_path)?,
+        };
+
+        let link = ShadowDriveHandler::upload_file(
+            &client,
+            &keypair,
+            &storage_account,
+            &info.file_path,
+            &info.content_type,
+            data,
+        )
+        .await?;
+
+        Ok((info.asset_id, link))
+    }
+
+    /// Upload the data of a file, returning its link.
+    ///
+    /// Files are stored under a name prefixed with the hash of their content,
+    /// so a file that is already in the storage account (e.g. uploaded by an
+    /// interrupted run) has the same content and is not uploaded again.
+    async fn upload_file(
+        client: &HttpClient,
+        keypair: &Keypair,
+        storage_account: &Pubkey,
+        file_path: &str,
+        content_type: &str,
+        data: Vec<u8>,
+    ) -> Result<String> {
+        let file_name = Path::new(file_path)
+            .file_name()
+            .and_then(OsStr::to_str)
+            .unwrap_or(file_path);
+        let file_name = format!("{}-{}", &hash_data(&data)[..HASH_PREFIX_LENGTH], file_name);
+        let link = ShadowDriveHandler::link(storage_account, &file_name);
+
+        let existing = client.head(&link).send().await;
+        if matches!(existing, Ok(response) if response.status().is_success()) {
+            return Ok(link);
+        }
+
+        // the uploader authenticates the request with a signed message
+        // containing the hash of the file names
+        let message = format!(
+            "Shadow Drive Signed Message:\nStorage Account: {}\nUpload files with hash: {}",
+            storage_account,
+            hash_data(file_name.as_bytes())
+        );
+        let signature = keypair.sign_message(message.as_bytes()).to_string();
+        let url = format!("{}/upload", SHADOW_DRIVE_ENDPOINT);
+
+        retry_async(Subsystem::Upload, || async {
+            let part = Part::bytes(data.clone())
+                .file_name(file_name.clone())
+                .mime_str(content_type)?;
+
+            let form = Form::new()
+                .part("file", part)
+                .text("message", signature.clone())
+                .text("signer", keypair.pubkey().to_string())
+                .text("storage_account", storage_account.to_string())
+                .text("fileNames", file_name.clone());
+
+            let response: UploadResponse = client
+                .post(&url)
+                .multipart(form)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+
+            if !response.upload_errors.is_empty() {
+                return Err(anyhow!(
+                    "Shadow Drive rejected file {}: {:?}",
+                    file_name,
+                    response.upload_errors
+                ));
+            }
+
+            Ok(response
+                .finalized_locations
+                .into_iter()
+                .next()
+                .unwrap_or_else(|| link.clone()))
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl UploadHandler for ShadowDriveHandler {
+    /// Check that the storage account has enough free space for the upload.
+    async fn prepare(
+        &self,
+        _sugar_config: &SugarConfig,
+        assets: &HashMap<usize, AssetPair>,
+        image_indices: &[usize],
+        metadata_indices: &[usize],
+        animation_indices: &[usize],
+        preview_indices: &[usize],
+        _dry_run: bool,
+    ) -> Result<()> {
+        // calculates the size of the files to upload
+        let mut total_size = 0;
+
+        for (indices, data_type) in [
+            (image_indices, DataType::Image),
+            (animation_indices, DataType::Animation),
+            (preview_indices, DataType::Preview),
+            (metadata_indices, DataType::Metadata),
+        ] {
+            total_size += upload_file_sizes(assets, indices, &data_type)?
+                .iter()
+                .sum::<u64>();
+        }
+
+        let storage = self.get_storage_account_info().await?;
+        let available = storage.reserved_bytes.saturating_sub(storage.current_usage);
+
+        info!(
+            "Shadow Drive storage available {} bytes, require {} bytes",
+            available, total_size
+        );
+
+        if total_size > available {
+            let error = anyhow!(
+                "Not enough space in Shadow Drive storage account {}: {} bytes available, {} \
+                bytes required. Add storage to the account (e.g. 'shdw-drive add-storage') and \
+                run the upload again.",
+                self.storage_account,
+                available,
+                total_size
+            );
+            error!("{error}");
+            return Err(error);
+        }
+
+        Ok(())
+    }
+
+    /// Upload the data to the Shadow Drive storage account.
+    async fn upload_data(
+        &self,
+        _sugar_config: &SugarConfig,
+        assets: &HashMap<usize, AssetPair>,
+        cache: &mut Cache,
+        indices: &[usize],
+        data_type: DataType,
+        interrupted: Arc<AtomicBool>,
+    ) -> Result<Vec<UploadError>> {
+        let mut paths = Vec::new();
+
+        for index in indices {
+            let item = assets.get(index).unwrap();
+            // chooses the file path based on the data type
+            let file_path = match data_type {
+                DataType::Image => item.image.clone(),
+                DataType::Metadata => item.metadata.clone(),
+                DataType::Animation => item.animation.clone().unwrap(),
+                DataType::Preview => item.preview.clone().unwrap(),
+            };
+
+            let locales = match data_type {
+                DataType::Metadata => item.locales.clone(),
+                _ => BTreeMap::new(),
+            };
+
+            paths.push((file_path, locales));
+        }
+
+        println!("\nSending data: (Ctrl+C to abort)");
+
+        let pb = progress_bar_with_style(paths.len() as u64);
+        let mut uploads = Vec::new();
+
+        for (file_path, locales) in paths {
+            // path to the image/metadata file
+            let path = Path::new(&file_path);
+            // id of the asset (to be used to update the cache link)
+            let asset_id = String::from(
+                path.file_stem()
+                    .and_then(OsStr::to_str)
+                    .expect("Failed to get convert path file ext to valid unicode."),
+            );
+            let cache_item = match cache.items.0.get(&asset_id) {
+                Some(item) => item,
+                None => {
+                    return Err(anyhow::anyhow!(
+                        "Failed to get config item at index: {}",
+                        asset_id
+                    ))
+                }
+            };
+
+            uploads.push(ShadowInfo {
+                asset_id: asset_id.to_string(),
+                file_path: String::from(
+                    path.to_str().expect("Failed to convert path from unicode."),
+                ),
+                image_link: cache_item.image_link.clone(),
+                data_type: data_type.clone(),
+                content_type: content_type(&file_path, &data_type)?,
+                animation_link: cache_item.animation_link.clone(),
+                preview_link: cache_item.preview_link.clone(),
+                template_variables: cache.program.template_variables.clone(),
+                locales,
+            });
+        }
+
+        let mut errors = Vec::new();
+        let (client, keypair, storage_account) = (
+            self.client.clone(),
+            self.keypair.clone(),
+            self.storage_account,
+        );
+        let uploads = uploads
+            .into_iter()
+            .map(|info| (info.asset_id.clone(), info))
+            .collect();
+
+        let not_started = run_bounded(
+            uploads,
+            upload_concurrency(PARALLEL_LIMIT),
+            &interrupted,
+            |info| {
+                ShadowDriveHandler::send_to_shadow_drive(
+                    client.clone(),
+                    keypair.clone(),
+                    storage_account,
+                    info,
+                )
+            },
+            |asset_id, result| {
+                match result {
+                    Ok((_, link)) => {
+                        // cache item to update
+                        let item = cache.get_item_mut(&asset_id).unwrap();
+
+                        match data_type {
+                            DataType::Image => item.image_link = link.clone(),
+                            DataType::Metadata => item.metadata_link = link.clone(),
+                            DataType::Animation => item.animation_link = Some(link.clone()),
+                            DataType::Preview => item.preview_link = Some(link.clone()),
+                        }
+                        // updates the progress bar
+                        pb.inc(1);
+                        upload_event(
+                            data_type.stage(),
+                            &asset_id,
+                            &link,
+                            pb.position(),
+                            pb.length(),
+                        );
+                    }
+                    Err(err) => {
+                        // user will need to retry the upload
+                        let error = format!("Shadow Drive upload error: {:?}", err);
+                        error_event(
+                            data_type.stage(),
+                            Some(&asset_id),
+                            pb.position(),
+                            pb.length(),
+                            &error,
+                        );
+                        cache.record_error(&asset_id, &error);
+                        errors.push(UploadError::SendDataFailed(error));
+                    }
+                }
+
+                // syncs cache (checkpoint)
+                cache.checkpoint()
+            },
+        )
+        .await?;
+
+        if !errors.is_empty() {
+            pb.abandon_with_message(format!("{}", style("Upload failed ").red().bold()));
+        } else if not_started > 0 {
+            pb.abandon_with_message(format!("{}", style("Upload aborted ").red().bold()));
+            return Err(
+                UploadError::SendDataFailed("Not all files were uploaded.".to_string()).into(),
+            );
+        } else {
+            pb.finish_with_message(format!("{}", style("Upload successful ").green().bold()));
+        }
+
+        // makes sure the cache file is updated
+        cache.sync_file()?;
+
+        Ok(errors)
+    }
+}
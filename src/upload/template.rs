@@ -0,0 +1,46 @@
+use chrono::Utc;
+use std::collections::BTreeMap;
+
+/// Values of the template placeholders (`{{name}}`) of the metadata files.
+pub type TemplateVariables = BTreeMap<String, String>;
+
+/// Placeholder of the asset index.
+pub const INDEX_VARIABLE: &str = "index";
+
+/// Placeholder of the asset number (index + 1).
+pub const NUMBER_VARIABLE: &str = "number";
+
+/// Placeholder of the upload date.
+pub const DATE_VARIABLE: &str = "date";
+
+/// Return the value of the date placeholder.
+pub fn template_date() -> String {
+    Utc::now().format("%Y-%m-%d").to_string()
+}
+
+/// Expand the template placeholders of the content of a metadata file. The
+/// values are JSON-escaped so they can be safely placed inside JSON strings.
+pub fn expand_template(content: &str, index: usize, variables: &TemplateVariables) -> String {
+    if !content.contains("{{") {
+        return content.to_string();
+    }
+
+    let mut expanded = content
+        .replace(&placeholder(INDEX_VARIABLE), &index.to_string())
+        .replace(&placeholder(NUMBER_VARIABLE), &(index + 1).to_string());
+
+    for (name, value) in variables {
+        expanded = expanded.replace(&placeholder(name), &escape(value));
+    }
+
+    expanded
+}
+
+fn placeholder(name: &str) -> String {
+    format!("{{{{{}}}}}", name)
+}
+
+fn escape(value: &str) -> String {
+    let quoted = serde_json::to_string(value).unwrap_or_default();
+    quoted[1..quoted.len() - 1].to_string()
+}
@@ -0,0 +1,211 @@
+use console::style;
+use notify::{recommended_watcher, Event, EventKind, RecursiveMode, Watcher};
+use std::{
+    collections::BTreeSet,
+    str::FromStr,
+    sync::{
+        atomic::Ordering,
+        mpsc::{channel, Receiver, RecvTimeoutError},
+    },
+    time::{Duration, Instant},
+};
+
+use crate::cache::load_cache;
+use crate::candy_machine::get_candy_machine_state;
+use crate::common::*;
+use crate::deploy::{process_deploy, DeployArgs};
+use crate::upload::{process_upload, UploadArgs, UploadError};
+
+/// Time without file changes before an upload starts, so that files copied
+/// in bulk (or written in several steps) are uploaded together.
+const WATCH_DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// Interval to check whether the watch was interrupted.
+const WATCH_POLL: Duration = Duration::from_millis(250);
+
+/// Upload the assets and keep watching the assets directory, uploading the
+/// new and modified files as they change. When the candy machine is already
+/// deployed, the config lines of the new and modified items are written after
+/// each upload.
+pub async fn process_upload_watch(args: UploadArgs) -> Result<()> {
+    let assets_dir = Path::new(&args.assets_dir);
+
+    if !assets_dir.is_dir() {
+        return Err(UploadError::InvalidAssetsDirectory(args.assets_dir.clone()).into());
+    }
+
+    let (sender, receiver) = channel();
+    let mut watcher = recommended_watcher(sender)?;
+    watcher.watch(assets_dir, RecursiveMode::NonRecursive)?;
+
+    let mut changes = BTreeSet::new();
+
+    loop {
+        if changes.is_empty() {
+            println!("\n{} sugar upload\n", style(">>>").magenta());
+        } else {
+            println!(
+                "\n{} sugar upload ({} file(s) changed: {})\n",
+                style(">>>").magenta(),
+                changes.len(),
+                changes.iter().cloned().collect::<Vec<String>>().join(", ")
+            );
+        }
+
+        // a Ctrl+C while uploading stops the watch once the upload finishes
+        args.interrupted.store(false, Ordering::SeqCst);
+
+        // a failed upload (e.g., a metadata file saved half-way) is retried
+        // on the next change instead of stopping the watch
+        if let Err(err) = upload_and_deploy(&args).await {
+            println!(
+                "\n{}{} {}",
+                ERROR_EMOJI,
+                style("Error running upload:").red(),
+                err
+            );
+        }
+
+        if args.interrupted.load(Ordering::SeqCst) {
+            break;
+        }
+
+        println!(
+            "\n{}Watching '{}' for changes (Ctrl+C to stop)...",
+            LOOKING_GLASS_EMOJI, args.assets_dir
+        );
+
+        match wait_for_changes(&receiver, &args)? {
+            Some(files) => changes = files,
+            None => break,
+        }
+    }
+
+    println!("\nStopped watching '{}'.", args.assets_dir);
+
+    Ok(())
+}
+
+/// Upload the new and modified files and, if the candy machine is deployed,
+/// write the config lines that are not on-chain.
+async fn upload_and_deploy(args: &UploadArgs) -> Result<()> {
+    process_upload(UploadArgs {
+        assets_dir: args.assets_dir.clone(),
+        config: args.config.clone(),
+        keypair: args.keypair.clone(),
+        rpc_url: args.rpc_url.clone(),
+        cache: args.cache.clone(),
+        concurrency: args.concurrency,
+        rate_limit: args.rate_limit,
+        dry_run: false,
+        bundlr_node: args.bundlr_node.clone(),
+        interrupted: args.interrupted.clone(),
+    })
+    .await?;
+
+    let cache = load_cache(&args.cache, false)?;
+
+    if cache.program.candy_machine.is_empty()
+        || cache.items.0.values().all(|item| item.on_chain)
+        || args.interrupted.load(Ordering::SeqCst)
+    {
+        return Ok(());
+    }
+
+    let candy_pubkey = Pubkey::from_str(&cache.program.candy_machine)
+        .map_err(|_| CacheError::InvalidCandyMachineAddress(cache.program.candy_machine.clone()))?;
+
+    // the config lines can only be written while the candy machine has room
+    // for the items of the cache
+    let sugar_config = sugar_setup(args.keypair.clone(), args.rpc_url.clone())?;
+    let state = get_candy_machine_state(&sugar_config, &candy_pubkey)?;
+
+    if state.data.hidden_settings.is_some() {
+        return Ok(());
+    }
+
+    if cache.items.0.len() as u64 > state.data.items_available {
+        println!(
+            "\n{}",
+            style(format!(
+                "Candy machine {} has room for {} item(s) and the cache has {}, the config lines \
+                were not written.",
+                candy_pubkey,
+                state.data.items_available,
+                cache.items.0.len()
+            ))
+            .yellow()
+        );
+        return Ok(());
+    }
+
+    println!("\n{} sugar deploy\n", style(">>>").magenta());
+
+    process_deploy(DeployArgs {
+        config: args.config.clone(),
+        keypair: args.keypair.clone(),
+        rpc_url: args.rpc_url.clone(),
+        cache: args.cache.clone(),
+        force_rewrite: false,
+        append: true,
+        interrupted: args.interrupted.clone(),
+    })
+    .await
+}
+
+/// Wait for changes in the assets directory, returning the names of the files
+/// that changed once no change happened for the debounce time. Returns `None`
+/// when the watch is interrupted.
+fn wait_for_changes(
+    receiver: &Receiver<notify::Result<Event>>,
+    args: &UploadArgs,
+) -> Result<Option<BTreeSet<String>>> {
+    let mut changes = BTreeSet::new();
+    let mut last_change: Option<Instant> = None;
+
+    loop {
+        if args.interrupted.load(Ordering::SeqCst) {
+            return Ok(None);
+        }
+
+        match receiver.recv_timeout(WATCH_POLL) {
+            Ok(event) => {
+                let event = event?;
+
+                if matches!(event.kind, EventKind::Access(_)) {
+                    continue;
+                }
+
+                for path in &event.paths {
+                    if let Some(name) = asset_file_name(path) {
+                        changes.insert(name);
+                        last_change = Some(Instant::now());
+                    }
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                if last_change.map_or(false, |last| last.elapsed() >= WATCH_DEBOUNCE) {
+                    return Ok(Some(changes));
+                }
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                return Err(anyhow!(
+                    "Stopped receiving changes of '{}'",
+                    args.assets_dir
+                ));
+            }
+        }
+    }
+}
+
+/// Return the name of a changed file when it can be an asset, ignoring hidden
+/// files (e.g., editor swap files) and directories.
+fn asset_file_name(path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_str()?;
+
+    if name.starts_with('.') || name.ends_with('~') || path.is_dir() {
+        None
+    } else {
+        Some(name.to_string())
+    }
+}
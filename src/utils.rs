@@ -1,18 +1,23 @@
 pub use anchor_client::solana_sdk::hash::Hash;
 use anchor_client::{
     solana_sdk::{
+        compute_budget,
+        instruction::Instruction,
         program_pack::{IsInitialized, Pack},
         pubkey::Pubkey,
     },
     Program,
 };
 pub use anyhow::{anyhow, Result};
-pub use indicatif::{ProgressBar, ProgressStyle};
+pub use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
 use solana_client::rpc_client::RpcClient;
 use spl_token::state::{Account, Mint};
 use std::str::FromStr;
 
 use crate::config::data::Cluster;
+use crate::events::json_output;
+use crate::interaction::is_interactive;
+use crate::rpc_cache;
 
 /// Hash for devnet cluster
 pub const DEVNET_HASH: &str = "EtWTRABZaYq6iMfeYKouRu166VU2xqa1wcaWoxPkrZBG";
@@ -38,10 +43,25 @@ pub fn get_cluster(rpc_client: RpcClient) -> Result<Cluster> {
     }
 }
 
+/// Create the instruction setting the compute unit price (in micro-lamports) of
+/// a transaction, which determines its prioritization fee.
+pub fn compute_unit_price_instruction(micro_lamports: u64) -> Instruction {
+    // 'SetComputeUnitPrice' is not available in the current sdk version,
+    // so the instruction data is serialized manually (discriminator 3)
+    let mut data = vec![3u8];
+    data.extend_from_slice(&micro_lamports.to_le_bytes());
+
+    Instruction {
+        program_id: compute_budget::id(),
+        accounts: vec![],
+        data,
+    }
+}
+
 /// Check that the mint token is a valid address.
 pub fn check_spl_token(program: &Program, input: &str) -> Result<Mint> {
     let pubkey = Pubkey::from_str(input)?;
-    let token_data = program.rpc().get_account_data(&pubkey)?;
+    let token_data = rpc_cache::get_account_data(program, &pubkey)?;
     if token_data.len() != 82 {
         return Err(anyhow!("Invalid spl-token passed in."));
     }
@@ -60,7 +80,7 @@ pub fn check_spl_token(program: &Program, input: &str) -> Result<Mint> {
 /// Check that the mint token account is a valid account.
 pub fn check_spl_token_account(program: &Program, input: &str) -> Result<()> {
     let pubkey = Pubkey::from_str(input)?;
-    let ata_data = program.rpc().get_account_data(&pubkey)?;
+    let ata_data = rpc_cache::get_account_data(program, &pubkey)?;
     let ata_account = Account::unpack_unchecked(&ata_data)?;
 
     if IsInitialized::is_initialized(&ata_account) {
@@ -89,6 +109,7 @@ pub fn spinner_with_style() -> ProgressBar {
             ])
             .template("{spinner:.dim} {msg}"),
     );
+    hide_progress_bar(&pb);
     pb
 }
 
@@ -100,5 +121,38 @@ pub fn progress_bar_with_style(len: u64) -> ProgressBar {
     pb.set_style(
         ProgressStyle::default_bar().template("[{elapsed_precise}] {msg}{wide_bar} {pos}/{len}"),
     );
+    hide_progress_bar(&pb);
     pb
 }
+
+/// Hide a progress bar when the output is machine-readable JSON or the
+/// command runs without interaction.
+pub fn hide_progress_bar(pb: &ProgressBar) {
+    if json_output() || !is_interactive() {
+        pb.set_draw_target(ProgressDrawTarget::hidden());
+    }
+}
+
+/// Format sorted indices as a list of ranges (e.g. "0-4, 7, 9-12").
+pub fn format_ranges(indices: &[usize]) -> String {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+
+    for &index in indices {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == index => *end = index,
+            _ => ranges.push((index, index)),
+        }
+    }
+
+    ranges
+        .into_iter()
+        .map(|(start, end)| {
+            if start == end {
+                start.to_string()
+            } else {
+                format!("{}-{}", start, end)
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+}
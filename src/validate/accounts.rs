@@ -0,0 +1,147 @@
+use anchor_client::solana_sdk::{account::Account, program_pack::Pack};
+use console::style;
+use spl_token::{
+    state::{Account as TokenAccount, Mint},
+    ID as TOKEN_PROGRAM_ID,
+};
+
+use crate::candy_machine::ID as CANDY_MACHINE_ID;
+use crate::common::*;
+use crate::config::data::ConfigData;
+use crate::validate::ValidateError;
+
+/// Check that the accounts referenced in the config (spl-token, whitelist mint
+/// and gatekeeper) exist on the cluster with the expected owners. Returns a
+/// list of warnings for settings that are valid but likely unintended.
+pub fn check_config_accounts(config_data: &ConfigData, client: &Client) -> Result<Vec<String>> {
+    let program = client.program(CANDY_MACHINE_ID);
+    let mut warnings = Vec::new();
+
+    if let Some(spl_token) = &config_data.spl_token {
+        let mint = check_mint(&program, "spl-token", spl_token)?;
+        println!(
+            "{} {} ({} decimals)",
+            style("spl-token:").bold(),
+            spl_token,
+            mint.decimals
+        );
+
+        if let Some(spl_token_account) = &config_data.spl_token_account {
+            let account = get_account(&program, "spl-token-account", spl_token_account)?;
+            check_owner(
+                "spl-token-account",
+                spl_token_account,
+                &account,
+                &TOKEN_PROGRAM_ID,
+            )?;
+
+            let token_account = TokenAccount::unpack(&account.data).map_err(|_| {
+                invalid(
+                    "spl-token-account",
+                    spl_token_account,
+                    "not a token account",
+                )
+            })?;
+
+            if token_account.mint != *spl_token {
+                return Err(invalid(
+                    "spl-token-account",
+                    spl_token_account,
+                    &format!("holds mint {} instead of {}", token_account.mint, spl_token),
+                )
+                .into());
+            }
+
+            println!(
+                "{} {}",
+                style("spl-token-account:").bold(),
+                spl_token_account
+            );
+        }
+    }
+
+    if let Some(whitelist_mint_settings) = &config_data.whitelist_mint_settings {
+        let whitelist_mint = whitelist_mint_settings.into_candy_format().mint;
+        let mint = check_mint(&program, "whitelist mint", &whitelist_mint)?;
+
+        if mint.decimals != 0 {
+            warnings.push(format!(
+                "Whitelist mint {} has {} decimals, each mint uses the smallest unit of the token",
+                whitelist_mint, mint.decimals
+            ));
+        }
+
+        println!(
+            "{} {} ({} decimals, supply {})",
+            style("whitelist mint:").bold(),
+            whitelist_mint,
+            mint.decimals,
+            mint.supply
+        );
+    }
+
+    if let Some(gatekeeper) = &config_data.gatekeeper {
+        let network = gatekeeper.into_candy_format().gatekeeper_network;
+        let gateway_program = Pubkey::from_str(CIVIC)?;
+
+        let account = get_account(&program, "gateway program", &gateway_program)?;
+
+        if !account.executable {
+            return Err(invalid(
+                "gateway program",
+                &gateway_program,
+                "account is not executable",
+            )
+            .into());
+        }
+
+        let known = [CIVIC_NETWORK, ENCORE_NETWORK]
+            .iter()
+            .any(|n| Pubkey::from_str(n).map(|n| n == network).unwrap_or(false));
+
+        if !known {
+            warnings.push(format!(
+                "Gatekeeper network {} is not a known network (Civic or Encore)",
+                network
+            ));
+        }
+
+        println!("{} {}", style("gatekeeper network:").bold(), network);
+    }
+
+    Ok(warnings)
+}
+
+fn check_mint(program: &Program, name: &str, address: &Pubkey) -> Result<Mint> {
+    let account = get_account(program, name, address)?;
+    check_owner(name, address, &account, &TOKEN_PROGRAM_ID)?;
+
+    let mint =
+        Mint::unpack(&account.data).map_err(|_| invalid(name, address, "not a token mint"))?;
+
+    Ok(mint)
+}
+
+fn get_account(program: &Program, name: &str, address: &Pubkey) -> Result<Account> {
+    program
+        .rpc()
+        .get_account(address)
+        .map_err(|_| invalid(name, address, "account not found on the cluster").into())
+}
+
+fn check_owner(name: &str, address: &Pubkey, account: &Account, owner: &Pubkey) -> Result<()> {
+    if account.owner != *owner {
+        return Err(invalid(
+            name,
+            address,
+            &format!("owned by {} instead of {}", account.owner, owner),
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+fn invalid(name: &str, address: &Pubkey, reason: &str) -> ValidateError {
+    ValidateError::InvalidAccount(name.to_string(), address.to_string(), reason.to_string())
+}
@@ -17,12 +17,18 @@ pub enum ValidateError {
     #[error("Url exceeds 200 chars.")]
     UrlTooLong,
 
+    #[error("Url '{0}' exceeds {1} chars.")]
+    UrlExceedsLimit(String, usize),
+
     #[error("Creator address: {0} is invalid.")]
     InvalidCreatorAddress(String),
 
     #[error("Creators' share does not equal 100%.")]
     InvalidCreatorShare,
 
+    #[error("More than {0} creators.")]
+    TooManyCreators(usize),
+
     #[error("Seller fee basis points must be between 0 and 10,000.")]
     InvalidSellerFeeBasisPoints,
 
@@ -34,4 +40,67 @@ pub enum ValidateError {
 
     #[error("Missing collection field")]
     MissingCollection,
+
+    #[error("Invalid {0} '{1}': {2}")]
+    InvalidAccount(String, String, String),
+
+    #[error("Invalid uses settings: {0}")]
+    InvalidUses(String),
+
+    #[error("Localized metadata '{0}' has no metadata file at index {1}")]
+    MissingLocaleBase(String, usize),
+
+    #[error("Localized metadata '{0}' has a different {1} than the metadata file")]
+    LocaleMismatch(String, String),
+
+    #[error("The {0} '{1}' is not listed in properties.files")]
+    NotInFiles(String, String),
+
+    #[error("Missing type of file '{0}' in properties.files")]
+    MissingFileType(String),
+
+    #[error("File '{0}' has type '{1}' in properties.files, expected '{2}'")]
+    FileTypeMismatch(String, String, String),
+
+    #[error("Missing image file (png, jpg or gif)")]
+    MissingImageFile,
+
+    #[error("File name is not a valid asset index")]
+    InvalidIndex,
+
+    #[error("Image file without a metadata file")]
+    MissingMetadataFile,
+
+    #[error("Missing asset index(es): {0}")]
+    IndexGaps(String),
+
+    #[error("File is empty")]
+    EmptyFile,
+
+    #[error("Image file is corrupted: {0}")]
+    CorruptedImage(String),
+
+    #[error("Image is {0} ({1}:{2}), most images have a {3}:{4} aspect ratio")]
+    AspectRatioMismatch(String, u32, u32, u32, u32),
+
+    #[error("Image is {0}, most images are {1}")]
+    ImageSizeMismatch(String, String),
+
+    #[error("File is {0:.1} MB, larger than {1} MB, which increases the upload fees")]
+    FileTooLarge(f64, u64),
+
+    #[error("{0} issue(s) found in the assets, see the report above")]
+    InvalidAssets(usize),
+
+    #[error("Unknown field '{0}', it is dropped when the metadata is uploaded")]
+    UnknownField(String),
+
+    #[error("Unknown category '{0}', expected image, video, audio, vr or html")]
+    InvalidCategory(String),
+
+    #[error("Category '{0}' requires a '{1}' file in properties.files")]
+    CategoryMismatch(String, String),
+
+    #[error("Unknown validation rule '{0}', expected one of: {1}")]
+    UnknownRule(String, String),
 }
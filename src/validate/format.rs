@@ -1,7 +1,10 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
 
-use crate::validate::{errors, parser};
+use crate::config::data::Uses;
+use crate::validate::{errors, parser, Rule, RuleSet, MAX_URI_LENGTH};
 
 #[derive(Debug, Clone, Deserialize, Default, Serialize)]
 pub struct Metadata {
@@ -14,6 +17,8 @@ pub struct Metadata {
     pub external_url: Option<String>,
     pub attributes: Vec<Attribute>,
     pub collection: Option<Collection>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uses: Option<Uses>,
     pub properties: Property,
 }
 
@@ -24,6 +29,10 @@ impl Metadata {
         parser::check_url(&self.image)?;
         parser::check_seller_fee_basis_points(self.seller_fee_basis_points)?;
 
+        if let Some(uses) = &self.uses {
+            parser::check_uses(uses)?;
+        }
+
         Ok(())
     }
 
@@ -49,8 +58,90 @@ impl Metadata {
         parser::check_url(&self.image)?;
         parser::check_seller_fee_basis_points(self.seller_fee_basis_points)?;
 
+        if let Some(uses) = &self.uses {
+            parser::check_uses(uses)?;
+        }
+
         Ok(())
     }
+
+    /// Check the metadata against the enabled rules, returning all the
+    /// issues found with their rule. Used by the validate command, while the
+    /// other commands only need [`Metadata::validate`] to pass.
+    pub fn check(&self, rules: &RuleSet) -> Vec<(Rule, errors::ValidateError)> {
+        let mut issues = Vec::new();
+        let mut push = |rule: Rule, result: Result<(), errors::ValidateError>| {
+            if let Err(e) = result {
+                if rules.is_enabled(rule) {
+                    issues.push((rule, e));
+                }
+            }
+        };
+
+        push(Rule::Name, parser::check_name(&self.name));
+        push(Rule::Symbol, parser::check_symbol(&self.symbol));
+        push(
+            Rule::SellerFee,
+            parser::check_seller_fee_basis_points(self.seller_fee_basis_points),
+        );
+
+        // URLs must fit in the on-chain URI; strict mode leaves no room for
+        // a URL that reaches the limit
+        let url_limit = if rules.strict {
+            MAX_URI_LENGTH - 1
+        } else {
+            MAX_URI_LENGTH
+        };
+        let urls = [
+            Some(&self.image),
+            self.animation_url.as_ref(),
+            self.external_url.as_ref(),
+        ];
+        for url in urls.into_iter().flatten() {
+            push(Rule::UrlLength, parser::check_url_length(url, url_limit));
+        }
+
+        if let Some(uses) = &self.uses {
+            push(Rule::Uses, parser::check_uses(uses));
+        }
+
+        if let Some(creators) = &self.properties.creators {
+            push(Rule::Creators, parser::check_creators(creators));
+        }
+
+        for issue in parser::check_files(self) {
+            push(Rule::Files, Err(issue));
+        }
+
+        for issue in parser::check_file_types(&self.properties.files) {
+            push(Rule::FileTypes, Err(issue));
+        }
+
+        if self.animation_url.is_none() {
+            push(
+                Rule::RequiredFields,
+                Err(errors::ValidateError::MissingAnimationUrl),
+            );
+        }
+
+        if self.collection.is_none() {
+            push(
+                Rule::RequiredFields,
+                Err(errors::ValidateError::MissingCollection),
+            );
+        }
+
+        if self.external_url.is_none() {
+            push(
+                Rule::RequiredFields,
+                Err(errors::ValidateError::MissingExternalUrl),
+            );
+        }
+
+        push(Rule::Category, parser::check_category(self));
+
+        issues
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Default, Serialize)]
@@ -63,12 +154,18 @@ pub struct Collection {
 pub struct Property {
     pub files: Vec<FileAttr>,
     pub category: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub creators: Option<Vec<CreatorAttr>>,
+    /// URIs of the localized metadata variants, keyed by locale.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub localization: Option<BTreeMap<String, String>>,
 }
 
 #[derive(Debug, Clone, Deserialize, Default, Serialize)]
 pub struct Attribute {
     pub trait_type: String,
-    pub value: String,
+    /// Usually a string, but numbers (and other JSON values) are also used.
+    pub value: Value,
 }
 
 #[derive(Debug, Clone, Deserialize, Default, Serialize)]
@@ -77,3 +174,9 @@ pub struct FileAttr {
     #[serde(rename = "type")]
     pub file_type: String,
 }
+
+#[derive(Debug, Clone, Deserialize, Default, Serialize)]
+pub struct CreatorAttr {
+    pub address: String,
+    pub share: u8,
+}
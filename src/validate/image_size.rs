@@ -0,0 +1,208 @@
+use std::{
+    fs::File,
+    io::{BufReader, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use crate::validate::ValidateError;
+
+/// Default size (in MB) above which an asset file is reported, since the
+/// upload fees grow with the size of the files.
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 10;
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+
+/// Last chunk of a PNG file: length, 'IEND' and its CRC.
+const PNG_END: [u8; 12] = [0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82];
+
+/// Number of bytes read at the end of a JPEG file to find its end marker,
+/// since some encoders pad the file after it.
+const JPEG_TAIL_LEN: u64 = 1024;
+
+/// Dimensions of an image, read from its header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ImageSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ImageSize {
+    /// Aspect ratio in lowest terms (e.g., 16:9).
+    pub fn aspect_ratio(&self) -> (u32, u32) {
+        let divisor = gcd(self.width, self.height).max(1);
+        (self.width / divisor, self.height / divisor)
+    }
+}
+
+impl std::fmt::Display for ImageSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
+/// Read the dimensions of a PNG, JPEG or GIF file, checking that the file is
+/// not empty or truncated. The image data itself is not decoded.
+pub fn read_image_size(path: &Path) -> Result<ImageSize, ValidateError> {
+    let corrupted = |reason: &str| ValidateError::CorruptedImage(reason.to_string());
+
+    let file = File::open(path).map_err(|err| corrupted(&err.to_string()))?;
+    let len = file
+        .metadata()
+        .map_err(|err| corrupted(&err.to_string()))?
+        .len();
+
+    if len == 0 {
+        return Err(ValidateError::EmptyFile);
+    }
+
+    let mut reader = BufReader::new(file);
+    let mut signature = [0u8; 8];
+    reader
+        .read_exact(&mut signature)
+        .map_err(|_| corrupted("file too short"))?;
+    reader
+        .seek(SeekFrom::Start(0))
+        .map_err(|err| corrupted(&err.to_string()))?;
+
+    let size = if signature == PNG_SIGNATURE {
+        read_png(&mut reader, len)
+    } else if signature.starts_with(&[0xff, 0xd8]) {
+        read_jpeg(&mut reader, len)
+    } else if signature.starts_with(b"GIF87a") || signature.starts_with(b"GIF89a") {
+        read_gif(&mut reader, len)
+    } else {
+        return Err(corrupted("not a png, jpg or gif image"));
+    }
+    .map_err(|reason| corrupted(&reason))?;
+
+    if size.width == 0 || size.height == 0 {
+        return Err(corrupted("image has no pixels"));
+    }
+
+    Ok(size)
+}
+
+/// The IHDR chunk follows the signature, and the file ends with IEND.
+fn read_png<R: Read + Seek>(reader: &mut R, len: u64) -> Result<ImageSize, String> {
+    let mut header = [0u8; 24];
+    reader
+        .read_exact(&mut header)
+        .map_err(|_| "missing png header".to_string())?;
+
+    if &header[12..16] != b"IHDR" {
+        return Err("missing png header".to_string());
+    }
+
+    let size = ImageSize {
+        width: u32::from_be_bytes([header[16], header[17], header[18], header[19]]),
+        height: u32::from_be_bytes([header[20], header[21], header[22], header[23]]),
+    };
+
+    if read_tail(reader, len, PNG_END.len() as u64)? != PNG_END {
+        return Err("truncated png file".to_string());
+    }
+
+    Ok(size)
+}
+
+/// The dimensions are in the start of frame segment, which comes after the
+/// metadata segments (e.g., EXIF), and the file ends with the EOI marker.
+fn read_jpeg<R: Read + Seek>(reader: &mut R, len: u64) -> Result<ImageSize, String> {
+    let truncated = |_| "truncated jpg file".to_string();
+    reader.seek(SeekFrom::Start(2)).map_err(truncated)?;
+
+    let size = loop {
+        let mut marker = [0u8; 2];
+        reader.read_exact(&mut marker).map_err(truncated)?;
+
+        if marker[0] != 0xff {
+            return Err("invalid jpg segment".to_string());
+        }
+
+        match marker[1] {
+            // fill bytes before a marker
+            0xff => {
+                reader.seek(SeekFrom::Current(-1)).map_err(truncated)?;
+                continue;
+            }
+            // markers without a segment
+            0x01 | 0xd0..=0xd7 => continue,
+            0xd9 | 0xda => return Err("missing jpg frame header".to_string()),
+            _ => (),
+        }
+
+        let mut segment_len = [0u8; 2];
+        reader.read_exact(&mut segment_len).map_err(truncated)?;
+        let segment_len = u16::from_be_bytes(segment_len) as i64;
+
+        // start of frame markers, except DHT (c4), JPG (c8) and DAC (cc)
+        if matches!(marker[1], 0xc0..=0xcf) && !matches!(marker[1], 0xc4 | 0xc8 | 0xcc) {
+            let mut frame = [0u8; 5];
+            reader.read_exact(&mut frame).map_err(truncated)?;
+            break ImageSize {
+                width: u16::from_be_bytes([frame[3], frame[4]]) as u32,
+                height: u16::from_be_bytes([frame[1], frame[2]]) as u32,
+            };
+        }
+
+        reader
+            .seek(SeekFrom::Current(segment_len - 2))
+            .map_err(truncated)?;
+    };
+
+    let tail = read_tail(reader, len, JPEG_TAIL_LEN.min(len))?;
+    let end = tail
+        .iter()
+        .rposition(|byte| *byte != 0)
+        .map_or(&tail[..0], |position| &tail[..=position]);
+
+    if !end.ends_with(&[0xff, 0xd9]) {
+        return Err("truncated jpg file".to_string());
+    }
+
+    Ok(size)
+}
+
+/// The logical screen size follows the signature, and the file ends with a
+/// trailer byte.
+fn read_gif<R: Read + Seek>(reader: &mut R, len: u64) -> Result<ImageSize, String> {
+    let mut header = [0u8; 10];
+    reader
+        .read_exact(&mut header)
+        .map_err(|_| "missing gif header".to_string())?;
+
+    let size = ImageSize {
+        width: u16::from_le_bytes([header[6], header[7]]) as u32,
+        height: u16::from_le_bytes([header[8], header[9]]) as u32,
+    };
+
+    if read_tail(reader, len, 1)? != [0x3b] {
+        return Err("truncated gif file".to_string());
+    }
+
+    Ok(size)
+}
+
+fn read_tail<R: Read + Seek>(reader: &mut R, len: u64, count: u64) -> Result<Vec<u8>, String> {
+    let truncated = |_| "file too short".to_string();
+
+    if count > len {
+        return Err("file too short".to_string());
+    }
+
+    reader
+        .seek(SeekFrom::Start(len - count))
+        .map_err(truncated)?;
+    let mut tail = vec![0u8; count as usize];
+    reader.read_exact(&mut tail).map_err(truncated)?;
+
+    Ok(tail)
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
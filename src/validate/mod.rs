@@ -1,9 +1,15 @@
+pub mod accounts;
 pub mod errors;
 pub mod format;
+pub mod image_size;
 pub mod parser;
 pub mod process;
+pub mod rules;
 
+pub use accounts::*;
 pub use errors::*;
 pub use format::*;
+pub use image_size::*;
 pub use parser::*;
 pub use process::*;
+pub use rules::*;
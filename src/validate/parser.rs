@@ -1,6 +1,13 @@
-pub use mpl_token_metadata::state::{MAX_NAME_LENGTH, MAX_SYMBOL_LENGTH, MAX_URI_LENGTH};
+pub use mpl_token_metadata::state::{
+    MAX_CREATOR_LIMIT, MAX_NAME_LENGTH, MAX_SYMBOL_LENGTH, MAX_URI_LENGTH,
+};
+use std::{ffi::OsStr, path::Path, str::FromStr};
 
+use crate::common::{Pubkey, Value};
+use crate::config::data::{UseMethod, Uses};
+use crate::upload::extension_content_type;
 use crate::validate::errors::ValidateError;
+use crate::validate::format::{CreatorAttr, FileAttr, Metadata};
 
 pub fn check_name(name: &str) -> Result<(), ValidateError> {
     if name.len() > MAX_NAME_LENGTH {
@@ -25,7 +32,185 @@ pub fn check_url(url: &str) -> Result<(), ValidateError> {
 
 pub fn check_seller_fee_basis_points(seller_fee_basis_points: u16) -> Result<(), ValidateError> {
     if seller_fee_basis_points > 10000 {
+        return Err(ValidateError::InvalidSellerFeeBasisPoints);
+    }
+    Ok(())
+}
+
+pub fn check_creators(creators: &[CreatorAttr]) -> Result<(), ValidateError> {
+    if creators.len() > MAX_CREATOR_LIMIT {
+        return Err(ValidateError::TooManyCreators(MAX_CREATOR_LIMIT));
+    }
+
+    for creator in creators {
+        if Pubkey::from_str(&creator.address).is_err() {
+            return Err(ValidateError::InvalidCreatorAddress(
+                creator.address.clone(),
+            ));
+        }
+    }
+
+    let total_share: u16 = creators.iter().map(|creator| creator.share as u16).sum();
+    if !creators.is_empty() && total_share != 100 {
         return Err(ValidateError::InvalidCreatorShare);
     }
+
+    Ok(())
+}
+
+/// Check that the image and animation of the metadata are listed in
+/// `properties.files`.
+pub fn check_files(metadata: &Metadata) -> Vec<ValidateError> {
+    let is_listed = |uri: &str| metadata.properties.files.iter().any(|file| file.uri == uri);
+    let mut issues = Vec::new();
+
+    if !is_listed(&metadata.image) {
+        issues.push(ValidateError::NotInFiles(
+            "image".to_string(),
+            metadata.image.clone(),
+        ));
+    }
+
+    if let Some(animation_url) = &metadata.animation_url {
+        if !is_listed(animation_url) {
+            issues.push(ValidateError::NotInFiles(
+                "animation_url".to_string(),
+                animation_url.clone(),
+            ));
+        }
+    }
+
+    issues
+}
+
+/// Check that the declared file types match the file extensions.
+pub fn check_file_types(files: &[FileAttr]) -> Vec<ValidateError> {
+    let mut issues = Vec::new();
+
+    for file in files {
+        if file.file_type.is_empty() {
+            issues.push(ValidateError::MissingFileType(file.uri.clone()));
+        } else if let Some(expected) = uri_content_type(&file.uri) {
+            if !file.file_type.eq_ignore_ascii_case(&expected) {
+                issues.push(ValidateError::FileTypeMismatch(
+                    file.uri.clone(),
+                    file.file_type.clone(),
+                    expected,
+                ));
+            }
+        }
+    }
+
+    issues
+}
+
+pub fn check_url_length(url: &str, limit: usize) -> Result<(), ValidateError> {
+    if url.len() > limit {
+        return Err(ValidateError::UrlExceedsLimit(url.to_string(), limit));
+    }
     Ok(())
 }
+
+/// Check that the category is one of the standard categories and that
+/// `properties.files` has a file of its type.
+pub fn check_category(metadata: &Metadata) -> Result<(), ValidateError> {
+    let category = &metadata.properties.category;
+    let file_type = match category.as_str() {
+        "image" => "image/",
+        "video" => "video/",
+        "audio" => "audio/",
+        "vr" => "model/",
+        "html" => "text/html",
+        _ => return Err(ValidateError::InvalidCategory(category.clone())),
+    };
+
+    if metadata
+        .properties
+        .files
+        .iter()
+        .any(|file| file.file_type.to_lowercase().starts_with(file_type))
+    {
+        Ok(())
+    } else {
+        Err(ValidateError::CategoryMismatch(
+            category.clone(),
+            file_type.trim_end_matches('/').to_string(),
+        ))
+    }
+}
+
+/// Check for fields of the metadata file that are not part of the metadata
+/// standard, which are dropped when the metadata is rewritten on upload.
+pub fn check_unknown_fields(raw: &Value, metadata: &Metadata) -> Vec<ValidateError> {
+    let mut unknown = Vec::new();
+
+    if let Ok(parsed) = serde_json::to_value(metadata) {
+        find_unknown_fields(raw, &parsed, "", &mut unknown);
+    }
+
+    unknown
+        .into_iter()
+        .map(ValidateError::UnknownField)
+        .collect()
+}
+
+fn find_unknown_fields(raw: &Value, parsed: &Value, path: &str, unknown: &mut Vec<String>) {
+    match (raw, parsed) {
+        (Value::Object(raw), Value::Object(parsed)) => {
+            for (key, value) in raw {
+                let field = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+
+                match parsed.get(key) {
+                    Some(parsed_value) => find_unknown_fields(value, parsed_value, &field, unknown),
+                    // unset optional fields are not serialized
+                    None if !value.is_null() => unknown.push(field),
+                    None => (),
+                }
+            }
+        }
+        (Value::Array(raw), Value::Array(parsed)) => {
+            for (index, (raw, parsed)) in raw.iter().zip(parsed).enumerate() {
+                find_unknown_fields(raw, parsed, &format!("{}[{}]", path, index), unknown);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Return the content type of a file uri from its extension, or None if the
+/// uri has no (known) extension.
+pub fn uri_content_type(uri: &str) -> Option<String> {
+    let path = uri.split(&['?', '#'][..]).next().unwrap_or(uri);
+
+    Path::new(path)
+        .extension()
+        .and_then(OsStr::to_str)
+        .map(extension_content_type)
+        .filter(|content_type| content_type != "application/octet-stream")
+}
+
+pub fn check_uses(uses: &Uses) -> Result<(), ValidateError> {
+    if uses.remaining > uses.total {
+        return Err(ValidateError::InvalidUses(format!(
+            "remaining ({}) cannot be greater than total ({})",
+            uses.remaining, uses.total
+        )));
+    }
+
+    match uses.use_method {
+        UseMethod::Single if uses.total != 1 || uses.remaining != 1 => Err(
+            ValidateError::InvalidUses("single use requires total and remaining of 1".to_string()),
+        ),
+        UseMethod::Multiple if uses.total < 2 => Err(ValidateError::InvalidUses(
+            "multiple use requires a total of at least 2".to_string(),
+        )),
+        UseMethod::Burn if uses.total == 0 => Err(ValidateError::InvalidUses(
+            "burn use requires a total of at least 1".to_string(),
+        )),
+        _ => Ok(()),
+    }
+}
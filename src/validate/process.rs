@@ -3,25 +3,69 @@ use console::style;
 use glob::glob;
 use rayon::prelude::*;
 use std::{
-    fs::File,
+    collections::{BTreeMap, BTreeSet},
+    ffi::OsStr,
+    fs::{self, File},
     path::Path,
-    sync::{Arc, Mutex},
+    str::FromStr,
+    sync::Mutex,
 };
 
 use crate::common::*;
+use crate::config::parser::{get_asset_overrides, get_config_data};
+use crate::upload::{content_type, list_files, parse_locale_filename, DataType};
 use crate::utils::*;
 use crate::validate::*;
 
+/// Extensions of the image files of the assets.
+const IMAGE_EXTENSIONS: [&str; 3] = ["png", "jpg", "gif"];
+
+/// Issue found in a file of the assets.
+struct Issue {
+    file: String,
+    rule: Rule,
+    message: String,
+}
+
+impl Issue {
+    fn new(file: &str, rule: Rule, message: impl ToString) -> Self {
+        Issue {
+            file: file.to_string(),
+            rule,
+            message: message.to_string(),
+        }
+    }
+}
+
 pub struct ValidateArgs {
     pub assets_dir: String,
     pub strict: bool,
+    pub skip: Vec<String>,
+    /// Size (in MB) above which asset files are reported, 0 to disable.
+    pub max_file_size: Option<u64>,
+    pub config: Option<String>,
+    pub keypair: Option<String>,
+    pub rpc_url: Option<String>,
 }
 
 pub fn process_validate(args: ValidateArgs) -> Result<()> {
+    let skip = args
+        .skip
+        .iter()
+        .map(|rule| Rule::from_str(rule))
+        .collect::<Result<Vec<Rule>, _>>()?;
+    let rules = RuleSet::new(args.strict, skip);
+
     // loading assets
     println!(
         "{} {}Loading assets",
-        style("[1/1]").bold().dim(),
+        style(if args.config.is_some() {
+            "[1/2]"
+        } else {
+            "[1/1]"
+        })
+        .bold()
+        .dim(),
         ASSETS_EMOJI
     );
 
@@ -45,75 +89,128 @@ pub fn process_validate(args: ValidateArgs) -> Result<()> {
     let paths: Vec<_> = paths.into_iter().map(Result::unwrap).collect();
     let path_errors: Vec<_> = errors.into_iter().map(Result::unwrap_err).collect();
 
-    let file_open_errors = Arc::new(Mutex::new(Vec::new()));
-    let deserialize_errors = Arc::new(Mutex::new(Vec::new()));
-    let validate_errors = Arc::new(Mutex::new(Vec::new()));
+    if !path_errors.is_empty() {
+        pb.finish_and_clear();
+        error!("Path errors: {:?}", path_errors);
+        return Err(ReadFilesError::PathErrors.into());
+    }
+
+    let issues = Mutex::new(Vec::new());
+    let parsed = Mutex::new(Vec::new());
 
     paths.par_iter().for_each(|path| {
-        let file_open_errors = file_open_errors.clone();
-        let f = match File::open(path) {
-            Ok(f) => f,
-            Err(error) => {
-                error!("{}: {}", path.display(), error);
-                file_open_errors
+        let file_name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default()
+            .to_string();
+        let report = |rule: Rule, message: String| {
+            if rules.is_enabled(rule) {
+                issues
                     .lock()
                     .unwrap()
-                    .push(FileOpenError { path, error });
+                    .push(Issue::new(&file_name, rule, message));
+            }
+        };
+
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(error) => {
+                report(Rule::Format, error.to_string());
                 return;
             }
         };
 
-        let metadata = match serde_json::from_reader::<File, Metadata>(f) {
+        let metadata = match serde_json::from_str::<Metadata>(&content) {
             Ok(metadata) => metadata,
             Err(error) => {
-                error!("{}: {}", path.display(), error);
-                deserialize_errors
-                    .lock()
-                    .unwrap()
-                    .push(DeserializeError { path, error });
+                report(Rule::Format, format!("Invalid metadata: {}", error));
                 return;
             }
         };
 
-        if args.strict {
-            match metadata.validate_strict() {
-                Ok(()) => {}
-                Err(e) => {
-                    error!("{}: {}", path.display(), e);
-                    validate_errors.lock().unwrap().push(e);
-                }
+        // localized variants must describe the same asset as the metadata file
+        // of the same index
+        if let Some((index, _)) = parse_locale_filename(&file_name) {
+            if let Err(e) = check_locale_variant(path, index, &metadata) {
+                report(Rule::Locale, e.to_string());
             }
-        } else {
-            match metadata.validate() {
-                Ok(()) => {}
-                Err(e) => {
-                    error!("{}: {}", path.display(), e);
-                    validate_errors.lock().unwrap().push(e);
+        }
+
+        for (rule, issue) in metadata.check(&rules) {
+            report(rule, issue.to_string());
+        }
+
+        if rules.is_enabled(Rule::UnknownFields) {
+            if let Ok(raw) = serde_json::from_str::<Value>(&content) {
+                for issue in check_unknown_fields(&raw, &metadata) {
+                    report(Rule::UnknownFields, issue.to_string());
                 }
             }
         }
+
+        parsed.lock().unwrap().push((file_name.clone(), metadata));
     });
 
+    let mut issues = issues.into_inner().unwrap();
+    let parsed = parsed.into_inner().unwrap();
+    issues.extend(
+        check_assets(assets_dir, &parsed)?
+            .into_iter()
+            .filter(|issue| rules.is_enabled(issue.rule)),
+    );
+
+    let max_file_size = args.max_file_size.unwrap_or(DEFAULT_MAX_FILE_SIZE);
+    let (image_issues, warnings) = check_images(assets_dir, max_file_size)?;
+    issues.extend(
+        image_issues
+            .into_iter()
+            .filter(|issue| rules.is_enabled(issue.rule)),
+    );
+    let warnings: Vec<Issue> = warnings
+        .into_iter()
+        .filter(|issue| rules.is_enabled(issue.rule))
+        .collect();
+
     pb.finish();
 
-    if !path_errors.is_empty() {
-        error!("Path errors: {:?}", path_errors);
-        return Err(ReadFilesError::PathErrors.into());
+    if !warnings.is_empty() {
+        print_warnings(&warnings);
     }
-
-    if !file_open_errors.lock().unwrap().is_empty() {
-        error!("File open errors: {:?}", file_open_errors);
-        return Err(ReadFilesError::FileOpenErrors.into());
+    if !issues.is_empty() {
+        print_report(&issues);
     }
+    print_rules(&rules, &issues, &warnings);
 
-    if !deserialize_errors.lock().unwrap().is_empty() {
-        error!("Deserialize errors: {:?}", deserialize_errors);
-        return Err(ReadFilesError::DeserializeErrors.into());
+    if !issues.is_empty() {
+        return Err(ValidateError::InvalidAssets(issues.len()).into());
     }
 
-    if !validate_errors.lock().unwrap().is_empty() {
-        error!("Validate errors: {:?}", validate_errors);
-        return Err(ReadFilesError::ValidateErrors.into());
+    if let Some(config) = &args.config {
+        println!(
+            "\n{} {}Checking config accounts",
+            style("[2/2]").bold().dim(),
+            LOOKING_GLASS_EMOJI
+        );
+
+        let config_data = get_config_data(config)?;
+
+        for (index, asset_override) in get_asset_overrides(&config_data)? {
+            if let Some(name) = &asset_override.name {
+                check_name(name).map_err(|e| anyhow!("Asset override {}: {}", index, e))?;
+            }
+            if let Some(symbol) = &asset_override.symbol {
+                check_symbol(symbol).map_err(|e| anyhow!("Asset override {}: {}", index, e))?;
+            }
+        }
+
+        let sugar_config = sugar_setup(args.keypair.clone(), args.rpc_url.clone())?;
+        let client = setup_client(&sugar_config)?;
+
+        for warning in check_config_accounts(&config_data, &client)? {
+            warn!("{}", warning);
+            println!("{}", style(format!("Warning: {}", warning)).yellow());
+        }
     }
 
     let message = "Validation complete, your metadata file(s) look good.";
@@ -122,3 +219,343 @@ pub fn process_validate(args: ValidateArgs) -> Result<()> {
 
     Ok(())
 }
+
+/// Check that a localized variant has the same image, symbol and royalties
+/// as the metadata file of its index.
+fn check_locale_variant(path: &Path, index: usize, variant: &Metadata) -> Result<()> {
+    let base_path = path.with_file_name(format!("{}.json", index));
+
+    let base: Metadata = match File::open(base_path) {
+        Ok(f) => serde_json::from_reader(f)?,
+        Err(_) => {
+            return Err(ValidateError::MissingLocaleBase(path.display().to_string(), index).into())
+        }
+    };
+
+    let mismatch = if base.image != variant.image {
+        Some("image")
+    } else if base.symbol != variant.symbol {
+        Some("symbol")
+    } else if base.seller_fee_basis_points != variant.seller_fee_basis_points {
+        Some("seller_fee_basis_points")
+    } else if base.animation_url != variant.animation_url {
+        Some("animation_url")
+    } else {
+        None
+    };
+
+    match mismatch {
+        Some(field) => {
+            Err(ValidateError::LocaleMismatch(path.display().to_string(), field.to_string()).into())
+        }
+        None => Ok(()),
+    }
+}
+
+/// Check the asset files of the metadata files: each asset has an image
+/// whose content matches the type declared in `properties.files`, and the
+/// asset indices have no gaps.
+fn check_assets(assets_dir: &Path, metadata: &[(String, Metadata)]) -> Result<Vec<Issue>> {
+    let assets_path = assets_dir
+        .to_str()
+        .ok_or(ValidateError::InvalidAssetsDirectory)?;
+    let mut issues = Vec::new();
+    let mut images = BTreeMap::new();
+
+    for entry in list_files(assets_path)? {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let path = Path::new(&file_name);
+        let is_image = path
+            .extension()
+            .and_then(OsStr::to_str)
+            .map(|extension| IMAGE_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+            .unwrap_or(false);
+
+        if let (true, Some(index)) = (is_image, asset_index(&file_name)) {
+            images.entry(index).or_insert(file_name);
+        }
+    }
+
+    let mut indices = BTreeSet::new();
+
+    for (file_name, metadata) in metadata {
+        // localized variants are checked against their metadata file, and
+        // the collection metadata is not an asset
+        if parse_locale_filename(file_name).is_some() || file_name == "collection.json" {
+            continue;
+        }
+
+        let index = match asset_index(file_name) {
+            Some(index) if file_name == &format!("{}.json", index) => index,
+            _ => {
+                issues.push(Issue::new(
+                    file_name,
+                    Rule::Indices,
+                    ValidateError::InvalidIndex,
+                ));
+                continue;
+            }
+        };
+        indices.insert(index);
+
+        let image = match images.get(&index) {
+            Some(image) => image,
+            None => {
+                issues.push(Issue::new(
+                    file_name,
+                    Rule::Images,
+                    ValidateError::MissingImageFile,
+                ));
+                continue;
+            }
+        };
+
+        // a type that does not match the uri extension is already reported
+        // by the metadata checks
+        let declared = metadata.properties.files.iter().find(|file| {
+            file.uri == metadata.image
+                && !file.file_type.is_empty()
+                && uri_content_type(&file.uri).map_or(true, |expected| {
+                    expected.eq_ignore_ascii_case(&file.file_type)
+                })
+        });
+
+        if let Some(file) = declared {
+            let image_path = assets_dir.join(image).to_string_lossy().to_string();
+            let content_type = content_type(&image_path, &DataType::Image)?;
+
+            if !file.file_type.eq_ignore_ascii_case(&content_type) {
+                let issue = ValidateError::FileTypeMismatch(
+                    file.uri.clone(),
+                    file.file_type.clone(),
+                    content_type,
+                );
+                issues.push(Issue::new(image, Rule::Images, issue));
+            }
+        }
+    }
+
+    for (index, image) in &images {
+        if !indices.contains(index) {
+            issues.push(Issue::new(
+                image,
+                Rule::Indices,
+                ValidateError::MissingMetadataFile,
+            ));
+        }
+    }
+
+    if let Some(last) = indices.iter().next_back() {
+        let gaps: Vec<String> = (0..*last)
+            .filter(|index| !indices.contains(index))
+            .map(|index| index.to_string())
+            .collect();
+
+        if !gaps.is_empty() {
+            let issue = ValidateError::IndexGaps(gaps.join(", "));
+            issues.push(Issue::new(assets_path, Rule::Indices, issue));
+        }
+    }
+
+    Ok(issues)
+}
+
+/// Check the image files of the assets: they are readable and not truncated,
+/// and share the same aspect ratio. Returns the issues and the warnings, i.e.,
+/// images of a different size and files larger than the size threshold (in
+/// MB, 0 to disable).
+fn check_images(assets_dir: &Path, max_file_size: u64) -> Result<(Vec<Issue>, Vec<Issue>)> {
+    let assets_path = assets_dir
+        .to_str()
+        .ok_or(ValidateError::InvalidAssetsDirectory)?;
+    let mut files: Vec<(String, u64)> = list_files(assets_path)?
+        .iter()
+        .filter_map(|entry| {
+            let len = entry.metadata().ok()?.len();
+            Some((entry.file_name().to_string_lossy().to_string(), len))
+        })
+        .collect();
+    files.sort_by_key(|(file_name, _)| (asset_index(file_name), file_name.clone()));
+
+    let mut issues = Vec::new();
+    let mut warnings = Vec::new();
+
+    for (file_name, len) in &files {
+        let megabytes = *len as f64 / (1024.0 * 1024.0);
+
+        if *len == 0 && !file_name.to_lowercase().ends_with(".json") {
+            issues.push(Issue::new(
+                file_name,
+                Rule::ImageIntegrity,
+                ValidateError::EmptyFile,
+            ));
+        } else if max_file_size > 0 && megabytes > max_file_size as f64 {
+            let warning = ValidateError::FileTooLarge(megabytes, max_file_size);
+            warnings.push(Issue::new(file_name, Rule::FileSize, warning));
+        }
+    }
+
+    let images: Vec<&String> = files
+        .iter()
+        .filter(|(file_name, len)| {
+            *len > 0
+                && Path::new(file_name)
+                    .extension()
+                    .and_then(OsStr::to_str)
+                    .map_or(false, |extension| {
+                        IMAGE_EXTENSIONS.contains(&extension.to_lowercase().as_str())
+                    })
+        })
+        .map(|(file_name, _)| file_name)
+        .collect();
+
+    // only the headers and the end of the files are read
+    let sizes: Vec<(&String, Result<ImageSize, ValidateError>)> = images
+        .par_iter()
+        .map(|file_name| (*file_name, read_image_size(&assets_dir.join(file_name))))
+        .collect();
+
+    // the collection image does not have to match the assets
+    let mut asset_sizes = Vec::new();
+
+    for (file_name, size) in sizes {
+        match size {
+            Ok(size) => {
+                if asset_index(file_name).is_some() {
+                    asset_sizes.push((file_name, size));
+                }
+            }
+            Err(issue) => issues.push(Issue::new(file_name, Rule::ImageIntegrity, issue)),
+        }
+    }
+
+    let ratio = most_common(asset_sizes.iter().map(|(_, size)| size.aspect_ratio()));
+    let common_size = most_common(asset_sizes.iter().map(|(_, size)| *size));
+
+    if let (Some((width, height)), Some(common_size)) = (ratio, common_size) {
+        for (file_name, size) in asset_sizes {
+            let (size_width, size_height) = size.aspect_ratio();
+
+            if (size_width, size_height) != (width, height) {
+                let issue = ValidateError::AspectRatioMismatch(
+                    size.to_string(),
+                    size_width,
+                    size_height,
+                    width,
+                    height,
+                );
+                issues.push(Issue::new(file_name, Rule::Dimensions, issue));
+            } else if size != common_size {
+                let warning =
+                    ValidateError::ImageSizeMismatch(size.to_string(), common_size.to_string());
+                warnings.push(Issue::new(file_name, Rule::Dimensions, warning));
+            }
+        }
+    }
+
+    Ok((issues, warnings))
+}
+
+/// Return the most common value, the smallest one on ties.
+fn most_common<T: Ord>(values: impl Iterator<Item = T>) -> Option<T> {
+    let mut counts = BTreeMap::new();
+
+    for value in values {
+        *counts.entry(value).or_insert(0) += 1;
+    }
+
+    let max = counts.values().max().copied()?;
+    counts
+        .into_iter()
+        .find(|(_, count)| *count == max)
+        .map(|(value, _)| value)
+}
+
+/// Return the asset index of a file name (e.g., 1 for '1.png').
+fn asset_index(file_name: &str) -> Option<usize> {
+    file_name.split('.').next()?.parse().ok()
+}
+
+/// Print the issues found, grouped by file in asset index order.
+fn print_report(issues: &[Issue]) {
+    let mut files: BTreeMap<(usize, &str), Vec<&Issue>> = BTreeMap::new();
+
+    for issue in issues {
+        let index = asset_index(&issue.file).unwrap_or(usize::MAX);
+        files.entry((index, &issue.file)).or_default().push(issue);
+    }
+
+    println!(
+        "\n{}",
+        style(format!(
+            "Found {} issue(s) in {} file(s):",
+            issues.len(),
+            files.len()
+        ))
+        .red()
+        .bold()
+    );
+
+    for ((_, file), file_issues) in files {
+        println!("\n{}", style(file).bold());
+
+        for issue in file_issues {
+            error!("{}: [{}] {}", file, issue.rule, issue.message);
+            println!(
+                "  - {} {}",
+                issue.message,
+                style(format!("[{}]", issue.rule)).dim()
+            );
+        }
+    }
+}
+
+/// Print the warnings found, which do not fail the validation.
+fn print_warnings(warnings: &[Issue]) {
+    let mut warnings: Vec<&Issue> = warnings.iter().collect();
+    warnings.sort_by_key(|warning| (asset_index(&warning.file), &warning.file));
+
+    println!(
+        "\n{}",
+        style(format!("Found {} warning(s):", warnings.len()))
+            .yellow()
+            .bold()
+    );
+
+    for warning in &warnings {
+        warn!("{}: [{}] {}", warning.file, warning.rule, warning.message);
+        println!(
+            "  - {}: {} {}",
+            style(&warning.file).bold(),
+            warning.message,
+            style(format!("[{}]", warning.rule)).dim()
+        );
+    }
+}
+
+/// Print the result of each rule.
+fn print_rules(rules: &RuleSet, issues: &[Issue], warnings: &[Issue]) {
+    println!("\n{}", style("Rules:").bold());
+
+    for rule in Rule::ALL {
+        let failed = issues.iter().filter(|issue| issue.rule == rule).count();
+        let warned = warnings
+            .iter()
+            .filter(|warning| warning.rule == rule)
+            .count();
+
+        let status = if rules.skip.contains(&rule) {
+            style("skip".to_string()).dim()
+        } else if !rules.is_enabled(rule) {
+            style("skip (strict only)".to_string()).dim()
+        } else if failed > 0 {
+            style(format!("fail ({} issue(s))", failed)).red()
+        } else if warned > 0 {
+            style(format!("pass ({} warning(s))", warned)).yellow()
+        } else {
+            style("pass".to_string()).green()
+        };
+
+        println!("  {:<16} {}", rule.name(), status);
+    }
+}
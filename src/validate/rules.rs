@@ -0,0 +1,133 @@
+use std::{fmt, str::FromStr};
+
+use crate::validate::errors::ValidateError;
+
+/// Validation rule, reported individually so that pipelines can gate on
+/// specific checks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Rule {
+    /// Metadata files can be read and parsed.
+    Format,
+    Name,
+    Symbol,
+    /// URLs fit in the on-chain URI (strict: strictly shorter than the limit).
+    UrlLength,
+    SellerFee,
+    Uses,
+    Creators,
+    /// The image and animation are listed in `properties.files`.
+    Files,
+    /// The types in `properties.files` match the file extensions.
+    FileTypes,
+    /// Localized variants match their metadata file.
+    Locale,
+    /// Each asset has an image matching its declared type.
+    Images,
+    /// Asset file names are contiguous indices.
+    Indices,
+    /// Image files are not empty, truncated or unreadable.
+    ImageIntegrity,
+    /// Images share the same aspect ratio (warns on different sizes).
+    Dimensions,
+    /// Warns on asset files larger than the size threshold.
+    FileSize,
+    /// Strict: animation_url, external_url and collection are set.
+    RequiredFields,
+    /// Strict: no fields that are dropped when the metadata is uploaded.
+    UnknownFields,
+    /// Strict: the category is known and matches the files.
+    Category,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 18] = [
+        Rule::Format,
+        Rule::Name,
+        Rule::Symbol,
+        Rule::UrlLength,
+        Rule::SellerFee,
+        Rule::Uses,
+        Rule::Creators,
+        Rule::Files,
+        Rule::FileTypes,
+        Rule::Locale,
+        Rule::Images,
+        Rule::Indices,
+        Rule::ImageIntegrity,
+        Rule::Dimensions,
+        Rule::FileSize,
+        Rule::RequiredFields,
+        Rule::UnknownFields,
+        Rule::Category,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rule::Format => "format",
+            Rule::Name => "name",
+            Rule::Symbol => "symbol",
+            Rule::UrlLength => "url-length",
+            Rule::SellerFee => "seller-fee",
+            Rule::Uses => "uses",
+            Rule::Creators => "creators",
+            Rule::Files => "files",
+            Rule::FileTypes => "file-types",
+            Rule::Locale => "locale",
+            Rule::Images => "images",
+            Rule::Indices => "indices",
+            Rule::ImageIntegrity => "image-integrity",
+            Rule::Dimensions => "dimensions",
+            Rule::FileSize => "file-size",
+            Rule::RequiredFields => "required-fields",
+            Rule::UnknownFields => "unknown-fields",
+            Rule::Category => "category",
+        }
+    }
+
+    /// Whether the rule only runs in strict mode.
+    pub fn is_strict(&self) -> bool {
+        matches!(
+            self,
+            Rule::RequiredFields | Rule::UnknownFields | Rule::Category
+        )
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Rule {
+    type Err = ValidateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Rule::ALL
+            .iter()
+            .find(|rule| rule.name() == s)
+            .copied()
+            .ok_or_else(|| {
+                let names: Vec<&str> = Rule::ALL.iter().map(Rule::name).collect();
+                ValidateError::UnknownRule(s.to_string(), names.join(", "))
+            })
+    }
+}
+
+/// Rules enabled for a validation: the lenient rules, plus the strict ones
+/// in strict mode, minus the skipped ones.
+#[derive(Debug, Clone, Default)]
+pub struct RuleSet {
+    pub strict: bool,
+    pub skip: Vec<Rule>,
+}
+
+impl RuleSet {
+    pub fn new(strict: bool, skip: Vec<Rule>) -> Self {
+        RuleSet { strict, skip }
+    }
+
+    pub fn is_enabled(&self, rule: Rule) -> bool {
+        (self.strict || !rule.is_strict()) && !self.skip.contains(&rule)
+    }
+}
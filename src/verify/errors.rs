@@ -6,4 +6,8 @@ pub enum VerifyError {
     FailedToGetAccountData(String),
     #[error("{0} mismatch (expected='{1}', found='{2}')")]
     Mismatch(String, String, String),
+    #[error("{0} link {1} could not be retrieved ({2})")]
+    ContentMissing(String, String, String),
+    #[error("Config line beyond the size of the candy machine")]
+    MissingConfigLine,
 }
@@ -1,14 +1,25 @@
 use anchor_lang::AccountDeserialize;
 use console::style;
-use std::{thread, time::Duration};
+use futures::{stream, StreamExt};
+use std::{
+    cmp,
+    sync::{atomic::AtomicBool, Arc},
+    thread,
+    time::Duration,
+};
 
 use mpl_candy_machine::CandyMachine;
 
 use crate::cache::*;
-use crate::candy_machine::ID as CANDY_MACHINE_ID;
+use crate::candy_machine::{scan_config_lines, ID as CANDY_MACHINE_ID};
 use crate::common::*;
 use crate::config::Cluster;
-use crate::constants::{CANDY_EMOJI, PAPER_EMOJI};
+use crate::constants::{CANDY_EMOJI, LOOKING_GLASS_EMOJI, PAPER_EMOJI};
+use crate::deploy::{process_deploy, DeployArgs};
+use crate::upload::{
+    get_updated_metadata, get_uploaded_asset_pairs, hash_data, hash_file, AssetPair,
+    TemplateVariables,
+};
 use crate::utils::*;
 use crate::verify::VerifyError;
 
@@ -16,6 +27,22 @@ pub struct VerifyArgs {
     pub keypair: Option<String>,
     pub rpc_url: Option<String>,
     pub cache: String,
+    pub assets_dir: String,
+    pub content: bool,
+    /// Report the invalid items without updating the cache.
+    pub read_only: bool,
+    /// Redeploy the invalid items after re-queueing them in the cache.
+    pub redeploy: bool,
+    pub config: String,
+    pub interrupted: Arc<AtomicBool>,
+}
+
+/// A single link to be compared against the local content.
+pub struct ContentCheck {
+    pub index: String,
+    pub kind: &'static str,
+    pub link: String,
+    pub expected_hash: String,
 }
 
 #[derive(Debug)]
@@ -24,8 +51,8 @@ pub struct OnChainItem {
     pub uri: String,
 }
 
-pub fn process_verify(args: VerifyArgs) -> Result<()> {
-    let sugar_config = sugar_setup(args.keypair, args.rpc_url)?;
+pub async fn process_verify(args: VerifyArgs) -> Result<()> {
+    let sugar_config = sugar_setup(args.keypair.clone(), args.rpc_url.clone())?;
 
     // loads the cache file (this needs to have been created by
     // the upload command)
@@ -43,9 +70,11 @@ pub fn process_verify(args: VerifyArgs) -> Result<()> {
         return Err(CacheError::CacheFileNotFound(args.cache).into());
     }
 
+    let steps = if args.content { 3 } else { 2 };
+
     println!(
         "{} {}Loading candy machine",
-        style("[1/2]").bold().dim(),
+        style(format!("[1/{}]", steps)).bold().dim(),
         CANDY_EMOJI
     );
 
@@ -78,53 +107,45 @@ pub fn process_verify(args: VerifyArgs) -> Result<()> {
 
     println!(
         "\n{} {}Verification",
-        style("[2/2]").bold().dim(),
+        style(format!("[2/{}]", steps)).bold().dim(),
         PAPER_EMOJI
     );
 
     if candy_machine.data.hidden_settings.is_none() {
-        let num_items = cache.items.0.len();
-        let cache_items = &mut cache.items.0;
+        let statuses = scan_config_lines(&data, &cache);
+        let num_items = statuses.len();
         let mut errors = Vec::new();
 
         println!("Verifying {} config line(s): (Ctrl+C to abort)", num_items);
         let pb = progress_bar_with_style(num_items as u64);
         // sleeps for a about 1 second
-        let step: u64 = 1_000_000 / num_items as u64;
-
-        for i in 0..num_items {
-            let name_start = CONFIG_ARRAY_START
-                + STRING_LEN_SIZE
-                + CONFIG_LINE_SIZE * (i as usize)
-                + CONFIG_NAME_OFFSET;
-            let name_end = name_start + MAX_NAME_LENGTH;
-
-            let uri_start = CONFIG_ARRAY_START
-                + STRING_LEN_SIZE
-                + CONFIG_LINE_SIZE * (i as usize)
-                + CONFIG_URI_OFFSET;
-            let uri_end = uri_start + MAX_URI_LENGTH;
-
-            let name_error = format!("Failed to decode name for item {}", i);
-            let name = String::from_utf8(data[name_start..name_end].to_vec())
-                .expect(&name_error)
-                .trim_matches(char::from(0))
-                .to_string();
-
-            let uri_error = format!("Failed to decode uri for item {}", i);
-            let uri = String::from_utf8(data[uri_start..uri_end].to_vec())
-                .expect(&uri_error)
-                .trim_matches(char::from(0))
-                .to_string();
-
-            let on_chain_item = OnChainItem { name, uri };
-            let cache_item = cache_items
-                .get_mut(&i.to_string())
-                .expect("Failed to get item from config.");
-
-            if let Err(err) = items_match(cache_item, &on_chain_item) {
-                cache_item.on_chain = false;
-                errors.push((i.to_string(), err.to_string()));
+        let step: u64 = 1_000_000 / cmp::max(num_items, 1) as u64;
+
+        for status in statuses {
+            let index = status.index.to_string();
+
+            if !status.on_chain {
+                let cache_item = cache
+                    .items
+                    .0
+                    .get(&index)
+                    .expect("Failed to get item from cache.");
+
+                let error = match status.config_line {
+                    Some(config_line) => items_match(
+                        cache_item,
+                        &OnChainItem {
+                            name: config_line.name,
+                            uri: config_line.uri,
+                        },
+                    )
+                    .err()
+                    .map(|err| err.to_string())
+                    .unwrap_or_default(),
+                    None => VerifyError::MissingConfigLine.to_string(),
+                };
+
+                errors.push((index, error));
             }
 
             pb.inc(1);
@@ -134,17 +155,41 @@ pub fn process_verify(args: VerifyArgs) -> Result<()> {
         pb.finish();
 
         if !errors.is_empty() {
-            cache.sync_file()?;
-
             let total = errors.len();
             println!("\nInvalid items found: ");
 
-            for e in errors {
+            for e in &errors {
                 println!("- Item {}: {}", e.0, e.1);
             }
-            println!("\nCache updated - re-run `deploy`.");
 
-            return Err(anyhow!("{} invalid item(s) found.", total));
+            if args.read_only {
+                println!("\nCache not updated (read-only mode).");
+                return Err(anyhow!("{} invalid item(s) found.", total));
+            }
+
+            requeue_items(&mut cache, &errors)?;
+
+            if !args.redeploy {
+                println!("\nCache updated - re-run `deploy` (or `verify --redeploy`).");
+                return Err(anyhow!("{} invalid item(s) found.", total));
+            }
+
+            println!("\nCache updated - redeploying {} item(s).\n", total);
+
+            return process_deploy(DeployArgs {
+                config: args.config,
+                cache: args.cache,
+                keypair: args.keypair,
+                rpc_url: args.rpc_url,
+                force_rewrite: false,
+                append: false,
+                interrupted: args.interrupted,
+            })
+            .await;
+        }
+
+        if args.content {
+            verify_content(&cache, &args.assets_dir, &args.config, steps).await?;
         }
 
         let cluster = match get_cluster(program.rpc())? {
@@ -160,12 +205,29 @@ pub fn process_verify(args: VerifyArgs) -> Result<()> {
     } else {
         // nothing else todo, there are no config lines in a candy machine
         // with hidden settings
+        if args.content {
+            verify_content(&cache, &args.assets_dir, &args.config, steps).await?;
+        }
+
         println!("\nHidden settings enabled. You're good to go!");
     }
 
     Ok(())
 }
 
+/// Mark the items whose config line does not match as not on-chain, so the
+/// next deploy writes them again, recording the mismatch as their error.
+fn requeue_items(cache: &mut Cache, errors: &[(String, String)]) -> Result<()> {
+    for (index, error) in errors {
+        if let Some(item) = cache.items.0.get_mut(index) {
+            item.on_chain = false;
+            item.last_error = Some(error.clone());
+        }
+    }
+
+    cache.sync_file()
+}
+
 fn items_match(cache_item: &CacheItem, on_chain_item: &OnChainItem) -> Result<()> {
     if cache_item.name != on_chain_item.name {
         return Err(VerifyError::Mismatch(
@@ -185,3 +247,175 @@ fn items_match(cache_item: &CacheItem, on_chain_item: &OnChainItem) -> Result<()
 
     Ok(())
 }
+
+/// Download the content of each cache link and compare its hash against the
+/// local asset files.
+async fn verify_content(cache: &Cache, assets_dir: &str, config: &str, steps: u8) -> Result<()> {
+    println!(
+        "\n{} {}Content verification",
+        style(format!("[{}/{}]", steps, steps)).bold().dim(),
+        LOOKING_GLASS_EMOJI
+    );
+
+    // the uploaded images are the optimized copies and previews of the assets
+    let asset_pairs = get_uploaded_asset_pairs(assets_dir, config)?;
+
+    let mut checks = Vec::new();
+    let mut errors = Vec::new();
+
+    for (index, item) in &cache.items.0 {
+        let pair = match index
+            .parse::<usize>()
+            .ok()
+            .and_then(|i| asset_pairs.get(&i))
+        {
+            Some(pair) => pair,
+            None => {
+                errors.push((index.to_string(), "local asset files not found".to_string()));
+                continue;
+            }
+        };
+
+        match content_checks(index, item, pair, &cache.program.template_variables) {
+            Ok(mut item_checks) => checks.append(&mut item_checks),
+            Err(err) => errors.push((index.to_string(), err.to_string())),
+        }
+    }
+
+    println!("Verifying {} link(s): (Ctrl+C to abort)", checks.len());
+    let pb = progress_bar_with_style(checks.len() as u64);
+    let http_client = http_client();
+
+    let results: Vec<(String, Option<String>)> = stream::iter(checks)
+        .map(|check| {
+            let http_client = &http_client;
+            let pb = &pb;
+            async move {
+                let result = check_link(http_client, &check).await;
+                pb.inc(1);
+                (check.index, result.err().map(|e| e.to_string()))
+            }
+        })
+        .buffer_unordered(PARALLEL_LIMIT)
+        .collect()
+        .await;
+
+    pb.finish();
+
+    for (index, error) in results {
+        if let Some(error) = error {
+            errors.push((index, error));
+        }
+    }
+
+    if !errors.is_empty() {
+        errors.sort_by(|a, b| a.0.cmp(&b.0));
+        let total = errors.len();
+        println!("\nDivergent or missing content found: ");
+
+        for e in errors {
+            println!("- Item {}: {}", e.0, e.1);
+        }
+
+        return Err(anyhow!("{} content error(s) found.", total));
+    }
+
+    println!("\nAll links match the local content.");
+
+    Ok(())
+}
+
+/// Determine the links of a cache item and the hash their content should have.
+pub fn content_checks(
+    index: &str,
+    item: &CacheItem,
+    pair: &AssetPair,
+    variables: &TemplateVariables,
+) -> Result<Vec<ContentCheck>> {
+    let mut checks = Vec::new();
+
+    if item.image_link.is_empty() {
+        return Err(anyhow!("missing image link"));
+    }
+
+    checks.push(ContentCheck {
+        index: index.to_string(),
+        kind: "image",
+        link: item.image_link.clone(),
+        expected_hash: hash_file(&pair.image)?,
+    });
+
+    if let Some(animation) = &pair.animation {
+        match &item.animation_link {
+            Some(link) if !link.is_empty() => checks.push(ContentCheck {
+                index: index.to_string(),
+                kind: "animation",
+                link: link.clone(),
+                expected_hash: hash_file(animation)?,
+            }),
+            _ => return Err(anyhow!("missing animation link")),
+        }
+    }
+
+    if let Some(preview) = &pair.preview {
+        match &item.preview_link {
+            Some(link) if !link.is_empty() => checks.push(ContentCheck {
+                index: index.to_string(),
+                kind: "preview",
+                link: link.clone(),
+                expected_hash: hash_file(preview)?,
+            }),
+            _ => return Err(anyhow!("missing preview link")),
+        }
+    }
+
+    if item.metadata_link.is_empty() {
+        return Err(anyhow!("missing metadata link"));
+    }
+
+    // the uploaded metadata has the image/animation links replaced
+    let metadata = get_updated_metadata(
+        &pair.metadata,
+        &item.image_link,
+        item.animation_link.clone(),
+        item.preview_link.clone(),
+        variables,
+    )?;
+
+    checks.push(ContentCheck {
+        index: index.to_string(),
+        kind: "metadata",
+        link: item.metadata_link.clone(),
+        expected_hash: hash_data(metadata.as_bytes()),
+    });
+
+    Ok(checks)
+}
+
+/// Download the content of a link and compare its hash to the expected one.
+pub async fn check_link(http_client: &HttpClient, check: &ContentCheck) -> Result<()> {
+    let response = http_client.get(&check.link).send().await?;
+
+    if !response.status().is_success() {
+        return Err(VerifyError::ContentMissing(
+            check.kind.to_string(),
+            check.link.clone(),
+            response.status().to_string(),
+        )
+        .into());
+    }
+
+    let data = response.bytes().await?;
+    let hash = hash_data(&data);
+
+    if hash != check.expected_hash {
+        return Err(VerifyError::Mismatch(
+            format!("{} content ({} bytes)", check.kind, data.len()),
+            check.expected_hash.clone(),
+            hash,
+        )
+        .into());
+    }
+
+    Ok(())
+}
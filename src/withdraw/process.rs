@@ -1,6 +1,7 @@
 pub use anchor_client::{
     solana_sdk::{
         commitment_config::{CommitmentConfig, CommitmentLevel},
+        instruction::Instruction,
         native_token::LAMPORTS_PER_SOL,
         pubkey::Pubkey,
         signature::{Keypair, Signature, Signer},
@@ -15,18 +16,20 @@ use solana_client::{
     rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
     rpc_filter::{Memcmp, MemcmpEncodedBytes, RpcFilterType},
 };
-use std::{
-    io::{stdin, stdout, Write},
-    rc::Rc,
-    str::FromStr,
-};
+use std::{rc::Rc, str::FromStr};
 
 use mpl_candy_machine::accounts as nft_accounts;
 use mpl_candy_machine::instruction as nft_instruction;
 
 use crate::candy_machine::ID as CANDY_MACHINE_ID;
 use crate::common::*;
+use crate::interaction::confirm;
+use crate::multisig::MultisigProposal;
+use crate::price::usd_suffix;
+use crate::rpc_cache;
 use crate::setup::{setup_client, sugar_setup};
+use crate::spend::record_transaction;
+use crate::transaction::send_with_retry;
 use crate::utils::*;
 
 pub struct WithdrawArgs {
@@ -34,6 +37,9 @@ pub struct WithdrawArgs {
     pub keypair: Option<String>,
     pub rpc_url: Option<String>,
     pub list: bool,
+    pub all: bool,
+    pub multisig: Option<String>,
+    pub proposal: String,
 }
 
 pub fn process_withdraw(args: WithdrawArgs) -> Result<()> {
@@ -48,39 +54,64 @@ pub fn process_withdraw(args: WithdrawArgs) -> Result<()> {
     let pb = spinner_with_style();
     pb.set_message("Connecting...");
 
-    let (program, payer) = setup_withdraw(args.keypair, args.rpc_url)?;
+    let (program, keypair) = setup_withdraw(args.keypair, args.rpc_url)?;
+
+    // with a multisig authority, the withdraw is written as a proposal
+    // instead of being signed by the keypair
+    let multisig = args
+        .multisig
+        .map(|multisig| {
+            Pubkey::from_str(&multisig)
+                .map_err(|_| anyhow!("Failed to parse multisig address: {}", multisig))
+        })
+        .transpose()?;
+    let authority = multisig.unwrap_or_else(|| keypair.pubkey());
 
     pb.finish_with_message("Connected");
 
+    // the --list flag takes precedence; even if a candy machine id is passed
+    // as an argument, we will list the candy machines (no draining happens)
+    let candy_machine = if args.list { None } else { args.candy_machine };
+    // candy machines are only drained in bulk when explicitly requested
+    let list = args.list || (candy_machine.is_none() && !args.all);
+
     println!(
         "\n{} {}{} funds",
         style("[2/2]").bold().dim(),
         WITHDRAW_EMOJI,
-        if args.list { "Listing" } else { "Retrieving" }
+        if list { "Listing" } else { "Retrieving" }
     );
 
-    // the --list flag takes precedence; even if a candy machine id is passed
-    // as an argument, we will list the candy machines (no draining happens)
-    let candy_machine = if args.list { None } else { args.candy_machine };
-
     // (2) Retrieving data for listing/draining
 
     match &candy_machine {
         Some(candy_machine) => {
             let candy_machine = Pubkey::from_str(candy_machine)?;
 
-            let pb = spinner_with_style();
-            pb.set_message("Draining candy machine...");
+            if let Some(multisig) = multisig {
+                MultisigProposal::new(
+                    &format!("Withdraw funds from candy machine {}", candy_machine),
+                    &multisig,
+                    &withdraw_instructions(&program, candy_machine, multisig)?,
+                )
+                .write(&args.proposal)?;
 
-            do_withdraw(Rc::new(program), candy_machine, payer)?;
+                println!("{} {}", style("Multisig proposal:").bold(), args.proposal);
+                println!("Propose the transaction message in the multisig UI to drain it.");
+            } else {
+                let pb = spinner_with_style();
+                pb.set_message("Draining candy machine...");
+
+                do_withdraw(Rc::new(program), candy_machine, &keypair)?;
 
-            pb.finish_with_message("Done");
+                pb.finish_with_message("Done");
+            }
         }
         None => {
             let config = RpcProgramAccountsConfig {
                 filters: Some(vec![RpcFilterType::Memcmp(Memcmp {
                     offset: 8, // key
-                    bytes: MemcmpEncodedBytes::Base58(payer.to_string()),
+                    bytes: MemcmpEncodedBytes::Base58(authority.to_string()),
                     encoding: None,
                 })]),
                 account_config: RpcAccountInfoConfig {
@@ -103,23 +134,24 @@ pub fn process_withdraw(args: WithdrawArgs) -> Result<()> {
 
             pb.finish_and_clear();
 
-            let mut total = 0.0f64;
+            let mut total = 0u64;
 
             accounts.iter().for_each(|account| {
                 let (_pubkey, account) = account;
-                total += account.lamports as f64;
+                total += account.lamports;
             });
 
             println!(
-                "Found {} candy machines, total amount: ◎ {}",
+                "Found {} candy machines, total amount: ◎ {}{}",
                 accounts.len(),
-                total / LAMPORTS_PER_SOL as f64
+                total as f64 / LAMPORTS_PER_SOL as f64,
+                usd_suffix(total)
             );
 
             if accounts.is_empty() {
                 // nothing else to do, we just say goodbye
                 println!("\n{}", style("[Completed]").bold().dim());
-            } else if args.list {
+            } else if list {
                 println!("\n{:48} Balance", "Candy Machine ID");
                 println!("{:-<61}", "-");
 
@@ -131,46 +163,60 @@ pub fn process_withdraw(args: WithdrawArgs) -> Result<()> {
                     );
                 }
 
+                if !args.list {
+                    println!("\nUse '--all' to drain them or '--candy-machine' to drain one.");
+                }
+
                 println!("\n{}", style("[Completed]").bold().dim());
             } else {
-                println!("\n+----------------------------------------------+");
-                println!("| WARNING: This will drain all candy machines. |");
-                println!("+----------------------------------------------+");
-
-                print!("\nContinue? [Y/n] (default \'n\'): ");
-                stdout().flush().ok();
-
-                let mut s = String::new();
-                stdin().read_line(&mut s).expect("Error reading input.");
-
-                if let Some('Y') = s.chars().next() {
-                    let pb = progress_bar_with_style(accounts.len() as u64);
-                    let mut not_drained = 0;
+                println!("\n+----------------------------------------------------------+");
+                println!("| WARNING: Drained candy machines are closed and unusable. |");
+                println!("+----------------------------------------------------------+");
+
+                let mut drained = 0;
+                let mut not_drained = 0;
+                let mut reclaimed = 0u64;
+
+                for (candy_machine, account) in &accounts {
+                    if !confirm(&format!(
+                        "Drain {} (◎ {}{})?",
+                        candy_machine,
+                        account.lamports as f64 / LAMPORTS_PER_SOL as f64,
+                        usd_suffix(account.lamports)
+                    ))? {
+                        println!("{}", style("Skipped").dim());
+                        continue;
+                    }
 
-                    accounts.iter().for_each(|account| {
-                        let (candy_machine, _account) = account;
-                        do_withdraw(program.clone(), *candy_machine, payer).unwrap_or_else(|e| {
+                    match do_withdraw(program.clone(), *candy_machine, &keypair) {
+                        Ok(signature) => {
+                            println!("{} {}", style("Signature:").bold(), signature);
+                            drained += 1;
+                            reclaimed += account.lamports;
+                        }
+                        Err(err) => {
+                            error!("Error: {}", err);
+                            println!("{}", style(format!("Failed: {}", err)).red());
                             not_drained += 1;
-                            error!("Error: {}", e);
-                        });
-                        pb.inc(1);
-                    });
-
-                    pb.finish();
-
-                    if not_drained > 0 {
-                        println!(
-                            "{}",
-                            style(format!("Could not drain {} candy machine(s)", not_drained))
-                                .red()
-                                .bold()
-                                .dim()
-                        );
+                        }
                     }
-                } else {
-                    // there were candy machines to drain, but the user decided
-                    // to abort the withdraw
-                    println!("\n{}", style("Withdraw aborted.").red().bold().dim());
+                }
+
+                println!(
+                    "\nDrained {} candy machine(s), reclaimed ◎ {}{}",
+                    drained,
+                    reclaimed as f64 / LAMPORTS_PER_SOL as f64,
+                    usd_suffix(reclaimed)
+                );
+
+                if not_drained > 0 {
+                    println!(
+                        "{}",
+                        style(format!("Could not drain {} candy machine(s)", not_drained))
+                            .red()
+                            .bold()
+                            .dim()
+                    );
                 }
             }
         }
@@ -179,24 +225,42 @@ pub fn process_withdraw(args: WithdrawArgs) -> Result<()> {
     Ok(())
 }
 
-fn setup_withdraw(keypair: Option<String>, rpc_url: Option<String>) -> Result<(Program, Pubkey)> {
+fn setup_withdraw(
+    keypair: Option<String>,
+    rpc_url: Option<String>,
+) -> Result<(Program, SugarSigner)> {
     let sugar_config = sugar_setup(keypair, rpc_url)?;
     let client = setup_client(&sugar_config)?;
     let program = client.program(CANDY_MACHINE_ID);
-    let payer = program.payer();
 
-    Ok((program, payer))
+    Ok((program, sugar_config.keypair))
+}
+
+fn do_withdraw(
+    program: Rc<Program>,
+    candy_machine: Pubkey,
+    payer: &dyn Signer,
+) -> Result<Signature> {
+    let instructions = withdraw_instructions(&program, candy_machine, payer.pubkey())?;
+    let signature = send_with_retry(&program, &instructions, &[payer])?;
+
+    rpc_cache::invalidate(&candy_machine);
+    record_transaction(1, 1, None);
+
+    Ok(signature)
 }
 
-fn do_withdraw(program: Rc<Program>, candy_machine: Pubkey, payer: Pubkey) -> Result<()> {
-    program
+fn withdraw_instructions(
+    program: &Program,
+    candy_machine: Pubkey,
+    authority: Pubkey,
+) -> Result<Vec<Instruction>> {
+    Ok(program
         .request()
         .accounts(nft_accounts::WithdrawFunds {
             candy_machine,
-            authority: payer,
+            authority,
         })
         .args(nft_instruction::WithdrawFunds {})
-        .send()?;
-
-    Ok(())
+        .instructions()?)
 }